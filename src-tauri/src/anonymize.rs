@@ -0,0 +1,246 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::bdf::push_field;
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::repair;
+
+/// 应用数据目录下的假名映射表文件名
+pub const PSEUDONYMS_FILE: &str = "pseudonyms.json";
+
+// 固定头部字段的偏移
+const PATIENT_OFFSET: usize = 8;
+const RECORDING_OFFSET: usize = 88;
+const RESERVED_OFFSET: usize = 192;
+
+/// EDF+ 规定的匿名起始日期和时间
+const ANONYMOUS_START: &str = "01.01.8500.00.00";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PseudonymEntry {
+    pseudonym: String,
+    patient_id: String,
+    created: chrono::DateTime<chrono::Utc>,
+}
+
+/// 假名映射表 - 受试者身份与假名一一对应，只保存在本机，不随导出文件分发
+pub struct PseudonymStore {
+    path: PathBuf,
+    entries: Vec<PseudonymEntry>,
+}
+
+impl PseudonymStore {
+    /// 打开映射表，文件不存在时为空表；无法解析时报错而不当作空表，避免覆盖已有映射
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| AppError::Config(format!("Invalid pseudonym mapping {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path: path.to_path_buf(), entries })
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// 受试者的假名：已有映射时沿用，否则使用指定的假名或分配 ANON0001 形式的新假名
+    pub fn pseudonym_for(&mut self, patient_id: &str, requested: Option<&str>) -> Result<String, AppError> {
+        if let Some(entry) = self.entries.iter().find(|entry| entry.patient_id == patient_id) {
+            return match requested {
+                Some(requested) if requested != entry.pseudonym => Err(AppError::Config(format!(
+                    "Subject is already mapped to pseudonym '{}'", entry.pseudonym
+                ))),
+                _ => Ok(entry.pseudonym.clone()),
+            };
+        }
+        
+        let pseudonym = match requested {
+            Some(requested) if self.is_used(requested) => {
+                return Err(AppError::Config(format!("Pseudonym '{}' is already assigned to another subject", requested)));
+            }
+            Some(requested) => requested.to_string(),
+            None => {
+                let mut number = self.entries.len() + 1;
+                while self.is_used(&format!("ANON{:04}", number)) {
+                    number += 1;
+                }
+                format!("ANON{:04}", number)
+            }
+        };
+        
+        self.entries.push(PseudonymEntry {
+            pseudonym: pseudonym.clone(),
+            patient_id: patient_id.to_string(),
+            created: chrono::Utc::now(),
+        });
+        if let Err(e) = self.save() {
+            self.entries.pop();
+            return Err(e);
+        }
+        
+        info!("🕶️  Assigned pseudonym {}", pseudonym);
+        Ok(pseudonym)
+    }
+    
+    fn is_used(&self, pseudonym: &str) -> bool {
+        self.entries.iter().any(|entry| entry.pseudonym == pseudonym)
+    }
+    
+    /// 先写临时文件再重命名，避免中途退出丢失映射
+    fn save(&self) -> Result<(), AppError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        
+        let text = serde_json::to_string_pretty(&self.entries)
+            .map_err(|e| AppError::Config(format!("Failed to serialize pseudonym mapping: {}", e)))?;
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, text)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// 导出去除受试者身份的EDF(+)/BDF(+)副本
+///
+/// 受试者字段改为 "假名 X X X"（源文件不含身份时为 "X X X X"），记录字段只保留起始日期，
+/// 研究编号、操作者和设备改为 X；可选地把起始日期时间改为匿名值。数据记录（含注释）原样复制。
+pub fn export_anonymized(
+    source: &Path,
+    destination: &Path,
+    store: &mut PseudonymStore,
+    options: &AnonymizeOptions,
+) -> Result<AnonymizeResult, AppError> {
+    options.validate()?;
+    if destination.exists() {
+        return Err(AppError::Config(format!("Destination already exists: {}", destination.display())));
+    }
+    
+    let mut file = File::open(source)?;
+    repair::read_layout(&mut file, source)?;
+    let mut fixed = [0u8; 256];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut fixed)?;
+    drop(file);
+    
+    let is_plus = matches!(&fixed[RESERVED_OFFSET..RESERVED_OFFSET + 4], b"EDF+" | b"BDF+");
+    let patient_field = text_field(&fixed, PATIENT_OFFSET, 80);
+    let recording_field = text_field(&fixed, RECORDING_OFFSET, 80);
+    
+    // 源文件没有身份时无需映射，指定的假名直接写入
+    let pseudonym = match patient_identity(&patient_field, is_plus) {
+        Some(identity) => Some(store.pseudonym_for(&identity, options.pseudonym.as_deref())?),
+        None => options.pseudonym.clone(),
+    };
+    let start_date = recording_field.strip_prefix("Startdate ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .filter(|_| is_plus && !options.remove_start_date);
+    
+    let mut header = Vec::with_capacity(176);
+    push_field(&mut header, &format!("{} X X X", edf_subfield(pseudonym.as_deref())), 80);
+    push_field(&mut header, &format!("Startdate {} X X X", edf_subfield(start_date)), 80);
+    if options.remove_start_date {
+        header.extend_from_slice(ANONYMOUS_START.as_bytes());
+    }
+    
+    std::fs::copy(source, destination)?;
+    let patched = OpenOptions::new().write(true).open(destination).and_then(|mut file| {
+        file.seek(SeekFrom::Start(PATIENT_OFFSET as u64))?;
+        file.write_all(&header)?;
+        file.sync_all()
+    });
+    if let Err(e) = patched {
+        let _ = std::fs::remove_file(destination);
+        return Err(e.into());
+    }
+    
+    info!("🕶️  Exported anonymized copy {} -> {}", source.display(), destination.display());
+    Ok(AnonymizeResult {
+        source: source.display().to_string(),
+        destination: destination.display().to_string(),
+        pseudonym,
+        mapping_file: store.path().display().to_string(),
+        start_date_removed: options.remove_start_date,
+    })
+}
+
+fn text_field(header: &[u8], offset: usize, width: usize) -> String {
+    String::from_utf8_lossy(&header[offset..offset + width]).trim().to_string()
+}
+
+/// 受试者字段中的身份：EDF+ 取编号（编号为 X 时取姓名），普通EDF取整个字段；没有身份时为 None
+fn patient_identity(field: &str, is_plus: bool) -> Option<String> {
+    if !is_plus {
+        return Some(field.to_string()).filter(|field| !field.is_empty() && field != "X");
+    }
+    let subfields: Vec<&str> = field.split_whitespace().collect();
+    let identity = [subfields.first(), subfields.get(3)].into_iter()
+        .flatten()
+        .find(|subfield| **subfield != "X")
+        .map(|subfield| subfield.to_string());
+    identity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::{BdfSignal, BdfWriter};
+    
+    fn write_bdf(path: &Path, patient: &str) {
+        let signal = BdfSignal {
+            label: "Cz".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -100.0,
+            physical_max: 100.0,
+            prefilter: String::new(),
+            samples_per_record: 4,
+        };
+        let mut writer = BdfWriter::create(path.to_str().unwrap(), vec![signal], 1.0, "2024-03-05T10:00:00Z".parse().unwrap(),
+                                           patient, "Startdate 05-MAR-2024 STUDY-1 A_Tech Open-CortexArray").unwrap();
+        writer.write_samples(&[vec![1.0, 2.0, 3.0, 4.0]]).unwrap();
+        writer.finalize().unwrap();
+    }
+    
+    #[test]
+    fn test_export_anonymized_bdf() {
+        let dir = std::env::temp_dir().join(format!("cortexarray_anonymize_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("session.bdf");
+        write_bdf(&source, "P007 F 02-MAY-1951 Jane_Doe");
+        let mut store = PseudonymStore::open(&dir.join(PSEUDONYMS_FILE)).unwrap();
+        
+        let shared = dir.join("shared.bdf");
+        let result = export_anonymized(&source, &shared, &mut store, &AnonymizeOptions::default()).unwrap();
+        assert_eq!(result.pseudonym.as_deref(), Some("ANON0001"));
+        let original = std::fs::read(&source).unwrap();
+        let exported = std::fs::read(&shared).unwrap();
+        assert_eq!(text_field(&exported, PATIENT_OFFSET, 80), "ANON0001 X X X");
+        assert_eq!(text_field(&exported, RECORDING_OFFSET, 80), "Startdate 05-MAR-2024 X X X");
+        assert_eq!(&exported[168..], &original[168..]);
+        
+        // 同一受试者沿用假名，映射表重新打开后仍在；不能改用其他假名，也不覆盖已有文件
+        let mut store = PseudonymStore::open(&dir.join(PSEUDONYMS_FILE)).unwrap();
+        let options = AnonymizeOptions { remove_start_date: true, ..AnonymizeOptions::default() };
+        let result = export_anonymized(&source, &dir.join("dated.bdf"), &mut store, &options).unwrap();
+        assert_eq!(result.pseudonym.as_deref(), Some("ANON0001"));
+        let exported = std::fs::read(dir.join("dated.bdf")).unwrap();
+        assert_eq!(text_field(&exported, RECORDING_OFFSET, 80), "Startdate X X X X");
+        assert_eq!(&exported[168..184], ANONYMOUS_START.as_bytes());
+        
+        let renamed = AnonymizeOptions { pseudonym: Some("SUBJ-9".to_string()), ..AnonymizeOptions::default() };
+        assert!(export_anonymized(&source, &dir.join("renamed.bdf"), &mut store, &renamed).is_err());
+        assert!(export_anonymized(&source, &shared, &mut store, &AnonymizeOptions::default()).is_err());
+        
+        let other = dir.join("other.bdf");
+        write_bdf(&other, "P008 M X X");
+        let result = export_anonymized(&other, &dir.join("other_shared.bdf"), &mut store, &renamed).unwrap();
+        assert_eq!(result.pseudonym.as_deref(), Some("SUBJ-9"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,3713 @@
+//! 桌面应用 - 全局状态、前端命令与 Tauri 入口（desktop 特性）
+
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tauri::{Emitter, Manager, State};
+use tauri::ipc::Channel;
+use std::time::Duration;
+
+use crate::{anonymize, archive, bids, capture, catalog, filtered_export, fft_processor, fusion, impedance, logging, metrics};
+use crate::{montage, mqtt, plugins, reader, recorder, repair, scripting, settings, simulator, stages, verify};
+use crate::data_types::*;
+use crate::error::{self, AppError};
+use crate::lsl_manager::{LslManager, LslSource};
+use crate::stream_source::StreamSource;
+use crate::eeg_processor::EegProcessor;
+use crate::session::{StreamEmitter, StreamSession, StreamSessions};
+use crate::playback::PlaybackPlayer;
+use crate::logging::Logging;
+use tracing::{error, info, warn};
+
+// 全局应用状态 - 每个已连接的流一个会话
+#[derive(Default)]
+struct AppState {
+    sessions: Arc<Mutex<StreamSessions>>,               // ✅ 流会话表（stream_id → 会话）
+    acquisition_mode: Arc<Mutex<AcquisitionMode>>,      // 下次连接使用的采集模式
+    chunk_config: Arc<Mutex<ChunkConfig>>,              // LSL拉取分块配置
+    inlet_config: Arc<Mutex<LslInletConfig>>,           // 下次连接使用的EEG inlet参数
+    rate_monitor: Arc<Mutex<RateMonitorConfig>>,        // 实际采样率监测配置
+    channel_config: Arc<Mutex<PipelineChannelConfig>>, // 下次连接使用的管道通道配置
+    fft_config: Arc<Mutex<FftConfig>>,                  // FFT配置，跨连接保留
+    spectrum_range: Arc<Mutex<SpectrumRange>>,          // 频谱输出范围，跨连接保留
+    spectrum_display: Arc<Mutex<SpectrumDisplayOptions>>, // 频谱刻度/归一化/平滑
+    spectral_metrics: Arc<Mutex<SpectralMetricsConfig>>, // alpha峰值/谱边缘频率计算范围
+    connectivity: Arc<Mutex<ConnectivityConfig>>,       // 通道间相干/PLV配置
+    frequency_bands: Arc<Mutex<FrequencyBands>>,        // 频带功率的频带定义
+    notch_filter: Arc<Mutex<NotchFilterConfig>>,        // 工频陷波滤波器配置
+    filters: Arc<Mutex<FilterConfig>>,                  // 高通/低通滤波链配置
+    signal_quality: Arc<Mutex<SignalQualityConfig>>,    // 坏导检测阈值
+    artifacts: Arc<Mutex<ArtifactConfig>>,              // 眨眼/运动伪迹检测配置
+    asr: Arc<Mutex<AsrConfig>>,                         // ASR清洗配置（校准按会话进行）
+    ica: Arc<Mutex<IcaConfig>>,                         // 在线ICA配置（拒绝的成分按会话设置）
+    display_rate: Arc<Mutex<DisplayRateConfig>>,        // 显示路径抽取倍数
+    frame_rate: Arc<Mutex<FrameRateConfig>>,            // 前端帧率（含低功耗模式）
+    processing_stages: Arc<Mutex<ProcessingStagesConfig>>, // 显示路径上可组合的处理阶段
+    scripting: Arc<Mutex<ScriptingConfig>>,             // 自定义指标脚本
+    history: Arc<Mutex<HistoryConfig>>,                 // 原始数据历史缓冲长度
+    spectrogram: Arc<Mutex<SpectrogramConfig>>,         // 频谱图历史深度与列速率
+    recording_triggers: Arc<Mutex<RecordingTriggerConfig>>, // 自动开始/停止录制的规则
+    epochs: Arc<Mutex<EpochConfig>>,                    // 标记锁定分段配置
+    erp: Arc<Mutex<ErpConfig>>,                         // 在线ERP平均配置
+    time_features: Arc<Mutex<TimeFeaturesConfig>>,      // Hjorth等时域特征配置
+    neurofeedback: Arc<Mutex<NeurofeedbackConfig>>,     // 神经反馈阈值规则
+    ssvep: Arc<Mutex<SsvepConfig>>,                     // SSVEP检测配置
+    classifier: Arc<Mutex<ClassifierConfig>>,           // 单试次分类特征/训练配置
+    spatial_filter: Arc<Mutex<Option<SpatialFilter>>>,  // 空间滤波器（CSP等）
+    vitals: Arc<Mutex<VitalsConfig>>,                   // ECG/呼吸辅助通道配置
+    impedance: Arc<Mutex<ImpedanceConfig>>,             // 阻抗分级阈值与检查协议参数
+    osc: Arc<Mutex<OscConfig>>,                         // OSC输出目标与映射
+    mqtt_config: Arc<Mutex<MqttConfig>>,                // MQTT发布配置
+    mqtt: mqtt::SharedMqtt,                             // MQTT发布端（各会话事件共享）
+    upload_config: Arc<Mutex<UploadConfig>>,            // 录制完成后自动上传（各会话共享）
+    montage: Arc<Mutex<MontageConfig>>,                 // 导联/重参考配置
+    output_directory: Arc<Mutex<Option<String>>>,      // 相对录制文件名的基准目录
+    recording_naming: Arc<Mutex<RecordingNamingConfig>>,   // 录制文件命名模板与重名处理
+    session_metadata: Arc<Mutex<SessionMetadata>>,     // 受试者/操作者信息（不持久化）
+    settings_path: Option<std::path::PathBuf>,          // 设置文件路径（setup阶段确定）
+    catalog: Option<Arc<catalog::RecordingCatalog>>,    // 录制索引（数据库无法打开时为 None）
+    pseudonyms: Option<Arc<std::sync::Mutex<anonymize::PseudonymStore>>>,  // 匿名化导出的假名映射表
+    review: Arc<std::sync::Mutex<Option<reader::RecordingReader>>>,  // open_recording 打开的复核文件
+    discovery: Arc<Mutex<Option<LslManager>>>,          // 后台流发现专用管理器（不连接流）
+    started_at: StartedAt,                              // 应用启动时间
+}
+
+// 数据源状态事件的轮询间隔
+const SOURCE_WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// AppState 创建时刻，用于计算运行时间
+struct StartedAt(std::time::Instant);
+
+impl Default for StartedAt {
+    fn default() -> Self {
+        Self(std::time::Instant::now())
+    }
+}
+
+impl AppState {
+    /// 当前保存的默认配置
+    async fn settings(&self) -> Settings {
+        Settings {
+            output_directory: self.output_directory.lock().await.clone(),
+            recording_naming: self.recording_naming.lock().await.clone(),
+            acquisition_mode: *self.acquisition_mode.lock().await,
+            chunk_config: *self.chunk_config.lock().await,
+            inlet_config: *self.inlet_config.lock().await,
+            rate_monitor: *self.rate_monitor.lock().await,
+            channel_config: *self.channel_config.lock().await,
+            fft_config: self.fft_config.lock().await.clone(),
+            spectrum_range: self.spectrum_range.lock().await.clone(),
+            spectrum_display: *self.spectrum_display.lock().await,
+            spectral_metrics: *self.spectral_metrics.lock().await,
+            connectivity: self.connectivity.lock().await.clone(),
+            frequency_bands: self.frequency_bands.lock().await.clone(),
+            notch_filter: *self.notch_filter.lock().await,
+            filters: *self.filters.lock().await,
+            signal_quality: *self.signal_quality.lock().await,
+            artifacts: self.artifacts.lock().await.clone(),
+            asr: *self.asr.lock().await,
+            ica: *self.ica.lock().await,
+            display_rate: *self.display_rate.lock().await,
+            frame_rate: *self.frame_rate.lock().await,
+            processing_stages: self.processing_stages.lock().await.clone(),
+            scripting: self.scripting.lock().await.clone(),
+            history: *self.history.lock().await,
+            spectrogram: *self.spectrogram.lock().await,
+            recording_triggers: self.recording_triggers.lock().await.clone(),
+            epochs: self.epochs.lock().await.clone(),
+            erp: *self.erp.lock().await,
+            time_features: *self.time_features.lock().await,
+            neurofeedback: self.neurofeedback.lock().await.clone(),
+            ssvep: self.ssvep.lock().await.clone(),
+            classifier: self.classifier.lock().await.clone(),
+            vitals: *self.vitals.lock().await,
+            impedance: *self.impedance.lock().await,
+            osc: self.osc.lock().await.clone(),
+            montage: self.montage.lock().await.clone(),
+            mqtt: self.mqtt_config.lock().await.clone(),
+            upload: self.upload_config.lock().await.clone(),
+        }
+    }
+    
+    /// 替换默认配置（已连接的会话不受影响，下次连接时应用）
+    async fn apply_settings(&self, settings: Settings) {
+        let Settings { output_directory, recording_naming, acquisition_mode, chunk_config, inlet_config, rate_monitor, channel_config, fft_config, spectrum_range, spectrum_display, spectral_metrics, connectivity, frequency_bands, notch_filter, filters, signal_quality, artifacts, asr, ica, display_rate, frame_rate, processing_stages, scripting, history, spectrogram, recording_triggers, epochs, erp, time_features, neurofeedback, ssvep, classifier, vitals, impedance, osc, montage, mqtt, upload } = settings;
+        
+        *self.output_directory.lock().await = output_directory;
+        *self.recording_naming.lock().await = recording_naming;
+        *self.acquisition_mode.lock().await = acquisition_mode;
+        *self.chunk_config.lock().await = chunk_config;
+        *self.inlet_config.lock().await = inlet_config;
+        *self.rate_monitor.lock().await = rate_monitor;
+        *self.channel_config.lock().await = channel_config;
+        *self.fft_config.lock().await = fft_config;
+        *self.spectrum_range.lock().await = spectrum_range;
+        *self.spectrum_display.lock().await = spectrum_display;
+        *self.spectral_metrics.lock().await = spectral_metrics;
+        *self.connectivity.lock().await = connectivity;
+        *self.frequency_bands.lock().await = frequency_bands;
+        *self.notch_filter.lock().await = notch_filter;
+        *self.filters.lock().await = filters;
+        *self.signal_quality.lock().await = signal_quality;
+        *self.artifacts.lock().await = artifacts;
+        *self.asr.lock().await = asr;
+        *self.ica.lock().await = ica;
+        *self.display_rate.lock().await = display_rate;
+        *self.frame_rate.lock().await = frame_rate;
+        *self.processing_stages.lock().await = processing_stages;
+        *self.scripting.lock().await = scripting;
+        *self.history.lock().await = history;
+        *self.spectrogram.lock().await = spectrogram;
+        *self.recording_triggers.lock().await = recording_triggers;
+        *self.epochs.lock().await = epochs;
+        *self.erp.lock().await = erp;
+        *self.time_features.lock().await = time_features;
+        *self.neurofeedback.lock().await = neurofeedback;
+        *self.ssvep.lock().await = ssvep;
+        *self.classifier.lock().await = classifier;
+        *self.vitals.lock().await = vitals;
+        *self.impedance.lock().await = impedance;
+        *self.osc.lock().await = osc;
+        *self.montage.lock().await = montage;
+        *self.mqtt_config.lock().await = mqtt;
+        *self.upload_config.lock().await = upload;
+    }
+    
+    async fn save_settings(&self) -> Result<(), AppError> {
+        match &self.settings_path {
+            Some(path) => settings::save(path, &self.settings().await),
+            None => Ok(()),
+        }
+    }
+}
+
+// Tauri命令接口实现
+// 带 stream_id 的命令：指定时作用于该会话；未指定时配置类命令作用于所有会话并保存为默认，
+// 其余命令仅在只连接了一个流时可省略
+
+/// 发现LSL流；filter 可按类型、名称通配符、主机名筛选，省略时返回所有流
+#[tauri::command]
+async fn discover_lsl_streams(filter: Option<StreamFilter>) -> Result<Vec<LslStreamInfo>, AppError> {
+    // 使用临时管理器发现流，避免阻塞已连接会话的采集线程
+    let mut temp_manager = LslManager::new();
+    temp_manager.start().await?;
+    
+    let result = temp_manager.discover_streams(filter.unwrap_or_default())
+        .await;
+    
+    temp_manager.stop().await?;
+    result
+}
+
+/// 启动后台流发现：流出现/消失时发送 stream-appeared / stream-disappeared（内容为 LslStreamInfo），
+/// 已在运行时替换过滤条件
+#[tauri::command]
+async fn start_stream_discovery(
+    filter: Option<StreamFilter>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<(), AppError> {
+    let filter = filter.unwrap_or_default();
+    let mut discovery = state.discovery.lock().await;
+    
+    if let Some(manager) = discovery.as_mut() {
+        return manager.start_discovery(filter);
+    }
+    
+    let mut manager = LslManager::new();
+    manager.start().await?;
+    let events = manager.get_discovery_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get discovery receiver from LSL manager".to_string()))?;
+    manager.start_discovery(filter)?;
+    *discovery = Some(manager);
+    
+    tokio::spawn(async move {
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    let result = match &event {
+                        DiscoveryEvent::Appeared(stream) => app.emit("stream-appeared", stream),
+                        DiscoveryEvent::Disappeared(stream) => app.emit("stream-disappeared", stream),
+                    };
+                    if let Err(e) = result {
+                        error!("❌ Failed to emit discovery event: {}", e);
+                    }
+                }
+                Err(crossbeam_channel::TryRecvError::Empty) => tokio::time::sleep(SOURCE_WATCH_INTERVAL).await,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => break,
+            }
+        }
+        info!("🛰️  Discovery event forwarder stopped");
+    });
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_stream_discovery(state: State<'_, AppState>) -> Result<(), AppError> {
+    if let Some(manager) = state.discovery.lock().await.take() {
+        manager.stop().await?;
+    }
+    Ok(())
+}
+
+/// 按名称连接EEG流；同名设备需同时给出 source_id（可选 hostname）
+#[tauri::command]
+async fn connect_to_stream(
+    stream_name: String,
+    source_id: Option<String>,
+    hostname: Option<String>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    let selector = StreamSelector { name: stream_name, source_id, hostname };
+    connect_selected_stream(selector, stream_id, state, app).await
+}
+
+/// 连接发现列表中的某个流（按名称 + source_id + 主机名精确定位）
+#[tauri::command]
+async fn connect_to_lsl_stream(
+    stream: LslStreamInfo,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    connect_selected_stream(stream.into(), stream_id, state, app).await
+}
+
+/// 连接内置信号模拟器（合成EEG：α/β节律、噪声与眨眼、肌电伪迹），数据经完整处理管道
+#[tauri::command]
+async fn connect_simulator(
+    config: Option<SimulatorConfig>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    info!("🧪 Starting simulator: {} channels @ {}Hz (seed {})", config.channels_count, config.sample_rate, config.seed);
+    
+    let selector = StreamSelector { name: config.name.clone(), source_id: None, hostname: None };
+    connect_source(Box::new(simulator::source(&config)), selector, stream_id, state, app).await
+}
+
+/// 把多台放大器的流按LSL时间戳对齐、重采样为一个虚拟流，经同一个处理器与录制
+#[tauri::command]
+async fn connect_fused_streams(
+    config: FusionConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    config.validate()?;
+    let names: Vec<&str> = config.streams.iter().map(|stream| stream.name.as_str()).collect();
+    info!("🔗 Fusing streams {:?} into '{}'", names, config.name);
+    
+    let selector = StreamSelector { name: config.name.clone(), source_id: None, hostname: None };
+    connect_source(Box::new(fusion::FusionSource::new(Box::new(LslSource), config)), selector, stream_id, state, app).await
+}
+
+async fn connect_selected_stream(
+    selector: StreamSelector,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    connect_source(Box::new(LslSource), selector, stream_id, state, app).await
+}
+
+/// 从给定数据源连接流并启动处理会话
+async fn connect_source(
+    source: Box<dyn StreamSource>,
+    selector: StreamSelector,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    let stream_id = StreamSessions::normalize_id(&stream_id.unwrap_or_else(|| selector.default_session_id()));
+    info!("🔌 Connecting to stream: {} (session '{}')", selector.name, stream_id);
+    
+    // Step 1: 停止同一标识的现有会话（消费式），其他会话不受影响
+    let is_primary = replace_session(&state, &stream_id).await?;
+    
+    // Step 2: 创建新的LSL管理器并连接
+    let acquisition_mode = *state.acquisition_mode.lock().await;
+    let mut manager = LslManager::with_source(source);
+    manager.set_acquisition_mode(acquisition_mode);
+    manager.set_chunk_config(*state.chunk_config.lock().await)?;
+    manager.set_inlet_config(*state.inlet_config.lock().await)?;
+    manager.set_rate_monitor(*state.rate_monitor.lock().await)?;
+    
+    manager.start().await?;
+    
+    let stream_info = manager.connect_to_stream(&selector)
+        .await?;
+    
+    info!("✅ Connected to stream: {} ({} channels @ {}Hz)", 
+             stream_info.name, stream_info.channels_count, stream_info.sample_rate);
+    
+    // Step 3: 获取数据通道
+    let data_rx = manager.get_data_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get data receiver from LSL manager".to_string()))?;
+    let marker_rx = manager.get_marker_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get marker receiver from LSL manager".to_string()))?;
+    let aux_rx = manager.get_aux_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get aux receiver from LSL manager".to_string()))?;
+    let impedance_rx = manager.get_impedance_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get impedance receiver from LSL manager".to_string()))?;
+    let clock_rx = manager.get_clock_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get clock receiver from LSL manager".to_string()))?;
+    let source_events = manager.get_source_event_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get source event receiver from LSL manager".to_string()))?;
+    
+    // 流描述中的通道阻抗（放大器连接时测得）
+    let channel_impedances = impedance::readings_from_metadata(&stream_info.channel_labels, manager.channel_impedances());
+    
+    // Step 4: 创建EEG处理器（主会话沿用原事件名）
+    let emitter = StreamEmitter::new(app.clone(), (!is_primary).then(|| stream_id.clone()))
+        .with_mqtt(state.mqtt.clone(), stream_id.clone());
+    let mut processor = create_processor(stream_info, emitter, &state).await?;
+    
+    // Step 5: 设置数据源并启动处理器
+    processor.set_data_source(data_rx);
+    processor.set_source_stats(manager.pull_stats());
+    processor.set_marker_source(marker_rx);
+    processor.set_aux_source(aux_rx);
+    processor.set_impedance_source(impedance_rx);
+    processor.set_clock_source(clock_rx);
+    processor.start().await?;
+    
+    if !channel_impedances.is_empty() {
+        info!("🔋 Stream reports impedance for {} channels", channel_impedances.len());
+        // 流描述中的阻抗没有测量时刻，时间戳记为0
+        processor.update_impedances(ImpedanceSource::ChannelMetadata, &channel_impedances, 0.0).await;
+    }
+    
+    info!("🚀 EEG processor started");
+    
+    // Step 6: 保存会话
+    let session = StreamSession {
+        stream_id,
+        lsl_manager: Some(manager),
+        playback: None,
+        eeg_processor: processor,
+        is_primary,
+    };
+    let info = session.info().await;
+    
+    state.sessions.lock().await.insert(session);
+    
+    info!("💾 Session '{}' saved (primary: {})", info.stream_id, info.is_primary);
+    
+    emit_connection_status(&app, &state.sessions).await;
+    spawn_source_watcher(app, state.sessions.clone(), info.stream_id.clone(), source_events);
+    
+    Ok(info)
+}
+
+/// 构建连接状态（主会话在前）
+async fn build_connection_status(sessions: &StreamSessions) -> ConnectionStatus {
+    let mut session_infos = Vec::with_capacity(sessions.len());
+    for session in sessions.iter() {
+        session_infos.push(session.info().await);
+    }
+    
+    ConnectionStatus {
+        is_lsl_connected: !sessions.is_empty(),
+        is_processor_running: session_infos.iter().any(|info| info.is_processing),
+        current_stream: session_infos.first().map(|info| info.stream_info.clone()),
+        sessions: session_infos,
+    }
+}
+
+/// 推送 connection-status-changed 事件，前端无需轮询 get_connection_status
+async fn emit_connection_status(app: &tauri::AppHandle, sessions: &Arc<Mutex<StreamSessions>>) {
+    let status = build_connection_status(&*sessions.lock().await).await;
+    mqtt::forward(&app.state::<AppState>().mqtt, "connection-status-changed", None, &status);
+    if let Err(e) = app.emit("connection-status-changed", &status) {
+        error!("❌ Failed to emit connection-status-changed: {}", e);
+    }
+}
+
+/// 监听LSL数据源的丢失/恢复事件和处理管道的停止并推送连接状态；
+/// 工作线程结束（会话停止）后退出
+fn spawn_source_watcher(
+    app: tauri::AppHandle,
+    sessions: Arc<Mutex<StreamSessions>>,
+    stream_id: String,
+    source_events: crossbeam_channel::Receiver<SourceEvent>,
+) {
+    tokio::spawn(async move {
+        let mut was_processing = true;
+        
+        loop {
+            match source_events.try_recv() {
+                // 连接成功已由connect_to_stream推送
+                Ok(SourceEvent::Connected) => {}
+                Ok(SourceEvent::RateNormal) => {}
+                Ok(SourceEvent::RateDeviation) => {
+                    let sessions = sessions.lock().await;
+                    let Ok(session) = sessions.resolve(Some(&stream_id)) else { continue };
+                    let warning = session.lsl_manager.as_ref().and_then(|manager| manager.rate_warning());
+                    if let Some(warning) = warning {
+                        let scope = session.eeg_processor.event_scope();
+                        if let Err(e) = StreamEmitter::new(app.clone(), scope).emit("sample-rate-warning", &warning) {
+                            error!("❌ Failed to emit sample-rate-warning: {}", e);
+                        }
+                    }
+                }
+                Ok(event) => {
+                    info!("📡 Session '{}' source event: {:?}", stream_id, event);
+                    emit_connection_status(&app, &sessions).await;
+                }
+                Err(crossbeam_channel::TryRecvError::Empty) => {
+                    tokio::time::sleep(SOURCE_WATCH_INTERVAL).await;
+                    
+                    // 处理管道自行停止（如Strict模式终止采集）
+                    let is_processing = match sessions.lock().await.resolve(Some(&stream_id)) {
+                        Ok(session) => session.eeg_processor.is_running().await,
+                        Err(_) => continue,
+                    };
+                    if was_processing && !is_processing {
+                        info!("📡 Session '{}' processor stopped", stream_id);
+                        emit_connection_status(&app, &sessions).await;
+                    }
+                    was_processing = is_processing;
+                }
+                Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                    info!("📡 Session '{}' source watcher stopped", stream_id);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// 停止同一标识的现有会话，返回新会话是否应为主会话
+async fn replace_session(state: &AppState, stream_id: &str) -> Result<bool, AppError> {
+    let mut sessions = state.sessions.lock().await;
+    let replaced_primary = match sessions.take(stream_id) {
+        Some(existing) => {
+            let was_primary = existing.is_primary;
+            existing.stop().await?;
+            was_primary
+        }
+        None => false,
+    };
+    Ok(replaced_primary || !sessions.has_primary())
+}
+
+/// 创建EEG处理器并应用保存的默认配置
+async fn create_processor(
+    stream_info: StreamInfo,
+    emitter: StreamEmitter,
+    state: &AppState,
+) -> Result<EegProcessor, AppError> {
+    let mut processor = EegProcessor::new(stream_info, emitter)?;
+    processor.set_acquisition_mode(*state.acquisition_mode.lock().await);
+    processor.set_channel_config(*state.channel_config.lock().await)?;
+    
+    let fft_config = state.fft_config.lock().await.clone();
+    processor.set_fft_config(fft_config).await?;
+    
+    let spectrum_range = state.spectrum_range.lock().await.clone();
+    if let Err(e) = processor.set_spectrum_range(spectrum_range).await {
+        // 保存的范围不适用于该流（超过奈奎斯特频率），退回默认范围
+        warn!("⚠️  {}, using default spectrum range", e);
+        processor.set_spectrum_range(SpectrumRange::default())
+            .await?;
+    }
+    
+    let spectrum_display = *state.spectrum_display.lock().await;
+    processor.set_spectrum_display_options(spectrum_display).await?;
+    
+    let spectral_metrics = *state.spectral_metrics.lock().await;
+    processor.set_spectral_metrics_config(spectral_metrics).await?;
+    
+    let connectivity = state.connectivity.lock().await.clone();
+    processor.set_connectivity_config(connectivity).await?;
+    
+    let frequency_bands = state.frequency_bands.lock().await.clone();
+    processor.set_frequency_bands(frequency_bands).await?;
+    
+    let notch_filter = *state.notch_filter.lock().await;
+    processor.set_notch_filter(notch_filter).await?;
+    
+    let filters = *state.filters.lock().await;
+    if let Err(e) = processor.set_filters(filters).await {
+        // 保存的截止频率不适用于该流的采样率，关闭滤波
+        warn!("⚠️  {}, filters disabled for this stream", e);
+    }
+    
+    let signal_quality = *state.signal_quality.lock().await;
+    processor.set_signal_quality(signal_quality).await?;
+    
+    let artifacts = state.artifacts.lock().await.clone();
+    processor.set_artifact_config(artifacts).await?;
+    
+    let asr = *state.asr.lock().await;
+    processor.set_asr_config(asr).await?;
+    
+    let ica = *state.ica.lock().await;
+    processor.set_ica_config(ica).await?;
+    
+    let display_rate = *state.display_rate.lock().await;
+    processor.set_display_rate(display_rate).await?;
+    
+    let frame_rate = *state.frame_rate.lock().await;
+    processor.set_frame_rate(frame_rate).await?;
+    
+    let processing_stages = state.processing_stages.lock().await.clone();
+    processor.set_processing_stages(processing_stages).await?;
+    
+    let scripting = state.scripting.lock().await.clone();
+    processor.set_scripting(scripting).await?;
+    
+    let history = *state.history.lock().await;
+    processor.set_history_config(history).await?;
+    
+    let spectrogram = *state.spectrogram.lock().await;
+    processor.set_spectrogram_config(spectrogram).await?;
+    
+    let recording_triggers = state.recording_triggers.lock().await.clone();
+    processor.set_recording_triggers(recording_triggers).await?;
+    
+    let epochs = state.epochs.lock().await.clone();
+    processor.set_epoch_config(epochs).await?;
+    
+    let erp = *state.erp.lock().await;
+    processor.set_erp_config(erp).await?;
+    
+    let time_features = *state.time_features.lock().await;
+    processor.set_time_features_config(time_features).await?;
+    
+    let neurofeedback = state.neurofeedback.lock().await.clone();
+    processor.set_neurofeedback_config(neurofeedback).await?;
+    
+    let ssvep = state.ssvep.lock().await.clone();
+    processor.set_ssvep_config(ssvep).await?;
+    
+    let classifier = state.classifier.lock().await.clone();
+    processor.set_classifier_config(classifier).await?;
+    
+    let spatial_filter = state.spatial_filter.lock().await.clone();
+    processor.set_spatial_filter(spatial_filter).await?;
+    
+    let vitals = *state.vitals.lock().await;
+    if let Err(e) = processor.set_vitals_config(vitals).await {
+        // 保存的辅助通道超出该流的通道数
+        warn!("⚠️  {}, vitals disabled for this stream", e);
+    }
+    
+    let impedance = *state.impedance.lock().await;
+    processor.set_impedance_config(impedance).await?;
+    
+    let osc = state.osc.lock().await.clone();
+    processor.set_osc_config(osc).await?;
+    
+    let metadata = state.session_metadata.lock().await.clone();
+    processor.set_session_metadata(metadata).await?;
+    
+    if let Some(catalog) = &state.catalog {
+        processor.set_catalog(catalog.clone());
+    }
+    processor.set_auto_upload(state.upload_config.clone());
+    
+    let montage = state.montage.lock().await.clone();
+    if let Err(e) = processor.set_montage(montage).await {
+        // 保存的导联引用了该流不存在的通道，使用原始参考
+        warn!("⚠️  {}, using raw reference for this stream", e);
+    }
+    
+    Ok(processor)
+}
+
+/// 打开录制文件（EDF/EDF+、BDF/BDF+、XDF）作为虚拟流会话，初始为暂停状态
+#[tauri::command]
+async fn open_playback(
+    path: String,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<SessionInfo, AppError> {
+    let stream_id = StreamSessions::normalize_id(stream_id.as_deref().unwrap_or("playback"));
+    info!("🎞️  Opening playback: {} (session '{}')", path, stream_id);
+    
+    let is_primary = replace_session(&state, &stream_id).await?;
+    
+    let chunk_config = *state.chunk_config.lock().await;
+    let mut player = PlaybackPlayer::open(&path, chunk_config)?;
+    
+    let data_rx = player.get_data_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get data receiver from playback".to_string()))?;
+    let marker_rx = player.get_marker_receiver()
+        .ok_or_else(|| AppError::Channel("Failed to get marker receiver from playback".to_string()))?;
+    
+    let emitter = StreamEmitter::new(app.clone(), (!is_primary).then(|| stream_id.clone()))
+        .with_mqtt(state.mqtt.clone(), stream_id.clone());
+    let mut processor = create_processor(player.stream_info().clone(), emitter, &state).await?;
+    
+    processor.set_data_source(data_rx);
+    processor.set_marker_source(marker_rx);
+    processor.start().await?;
+    
+    let session = StreamSession {
+        stream_id,
+        lsl_manager: None,
+        playback: Some(player),
+        eeg_processor: processor,
+        is_primary,
+    };
+    let info = session.info().await;
+    
+    state.sessions.lock().await.insert(session);
+    
+    info!("💾 Playback session '{}' saved (primary: {})", info.stream_id, info.is_primary);
+    
+    emit_connection_status(&app, &state.sessions).await;
+    
+    Ok(info)
+}
+
+#[tauri::command]
+async fn playback_play(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<PlaybackStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    let player = session.playback()?;
+    
+    player.play()?;
+    Ok(player.status())
+}
+
+#[tauri::command]
+async fn playback_pause(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<PlaybackStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    let player = session.playback()?;
+    
+    player.pause()?;
+    Ok(player.status())
+}
+
+/// 跳转到文件内的指定秒数，播放状态保持不变
+#[tauri::command]
+async fn playback_seek(
+    position_seconds: f64,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<PlaybackStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    let player = session.playback()?;
+    
+    player.seek(position_seconds)?;
+    Ok(player.status())
+}
+
+/// 设置回放速度倍率（0.1 - 16）
+#[tauri::command]
+async fn playback_set_speed(
+    speed: f64,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<PlaybackStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    let player = session.playback()?;
+    
+    player.set_speed(speed)?;
+    Ok(player.status())
+}
+
+#[tauri::command]
+async fn get_playback_status(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<PlaybackStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.playback()?.status())
+}
+
+/// 断开指定会话；未指定时断开所有流
+#[tauri::command]
+async fn disconnect_stream(
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<String, AppError> {
+    info!("🔌 Disconnecting stream: {}", stream_id.as_deref().unwrap_or("<all>"));
+    
+    let sessions_to_stop = {
+        let mut sessions = state.sessions.lock().await;
+        match stream_id.as_deref() {
+            Some(id) => vec![sessions.remove(id)
+                .ok_or_else(|| error::AppError::UnknownStream(id.to_string()))?],
+            None => sessions.drain(),
+        }
+    };
+    
+    let mut sessions_stopped = 0;
+    for session in sessions_to_stop {
+        let stream_id = session.stream_id.clone();
+        if let Err(e) = session.stop().await {
+            warn!("⚠️  Error stopping session '{}': {}", stream_id, e);
+        } else {
+            sessions_stopped += 1;
+        }
+    }
+    
+    info!("✅ Stream disconnected successfully");
+    
+    emit_connection_status(&app, &state.sessions).await;
+    
+    if sessions_stopped > 0 {
+        Ok(format!("Successfully disconnected {} streams", sessions_stopped))
+    } else {
+        Ok("No active connections to disconnect".to_string())
+    }
+}
+
+/// 未指定时返回主会话的流信息
+#[tauri::command]
+async fn get_stream_info(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<StreamInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    
+    let session = match stream_id.as_deref() {
+        Some(_) => Some(sessions.resolve(stream_id.as_deref())?),
+        None => sessions.iter().next(),
+    };
+    
+    match session {
+        Some(session) => match &session.lsl_manager {
+            Some(lsl_manager) => Ok(lsl_manager.get_current_stream_info().await),
+            None => Ok(Some(session.eeg_processor.stream_info().clone())),
+        },
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+async fn list_sessions(
+    state: State<'_, AppState>
+) -> Result<Vec<SessionInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    
+    let mut infos = Vec::with_capacity(sessions.len());
+    for session in sessions.iter() {
+        infos.push(session.info().await);
+    }
+    
+    Ok(infos)
+}
+
+/// 连接LSL标记流（字符串、不规则采样率），标记对齐到EEG样本后发送 marker-received 并写入录制
+#[tauri::command]
+async fn connect_marker_stream(
+    stream_name: String,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<LslStreamInfo, AppError> {
+    info!("🏷️  Connecting marker stream: {}", stream_name);
+    
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    
+    session.lsl_mut()?
+        .connect_marker_stream(&stream_name)
+        .await
+}
+
+#[tauri::command]
+async fn disconnect_marker_stream(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    
+    session.lsl_mut()?
+        .disconnect_marker_stream()
+        .await
+}
+
+#[tauri::command]
+async fn get_marker_stream_info(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<LslStreamInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.lsl_manager.as_ref().and_then(|lsl_manager| lsl_manager.get_marker_stream_info()))
+}
+
+/// 连接辅助流（类型为 Accelerometer/IMU 等的数值流），样本按时间对齐后随帧发送（辅助流段 / aux-update），
+/// 录制选项 record_aux 开启时同时写入旁路CSV
+#[tauri::command]
+async fn connect_aux_stream(
+    stream_name: String,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<StreamInfo, AppError> {
+    info!("🧭 Connecting aux stream: {}", stream_name);
+    
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    
+    session.lsl_mut()?
+        .connect_aux_stream(&stream_name)
+        .await
+}
+
+#[tauri::command]
+async fn disconnect_aux_stream(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    
+    session.lsl_mut()?
+        .disconnect_aux_stream()
+        .await
+}
+
+#[tauri::command]
+async fn get_aux_stream_info(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<StreamInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.lsl_manager.as_ref().and_then(|lsl_manager| lsl_manager.get_aux_stream_info()))
+}
+
+/// 连接阻抗流（类型为 Impedance 的数值流，各通道为电极阻抗kΩ），每组阻抗发送 impedance-update 并写入会话元数据
+#[tauri::command]
+async fn connect_impedance_stream(
+    stream_name: String,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<StreamInfo, AppError> {
+    info!("🔋 Connecting impedance stream: {}", stream_name);
+    
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    
+    session.lsl_mut()?
+        .connect_impedance_stream(&stream_name)
+        .await
+}
+
+#[tauri::command]
+async fn disconnect_impedance_stream(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    
+    session.lsl_mut()?
+        .disconnect_impedance_stream()
+        .await
+}
+
+#[tauri::command]
+async fn get_impedance_stream_info(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<StreamInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.lsl_manager.as_ref().and_then(|lsl_manager| lsl_manager.get_impedance_stream_info()))
+}
+
+/// 各电极最近一次阻抗（来源：阻抗流、流描述的通道阻抗或检查协议）
+#[tauri::command]
+async fn get_impedances(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Vec<ElectrodeImpedance>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_impedances().await)
+}
+
+/// 运行阻抗检查协议：放大器开启检测电流后调用，测量结束后返回并发送 impedance-update
+#[tauri::command]
+async fn run_impedance_check(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ImpedanceUpdate, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.run_impedance_check().await
+}
+
+/// LSL时钟偏移序列（每5秒测量一次），以及将LSL时间戳换算为UTC的偏移；回放会话返回None
+#[tauri::command]
+async fn get_clock_offset(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<ClockOffsetInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.lsl_manager.as_ref().and_then(|lsl_manager| lsl_manager.get_clock_offset()))
+}
+
+/// 订阅二进制帧：帧以原始字节经 ipc::Channel 发送（布局见 BinaryFrameBuilder）
+#[tauri::command]
+async fn subscribe_frames(
+    channel: Channel,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.set_frame_channel(Some(channel)).await;
+    info!("📦 Binary frame channel subscribed for '{}'", session.stream_id);
+    
+    Ok(())
+}
+
+/// 取消二进制帧订阅，恢复 binary-frame-update / frequency-update 事件
+#[tauri::command]
+async fn unsubscribe_frames(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.set_frame_channel(None).await;
+    info!("📦 Binary frame channel unsubscribed for '{}'", session.stream_id);
+    
+    Ok(())
+}
+
+/// 设置前端帧订阅：只发送所列通道，可关闭频谱、限制帧率（传默认值恢复全部）
+#[tauri::command]
+async fn subscribe_frame_data(
+    subscription: FrameSubscription,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    info!("📦 Frame subscription for '{}': channels={:?}, spectrum={}, max_rate={:?}", 
+             session.stream_id, subscription.channels, subscription.include_spectrum, subscription.max_rate);
+    session.eeg_processor.set_frame_subscription(subscription).await
+}
+
+/// 暂停前端帧发送（采集、分析与录制继续），用于冻结显示或长时间无人值守录制时降低CPU占用
+#[tauri::command]
+async fn pause_streaming(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    for session in sessions.targets(stream_id.as_deref())? {
+        session.eeg_processor.set_streaming_paused(true).await;
+        info!("⏸️ Frame streaming paused for '{}'", session.stream_id);
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn resume_streaming(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    for session in sessions.targets(stream_id.as_deref())? {
+        session.eeg_processor.set_streaming_paused(false).await;
+        info!("▶️ Frame streaming resumed for '{}'", session.stream_id);
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_frame_subscription(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FrameSubscription, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_frame_subscription().await)
+}
+
+/// 开始录制，返回主文件路径
+///
+/// 未指定文件名时按设置中的命名模板生成；相对路径写入设置的输出目录，缺少的目录自动创建；
+/// 文件已存在时追加序号（除非设置允许覆盖）。
+/// `outputs` 为同时写入的附加文件，各输出的 `options.processing` 决定写入原始还是滤波后的数据
+#[tauri::command]
+async fn start_recording(
+    filename: Option<String>,
+    options: Option<RecordingOptions>,
+    outputs: Option<Vec<RecordingOutput>>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<String, AppError> {
+    let options = options.unwrap_or_default();
+    let naming = state.recording_naming.lock().await.clone();
+    let output_directory = state.output_directory.lock().await.clone();
+    
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    let filename = match filename {
+        Some(filename) => filename,
+        None => {
+            let metadata = session.eeg_processor.get_session_metadata().await;
+            naming.filename(
+                metadata.patient_code.as_deref(),
+                &session.eeg_processor.stream_info().name,
+                options.format,
+                chrono::Local::now(),
+            )?
+        }
+    };
+    let resolve = |filename: String| -> Result<String, AppError> {
+        let path = match output_directory.as_deref() {
+            Some(dir) if std::path::Path::new(&filename).is_relative() => std::path::Path::new(dir).join(&filename),
+            _ => std::path::PathBuf::from(&filename),
+        };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::Recording(format!("Failed to create directory {}: {}", parent.display(), e)))?;
+        }
+        let path = path.to_string_lossy().into_owned();
+        Ok(if naming.overwrite { path } else { recorder::EdfRecorder::available_filename(&path) })
+    };
+    let filename = resolve(filename)?;
+    let outputs = outputs.unwrap_or_default().into_iter()
+        .map(|output| Ok(RecordingOutput { filename: resolve(output.filename)?, ..output }))
+        .collect::<Result<Vec<_>, AppError>>()?;
+    info!("🔴 Starting recording: {} ({:?}), {} additional outputs", filename, options, outputs.len());
+    
+    session.eeg_processor.start_recording(&filename, options, outputs)
+        .await?;
+    Ok(filename)
+}
+
+/// 设置会话元数据（受试者编号、性别、出生日期、姓名、研究编号、操作者、设备、备注），
+/// 写入之后开始的录制文件头（EDF+/BDF+）；备注作为录制开头的注释
+#[tauri::command]
+async fn set_session_metadata(
+    metadata: SessionMetadata,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    metadata.validate()?;
+    
+    // 元数据含个人信息，日志中不记录内容
+    info!("🧾 Session metadata updated");
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_session_metadata(metadata.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut metadata_guard = state.session_metadata.lock().await;
+        *metadata_guard = metadata;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_session_metadata(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SessionMetadata, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_session_metadata().await);
+    }
+    
+    Ok(state.session_metadata.lock().await.clone())
+}
+
+/// 录制进度：当前文件、时长、已写样本数、文件大小和录制队列占用
+#[tauri::command]
+async fn get_recording_status(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<RecordingStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_recording_status().await)
+}
+
+/// 修复异常退出时未正常关闭的EDF/BDF录制（按文件长度重写记录数并截去不完整的记录）
+#[tauri::command]
+async fn repair_recording(
+    path: String,
+    state: State<'_, AppState>
+) -> Result<RepairReport, AppError> {
+    // 不能修复正在写入的文件
+    ensure_not_recording(&state, &path).await?;
+    
+    info!("🩹 Repairing recording {}", path);
+    repair::repair_recording(std::path::Path::new(&path))
+}
+
+/// 校验录制文件：EDF/BDF 的文件结构（记录数、文件头一致性），以及与录制索引中登记的 SHA-256 比对
+#[tauri::command]
+async fn verify_recording(
+    path: String,
+    state: State<'_, AppState>
+) -> Result<VerificationReport, AppError> {
+    ensure_not_recording(&state, &path).await?;
+    
+    let expected = match state.catalog.as_deref().map(|catalog| catalog.find_checksum(&path)) {
+        Some(Ok(checksum)) => checksum,
+        Some(Err(e)) => {
+            warn!("⚠️ Failed to look up checksum of {}: {}", path, e);
+            None
+        }
+        None => None,
+    };
+    if expected.is_none() {
+        info!("🔍 No indexed checksum for {}, checking structure only", path);
+    }
+    
+    // 大文件计算校验和较慢，放到阻塞线程池
+    tokio::task::spawn_blocking(move || verify::verify_recording(std::path::Path::new(&path), expected.as_deref()))
+        .await?
+}
+
+/// 导出去除受试者身份的EDF/BDF副本；假名映射保存在应用数据目录，不随导出文件分发
+#[tauri::command]
+async fn export_anonymized(
+    path: String,
+    dest: String,
+    options: Option<AnonymizeOptions>,
+    state: State<'_, AppState>
+) -> Result<AnonymizeResult, AppError> {
+    ensure_not_recording(&state, &path).await?;
+    let store = state.pseudonyms.clone()
+        .ok_or_else(|| AppError::Config("Pseudonym mapping is not available".to_string()))?;
+    let options = options.unwrap_or_default();
+    
+    info!("🕶️  Exporting anonymized copy of {} to {}", path, dest);
+    tokio::task::spawn_blocking(move || {
+        let mut store = store.lock().map_err(|_| AppError::Config("Pseudonym mapping poisoned".to_string()))?;
+        anonymize::export_anonymized(std::path::Path::new(&path), std::path::Path::new(&dest), &mut store, &options)
+    })
+        .await?
+}
+
+/// 打开已录制的 EDF/BDF 文件用于复核，返回通道、时长和全部注释；替换之前打开的文件
+#[tauri::command]
+async fn open_recording(
+    path: String,
+    state: State<'_, AppState>
+) -> Result<RecordingFileInfo, AppError> {
+    let review = state.review.clone();
+    
+    // 读取注释需要扫描全部数据记录
+    tokio::task::spawn_blocking(move || {
+        let reader = reader::RecordingReader::open(std::path::Path::new(&path))?;
+        let info = reader.info().clone();
+        *review.lock().map_err(|_| AppError::Playback("Review file poisoned".to_string()))? = Some(reader);
+        Ok::<_, AppError>(info)
+    })
+        .await?
+}
+
+/// 从 open_recording 打开的文件读取一段波形（物理值）及其间的注释；channels 为空时读取全部通道
+#[tauri::command]
+async fn read_segment(
+    start: f64,
+    duration: f64,
+    channels: Option<Vec<String>>,
+    state: State<'_, AppState>
+) -> Result<RecordingSegment, AppError> {
+    let review = state.review.clone();
+    
+    tokio::task::spawn_blocking(move || {
+        let mut review = review.lock().map_err(|_| AppError::Playback("Review file poisoned".to_string()))?;
+        let reader = review.as_mut()
+            .ok_or_else(|| AppError::Playback("No recording is open for review".to_string()))?;
+        reader.read_segment(start, duration, channels.as_deref())
+    })
+        .await?
+}
+
+/// 对已完成的录制做零相位滤波和可选重采样，导出为新的 BDF/GDF 文件（由 dest 扩展名决定）
+#[tauri::command]
+async fn export_filtered(
+    path: String,
+    dest: String,
+    filter_spec: OfflineFilterSpec,
+    state: State<'_, AppState>
+) -> Result<FilteredExportResult, AppError> {
+    ensure_not_recording(&state, &path).await?;
+    
+    info!("🎛️  Exporting filtered copy of {} to {}", path, dest);
+    tokio::task::spawn_blocking(move || {
+        filtered_export::export_filtered(std::path::Path::new(&path), std::path::Path::new(&dest), &filter_spec)
+    })
+        .await?
+}
+
+async fn ensure_not_recording(state: &AppState, path: &str) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    for session in sessions.iter() {
+        let status = session.eeg_processor.get_recording_status().await;
+        if status.filename.as_deref() == Some(path) {
+            return Err(AppError::Recording(format!("{} is still being recorded by session '{}'", path, session.stream_id)));
+        }
+    }
+    Ok(())
+}
+
+/// 停止指定会话的录制；未指定时停止所有会话的录制
+#[tauri::command]
+async fn stop_recording(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("⏹️  Stopping recording");
+    
+    let sessions = state.sessions.lock().await;
+    if sessions.is_empty() {
+        return Err(AppError::NotConnected);
+    }
+    
+    for session in sessions.targets(stream_id.as_deref())? {
+        session.eeg_processor.stop_recording()
+            .await?;
+    }
+    
+    Ok(())
+}
+
+/// 开始采集会话的原始样本流（.cxcap），用于确定性回放与回归测试；相对路径位于输出目录下
+#[tauri::command]
+async fn start_capture(
+    filename: String,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<String, AppError> {
+    let output_directory = state.output_directory.lock().await.clone();
+    let mut path = match output_directory.as_deref() {
+        Some(dir) if std::path::Path::new(&filename).is_relative() => std::path::Path::new(dir).join(&filename),
+        _ => std::path::PathBuf::from(&filename),
+    };
+    if path.extension().is_none() {
+        path.set_extension(capture::CAPTURE_EXTENSION);
+    }
+    let filename = path.to_string_lossy().into_owned();
+    
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    session.eeg_processor.start_capture(&filename).await?;
+    
+    Ok(filename)
+}
+
+/// 结束采集，未在采集时返回 None
+#[tauri::command]
+async fn stop_capture(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<CaptureStats>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    session.eeg_processor.stop_capture().await
+}
+
+/// 暂停录制（文件保持打开）；未指定时暂停所有会话的录制
+#[tauri::command]
+async fn pause_recording(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("⏸️  Pausing recording");
+    
+    let sessions = state.sessions.lock().await;
+    if sessions.is_empty() {
+        return Err(AppError::NotConnected);
+    }
+    
+    for session in sessions.targets(stream_id.as_deref())? {
+        session.eeg_processor.pause_recording()
+            .await?;
+    }
+    
+    Ok(())
+}
+
+/// 恢复录制并在间隙处写入注释；未指定时恢复所有会话的录制
+#[tauri::command]
+async fn resume_recording(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("▶️  Resuming recording");
+    
+    let sessions = state.sessions.lock().await;
+    if sessions.is_empty() {
+        return Err(AppError::NotConnected);
+    }
+    
+    for session in sessions.targets(stream_id.as_deref())? {
+        session.eeg_processor.resume_recording()
+            .await?;
+    }
+    
+    Ok(())
+}
+
+/// 设置原始数据历史缓冲长度（秒）
+#[tauri::command]
+async fn set_history_config(
+    config: HistoryConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🕘 History buffer: {}s", config.seconds);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_history_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.history.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_history_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<HistoryConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_history_config().await);
+    }
+    
+    Ok(*state.history.lock().await)
+}
+
+/// 回看单通道最近 seconds 秒的原始数据
+#[tauri::command]
+async fn get_history(
+    channel: u32,
+    seconds: f64,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<HistorySegment, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.get_history(channel, seconds)
+        .await
+}
+
+/// 保存最近 seconds 秒的原始数据与当前频谱（raw.csv、spectra.csv、snapshot.json），返回快照目录
+///
+/// 未指定 directory 时写入输出目录下的 snapshots/
+#[tauri::command]
+async fn capture_snapshot(
+    seconds: f64,
+    directory: Option<String>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<String, AppError> {
+    let directory = match directory {
+        Some(directory) => std::path::PathBuf::from(directory),
+        None => {
+            let output_directory = state.output_directory.lock().await.clone();
+            std::path::Path::new(output_directory.as_deref().unwrap_or(".")).join("snapshots")
+        }
+    };
+    
+    let snapshot = {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        session.eeg_processor.capture_snapshot(seconds).await?
+    };
+    
+    tokio::task::spawn_blocking(move || snapshot.write_bundle(&directory))
+        .await?
+        .map(|bundle| bundle.to_string_lossy().into_owned())
+}
+
+/// 设置频谱图历史：保留最近 seconds 秒，每秒 columns_per_second 列
+#[tauri::command]
+async fn set_spectrogram_config(
+    config: SpectrogramConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🕘 Spectrogram buffer: {}s at {} columns/s", config.seconds, config.columns_per_second);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_spectrogram_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.spectrogram.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_spectrogram_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SpectrogramConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_spectrogram_config().await);
+    }
+    
+    Ok(*state.spectrogram.lock().await)
+}
+
+/// 单通道（显示路径）最近 seconds 秒的频谱图，供频谱图视图绘制与回滚
+#[tauri::command]
+async fn get_spectrogram(
+    channel: u32,
+    seconds: f64,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SpectrogramSegment, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.get_spectrogram(channel, seconds)
+        .await
+}
+
+/// 将最近 seconds 秒的历史数据保存为文件（未在录制的片段也能保存）
+#[tauri::command]
+async fn export_snapshot(
+    filename: String,
+    seconds: f64,
+    options: Option<RecordingOptions>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<recorder::RecordingStats, AppError> {
+    info!("📸 Exporting last {}s to {}", seconds, filename);
+    
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.export_snapshot(&filename, seconds, options.unwrap_or_default())
+        .await
+}
+
+/// 将会话最近完成的录制导出为BIDS-EEG目录结构；
+/// 未指定电源频率时使用该会话启用的陷波滤波器频率
+#[tauri::command]
+async fn export_bids(
+    session_id: String,
+    destination: String,
+    options: Option<BidsExportOptions>,
+    state: State<'_, AppState>
+) -> Result<BidsExportResult, AppError> {
+    let mut options = options.unwrap_or_default();
+    options.validate()?;
+    
+    let (recording, notch) = {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(Some(&session_id))?;
+        let recording = session.eeg_processor.last_recording().await
+            .ok_or_else(|| AppError::Recording(format!("Session '{}' has no finished recording", session_id)))?;
+        (recording, session.eeg_processor.get_notch_filter().await)
+    };
+    if options.power_line_frequency.is_none() && notch.enabled {
+        options.power_line_frequency = Some(notch.frequency_hz);
+    }
+    
+    info!("📦 Exporting {} to BIDS dataset {}", recording.stats.filename, destination);
+    
+    // 复制录制文件可能较慢，放到阻塞线程池
+    tokio::task::spawn_blocking(move || bids::export(&recording, std::path::Path::new(&destination), &options))
+        .await?
+}
+
+/// 将会话最近完成的录制打包为zip归档：录制文件、注释日志、设置快照和会话报告
+#[tauri::command]
+async fn export_session_archive(
+    session_id: String,
+    destination: String,
+    options: Option<ArchiveOptions>,
+    state: State<'_, AppState>
+) -> Result<ArchiveResult, AppError> {
+    let options = options.unwrap_or_default();
+    let recording = {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(Some(&session_id))?;
+        session.eeg_processor.last_recording().await
+            .ok_or_else(|| AppError::Recording(format!("Session '{}' has no finished recording", session_id)))?
+    };
+    let settings = state.settings().await;
+    
+    info!("📦 Archiving {} to {}", recording.stats.filename, destination);
+    
+    // 压缩录制文件可能较慢，放到阻塞线程池
+    tokio::task::spawn_blocking(move || archive::export(&recording, &settings, std::path::Path::new(&destination), &options))
+        .await?
+}
+
+fn recording_catalog(state: &AppState) -> Result<&catalog::RecordingCatalog, AppError> {
+    state.catalog.as_deref().ok_or_else(|| AppError::Catalog("Recording catalog is not available".to_string()))
+}
+
+/// 录制索引中的全部录制，最近的在前
+#[tauri::command]
+async fn list_recordings(state: State<'_, AppState>) -> Result<Vec<RecordingEntry>, AppError> {
+    recording_catalog(&state)?.list()
+}
+
+/// 按文本、标签和时间范围检索录制索引
+#[tauri::command]
+async fn search_recordings(
+    query: RecordingQuery,
+    state: State<'_, AppState>
+) -> Result<Vec<RecordingEntry>, AppError> {
+    recording_catalog(&state)?.search(&query)
+}
+
+/// 从录制索引中删除；delete_files 为 true 时同时删除录制文件
+#[tauri::command]
+async fn delete_recording(
+    id: i64,
+    delete_files: Option<bool>,
+    state: State<'_, AppState>
+) -> Result<RecordingEntry, AppError> {
+    info!("🗑️  Deleting recording #{}", id);
+    
+    recording_catalog(&state)?.delete(id, delete_files.unwrap_or(false))
+}
+
+/// 设置自动录制规则：收到指定标记或幅值/频带功率越过阈值时开始或停止录制，
+/// 每次切换推送 recording-triggered 说明原因
+#[tauri::command]
+async fn set_recording_triggers(
+    config: RecordingTriggerConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🎯 Recording triggers: {} rules", config.rules.len());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_recording_triggers(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.recording_triggers.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_recording_triggers(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<RecordingTriggerConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_recording_triggers().await);
+    }
+    
+    Ok(state.recording_triggers.lock().await.clone())
+}
+
+/// 设置标记锁定分段：标记前后固定时间窗，可选基线校正与峰峰值拒绝，
+/// 每个完成的分段推送 epoch-extracted（含各标记的接受/拒绝计数）
+#[tauri::command]
+async fn set_epoch_config(
+    config: EpochConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 Epoching: enabled={}, -{}s/+{}s, markers={:?}", 
+             config.enabled, config.pre_seconds, config.post_seconds, config.markers);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_epoch_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.epochs.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_epoch_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<EpochConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_epoch_config().await);
+    }
+    
+    Ok(state.epochs.lock().await.clone())
+}
+
+/// 设置在线ERP平均（需要同时启用分段）：按标记累计接受的分段，
+/// 按间隔推送 erp-update（平均波形、试次数、置信区间）
+#[tauri::command]
+async fn set_erp_config(
+    config: ErpConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 ERP averaging: enabled={}, interval={}ms, CI={}", 
+             config.enabled, config.update_interval_ms, config.confidence_level);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_erp_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.erp.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_erp_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ErpConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_erp_config().await);
+    }
+    
+    Ok(*state.erp.lock().await)
+}
+
+/// 设置时域特征：按间隔推送 time-features（Hjorth活动度/移动度/复杂度、RMS、峰度、过零率）
+#[tauri::command]
+async fn set_time_features_config(
+    config: TimeFeaturesConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 Time features: enabled={}, window={}s, interval={}ms", 
+             config.enabled, config.window_seconds, config.update_interval_ms);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_time_features_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.time_features.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_time_features_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<TimeFeaturesConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_time_features_config().await);
+    }
+    
+    Ok(*state.time_features.lock().await)
+}
+
+/// 设置神经反馈规则：频带功率持续越过阈值时推送 feedback-trigger（active=true），释放时 active=false
+#[tauri::command]
+async fn set_neurofeedback_config(
+    config: NeurofeedbackConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🎯 Neurofeedback: {} rules", config.rules.len());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_neurofeedback_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.neurofeedback.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_neurofeedback_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<NeurofeedbackConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_neurofeedback_config().await);
+    }
+    
+    Ok(state.neurofeedback.lock().await.clone())
+}
+
+/// 设置SSVEP检测：按间隔推送 ssvep-detection（各目标频率的典型相关与判定结果）
+#[tauri::command]
+async fn set_ssvep_config(
+    config: SsvepConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 SSVEP: enabled={}, targets={:?} Hz, harmonics={}, window={}s", 
+             config.enabled, config.target_frequencies, config.harmonics, config.window_seconds);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_ssvep_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.ssvep.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_ssvep_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SsvepConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_ssvep_config().await);
+    }
+    
+    Ok(state.ssvep.lock().await.clone())
+}
+
+/// 设置单试次分类：目标/非目标标记、特征区间与分箱、LDA收缩系数（需先开启分段）
+#[tauri::command]
+async fn set_classifier_config(
+    config: ClassifierConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 Classifier: targets={:?}, window={}-{}s, bin={}ms, shrinkage={}", 
+             config.target_markers, config.start_seconds, config.end_seconds, config.bin_ms, config.shrinkage);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_classifier_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.classifier.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_classifier_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ClassifierConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_classifier_config().await);
+    }
+    
+    Ok(state.classifier.lock().await.clone())
+}
+
+/// 用该流已收集的带标签分段训练分类器，返回训练集规模与准确率
+#[tauri::command]
+async fn train_classifier(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ClassifierStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    let status = session.eeg_processor.train_classifier()
+        .await?;
+    
+    info!("🧠 Classifier trained on {} epochs ({} target), accuracy {:?}", 
+             status.training_epochs, status.target_epochs, status.training_accuracy);
+    Ok(status)
+}
+
+/// 开启/关闭在线分类，开启时每个分段推送 classifier-prediction
+#[tauri::command]
+async fn classify_online(
+    enabled: bool,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ClassifierStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    session.eeg_processor.set_online_classification(enabled)
+        .await
+}
+
+#[tauri::command]
+async fn get_classifier_status(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ClassifierStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    Ok(session.eeg_processor.get_classifier_status().await)
+}
+
+/// 用该流已收集的两类分段训练CSP，返回的滤波器需通过 set_spatial_filter 应用
+#[tauri::command]
+async fn train_csp(
+    request: CspTrainingRequest,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<CspResult, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    let result = session.eeg_processor.train_csp(&request)
+        .await?;
+    
+    info!("🧠 CSP trained on {} / {} epochs, eigenvalues {:?}", 
+             result.epochs_a, result.epochs_b, result.eigenvalues);
+    Ok(result)
+}
+
+/// 设置空间滤波器（None 清除）：输出成分替换导联后的通道，送往显示、FFT与频带功率
+#[tauri::command]
+async fn set_spatial_filter(
+    filter: Option<SpatialFilter>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    if let Some(filter) = filter.as_ref() {
+        filter.validate()?;
+        info!("🧩 Spatial filter: {:?} -> {:?}", filter.input_labels, filter.output_labels);
+    } else {
+        info!("🧩 Spatial filter cleared");
+    }
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_spatial_filter(filter.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut filter_guard = state.spatial_filter.lock().await;
+        *filter_guard = filter;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_spatial_filter(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Option<SpatialFilter>, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_spatial_filter().await);
+    }
+    
+    Ok(state.spatial_filter.lock().await.clone())
+}
+
+/// 设置生理信号提取：ECG通道检测R波（心率、RMSSD），呼吸通道估计呼吸频率，按间隔推送 vitals-update
+#[tauri::command]
+async fn set_vitals_config(
+    config: VitalsConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 Vitals: enabled={}, ECG={:?}, respiration={:?}", 
+             config.enabled, config.ecg_channel, config.respiration_channel);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_vitals_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.vitals.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_vitals_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<VitalsConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_vitals_config().await);
+    }
+    
+    Ok(*state.vitals.lock().await)
+}
+
+/// 设置阻抗分级阈值（良好/一般/差）与检查协议的检测电流参数
+#[tauri::command]
+async fn set_impedance_config(
+    config: ImpedanceConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🔋 Impedance: good≤{}kΩ, poor>{}kΩ, drive {}nA @ {}Hz", 
+             config.good_kohm, config.poor_kohm, config.drive_current_na, config.drive_frequency);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_impedance_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.impedance.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_impedance_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ImpedanceConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_impedance_config().await);
+    }
+    
+    Ok(*state.impedance.lock().await)
+}
+
+/// 配置OSC输出：将频带功率、神经反馈触发按映射发送到指定主机/端口（Max/MSP、TouchDesigner等）
+#[tauri::command]
+async fn configure_osc_output(
+    config: OscConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🎛️  OSC output: enabled={}, target={}:{}, {} mappings", 
+             config.enabled, config.host, config.port, config.mappings.len());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_osc_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.osc.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_osc_output(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<OscConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_osc_config().await);
+    }
+    
+    Ok(state.osc.lock().await.clone())
+}
+
+/// 配置MQTT输出：频带功率、伪迹、连接状态与系统健康发布到broker的配置主题；
+/// 重新配置时替换现有连接，enabled 为 false 时断开
+#[tauri::command]
+async fn configure_mqtt_output(
+    mut config: MqttConfig,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    // get_mqtt_output 不返回密码，未提供时沿用当前的
+    if config.password.is_none() {
+        config.password = state.mqtt_config.lock().await.password.clone();
+    }
+    config.validate()?;
+    
+    info!("📡 MQTT output: enabled={}, broker={}:{}", config.enabled, config.host, config.port);
+    
+    restart_mqtt(app, &state, &config)?;
+    
+    let mut config_guard = state.mqtt_config.lock().await;
+    *config_guard = config;
+    
+    Ok(())
+}
+
+/// 按配置替换MQTT发布端，未启用时断开
+fn restart_mqtt(app: tauri::AppHandle, state: &AppState, config: &MqttConfig) -> Result<(), AppError> {
+    let publisher = config.enabled.then(|| {
+        let mut publisher = mqtt::MqttPublisher::connect(config);
+        if publisher.topics().health.is_some() {
+            publisher.attach_task(spawn_health_publisher(app, Duration::from_secs(config.health_interval_secs)));
+        }
+        publisher
+    });
+    *state.mqtt.write().map_err(|_| AppError::Config("MQTT state lock poisoned".to_string()))? = publisher;
+    
+    Ok(())
+}
+
+/// 配置录制完成后自动上传到 S3 或 WebDAV；对之后结束的录制生效
+#[tauri::command]
+async fn configure_upload(
+    mut config: UploadConfig,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    // get_upload_config 不返回密钥，未提供时沿用当前的
+    if config.secret_key.is_none() {
+        config.secret_key = state.upload_config.lock().await.secret_key.clone();
+    }
+    config.validate()?;
+    
+    info!("☁️  Upload: enabled={}, target={:?}, endpoint={}", config.enabled, config.target, config.endpoint);
+    
+    *state.upload_config.lock().await = config;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_upload_config(
+    state: State<'_, AppState>
+) -> Result<UploadConfig, AppError> {
+    Ok(state.upload_config.lock().await.clone())
+}
+
+#[tauri::command]
+async fn get_mqtt_output(
+    state: State<'_, AppState>
+) -> Result<MqttConfig, AppError> {
+    Ok(state.mqtt_config.lock().await.clone())
+}
+
+/// 定时发布系统健康状态，随MQTT发布端一同终止
+fn spawn_health_publisher(app: tauri::AppHandle, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let state = app.state::<AppState>();
+            let health = build_system_health(&state).await;
+            mqtt::forward(&state.mqtt, "system-health", None, &health);
+        }
+    })
+}
+
+/// 在当前录制时间点添加事件标记（EDF+注释），返回注释起点（秒）
+/// 未指定流时写入所有正在录制的会话
+#[tauri::command]
+async fn add_annotation(
+    description: String,
+    duration: Option<f64>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<f64, AppError> {
+    let sessions = state.sessions.lock().await;
+    if sessions.is_empty() {
+        return Err(AppError::NotConnected);
+    }
+    
+    let mut first_onset = None;
+    let mut last_error = None;
+    
+    for session in sessions.targets(stream_id.as_deref())? {
+        match session.eeg_processor.add_annotation(&description, duration).await {
+            Ok(onset) => {
+                info!("📝 Annotation at {:.3}s ({}): {}", onset, session.stream_id, description);
+                first_onset.get_or_insert(onset);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    
+    first_onset.ok_or_else(|| last_error.unwrap_or_else(|| AppError::Recording("No active recording".to_string())))
+}
+
+/// 设置FFT窗长、跳步和补零；若已连接则立即应用到运行中的FFT线程
+#[tauri::command]
+async fn set_fft_config(
+    config: FftConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FftConfig, AppError> {
+    config.validate()?;
+    
+    info!("🟡 Updating FFT config: {:?}", config);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_fft_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.fft_config.lock().await;
+        *config_guard = config.clone();
+    }
+    
+    Ok(config)
+}
+
+#[tauri::command]
+async fn get_fft_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FftConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_fft_config().await);
+    }
+    
+    Ok(state.fft_config.lock().await.clone())
+}
+
+/// 设置频谱输出范围，返回按奈奎斯特频率截断后的实际范围（多个流时为主会话的范围）
+#[tauri::command]
+async fn set_spectrum_range(
+    min_hz: f64,
+    max_hz: f64,
+    bin_count: usize,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SpectrumRange, AppError> {
+    let range = SpectrumRange { min_hz, max_hz, bin_count };
+    range.validate()?;
+    
+    info!("🟡 Updating spectrum range: {}-{}Hz, {} bins", min_hz, max_hz, bin_count);
+    
+    let mut effective = None;
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            let session_range = session.eeg_processor.set_spectrum_range(range.clone())
+                .await?;
+            effective.get_or_insert(session_range);
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut range_guard = state.spectrum_range.lock().await;
+        *range_guard = range.clone();
+    }
+    
+    Ok(effective.unwrap_or(range))
+}
+
+#[tauri::command]
+async fn get_spectrum_range(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SpectrumRange, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_spectrum_range().await);
+    }
+    
+    Ok(state.spectrum_range.lock().await.clone())
+}
+
+/// 设置频谱显示后处理：线性/dB刻度、相对功率归一化、帧间指数平滑
+#[tauri::command]
+async fn set_spectrum_display_options(
+    options: SpectrumDisplayOptions,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    options.validate()?;
+    
+    info!("🟡 Spectrum display: scale={:?}, relative={}, smoothing={}", 
+             options.scale, options.relative, options.smoothing);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_spectrum_display_options(options)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut options_guard = state.spectrum_display.lock().await;
+        *options_guard = options;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_spectrum_display_options(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SpectrumDisplayOptions, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_spectrum_display_options().await);
+    }
+    
+    Ok(*state.spectrum_display.lock().await)
+}
+
+/// 设置频谱指标：每秒推送 spectral-metrics（个体alpha峰值频率、谱边缘频率、总功率）
+#[tauri::command]
+async fn set_spectral_metrics_config(
+    config: SpectralMetricsConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🟡 Spectral metrics: enabled={}, alpha {}-{}Hz, SEF{:.0} over {}-{}Hz", 
+             config.enabled, config.alpha_min_hz, config.alpha_max_hz,
+             config.edge_fraction * 100.0, config.min_hz, config.max_hz);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_spectral_metrics_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.spectral_metrics.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_spectral_metrics_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SpectralMetricsConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_spectral_metrics_config().await);
+    }
+    
+    Ok(*state.spectral_metrics.lock().await)
+}
+
+/// 设置通道间连接性：按间隔推送 connectivity-update（各频带的NxN相干矩阵，可选PLV），
+/// 频带名称引用频带功率的频带定义
+#[tauri::command]
+async fn set_connectivity_config(
+    config: ConnectivityConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🟡 Connectivity: enabled={}, bands={:?}, plv={}, interval={}ms", 
+             config.enabled, config.bands, config.include_plv, config.update_interval_ms);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_connectivity_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.connectivity.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_connectivity_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ConnectivityConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_connectivity_config().await);
+    }
+    
+    Ok(state.connectivity.lock().await.clone())
+}
+
+/// 设置频带功率的频带边界（如 alpha 8-13Hz），立即应用到运行中的处理器
+#[tauri::command]
+async fn set_frequency_bands(
+    bands: Vec<FrequencyBand>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FrequencyBands, AppError> {
+    let bands = FrequencyBands(bands);
+    bands.validate()?;
+    
+    info!("📶 Updating frequency bands: {:?}", bands.names());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_frequency_bands(bands.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut bands_guard = state.frequency_bands.lock().await;
+        *bands_guard = bands.clone();
+    }
+    
+    Ok(bands)
+}
+
+#[tauri::command]
+async fn get_frequency_bands(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FrequencyBands, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_frequency_bands().await);
+    }
+    
+    Ok(state.frequency_bands.lock().await.clone())
+}
+
+/// 开关工频陷波滤波器（50/60Hz，可选谐波），立即应用到运行中的处理器
+#[tauri::command]
+async fn set_notch_filter(
+    enabled: bool,
+    frequency_hz: f64,
+    harmonics: bool,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<NotchFilterConfig, AppError> {
+    let config = NotchFilterConfig {
+        enabled,
+        frequency_hz,
+        harmonics,
+        ..NotchFilterConfig::default()
+    };
+    config.validate()?;
+    
+    info!("🔇 Notch filter: enabled={}, {}Hz, harmonics={}", enabled, frequency_hz, harmonics);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_notch_filter(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.notch_filter.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(config)
+}
+
+#[tauri::command]
+async fn get_notch_filter(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<NotchFilterConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_notch_filter().await);
+    }
+    
+    Ok(*state.notch_filter.lock().await)
+}
+
+/// 设置高通/低通滤波（如 hp=0.5, lp=45），两者都设置即为带通
+#[tauri::command]
+async fn set_filters(
+    hp: Option<f64>,
+    lp: Option<f64>,
+    order: Option<usize>,
+    apply_to_recording: Option<bool>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FilterConfig, AppError> {
+    let defaults = FilterConfig::default();
+    let config = FilterConfig {
+        hp,
+        lp,
+        order: order.unwrap_or(defaults.order),
+        apply_to_recording: apply_to_recording.unwrap_or(defaults.apply_to_recording),
+    };
+    
+    info!("🎚️  Filters: hp={:?}, lp={:?}, order={}, recording={}", 
+             config.hp, config.lp, config.order, config.apply_to_recording);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        let targets = sessions.targets(stream_id.as_deref())?;
+        if targets.is_empty() {
+            config.validate(0.0)?;
+        }
+        for session in targets {
+            session.eeg_processor.set_filters(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.filters.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(config)
+}
+
+#[tauri::command]
+async fn get_filters(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FilterConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_filters().await);
+    }
+    
+    Ok(*state.filters.lock().await)
+}
+
+/// 设置坏导检测阈值（平线、贴轨、方差异常、工频占比），结果以 channel-quality-update 每秒推送
+#[tauri::command]
+async fn set_signal_quality_config(
+    config: SignalQualityConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🩺 Signal quality: {:?}", config);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_signal_quality(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.signal_quality.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_signal_quality_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<SignalQualityConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_signal_quality().await);
+    }
+    
+    Ok(*state.signal_quality.lock().await)
+}
+
+/// 设置眨眼/运动伪迹检测（额区通道的幅值与斜率阈值），检测到的伪迹段以 artifact-detected 推送
+#[tauri::command]
+async fn set_artifact_config(
+    config: ArtifactConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("👁️  Artifact detection: {:?}", config);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_artifact_config(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.artifacts.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_artifact_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ArtifactConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_artifact_config().await);
+    }
+    
+    Ok(state.artifacts.lock().await.clone())
+}
+
+/// 设置ASR清洗（仅显示/FFT路径）；启用后需调用 calibrate_asr 采集干净数据
+#[tauri::command]
+async fn set_asr_config(
+    config: AsrConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧹 ASR: {:?}", config);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_asr_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.asr.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_asr_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<AsrConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_asr_config().await);
+    }
+    
+    Ok(*state.asr.lock().await)
+}
+
+/// 开始ASR校准窗口，完成后推送 asr-calibrated
+#[tauri::command]
+async fn calibrate_asr(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    session.eeg_processor.request_asr_calibration()
+        .await
+}
+
+/// 设置在线ICA（仅显示/FFT路径），成分权重与时间序列以 ica-update 推送
+#[tauri::command]
+async fn set_ica_config(
+    config: IcaConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🧩 ICA: {:?}", config);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_ica_config(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.ica.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_ica_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<IcaConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_ica_config().await);
+    }
+    
+    Ok(*state.ica.lock().await)
+}
+
+/// 设置重混合显示信号时置零的ICA成分，空列表恢复原始信号
+#[tauri::command]
+async fn set_ica_rejected_components(
+    components: Vec<u32>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("🧩 ICA rejected components: {:?}", components);
+    
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.set_ica_rejected(components)
+        .await
+}
+
+#[tauri::command]
+async fn get_ica_rejected_components(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Vec<u32>, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_ica_rejected().await)
+}
+
+/// 设置显示路径抽取倍数（带抗混叠滤波），录制与FFT保持全速率
+#[tauri::command]
+async fn set_display_decimation(
+    factor: u32,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let config = DisplayRateConfig { decimation_factor: factor };
+    config.validate()?;
+    
+    info!("📉 Display decimation: x{}", factor);
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_display_rate(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.display_rate.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_display_rate(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<DisplayRateInfo, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_display_rate().await)
+}
+
+/// 设置前端帧率（10-60 FPS）；low_power 为 true 时固定 5 FPS，用于长时间无人值守录制
+#[tauri::command]
+async fn set_frame_rate(
+    fps: u32,
+    low_power: Option<bool>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let config = FrameRateConfig { fps, low_power: low_power.unwrap_or(false) };
+    config.validate()?;
+    
+    info!("🎞️ Frame rate: {} FPS{}", config.effective_fps(), if config.low_power { " (low power)" } else { "" });
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_frame_rate(config)
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.frame_rate.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_frame_rate(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<FrameRateConfig, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_frame_rate().await)
+}
+
+/// 设置显示路径上的处理阶段链（内置阶段与已注册的自定义阶段按顺序组合），输出经 stage-metrics 事件推送
+#[tauri::command]
+async fn set_processing_stages(
+    stages: Vec<StageSpec>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let config = ProcessingStagesConfig { stages };
+    config.validate()?;
+    
+    info!("🧩 Processing stages: {} configured", config.stages.len());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_processing_stages(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.processing_stages.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_processing_stages(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ProcessingStagesConfig, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_processing_stages().await)
+}
+
+/// 已注册、可在 StageSpec::Custom 中引用的自定义阶段名称
+#[tauri::command]
+async fn list_custom_stages() -> Result<Vec<String>, AppError> {
+    let registry = stages::StageRegistry::global().read()
+        .map_err(|_| AppError::Plugin("Stage registry lock poisoned".to_string()))?;
+    Ok(registry.names())
+}
+
+/// 已安装的插件及其注册的阶段
+#[tauri::command]
+async fn list_plugins() -> Result<Vec<PluginInfo>, AppError> {
+    Ok(plugins::installed())
+}
+
+/// 设置自定义指标脚本（Rhai），指标经 script-metrics 事件推送，record 为 true 时写入录制旁路文件
+#[tauri::command]
+async fn set_scripts(
+    scripts: Vec<ScriptHook>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let config = ScriptingConfig { scripts };
+    scripting::ScriptHooks::check(&config)?;
+    
+    info!("📜 Scripts: {:?}", config.scripts.iter().map(|script| &script.name).collect::<Vec<_>>());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            session.eeg_processor.set_scripting(config.clone())
+                .await?;
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.scripting.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_scripts(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ScriptingConfig, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_scripting().await)
+}
+
+/// 只编译检查脚本（语法与入口函数），供编辑器即时反馈
+#[tauri::command]
+async fn check_script(script: ScriptHook) -> Result<(), AppError> {
+    scripting::ScriptHooks::check(&ScriptingConfig { scripts: vec![script] })
+}
+
+/// 热更新处理配置（滤波截止频率、显示抽取、频谱范围、平滑等），无需重启管道；
+/// 先对所有目标会话校验全部更新，任一失败则不写入
+#[tauri::command]
+async fn update_processor_config(
+    updates: Vec<ProcessorConfigUpdate>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("🔧 Applying {} processor config update(s)", updates.len());
+    
+    {
+        let sessions = state.sessions.lock().await;
+        let targets = sessions.targets(stream_id.as_deref())?;
+        for session in &targets {
+            for update in &updates {
+                session.eeg_processor.check_config_update(update)?;
+            }
+        }
+        for session in &targets {
+            for update in &updates {
+                session.eeg_processor.apply_config_update(update)
+                    .await?;
+            }
+        }
+    }
+    
+    if stream_id.is_none() {
+        for update in updates {
+            match update {
+                ProcessorConfigUpdate::Filters(config) => *state.filters.lock().await = config,
+                ProcessorConfigUpdate::NotchFilter(config) => *state.notch_filter.lock().await = config,
+                ProcessorConfigUpdate::DisplayRate(config) => *state.display_rate.lock().await = config,
+                ProcessorConfigUpdate::SpectrumRange(range) => *state.spectrum_range.lock().await = range,
+                ProcessorConfigUpdate::SpectrumDisplay(options) => *state.spectrum_display.lock().await = options,
+                ProcessorConfigUpdate::Fft(config) => *state.fft_config.lock().await = config,
+                ProcessorConfigUpdate::FrameRate(config) => *state.frame_rate.lock().await = config,
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// 设置导联（共平均、连接乳突、双极、自定义参考或10-20预设），仅作用于显示与FFT
+#[tauri::command]
+async fn set_montage(
+    montage: MontageConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<MontageInfo, AppError> {
+    info!("🧭 Montage: {:?}", montage);
+    
+    let mut info = None;
+    {
+        let sessions = state.sessions.lock().await;
+        for session in sessions.targets(stream_id.as_deref())? {
+            let session_info = session.eeg_processor.set_montage(montage.clone())
+                .await?;
+            info.get_or_insert(session_info);
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.montage.lock().await;
+        *config_guard = montage.clone();
+    }
+    
+    // 未连接时通道标签未知，连接时再解析
+    Ok(info.unwrap_or(MontageInfo { config: montage, channel_labels: Vec::new() }))
+}
+
+#[tauri::command]
+async fn get_montage(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<MontageConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.eeg_processor.get_montage().await);
+    }
+    
+    Ok(state.montage.lock().await.clone())
+}
+
+/// 选择参与处理的通道（排除坏电极），被排除的通道不参与显示、导联、FFT和频带功率；
+/// indices 为空表示恢复全部通道。通道索引随流而定，不保存为默认
+#[tauri::command]
+async fn set_active_channels(
+    indices: Vec<u32>,
+    record_excluded: Option<bool>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ChannelSelectionInfo, AppError> {
+    let selection = ChannelSelection {
+        active: (!indices.is_empty()).then_some(indices),
+        record_excluded: record_excluded.unwrap_or(true),
+    };
+    
+    info!("🎚️  Active channels: {:?}", selection);
+    
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    session.eeg_processor.set_channel_selection(selection)
+        .await
+}
+
+#[tauri::command]
+async fn get_active_channels(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ChannelSelection, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.get_channel_selection().await)
+}
+
+/// 列出10-20导联预设，并标注指定流（默认主会话）能否使用
+#[tauri::command]
+async fn get_available_montages(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<Vec<MontagePresetInfo>, AppError> {
+    let sessions = state.sessions.lock().await;
+    
+    let session = match stream_id.as_deref() {
+        Some(_) => Some(sessions.resolve(stream_id.as_deref())?),
+        None => sessions.iter().next(),
+    };
+    
+    Ok(match session {
+        Some(session) => session.eeg_processor.available_montages(),
+        None => montage::available_presets(&[]),
+    })
+}
+
+/// 设置LSL拉取分块：凑满 chunk_size 个样本或等待 timeout_ms 后整块送入处理管道
+#[tauri::command]
+async fn set_chunk_config(
+    chunk_size: usize,
+    timeout_ms: u64,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ChunkConfig, AppError> {
+    let config = ChunkConfig { chunk_size, timeout_ms };
+    config.validate()?;
+    
+    info!("📦 Chunk config: {} samples / {}ms", chunk_size, timeout_ms);
+    
+    {
+        let mut sessions = state.sessions.lock().await;
+        for session in sessions.targets_mut(stream_id.as_deref())? {
+            // 回放会话在打开时使用分块配置，不可热更新
+            if let Some(lsl_manager) = session.lsl_manager.as_mut() {
+                lsl_manager.set_chunk_config(config)?;
+            }
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.chunk_config.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(config)
+}
+
+#[tauri::command]
+async fn get_chunk_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<ChunkConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.lsl()?.get_chunk_config());
+    }
+    
+    Ok(*state.chunk_config.lock().await)
+}
+
+/// 设置实际采样率监测窗口与容差，超出容差时发送 sample-rate-warning
+#[tauri::command]
+async fn set_rate_monitor_config(
+    config: RateMonitorConfig,
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("⏱️  Rate monitor: {}s window, ±{}%", config.window_seconds, config.tolerance_percent);
+    
+    {
+        let mut sessions = state.sessions.lock().await;
+        for session in sessions.targets_mut(stream_id.as_deref())? {
+            // 回放会话按文件的名义采样率播放，无需监测
+            if let Some(lsl_manager) = session.lsl_manager.as_mut() {
+                lsl_manager.set_rate_monitor(config)?;
+            }
+        }
+    }
+    
+    if stream_id.is_none() {
+        let mut config_guard = state.rate_monitor.lock().await;
+        *config_guard = config;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_rate_monitor_config(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<RateMonitorConfig, AppError> {
+    if stream_id.is_some() {
+        let sessions = state.sessions.lock().await;
+        let session = sessions.resolve(stream_id.as_deref())?;
+        return Ok(session.lsl()?.get_rate_monitor());
+    }
+    
+    Ok(*state.rate_monitor.lock().await)
+}
+
+/// 设置采集模式（standard / strict），在下一次连接时生效
+#[tauri::command]
+async fn set_acquisition_mode(
+    mode: AcquisitionMode,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("🔒 Acquisition mode set to {:?} (applies to next connection)", mode);
+    
+    let mut mode_guard = state.acquisition_mode.lock().await;
+    *mode_guard = mode;
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_acquisition_mode(
+    state: State<'_, AppState>
+) -> Result<AcquisitionMode, AppError> {
+    Ok(*state.acquisition_mode.lock().await)
+}
+
+/// 设置管道通道容量与溢出策略，在下一次连接或 restart_processor 时生效
+#[tauri::command]
+async fn set_channel_config(
+    config: PipelineChannelConfig,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("🚰 Pipeline channels set to {:?} (applies to next connection or pipeline restart)", config);
+    
+    let mut config_guard = state.channel_config.lock().await;
+    *config_guard = config;
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_channel_config(
+    state: State<'_, AppState>
+) -> Result<PipelineChannelConfig, AppError> {
+    Ok(*state.channel_config.lock().await)
+}
+
+/// 设置EEG inlet缓冲、分块与后处理参数，在下一次连接时生效
+#[tauri::command]
+async fn set_lsl_inlet_config(
+    config: LslInletConfig,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    config.validate()?;
+    
+    info!("📥 LSL inlet set to {:?} (applies to next connection)", config);
+    
+    let mut config_guard = state.inlet_config.lock().await;
+    *config_guard = config;
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_lsl_inlet_config(
+    state: State<'_, AppState>
+) -> Result<LslInletConfig, AppError> {
+    Ok(*state.inlet_config.lock().await)
+}
+
+#[tauri::command]
+async fn get_connection_status(
+    state: State<'_, AppState>
+) -> Result<ConnectionStatus, AppError> {
+    let sessions = state.sessions.lock().await;
+    
+    Ok(build_connection_status(&sessions).await)
+}
+
+#[tauri::command]
+async fn initialize_system(
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    info!("🚀 Initializing EEG system");
+    
+    // 检查是否已经初始化
+    let sessions = state.sessions.lock().await;
+    if !sessions.is_empty() {
+        return Ok(()); // 已经初始化
+    }
+    drop(sessions);
+    
+    // 系统初始化逻辑可以在这里添加
+    // 例如：检查LSL库是否可用、设备权限等
+    
+    info!("✅ EEG system initialized");
+    Ok(())
+}
+
+#[tauri::command]
+async fn shutdown_system(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle
+) -> Result<(), AppError> {
+    info!("🔌 Shutting down EEG system");
+    
+    // 优雅关闭所有组件
+    disconnect_stream(None, state, app).await?;
+    
+    info!("✅ EEG system shutdown complete");
+    Ok(())
+}
+
+/// 获取持久化设置（各项默认配置与录制输出目录）
+#[tauri::command]
+async fn get_settings(
+    state: State<'_, AppState>
+) -> Result<Settings, AppError> {
+    Ok(state.settings().await)
+}
+
+/// 更新设置并写入配置目录：patch 为部分JSON，对象递归合并，null 恢复默认；
+/// 新的默认配置在下次连接时应用（已连接的会话使用各 set_* 命令调整）
+#[tauri::command]
+async fn update_settings(
+    patch: serde_json::Value,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<Settings, AppError> {
+    let current = state.settings().await;
+    let settings = settings::merge(&current, patch)?;
+    
+    if settings.mqtt != current.mqtt {
+        restart_mqtt(app, &state, &settings.mqtt)?;
+    }
+    state.apply_settings(settings.clone()).await;
+    state.save_settings().await?;
+    
+    info!("⚙️  Settings updated");
+    Ok(settings)
+}
+
+// 新增：获取系统健康状态
+#[tauri::command]
+async fn get_system_health(
+    state: State<'_, AppState>
+) -> Result<SystemHealth, AppError> {
+    Ok(build_system_health(&state).await)
+}
+
+async fn build_system_health(state: &AppState) -> SystemHealth {
+    let sessions = state.sessions.lock().await;
+    
+    SystemHealth {
+        lsl_manager_status: if !sessions.is_empty() { 
+            "Running".to_string() 
+        } else { 
+            "Stopped".to_string() 
+        },
+        processor_status: if !sessions.is_empty() { 
+            "Running".to_string() 
+        } else { 
+            "Stopped".to_string() 
+        },
+        active_sessions: sessions.len(),
+        memory_usage_mb: metrics::process_memory_mb(),
+        uptime_seconds: state.started_at.0.elapsed().as_secs(),
+        pipelines: sessions
+            .iter()
+            .map(|session| {
+                let mut pipeline = session.eeg_processor.metrics().snapshot(&session.stream_id);
+                pipeline.effective_sample_rate = session.lsl_manager.as_ref()
+                    .and_then(|manager| manager.effective_sample_rate());
+                pipeline
+            })
+            .collect(),
+    }
+}
+
+/// 重建会话的处理线程（应用保存的管道通道配置），LSL inlet与进行中的录制保持不变
+#[tauri::command]
+async fn restart_processor(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<(), AppError> {
+    let channel_config = *state.channel_config.lock().await;
+    
+    let mut sessions = state.sessions.lock().await;
+    let session = sessions.resolve_mut(stream_id.as_deref())?;
+    session.eeg_processor.set_channel_config(channel_config)?;
+    session.eeg_processor.restart_pipeline().await?;
+    
+    info!("🔄 Processor restarted for '{}'", session.stream_id);
+    Ok(())
+}
+
+/// 管道各阶段（LSL拉取、分发、收集、FFT、发送、录制）的延迟分位数与吞吐，用于性能排查；
+/// 运行中每2秒另有 pipeline-metrics 事件
+#[tauri::command]
+async fn get_pipeline_metrics(
+    stream_id: Option<String>,
+    state: State<'_, AppState>
+) -> Result<PipelineMetricsReport, AppError> {
+    let sessions = state.sessions.lock().await;
+    let session = sessions.resolve(stream_id.as_deref())?;
+    
+    Ok(session.eeg_processor.metrics().stage_report())
+}
+
+// 日志命令
+#[tauri::command]
+async fn get_recent_logs(
+    level: Option<String>,
+    n: Option<usize>,
+    logging: State<'_, Logging>
+) -> Result<Vec<LogEntry>, AppError> {
+    let min_level = level.as_deref().map(logging::parse_level).transpose()?;
+    Ok(logging.recent(min_level, n))
+}
+
+#[tauri::command]
+async fn set_log_level(level: String, logging: State<'_, Logging>) -> Result<(), AppError> {
+    let level = logging::parse_level(&level)?;
+    logging.set_level(level)?;
+    info!("📝 Log level set to {}", level);
+    Ok(())
+}
+
+// Tauri应用配置
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    run_with_plugins(Vec::new())
+}
+
+/// 启动应用并安装静态链接的插件（动态库插件在 setup 阶段从应用数据目录加载）
+pub fn run_with_plugins(plugins: Vec<Box<dyn plugins::Plugin>>) {
+    let logging = Logging::init();
+    info!("🧠 Starting Open-CortexArray EEG Visualization System");
+    
+    for plugin in plugins {
+        if let Err(e) = plugins::install(plugin) {
+            error!("❌ {}", e);
+        }
+    }
+    
+    tauri::Builder::default()
+        .manage(logging)
+        .invoke_handler(tauri::generate_handler![
+            discover_lsl_streams,
+            start_stream_discovery,
+            stop_stream_discovery,
+            connect_to_stream,
+            connect_to_lsl_stream,
+            connect_simulator,
+            connect_fused_streams,
+            disconnect_stream,
+            get_stream_info,
+            list_sessions,
+            connect_marker_stream,
+            disconnect_marker_stream,
+            get_marker_stream_info,
+            connect_aux_stream,
+            disconnect_aux_stream,
+            get_aux_stream_info,
+            connect_impedance_stream,
+            disconnect_impedance_stream,
+            get_impedance_stream_info,
+            get_impedances,
+            run_impedance_check,
+            get_clock_offset,
+            open_playback,
+            playback_play,
+            playback_pause,
+            playback_seek,
+            playback_set_speed,
+            get_playback_status,
+            subscribe_frames,
+            unsubscribe_frames,
+            subscribe_frame_data,
+            get_frame_subscription,
+            pause_streaming,
+            resume_streaming,
+            start_recording,
+            set_session_metadata,
+            get_session_metadata,
+            stop_recording,
+            start_capture,
+            stop_capture,
+            pause_recording,
+            resume_recording,
+            add_annotation,
+            set_fft_config,
+            get_fft_config,
+            set_spectrum_range,
+            get_spectrum_range,
+            set_spectrum_display_options,
+            get_spectrum_display_options,
+            set_spectral_metrics_config,
+            get_spectral_metrics_config,
+            set_connectivity_config,
+            get_connectivity_config,
+            set_frequency_bands,
+            get_frequency_bands,
+            set_notch_filter,
+            get_notch_filter,
+            set_filters,
+            get_filters,
+            set_signal_quality_config,
+            get_signal_quality_config,
+            set_artifact_config,
+            get_artifact_config,
+            set_asr_config,
+            get_asr_config,
+            calibrate_asr,
+            set_ica_config,
+            get_ica_config,
+            set_ica_rejected_components,
+            get_ica_rejected_components,
+            set_display_decimation,
+            get_display_rate,
+            set_frame_rate,
+            get_frame_rate,
+            update_processor_config,
+            set_processing_stages,
+            get_processing_stages,
+            list_custom_stages,
+            list_plugins,
+            set_scripts,
+            get_scripts,
+            check_script,
+            set_history_config,
+            get_history_config,
+            get_history,
+            capture_snapshot,
+            set_spectrogram_config,
+            get_spectrogram_config,
+            get_spectrogram,
+            export_snapshot,
+            export_bids,
+            export_session_archive,
+            get_recording_status,
+            repair_recording,
+            verify_recording,
+            open_recording,
+            read_segment,
+            export_filtered,
+            export_anonymized,
+            list_recordings,
+            search_recordings,
+            delete_recording,
+            set_recording_triggers,
+            get_recording_triggers,
+            set_epoch_config,
+            get_epoch_config,
+            set_erp_config,
+            get_erp_config,
+            set_time_features_config,
+            get_time_features_config,
+            set_neurofeedback_config,
+            get_neurofeedback_config,
+            set_ssvep_config,
+            get_ssvep_config,
+            set_classifier_config,
+            get_classifier_config,
+            train_classifier,
+            classify_online,
+            get_classifier_status,
+            train_csp,
+            set_spatial_filter,
+            get_spatial_filter,
+            set_vitals_config,
+            get_vitals_config,
+            set_impedance_config,
+            get_impedance_config,
+            configure_osc_output,
+            get_osc_output,
+            configure_mqtt_output,
+            get_mqtt_output,
+            configure_upload,
+            get_upload_config,
+            get_settings,
+            update_settings,
+            set_montage,
+            get_montage,
+            get_available_montages,
+            set_active_channels,
+            get_active_channels,
+            set_chunk_config,
+            get_chunk_config,
+            set_rate_monitor_config,
+            get_rate_monitor_config,
+            set_acquisition_mode,
+            get_acquisition_mode,
+            set_channel_config,
+            get_channel_config,
+            set_lsl_inlet_config,
+            get_lsl_inlet_config,
+            get_connection_status,
+            initialize_system,
+            shutdown_system,
+            get_system_health,
+            get_pipeline_metrics,
+            restart_processor,
+            get_recent_logs,
+            set_log_level
+        ])
+        .setup(|app| {
+            logging::attach_app_handle(app.handle().clone());
+        
+            // 加载持久化设置作为各项默认配置
+            let settings_path = app.path().app_config_dir()?.join(settings::SETTINGS_FILE);
+            let settings = settings::load(&settings_path);
+            // 录制索引打不开时仍可正常录制，只是不登记
+            let catalog_path = app.path().app_data_dir()?.join(catalog::CATALOG_FILE);
+            let catalog = match catalog::RecordingCatalog::open(&catalog_path) {
+                Ok(catalog) => Some(Arc::new(catalog)),
+                Err(e) => {
+                    error!("❌ Failed to open recording catalog {}: {}", catalog_path.display(), e);
+                    None
+                }
+            };
+        
+            let pseudonyms_path = app.path().app_data_dir()?.join(anonymize::PSEUDONYMS_FILE);
+            let pseudonyms = match anonymize::PseudonymStore::open(&pseudonyms_path) {
+                Ok(store) => Some(Arc::new(std::sync::Mutex::new(store))),
+                Err(e) => {
+                    error!("❌ Failed to open pseudonym mapping {}: {}", pseudonyms_path.display(), e);
+                    None
+                }
+            };
+        
+            let state = AppState {
+                settings_path: Some(settings_path),
+                catalog,
+                pseudonyms,
+                ..AppState::default()
+            };
+            let mqtt = settings.mqtt.clone();
+            tauri::async_runtime::block_on(state.apply_settings(settings));
+            app.manage(state);
+        
+            if mqtt.enabled {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = restart_mqtt(handle.clone(), &handle.state::<AppState>(), &mqtt) {
+                        error!("❌ Failed to start MQTT output: {}", e);
+                    }
+                });
+            }
+        
+            #[cfg(feature = "dynamic-plugins")]
+            plugins::load_directory(&app.path().app_data_dir()?.join(plugins::PLUGINS_DIR));
+        
+            // 预热常用FFT长度，避免首次连接时规划卡顿
+            if let Ok(mut cache) = fft_processor::FftPlanCache::global().lock() {
+                cache.prewarm(&[256, 512, 1024]);
+            }
+        
+            info!("🎯 EEG Visualization Backend Started");
+            info!("📡 Ready to discover LSL streams");
+            info!("🖥️  Frontend interface available");
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            match event {
+                tauri::WindowEvent::CloseRequested { .. } => {
+                    info!("🔌 Window closing, shutting down gracefully");
+                    // 保存通过 set_* 命令修改的默认配置
+                    let state = window.app_handle().state::<AppState>();
+                    if let Err(e) = tauri::async_runtime::block_on(state.save_settings()) {
+                        error!("❌ Failed to save settings: {}", e);
+                    }
+                    // TODO: 在这里可以添加优雅关闭逻辑
+                }
+                _ => {}
+            }
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
\ No newline at end of file
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use serde_json::json;
+use tracing::info;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::recorder::{EdfRecorder, FinishedRecording};
+
+/// session.json 的格式版本，字段不兼容变更时递增
+const REPORT_VERSION: u32 = 1;
+
+/// 将已完成的录制打包为zip归档，便于传输到分析机器
+///
+/// 归档内容：recording/ 下的录制文件（各分段及旁路CSV）、annotations.tsv 注释日志、
+/// settings.json 设置快照，以及 session.json 会话报告（统计、流信息、会话信息和各文件的 SHA-256）。
+/// 不覆盖已存在的文件，写入失败时删除不完整的归档。
+pub fn export(
+    recording: &FinishedRecording,
+    settings: &Settings,
+    destination: &Path,
+    options: &ArchiveOptions,
+) -> Result<ArchiveResult, AppError> {
+    if destination.exists() {
+        return Err(AppError::Config(format!("Archive already exists: {}", destination.display())));
+    }
+    if let Some(dir) = destination.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    
+    let files = match write_archive(recording, settings, destination, options) {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = std::fs::remove_file(destination);
+            return Err(e);
+        }
+    };
+    let size_bytes = std::fs::metadata(destination)?.len();
+    
+    info!("📦 Archived {} ({} files, {} bytes) to {}", recording.stats.filename, files.len(), size_bytes, destination.display());
+    Ok(ArchiveResult {
+        path: destination.display().to_string(),
+        files,
+        size_bytes,
+    })
+}
+
+fn write_archive(
+    recording: &FinishedRecording,
+    settings: &Settings,
+    destination: &Path,
+    options: &ArchiveOptions,
+) -> Result<Vec<String>, AppError> {
+    let method = match options.compression {
+        ArchiveCompression::Deflate => CompressionMethod::Deflated,
+        ArchiveCompression::Zstd => CompressionMethod::Zstd,
+    };
+    let file_options = SimpleFileOptions::default().compression_method(method);
+    let mut zip = ZipWriter::new(File::create(destination)?);
+    let mut names = Vec::new();
+    let mut file_reports = Vec::new();
+    
+    // 录制文件逐个流式写入；分段的校验和录制结束时已计算
+    let stats = &recording.stats;
+    let sources = stats.segments.iter().enumerate()
+        .map(|(index, path)| (path, stats.checksums.get(index).cloned().flatten()))
+        .chain(recording.sidecars.iter().map(|path| (path, None)));
+    for (path, checksum) in sources {
+        let file_name = Path::new(path).file_name().and_then(|name| name.to_str())
+            .ok_or_else(|| AppError::Config(format!("Invalid recording path: {}", path)))?;
+        let name = format!("recording/{}", file_name);
+        let size_bytes = std::fs::metadata(path)?.len();
+        let sha256 = match checksum {
+            Some(checksum) => checksum,
+            None => crate::verify::sha256_file(Path::new(path))?,
+        };
+        
+        zip.start_file(name.as_str(), file_options.large_file(size_bytes > u32::MAX as u64)).map_err(zip_error)?;
+        std::io::copy(&mut File::open(path)?, &mut zip)?;
+        file_reports.push(json!({ "name": name, "size_bytes": size_bytes, "sha256": sha256 }));
+        names.push(name);
+    }
+    
+    let settings_json = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::Config(format!("Failed to serialize settings: {}", e)))?;
+    let report = json!({
+        "report_version": REPORT_VERSION,
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "application": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+        "recording": stats,
+        "stream": recording.stream_info,
+        "channel_labels": EdfRecorder::channel_labels(&recording.stream_info),
+        "metadata": recording.metadata,
+        "tags": recording.tags,
+        "annotations_count": recording.annotations.len(),
+        "files": file_reports,
+    });
+    let report_json = serde_json::to_string_pretty(&report)
+        .map_err(|e| AppError::Config(format!("Failed to serialize session report: {}", e)))?;
+    
+    let documents = [
+        ("annotations.tsv", annotations_tsv(&recording.annotations)),
+        ("settings.json", settings_json),
+        ("session.json", report_json),
+    ];
+    for (name, content) in documents {
+        zip.start_file(name, file_options).map_err(zip_error)?;
+        zip.write_all(content.as_bytes())?;
+        names.push(name.to_string());
+    }
+    
+    zip.finish().map_err(zip_error)?.sync_all()?;
+    Ok(names)
+}
+
+/// 注释日志：onset、duration（秒，无持续时间为 n/a）、description
+fn annotations_tsv(annotations: &[AnnotationRecord]) -> String {
+    let mut tsv = String::from("onset\tduration\tdescription\n");
+    for annotation in annotations {
+        let duration = annotation.duration_seconds.map_or("n/a".to_string(), |duration| format!("{:.6}", duration));
+        let description = annotation.description.replace(['\t', '\n', '\r'], " ");
+        tsv.push_str(&format!("{:.6}\t{}\t{}\n", annotation.onset_seconds, duration, description));
+    }
+    tsv
+}
+
+fn zip_error(e: zip::result::ZipError) -> AppError {
+    AppError::Recording(format!("Failed to write archive: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use crate::recorder::RecordingStats;
+    
+    #[test]
+    fn test_export_archive_contents() {
+        let dir = std::env::temp_dir().join(format!("cortexarray_archive_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let segment = dir.join("session.bdf");
+        let sidecar = dir.join("session_clock.csv");
+        std::fs::write(&segment, vec![7u8; 4096]).unwrap();
+        std::fs::write(&sidecar, "timestamp,offset,unix_time\n").unwrap();
+        
+        let recording = FinishedRecording {
+            stats: RecordingStats {
+                filename: segment.display().to_string(),
+                format: RecordingFormat::Bdf,
+                segments: vec![segment.display().to_string()],
+                duration_seconds: 10.0,
+                samples_written: 2500,
+                annotations_written: 2,
+                markers_written: 1,
+                paused_seconds: 0.0,
+                channels_count: 2,
+                sample_rate: 250.0,
+                start_time: "2024-03-05T10:00:00Z".parse().unwrap(),
+                file_size_bytes: 4096,
+                checksums: vec![None],
+                physical_ranges: Vec::new(),
+                clipped_samples: Vec::new(),
+            },
+            stream_info: StreamInfo {
+                name: "Cyton".to_string(),
+                stream_type: "EEG".to_string(),
+                channels_count: 2,
+                sample_rate: 250.0,
+                is_connected: true,
+                source_id: "cyton-01".to_string(),
+                channel_labels: vec!["C3".to_string(), "C4".to_string()],
+                effective_sample_rate: None,
+            },
+            metadata: SessionMetadata::default(),
+            tags: vec!["pilot".to_string()],
+            annotations: vec![
+                AnnotationRecord { onset_seconds: 0.0, duration_seconds: None, description: "Notes: eyes\tclosed".to_string() },
+                AnnotationRecord { onset_seconds: 2.5, duration_seconds: Some(1.0), description: "stimulus".to_string() },
+            ],
+            sidecars: vec![sidecar.display().to_string()],
+        };
+        
+        for (name, compression) in [("deflate.zip", ArchiveCompression::Deflate), ("zstd.zip", ArchiveCompression::Zstd)] {
+            let destination = dir.join(name);
+            let result = export(&recording, &Settings::default(), &destination, &ArchiveOptions { compression }).unwrap();
+            assert_eq!(result.files, vec!["recording/session.bdf", "recording/session_clock.csv", "annotations.tsv", "settings.json", "session.json"]);
+            assert!(result.size_bytes < 4096);
+            
+            let mut archive = zip::ZipArchive::new(File::open(&destination).unwrap()).unwrap();
+            let mut data = Vec::new();
+            archive.by_name("recording/session.bdf").unwrap().read_to_end(&mut data).unwrap();
+            assert_eq!(data, vec![7u8; 4096]);
+            
+            let mut annotations = String::new();
+            archive.by_name("annotations.tsv").unwrap().read_to_string(&mut annotations).unwrap();
+            assert_eq!(annotations, "onset\tduration\tdescription\n0.000000\tn/a\tNotes: eyes closed\n2.500000\t1.000000\tstimulus\n");
+            
+            let report: serde_json::Value = serde_json::from_reader(archive.by_name("session.json").unwrap()).unwrap();
+            assert_eq!(report["files"][0]["sha256"], json!(crate::verify::sha256_file(&segment).unwrap()));
+            assert_eq!(report["channel_labels"], json!(["C3", "C4"]));
+            
+            assert!(export(&recording, &Settings::default(), &destination, &ArchiveOptions::default()).is_err());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
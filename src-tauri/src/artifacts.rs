@@ -0,0 +1,179 @@
+use crate::data_types::*;
+
+// 基线（指数滑动平均）的时间常数，只在非伪迹段更新
+const BASELINE_TIME_CONSTANT: f64 = 1.0;
+// 低于阈值持续该时长后伪迹段结束
+const SEGMENT_HOLD_SECONDS: f64 = 0.05;
+// 超过该时长的段强制结束并重新开始，避免长时间漂移吞掉后续事件
+const MAX_SEGMENT_SECONDS: f64 = 5.0;
+
+/// 在线伪迹检测 - 额区通道的幅值阈值 + 斜率启发式
+pub struct ArtifactDetector {
+    config: ArtifactConfig,
+    sample_rate: f64,
+    baseline_alpha: f64,
+    channels: Vec<ChannelState>,
+}
+
+struct ChannelState {
+    index: usize,
+    label: String,
+    baseline: Option<f64>,
+    last_value: Option<f64>,
+    segment: Option<Segment>,
+}
+
+struct Segment {
+    start: f64,
+    last_above: f64,
+    peak: f64,
+    max_slope: f64,
+}
+
+impl ArtifactDetector {
+    pub fn new(config: ArtifactConfig, stream_info: &StreamInfo) -> Self {
+        let mut indices: Vec<usize> = config.channels.iter()
+            .filter_map(|wanted| stream_info.channel_labels.iter()
+                .position(|label| label.eq_ignore_ascii_case(wanted)))
+            .collect();
+        indices.dedup();
+        
+        // 没有标准额区标签时退回前两个通道
+        if indices.is_empty() {
+            indices = (0..stream_info.channels_count.min(2) as usize).collect();
+        }
+        
+        let channels = indices.into_iter()
+            .map(|index| ChannelState {
+                index,
+                label: stream_info.channel_labels.get(index).cloned().unwrap_or_default(),
+                baseline: None,
+                last_value: None,
+                segment: None,
+            })
+            .collect();
+        
+        let sample_rate = stream_info.sample_rate.max(1.0);
+        
+        Self {
+            config,
+            sample_rate,
+            baseline_alpha: 1.0 / (BASELINE_TIME_CONSTANT * sample_rate),
+            channels,
+        }
+    }
+    
+    /// 检测的通道标签
+    pub fn channel_labels(&self) -> Vec<&str> {
+        self.channels.iter().map(|channel| channel.label.as_str()).collect()
+    }
+    
+    /// 加入一个样本，返回本样本结束的伪迹段
+    pub fn push(&mut self, sample: &EegSample) -> Vec<ArtifactEvent> {
+        let mut events = Vec::new();
+        
+        for channel in &mut self.channels {
+            let Some(&value) = sample.channels.get(channel.index) else { continue };
+            
+            let baseline = *channel.baseline.get_or_insert(value);
+            let slope = channel.last_value.map_or(0.0, |last| (value - last).abs() * self.sample_rate);
+            channel.last_value = Some(value);
+            
+            let deviation = (value - baseline).abs();
+            let above = deviation > self.config.amplitude_threshold || slope > self.config.slope_threshold;
+            
+            match channel.segment.as_mut() {
+                Some(segment) => {
+                    if above {
+                        segment.last_above = sample.timestamp;
+                        segment.peak = segment.peak.max(deviation);
+                        segment.max_slope = segment.max_slope.max(slope);
+                    }
+                    
+                    let quiet = sample.timestamp - segment.last_above >= SEGMENT_HOLD_SECONDS;
+                    let too_long = sample.timestamp - segment.start >= MAX_SEGMENT_SECONDS;
+                    if quiet || too_long {
+                        let segment = channel.segment.take().expect("segment exists");
+                        events.push(Self::finish(channel, segment, &self.config));
+                        if too_long {
+                            // 持续偏移视为新的基线
+                            channel.baseline = Some(value);
+                        }
+                    }
+                }
+                None if above => {
+                    channel.segment = Some(Segment {
+                        start: sample.timestamp,
+                        last_above: sample.timestamp,
+                        peak: deviation,
+                        max_slope: slope,
+                    });
+                }
+                None => {
+                    channel.baseline = Some(baseline + self.baseline_alpha * (value - baseline));
+                }
+            }
+        }
+        
+        events
+    }
+    
+    fn finish(channel: &ChannelState, segment: Segment, config: &ArtifactConfig) -> ArtifactEvent {
+        let kind = if segment.max_slope > config.slope_threshold {
+            ArtifactKind::Motion
+        } else {
+            ArtifactKind::Blink
+        };
+        
+        ArtifactEvent {
+            kind,
+            channel_index: channel.index as u32,
+            channel_label: channel.label.clone(),
+            start_timestamp: segment.start,
+            end_timestamp: segment.last_above,
+            peak_amplitude: segment.peak,
+            recording_onset: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blink_and_motion_classification() {
+        let stream_info = StreamInfo {
+            name: "Test".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 3,
+            sample_rate: 250.0,
+            is_connected: true,
+            source_id: "test".to_string(),
+            channel_labels: vec!["Cz".to_string(), "Fp1".to_string(), "Fp2".to_string()],
+            effective_sample_rate: None,
+        };
+        let config = ArtifactConfig { enabled: true, ..ArtifactConfig::default() };
+        let mut detector = ArtifactDetector::new(config, &stream_info);
+        assert_eq!(detector.channel_labels(), vec!["Fp1", "Fp2"]);
+        
+        let mut events = Vec::new();
+        for i in 0..750u64 {
+            let t = i as f64 / 250.0;
+            // Fp1: 1.0s处 300ms 的平缓眨眼（幅值200µV，斜率远低于阈值）
+            let blink = if (1.0..1.3).contains(&t) { 200.0 * (std::f64::consts::PI * (t - 1.0) / 0.3).sin() } else { 0.0 };
+            // Fp2: 2.0s处的阶跃（运动）
+            let motion = if (2.0..2.1).contains(&t) { 500.0 } else { 0.0 };
+            let sample = EegSample { timestamp: t, channels: vec![0.0, 10.0 + blink, -20.0 + motion], sample_id: i };
+            events.extend(detector.push(&sample));
+        }
+        
+        assert_eq!(events.len(), 2, "{:?}", events);
+        let blink = events.iter().find(|event| event.channel_label == "Fp1").unwrap();
+        assert_eq!(blink.kind, ArtifactKind::Blink);
+        assert!(blink.start_timestamp > 1.0 && blink.end_timestamp < 1.3);
+        let motion = events.iter().find(|event| event.channel_label == "Fp2").unwrap();
+        assert_eq!(motion.kind, ArtifactKind::Motion);
+        assert!(motion.peak_amplitude >= 499.0);
+    }
+}
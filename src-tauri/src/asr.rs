@@ -0,0 +1,365 @@
+use std::collections::VecDeque;
+use nalgebra::{DMatrix, DVector};
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::signal_quality::median;
+
+// 最多重建的成分比例（其余最小的成分始终保留）
+const MAX_DROPPED_FRACTION: f64 = 0.66;
+// 校准数据至少包含的窗口数
+const MIN_CALIBRATION_WINDOWS: usize = 8;
+
+/// ASR 清洗阶段 - 校准阶段用干净数据估计各主成分的RMS分布，
+/// 之后在滑动窗口上做PCA，方差超过阈值的成分由干净子空间重建。
+///
+/// 重建矩阵每半个窗口更新一次，相邻矩阵之间线性过渡避免跳变。
+/// 重建作用于去均值后的信号，直流偏置原样保留；ASR 假设数据已高通，
+/// 建议同时启用显示路径的高通滤波。
+pub struct AsrStage {
+    config: AsrConfig,
+    channels: usize,
+    window_len: usize,
+    hop: usize,
+    calibration: Option<Calibration>,
+    collecting: Option<Vec<Vec<f64>>>,
+    calibration_target: usize,
+    window: VecDeque<Vec<f64>>,
+    since_update: usize,
+    previous: Option<DMatrix<f64>>,     // None 表示单位矩阵（不重建）
+    current: Option<DMatrix<f64>>,
+}
+
+struct Calibration {
+    mixing: DMatrix<f64>,           // 校准协方差的矩阵平方根
+    eigenvectors: DMatrix<f64>,     // 校准协方差的特征向量（按特征值升序）
+    rms_mean: Vec<f64>,
+    rms_std: Vec<f64>,
+    samples: usize,
+}
+
+impl AsrStage {
+    pub fn new(config: AsrConfig, sample_rate: f64, channels: usize) -> Self {
+        let window_len = ((config.window_seconds * sample_rate).round() as usize).max(2);
+        
+        Self {
+            config,
+            channels,
+            window_len,
+            hop: (window_len / 2).max(1),
+            calibration: None,
+            collecting: None,
+            calibration_target: (config.calibration_seconds * sample_rate).round() as usize,
+            window: VecDeque::with_capacity(window_len),
+            since_update: 0,
+            previous: None,
+            current: None,
+        }
+    }
+    
+    /// 通道数（通道选择变化时需要重建并重新校准）
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+    
+    pub fn is_calibrated(&self) -> bool {
+        self.calibration.is_some()
+    }
+    
+    /// 开始采集校准数据，期间信号原样通过
+    pub fn start_calibration(&mut self) {
+        self.collecting = Some(Vec::with_capacity(self.calibration_target));
+    }
+    
+    /// 阈值参数变化时保留校准；窗长变化会改变RMS分布，需要重新校准
+    pub fn reconfigure(self, config: AsrConfig, sample_rate: f64) -> Self {
+        let mut stage = Self::new(config, sample_rate, self.channels);
+        if config.window_seconds == self.config.window_seconds {
+            stage.calibration = self.calibration;
+        }
+        stage.collecting = self.collecting;
+        stage
+    }
+    
+    /// 处理一个样本（原地清洗），校准完成时返回校准结果
+    pub fn process_sample(&mut self, channels: &mut [f64]) -> Option<Result<AsrCalibration, AppError>> {
+        if channels.len() != self.channels {
+            return None;
+        }
+        
+        if let Some(buffer) = self.collecting.as_mut() {
+            buffer.push(channels.to_vec());
+            if buffer.len() < self.calibration_target {
+                return None;
+            }
+            
+            let data = self.collecting.take().unwrap_or_default();
+            let result = self.calibrate(&data);
+            return Some(result);
+        }
+        
+        let calibration = self.calibration.as_ref()?;
+        
+        if self.window.len() == self.window_len {
+            self.window.pop_front();
+        }
+        self.window.push_back(channels.to_vec());
+        self.since_update += 1;
+        
+        if self.window.len() == self.window_len && self.since_update >= self.hop {
+            let reconstruction = Self::reconstruction(calibration, self.config.cutoff, &self.window, self.channels);
+            self.previous = self.current.take();
+            self.current = reconstruction;
+            self.since_update = 0;
+        }
+        
+        if self.previous.is_none() && self.current.is_none() {
+            return None;
+        }
+        
+        // 在窗口均值附近重建，保留直流偏置
+        let mean = column_mean(&self.window, self.channels);
+        let centered = DVector::from_iterator(self.channels, channels.iter().zip(mean.iter()).map(|(x, m)| x - m));
+        
+        let reconstruct = |matrix: &Option<DMatrix<f64>>| match matrix {
+            Some(matrix) => matrix * &centered,
+            None => centered.clone(),
+        };
+        let weight = (self.since_update as f64 / self.hop as f64).min(1.0);
+        let cleaned = reconstruct(&self.previous) * (1.0 - weight) + reconstruct(&self.current) * weight;
+        
+        for (i, value) in channels.iter_mut().enumerate() {
+            *value = cleaned[i] + mean[i];
+        }
+        
+        None
+    }
+    
+    fn calibrate(&mut self, data: &[Vec<f64>]) -> Result<AsrCalibration, AppError> {
+        let channels = self.channels;
+        if channels < 2 {
+            return Err(AppError::Config("ASR requires at least 2 channels".to_string()));
+        }
+        if data.len() < self.window_len * MIN_CALIBRATION_WINDOWS {
+            return Err(AppError::Config(format!(
+                "ASR calibration needs at least {} samples, got {}",
+                self.window_len * MIN_CALIBRATION_WINDOWS, data.len()
+            )));
+        }
+        
+        let (values, eigenvectors) = sorted_eigen(covariance(data.iter(), channels));
+        let sqrt_values = DMatrix::from_diagonal(&values.map(|v| v.max(0.0).sqrt()));
+        let mixing = &eigenvectors * sqrt_values * eigenvectors.transpose();
+        
+        // 主成分空间中每个窗口的RMS分布
+        let mean = column_mean(data, channels);
+        let projected: Vec<DVector<f64>> = data.iter()
+            .map(|sample| {
+                let centered = DVector::from_iterator(channels, sample.iter().zip(mean.iter()).map(|(x, m)| x - m));
+                eigenvectors.transpose() * centered
+            })
+            .collect();
+        
+        let mut window_rms = vec![Vec::new(); channels];
+        let mut start = 0;
+        while start + self.window_len <= projected.len() {
+            for (component, rms) in window_rms.iter_mut().enumerate() {
+                let energy: f64 = projected[start..start + self.window_len].iter()
+                    .map(|y| y[component] * y[component])
+                    .sum();
+                rms.push((energy / self.window_len as f64).sqrt());
+            }
+            start += self.hop;
+        }
+        
+        // 中位数/MAD 稳健估计，校准数据中的零星伪迹不会拉高阈值
+        let mut rms_mean = Vec::with_capacity(channels);
+        let mut rms_std = Vec::with_capacity(channels);
+        for rms in &window_rms {
+            let center = median(rms);
+            let deviations: Vec<f64> = rms.iter().map(|value| (value - center).abs()).collect();
+            let spread = (median(&deviations) * 1.4826).max(center * 1e-3);
+            rms_mean.push(center);
+            rms_std.push(spread);
+        }
+        
+        let calibration = Calibration {
+            mixing,
+            eigenvectors,
+            rms_mean,
+            rms_std,
+            samples: data.len(),
+        };
+        let result = AsrCalibration {
+            channels: channels as u32,
+            samples: calibration.samples,
+            component_thresholds: calibration.thresholds(self.config.cutoff),
+        };
+        
+        self.calibration = Some(calibration);
+        self.window.clear();
+        self.since_update = 0;
+        self.previous = None;
+        self.current = None;
+        
+        Ok(result)
+    }
+    
+    /// 计算当前窗口的重建矩阵，没有成分超限时返回 None
+    fn reconstruction(
+        calibration: &Calibration,
+        cutoff: f64,
+        window: &VecDeque<Vec<f64>>,
+        channels: usize,
+    ) -> Option<DMatrix<f64>> {
+        let (values, vectors) = sorted_eigen(covariance(window.iter(), channels));
+        
+        // 阈值矩阵投影到当前窗口的主成分上
+        let thresholds = DMatrix::from_diagonal(&DVector::from_vec(calibration.thresholds(cutoff)));
+        let threshold_matrix = thresholds * calibration.eigenvectors.transpose();
+        let projected = &threshold_matrix * &vectors;
+        
+        let always_kept = channels - (MAX_DROPPED_FRACTION * channels as f64).round() as usize;
+        let keep: Vec<bool> = (0..channels)
+            .map(|k| k < always_kept || values[k] < projected.column(k).norm_squared())
+            .collect();
+        
+        if keep.iter().all(|&k| k) {
+            return None;
+        }
+        
+        let mut kept = vectors.transpose() * &calibration.mixing;
+        for (k, &keep) in keep.iter().enumerate() {
+            if !keep {
+                kept.row_mut(k).fill(0.0);
+            }
+        }
+        
+        let inverse = kept.pseudo_inverse(1e-10).ok()?;
+        Some(&calibration.mixing * inverse * vectors.transpose())
+    }
+}
+
+impl Calibration {
+    fn thresholds(&self, cutoff: f64) -> Vec<f64> {
+        self.rms_mean.iter().zip(&self.rms_std)
+            .map(|(mean, std)| mean + cutoff * std)
+            .collect()
+    }
+}
+
+fn column_mean<'a>(samples: impl IntoIterator<Item = &'a Vec<f64>>, channels: usize) -> Vec<f64> {
+    let mut sum = vec![0.0; channels];
+    let mut count = 0usize;
+    for sample in samples {
+        for (acc, value) in sum.iter_mut().zip(sample) {
+            *acc += value;
+        }
+        count += 1;
+    }
+    sum.iter().map(|total| total / count.max(1) as f64).collect()
+}
+
+/// 去均值协方差矩阵
+fn covariance<'a, I>(samples: I, channels: usize) -> DMatrix<f64>
+where
+    I: Iterator<Item = &'a Vec<f64>> + Clone,
+{
+    let mean = column_mean(samples.clone(), channels);
+    let mut covariance = DMatrix::zeros(channels, channels);
+    let mut count = 0usize;
+    
+    for sample in samples {
+        let centered = DVector::from_iterator(channels, sample.iter().zip(mean.iter()).map(|(x, m)| x - m));
+        covariance += &centered * centered.transpose();
+        count += 1;
+    }
+    
+    covariance / count.max(1) as f64
+}
+
+/// 对称矩阵特征分解，按特征值升序排列
+pub(crate) fn sorted_eigen(matrix: DMatrix<f64>) -> (DVector<f64>, DMatrix<f64>) {
+    let eigen = matrix.symmetric_eigen();
+    let mut order: Vec<usize> = (0..eigen.eigenvalues.len()).collect();
+    order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+    
+    let values = DVector::from_iterator(order.len(), order.iter().map(|&i| eigen.eigenvalues[i]));
+    let columns: Vec<_> = order.iter().map(|&i| eigen.eigenvectors.column(i).into_owned()).collect();
+    
+    (values, DMatrix::from_columns(&columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// 4通道：独立的正弦 + 伪随机噪声
+    fn clean_sample(n: usize, sample_rate: f64, seed: &mut u64) -> Vec<f64> {
+        let t = n as f64 / sample_rate;
+        (0..4)
+            .map(|ch| {
+                *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let noise = ((*seed >> 33) as f64 / (1u64 << 31) as f64 - 0.5) * 10.0;
+                10.0 * (2.0 * std::f64::consts::PI * (7.0 + ch as f64 * 3.0) * t).sin() + noise
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_calibration_and_burst_reconstruction() {
+        let sample_rate = 250.0;
+        let config = AsrConfig {
+            enabled: true,
+            calibration_seconds: 20.0,
+            ..AsrConfig::default()
+        };
+        let mut stage = AsrStage::new(config, sample_rate, 4);
+        let mut seed = 42u64;
+        let mut n = 0;
+        
+        stage.start_calibration();
+        let mut calibration = None;
+        while calibration.is_none() {
+            let mut sample = clean_sample(n, sample_rate, &mut seed);
+            calibration = stage.process_sample(&mut sample);
+            n += 1;
+        }
+        let calibration = calibration.unwrap().unwrap();
+        assert_eq!(calibration.samples, 5000);
+        assert!(stage.is_calibrated());
+        
+        // 干净数据基本不变
+        let mut max_change: f64 = 0.0;
+        for _ in 0..500 {
+            let original = clean_sample(n, sample_rate, &mut seed);
+            let mut sample = original.clone();
+            stage.process_sample(&mut sample);
+            for (a, b) in original.iter().zip(&sample) {
+                max_change = max_change.max((a - b).abs());
+            }
+            n += 1;
+        }
+        assert!(max_change < 1e-6, "clean data changed by {}", max_change);
+        
+        // 共同源的大幅伪迹被抑制
+        let mut burst_energy = 0.0;
+        let mut cleaned_energy = 0.0;
+        for i in 0..500 {
+            let artifact = 500.0 * (2.0 * std::f64::consts::PI * 2.0 * i as f64 / sample_rate).sin();
+            let mut sample = clean_sample(n, sample_rate, &mut seed);
+            for (ch, value) in sample.iter_mut().enumerate() {
+                *value += artifact * (1.0 + ch as f64 * 0.3);
+            }
+            let energy: f64 = sample.iter().map(|v| v * v).sum();
+            stage.process_sample(&mut sample);
+            // 前半段用于窗口适应，只比较后半段
+            if i >= 250 {
+                burst_energy += energy;
+                cleaned_energy += sample.iter().map(|v| v * v).sum::<f64>();
+            }
+            n += 1;
+        }
+        assert!(cleaned_energy < burst_energy * 0.05,
+                "burst not attenuated: {} vs {}", cleaned_energy, burst_energy);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::data_types::*;
+
+/// 从单边幅值谱计算单通道的绝对/相对频带功率
+///
+/// magnitudes[k] 对应频率 k * freq_resolution，功率取幅值平方。
+pub fn compute_band_powers(
+    channel_index: u32,
+    magnitudes: &[f64],
+    freq_resolution: f64,
+    bands: &FrequencyBands,
+) -> ChannelBandPower {
+    let absolute: Vec<f64> = bands.0
+        .iter()
+        .map(|band| power_in_range(magnitudes, freq_resolution, band.low_hz, band.high_hz))
+        .collect();
+    
+    let (span_low, span_high) = bands.span();
+    let total_power = power_in_range(magnitudes, freq_resolution, span_low, span_high);
+    
+    let relative = absolute
+        .iter()
+        .map(|&power| if total_power > 0.0 { power / total_power } else { 0.0 })
+        .collect();
+    
+    ChannelBandPower {
+        channel_index,
+        absolute,
+        relative,
+    }
+}
+
+/// 在频带功率更新中查找某通道某频带的值（频带名称不区分大小写）
+pub fn band_value(update: &BandPowerUpdate, channel: u32, band: &str, relative: bool) -> Option<f64> {
+    let band_index = update.bands.iter().position(|name| name.eq_ignore_ascii_case(band))?;
+    let power = update.channels.iter().find(|power| power.channel_index == channel)?;
+    let values = if relative { &power.relative } else { &power.absolute };
+    values.get(band_index).copied()
+}
+
+/// 对 [low_hz, high_hz) 内的FFT bin 求功率和
+pub(crate) fn power_in_range(magnitudes: &[f64], freq_resolution: f64, low_hz: f64, high_hz: f64) -> f64 {
+    if freq_resolution <= 0.0 {
+        return 0.0;
+    }
+    
+    let first_bin = (low_hz / freq_resolution).ceil() as usize;
+    
+    magnitudes
+        .iter()
+        .enumerate()
+        .skip(first_bin)
+        .take_while(|(k, _)| (*k as f64) * freq_resolution < high_hz)
+        .map(|(_, &magnitude)| magnitude * magnitude)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_alpha_peak_dominates_relative_power() {
+        // 1Hz分辨率，10Hz处单一峰值
+        let mut magnitudes = vec![0.1; 65];
+        magnitudes[10] = 5.0;
+        
+        let bands = FrequencyBands::default();
+        let result = compute_band_powers(0, &magnitudes, 1.0, &bands);
+        
+        let alpha_index = bands.names().iter().position(|name| name == "alpha").unwrap();
+        let max_index = result.relative
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(i, _)| i)
+            .unwrap();
+        
+        assert_eq!(max_index, alpha_index);
+        
+        let relative_sum: f64 = result.relative.iter().sum();
+        assert!((relative_sum - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use tracing::warn;
+
+// BDF 24位数字范围
+pub const BDF_DIGITAL_MIN: i32 = -8_388_608;
+pub const BDF_DIGITAL_MAX: i32 = 8_388_607;
+
+// BDF+注释信号每个数据记录至少占用的样本数（每样本3字节）
+const MIN_ANNOTATION_SAMPLES_PER_RECORD: usize = 64;
+
+// 按此标记速率和单条TAL长度为注释信号预留空间，突发超出时顺延到后续记录
+const EXPECTED_ANNOTATIONS_PER_SECOND: f64 = 10.0;
+const TYPICAL_TAL_BYTES: usize = 48;
+
+// 文件头中"数据记录数"字段的偏移
+const NUM_RECORDS_OFFSET: u64 = 236;
+
+// 为每个记录开头的时间标记TAL预留的字节数
+const TIMEKEEPING_TAL_RESERVE: usize = 16;
+
+/// BDF信号参数
+#[derive(Clone, Debug)]
+pub struct BdfSignal {
+    pub label: String,
+    pub transducer: String,
+    pub physical_dimension: String,
+    pub physical_min: f64,
+    pub physical_max: f64,
+    pub prefilter: String,
+    pub samples_per_record: usize,
+}
+
+/// BDF+ (24位) 写入器 - 连续记录，注释写入 "BDF Annotations" 信号
+pub struct BdfWriter {
+    file: BufWriter<File>,
+    signals: Vec<BdfSignal>,
+    record_duration: f64,
+    records_written: u64,
+    annotation_bytes: usize,            // 每个数据记录中注释信号的字节数
+    pending_annotations: VecDeque<Vec<u8>>,
+}
+
+impl BdfWriter {
+    pub fn create(
+        filename: &str,
+        signals: Vec<BdfSignal>,
+        record_duration: f64,
+        start_time: DateTime<Utc>,
+        patient_id: &str,
+        recording_id: &str,
+    ) -> Result<Self, AppError> {
+        if signals.is_empty() {
+            return Err(AppError::Recording("BDF file needs at least one signal".to_string()));
+        }
+        
+        // 物理范围按头字段能表示的精度取整，样本换算与读取方使用同一范围；
+        // 为空（平坦/未配置的通道）时换算比例无穷大，与EDF写入一致展开为 ±1
+        let signals = signals.into_iter().map(|mut signal| {
+            signal.physical_min = header_value(signal.physical_min)?;
+            signal.physical_max = header_value(signal.physical_max)?;
+            let flat = signal.physical_max.partial_cmp(&signal.physical_min) != Some(std::cmp::Ordering::Greater);
+            if flat {
+                let center = if signal.physical_min.is_finite() { signal.physical_min } else { 0.0 };
+                warn!("⚠️  BDF signal {} has an empty physical range, widening to {}±1", signal.label, center);
+                signal.physical_min = center - 1.0;
+                signal.physical_max = center + 1.0;
+            }
+            Ok(signal)
+        }).collect::<Result<Vec<BdfSignal>, AppError>>()?;
+        
+        let file = File::create(filename)
+            .map_err(|e| AppError::Recording(format!("Failed to create BDF file: {}", e)))?;
+        
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            signals,
+            record_duration,
+            records_written: 0,
+            annotation_bytes: annotation_samples_per_record(record_duration) * 3,
+            pending_annotations: VecDeque::new(),
+        };
+        writer.write_header(start_time, patient_id, recording_id)?;
+        
+        Ok(writer)
+    }
+    
+    fn write_header(&mut self, start_time: DateTime<Utc>, patient_id: &str, recording_id: &str) -> Result<(), AppError> {
+        let signal_count = self.signals.len() + 1; // 含注释信号
+        let header_bytes = 256 * (signal_count + 1);
+        
+        let mut header = Vec::with_capacity(header_bytes);
+        header.push(0xFF);
+        push_field(&mut header, "BIOSEMI", 7);
+        push_field(&mut header, patient_id, 80);
+        push_field(&mut header, recording_id, 80);
+        push_field(&mut header, &start_time.format("%d.%m.%y").to_string(), 8);
+        push_field(&mut header, &start_time.format("%H.%M.%S").to_string(), 8);
+        push_field(&mut header, &header_bytes.to_string(), 8);
+        push_field(&mut header, "BDF+C", 44);
+        push_field(&mut header, "-1", 8); // 完成时回填
+        push_field(&mut header, &format_number(self.record_duration)?, 8);
+        push_field(&mut header, &signal_count.to_string(), 4);
+        
+        let annotation = BdfSignal {
+            label: "BDF Annotations".to_string(),
+            transducer: String::new(),
+            physical_dimension: String::new(),
+            physical_min: -1.0,
+            physical_max: 1.0,
+            prefilter: String::new(),
+            samples_per_record: self.annotation_bytes / 3,
+        };
+        let signals: Vec<&BdfSignal> = self.signals.iter().chain(std::iter::once(&annotation)).collect();
+        
+        for signal in &signals { push_field(&mut header, &signal.label, 16); }
+        for signal in &signals { push_field(&mut header, &signal.transducer, 80); }
+        for signal in &signals { push_field(&mut header, &signal.physical_dimension, 8); }
+        for signal in &signals { push_field(&mut header, &format_number(signal.physical_min)?, 8); }
+        for signal in &signals { push_field(&mut header, &format_number(signal.physical_max)?, 8); }
+        for _ in &signals { push_field(&mut header, &BDF_DIGITAL_MIN.to_string(), 8); }
+        for _ in &signals { push_field(&mut header, &BDF_DIGITAL_MAX.to_string(), 8); }
+        for signal in &signals { push_field(&mut header, &signal.prefilter, 80); }
+        for signal in &signals { push_field(&mut header, &signal.samples_per_record.to_string(), 8); }
+        for _ in &signals { push_field(&mut header, "", 32); }
+        
+        debug_assert_eq!(header.len(), header_bytes);
+        
+        self.file.write_all(&header)
+            .map_err(|e| AppError::Recording(format!("Failed to write BDF header: {}", e)))
+    }
+    
+    /// 排队一条注释，随下一个数据记录写入
+    pub fn add_annotation(&mut self, onset: f64, duration: Option<f64>, description: &str) -> Result<(), AppError> {
+        // TAL分隔符不能出现在描述中
+        let description: String = description
+            .chars()
+            .map(|c| if matches!(c, '\u{14}' | '\u{15}' | '\0') { ' ' } else { c })
+            .collect();
+        
+        let mut tal = format!("+{}", format_seconds(onset.max(0.0)));
+        if let Some(duration) = duration {
+            tal.push('\u{15}');
+            tal.push_str(&format_seconds(duration));
+        }
+        tal.push('\u{14}');
+        
+        // TAL不能跨数据记录，超出一个记录的描述按字符边界截断
+        let available = self.annotation_bytes - TIMEKEEPING_TAL_RESERVE;
+        let budget = available.saturating_sub(tal.len() + 2);
+        if budget == 0 {
+            return Err(AppError::Recording(format!(
+                "Annotation onset/duration too long for BDF+ record ({} bytes)", tal.len()
+            )));
+        }
+        let mut end = description.len().min(budget);
+        while !description.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end < description.len() {
+            warn!("⚠️  BDF+ annotation truncated to {} of {} bytes", end, description.len());
+        }
+        tal.push_str(&description[..end]);
+        tal.push_str("\u{14}\0");
+        
+        self.pending_annotations.push_back(tal.into_bytes());
+        Ok(())
+    }
+    
+    /// 写入一个数据记录，record[i] 为第i个信号的物理值
+    pub fn write_samples(&mut self, record: &[Vec<f64>]) -> Result<(), AppError> {
+        if record.len() != self.signals.len() {
+            return Err(AppError::Recording(format!(
+                "Record has {} signals, BDF header declares {}", record.len(), self.signals.len()
+            )));
+        }
+        
+        let mut bytes = Vec::with_capacity(
+            self.signals.iter().map(|s| s.samples_per_record * 3).sum::<usize>() + self.annotation_bytes
+        );
+        
+        for (signal, samples) in self.signals.iter().zip(record) {
+            for i in 0..signal.samples_per_record {
+                let value = samples.get(i).copied().unwrap_or(0.0);
+                let digital = physical_to_digital(value, signal.physical_min, signal.physical_max);
+                bytes.extend_from_slice(&digital.to_le_bytes()[..3]);
+            }
+        }
+        
+        bytes.extend_from_slice(&self.next_annotation_block());
+        
+        self.file.write_all(&bytes)
+            .map_err(|e| AppError::Recording(format!("Failed to write BDF data record: {}", e)))?;
+        self.records_written += 1;
+        
+        Ok(())
+    }
+    
+    /// 注释信号：时间标记TAL + 尽可能多的待写注释，剩余补零
+    fn next_annotation_block(&mut self) -> Vec<u8> {
+        let record_onset = self.records_written as f64 * self.record_duration;
+        let mut block = format!("+{}\u{14}\u{14}\0", format_seconds(record_onset)).into_bytes();
+        
+        while let Some(tal) = self.pending_annotations.front() {
+            if block.len() + tal.len() > self.annotation_bytes {
+                break;
+            }
+            block.extend_from_slice(tal);
+            self.pending_annotations.pop_front();
+        }
+        
+        block.resize(self.annotation_bytes, 0);
+        block
+    }
+    
+    /// 检查点：回填当前数据记录数并同步到磁盘，异常退出时文件头与已写入的记录一致
+    pub fn checkpoint(&mut self) -> Result<(), AppError> {
+        self.write_record_count()
+            .and_then(|_| self.file.seek(SeekFrom::End(0)))
+            .and_then(|_| self.file.get_ref().sync_data())
+            .map_err(|e| AppError::Recording(format!("Failed to checkpoint BDF file: {}", e)))
+    }
+    
+    /// 回填数据记录数并关闭文件
+    ///
+    /// 标记突发导致仍有注释未写入时，追加信号为0的数据记录承载它们，不丢弃标记。
+    pub fn finalize(mut self) -> Result<(), AppError> {
+        if !self.pending_annotations.is_empty() {
+            warn!("⚠️  {} BDF+ annotations left after the last data record, appending padding records",
+                     self.pending_annotations.len());
+            let padding: Vec<Vec<f64>> = self.signals.iter().map(|_| Vec::new()).collect();
+            while !self.pending_annotations.is_empty() {
+                self.write_samples(&padding)?;
+            }
+        }
+        
+        self.write_record_count()
+            .map_err(|e| AppError::Recording(format!("Failed to finalize BDF file: {}", e)))
+    }
+    
+    /// 写出缓冲数据并将已写入的记录数填入文件头
+    fn write_record_count(&mut self) -> std::io::Result<()> {
+        let mut field = Vec::with_capacity(8);
+        push_field(&mut field, &self.records_written.to_string(), 8);
+        
+        self.file.seek(SeekFrom::Start(NUM_RECORDS_OFFSET))?;
+        self.file.write_all(&field)?;
+        self.file.flush()
+    }
+}
+
+/// 注释信号样本数：按记录时长与预期标记速率预留，至少 MIN_ANNOTATION_SAMPLES_PER_RECORD
+fn annotation_samples_per_record(record_duration: f64) -> usize {
+    let annotations = (record_duration * EXPECTED_ANNOTATIONS_PER_SECOND).ceil().max(1.0) as usize;
+    let bytes = TIMEKEEPING_TAL_RESERVE + annotations * TYPICAL_TAL_BYTES;
+    bytes.div_ceil(3).max(MIN_ANNOTATION_SAMPLES_PER_RECORD)
+}
+
+fn physical_to_digital(value: f64, physical_min: f64, physical_max: f64) -> i32 {
+    let scale = (BDF_DIGITAL_MAX - BDF_DIGITAL_MIN) as f64 / (physical_max - physical_min);
+    let digital = (value - physical_min) * scale + BDF_DIGITAL_MIN as f64;
+    digital.round().clamp(BDF_DIGITAL_MIN as f64, BDF_DIGITAL_MAX as f64) as i32
+}
+
+/// ASCII字段：左对齐、空格补齐、超长截断
+pub(crate) fn push_field(buffer: &mut Vec<u8>, value: &str, width: usize) {
+    let mut bytes: Vec<u8> = value.bytes().filter(|b| b.is_ascii()).take(width).collect();
+    bytes.resize(width, b' ');
+    buffer.extend_from_slice(&bytes);
+}
+
+/// 头字段中的数值，最多8个字符：超长时降低小数精度，整数部分放不下时报错
+fn format_number(value: f64) -> Result<String, AppError> {
+    (0..=6).rev()
+        .map(|decimals| format_decimal(value, decimals))
+        .find(|text| text.len() <= 8)
+        .ok_or_else(|| AppError::Recording(format!("Value {} does not fit in an 8-character BDF header field", value)))
+}
+
+/// 数值写入头字段后读回的值
+fn header_value(value: f64) -> Result<f64, AppError> {
+    format_number(value)?.parse()
+        .map_err(|e| AppError::Recording(format!("Invalid BDF header value {}: {}", value, e)))
+}
+
+/// 去掉多余零的十进制表示（TAL不允许指数形式）
+fn format_seconds(value: f64) -> String {
+    format_decimal(value, 6)
+}
+
+fn format_decimal(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text.is_empty() || text == "-" { "0".to_string() } else { text.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_bdf_header_and_24bit_samples() {
+        let path = std::env::temp_dir().join(format!("bdf_writer_test_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        
+        let signal = BdfSignal {
+            label: "Cz".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: BDF_DIGITAL_MIN as f64,
+            physical_max: BDF_DIGITAL_MAX as f64,
+            prefilter: String::new(),
+            samples_per_record: 2,
+        };
+        
+        let mut writer = BdfWriter::create(&filename, vec![signal], 1.0, Utc::now(), "P01 F 02-MAY-1951 X", "Startdate X X X X").unwrap();
+        writer.add_annotation(0.5, None, "stimulus").unwrap();
+        writer.write_samples(&[vec![100.0, -100.0]]).unwrap();
+        writer.finalize().unwrap();
+        
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        assert_eq!(&bytes[..8], b"\xFFBIOSEMI");
+        assert!(bytes[8..88].starts_with(b"P01 F 02-MAY-1951 X "));
+        assert_eq!(&bytes[192..197], b"BDF+C");
+        assert_eq!(std::str::from_utf8(&bytes[236..244]).unwrap().trim(), "1");
+        
+        // 物理范围等于数字范围时 1µV = 1个数字单位，-100 需按24位补码还原
+        let header_bytes = 256 * 3;
+        let first = &bytes[header_bytes..header_bytes + 3];
+        assert_eq!(i32::from_le_bytes([first[0], first[1], first[2], 0]), 100);
+        let second = &bytes[header_bytes + 3..header_bytes + 6];
+        assert_eq!(i32::from_le_bytes([0, second[0], second[1], second[2]]) >> 8, -100);
+        
+        let annotations = &bytes[header_bytes + 6..];
+        assert!(annotations.starts_with(b"+0\x14\x14\0+0.5\x14stimulus\x14\0"));
+    }
+    
+    #[test]
+    fn test_flat_range_is_widened_and_long_annotation_truncated() {
+        let path = std::env::temp_dir().join(format!("bdf_writer_flat_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        
+        let signal = BdfSignal {
+            label: "Flat".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: 0.0,
+            physical_max: 0.0,
+            prefilter: String::new(),
+            samples_per_record: 1,
+        };
+        
+        let mut writer = BdfWriter::create(&filename, vec![signal], 1.0, Utc::now(), "X", "X").unwrap();
+        let label = "é".repeat(400);
+        writer.add_annotation(0.25, None, &label).unwrap();
+        writer.write_samples(&[vec![0.0]]).unwrap();
+        writer.finalize().unwrap();
+        
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        // 文件头物理范围展开为 -1..1，0µV 写在数字范围中点（而不是NaN换算出的垃圾值）
+        let signals = 2;
+        let physical_min = 256 + signals * (16 + 80 + 8);
+        assert_eq!(std::str::from_utf8(&bytes[physical_min..physical_min + 8]).unwrap().trim(), "-1");
+        assert_eq!(std::str::from_utf8(&bytes[physical_min + 16..physical_min + 24]).unwrap().trim(), "1");
+        
+        let header_bytes = 256 * 3;
+        let sample = &bytes[header_bytes..header_bytes + 3];
+        assert!((i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) >> 8).abs() <= 1);
+        
+        // 超长描述被截断后仍写入同一个记录，且保持有效的UTF-8
+        let annotations = &bytes[header_bytes + 3..header_bytes + 3 + annotation_samples_per_record(1.0) * 3];
+        let text = std::str::from_utf8(annotations).unwrap();
+        let tal = text.split('\0').filter(|part| !part.is_empty()).nth(1).unwrap();
+        assert!(tal.starts_with("+0.25\u{14}é"));
+        assert!(tal.ends_with('\u{14}'));
+        assert!(tal.len() < label.len());
+    }
+    
+    #[test]
+    fn test_annotation_burst_is_kept() {
+        let path = std::env::temp_dir().join(format!("bdf_writer_burst_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        
+        let signal = BdfSignal {
+            label: "Cz".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -100.0,
+            physical_max: 100.0,
+            prefilter: String::new(),
+            samples_per_record: 1,
+        };
+        
+        // 一个记录内的标记远超预留空间：顺延到后续记录，结束时追加记录写完
+        let mut writer = BdfWriter::create(&filename, vec![signal], 1.0, Utc::now(), "X", "X").unwrap();
+        for i in 0..100 {
+            writer.add_annotation(0.5, None, &format!("burst marker {:03}", i)).unwrap();
+        }
+        writer.write_samples(&[vec![1.0]]).unwrap();
+        writer.finalize().unwrap();
+        
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        let records: usize = std::str::from_utf8(&bytes[236..244]).unwrap().trim().parse().unwrap();
+        assert!(records > 1);
+        let text = String::from_utf8_lossy(&bytes[256 * 3..]);
+        for i in 0..100 {
+            assert!(text.contains(&format!("+0.5\u{14}burst marker {:03}\u{14}", i)), "marker {} missing", i);
+        }
+    }
+    
+    #[test]
+    fn test_header_numbers_fit_eight_characters() {
+        assert_eq!(format_number(-8388608.0).unwrap(), "-8388608");
+        assert_eq!(format_number(0.25).unwrap(), "0.25");
+        // 超长时降低精度而不是截断字符
+        assert_eq!(format_number(-1234.56789).unwrap(), "-1234.57");
+        assert_eq!(format_number(12345678.9).unwrap(), "12345679");
+        assert_eq!(header_value(1.23456789).unwrap(), 1.234568);
+        assert!(format_number(-123456789.5).is_err());
+        
+        let path = std::env::temp_dir().join(format!("bdf_writer_wide_{}.bdf", std::process::id()));
+        let signal = BdfSignal {
+            label: "Wide".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -123456789.5,
+            physical_max: 123456789.5,
+            prefilter: String::new(),
+            samples_per_record: 1,
+        };
+        assert!(BdfWriter::create(path.to_str().unwrap(), vec![signal], 1.0, Utc::now(), "X", "X").is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
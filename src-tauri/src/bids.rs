@@ -0,0 +1,285 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use serde_json::json;
+use tracing::info;
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::recorder::{self, EdfRecorder, FinishedRecording};
+
+/// 导出遵循的BIDS规范版本
+const BIDS_VERSION: &str = "1.9.0";
+
+/// 将已完成的EDF+/BDF+录制导出为BIDS-EEG目录结构
+///
+/// 写入 sub-<label>/[ses-<label>/]eeg 下的数据文件、_eeg.json 描述、_channels.tsv，
+/// 以及会话级的 _electrodes.tsv / _coordsystem.json；数据集根目录缺少
+/// dataset_description.json 时创建，participants.tsv 中追加新受试者。
+/// 分段录制的每个文件导出为一个 run。已存在的同名文件不会被覆盖。
+pub fn export(recording: &FinishedRecording, destination: &Path, options: &BidsExportOptions) -> Result<BidsExportResult, AppError> {
+    options.validate()?;
+    
+    let stats = &recording.stats;
+    let extension = match stats.format {
+        RecordingFormat::Edf => "edf",
+        RecordingFormat::Bdf => "bdf",
+        other => {
+            return Err(AppError::Recording(format!("BIDS-EEG export requires an EDF+ or BDF+ recording, got {:?}", other)));
+        }
+    };
+    
+    let subject = match options.subject.clone() {
+        Some(subject) => subject,
+        None => recording.metadata.patient_code.as_deref().map(bids_label).filter(|label| !label.is_empty())
+            .ok_or_else(|| AppError::Config("No BIDS subject label: set a patient code or pass a subject".to_string()))?,
+    };
+    let session = options.session.clone().unwrap_or_else(|| stats.start_time.format("%Y%m%d").to_string());
+    
+    let session_prefix = format!("sub-{}_ses-{}", subject, session);
+    let eeg_dir = destination.join(format!("sub-{}", subject)).join(format!("ses-{}", session)).join("eeg");
+    
+    // 每个分段一个 run；先检查全部目标，避免导出到一半才发现冲突
+    let runs: Vec<(String, &String)> = stats.segments.iter().enumerate()
+        .map(|(index, segment)| {
+            let run = if stats.segments.len() > 1 { format!("_run-{}", index + 1) } else { String::new() };
+            (format!("{}_task-{}{}", session_prefix, options.task, run), segment)
+        })
+        .collect();
+    for (prefix, _) in &runs {
+        let target = eeg_dir.join(format!("{}_eeg.{}", prefix, extension));
+        if target.exists() {
+            return Err(AppError::Config(format!("BIDS file already exists: {}", target.display())));
+        }
+    }
+    
+    std::fs::create_dir_all(&eeg_dir)?;
+    let mut files = Vec::new();
+    
+    write_dataset_description(destination, &mut files)?;
+    add_participant(destination, &subject, recording, &mut files)?;
+    
+    for (prefix, segment) in &runs {
+        let data_path = eeg_dir.join(format!("{}_eeg.{}", prefix, extension));
+        std::fs::copy(segment, &data_path)?;
+        files.push(data_path);
+        
+        let duration = (runs.len() == 1).then_some(stats.duration_seconds);
+        let sidecar = eeg_sidecar(recording, &options.task, options.power_line_frequency, duration);
+        files.push(write_file(&eeg_dir.join(format!("{}_eeg.json", prefix)), &sidecar)?);
+        files.push(write_file(&eeg_dir.join(format!("{}_channels.tsv", prefix)), &channels_tsv(&recording.stream_info))?);
+    }
+    
+    files.push(write_file(&eeg_dir.join(format!("{}_electrodes.tsv", session_prefix)), &electrodes_tsv(recording))?);
+    files.push(write_file(&eeg_dir.join(format!("{}_coordsystem.json", session_prefix)), &coordsystem_json())?);
+    
+    info!("📦 BIDS export: {} run(s) to {}", runs.len(), eeg_dir.display());
+    
+    Ok(BidsExportResult {
+        directory: eeg_dir.display().to_string(),
+        files: files.iter().map(|path| path.display().to_string()).collect(),
+    })
+}
+
+/// BIDS标签只允许字母和数字，其他字符去除
+pub fn bids_label(raw: &str) -> String {
+    raw.chars().filter(char::is_ascii_alphanumeric).collect()
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<PathBuf, AppError> {
+    std::fs::write(path, contents)?;
+    Ok(path.to_path_buf())
+}
+
+fn to_json(value: &serde_json::Value) -> String {
+    // Value 序列化不会失败
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// 数据集根目录的 dataset_description.json（已存在时保留）
+fn write_dataset_description(destination: &Path, files: &mut Vec<PathBuf>) -> Result<(), AppError> {
+    let path = destination.join("dataset_description.json");
+    if path.exists() {
+        return Ok(());
+    }
+    
+    let description = json!({
+        "Name": "Open-CortexArray recordings",
+        "BIDSVersion": BIDS_VERSION,
+        "DatasetType": "raw",
+        "GeneratedBy": [{ "Name": "Open-CortexArray", "Version": env!("CARGO_PKG_VERSION") }],
+    });
+    files.push(write_file(&path, &to_json(&description))?);
+    Ok(())
+}
+
+/// 在 participants.tsv 中登记受试者（已登记时不重复）
+fn add_participant(destination: &Path, subject: &str, recording: &FinishedRecording, files: &mut Vec<PathBuf>) -> Result<(), AppError> {
+    let path = destination.join("participants.tsv");
+    let participant_id = format!("sub-{}", subject);
+    
+    let mut contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "participant_id\tsex\tage\n".to_string(),
+        Err(e) => return Err(e.into()),
+    };
+    if contents.lines().any(|line| line.split('\t').next() == Some(participant_id.as_str())) {
+        return Ok(());
+    }
+    
+    let metadata = &recording.metadata;
+    let age = metadata.birthdate
+        .and_then(|birthdate| recording.stats.start_time.date_naive().years_since(birthdate))
+        .map_or("n/a".to_string(), |years| years.to_string());
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    let _ = writeln!(contents, "{}\t{}\t{}", participant_id, metadata.sex.as_deref().unwrap_or("n/a"), age);
+    
+    files.push(write_file(&path, &contents)?);
+    Ok(())
+}
+
+/// 记录级描述 _eeg.json；录制保存的是滤波前的原始数据
+fn eeg_sidecar(recording: &FinishedRecording, task: &str, power_line_frequency: Option<f64>, duration: Option<f64>) -> String {
+    let stream_info = &recording.stream_info;
+    let mut sidecar = json!({
+        "TaskName": task,
+        "SamplingFrequency": stream_info.sample_rate,
+        "EEGReference": "n/a",
+        "PowerLineFrequency": power_line_frequency.map_or(json!("n/a"), |frequency| json!(frequency)),
+        "SoftwareFilters": "n/a",
+        "EEGChannelCount": stream_info.channels_count,
+        "RecordingType": "continuous",
+        "ManufacturersModelName": stream_info.name,
+        "DeviceSerialNumber": stream_info.source_id,
+        "SoftwareVersions": format!("Open-CortexArray {}", env!("CARGO_PKG_VERSION")),
+    });
+    if let Some(duration) = duration {
+        sidecar["RecordingDuration"] = json!(duration);
+    }
+    to_json(&sidecar)
+}
+
+/// _channels.tsv：名称与数据文件中的信号标签一致，描述列为流中的通道标签
+fn channels_tsv(stream_info: &StreamInfo) -> String {
+    let mut tsv = "name\ttype\tunits\tdescription\tsampling_frequency\tstatus\n".to_string();
+    for (ch_idx, label) in EdfRecorder::channel_labels(stream_info).iter().enumerate() {
+        let _ = writeln!(tsv, "{}\tEEG\tuV\t{}\t{}\tgood", recorder::signal_label(ch_idx as u32), label, stream_info.sample_rate);
+    }
+    tsv
+}
+
+/// _electrodes.tsv：电极位置未经数字化，坐标为 n/a
+/// 电极列表；录制前测得的阻抗写入 impedance 列（kΩ）
+fn electrodes_tsv(recording: &FinishedRecording) -> String {
+    let mut tsv = "name\tx\ty\tz\timpedance\n".to_string();
+    for label in EdfRecorder::channel_labels(&recording.stream_info) {
+        let impedance = recording.metadata.impedances.iter()
+            .find(|electrode| electrode.label == label)
+            .map_or_else(|| "n/a".to_string(), |electrode| format!("{:.1}", electrode.kohm));
+        let _ = writeln!(tsv, "{}\tn/a\tn/a\tn/a\t{}", label, impedance);
+    }
+    tsv
+}
+
+fn coordsystem_json() -> String {
+    to_json(&json!({
+        "EEGCoordinateSystem": "Other",
+        "EEGCoordinateUnits": "n/a",
+        "EEGCoordinateSystemDescription": "Electrode positions were not digitized; names follow the acquisition stream channel labels",
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::RecordingStats;
+    
+    fn finished_recording(segments: Vec<String>) -> FinishedRecording {
+        let stream_info = StreamInfo {
+            name: "Cyton".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 2,
+            sample_rate: 250.0,
+            is_connected: true,
+            source_id: "cyton-01".to_string(),
+            channel_labels: vec!["Fp1".to_string(), "Fp2".to_string()],
+            effective_sample_rate: None,
+        };
+        FinishedRecording {
+            stats: RecordingStats {
+                filename: segments[0].clone(),
+                format: RecordingFormat::Edf,
+                segments,
+                duration_seconds: 60.0,
+                samples_written: 15000,
+                annotations_written: 0,
+                markers_written: 0,
+                paused_seconds: 0.0,
+                channels_count: 2,
+                sample_rate: 250.0,
+                start_time: "2024-03-05T10:00:00Z".parse().unwrap(),
+                file_size_bytes: 0,
+                checksums: vec![None],
+                physical_ranges: Vec::new(),
+                clipped_samples: Vec::new(),
+            },
+            stream_info,
+            metadata: SessionMetadata {
+                patient_code: Some("P-007".to_string()),
+                sex: Some("F".to_string()),
+                birthdate: chrono::NaiveDate::from_ymd_opt(1990, 6, 1),
+                impedances: vec![ElectrodeImpedance {
+                    label: "Fp1".to_string(),
+                    kohm: 4.3,
+                    status: ImpedanceStatus::Good,
+                    source: ImpedanceSource::Check,
+                    timestamp: 0.0,
+                }],
+                ..SessionMetadata::default()
+            },
+            tags: Vec::new(),
+            annotations: Vec::new(),
+            sidecars: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_export_bids_layout() {
+        let dir = std::env::temp_dir().join(format!("cortexarray_bids_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("session.edf");
+        std::fs::write(&source, b"0       ").unwrap();
+        let dataset = dir.join("dataset");
+        
+        let recording = finished_recording(vec![source.display().to_string()]);
+        let result = export(&recording, &dataset, &BidsExportOptions::default()).unwrap();
+        
+        let eeg_dir = dataset.join("sub-P007/ses-20240305/eeg");
+        assert_eq!(Path::new(&result.directory), eeg_dir);
+        assert!(eeg_dir.join("sub-P007_ses-20240305_task-rest_eeg.edf").exists());
+        assert!(eeg_dir.join("sub-P007_ses-20240305_coordsystem.json").exists());
+        
+        let channels = std::fs::read_to_string(eeg_dir.join("sub-P007_ses-20240305_task-rest_channels.tsv")).unwrap();
+        assert_eq!(channels.lines().nth(1), Some("EEG Ch01\tEEG\tuV\tFp1\t250\tgood"));
+        let electrodes = std::fs::read_to_string(eeg_dir.join("sub-P007_ses-20240305_electrodes.tsv")).unwrap();
+        assert_eq!(electrodes.lines().nth(1), Some("Fp1\tn/a\tn/a\tn/a\t4.3"));
+        assert_eq!(electrodes.lines().nth(2), Some("Fp2\tn/a\tn/a\tn/a\tn/a"));
+        
+        let sidecar: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(eeg_dir.join("sub-P007_ses-20240305_task-rest_eeg.json")).unwrap()
+        ).unwrap();
+        assert_eq!(sidecar["SamplingFrequency"], json!(250.0));
+        assert_eq!(sidecar["PowerLineFrequency"], json!("n/a"));
+        
+        // 同一目标不覆盖；新会话追加 run 但受试者只登记一次
+        assert!(export(&recording, &dataset, &BidsExportOptions::default()).is_err());
+        let options = BidsExportOptions { session: Some("02".to_string()), power_line_frequency: Some(50.0), ..BidsExportOptions::default() };
+        export(&recording, &dataset, &options).unwrap();
+        let participants = std::fs::read_to_string(dataset.join("participants.tsv")).unwrap();
+        assert_eq!(participants, "participant_id\tsex\tage\nsub-P007\tF\t33\n");
+        
+        let invalid = BidsExportOptions { task: "resting state".to_string(), ..BidsExportOptions::default() };
+        assert!(export(&recording, &dataset, &invalid).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+//! cortexctl - 无界面采集/录制，与桌面应用共用同一处理核心
+//!
+//!     cortexctl list [--type EEG] [--name "Cyton*"] [--host lab-pc]
+//!     cortexctl record --stream X --out file.edf [--source-id ID] [--host HOST]
+//!                      [--markers NAME] [--format edf|bdf|gdf|csv|tsv|hdf5] [--duration SECONDS] [--aux]
+//!                      [--capture FILE.cxcap]
+//!     cortexctl replay --capture FILE.cxcap [--out FILE] [--raw] [--fft-window N] [--golden REPORT.json]
+//!
+//! 不依赖 tauri，可单独构建：cargo build --bin cortexctl --no-default-features
+
+use std::process::ExitCode;
+use std::time::Duration;
+use cortexarray_lib::headless::{self, RecordRequest, ReplayReport, ReplayRequest};
+use cortexarray_lib::{FftConfig, RecordingFormat, RecordingProcessing, StreamFilter};
+
+// 与金标准比对时谱值允许的相对误差
+const GOLDEN_TOLERANCE: f64 = 1e-9;
+
+const USAGE: &str = "\
+Usage:
+  cortexctl list [--type TYPE] [--name PATTERN] [--host HOSTNAME]
+  cortexctl record --stream NAME --out FILE [options]
+  cortexctl replay --capture FILE [options]
+
+Record options:
+  --source-id ID       select among streams with the same name
+  --host HOSTNAME      only match streams from this host
+  --markers NAME       also record annotations from an LSL marker stream
+  --format FORMAT      edf, bdf, gdf, csv, tsv or hdf5 (default: from file extension)
+  --duration SECONDS   stop after this many seconds (default: until Ctrl-C)
+  --aux                record auxiliary streams (IMU) to <file>_aux.csv
+  --capture FILE       also capture the raw sample stream for replay
+
+Replay options (prints a JSON report):
+  --out FILE           record the replayed stream to FILE
+  --format FORMAT      recording format (default: from file extension)
+  --raw                record without notch/band-pass filtering
+  --fft-window N       also compute magnitude spectra every N samples
+  --golden FILE        compare the report with a saved report, fail on mismatch";
+
+enum Command {
+    List(StreamFilter),
+    Record(Box<RecordRequest>),
+    Replay(Box<ReplayRequest>, Option<String>),
+}
+
+/// 解析 `--flag value` 形式的参数
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let (command, rest) = args.split_first().ok_or("Missing command")?;
+    
+    let mut flags = std::collections::HashMap::new();
+    let mut switches = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let flag = arg.strip_prefix("--").ok_or_else(|| format!("Unexpected argument '{}'", arg))?;
+        if flag == "aux" || flag == "raw" {
+            switches.push(flag);
+            continue;
+        }
+        let value = iter.next().ok_or_else(|| format!("Missing value for --{}", flag))?;
+        flags.insert(flag, value.clone());
+    }
+    
+    let mut take = |flag: &str| flags.remove(flag);
+    let command = match command.as_str() {
+        "list" => Command::List(StreamFilter {
+            stream_type: take("type"),
+            name_pattern: take("name"),
+            hostname: take("host"),
+        }),
+        "record" => {
+            let name = take("stream").ok_or("--stream is required")?;
+            let filename = take("out").ok_or("--out is required")?;
+            let format = match take("format") {
+                Some(format) => RecordingFormat::from_extension(&format!("recording.{}", format))
+                    .ok_or_else(|| format!("Unknown format '{}'", format))?,
+                None => RecordingFormat::from_extension(&filename).unwrap_or_default(),
+            };
+            let duration = take("duration")
+                .map(|seconds| match seconds.parse::<f64>() {
+                    Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+                    _ => Err(format!("Invalid duration '{}'", seconds)),
+                })
+                .transpose()?;
+            
+            let mut request = RecordRequest {
+                filename,
+                duration,
+                marker_stream: take("markers"),
+                ..RecordRequest::default()
+            };
+            request.stream.name = name;
+            request.stream.source_id = take("source-id");
+            request.stream.hostname = take("host");
+            request.options.format = format;
+            request.options.record_aux = switches.contains(&"aux");
+            request.capture = take("capture");
+            Command::Record(Box::new(request))
+        }
+        "replay" => {
+            let mut request = ReplayRequest {
+                capture: take("capture").ok_or("--capture is required")?,
+                filename: take("out"),
+                ..ReplayRequest::default()
+            };
+            request.options.format = match take("format") {
+                Some(format) => RecordingFormat::from_extension(&format!("recording.{}", format))
+                    .ok_or_else(|| format!("Unknown format '{}'", format))?,
+                None => request.filename.as_deref().and_then(RecordingFormat::from_extension).unwrap_or_default(),
+            };
+            if switches.contains(&"raw") {
+                request.options.processing = RecordingProcessing::Raw;
+            }
+            request.fft = take("fft-window")
+                .map(|window| match window.parse::<usize>() {
+                    Ok(window_size) => Ok(FftConfig { window_size, hop_size: window_size, zero_padding_factor: 1 }),
+                    Err(_) => Err(format!("Invalid FFT window '{}'", window)),
+                })
+                .transpose()?;
+            Command::Replay(Box::new(request), take("golden"))
+        }
+        other => return Err(format!("Unknown command '{}'", other)),
+    };
+    
+    if let Some(flag) = flags.keys().next() {
+        return Err(format!("Unknown option --{} for '{}'", flag, command_name(&command)));
+    }
+    Ok(command)
+}
+
+fn command_name(command: &Command) -> &'static str {
+    match command {
+        Command::List(_) => "list",
+        Command::Record(_) => "record",
+        Command::Replay(..) => "replay",
+    }
+}
+
+/// 与保存的回放报告比对
+fn compare_golden(report: &ReplayReport, path: &str) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let golden: ReplayReport = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    report.compare(&golden, GOLDEN_TOLERANCE).map_err(|e| format!("Replay differs from {}: {}", path, e))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    
+    headless::init_logging();
+    
+    let result = match command {
+        Command::List(filter) => headless::list_streams(filter).await.map(|streams| {
+            for stream in streams {
+                println!("{}\t{}\t{} ch @ {} Hz\t{}\t{}",
+                         stream.name, stream.stream_type, stream.channels_count,
+                         stream.sample_rate, stream.source_id, stream.hostname);
+            }
+        }).map_err(|e| e.to_string()),
+        Command::Record(request) => headless::record(*request).await.map(|stats| {
+            if let Some(stats) = stats {
+                println!("Recorded {} samples ({:.1}s) to {}",
+                         stats.samples_written, stats.duration_seconds, stats.segments.join(", "));
+            }
+        }).map_err(|e| e.to_string()),
+        Command::Replay(request, golden) => match headless::replay(*request).await {
+            Ok(report) => {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+                match golden {
+                    Some(golden) => compare_golden(&report, &golden),
+                    None => Ok(()),
+                }
+            }
+            Err(e) => Err(e.to_string()),
+        },
+    };
+    
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+    
+    #[test]
+    fn test_parse_record_command() {
+        let Ok(Command::Record(request)) = parse_args(&args("record --stream Cyton --out /data/s01.bdf --duration 90 --aux")) else {
+            panic!("expected record command");
+        };
+        assert_eq!(request.stream.name, "Cyton");
+        assert_eq!(request.options.format, RecordingFormat::Bdf);
+        assert_eq!(request.duration, Some(Duration::from_secs(90)));
+        assert!(request.options.record_aux);
+        
+        assert!(parse_args(&args("record --out s01.edf")).is_err());
+        assert!(parse_args(&args("record --stream X --out s01.edf --format wav")).is_err());
+        assert!(parse_args(&args("list --type EEG --bogus 1")).is_err());
+    }
+    
+    #[test]
+    fn test_parse_replay_command() {
+        let Ok(Command::Replay(request, golden)) = parse_args(&args("replay --capture s01.cxcap --out s01.csv --raw --fft-window 128 --golden golden.json")) else {
+            panic!("expected replay command");
+        };
+        assert_eq!(request.filename.as_deref(), Some("s01.csv"));
+        assert_eq!(request.options.format, RecordingFormat::Csv);
+        assert_eq!(request.options.processing, RecordingProcessing::Raw);
+        assert_eq!(request.fft.map(|fft| fft.window_size), Some(128));
+        assert_eq!(golden.as_deref(), Some("golden.json"));
+        
+        assert!(parse_args(&args("replay --out s01.csv")).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use tracing::{info, warn};
+
+/// 采集文件扩展名
+pub const CAPTURE_EXTENSION: &str = "cxcap";
+
+const MAGIC: &[u8; 5] = b"CXCAP";
+const VERSION: u8 = 1;
+
+// 记录类型
+const RECORD_CHUNK: u8 = 1;
+const RECORD_MARKER: u8 = 2;
+
+// 流描述（JSON）与标记字符串的长度上限，防止损坏文件导致超大分配
+const MAX_HEADER_BYTES: u32 = 1 << 20;
+const MAX_STRING_BYTES: u32 = 1 << 16;
+const MAX_CHUNK_SAMPLES: u32 = 1 << 20;
+
+/// 原始样本采集写入器 - 按处理器收到的顺序记录样本块（含 sample_id 与时间戳）和标记
+///
+/// 文件布局（小端）：`CXCAP` + 版本 + u32 长度的流描述JSON，随后为记录：
+/// - 样本块：1, u32 样本数, 每个样本 u64 sample_id + f64 时间戳 + 各通道 f64
+/// - 标记：2, f64 时间戳, u32 长度的标签, u32 长度的流名称
+pub struct CaptureWriter {
+    file: BufWriter<File>,
+    filename: String,
+    channels_count: usize,
+    samples_written: u64,
+    markers_written: u64,
+}
+
+impl CaptureWriter {
+    pub fn create(filename: &str, stream_info: &StreamInfo) -> Result<Self, AppError> {
+        let file = File::create(filename)
+            .map_err(|e| AppError::Capture(format!("Failed to create {}: {}", filename, e)))?;
+        let header = serde_json::to_vec(stream_info)
+            .map_err(|e| AppError::Capture(e.to_string()))?;
+        
+        let mut file = BufWriter::new(file);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        file.write_all(&(header.len() as u32).to_le_bytes())?;
+        file.write_all(&header)?;
+        
+        info!("📼 Capturing raw samples to {}", filename);
+        Ok(Self {
+            file,
+            filename: filename.to_string(),
+            channels_count: stream_info.channels_count as usize,
+            samples_written: 0,
+            markers_written: 0,
+        })
+    }
+    
+    pub fn write_chunk(&mut self, chunk: &[EegSample]) -> Result<(), AppError> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        if let Some(sample) = chunk.iter().find(|sample| sample.channels.len() != self.channels_count) {
+            return Err(AppError::Capture(format!(
+                "Sample {} has {} channels, expected {}", sample.sample_id, sample.channels.len(), self.channels_count
+            )));
+        }
+        
+        self.file.write_all(&[RECORD_CHUNK])?;
+        self.file.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        for sample in chunk {
+            self.file.write_all(&sample.sample_id.to_le_bytes())?;
+            self.file.write_all(&sample.timestamp.to_le_bytes())?;
+            for value in &sample.channels {
+                self.file.write_all(&value.to_le_bytes())?;
+            }
+        }
+        self.samples_written += chunk.len() as u64;
+        Ok(())
+    }
+    
+    pub fn write_marker(&mut self, marker: &LslMarker) -> Result<(), AppError> {
+        self.file.write_all(&[RECORD_MARKER])?;
+        self.file.write_all(&marker.timestamp.to_le_bytes())?;
+        write_string(&mut self.file, &marker.label)?;
+        write_string(&mut self.file, &marker.stream_name)?;
+        self.markers_written += 1;
+        Ok(())
+    }
+    
+    pub fn finish(mut self) -> Result<CaptureStats, AppError> {
+        self.file.flush()?;
+        let file_size_bytes = self.file.get_ref().metadata()?.len();
+        info!("📼 Capture finished: {} ({} samples, {} markers, {} bytes)",
+              self.filename, self.samples_written, self.markers_written, file_size_bytes);
+        
+        Ok(CaptureStats {
+            filename: self.filename,
+            samples_written: self.samples_written,
+            markers_written: self.markers_written,
+            file_size_bytes,
+        })
+    }
+}
+
+fn write_string(file: &mut impl Write, value: &str) -> Result<(), AppError> {
+    let mut end = value.len().min(MAX_STRING_BYTES as usize);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    let bytes = &value.as_bytes()[..end];
+    file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    file.write_all(bytes)?;
+    Ok(())
+}
+
+/// 读取完成的采集文件
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub stream_info: StreamInfo,
+    pub chunks: Vec<EegChunk>,
+    pub markers: Vec<LslMarker>,
+    pub truncated: bool,                // 末尾记录不完整（采集中途中断），已忽略
+}
+
+impl Capture {
+    pub fn read(filename: &str) -> Result<Self, AppError> {
+        let file = File::open(filename)
+            .map_err(|e| AppError::Capture(format!("Failed to open {}: {}", filename, e)))?;
+        let mut reader = BufReader::new(file);
+        let invalid = |message: &str| AppError::Capture(format!("{}: {}", filename, message));
+        
+        let mut magic = [0u8; 6];
+        reader.read_exact(&mut magic).map_err(|_| invalid("not a capture file"))?;
+        if &magic[..5] != MAGIC {
+            return Err(invalid("not a capture file"));
+        }
+        if magic[5] != VERSION {
+            return Err(invalid(&format!("unsupported capture version {}", magic[5])));
+        }
+        
+        let header_len = read_u32(&mut reader).map_err(|_| invalid("truncated header"))?;
+        if header_len > MAX_HEADER_BYTES {
+            return Err(invalid("stream header too large"));
+        }
+        let mut header = vec![0u8; header_len as usize];
+        reader.read_exact(&mut header).map_err(|_| invalid("truncated header"))?;
+        let stream_info: StreamInfo = serde_json::from_slice(&header)
+            .map_err(|e| invalid(&format!("invalid stream header: {}", e)))?;
+        
+        let mut capture = Self { stream_info, chunks: Vec::new(), markers: Vec::new(), truncated: false };
+        loop {
+            let mut tag = [0u8; 1];
+            match reader.read(&mut tag) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Err(e.into()),
+            }
+            let record = match tag[0] {
+                RECORD_CHUNK => capture.read_chunk(&mut reader).map(|chunk| capture.chunks.push(chunk)),
+                RECORD_MARKER => read_marker(&mut reader).map(|marker| capture.markers.push(marker)),
+                other => return Err(invalid(&format!("unknown record type {}", other))),
+            };
+            match record {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    warn!("⚠️ Capture {} ends with an incomplete record, ignoring it", filename);
+                    capture.truncated = true;
+                    break;
+                }
+                Err(e) if e.kind() == ErrorKind::InvalidData => return Err(invalid(&e.to_string())),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        
+        Ok(capture)
+    }
+    
+    fn read_chunk(&self, reader: &mut impl Read) -> std::io::Result<EegChunk> {
+        let count = read_u32(reader)?;
+        if count > MAX_CHUNK_SAMPLES {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, format!("chunk of {} samples", count)));
+        }
+        (0..count)
+            .map(|_| {
+                let sample_id = read_u64(reader)?;
+                let timestamp = read_f64(reader)?;
+                let channels = (0..self.stream_info.channels_count)
+                    .map(|_| read_f64(reader))
+                    .collect::<std::io::Result<Vec<f64>>>()?;
+                Ok(EegSample { timestamp, channels, sample_id })
+            })
+            .collect()
+    }
+    
+    pub fn samples(&self) -> impl Iterator<Item = &EegSample> {
+        self.chunks.iter().flatten()
+    }
+    
+    pub fn samples_count(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.len() as u64).sum()
+    }
+}
+
+fn read_marker(reader: &mut impl Read) -> std::io::Result<LslMarker> {
+    let timestamp = read_f64(reader)?;
+    let label = read_string(reader)?;
+    let stream_name = read_string(reader)?;
+    Ok(LslMarker { label, timestamp, stream_name })
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(reader: &mut impl Read) -> std::io::Result<f64> {
+    Ok(f64::from_bits(read_u64(reader)?))
+}
+
+fn read_string(reader: &mut impl Read) -> std::io::Result<String> {
+    let len = read_u32(reader)?;
+    if len > MAX_STRING_BYTES {
+        return Err(std::io::Error::new(ErrorKind::InvalidData, format!("string of {} bytes", len)));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn stream_info() -> StreamInfo {
+        StreamInfo {
+            name: "Capture".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 2,
+            sample_rate: 250.0,
+            is_connected: true,
+            source_id: "capture".to_string(),
+            channel_labels: vec!["C3".to_string(), "C4".to_string()],
+            effective_sample_rate: None,
+        }
+    }
+    
+    fn values(chunks: &[EegChunk]) -> Vec<(u64, f64, Vec<f64>)> {
+        chunks.iter().flatten().map(|sample| (sample.sample_id, sample.timestamp, sample.channels.clone())).collect()
+    }
+    
+    #[test]
+    fn test_capture_round_trip_and_truncated_tail() {
+        let dir = std::env::temp_dir().join(format!("cortexarray_capture_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.cxcap").to_string_lossy().into_owned();
+        
+        let chunk: EegChunk = (0..3)
+            .map(|i| EegSample { timestamp: 100.0 + i as f64 / 250.0, channels: vec![i as f64, -0.1 * i as f64], sample_id: i })
+            .collect();
+        let marker = LslMarker { label: "stimulus/1".to_string(), timestamp: 100.004, stream_name: "Markers".to_string() };
+        
+        let mut writer = CaptureWriter::create(&path, &stream_info()).unwrap();
+        writer.write_marker(&marker).unwrap();
+        writer.write_chunk(&chunk).unwrap();
+        writer.write_chunk(&chunk[..1]).unwrap();
+        assert!(writer.write_chunk(&[EegSample { timestamp: 0.0, channels: vec![1.0], sample_id: 9 }]).is_err());
+        let stats = writer.finish().unwrap();
+        assert_eq!((stats.samples_written, stats.markers_written), (4, 1));
+        
+        let capture = Capture::read(&path).unwrap();
+        assert_eq!(capture.stream_info.channel_labels, stream_info().channel_labels);
+        assert_eq!(capture.chunks.len(), 2);
+        assert_eq!(values(&capture.chunks), values(&[chunk.clone(), chunk[..1].to_vec()]));
+        assert_eq!(capture.markers[0].label, marker.label);
+        assert_eq!(capture.samples_count(), 4);
+        assert!(!capture.truncated);
+        
+        // 中途中断的文件保留完整的记录
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 5]).unwrap();
+        let capture = Capture::read(&path).unwrap();
+        assert_eq!(values(&capture.chunks), values(&[chunk]));
+        assert!(capture.truncated);
+        
+        std::fs::write(&path, b"EDF+ not a capture").unwrap();
+        assert!(Capture::read(&path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,392 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use rusqlite::types::{Type, Value};
+use tracing::{info, warn};
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::recorder::{EdfRecorder, FinishedRecording};
+
+/// 应用数据目录下的录制索引数据库文件名
+pub const CATALOG_FILE: &str = "recordings.db";
+
+/// 默认返回的最大条目数
+const DEFAULT_LIMIT: u32 = 500;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS recordings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        file_path TEXT NOT NULL,
+        segments TEXT NOT NULL,
+        format TEXT NOT NULL,
+        start_time TEXT NOT NULL,
+        duration_seconds REAL NOT NULL,
+        channels_count INTEGER NOT NULL,
+        sample_rate REAL NOT NULL,
+        channel_labels TEXT NOT NULL,
+        stream_name TEXT NOT NULL,
+        stream_type TEXT NOT NULL,
+        source_id TEXT NOT NULL,
+        patient_code TEXT,
+        notes TEXT,
+        markers_count INTEGER NOT NULL,
+        file_size_bytes INTEGER NOT NULL,
+        checksums TEXT NOT NULL DEFAULT '[]'
+    );
+    CREATE INDEX IF NOT EXISTS recordings_start_time ON recordings (start_time);
+    CREATE TABLE IF NOT EXISTS recording_tags (
+        recording_id INTEGER NOT NULL REFERENCES recordings (id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (recording_id, tag)
+    );
+";
+
+const SELECT_COLUMNS: &str = "
+    SELECT r.id, r.file_path, r.segments, r.format, r.start_time, r.duration_seconds, r.channels_count,
+           r.sample_rate, r.channel_labels, r.stream_name, r.stream_type, r.source_id, r.patient_code,
+           r.notes, r.markers_count, r.file_size_bytes, r.checksums,
+           (SELECT json_group_array(tag) FROM (SELECT tag FROM recording_tags WHERE recording_id = r.id ORDER BY tag))
+    FROM recordings r
+";
+
+/// 录制索引 - SQLite持久化的录制清单，供前端浏览历史会话
+///
+/// 录制结束时由处理器写入；列表和向量字段（分段、通道标签）以JSON文本存储。
+pub struct RecordingCatalog {
+    connection: Mutex<Connection>,
+}
+
+impl RecordingCatalog {
+    /// 打开（必要时创建）索引数据库
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.execute_batch(SCHEMA)?;
+        migrate(&connection)?;
+        
+        info!("🗂️  Recording catalog: {}", path.display());
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+    
+    fn connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>, AppError> {
+        self.connection.lock().map_err(|_| AppError::Catalog("Catalog connection poisoned".to_string()))
+    }
+    
+    /// 登记已完成的录制，返回条目ID
+    pub fn insert(&self, recording: &FinishedRecording) -> Result<i64, AppError> {
+        let stats = &recording.stats;
+        let stream_info = &recording.stream_info;
+        let mut connection = self.connection()?;
+        let transaction = connection.transaction()?;
+        
+        transaction.execute(
+            "INSERT INTO recordings (file_path, segments, format, start_time, duration_seconds, channels_count,
+                                     sample_rate, channel_labels, stream_name, stream_type, source_id, patient_code,
+                                     notes, markers_count, file_size_bytes, checksums)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                stats.filename,
+                to_json(&stats.segments)?,
+                to_json(&stats.format)?,
+                timestamp(&stats.start_time),
+                stats.duration_seconds,
+                stats.channels_count,
+                stats.sample_rate,
+                to_json(&crate::recorder::EdfRecorder::channel_labels(stream_info))?,
+                stream_info.name,
+                stream_info.stream_type,
+                stream_info.source_id,
+                recording.metadata.patient_code,
+                recording.metadata.notes,
+                stats.markers_written,
+                stats.file_size_bytes,
+                to_json(&stats.checksums)?,
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+        for tag in &recording.tags {
+            transaction.execute(
+                "INSERT OR IGNORE INTO recording_tags (recording_id, tag) VALUES (?1, ?2)",
+                params![id, tag.trim()],
+            )?;
+        }
+        transaction.commit()?;
+        
+        info!("🗂️  Indexed recording #{}: {}", id, stats.filename);
+        Ok(id)
+    }
+    
+    /// 按开始时间倒序列出录制
+    pub fn list(&self) -> Result<Vec<RecordingEntry>, AppError> {
+        self.search(&RecordingQuery::default())
+    }
+    
+    /// 按条件检索录制，开始时间倒序
+    pub fn search(&self, query: &RecordingQuery) -> Result<Vec<RecordingEntry>, AppError> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        
+        if let Some(text) = query.text.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+            conditions.push("(r.file_path LIKE ? ESCAPE '\\' OR r.stream_name LIKE ? ESCAPE '\\' \
+                             OR r.patient_code LIKE ? ESCAPE '\\' OR r.notes LIKE ? ESCAPE '\\')");
+            let pattern = format!("%{}%", escape_like(text));
+            values.extend(std::iter::repeat_n(Value::Text(pattern), 4));
+        }
+        for tag in &query.tags {
+            conditions.push("EXISTS (SELECT 1 FROM recording_tags t WHERE t.recording_id = r.id AND t.tag = ?)");
+            values.push(Value::Text(tag.trim().to_string()));
+        }
+        if let Some(after) = query.started_after {
+            conditions.push("r.start_time >= ?");
+            values.push(Value::Text(timestamp(&after)));
+        }
+        if let Some(before) = query.started_before {
+            conditions.push("r.start_time < ?");
+            values.push(Value::Text(timestamp(&before)));
+        }
+        
+        let mut sql = SELECT_COLUMNS.to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY r.start_time DESC, r.id DESC LIMIT ?");
+        values.push(Value::Integer(query.limit.unwrap_or(DEFAULT_LIMIT) as i64));
+        
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values), read_entry)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+    
+    pub fn get(&self, id: i64) -> Result<Option<RecordingEntry>, AppError> {
+        let connection = self.connection()?;
+        Ok(connection
+            .query_row(&format!("{} WHERE r.id = ?1", SELECT_COLUMNS), [id], read_entry)
+            .optional()?)
+    }
+    
+    /// 查找录制文件（任一分段）登记的 SHA-256，有多条时取最近登记的
+    pub fn find_checksum(&self, path: &str) -> Result<Option<String>, AppError> {
+        let connection = self.connection()?;
+        let checksum: Option<Option<String>> = connection
+            .query_row(
+                "SELECT json_extract(r.checksums, '$[' || s.key || ']')
+                 FROM recordings r, json_each(r.segments) s
+                 WHERE s.value = ?1
+                 ORDER BY r.id DESC LIMIT 1",
+                [path],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(checksum.flatten())
+    }
+    
+    /// 从索引中删除录制；delete_files 为 true 时同时删除录制文件及其旁路文件（注释、辅助流、时钟偏移、脚本输出），
+    /// 已不存在的文件忽略
+    pub fn delete(&self, id: i64, delete_files: bool) -> Result<RecordingEntry, AppError> {
+        let entry = self.get(id)?
+            .ok_or_else(|| AppError::Catalog(format!("No recording with id {}", id)))?;
+        
+        if delete_files {
+            for segment in &entry.segments {
+                match std::fs::remove_file(segment) {
+                    Ok(()) => info!("🗑️  Deleted {}", segment),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => warn!("⚠️ Recording file already missing: {}", segment),
+                    Err(e) => return Err(e.into()),
+                }
+                for sidecar in sidecar_files(segment)? {
+                    std::fs::remove_file(&sidecar)?;
+                    info!("🗑️  Deleted {}", sidecar.display());
+                }
+            }
+        }
+        
+        self.connection()?.execute("DELETE FROM recordings WHERE id = ?1", [id])?;
+        info!("🗂️  Removed recording #{} from catalog", id);
+        Ok(entry)
+    }
+}
+
+/// 录制文件旁已存在的旁路文件：CSV/TSV 注释文件、辅助流与时钟偏移CSV、各脚本的输出CSV
+fn sidecar_files(segment: &str) -> Result<Vec<PathBuf>, AppError> {
+    let mut candidates: Vec<PathBuf> = [
+        crate::delimited::annotations_path(segment),
+        EdfRecorder::sidecar_filename(segment, "aux"),
+        EdfRecorder::sidecar_filename(segment, "clock"),
+    ].into_iter().map(PathBuf::from).collect();
+    
+    let path = Path::new(segment);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let script_prefix = format!("{}_script_", stem);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match std::fs::read_dir(dir) {
+        Ok(entries) => {
+            for entry in entries {
+                let entry_path = entry?.path();
+                let name = entry_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                if name.starts_with(&script_prefix) && name.ends_with(".csv") {
+                    candidates.push(entry_path);
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    
+    candidates.retain(|candidate| candidate.as_path() != path && candidate.is_file());
+    Ok(candidates)
+}
+
+/// 升级旧版本创建的数据库
+fn migrate(connection: &Connection) -> Result<(), AppError> {
+    let has_checksums: bool = connection.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('recordings') WHERE name = 'checksums'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_checksums {
+        connection.execute_batch("ALTER TABLE recordings ADD COLUMN checksums TEXT NOT NULL DEFAULT '[]'")?;
+        info!("🗂️  Added checksums column to recording catalog");
+    }
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<String, AppError> {
+    serde_json::to_string(value).map_err(|e| AppError::Catalog(format!("Failed to serialize catalog field: {}", e)))
+}
+
+/// LIKE 模式中的 % _ 和转义符按字面匹配
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// 统一的时间文本格式，保证按字符串比较即按时间先后
+fn timestamp(time: &chrono::DateTime<chrono::Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+}
+
+/// 读取JSON文本列
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_str(&text).map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+fn read_entry(row: &Row) -> rusqlite::Result<RecordingEntry> {
+    let start_time: String = row.get(4)?;
+    let start_time = chrono::DateTime::parse_from_rfc3339(&start_time)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e)))?
+        .with_timezone(&chrono::Utc);
+    
+    Ok(RecordingEntry {
+        id: row.get(0)?,
+        file_path: row.get(1)?,
+        segments: json_column(row, 2)?,
+        format: json_column(row, 3)?,
+        start_time,
+        duration_seconds: row.get(5)?,
+        channels_count: row.get(6)?,
+        sample_rate: row.get(7)?,
+        channel_labels: json_column(row, 8)?,
+        stream_name: row.get(9)?,
+        stream_type: row.get(10)?,
+        source_id: row.get(11)?,
+        patient_code: row.get(12)?,
+        notes: row.get(13)?,
+        markers_count: row.get(14)?,
+        file_size_bytes: row.get(15)?,
+        checksums: json_column(row, 16)?,
+        tags: json_column(row, 17)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::RecordingStats;
+    
+    fn recording(filename: &str, start_time: &str, tags: &[&str], notes: Option<&str>) -> FinishedRecording {
+        FinishedRecording {
+            stats: RecordingStats {
+                filename: filename.to_string(),
+                format: RecordingFormat::Bdf,
+                segments: vec![filename.to_string()],
+                duration_seconds: 120.0,
+                samples_written: 30000,
+                annotations_written: 5,
+                markers_written: 3,
+                paused_seconds: 0.0,
+                channels_count: 2,
+                sample_rate: 250.0,
+                start_time: start_time.parse().unwrap(),
+                file_size_bytes: 4096,
+                checksums: vec![Some("ab".repeat(32))],
+                physical_ranges: Vec::new(),
+                clipped_samples: Vec::new(),
+            },
+            stream_info: StreamInfo {
+                name: "Cyton".to_string(),
+                stream_type: "EEG".to_string(),
+                channels_count: 2,
+                sample_rate: 250.0,
+                is_connected: true,
+                source_id: "cyton-01".to_string(),
+                channel_labels: vec!["C3".to_string(), "C4".to_string()],
+                effective_sample_rate: None,
+            },
+            metadata: SessionMetadata { notes: notes.map(str::to_string), ..SessionMetadata::default() },
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            annotations: Vec::new(),
+            sidecars: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_insert_search_delete() {
+        let dir = std::env::temp_dir().join(format!("cortexarray_catalog_{}", std::process::id()));
+        let catalog = RecordingCatalog::open(&dir.join(CATALOG_FILE)).unwrap();
+        
+        let resting = dir.join("resting.bdf");
+        std::fs::write(&resting, b"data").unwrap();
+        let sidecars = ["resting_aux.csv", "resting_clock.csv", "resting_script_alpha.csv", "resting_annotations.bdf"]
+            .map(|name| dir.join(name));
+        for sidecar in &sidecars {
+            std::fs::write(sidecar, b"time").unwrap();
+        }
+        let unrelated = dir.join("resting2_aux.csv");
+        std::fs::write(&unrelated, b"time").unwrap();
+        let first = catalog.insert(&recording(&resting.display().to_string(), "2024-03-05T10:00:00Z", &["rest", "pilot"], None)).unwrap();
+        let second = catalog.insert(&recording("/data/p300_100%.bdf", "2024-03-06T10:00:00Z", &["p300"], Some("Good signal"))).unwrap();
+        
+        let all = catalog.list().unwrap();
+        assert_eq!(all.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(all[1].tags, vec!["pilot".to_string(), "rest".to_string()]);
+        assert_eq!(all[1].channel_labels, vec!["C3".to_string(), "C4".to_string()]);
+        assert_eq!(all[1].format, RecordingFormat::Bdf);
+        assert_eq!(all[1].markers_count, 3);
+        assert_eq!(catalog.find_checksum("/data/p300_100%.bdf").unwrap(), Some("ab".repeat(32)));
+        assert_eq!(catalog.find_checksum("/data/missing.bdf").unwrap(), None);
+        
+        let search = |query: RecordingQuery| catalog.search(&query).unwrap().iter().map(|entry| entry.id).collect::<Vec<_>>();
+        assert_eq!(search(RecordingQuery { tags: vec!["rest".to_string()], ..Default::default() }), vec![first]);
+        assert_eq!(search(RecordingQuery { text: Some("good".to_string()), ..Default::default() }), vec![second]);
+        assert_eq!(search(RecordingQuery { text: Some("0%".to_string()), ..Default::default() }), vec![second]);
+        assert_eq!(search(RecordingQuery { started_after: Some("2024-03-06T00:00:00Z".parse().unwrap()), ..Default::default() }), vec![second]);
+        assert_eq!(search(RecordingQuery { limit: Some(1), ..Default::default() }), vec![second]);
+        
+        let deleted = catalog.delete(first, true).unwrap();
+        assert_eq!(deleted.id, first);
+        assert!(!resting.exists());
+        assert!(sidecars.iter().all(|sidecar| !sidecar.exists()));
+        assert!(unrelated.exists());
+        assert!(catalog.get(first).unwrap().is_none());
+        assert!(catalog.delete(first, false).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+use nalgebra::{DMatrix, DVector};
+use crate::data_types::*;
+use crate::error::AppError;
+
+/// 单试次分类器接口 - 二分类（目标/非目标），输入为分段特征向量
+pub trait EpochClassifier: Send {
+    fn name(&self) -> &'static str;
+    
+    fn train(&mut self, features: &[Vec<f64>], targets: &[bool]) -> Result<(), AppError>;
+    
+    /// 属于目标类的概率，未训练或特征维度不符时返回 None
+    fn predict(&self, features: &[f64]) -> Option<f64>;
+}
+
+/// 收缩LDA - 合并类内协方差向 (迹/维度)·I 收缩，适合样本数少于特征维度的ERP数据
+///
+/// 判别值 w·x + b 在等协方差高斯假设下即为等先验的对数几率，经 sigmoid 得到概率。
+pub struct LdaClassifier {
+    shrinkage: f64,
+    weights: Option<DVector<f64>>,
+    bias: f64,
+}
+
+impl LdaClassifier {
+    pub fn new(shrinkage: f64) -> Self {
+        Self {
+            shrinkage,
+            weights: None,
+            bias: 0.0,
+        }
+    }
+}
+
+impl EpochClassifier for LdaClassifier {
+    fn name(&self) -> &'static str {
+        "lda"
+    }
+    
+    fn train(&mut self, features: &[Vec<f64>], targets: &[bool]) -> Result<(), AppError> {
+        let dimension = features.first().map_or(0, |row| row.len());
+        if dimension == 0 || features.len() != targets.len() || features.iter().any(|row| row.len() != dimension) {
+            return Err(AppError::Config("Inconsistent training features (channel layout changed?)".to_string()));
+        }
+        
+        let target_count = targets.iter().filter(|&&target| target).count();
+        if target_count < 2 || features.len() - target_count < 2 {
+            return Err(AppError::Config(format!(
+                "LDA needs at least 2 epochs of each class, got {} target / {} non-target",
+                target_count, features.len() - target_count
+            )));
+        }
+        
+        let class_mean = |class: bool| {
+            let rows: Vec<&Vec<f64>> = features.iter().zip(targets).filter(|(_, &t)| t == class).map(|(row, _)| row).collect();
+            rows.iter().fold(DVector::zeros(dimension), |sum, row| sum + DVector::from_column_slice(row)) / rows.len() as f64
+        };
+        let target_mean = class_mean(true);
+        let non_target_mean = class_mean(false);
+        
+        let mut covariance = DMatrix::zeros(dimension, dimension);
+        for (row, &target) in features.iter().zip(targets) {
+            let centered = DVector::from_column_slice(row) - if target { &target_mean } else { &non_target_mean };
+            covariance += &centered * centered.transpose();
+        }
+        covariance /= (features.len() - 2) as f64;
+        
+        let scale = covariance.trace() / dimension as f64;
+        let shrunk = covariance * (1.0 - self.shrinkage) + DMatrix::identity(dimension, dimension) * (self.shrinkage * scale);
+        
+        let difference = &target_mean - &non_target_mean;
+        let weights = match shrunk.clone().cholesky() {
+            Some(cholesky) => cholesky.solve(&difference),
+            // 不收缩且奇异时退回伪逆
+            None => shrunk.pseudo_inverse(1e-10)
+                .map_err(|e| AppError::Config(format!("LDA covariance is singular: {}", e)))? * difference,
+        };
+        
+        self.bias = -weights.dot(&(&target_mean + &non_target_mean)) / 2.0;
+        self.weights = Some(weights);
+        Ok(())
+    }
+    
+    fn predict(&self, features: &[f64]) -> Option<f64> {
+        let weights = self.weights.as_ref()?;
+        if weights.len() != features.len() {
+            return None;
+        }
+        let score = weights.dot(&DVector::from_column_slice(features)) + self.bias;
+        Some(1.0 / (1.0 + (-score).exp()))
+    }
+}
+
+/// 分段特征：标记后 [start, end) 区间内各选定通道按 bin_ms 分箱的均值，数据不足时返回 None
+pub fn epoch_features(epoch: &Epoch, config: &ClassifierConfig) -> Option<Vec<f64>> {
+    let sample_rate = epoch.sample_rate;
+    let onset = (epoch.pre_seconds * sample_rate).round() as usize;
+    let start = onset + (config.start_seconds * sample_rate).round() as usize;
+    let end = onset + (config.end_seconds * sample_rate).round() as usize;
+    let bin = ((config.bin_ms as f64 / 1000.0 * sample_rate).round() as usize).max(1);
+    
+    let mut features = Vec::new();
+    for (label, channel) in epoch.channel_labels.iter().zip(&epoch.data) {
+        if !config.channels.is_empty() && !config.channels.iter().any(|name| name.eq_ignore_ascii_case(label)) {
+            continue;
+        }
+        if channel.len() < end || start >= end {
+            return None;
+        }
+        features.extend(channel[start..end].chunks(bin)
+            .map(|chunk| chunk.iter().map(|&value| value as f64).sum::<f64>() / chunk.len() as f64));
+    }
+    
+    (!features.is_empty()).then_some(features)
+}
+
+/// 分类会话 - 训练集、模型与在线状态
+///
+/// 分发器在每个分段完成时调用 on_epoch；训练与在线切换由命令触发。
+/// 影响特征的配置变化时清空训练集，模型需重新训练。
+pub struct ClassifierSession {
+    config: ClassifierConfig,
+    training: VecDeque<(Vec<f64>, bool)>,
+    model: Box<dyn EpochClassifier>,
+    trained: bool,
+    training_accuracy: Option<f64>,
+    online: bool,
+}
+
+impl Default for ClassifierSession {
+    fn default() -> Self {
+        Self::new(ClassifierConfig::default())
+    }
+}
+
+impl ClassifierSession {
+    pub fn new(config: ClassifierConfig) -> Self {
+        Self {
+            model: Box::new(LdaClassifier::new(config.shrinkage)),
+            config,
+            training: VecDeque::new(),
+            trained: false,
+            training_accuracy: None,
+            online: false,
+        }
+    }
+    
+    pub fn config(&self) -> &ClassifierConfig {
+        &self.config
+    }
+    
+    pub fn set_config(&mut self, config: ClassifierConfig) {
+        let features_changed = config.target_markers != self.config.target_markers
+            || config.non_target_markers != self.config.non_target_markers
+            || config.channels != self.config.channels
+            || config.start_seconds != self.config.start_seconds
+            || config.end_seconds != self.config.end_seconds
+            || config.bin_ms != self.config.bin_ms;
+        
+        if features_changed {
+            self.training.clear();
+        }
+        if features_changed || config.shrinkage != self.config.shrinkage {
+            self.model = Box::new(LdaClassifier::new(config.shrinkage));
+            self.trained = false;
+            self.training_accuracy = None;
+            self.online = false;
+        }
+        while self.training.len() > config.max_training_epochs {
+            self.training.pop_front();
+        }
+        self.config = config;
+    }
+    
+    /// 标记对应的真实类别，未配置的标记返回 None
+    fn expected_target(&self, label: &str) -> Option<bool> {
+        if self.config.target_markers.iter().any(|marker| marker == label) {
+            Some(true)
+        } else if self.config.non_target_markers.iter().any(|marker| marker == label)
+            || (self.config.non_target_markers.is_empty() && !self.config.target_markers.is_empty())
+        {
+            Some(false)
+        } else {
+            None
+        }
+    }
+    
+    /// 处理一个完成的分段：加入训练集，在线时返回预测
+    pub fn on_epoch(&mut self, epoch: &Epoch) -> Option<ClassifierPrediction> {
+        if epoch.rejected {
+            return None;
+        }
+        let features = epoch_features(epoch, &self.config)?;
+        let expected_target = self.expected_target(&epoch.label);
+        
+        let prediction = if self.online {
+            self.model.predict(&features).map(|probability| ClassifierPrediction {
+                label: epoch.label.clone(),
+                marker_timestamp: epoch.marker_timestamp,
+                probability,
+                is_target: probability >= 0.5,
+                expected_target,
+            })
+        } else {
+            None
+        };
+        
+        if let (true, Some(target)) = (self.config.collect_training, expected_target) {
+            if self.training.len() == self.config.max_training_epochs {
+                self.training.pop_front();
+            }
+            self.training.push_back((features, target));
+        }
+        
+        prediction
+    }
+    
+    /// 用当前训练集训练模型，并报告训练集准确率
+    pub fn train(&mut self) -> Result<ClassifierStatus, AppError> {
+        let (features, targets): (Vec<Vec<f64>>, Vec<bool>) = self.training.iter().cloned().unzip();
+        self.model.train(&features, &targets)?;
+        
+        let correct = features.iter()
+            .zip(&targets)
+            .filter(|(row, &target)| self.model.predict(row).is_some_and(|probability| (probability >= 0.5) == target))
+            .count();
+        self.trained = true;
+        self.training_accuracy = Some(correct as f64 / features.len() as f64);
+        
+        Ok(self.status())
+    }
+    
+    pub fn set_online(&mut self, enabled: bool) -> Result<ClassifierStatus, AppError> {
+        if enabled && !self.trained {
+            return Err(AppError::Config("Classifier is not trained".to_string()));
+        }
+        self.online = enabled;
+        Ok(self.status())
+    }
+    
+    pub fn status(&self) -> ClassifierStatus {
+        ClassifierStatus {
+            classifier: self.model.name().to_string(),
+            training_epochs: self.training.len(),
+            target_epochs: self.training.iter().filter(|(_, target)| *target).count(),
+            trained: self.trained,
+            training_accuracy: self.training_accuracy,
+            online: self.online,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn epoch(label: &str, amplitude: f32, noise: &mut impl FnMut() -> f32) -> Epoch {
+        // 100Hz，标记前0.2秒、标记后0.8秒；目标在300ms附近有正波
+        let data = (0..2)
+            .map(|_| (0..100).map(|n| {
+                let t = (n as f32 - 20.0) / 100.0;
+                amplitude * (-((t - 0.3) / 0.08).powi(2)).exp() + noise()
+            }).collect())
+            .collect();
+        Epoch {
+            label: label.to_string(),
+            marker_timestamp: 0.0,
+            sample_id: 0,
+            sample_rate: 100.0,
+            pre_seconds: 0.2,
+            channel_labels: vec!["Cz".to_string(), "Pz".to_string()],
+            data,
+            rejected: false,
+            reject_reason: None,
+        }
+    }
+    
+    #[test]
+    fn test_lda_separates_target_epochs() {
+        let config = ClassifierConfig { target_markers: vec!["target".to_string()], ..ClassifierConfig::default() };
+        let mut session = ClassifierSession::new(config);
+        
+        let mut seed = 3u64;
+        let mut noise = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5) as f32 * 8.0
+        };
+        
+        // 未训练时不能在线分类
+        assert!(session.set_online(true).is_err());
+        
+        for trial in 0..60 {
+            let target = trial % 4 == 0;
+            let label = if target { "target" } else { "other" };
+            assert!(session.on_epoch(&epoch(label, if target { 5.0 } else { 0.0 }, &mut noise)).is_none());
+        }
+        
+        // 0.1-0.8秒、50ms分箱：每通道14个特征
+        assert_eq!(epoch_features(&epoch("x", 0.0, &mut noise), session.config()).unwrap().len(), 28);
+        
+        let status = session.train().unwrap();
+        assert_eq!((status.training_epochs, status.target_epochs), (60, 15));
+        assert!(status.training_accuracy.unwrap() > 0.9);
+        
+        session.set_online(true).unwrap();
+        let target = session.on_epoch(&epoch("target", 5.0, &mut noise)).unwrap();
+        let other = session.on_epoch(&epoch("other", 0.0, &mut noise)).unwrap();
+        assert!(target.is_target && target.probability > 0.9);
+        assert!(!other.is_target && other.expected_target == Some(false));
+        assert_eq!(session.status().training_epochs, 62);
+    }
+}
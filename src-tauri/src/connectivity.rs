@@ -0,0 +1,206 @@
+use rustfft::num_complex::Complex;
+use tracing::warn;
+use crate::data_types::*;
+
+/// 单个频带的互谱累计（通道对按上三角顺序存放）
+struct BandAccumulator {
+    name: String,
+    low_hz: f64,
+    high_hz: f64,
+    cross: Vec<Complex<f64>>,       // Σ X·conj(Y)
+    auto: Vec<f64>,                 // Σ |X|²
+    phase: Vec<Complex<f64>>,       // Σ e^{i(φx-φy)}
+    phase_count: u64,
+}
+
+/// 通道间连接性 - 按间隔累计各FFT帧的互谱，输出幅值平方相干与可选的锁相值
+///
+/// 相干在频带内的所有bin与间隔内的所有帧上共同平均，帧数太少时估计偏高。
+/// 通道数或频带定义变化时由调用方重建。
+pub struct ConnectivityAccumulator {
+    config: ConnectivityConfig,
+    channels: usize,
+    bands: Vec<BandAccumulator>,
+    frames: u32,
+    started: Option<f64>,
+}
+
+impl ConnectivityAccumulator {
+    pub fn new(config: ConnectivityConfig, bands: &FrequencyBands, channels: usize) -> Self {
+        let pairs = channels * channels.saturating_sub(1) / 2;
+        let selected = bands.0.iter()
+            .filter(|band| config.bands.is_empty()
+                || config.bands.iter().any(|name| name.eq_ignore_ascii_case(&band.name)))
+            .map(|band| BandAccumulator {
+                name: band.name.clone(),
+                low_hz: band.low_hz,
+                high_hz: band.high_hz,
+                cross: vec![Complex::new(0.0, 0.0); pairs],
+                auto: vec![0.0; channels],
+                phase: vec![Complex::new(0.0, 0.0); pairs],
+                phase_count: 0,
+            })
+            .collect::<Vec<_>>();
+        
+        if selected.len() < config.bands.len() {
+            warn!("⚠️ Connectivity: some requested bands {:?} are not defined", config.bands);
+        }
+        
+        Self {
+            config,
+            channels,
+            bands: selected,
+            frames: 0,
+            started: None,
+        }
+    }
+    
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+    
+    /// 加入一帧复数谱（spectra[通道][bin]，bin k 对应 k·freq_resolution）
+    pub fn push(&mut self, timestamp: f64, spectra: &[Vec<Complex<f64>>], freq_resolution: f64) {
+        if spectra.len() != self.channels || freq_resolution <= 0.0 {
+            return;
+        }
+        self.started.get_or_insert(timestamp);
+        self.frames += 1;
+        
+        let include_plv = self.config.include_plv;
+        for band in &mut self.bands {
+            let bins_count = spectra[0].len();
+            let first_bin = ((band.low_hz / freq_resolution).ceil() as usize).min(bins_count);
+            let end_bin = (first_bin..bins_count)
+                .find(|&k| (k as f64) * freq_resolution >= band.high_hz)
+                .unwrap_or(bins_count);
+            let bins = first_bin..end_bin;
+            
+            for (auto, spectrum) in band.auto.iter_mut().zip(spectra) {
+                *auto += spectrum[bins.clone()].iter().map(|value| value.norm_sqr()).sum::<f64>();
+            }
+            
+            let mut pair = 0;
+            for i in 0..self.channels {
+                for j in i + 1..self.channels {
+                    for (x, y) in spectra[i][bins.clone()].iter().zip(&spectra[j][bins.clone()]) {
+                        let cross = x * y.conj();
+                        band.cross[pair] += cross;
+                        if include_plv {
+                            let magnitude = cross.norm();
+                            if magnitude > 0.0 {
+                                band.phase[pair] += cross / magnitude;
+                            }
+                        }
+                    }
+                    pair += 1;
+                }
+            }
+            band.phase_count += bins.len() as u64;
+        }
+    }
+    
+    /// 到输出间隔时返回各频带矩阵并开始新的累计
+    pub fn poll(&mut self, timestamp: f64, batch_id: u64) -> Option<ConnectivityUpdate> {
+        let started = self.started?;
+        if timestamp - started < self.config.update_interval_ms as f64 / 1000.0 {
+            return None;
+        }
+        
+        let channels = self.channels;
+        let bands = self.bands.iter_mut()
+            .map(|band| {
+                let coherence = symmetric_matrix(channels, |pair, i, j| {
+                    let denominator = band.auto[i] * band.auto[j];
+                    if denominator > 0.0 { band.cross[pair].norm_sqr() / denominator } else { 0.0 }
+                });
+                let plv = self.config.include_plv.then(|| symmetric_matrix(channels, |pair, _, _| {
+                    if band.phase_count > 0 { band.phase[pair].norm() / band.phase_count as f64 } else { 0.0 }
+                }));
+                
+                band.cross.iter_mut().for_each(|value| *value = Complex::new(0.0, 0.0));
+                band.phase.iter_mut().for_each(|value| *value = Complex::new(0.0, 0.0));
+                band.auto.iter_mut().for_each(|value| *value = 0.0);
+                band.phase_count = 0;
+                
+                ConnectivityBand { name: band.name.clone(), coherence, plv }
+            })
+            .collect();
+        
+        let update = ConnectivityUpdate {
+            batch_id,
+            timestamp,
+            channels_count: channels as u32,
+            frames: self.frames,
+            bands,
+        };
+        self.frames = 0;
+        self.started = None;
+        
+        Some(update)
+    }
+}
+
+/// 由上三角通道对的值构建对称矩阵，对角线为1
+fn symmetric_matrix(channels: usize, value: impl Fn(usize, usize, usize) -> f64) -> Vec<Vec<f32>> {
+    (0..channels)
+        .map(|row| (0..channels)
+            .map(|column| {
+                if row == column {
+                    return 1.0;
+                }
+                let (i, j) = (row.min(column), row.max(column));
+                // 上三角按行展开的通道对编号
+                let pair = i * (2 * channels - i - 1) / 2 + (j - i - 1);
+                value(pair, i, j) as f32
+            })
+            .collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_coherent_pair_versus_independent_noise() {
+        let config = ConnectivityConfig { enabled: true, include_plv: true, ..ConnectivityConfig::default() };
+        let bands = FrequencyBands(vec![FrequencyBand::new("alpha", 8.0, 13.0)]);
+        let mut accumulator = ConnectivityAccumulator::new(config, &bands, 3);
+        
+        // 通道0、1共享同一随机相位（固定相位差），通道2相位独立
+        let mut seed = 11u64;
+        let mut random_phase = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64 * std::f64::consts::TAU
+        };
+        // 8-12Hz 之外的bin为零
+        let spectrum = |band_values: Vec<Complex<f64>>| {
+            let mut spectrum = vec![Complex::new(0.0, 0.0); 8];
+            spectrum.extend(band_values);
+            spectrum.resize(20, Complex::new(0.0, 0.0));
+            spectrum
+        };
+        for frame in 0..50 {
+            let phases: Vec<(f64, f64)> = (0..5).map(|_| (random_phase(), random_phase())).collect();
+            let spectra = vec![
+                spectrum(phases.iter().map(|&(shared, _)| Complex::from_polar(2.0, shared)).collect()),
+                spectrum(phases.iter().map(|&(shared, _)| Complex::from_polar(1.0, shared + 0.7)).collect()),
+                spectrum(phases.iter().map(|&(_, independent)| Complex::from_polar(1.5, independent)).collect()),
+            ];
+            accumulator.push(frame as f64 * 0.02, &spectra, 1.0);
+        }
+        
+        let update = accumulator.poll(1.0, 7).unwrap();
+        assert_eq!(update.frames, 50);
+        let alpha = &update.bands[0];
+        assert!(alpha.coherence[0][1] > 0.99);
+        assert!(alpha.coherence[0][2] < 0.1, "coherence {}", alpha.coherence[0][2]);
+        assert_eq!(alpha.coherence[2][0], alpha.coherence[0][2]);
+        let plv = alpha.plv.as_ref().unwrap();
+        assert!(plv[1][0] > 0.99 && plv[1][2] < 0.2);
+        
+        // 输出后重新累计
+        assert!(accumulator.poll(2.0, 8).is_none());
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use nalgebra::{DMatrix, DVector};
+use crate::asr::sorted_eigen;
+use crate::data_types::*;
+use crate::error::AppError;
+
+/// 为CSP训练保留的最近分段数
+const STORE_CAPACITY: usize = 400;
+
+/// CSP训练用分段缓存 - 分发器加入每个未被拒绝的分段，训练时按标记挑选两类
+pub struct CspEpochStore {
+    epochs: VecDeque<Epoch>,
+}
+
+impl Default for CspEpochStore {
+    fn default() -> Self {
+        Self { epochs: VecDeque::with_capacity(STORE_CAPACITY) }
+    }
+}
+
+impl CspEpochStore {
+    pub fn push(&mut self, epoch: &Epoch) {
+        if epoch.rejected {
+            return;
+        }
+        if self.epochs.len() == STORE_CAPACITY {
+            self.epochs.pop_front();
+        }
+        self.epochs.push_back(epoch.clone());
+    }
+    
+    /// 用最近通道布局下的两类分段训练CSP
+    pub fn train(&self, request: &CspTrainingRequest) -> Result<CspResult, AppError> {
+        let labels = &self.epochs.back()
+            .ok_or_else(|| AppError::Config("No epochs collected (is epoching enabled?)".to_string()))?
+            .channel_labels;
+        let class = |markers: &[String]| -> Vec<&Epoch> {
+            self.epochs.iter()
+                .filter(|epoch| &epoch.channel_labels == labels && markers.contains(&epoch.label))
+                .collect()
+        };
+        train_csp(&class(&request.class_a), &class(&request.class_b), request)
+    }
+}
+
+/// 共空间模式：白化两类平均协方差之和后，对A类协方差做特征分解
+///
+/// 成分按特征值降序排列，两端各取 components_per_class 个：前端A类方差最大，后端B类方差最大。
+/// 复合协方差中接近零的方向（如共平均参考造成的秩亏）在白化时丢弃。
+pub fn train_csp(class_a: &[&Epoch], class_b: &[&Epoch], request: &CspTrainingRequest) -> Result<CspResult, AppError> {
+    if class_a.len() < 2 || class_b.len() < 2 {
+        return Err(AppError::Config(format!(
+            "CSP needs at least 2 epochs per class, got {} / {}", class_a.len(), class_b.len()
+        )));
+    }
+    let labels = class_a[0].channel_labels.clone();
+    let channels = labels.len();
+    if channels < 2 {
+        return Err(AppError::Config("CSP needs at least 2 channels".to_string()));
+    }
+    
+    let class_covariance = |epochs: &[&Epoch]| -> Result<DMatrix<f64>, AppError> {
+        let mut sum = DMatrix::zeros(channels, channels);
+        for epoch in epochs {
+            sum += trial_covariance(epoch, request)
+                .ok_or_else(|| AppError::Config("CSP window exceeds epoch length".to_string()))?;
+        }
+        Ok(sum / epochs.len() as f64)
+    };
+    let covariance_a = class_covariance(class_a)?;
+    let covariance_b = class_covariance(class_b)?;
+    
+    let (values, vectors) = sorted_eigen(&covariance_a + &covariance_b);
+    let largest = values.max();
+    let kept: Vec<usize> = (0..channels).filter(|&i| values[i] > largest * 1e-10).collect();
+    if kept.len() < 2 {
+        return Err(AppError::Config("CSP covariance is rank deficient".to_string()));
+    }
+    let whitening = DMatrix::from_rows(&kept.iter()
+        .map(|&i| vectors.column(i).transpose() / values[i].sqrt())
+        .collect::<Vec<_>>());
+    
+    let (eigenvalues, rotation) = sorted_eigen(&whitening * covariance_a * whitening.transpose());
+    let filters = rotation.transpose() * &whitening;
+    
+    // 升序特征值：A类成分在末尾
+    let dimension = kept.len();
+    let per_class = request.components_per_class.min(dimension / 2);
+    let selected: Vec<usize> = (0..per_class).map(|k| dimension - 1 - k)
+        .chain((0..per_class).rev())
+        .collect();
+    
+    Ok(CspResult {
+        filter: SpatialFilter {
+            input_labels: labels,
+            output_labels: (1..=selected.len()).map(|k| format!("CSP{}", k)).collect(),
+            weights: selected.iter().map(|&i| filters.row(i).iter().copied().collect()).collect(),
+        },
+        eigenvalues: selected.iter().map(|&i| eigenvalues[i]).collect(),
+        epochs_a: class_a.len(),
+        epochs_b: class_b.len(),
+    })
+}
+
+/// 单个分段窗口的迹归一化协方差
+fn trial_covariance(epoch: &Epoch, request: &CspTrainingRequest) -> Option<DMatrix<f64>> {
+    let onset = (epoch.pre_seconds * epoch.sample_rate).round() as usize;
+    let start = onset + (request.start_seconds * epoch.sample_rate).round() as usize;
+    let end = onset + (request.end_seconds * epoch.sample_rate).round() as usize;
+    if epoch.data.iter().any(|channel| channel.len() < end) || end <= start + 1 {
+        return None;
+    }
+    
+    let rows: Vec<_> = epoch.data.iter()
+        .map(|channel| {
+            let segment = DVector::from_iterator(end - start, channel[start..end].iter().map(|&v| v as f64));
+            segment.add_scalar(-segment.mean()).transpose()
+        })
+        .collect();
+    let data = DMatrix::from_rows(&rows);
+    let covariance = &data * data.transpose();
+    let trace = covariance.trace();
+    (trace > 0.0).then(|| covariance / trace)
+}
+
+/// 将一个样本替换为空间滤波输出
+pub fn apply_spatial_filter(filter: &SpatialFilter, channels: &mut Vec<f64>) {
+    let output = filter.weights.iter()
+        .map(|row| row.iter().zip(channels.iter()).map(|(w, x)| w * x).sum())
+        .collect();
+    *channels = output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn epoch(label: &str, gains: [f32; 3], noise: &mut impl FnMut() -> f32) -> Epoch {
+        let samples: Vec<[f32; 3]> = (0..300).map(|_| [noise(), noise(), noise()]).collect();
+        Epoch {
+            label: label.to_string(),
+            marker_timestamp: 0.0,
+            sample_id: 0,
+            sample_rate: 100.0,
+            pre_seconds: 0.5,
+            channel_labels: vec!["C3".to_string(), "C4".to_string(), "Cz".to_string()],
+            data: (0..3).map(|ch| samples.iter().map(|s| s[ch] * gains[ch]).collect()).collect(),
+            rejected: false,
+            reject_reason: None,
+        }
+    }
+    
+    #[test]
+    fn test_csp_separates_class_variance() {
+        let mut seed = 9u64;
+        let mut noise = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 11) as f32 / (1u64 << 53) as f32 - 0.5
+        };
+        
+        // A类 C3 方差大，B类 C4 方差大
+        let mut store = CspEpochStore::default();
+        for trial in 0..20 {
+            store.push(&epoch("left", [3.0, 1.0, 1.0], &mut noise));
+            store.push(&epoch("right", [1.0, 3.0, 1.0], &mut noise));
+            if trial == 0 {
+                store.push(&epoch("rest", [1.0, 1.0, 1.0], &mut noise));
+            }
+        }
+        
+        let request = CspTrainingRequest {
+            class_a: vec!["left".to_string()],
+            class_b: vec!["right".to_string()],
+            start_seconds: 0.0,
+            end_seconds: 2.0,
+            components_per_class: 1,
+        };
+        let result = store.train(&request).unwrap();
+        assert_eq!((result.epochs_a, result.epochs_b), (20, 20));
+        assert_eq!(result.filter.output_labels, ["CSP1", "CSP2"]);
+        assert!(result.eigenvalues[0] > 0.8 && result.eigenvalues[1] < 0.2, "{:?}", result.eigenvalues);
+        
+        // 第一个成分以C3为主，第二个以C4为主
+        let dominant = |row: &Vec<f64>| (0..3).max_by(|&a, &b| row[a].abs().total_cmp(&row[b].abs())).unwrap();
+        assert_eq!(dominant(&result.filter.weights[0]), 0);
+        assert_eq!(dominant(&result.filter.weights[1]), 1);
+        
+        let mut sample = vec![1.0, 2.0, 3.0];
+        apply_spatial_filter(&result.filter, &mut sample);
+        assert_eq!(sample.len(), 2);
+    }
+}
@@ -1,52 +1,3464 @@
 use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use std::time::Duration;
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LslStreamInfo {
+    pub name: String,
+    pub stream_type: String,
+    pub channels_count: u32,
+    pub sample_rate: f64,
+    pub source_id: String,
+    pub hostname: String,
+}
+
+/// 要连接的流 - 名称必填；同名设备以 source_id（及主机名）区分，解析时组合为一个谓词
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct StreamSelector {
+    pub name: String,
+    pub source_id: Option<String>,
+    pub hostname: Option<String>,
+}
+
+impl StreamSelector {
+    fn field(value: &Option<String>) -> Option<&str> {
+        value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+    }
+    
+    /// 是否指定了 source_id（同名流中的唯一设备）
+    pub fn has_source_id(&self) -> bool {
+        Self::field(&self.source_id).is_some()
+    }
+    
+    /// LSL解析谓词（XPath 1.0），如 name='EEG' and source_id='amp-1'
+    pub fn predicate(&self) -> String {
+        let mut terms = vec![format!("name={}", xpath_literal(&self.name))];
+        if let Some(source_id) = Self::field(&self.source_id) {
+            terms.push(format!("source_id={}", xpath_literal(source_id)));
+        }
+        if let Some(hostname) = Self::field(&self.hostname) {
+            terms.push(format!("hostname={}", xpath_literal(hostname)));
+        }
+        terms.join(" and ")
+    }
+    
+    /// 未指定会话标识时的默认值：指定了 source_id 时附加，避免同名设备替换彼此的会话
+    pub fn default_session_id(&self) -> String {
+        match Self::field(&self.source_id) {
+            Some(source_id) => format!("{}_{}", self.name, source_id),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl From<LslStreamInfo> for StreamSelector {
+    fn from(stream: LslStreamInfo) -> Self {
+        Self {
+            name: stream.name,
+            source_id: Some(stream.source_id),
+            hostname: Some(stream.hostname),
+        }
+    }
+}
+
+/// XPath字符串字面量：XPath 1.0 没有转义，含单引号时改用双引号，两者都有时用 concat()
+fn xpath_literal(value: &str) -> String {
+    if !value.contains('\'') {
+        format!("'{}'", value)
+    } else if !value.contains('"') {
+        format!("\"{}\"", value)
+    } else {
+        let parts: Vec<String> = value.split('\'').map(|part| format!("'{}'", part)).collect();
+        format!("concat({})", parts.join(", \"'\", "))
+    }
+}
+
+/// 流发现过滤条件 - 各项均不区分大小写，未设置的项不过滤（全部为空时返回所有流）
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct StreamFilter {
+    pub stream_type: Option<String>,    // 如 EEG、EMG、ECG、Markers
+    pub name_pattern: Option<String>,   // 支持 * 与 ? 通配符
+    pub hostname: Option<String>,
+}
+
+impl StreamFilter {
+    pub fn matches(&self, stream: &LslStreamInfo) -> bool {
+        fn set(value: &Option<String>) -> Option<&str> {
+            value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+        }
+        
+        set(&self.stream_type).is_none_or(|stream_type| stream_type.eq_ignore_ascii_case(stream.stream_type.trim()))
+            && set(&self.hostname).is_none_or(|hostname| hostname.eq_ignore_ascii_case(stream.hostname.trim()))
+            && set(&self.name_pattern).is_none_or(|pattern| wildcard_match(
+                &pattern.to_lowercase().chars().collect::<Vec<_>>(),
+                &stream.name.to_lowercase().chars().collect::<Vec<_>>(),
+            ))
+    }
+}
+
+/// 通配符匹配：* 匹配任意长度，? 匹配单个字符
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((&expected, rest)) => text.split_first()
+            .is_some_and(|(&actual, remaining)| (expected == '?' || expected == actual) && wildcard_match(rest, remaining)),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StreamInfo {
+    pub name: String,
+    pub stream_type: String,
+    pub channels_count: u32,
+    pub sample_rate: f64,
+    pub is_connected: bool,
+    pub source_id: String,
+    #[serde(default)]
+    pub channel_labels: Vec<String>,   // 来自LSL流描述，缺失时为 Ch1..ChN
+    #[serde(default)]
+    pub effective_sample_rate: Option<f64>,  // 按LSL时间戳估计的实际采样率，窗口未满时为None
+}
+
+impl StreamInfo {
+    /// 生成默认通道标签 Ch1..ChN
+    pub fn default_channel_labels(channels_count: u32) -> Vec<String> {
+        (1..=channels_count).map(|i| format!("Ch{}", i)).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EegSample {
+    pub timestamp: f64,
+    pub channels: Vec<f64>,
+    pub sample_id: u64,
+}
+
+/// 录制文件格式 - EDF+ 为16位，BDF+ 为24位，GDF 为 float32，CSV/TSV 为纯文本，
+/// HDF5 需启用 `hdf5` feature
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    #[default]
+    Edf,
+    Bdf,
+    Gdf,
+    Csv,
+    Tsv,
+    Hdf5,
+}
+
+impl RecordingFormat {
+    /// 按文件扩展名推断格式
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "edf" => Some(Self::Edf),
+            "bdf" => Some(Self::Bdf),
+            "gdf" => Some(Self::Gdf),
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            "h5" | "hdf5" => Some(Self::Hdf5),
+            _ => None,
+        }
+    }
+    
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Edf => "edf",
+            Self::Bdf => "bdf",
+            Self::Gdf => "gdf",
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+            Self::Hdf5 => "h5",
+        }
+    }
+}
+
+/// 录制文件命名 - start_recording 未指定文件名时按模板生成；文件已存在时追加序号
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RecordingNamingConfig {
+    pub template: String,               // 可含子目录，如 "{subject}/{date}_{time}_{stream}.{ext}"
+    pub overwrite: bool,                // 允许覆盖已存在的文件
+}
+
+impl Default for RecordingNamingConfig {
+    fn default() -> Self {
+        Self {
+            template: "{subject}_{date}_{time}_{stream}.{ext}".to_string(),
+            overwrite: false,
+        }
+    }
+}
+
+impl RecordingNamingConfig {
+    /// 受试者编号、本地日期（YYYYMMDD）与时间（HHMMSS）、流名称、格式扩展名
+    pub const TOKENS: [&'static str; 5] = ["subject", "date", "time", "stream", "ext"];
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.template.trim().is_empty() {
+            return Err(AppError::Config("Recording filename template cannot be empty".to_string()));
+        }
+        Self::expand(&self.template, |_| Some(String::new())).map(|_| ())
+    }
+    
+    /// 按模板生成文件名；替换值中的路径分隔符等字符替换为下划线
+    pub fn filename(
+        &self,
+        subject: Option<&str>,
+        stream: &str,
+        format: RecordingFormat,
+        started: chrono::DateTime<chrono::Local>,
+    ) -> Result<String, AppError> {
+        Self::expand(&self.template, |token| {
+            let value = match token {
+                "subject" => subject.unwrap_or_default().to_string(),
+                "date" => started.format("%Y%m%d").to_string(),
+                "time" => started.format("%H%M%S").to_string(),
+                "stream" => stream.to_string(),
+                "ext" => format.extension().to_string(),
+                _ => return None,
+            };
+            let value: String = value.trim().chars()
+                .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') { c } else { '_' })
+                .collect();
+            let value = value.trim_matches('.');
+            Some(if value.is_empty() { "unknown".to_string() } else { value.to_string() })
+        })
+    }
+    
+    fn expand(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> Result<String, AppError> {
+        let mut expanded = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            expanded.push_str(&rest[..start]);
+            let end = rest[start..].find('}')
+                .ok_or_else(|| AppError::Config(format!("Unclosed '{{' in filename template '{}'", template)))?;
+            let token = &rest[start + 1..start + end];
+            let replacement = Self::TOKENS.contains(&token).then(|| value(token)).flatten()
+                .ok_or_else(|| AppError::Config(format!(
+                    "Unknown filename token '{{{}}}', expected one of: {}", token, Self::TOKENS.join(", ")
+                )))?;
+            expanded.push_str(&replacement);
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(AppError::Config(format!("Unmatched '}}' in filename template '{}'", template)));
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+/// 录制文件自动分段 - 达到任一上限时切换到下一个文件（session_001.edf, session_002.edf ...）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct RotationConfig {
+    pub max_minutes: Option<f64>,
+    pub max_megabytes: Option<f64>,
+}
+
+impl RotationConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.max_minutes.is_some() || self.max_megabytes.is_some()
+    }
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        for (name, value) in [("max_minutes", self.max_minutes), ("max_megabytes", self.max_megabytes)] {
+            if let Some(value) = value {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(AppError::Config(format!("Rotation {} must be positive, got {}", name, value)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 磁盘空间监控阈值（MB）- 低于 warning 时发送 low-disk-warning，低于 stop 时结束录制
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct DiskSpaceConfig {
+    pub warning_megabytes: f64,
+    pub stop_megabytes: f64,
+}
+
+impl Default for DiskSpaceConfig {
+    fn default() -> Self {
+        Self {
+            warning_megabytes: 2048.0,
+            stop_megabytes: 256.0,
+        }
+    }
+}
+
+impl DiskSpaceConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(self.stop_megabytes >= 0.0 && self.warning_megabytes >= self.stop_megabytes) {
+            return Err(AppError::Config(format!(
+                "Disk thresholds must satisfy 0 <= stop ({}) <= warning ({})",
+                self.stop_megabytes, self.warning_megabytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// low-disk-warning 事件
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LowDiskWarning {
+    pub filename: String,
+    pub available_megabytes: f64,
+    pub warning_megabytes: f64,
+    pub stop_megabytes: f64,
+    pub recording_stopped: bool,
+}
+
+/// start_recording 的可选参数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct RecordingOptions {
+    pub format: RecordingFormat,
+    pub rotation: Option<RotationConfig>,
+    pub disk: DiskSpaceConfig,
+    pub resample_hz: Option<f64>,       // 按目标速率写入（如256Hz），None 保持采集速率
+    pub include_last_seconds: Option<f64>,  // 从历史缓冲中前置的秒数（触发前数据）
+    pub record_aux: bool,               // 同时将辅助流（IMU等）写入 `<文件名>_aux.csv`
+    pub tags: Vec<String>,              // 录制索引中的标签，便于检索
+    pub physical_range: PhysicalRangeConfig,    // EDF/BDF 各通道的物理量程
+    pub record_duration: Option<f64>,   // EDF/BDF 数据记录长度（秒），None 按采样率自动选择
+    pub processing: RecordingProcessing,
+    pub channels: Option<Vec<String>>,  // 只录制这些通道（按标签，按给定顺序写入），None 录制全部
+}
+
+/// 录制样本的处理方式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingProcessing {
+    #[default]
+    Pipeline,   // 陷波滤波，带通滤波按 FilterConfig::apply_to_recording
+    Raw,        // 未经陷波和带通滤波，适合存档
+    Filtered,   // 陷波和带通滤波后，与显示一致，适合复核
+}
+
+/// start_recording 的附加输出 - 与主文件同时写入的另一个文件
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingOutput {
+    pub filename: String,
+    #[serde(default)]
+    pub options: RecordingOptions,
+}
+
+impl RecordingOutput {
+    pub const MAX_OUTPUTS: usize = 4;
+}
+
+impl RecordingOptions {
+    pub const MAX_RECORD_DURATION: f64 = 60.0;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(rotation) = &self.rotation {
+            rotation.validate()?;
+        }
+        if let Some(seconds) = self.include_last_seconds {
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(AppError::Config(format!("Invalid pre-trigger duration: {}", seconds)));
+            }
+        }
+        if let Some(rate) = self.resample_hz {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(AppError::Config(format!("Invalid resample rate: {}", rate)));
+            }
+        }
+        if let Some(duration) = self.record_duration {
+            if !duration.is_finite() || duration <= 0.0 || duration > Self::MAX_RECORD_DURATION {
+                return Err(AppError::Config(format!(
+                    "Data record duration must be in (0, {}] seconds, got {}", Self::MAX_RECORD_DURATION, duration
+                )));
+            }
+        }
+        if let Some(tag) = self.tags.iter().find(|tag| tag.trim().is_empty() || tag.chars().count() > 64) {
+            return Err(AppError::Config(format!("Recording tags must be 1-64 characters, got '{}'", tag)));
+        }
+        if let Some(channels) = &self.channels {
+            if channels.is_empty() {
+                return Err(AppError::Config("Select at least one channel to record".to_string()));
+            }
+            let mut seen = std::collections::HashSet::new();
+            if let Some(duplicate) = channels.iter().find(|label| !seen.insert(label.as_str())) {
+                return Err(AppError::Config(format!("Channel '{}' is selected more than once", duplicate)));
+            }
+        }
+        self.physical_range.validate()?;
+        self.disk.validate()
+    }
+}
+
+/// EDF/BDF 物理量程模式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PhysicalRangeMode {
+    #[default]
+    Fixed,
+    Auto,       // 按录制开头的数据估计，估计完成前数据暂存在内存中
+}
+
+/// 通道的物理量程（µV）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ChannelRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ChannelRange {
+    pub fn symmetric(limit: f64) -> Self {
+        Self { min: -limit, max: limit }
+    }
+    
+    pub fn contains(&self, value: f64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+/// EDF/BDF 各通道的物理量程 - 超出量程的样本在文件中被截断，并计入 RecordingStats.clipped_samples
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PhysicalRangeConfig {
+    pub mode: PhysicalRangeMode,
+    pub ranges: Vec<ChannelRange>,      // 固定量程：为空时用格式默认值，一个值作用于全部通道，或每通道一个
+    pub calibration_seconds: f64,       // 自动量程：用于估计的开头时长
+    pub headroom: f64,                  // 自动量程：以零为中心，取估计期间绝对峰值的该倍数
+}
+
+impl Default for PhysicalRangeConfig {
+    fn default() -> Self {
+        Self {
+            mode: PhysicalRangeMode::Fixed,
+            ranges: Vec::new(),
+            calibration_seconds: 5.0,
+            headroom: 2.0,
+        }
+    }
+}
+
+impl PhysicalRangeConfig {
+    pub const MAX_CALIBRATION_SECONDS: f64 = 60.0;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(range) = self.ranges.iter().find(|range| !(range.min.is_finite() && range.max.is_finite() && range.min < range.max)) {
+            return Err(AppError::Config(format!("Invalid physical range {} to {}", range.min, range.max)));
+        }
+        if !(self.calibration_seconds > 0.0 && self.calibration_seconds <= Self::MAX_CALIBRATION_SECONDS) {
+            return Err(AppError::Config(format!(
+                "Range calibration must be 0-{} seconds, got {}", Self::MAX_CALIBRATION_SECONDS, self.calibration_seconds
+            )));
+        }
+        if !(1.0..=100.0).contains(&self.headroom) {
+            return Err(AppError::Config(format!("Range headroom must be 1-100, got {}", self.headroom)));
+        }
+        Ok(())
+    }
+    
+    /// 固定量程模式下各通道的量程
+    pub fn fixed_ranges(&self, channels: usize, default: ChannelRange) -> Result<Vec<ChannelRange>, AppError> {
+        match self.ranges.len() {
+            0 => Ok(vec![default; channels]),
+            1 => Ok(vec![self.ranges[0]; channels]),
+            count if count == channels => Ok(self.ranges.clone()),
+            count => Err(AppError::Config(format!(
+                "Expected 1 or {} physical ranges, got {}", channels, count
+            ))),
+        }
+    }
+}
+
+/// 会话元数据 - 写入EDF+/BDF+文件头的受试者与记录信息
+///
+/// 文件头只允许ASCII，各子字段中的空格替换为下划线，未知项写作 "X"（EDF+ 规范）。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct SessionMetadata {
+    pub patient_code: Option<String>,               // 受试者编号（去标识化ID）
+    pub patient_name: Option<String>,
+    pub sex: Option<String>,                        // "M" 或 "F"
+    pub birthdate: Option<chrono::NaiveDate>,
+    pub admin_code: Option<String>,                 // 研究/项目编号
+    pub operator: Option<String>,
+    pub equipment: Option<String>,
+    pub notes: Option<String>,                      // 作为录制开头的注释写入
+    pub impedances: Vec<ElectrodeImpedance>,        // 录制开始前最近一次测得的电极阻抗
+}
+
+impl SessionMetadata {
+    const DEFAULT_EQUIPMENT: &'static str = "Open-CortexArray";
+    const MAX_NOTES_CHARS: usize = 1000;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        let fields = [
+            ("patient code", &self.patient_code),
+            ("patient name", &self.patient_name),
+            ("admin code", &self.admin_code),
+            ("operator", &self.operator),
+            ("equipment", &self.equipment),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                if value.chars().any(|c| !(' '..='~').contains(&c)) {
+                    return Err(AppError::Config(format!("Session {} must be printable ASCII: '{}'", name, value)));
+                }
+            }
+        }
+        if let Some(sex) = &self.sex {
+            if sex != "M" && sex != "F" {
+                return Err(AppError::Config(format!("Sex must be 'M' or 'F', got '{}'", sex)));
+            }
+        }
+        if self.birthdate.is_some_and(|date| date > chrono::Utc::now().date_naive()) {
+            return Err(AppError::Config("Birthdate is in the future".to_string()));
+        }
+        if self.notes.as_ref().is_some_and(|notes| notes.chars().count() > Self::MAX_NOTES_CHARS) {
+            return Err(AppError::Config(format!("Session notes exceed {} characters", Self::MAX_NOTES_CHARS)));
+        }
+        
+        // 文件头中的受试者/记录字段各80字节
+        let longest = self.edf_patient_field().len().max(self.edf_recording_field(chrono::Utc::now()).len());
+        if longest > 80 {
+            return Err(AppError::Config(format!("Session metadata too long for EDF header ({} > 80 characters)", longest)));
+        }
+        Ok(())
+    }
+    
+    /// EDF+ 受试者字段："编号 性别 出生日期 姓名"
+    pub fn edf_patient_field(&self) -> String {
+        [
+            edf_subfield(self.patient_code.as_deref()),
+            edf_subfield(self.sex.as_deref()),
+            edf_subfield(self.birthdate.map(|date| edf_date(&date)).as_deref()),
+            edf_subfield(self.patient_name.as_deref()),
+        ].join(" ")
+    }
+    
+    /// EDF+ 记录字段："Startdate 日期 研究编号 操作者 设备"
+    pub fn edf_recording_field(&self, start_time: chrono::DateTime<chrono::Utc>) -> String {
+        self.edf_recording_field_for_date(&edf_date(&start_time.date_naive()))
+    }
+    
+    /// 按已有的 EDF+ 日期子字段（如 "15-JAN-2024"）生成记录字段
+    pub fn edf_recording_field_for_date(&self, start_date: &str) -> String {
+        format!(
+            "Startdate {} {} {} {}",
+            start_date,
+            edf_subfield(self.admin_code.as_deref()),
+            edf_subfield(self.operator.as_deref()),
+            edf_subfield(Some(self.equipment.as_deref().unwrap_or(Self::DEFAULT_EQUIPMENT))),
+        )
+    }
+}
+
+/// EDF+ 子字段：空格替换为下划线，缺失或为空时为 "X"
+pub fn edf_subfield(value: Option<&str>) -> String {
+    match value.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(value) => value.replace(' ', "_"),
+        None => "X".to_string(),
+    }
+}
+
+/// EDF+ 日期格式：02-MAY-1951
+pub fn edf_date(date: &chrono::NaiveDate) -> String {
+    date.format("%d-%b-%Y").to_string().to_uppercase()
+}
+
+/// BIDS-EEG导出选项 - 各标签只能包含字母和数字
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BidsExportOptions {
+    pub subject: Option<String>,                // 缺省使用会话信息中的受试者编号
+    pub session: Option<String>,                // 缺省使用录制日期（YYYYMMDD）
+    pub task: String,
+    pub power_line_frequency: Option<f64>,      // 缺省使用陷波滤波器频率，未启用时写 "n/a"
+}
+
+impl Default for BidsExportOptions {
+    fn default() -> Self {
+        Self {
+            subject: None,
+            session: None,
+            task: "rest".to_string(),
+            power_line_frequency: None,
+        }
+    }
+}
+
+impl BidsExportOptions {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let labels = [("subject", self.subject.as_deref()), ("session", self.session.as_deref()), ("task", Some(self.task.as_str()))];
+        for (name, label) in labels {
+            if let Some(label) = label {
+                if label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(AppError::Config(format!("BIDS {} label must be alphanumeric, got '{}'", name, label)));
+                }
+            }
+        }
+        if let Some(frequency) = self.power_line_frequency {
+            if !frequency.is_finite() || frequency <= 0.0 {
+                return Err(AppError::Config(format!("Invalid power line frequency: {}", frequency)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// BIDS导出结果
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BidsExportResult {
+    pub directory: String,          // sub-<label>/[ses-<label>/]eeg
+    pub files: Vec<String>,         // 本次写入的文件
+}
+
+/// 会话归档的压缩方式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveCompression {
+    #[default]
+    Deflate,
+    Zstd,       // 压缩率和速度更好，但部分解压工具不支持
+}
+
+/// 会话归档导出选项
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    pub compression: ArchiveCompression,
+}
+
+/// 会话归档导出结果
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveResult {
+    pub path: String,
+    pub files: Vec<String>,         // 归档内的文件名
+    pub size_bytes: u64,
+}
+
+/// 匿名化导出选项
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct AnonymizeOptions {
+    pub pseudonym: Option<String>,      // 指定假名；缺省沿用映射表中该受试者的假名或新分配
+    pub remove_start_date: bool,        // 起始日期时间改为 EDF+ 规定的匿名值 01.01.85 00.00.00
+}
+
+impl AnonymizeOptions {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(pseudonym) = &self.pseudonym {
+            if pseudonym.is_empty() || pseudonym.len() > 40
+                || !pseudonym.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(AppError::Config(format!(
+                    "Pseudonym must be 1-40 letters, digits, '-' or '_', got '{}'", pseudonym
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// export_filtered 的处理参数 - 离线前向-后向（零相位）滤波，可选重采样
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct OfflineFilterSpec {
+    pub filters: FilterConfig,          // 高通/低通；两遍滤波后幅频响应为单遍的平方
+    pub notch: NotchFilterConfig,
+    pub resample_hz: Option<f64>,       // 滤波后重采样到该速率，所有通道相同
+}
+
+impl OfflineFilterSpec {
+    pub fn validate(&self, sample_rate: f64) -> Result<(), AppError> {
+        self.filters.validate(sample_rate)?;
+        if self.notch.enabled {
+            self.notch.validate()?;
+        }
+        if let Some(rate) = self.resample_hz {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(AppError::Config(format!("Resample rate must be positive, got {}", rate)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 离线滤波导出结果
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FilteredExportResult {
+    pub source: String,
+    pub destination: String,
+    pub channels: usize,
+    pub sample_rate: f64,               // 首个通道的输出采样率
+    pub duration_seconds: f64,
+    pub annotations: usize,             // 复制的注释数
+}
+
+/// 匿名化导出结果
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnonymizeResult {
+    pub source: String,
+    pub destination: String,
+    pub pseudonym: Option<String>,      // 源文件不含受试者身份时为 None
+    pub mapping_file: String,           // 假名映射表（不随导出文件分发）
+    pub start_date_removed: bool,
+}
+
+/// 录制进度 - get_recording_status 返回，录制期间每秒推送 recording-progress
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct RecordingStatus {
+    pub is_recording: bool,
+    pub is_paused: bool,
+    pub filename: Option<String>,       // 当前写入的文件（分段录制时为当前分段）
+    pub segments: u32,
+    pub elapsed_seconds: f64,
+    pub samples_written: u64,
+    pub file_size_bytes: u64,           // 全部分段合计
+    pub buffer_fill: f64,               // 录制队列占用比例（0-1），接近1说明写盘跟不上
+}
+
+/// repair_recording 的结果
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RepairReport {
+    pub path: String,
+    pub records_in_header: i64,         // 修复前文件头中的记录数（未完成的文件为 -1）
+    pub records_recovered: u64,         // 文件中完整的数据记录数
+    pub duration_seconds: f64,
+    pub bytes_truncated: u64,           // 截去的不完整记录字节数
+    pub repaired: bool,                 // 文件头已一致时为 false
+}
+
+/// verify_recording 的结果
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VerificationReport {
+    pub path: String,
+    pub sha256: String,
+    pub expected_sha256: Option<String>,    // 录制索引中登记的校验和，未登记时为 None
+    pub checksum_matches: Option<bool>,
+    pub structure_checked: bool,            // 仅 EDF/BDF 检查文件结构
+    pub records_in_header: Option<i64>,
+    pub records_in_file: Option<u64>,
+    pub duration_seconds: Option<f64>,
+    pub issues: Vec<String>,
+    pub valid: bool,                        // 没有任何问题
+}
+
+/// open_recording 的结果：已打开的 EDF/BDF 文件概要
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingFileInfo {
+    pub path: String,
+    pub format: RecordingFormat,
+    pub patient: String,                    // 文件头受试者字段
+    pub recording: String,                  // 文件头记录字段
+    pub start_time: Option<chrono::NaiveDateTime>,
+    pub duration_seconds: f64,
+    pub record_duration: f64,
+    pub records: u64,                       // 完整的数据记录数（未完成的文件按长度计算）
+    pub channels: Vec<RecordingFileChannel>,
+    pub annotations: Vec<RecordingFileAnnotation>,
+}
+
+/// 录制文件中的一个信号（不含注释信号）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingFileChannel {
+    pub label: String,
+    pub sample_rate: f64,
+    pub physical_dimension: String,
+    pub physical_min: f64,
+    pub physical_max: f64,
+    pub prefilter: String,
+}
+
+/// EDF+/BDF+ 注释，onset 为距文件开始的秒数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingFileAnnotation {
+    pub onset: f64,
+    pub duration: Option<f64>,
+    pub description: String,
+}
+
+/// read_segment 的结果：一段波形（物理值）及其间的注释
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingSegment {
+    pub start: f64,                         // 实际起点（已限制在文件范围内）
+    pub duration: f64,
+    pub channels: Vec<SegmentChannel>,
+    pub annotations: Vec<RecordingFileAnnotation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SegmentChannel {
+    pub label: String,
+    pub sample_rate: f64,
+    pub samples: Vec<f64>,
+}
+
+/// 录制索引中的一条录制（录制结束时写入）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordingEntry {
+    pub id: i64,
+    pub file_path: String,
+    pub segments: Vec<String>,
+    pub format: RecordingFormat,
+    pub start_time: chrono::DateTime<chrono::Utc>,
+    pub duration_seconds: f64,
+    pub channels_count: u32,
+    pub sample_rate: f64,
+    pub channel_labels: Vec<String>,
+    pub stream_name: String,
+    pub stream_type: String,
+    pub source_id: String,
+    pub patient_code: Option<String>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    pub markers_count: u64,
+    pub file_size_bytes: u64,
+    pub checksums: Vec<Option<String>>, // 各分段文件关闭时的 SHA-256，与 segments 对应
+}
+
+/// 录制检索条件 - 各条件同时满足；未设置的条件不过滤
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct RecordingQuery {
+    pub text: Option<String>,       // 匹配文件路径、流名称、受试者编号和备注
+    pub tags: Vec<String>,          // 须包含全部标签
+    pub started_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub started_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: Option<u32>,
+}
+
+/// LSL标记流中的原始事件（时间戳已经过时钟同步）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LslMarker {
+    pub label: String,
+    pub timestamp: f64,
+    pub stream_name: String,
+}
+
+/// 与EEG样本对齐后的标记事件（marker-received）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MarkerEvent {
+    pub label: String,
+    pub timestamp: f64,
+    pub stream_name: String,
+    pub sample_id: u64,                  // 时间戳不早于标记的第一个EEG样本
+    pub recording_onset: Option<f64>,    // 写入EDF+注释的起点（秒），未录制时为None
+}
+
+/// 录制中写入的一条注释（含标记），onset 为相对录制起点的秒数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnnotationRecord {
+    pub onset_seconds: f64,
+    pub duration_seconds: Option<f64>,
+    pub description: String,
+}
+
+/// LSL时钟偏移测量：发送端时间戳 + offset = 本机LSL时钟；unix_time 为测量时刻的本机墙钟（秒）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ClockOffsetSample {
+    pub local_time: f64,        // 测量时刻的本机LSL时钟
+    pub offset: f64,
+    pub unix_time: f64,
+}
+
+/// get_clock_offset 的结果 - LSL时间戳加 lsl_to_unix 即为UTC Unix时间
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClockOffsetInfo {
+    pub stream_name: String,
+    pub offset: Option<f64>,    // 最近一次测量，尚未测量时为None
+    pub lsl_to_unix: f64,
+    pub samples: Vec<ClockOffsetSample>,
+}
+
+/// 辅助（非EEG）LSL流的一个样本，如加速度计/陀螺仪；时间戳经时钟同步，与EEG同一时钟
+#[derive(Clone, Debug)]
+pub struct AuxSample {
+    pub timestamp: f64,
+    pub values: Vec<f64>,
+    pub channel_labels: std::sync::Arc<[String]>,
+}
+
+/// 与一个EEG批次对齐的辅助流样本（时间戳不晚于批次最后一个EEG样本）
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AuxBatch {
+    pub channel_labels: Vec<String>,
+    pub timestamps: Vec<f64>,
+    pub samples: Vec<Vec<f32>>,         // 样本优先
+}
+
+impl AuxBatch {
+    /// 中途切换辅助流时只保留与最后一个样本通道布局相同的样本
+    pub fn from_samples(samples: &[AuxSample]) -> Self {
+        let Some(last) = samples.last() else {
+            return Self::default();
+        };
+        let current: Vec<&AuxSample> = samples.iter()
+            .filter(|sample| sample.channel_labels == last.channel_labels)
+            .collect();
+        
+        Self {
+            channel_labels: last.channel_labels.to_vec(),
+            timestamps: current.iter().map(|sample| sample.timestamp).collect(),
+            samples: current.iter()
+                .map(|sample| sample.values.iter().map(|&value| value as f32).collect())
+                .collect(),
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+}
+
+/// 数据间隙类型
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GapKind {
+    SampleId,   // sample_id 跳变（样本丢失）
+    Timing,     // 时间戳间隔超过名义采样间隔
+}
+
+/// 数据间隙事件（data-gap）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DataGap {
+    pub kind: GapKind,
+    pub start_timestamp: f64,            // 间隙前最后一个样本的LSL时间戳
+    pub end_timestamp: f64,              // 间隙后第一个样本的LSL时间戳
+    pub duration_seconds: f64,
+    pub missing_samples: u64,
+    pub sample_id: u64,                  // 间隙后第一个样本
+    pub recording_onset: Option<f64>,    // 写入EDF+注释的起点（秒），未录制时为None
+}
+
+/// LSL工作线程一次拉取的样本块，整块在管道中传递以减少逐样本开销
+pub type EegChunk = Vec<EegSample>;
+
+/// 发往录制线程的样本 - 原始/滤波后的通道值仅在有录制输出需要时填充
+#[derive(Clone, Debug)]
+pub struct RecordingSample {
+    pub sample: EegSample,              // RecordingProcessing::Pipeline
+    pub raw: Option<Vec<f64>>,
+    pub filtered: Option<Vec<f64>>,
+}
+
+impl RecordingSample {
+    pub fn channels(&self, processing: RecordingProcessing) -> Option<&[f64]> {
+        match processing {
+            RecordingProcessing::Pipeline => Some(&self.sample.channels),
+            RecordingProcessing::Raw => self.raw.as_deref(),
+            RecordingProcessing::Filtered => self.filtered.as_deref(),
+        }
+    }
+}
+
+pub type RecordingChunk = Vec<RecordingSample>;
+
+/// 可视化/FFT路径的样本 - 显示不需要f64精度，f32使内存与序列化开销减半
+/// （录制路径始终保留f64的 EegSample）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DisplaySample {
+    pub timestamp: f64,
+    pub channels: Vec<f32>,
+    pub sample_id: u64,
+}
+
+impl From<EegSample> for DisplaySample {
+    fn from(sample: EegSample) -> Self {
+        Self {
+            timestamp: sample.timestamp,
+            channels: sample.channels.iter().map(|&value| value as f32).collect(),
+            sample_id: sample.sample_id,
+        }
+    }
+}
+
+pub type DisplayChunk = Vec<DisplaySample>;
+
+/// EEG流inlet参数，在下一次连接时生效
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct LslInletConfig {
+    pub buffer_seconds: i32,        // inlet缓冲的最长时长（秒）
+    pub max_chunk_length: i32,      // 传输块的最大样本数，0 沿用发送端的分块
+    pub recover: bool,              // 发送端重启后自动重连（同一source_id）
+    pub clock_sync: bool,           // 时间戳换算到本机时钟
+    pub dejitter: bool,             // 平滑规则采样率流的时间戳抖动
+    pub threadsafe: bool,           // 后处理状态加锁（多线程拉取同一inlet时需要）
+}
+
+impl Default for LslInletConfig {
+    fn default() -> Self {
+        Self {
+            buffer_seconds: 300,
+            max_chunk_length: 0,
+            recover: true,
+            clock_sync: true,
+            dejitter: true,
+            threadsafe: false,
+        }
+    }
+}
+
+impl LslInletConfig {
+    pub const MAX_BUFFER_SECONDS: i32 = 3600;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.buffer_seconds < 1 || self.buffer_seconds > Self::MAX_BUFFER_SECONDS {
+            return Err(AppError::Config(format!(
+                "Inlet buffer must be between 1 and {} seconds", Self::MAX_BUFFER_SECONDS
+            )));
+        }
+        if self.max_chunk_length < 0 || self.max_chunk_length as usize > ChunkConfig::MAX_CHUNK_SIZE {
+            return Err(AppError::Config(format!(
+                "Inlet chunk length must be between 0 and {}", ChunkConfig::MAX_CHUNK_SIZE
+            )));
+        }
+        Ok(())
+    }
+    
+    pub fn processing_options(&self) -> Vec<lsl::ProcessingOption> {
+        let flags = [
+            (self.clock_sync, lsl::ProcessingOption::ClockSync),
+            (self.dejitter, lsl::ProcessingOption::Dejitter),
+            (self.threadsafe, lsl::ProcessingOption::Threadsafe),
+        ];
+        flags.into_iter().filter(|(enabled, _)| *enabled).map(|(_, option)| option).collect()
+    }
+}
+
+/// LSL拉取分块配置
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkConfig {
+    pub chunk_size: usize,     // 凑满多少个样本即发送
+    pub timeout_ms: u64,       // 块未满时的最长等待（同时作为阻塞拉取超时）
+}
+
+impl Default for ChunkConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 32,
+            timeout_ms: 10,
+        }
+    }
+}
+
+impl ChunkConfig {
+    pub const MAX_CHUNK_SIZE: usize = 4096;
+    pub const MAX_TIMEOUT_MS: u64 = 1000;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.chunk_size == 0 || self.chunk_size > Self::MAX_CHUNK_SIZE {
+            return Err(AppError::Config(format!(
+                "Chunk size must be between 1 and {}", Self::MAX_CHUNK_SIZE
+            )));
+        }
+        if self.timeout_ms == 0 || self.timeout_ms > Self::MAX_TIMEOUT_MS {
+            return Err(AppError::Config(format!(
+                "Chunk timeout must be between 1 and {}ms", Self::MAX_TIMEOUT_MS
+            )));
+        }
+        Ok(())
+    }
+    
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// 通道满时的处理策略
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    #[default]
+    Block,          // 阻塞生产者，不丢数据（录制路径）
+    DropOldest,     // 丢弃队列中最旧的一项（可视化路径，保持最新）
+    DropNewest,     // 丢弃正要发送的一项
+}
+
+/// 单个管道通道的容量与溢出策略
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChannelConfig {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl ChannelConfig {
+    pub const MAX_CAPACITY: usize = 65536;
+    
+    pub const fn new(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self { capacity, overflow }
+    }
+    
+    fn validate(&self, path: &str) -> Result<(), AppError> {
+        if self.capacity == 0 || self.capacity > Self::MAX_CAPACITY {
+            return Err(AppError::Config(format!(
+                "{} channel capacity must be between 1 and {}", path, Self::MAX_CAPACITY
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 处理管道各通道配置（录制/时域按块计数，FFT/前端按批次计数）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct PipelineChannelConfig {
+    pub recording: ChannelConfig,       // 分发器 → 录制线程
+    pub time_domain: ChannelConfig,     // 分发器 → 时域收集器
+    pub fft: ChannelConfig,             // 时域收集器 → FFT线程
+    pub frontend: ChannelConfig,        // 收集器/FFT → 前端线程
+}
+
+impl Default for PipelineChannelConfig {
+    fn default() -> Self {
+        Self {
+            recording: ChannelConfig::new(4096, OverflowPolicy::Block),
+            time_domain: ChannelConfig::new(1024, OverflowPolicy::DropOldest),
+            fft: ChannelConfig::new(8, OverflowPolicy::DropOldest),
+            frontend: ChannelConfig::new(64, OverflowPolicy::DropOldest),
+        }
+    }
+}
+
+impl PipelineChannelConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.recording.validate("Recording")?;
+        // 录制通道之前已完成缺口检测，丢弃的样本不会被发现，只能阻塞
+        if self.recording.overflow != OverflowPolicy::Block {
+            return Err(AppError::Config(
+                "Recording channel must use the block overflow policy, dropping would lose recorded samples".to_string()
+            ));
+        }
+        self.time_domain.validate("Time domain")?;
+        self.fft.validate("FFT")?;
+        self.frontend.validate("Frontend")?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EegBatch {
+    pub samples: Vec<DisplaySample>,
+    pub batch_id: u64,
+    pub channels_count: u32,
+    pub sample_rate: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FreqData {
+    pub channel_index: u32,
+    pub spectrum: Vec<f64>,
+    pub frequency_bins: Vec<f64>,
+    pub batch_id: Option<u64>,  // ✅ 添加批次ID关联
+}
+
+/// FFT处理配置 - 可在运行时通过 set_fft_config 修改
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FftConfig {
+    pub window_size: usize,          // 分析窗长度（样本数）
+    pub hop_size: usize,             // 两次FFT之间至少新增的样本数，0表示每个批次都计算
+    pub zero_padding_factor: usize,  // 补零倍数，FFT长度 = window_size * zero_padding_factor
+}
+
+impl Default for FftConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 256,
+            hop_size: 0,
+            zero_padding_factor: 1,
+        }
+    }
+}
+
+impl FftConfig {
+    pub const MIN_WINDOW_SIZE: usize = 16;
+    pub const MAX_WINDOW_SIZE: usize = 16384;
+    pub const MAX_ZERO_PADDING_FACTOR: usize = 8;
+    
+    /// 实际FFT长度（含补零）
+    pub fn fft_size(&self) -> usize {
+        self.window_size * self.zero_padding_factor
+    }
+    
+    /// 相邻窗口重叠比例
+    pub fn overlap(&self) -> f64 {
+        if self.hop_size == 0 || self.hop_size >= self.window_size {
+            0.0
+        } else {
+            1.0 - self.hop_size as f64 / self.window_size as f64
+        }
+    }
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(Self::MIN_WINDOW_SIZE..=Self::MAX_WINDOW_SIZE).contains(&self.window_size) {
+            return Err(AppError::Config(format!(
+                "FFT window size must be between {} and {}, got {}",
+                Self::MIN_WINDOW_SIZE, Self::MAX_WINDOW_SIZE, self.window_size
+            )));
+        }
+        if self.hop_size > self.window_size {
+            return Err(AppError::Config(format!(
+                "FFT hop size {} exceeds window size {}", self.hop_size, self.window_size
+            )));
+        }
+        if !(1..=Self::MAX_ZERO_PADDING_FACTOR).contains(&self.zero_padding_factor) {
+            return Err(AppError::Config(format!(
+                "Zero padding factor must be between 1 and {}, got {}",
+                Self::MAX_ZERO_PADDING_FACTOR, self.zero_padding_factor
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 频谱纵轴刻度
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpectrumScale {
+    #[default]
+    Linear,
+    Db,
+}
+
+/// 频谱显示后处理 - 只作用于推送给前端的频谱，频带功率与频谱图保留原始幅值
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct SpectrumDisplayOptions {
+    pub scale: SpectrumScale,
+    pub relative: bool,         // 转换为输出范围内的相对功率（各频点之和为1）
+    pub smoothing: f64,         // 帧间指数平滑系数 0-0.99，0为关闭（越大越平滑）
+}
+
+impl SpectrumDisplayOptions {
+    // dB刻度下限，避免零幅值得到负无穷
+    pub const DB_FLOOR: f64 = -120.0;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(0.0..=0.99).contains(&self.smoothing) {
+            return Err(AppError::Config(format!("Spectrum smoothing must be 0-0.99, got {}", self.smoothing)));
+        }
+        Ok(())
+    }
+    
+    /// 按配置转换（平滑后的）幅值谱：相对功率归一化，然后转换刻度
+    pub fn apply(&self, spectrum: &mut [f64]) {
+        if self.relative {
+            let total: f64 = spectrum.iter().map(|m| m * m).sum();
+            for value in spectrum.iter_mut() {
+                *value = if total > 0.0 { *value * *value / total } else { 0.0 };
+            }
+        }
+        
+        if self.scale == SpectrumScale::Db {
+            // 幅值按 20·log10，相对功率按 10·log10
+            let factor = if self.relative { 10.0 } else { 20.0 };
+            for value in spectrum.iter_mut() {
+                *value = if *value > 0.0 { (factor * value.log10()).max(Self::DB_FLOOR) } else { Self::DB_FLOOR };
+            }
+        }
+    }
+}
+
+/// 频谱输出范围 - FreqData 在 [min_hz, max_hz] 上均匀取 bin_count 个频点
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpectrumRange {
+    pub min_hz: f64,
+    pub max_hz: f64,
+    pub bin_count: usize,
+}
+
+impl Default for SpectrumRange {
+    fn default() -> Self {
+        Self {
+            min_hz: 1.0,
+            max_hz: 50.0,
+            bin_count: 50,
+        }
+    }
+}
+
+impl SpectrumRange {
+    pub const MAX_BIN_COUNT: usize = 4096;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.min_hz.is_finite() || !self.max_hz.is_finite() || self.min_hz < 0.0 {
+            return Err(AppError::Config(format!(
+                "Invalid spectrum range {}-{}Hz", self.min_hz, self.max_hz
+            )));
+        }
+        if self.max_hz <= self.min_hz {
+            return Err(AppError::Config(format!(
+                "Spectrum max {}Hz must be greater than min {}Hz", self.max_hz, self.min_hz
+            )));
+        }
+        if !(1..=Self::MAX_BIN_COUNT).contains(&self.bin_count) {
+            return Err(AppError::Config(format!(
+                "Spectrum bin count must be between 1 and {}, got {}",
+                Self::MAX_BIN_COUNT, self.bin_count
+            )));
+        }
+        Ok(())
+    }
+    
+    /// 按流的奈奎斯特频率截断范围（不规则采样率的流保持原样）
+    pub fn clamped_to(&self, sample_rate: f64) -> SpectrumRange {
+        if sample_rate <= 0.0 {
+            return self.clone();
+        }
+        
+        let nyquist = sample_rate / 2.0;
+        SpectrumRange {
+            min_hz: self.min_hz.min(nyquist),
+            max_hz: self.max_hz.min(nyquist),
+            bin_count: self.bin_count,
+        }
+    }
+    
+    /// 输出频点列表
+    pub fn frequencies(&self) -> Vec<f64> {
+        if self.bin_count <= 1 {
+            return vec![self.min_hz; self.bin_count];
+        }
+        
+        let step = (self.max_hz - self.min_hz) / (self.bin_count - 1) as f64;
+        (0..self.bin_count)
+            .map(|i| self.min_hz + step * i as f64)
+            .collect()
+    }
+}
+
+/// 工频陷波滤波器配置
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NotchFilterConfig {
+    pub enabled: bool,
+    pub frequency_hz: f64,   // 50 或 60
+    pub harmonics: bool,     // 是否同时滤除谐波
+    pub q_factor: f64,
+}
+
+impl Default for NotchFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency_hz: 50.0,
+            harmonics: false,
+            q_factor: 30.0,
+        }
+    }
+}
+
+impl NotchFilterConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.frequency_hz != 50.0 && self.frequency_hz != 60.0 {
+            return Err(AppError::Config(format!(
+                "Notch frequency must be 50 or 60Hz, got {}", self.frequency_hz
+            )));
+        }
+        if !self.q_factor.is_finite() || self.q_factor <= 0.0 {
+            return Err(AppError::Config(format!("Invalid notch Q factor: {}", self.q_factor)));
+        }
+        Ok(())
+    }
+}
+
+/// 通道信号质量检测配置（坏导自动识别）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SignalQualityConfig {
+    pub enabled: bool,
+    pub flat_std: f64,              // 标准差低于该值视为平线（与数据同单位，通常µV）
+    pub clip_fraction: f64,         // 处于窗口极值的样本比例超过该值视为饱和/贴轨
+    pub variance_ratio: f64,        // 标准差偏离所有通道中位数的倍数上限
+    pub line_noise_ratio: f64,      // 工频功率占总方差的比例上限
+}
+
+impl Default for SignalQualityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flat_std: 0.5,
+            clip_fraction: 0.1,
+            variance_ratio: 10.0,
+            line_noise_ratio: 0.5,
+        }
+    }
+}
+
+impl SignalQualityConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.flat_std.is_finite() || self.flat_std < 0.0 {
+            return Err(AppError::Config(format!("Invalid flatline threshold: {}", self.flat_std)));
+        }
+        if !(self.clip_fraction > 0.0 && self.clip_fraction <= 1.0) {
+            return Err(AppError::Config("Clip fraction must be between 0 and 1".to_string()));
+        }
+        if !self.variance_ratio.is_finite() || self.variance_ratio <= 1.0 {
+            return Err(AppError::Config("Variance ratio must be greater than 1".to_string()));
+        }
+        if !(self.line_noise_ratio > 0.0 && self.line_noise_ratio <= 1.0) {
+            return Err(AppError::Config("Line noise ratio must be between 0 and 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 坏导原因
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssue {
+    Flatline,
+    Railing,
+    HighVariance,
+    LowVariance,
+    LineNoise,
+}
+
+/// 单通道信号质量
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelQuality {
+    pub channel_index: u32,
+    pub score: f64,                 // 0（坏）~ 1（好）
+    pub bad: bool,
+    pub issues: Vec<QualityIssue>,
+    pub std_dev: f64,
+    pub line_noise_ratio: f64,
+}
+
+/// 每秒一次的信号质量更新（channel-quality-update）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelQualityUpdate {
+    pub timestamp: f64,             // 窗口末样本的LSL时间戳
+    pub channels: Vec<ChannelQuality>,
+}
+
+/// 眨眼/运动伪迹检测配置
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ArtifactConfig {
+    pub enabled: bool,
+    pub channels: Vec<String>,          // 检测的通道标签（默认额区），未匹配到时使用前两个通道
+    pub amplitude_threshold: f64,       // 相对基线的幅值阈值（µV）
+    pub slope_threshold: f64,           // 斜率阈值（µV/s），超过即判为运动伪迹
+    pub annotate: bool,                 // 录制时写入EDF+注释
+}
+
+impl Default for ArtifactConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channels: ["Fp1", "Fp2", "AF7", "AF8", "F7", "F8"].iter().map(|label| label.to_string()).collect(),
+            amplitude_threshold: 100.0,
+            slope_threshold: 5000.0,
+            annotate: true,
+        }
+    }
+}
+
+impl ArtifactConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.amplitude_threshold.is_finite() || self.amplitude_threshold <= 0.0 {
+            return Err(AppError::Config(format!("Invalid amplitude threshold: {}", self.amplitude_threshold)));
+        }
+        if !self.slope_threshold.is_finite() || self.slope_threshold <= 0.0 {
+            return Err(AppError::Config(format!("Invalid slope threshold: {}", self.slope_threshold)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Blink,      // 幅值超限、斜率平缓
+    Motion,     // 斜率超限
+}
+
+impl ArtifactKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArtifactKind::Blink => "blink",
+            ArtifactKind::Motion => "motion",
+        }
+    }
+}
+
+/// 伪迹段事件（artifact-detected）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArtifactEvent {
+    pub kind: ArtifactKind,
+    pub channel_index: u32,
+    pub channel_label: String,
+    pub start_timestamp: f64,
+    pub end_timestamp: f64,
+    pub peak_amplitude: f64,            // 相对基线的最大偏移
+    pub recording_onset: Option<f64>,   // 写入EDF+注释的起点（秒），未录制时为None
+}
+
+/// ASR（Artifact Subspace Reconstruction）配置 - 仅作用于显示/FFT路径
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct AsrConfig {
+    pub enabled: bool,
+    pub cutoff: f64,                // 成分RMS超过校准分布 均值+cutoff×标准差 即视为伪迹
+    pub window_seconds: f64,        // 滑动窗长度，每半个窗更新一次重建矩阵
+    pub calibration_seconds: f64,   // 校准采集时长，应为受试者静息的干净数据
+}
+
+impl Default for AsrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cutoff: 20.0,
+            window_seconds: 0.5,
+            calibration_seconds: 60.0,
+        }
+    }
+}
+
+impl AsrConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.cutoff.is_finite() || self.cutoff <= 0.0 {
+            return Err(AppError::Config(format!("Invalid ASR cutoff: {}", self.cutoff)));
+        }
+        if !(0.1..=5.0).contains(&self.window_seconds) {
+            return Err(AppError::Config(format!("ASR window must be 0.1-5 seconds, got {}", self.window_seconds)));
+        }
+        if !(5.0..=600.0).contains(&self.calibration_seconds) {
+            return Err(AppError::Config(format!("ASR calibration must be 5-600 seconds, got {}", self.calibration_seconds)));
+        }
+        Ok(())
+    }
+}
+
+/// ASR校准结果（asr-calibrated）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AsrCalibration {
+    pub channels: u32,
+    pub samples: usize,
+    pub component_thresholds: Vec<f64>,     // 各主成分的RMS阈值（µV），按特征值升序
+}
+
+/// 在线ICA配置 - 仅作用于显示/FFT路径
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct IcaConfig {
+    pub enabled: bool,
+    pub learning_rate: f64,         // 自然梯度步长
+    pub block_size: usize,          // 每块样本更新一次解混矩阵
+    pub memory_seconds: f64,        // 白化协方差的遗忘时间常数
+    pub update_interval_ms: u64,    // ica-update 推送间隔
+}
+
+impl Default for IcaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            learning_rate: 0.01,
+            block_size: 16,
+            memory_seconds: 30.0,
+            update_interval_ms: 1000,
+        }
+    }
+}
+
+impl IcaConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !self.learning_rate.is_finite() || self.learning_rate <= 0.0 || self.learning_rate > 1.0 {
+            return Err(AppError::Config(format!("Invalid ICA learning rate: {}", self.learning_rate)));
+        }
+        if !(1..=1024).contains(&self.block_size) {
+            return Err(AppError::Config(format!("ICA block size must be 1-1024, got {}", self.block_size)));
+        }
+        if !self.memory_seconds.is_finite() || self.memory_seconds < 1.0 {
+            return Err(AppError::Config(format!("ICA memory must be at least 1 second, got {}", self.memory_seconds)));
+        }
+        if self.update_interval_ms < 100 {
+            return Err(AppError::Config(format!("ICA update interval must be at least 100ms, got {}", self.update_interval_ms)));
+        }
+        Ok(())
+    }
+}
+
+/// ICA成分更新（ica-update）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IcaUpdate {
+    pub channel_labels: Vec<String>,
+    pub scalp_weights: Vec<Vec<f32>>,   // [成分][通道]，混合矩阵的列
+    pub time_courses: Vec<Vec<f32>>,    // [成分][样本]，上次推送以来的成分时间序列
+    pub sample_rate: f64,
+    pub rejected: Vec<u32>,             // 重建显示信号时置零的成分
+}
+
+/// 显示路径抽取配置 - 前端收到 sample_rate / decimation_factor 的数据，录制与FFT保持全速率
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct DisplayRateConfig {
+    pub decimation_factor: u32,     // 1 为不抽取
+}
+
+impl Default for DisplayRateConfig {
+    fn default() -> Self {
+        Self { decimation_factor: 1 }
+    }
+}
+
+impl DisplayRateConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(1..=32).contains(&self.decimation_factor) {
+            return Err(AppError::Config(format!("Decimation factor must be 1-32, got {}", self.decimation_factor)));
+        }
+        Ok(())
+    }
+    
+    pub fn display_rate(&self, sample_rate: f64) -> f64 {
+        sample_rate / self.decimation_factor.max(1) as f64
+    }
+}
+
+/// 前端帧率 - 时域收集器按此间隔打包显示批次，前端线程按此间隔发送帧
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct FrameRateConfig {
+    pub fps: u32,                   // 10-60
+    pub low_power: bool,            // 低功耗模式固定 5 FPS，忽略 fps
+}
+
+impl Default for FrameRateConfig {
+    fn default() -> Self {
+        Self { fps: 30, low_power: false }
+    }
+}
+
+impl FrameRateConfig {
+    pub const LOW_POWER_FPS: u32 = 5;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(10..=60).contains(&self.fps) {
+            return Err(AppError::Config(format!("Frame rate must be 10-60 FPS, got {}", self.fps)));
+        }
+        Ok(())
+    }
+    
+    pub fn effective_fps(&self) -> u32 {
+        if self.low_power { Self::LOW_POWER_FPS } else { self.fps }
+    }
+    
+    /// 帧间隔（整毫秒，30 FPS 为 33ms）
+    pub fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(1000 / self.effective_fps().max(1) as u64)
+    }
+}
+
+/// 运行时处理配置更新 - 各管道线程在下一个数据块或下一帧读取，无需重启管道
+///
+/// 更新写入各项共享配置（与其余运行时配置相同的 RwLock），而不是经 watch 通道广播：
+/// watch 只保留最新一个值，连续提交的不同种类更新（如先滤波后帧率）会互相覆盖
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", content = "config", rename_all = "snake_case")]
+pub enum ProcessorConfigUpdate {
+    Filters(FilterConfig),                      // 高通/低通截止频率
+    NotchFilter(NotchFilterConfig),             // 工频陷波
+    DisplayRate(DisplayRateConfig),             // 显示路径抽取
+    SpectrumRange(SpectrumRange),               // 频谱输出范围
+    SpectrumDisplay(SpectrumDisplayOptions),    // 频谱刻度/归一化/平滑
+    Fft(FftConfig),                             // FFT窗口与重叠
+    FrameRate(FrameRateConfig),                 // 前端帧率
+}
+
+/// 可组合处理阶段 - 按配置顺序作用于显示/FFT路径（内置处理之后、送往收集器与FFT之前）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", content = "config", rename_all = "snake_case")]
+pub enum StageSpec {
+    Notch(NotchFilterConfig),
+    Filters(FilterConfig),
+    Montage(MontageConfig),
+    ArtifactDetection(ArtifactConfig),
+    /// 通过 StageRegistry 注册的自定义阶段，params 原样交给构造函数
+    Custom {
+        name: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    },
+}
+
+/// 处理阶段链配置，空列表表示不启用
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ProcessingStagesConfig {
+    pub stages: Vec<StageSpec>,
+}
+
+impl ProcessingStagesConfig {
+    pub const MAX_STAGES: usize = 32;
+    
+    /// 与流无关的校验；滤波器（依赖采样率）与自定义阶段（依赖注册表）在应用到流时校验
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.stages.len() > Self::MAX_STAGES {
+            return Err(AppError::Config(format!(
+                "At most {} processing stages are supported, got {}", Self::MAX_STAGES, self.stages.len()
+            )));
+        }
+        for stage in &self.stages {
+            match stage {
+                StageSpec::Notch(config) => config.validate()?,
+                StageSpec::ArtifactDetection(config) => config.validate()?,
+                StageSpec::Custom { name, .. } if name.trim().is_empty() => {
+                    return Err(AppError::Config("Custom stage name cannot be empty".to_string()));
+                }
+                StageSpec::Filters(_) | StageSpec::Montage(_) | StageSpec::Custom { .. } => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 处理阶段输出的单个指标值
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StageMetric {
+    pub name: String,
+    pub channel_index: Option<u32>,     // None：与通道无关
+    pub value: f64,
+}
+
+/// stage-metrics 事件 - 一个阶段在一个数据块上产生的指标
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StageMetricsUpdate {
+    pub stage: String,
+    pub timestamp: f64,                 // 数据块最后一个样本的LSL时间戳
+    pub metrics: Vec<StageMetric>,
+}
+
+/// 自定义指标脚本的输入
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptInput {
+    #[default]
+    Batch,          // 每个数据块调用 on_batch(batch)，位于处理阶段链之后
+    Spectrum,       // 每次FFT调用 on_spectrum(spectrum)，幅值谱为完整分辨率
+}
+
+/// 自定义指标脚本（Rhai）- 入口函数返回 #{ 名称: 数值 或 每通道数值数组 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScriptHook {
+    pub name: String,
+    pub source: String,
+    #[serde(default)]
+    pub input: ScriptInput,
+    #[serde(default)]
+    pub record: bool,               // 录制中写入 <录制名>_script_<name>.csv
+}
+
+/// 自定义指标脚本配置，空列表表示不启用
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ScriptingConfig {
+    pub scripts: Vec<ScriptHook>,
+}
+
+impl ScriptingConfig {
+    pub const MAX_SCRIPTS: usize = 16;
+    pub const MAX_SOURCE_BYTES: usize = 64 * 1024;
+    
+    /// 名称与大小校验；语法与入口函数在编译时检查
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.scripts.len() > Self::MAX_SCRIPTS {
+            return Err(AppError::Config(format!(
+                "At most {} scripts are supported, got {}", Self::MAX_SCRIPTS, self.scripts.len()
+            )));
+        }
+        for (index, script) in self.scripts.iter().enumerate() {
+            // 名称用于事件与旁路文件名
+            let valid_name = !script.name.is_empty()
+                && script.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid_name {
+                return Err(AppError::Config(format!(
+                    "Script name '{}' must be non-empty and contain only letters, digits, '_' or '-'", script.name
+                )));
+            }
+            if self.scripts[..index].iter().any(|other| other.name == script.name) {
+                return Err(AppError::Config(format!("Duplicate script name '{}'", script.name)));
+            }
+            if script.source.trim().is_empty() || script.source.len() > Self::MAX_SOURCE_BYTES {
+                return Err(AppError::Config(format!(
+                    "Script '{}' source must be 1-{} bytes", script.name, Self::MAX_SOURCE_BYTES
+                )));
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn is_recorded(&self, name: &str) -> bool {
+        self.scripts.iter().any(|script| script.name == name && script.record)
+    }
+}
+
+/// script-metrics 事件 - 一个脚本在一个数据块或一帧频谱上返回的指标
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScriptMetricsUpdate {
+    pub script: String,
+    pub input: ScriptInput,
+    pub timestamp: f64,
+    pub metrics: Vec<StageMetric>,
+}
+
+/// 内置信号模拟器配置 - 幅值单位为微伏，节律按通道由前到后混合（前部β、后部α为主）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SimulatorConfig {
+    pub name: String,                   // 流名称，同时作为默认会话标识
+    pub channels_count: u32,
+    pub sample_rate: f64,
+    pub alpha_amplitude: f64,
+    pub alpha_frequency: f64,
+    pub beta_amplitude: f64,
+    pub beta_frequency: f64,
+    pub noise_amplitude: f64,           // 白噪声标准差
+    pub line_noise_amplitude: f64,      // 工频干扰，0 关闭
+    pub line_frequency: f64,
+    pub blinks_per_minute: f64,         // 眨眼伪迹（前部通道），0 关闭
+    pub blink_amplitude: f64,
+    pub muscle_bursts_per_minute: f64,  // 肌电爆发（高频噪声，约0.5秒），0 关闭
+    pub muscle_amplitude: f64,
+    pub seed: u64,                      // 相同种子生成相同的数据
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            name: "Simulator".to_string(),
+            channels_count: 8,
+            sample_rate: 250.0,
+            alpha_amplitude: 20.0,
+            alpha_frequency: 10.0,
+            beta_amplitude: 5.0,
+            beta_frequency: 20.0,
+            noise_amplitude: 2.0,
+            line_noise_amplitude: 0.0,
+            line_frequency: 50.0,
+            blinks_per_minute: 6.0,
+            blink_amplitude: 100.0,
+            muscle_bursts_per_minute: 2.0,
+            muscle_amplitude: 30.0,
+            seed: 0,
+        }
+    }
+}
+
+impl SimulatorConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.name.trim().is_empty() {
+            return Err(AppError::Config("Simulator name cannot be empty".to_string()));
+        }
+        if !(1..=256).contains(&self.channels_count) {
+            return Err(AppError::Config("Simulator channels must be between 1 and 256".to_string()));
+        }
+        if !(self.sample_rate >= 1.0 && self.sample_rate <= 16000.0) {
+            return Err(AppError::Config("Simulator sample rate must be between 1 and 16000Hz".to_string()));
+        }
+        let nyquist = self.sample_rate / 2.0;
+        for (name, frequency) in [("alpha", self.alpha_frequency), ("beta", self.beta_frequency), ("line", self.line_frequency)] {
+            if !(frequency > 0.0 && frequency < nyquist) {
+                return Err(AppError::Config(format!(
+                    "Simulator {} frequency must be between 0 and {}Hz", name, nyquist
+                )));
+            }
+        }
+        let amplitudes = [
+            self.alpha_amplitude, self.beta_amplitude, self.noise_amplitude, self.line_noise_amplitude,
+            self.blink_amplitude, self.muscle_amplitude,
+        ];
+        if !amplitudes.iter().all(|amplitude| amplitude.is_finite() && *amplitude >= 0.0) {
+            return Err(AppError::Config("Simulator amplitudes must be non-negative".to_string()));
+        }
+        for rate in [self.blinks_per_minute, self.muscle_bursts_per_minute] {
+            if !(0.0..=60.0).contains(&rate) {
+                return Err(AppError::Config("Simulator artifact rates must be between 0 and 60 per minute".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 多流融合配置 - 多台放大器按LSL时间戳对齐、重采样为一个虚拟流，通道按 streams 顺序拼接
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FusionConfig {
+    pub name: String,                   // 虚拟流名称，同时作为默认会话标识
+    pub streams: Vec<StreamSelector>,   // 参与融合的流，至少2个
+    pub sample_rate: Option<f64>,       // 输出采样率，None 时沿用第一个流的名义采样率
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            name: "Fused".to_string(),
+            streams: Vec::new(),
+            sample_rate: None,
+        }
+    }
+}
+
+impl FusionConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.name.trim().is_empty() {
+            return Err(AppError::Config("Fused stream name cannot be empty".to_string()));
+        }
+        if self.streams.len() < 2 {
+            return Err(AppError::Config("Fusion needs at least 2 streams".to_string()));
+        }
+        if let Some(stream) = self.streams.iter().find(|stream| stream.name.trim().is_empty() || stream.name == self.name) {
+            return Err(AppError::Config(format!("Invalid stream '{}' in fusion", stream.name)));
+        }
+        if self.streams.iter().enumerate().any(|(i, stream)| self.streams[..i].contains(stream)) {
+            return Err(AppError::Config("Fusion streams must be distinct".to_string()));
+        }
+        if let Some(rate) = self.sample_rate {
+            if !(1.0..=16000.0).contains(&rate) {
+                return Err(AppError::Config("Fused sample rate must be between 1 and 16000Hz".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 已安装插件的信息
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: String,
+    pub stages: Vec<String>,            // 注册的阶段（带命名空间 `<插件>/<阶段>`）
+    pub library: Option<String>,        // 动态库路径，启动时注册的插件为 None
+}
+
+/// 原始样本采集文件（capture）的写入统计
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CaptureStats {
+    pub filename: String,
+    pub samples_written: u64,
+    pub markers_written: u64,
+    pub file_size_bytes: u64,
+}
+
+/// 一帧幅值谱（回放回归比对用）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpectrumFrame {
+    pub timestamp: f64,                 // 窗内最后一个样本的时间戳
+    pub resolution: f64,                // 频率分辨率（Hz/bin）
+    pub magnitudes: Vec<Vec<f64>>,      // 每个通道的单边幅值谱
+}
+
+/// 前端帧订阅 - 发送线程只序列化当前视图需要的通道与频谱
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FrameSubscription {
+    pub channels: Option<Vec<u32>>, // 显示通道索引，None 为全部
+    pub include_spectrum: bool,
+    pub max_rate: Option<f64>,      // 帧/秒上限，未到发送时间的样本并入下一帧
+}
+
+impl Default for FrameSubscription {
+    fn default() -> Self {
+        Self { channels: None, include_spectrum: true, max_rate: None }
+    }
+}
+
+impl FrameSubscription {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(channels) = &self.channels {
+            let mut sorted = channels.clone();
+            sorted.sort_unstable();
+            sorted.dedup();
+            if sorted.len() != channels.len() {
+                return Err(AppError::Config("Subscribed channels contain duplicates".to_string()));
+            }
+        }
+        if let Some(rate) = self.max_rate {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(AppError::Config(format!("Max frame rate must be positive, got {}", rate)));
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn wants_channel(&self, channel_index: u32) -> bool {
+        self.channels.as_ref().is_none_or(|channels| channels.contains(&channel_index))
+    }
+}
+
+/// 显示速率查询结果
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DisplayRateInfo {
+    pub decimation_factor: u32,
+    pub sample_rate: f64,           // 采集速率
+    pub display_rate: f64,          // 前端收到的速率
+}
+
+/// 原始数据历史缓冲配置 - 保留最近 seconds 秒，用于回看和快照导出
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub seconds: f64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { seconds: 60.0 }
+    }
+}
+
+impl HistoryConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(1.0..=600.0).contains(&self.seconds) {
+            return Err(AppError::Config(format!("History length must be 1-600 seconds, got {}", self.seconds)));
+        }
+        Ok(())
+    }
+}
+
+/// 单通道历史数据（get_history）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistorySegment {
+    pub channel: u32,
+    pub label: String,
+    pub sample_rate: f64,
+    pub timestamps: Vec<f64>,
+    pub values: Vec<f64>,
+}
+
+/// 频谱图历史配置 - 保留最近 seconds 秒，每秒最多 columns_per_second 列
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct SpectrogramConfig {
+    pub seconds: f64,
+    pub columns_per_second: f64,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self {
+            seconds: 300.0,
+            columns_per_second: 2.0,
+        }
+    }
+}
+
+impl SpectrogramConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(10.0..=3600.0).contains(&self.seconds) {
+            return Err(AppError::Config(format!("Spectrogram length must be 10-3600 seconds, got {}", self.seconds)));
+        }
+        if !(0.1..=10.0).contains(&self.columns_per_second) {
+            return Err(AppError::Config(format!("Spectrogram rate must be 0.1-10 columns/s, got {}", self.columns_per_second)));
+        }
+        Ok(())
+    }
+}
+
+/// 单通道频谱图（get_spectrogram）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpectrogramSegment {
+    pub channel: u32,
+    pub frequency_bins: Vec<f64>,
+    pub timestamps: Vec<f64>,           // 每列对应FFT窗口末尾样本的时间戳
+    pub columns: Vec<Vec<f32>>,         // [时间][频点] 幅值
+}
+
+/// 快照中的频谱：频谱图缓冲中最新一列（显示路径，全部通道）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SnapshotSpectrum {
+    pub timestamp: f64,
+    pub frequency_bins: Vec<f64>,
+    pub spectra: Vec<Vec<f32>>,         // [通道][频点] 幅值
+}
+
+/// capture_snapshot 保存的 snapshot.json：采集参数与文件清单
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotInfo {
+    pub captured_at: chrono::DateTime<chrono::Utc>,
+    pub stream: StreamInfo,
+    pub requested_seconds: f64,
+    pub raw_seconds: f64,               // 历史缓冲不足时小于请求的时长
+    pub raw_samples: usize,
+    pub spectrum_timestamp: Option<f64>,
+    pub notch_filter: NotchFilterConfig,
+    pub filters: FilterConfig,
+    pub files: Vec<String>,
+}
+
+/// 自动录制触发条件
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerCondition {
+    /// 收到标签完全一致的LSL标记
+    Marker { label: String },
+    /// 通道幅值（绝对值，µV）越过阈值
+    Amplitude { channel: u32, threshold: f64, above: bool },
+    /// 通道频带功率越过阈值，relative 为相对功率（0-1），否则为绝对功率（µV²）
+    BandPower { channel: u32, band: String, threshold: f64, above: bool, relative: bool },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerAction {
+    Start,
+    Stop,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TriggerRule {
+    pub action: TriggerAction,
+    pub condition: TriggerCondition,
+}
+
+/// 事件触发录制配置 - 阈值条件只在越过阈值的瞬间触发，回落后重新布防
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct RecordingTriggerConfig {
+    pub rules: Vec<TriggerRule>,
+    pub filename: String,               // 触发录制的基础文件名，实际文件名追加启动时间
+    pub options: RecordingOptions,
+}
+
+impl RecordingTriggerConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let starts = self.rules.iter().any(|rule| rule.action == TriggerAction::Start);
+        if starts && self.filename.trim().is_empty() {
+            return Err(AppError::Config("Trigger filename is required for start rules".to_string()));
+        }
+        for rule in &self.rules {
+            match &rule.condition {
+                TriggerCondition::Marker { label } if label.is_empty() => {
+                    return Err(AppError::Config("Marker trigger label must not be empty".to_string()));
+                }
+                TriggerCondition::Amplitude { threshold, .. } | TriggerCondition::BandPower { threshold, .. }
+                    if !threshold.is_finite() => {
+                    return Err(AppError::Config(format!("Invalid trigger threshold: {}", threshold)));
+                }
+                _ => {}
+            }
+        }
+        self.options.validate()
+    }
+}
+
+/// 录制被触发规则切换（recording-triggered）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordingTriggerEvent {
+    pub action: TriggerAction,
+    pub rule_index: usize,
+    pub reason: String,
+    pub timestamp: f64,                 // 触发时刻的LSL时间戳
+    pub filename: Option<String>,
+}
+
+/// 神经反馈规则 - 频带功率持续越过阈值 hold_ms 后触发，回落超过滞回量后释放
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FeedbackRule {
+    pub name: String,
+    pub channel: u32,                   // 显示路径（导联之后）的通道序号
+    pub band: String,
+    pub relative: bool,                 // 相对功率（0-1），否则为绝对功率（µV²）
+    pub threshold: f64,
+    pub above: bool,
+    pub hold_ms: u64,                   // 条件需持续的时长
+    pub hysteresis: f64,                // 释放阈值与触发阈值的距离（同单位）
+    pub refractory_ms: u64,             // 两次触发之间的最短间隔
+}
+
+impl Default for FeedbackRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            channel: 0,
+            band: "alpha".to_string(),
+            relative: false,
+            threshold: 0.0,
+            above: true,
+            hold_ms: 500,
+            hysteresis: 0.0,
+            refractory_ms: 1000,
+        }
+    }
+}
+
+/// 神经反馈规则集
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct NeurofeedbackConfig {
+    pub rules: Vec<FeedbackRule>,
+}
+
+impl NeurofeedbackConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        for rule in &self.rules {
+            if rule.band.trim().is_empty() {
+                return Err(AppError::Config(format!("Feedback rule '{}' has no band", rule.name)));
+            }
+            if !rule.threshold.is_finite() || !rule.hysteresis.is_finite() || rule.hysteresis < 0.0 {
+                return Err(AppError::Config(format!(
+                    "Invalid threshold/hysteresis for feedback rule '{}': {} / {}", rule.name, rule.threshold, rule.hysteresis
+                )));
+            }
+            if rule.hold_ms > 60_000 || rule.refractory_ms > 600_000 {
+                return Err(AppError::Config(format!("Feedback rule '{}' hold/refractory period too long", rule.name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// feedback-trigger 事件负载：active 为 true 表示触发，false 表示释放
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FeedbackEvent {
+    pub rule_index: usize,
+    pub name: String,
+    pub active: bool,
+    pub value: f64,
+    pub threshold: f64,
+    pub timestamp: f64,
+}
+
+/// OSC映射的数据来源
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OscSource {
+    /// 频带功率，channel 为 None 时取所有通道的平均
+    BandPower {
+        band: String,
+        #[serde(default)]
+        channel: Option<u32>,
+        #[serde(default)]
+        relative: bool,
+    },
+    /// 神经反馈触发/释放，rule 为 None 时转发所有规则
+    FeedbackTrigger {
+        #[serde(default)]
+        rule: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OscMapping {
+    pub address: String,
+    pub source: OscSource,
+}
+
+/// OSC输出配置 - 将选定指标以UDP发送给音视频工具
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct OscConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub mappings: Vec<OscMapping>,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 9000,
+            mappings: Vec::new(),
+        }
+    }
+}
+
+impl OscConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.host.trim().is_empty() || self.port == 0 {
+            return Err(AppError::Config(format!("Invalid OSC target {}:{}", self.host, self.port)));
+        }
+        for mapping in &self.mappings {
+            // OSC地址模式保留字符不能出现在发送地址中
+            if !mapping.address.starts_with('/')
+                || mapping.address.chars().any(|c| c.is_whitespace() || "#*,?[]{}".contains(c))
+            {
+                return Err(AppError::Config(format!("Invalid OSC address '{}'", mapping.address)));
+            }
+            if let OscSource::BandPower { band, .. } = &mapping.source {
+                if band.trim().is_empty() {
+                    return Err(AppError::Config(format!("OSC mapping '{}' has no band", mapping.address)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// MQTT发布主题 - "{stream}" 替换为会话ID（系统健康为全局，替换为 "all"）；为 None 时不发布该项
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MqttTopics {
+    pub band_power: Option<String>,
+    pub artifacts: Option<String>,
+    pub connection_status: Option<String>,
+    pub health: Option<String>,
+}
+
+impl Default for MqttTopics {
+    fn default() -> Self {
+        Self {
+            band_power: Some("cortexarray/{stream}/band_power".to_string()),
+            artifacts: Some("cortexarray/{stream}/artifacts".to_string()),
+            connection_status: Some("cortexarray/status".to_string()),
+            health: Some("cortexarray/health".to_string()),
+        }
+    }
+}
+
+impl MqttTopics {
+    /// 事件名对应的主题模板
+    pub fn for_event(&self, event: &str) -> Option<&str> {
+        match event {
+            "band-power-update" => self.band_power.as_deref(),
+            "artifact-detected" => self.artifacts.as_deref(),
+            "connection-status-changed" => self.connection_status.as_deref(),
+            "system-health" => self.health.as_deref(),
+            _ => None,
+        }
+    }
+    
+    fn all(&self) -> impl Iterator<Item = &String> {
+        [&self.band_power, &self.artifacts, &self.connection_status, &self.health]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// MQTT输出配置 - 长时间监测时向现有IoT仪表盘发布指标与运行状态
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    #[serde(skip_serializing)]
+    pub password: Option<String>,       // 只保存在内存中，不写入设置文件、不返回前端
+    pub topics: MqttTopics,
+    pub health_interval_secs: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "cortexarray".to_string(),
+            username: None,
+            password: None,
+            topics: MqttTopics::default(),
+            health_interval_secs: 10,
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.host.trim().is_empty() || self.port == 0 {
+            return Err(AppError::Config(format!("Invalid MQTT broker {}:{}", self.host, self.port)));
+        }
+        if self.client_id.trim().is_empty() {
+            return Err(AppError::Config("MQTT client id cannot be empty".to_string()));
+        }
+        if !(1..=3600).contains(&self.health_interval_secs) {
+            return Err(AppError::Config(format!(
+                "MQTT health interval must be 1-3600 seconds, got {}", self.health_interval_secs
+            )));
+        }
+        for topic in self.topics.all() {
+            // 发布主题不能包含通配符
+            if topic.is_empty() || topic.contains(['+', '#', '\0']) {
+                return Err(AppError::Config(format!("Invalid MQTT topic '{}'", topic)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 自动上传目标
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadTarget {
+    #[default]
+    S3,         // AWS S3 或兼容服务（MinIO等），路径式URL
+    Webdav,
+}
+
+/// 录制完成后自动上传配置 - 多个采集点的数据集中到同一存储
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct UploadConfig {
+    pub enabled: bool,
+    pub target: UploadTarget,
+    pub endpoint: String,               // S3 服务地址（如 https://s3.eu-central-1.amazonaws.com）或 WebDAV 目录URL
+    pub bucket: String,                 // 仅 S3
+    pub region: String,                 // 仅 S3
+    pub prefix: String,                 // 对象键/子目录前缀，如 "site-a/2024"
+    pub access_key: Option<String>,     // S3 access key；WebDAV 用户名
+    #[serde(skip_serializing)]
+    pub secret_key: Option<String>,     // S3 secret key；WebDAV 密码（只保存在内存中，不写入设置文件、不返回前端）
+    pub include_sidecars: bool,         // 同时上传辅助流、时钟偏移等旁路文件
+    pub max_retries: u32,               // 失败后的重试次数（间隔按指数增长）
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: UploadTarget::S3,
+            endpoint: String::new(),
+            bucket: String::new(),
+            region: "us-east-1".to_string(),
+            prefix: String::new(),
+            access_key: None,
+            secret_key: None,
+            include_sidecars: true,
+            max_retries: 3,
+        }
+    }
+}
+
+impl UploadConfig {
+    pub const MAX_RETRIES: u32 = 10;
+    
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.max_retries > Self::MAX_RETRIES {
+            return Err(AppError::Config(format!(
+                "Upload retries must be at most {}, got {}", Self::MAX_RETRIES, self.max_retries
+            )));
+        }
+        if self.prefix.split('/').any(|part| part == "..") {
+            return Err(AppError::Config(format!("Invalid upload prefix '{}'", self.prefix)));
+        }
+        // 未启用时允许保存不完整的配置
+        if !self.enabled {
+            return Ok(());
+        }
+        if !(self.endpoint.starts_with("http://") || self.endpoint.starts_with("https://")) {
+            return Err(AppError::Config(format!("Upload endpoint must be an http(s) URL, got '{}'", self.endpoint)));
+        }
+        if self.target == UploadTarget::S3 {
+            if self.bucket.trim().is_empty() || self.region.trim().is_empty() {
+                return Err(AppError::Config("S3 upload requires a bucket and region".to_string()));
+            }
+            if self.missing_credentials() {
+                return Err(AppError::Config("S3 upload requires an access key and secret key".to_string()));
+            }
+        }
+        Ok(())
+    }
+    
+    /// S3 上传缺少 access key 或 secret key
+    pub fn missing_credentials(&self) -> bool {
+        let has_key = |key: &Option<String>| key.as_deref().is_some_and(|key| !key.is_empty());
+        self.target == UploadTarget::S3 && (!has_key(&self.access_key) || !has_key(&self.secret_key))
+    }
+}
+
+/// 上传状态
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    Uploading,
+    Retrying,       // 本次尝试失败，等待重试
+    Completed,
+    Failed,         // 重试用尽或不可重试的错误
+}
+
+/// 上传进度事件（upload-progress）- 上传中约每秒推送，状态变化时立即推送
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UploadProgress {
+    pub file: String,               // 本地文件
+    pub url: String,                // 上传目标
+    pub status: UploadStatus,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+    pub attempt: u32,               // 从 1 开始
+    pub error: Option<String>,
+}
+
+/// 持久化设置 - 保存在应用配置目录的 settings.json，启动时加载，连接新流时作为默认配置应用
+///
+/// 字段与各 set_* 命令未指定 stream_id 时保存的默认配置一一对应；缺失的字段取默认值。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub output_directory: Option<String>,   // 相对路径的录制文件名以此为基准
+    pub recording_naming: RecordingNamingConfig,
+    pub acquisition_mode: AcquisitionMode,
+    pub chunk_config: ChunkConfig,
+    pub inlet_config: LslInletConfig,
+    pub rate_monitor: RateMonitorConfig,
+    pub channel_config: PipelineChannelConfig,
+    pub fft_config: FftConfig,
+    pub spectrum_range: SpectrumRange,
+    pub spectrum_display: SpectrumDisplayOptions,
+    pub spectral_metrics: SpectralMetricsConfig,
+    pub connectivity: ConnectivityConfig,
+    pub frequency_bands: FrequencyBands,
+    pub notch_filter: NotchFilterConfig,
+    pub filters: FilterConfig,
+    pub signal_quality: SignalQualityConfig,
+    pub artifacts: ArtifactConfig,
+    pub asr: AsrConfig,
+    pub ica: IcaConfig,
+    pub display_rate: DisplayRateConfig,
+    pub frame_rate: FrameRateConfig,
+    pub processing_stages: ProcessingStagesConfig,
+    pub scripting: ScriptingConfig,
+    pub history: HistoryConfig,
+    pub spectrogram: SpectrogramConfig,
+    pub recording_triggers: RecordingTriggerConfig,
+    pub epochs: EpochConfig,
+    pub erp: ErpConfig,
+    pub time_features: TimeFeaturesConfig,
+    pub neurofeedback: NeurofeedbackConfig,
+    pub ssvep: SsvepConfig,
+    pub classifier: ClassifierConfig,
+    pub vitals: VitalsConfig,
+    pub impedance: ImpedanceConfig,
+    pub osc: OscConfig,
+    pub mqtt: MqttConfig,
+    pub montage: MontageConfig,
+    pub upload: UploadConfig,
+}
+
+impl Settings {
+    /// 与流无关的校验；滤波器（依赖采样率）与导联（依赖通道）在连接时校验
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.output_directory.as_ref().is_some_and(|dir| dir.trim().is_empty()) {
+            return Err(AppError::Config("Output directory cannot be empty".to_string()));
+        }
+        self.recording_naming.validate()?;
+        self.chunk_config.validate()?;
+        self.inlet_config.validate()?;
+        self.rate_monitor.validate()?;
+        self.channel_config.validate()?;
+        self.fft_config.validate()?;
+        self.spectrum_range.validate()?;
+        self.spectrum_display.validate()?;
+        self.spectral_metrics.validate()?;
+        self.connectivity.validate()?;
+        self.frequency_bands.validate()?;
+        self.notch_filter.validate()?;
+        self.signal_quality.validate()?;
+        self.artifacts.validate()?;
+        self.asr.validate()?;
+        self.ica.validate()?;
+        self.display_rate.validate()?;
+        self.frame_rate.validate()?;
+        self.processing_stages.validate()?;
+        self.scripting.validate()?;
+        self.history.validate()?;
+        self.spectrogram.validate()?;
+        self.recording_triggers.validate()?;
+        self.epochs.validate()?;
+        self.erp.validate()?;
+        self.time_features.validate()?;
+        self.neurofeedback.validate()?;
+        self.ssvep.validate()?;
+        self.classifier.validate()?;
+        self.vitals.validate()?;
+        self.impedance.validate()?;
+        self.osc.validate()?;
+        self.mqtt.validate()?;
+        self.upload.validate()
+    }
+}
+
+/// 标记锁定分段配置 - 作用于显示路径（滤波、导联之后）的数据
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EpochConfig {
+    pub enabled: bool,
+    pub pre_seconds: f64,               // 标记前时长
+    pub post_seconds: f64,              // 标记后时长
+    pub baseline_correction: bool,      // 减去标记前区间的均值
+    pub reject_peak_to_peak: Option<f64>,   // 任一通道峰峰值超过该值（µV）即拒绝
+    pub markers: Vec<String>,           // 只对这些标记分段，空为全部
+    pub emit_epochs: bool,              // 推送完整分段数据；关闭时只推送计数
+}
+
+impl Default for EpochConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pre_seconds: 0.2,
+            post_seconds: 0.8,
+            baseline_correction: true,
+            reject_peak_to_peak: Some(150.0),
+            markers: Vec::new(),
+            emit_epochs: true,
+        }
+    }
+}
+
+impl EpochConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(0.0..=10.0).contains(&self.pre_seconds) {
+            return Err(AppError::Config(format!("Epoch pre interval must be 0-10 seconds, got {}", self.pre_seconds)));
+        }
+        if !(self.post_seconds > 0.0 && self.post_seconds <= 10.0) {
+            return Err(AppError::Config(format!("Epoch post interval must be 0-10 seconds, got {}", self.post_seconds)));
+        }
+        if self.baseline_correction && self.pre_seconds == 0.0 {
+            return Err(AppError::Config("Baseline correction requires a pre-marker interval".to_string()));
+        }
+        if let Some(threshold) = self.reject_peak_to_peak {
+            if !threshold.is_finite() || threshold <= 0.0 {
+                return Err(AppError::Config(format!("Invalid rejection threshold: {}", threshold)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单个标记的分段计数
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct EpochCount {
+    pub label: String,
+    pub accepted: u32,
+    pub rejected: u32,
+}
+
+/// 标记锁定的数据分段
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Epoch {
+    pub label: String,
+    pub marker_timestamp: f64,
+    pub sample_id: u64,                 // 标记对齐的首个样本
+    pub sample_rate: f64,
+    pub pre_seconds: f64,
+    pub channel_labels: Vec<String>,
+    pub data: Vec<Vec<f32>>,            // [通道][样本]
+    pub rejected: bool,
+    pub reject_reason: Option<String>,
+}
+
+/// epoch-extracted 事件负载；emit_epochs 关闭时 epoch 为 None
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EpochEvent {
+    pub label: String,
+    pub rejected: bool,
+    pub epoch: Option<Epoch>,
+    pub counts: Vec<EpochCount>,
+}
+
+/// 在线ERP平均配置 - 对分段器接受的分段按标记分别累计平均
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct ErpConfig {
+    pub enabled: bool,
+    pub update_interval_ms: u64,        // erp-update 推送间隔（按数据时间）
+    pub confidence_level: f64,          // 置信区间水平，如0.95
+}
+
+impl Default for ErpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            update_interval_ms: 1000,
+            confidence_level: 0.95,
+        }
+    }
+}
+
+impl ErpConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(100..=60_000).contains(&self.update_interval_ms) {
+            return Err(AppError::Config(format!("ERP update interval must be 100-60000ms, got {}", self.update_interval_ms)));
+        }
+        if !(self.confidence_level > 0.5 && self.confidence_level < 1.0) {
+            return Err(AppError::Config(format!("Confidence level must be between 0.5 and 1, got {}", self.confidence_level)));
+        }
+        Ok(())
+    }
+}
+
+/// 单个标记的ERP平均波形（erp-update）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ErpUpdate {
+    pub label: String,
+    pub trials: u32,
+    pub sample_rate: f64,
+    pub pre_seconds: f64,
+    pub channel_labels: Vec<String>,
+    pub average: Vec<Vec<f32>>,         // [通道][样本]
+    pub ci_lower: Vec<Vec<f32>>,        // 平均值的置信区间下限（试次数<2时等于平均值）
+    pub ci_upper: Vec<Vec<f32>>,
+}
+
+/// 时域特征配置 - 基于显示路径（导联之后）的滑动窗口
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct TimeFeaturesConfig {
+    pub enabled: bool,
+    pub window_seconds: f64,
+    pub update_interval_ms: u64,
+}
+
+impl Default for TimeFeaturesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_seconds: 1.0,
+            update_interval_ms: 500,
+        }
+    }
+}
+
+impl TimeFeaturesConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(0.1..=30.0).contains(&self.window_seconds) {
+            return Err(AppError::Config(format!("Feature window must be 0.1-30 seconds, got {}", self.window_seconds)));
+        }
+        if !(100..=10_000).contains(&self.update_interval_ms) {
+            return Err(AppError::Config(format!("Feature interval must be 100-10000ms, got {}", self.update_interval_ms)));
+        }
+        Ok(())
+    }
+}
+
+/// 单通道时域特征
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChannelTimeFeatures {
+    pub channel_index: u32,
+    pub activity: f64,              // Hjorth活动度（方差，µV²）
+    pub mobility: f64,              // Hjorth移动度
+    pub complexity: f64,            // Hjorth复杂度
+    pub rms: f64,
+    pub kurtosis: f64,              // 超额峰度（高斯为0）
+    pub zero_crossing_rate: f64,    // 去均值后每秒过零次数
+}
+
+/// time-features 事件负载
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimeFeaturesUpdate {
+    pub timestamp: f64,
+    pub window_seconds: f64,
+    pub channel_labels: Vec<String>,
+    pub channels: Vec<ChannelTimeFeatures>,
+}
+
+/// SSVEP检测配置 - 对显示路径（导联之后）的滑动窗口做CCA，参考信号为各目标频率及其谐波的正弦/余弦
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SsvepConfig {
+    pub enabled: bool,
+    pub target_frequencies: Vec<f64>,   // 刺激频率（Hz）
+    pub harmonics: usize,               // 参考信号包含的谐波数（含基频）
+    pub channels: Vec<String>,          // 参与检测的通道标签，空为全部
+    pub window_seconds: f64,
+    pub update_interval_ms: u64,
+    pub min_correlation: f64,           // 最大典型相关低于该值时不给出判定
+}
+
+impl Default for SsvepConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_frequencies: vec![8.57, 10.0, 12.0, 15.0],
+            harmonics: 2,
+            channels: Vec::new(),
+            window_seconds: 2.0,
+            update_interval_ms: 1000,
+            min_correlation: 0.3,
+        }
+    }
+}
+
+impl SsvepConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.target_frequencies.is_empty() {
+            return Err(AppError::Config("SSVEP requires at least one target frequency".to_string()));
+        }
+        if let Some(frequency) = self.target_frequencies.iter().find(|f| !(**f > 0.0 && **f <= 100.0)) {
+            return Err(AppError::Config(format!("SSVEP target frequency must be 0-100 Hz, got {}", frequency)));
+        }
+        if !(1..=5).contains(&self.harmonics) {
+            return Err(AppError::Config(format!("SSVEP harmonics must be 1-5, got {}", self.harmonics)));
+        }
+        if !(0.5..=10.0).contains(&self.window_seconds) {
+            return Err(AppError::Config(format!("SSVEP window must be 0.5-10 seconds, got {}", self.window_seconds)));
+        }
+        if !(200..=10_000).contains(&self.update_interval_ms) {
+            return Err(AppError::Config(format!("SSVEP interval must be 200-10000ms, got {}", self.update_interval_ms)));
+        }
+        if !(0.0..=1.0).contains(&self.min_correlation) {
+            return Err(AppError::Config(format!("SSVEP correlation threshold must be 0-1, got {}", self.min_correlation)));
+        }
+        Ok(())
+    }
+}
+
+/// 单个目标频率的典型相关系数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SsvepScore {
+    pub frequency: f64,
+    pub correlation: f64,
+}
+
+/// ssvep-detection 事件负载
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SsvepDetection {
+    pub timestamp: f64,
+    pub window_seconds: f64,
+    pub channel_labels: Vec<String>,    // 参与检测的通道
+    pub scores: Vec<SsvepScore>,        // 顺序与 target_frequencies 一致
+    pub detected_hz: Option<f64>,       // 相关最高且超过 min_correlation 的目标
+    pub confidence: f64,                // (最高 - 次高) / 最高，0-1
+}
+
+/// 单试次分类配置 - 特征为标记后 [start, end) 区间内各通道按 bin_ms 分箱的均值（P300类ERP）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    pub target_markers: Vec<String>,        // 目标类标记
+    pub non_target_markers: Vec<String>,    // 非目标类标记，空为其余全部标记
+    pub channels: Vec<String>,              // 特征通道标签，空为全部
+    pub start_seconds: f64,                 // 相对标记
+    pub end_seconds: f64,
+    pub bin_ms: u64,
+    pub shrinkage: f64,                     // LDA协方差收缩系数（0-1）
+    pub collect_training: bool,             // 将带标签的分段加入训练集
+    pub max_training_epochs: usize,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            target_markers: Vec::new(),
+            non_target_markers: Vec::new(),
+            channels: Vec::new(),
+            start_seconds: 0.1,
+            end_seconds: 0.8,
+            bin_ms: 50,
+            shrinkage: 0.1,
+            collect_training: true,
+            max_training_epochs: 2000,
+        }
+    }
+}
+
+impl ClassifierConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(0.0..=10.0).contains(&self.start_seconds) || self.end_seconds <= self.start_seconds || self.end_seconds > 10.0 {
+            return Err(AppError::Config(format!(
+                "Classifier window must satisfy 0 <= start < end <= 10 seconds, got {}-{}", self.start_seconds, self.end_seconds
+            )));
+        }
+        if !(10..=1000).contains(&self.bin_ms) {
+            return Err(AppError::Config(format!("Classifier bin must be 10-1000ms, got {}", self.bin_ms)));
+        }
+        if !(0.0..=1.0).contains(&self.shrinkage) {
+            return Err(AppError::Config(format!("LDA shrinkage must be 0-1, got {}", self.shrinkage)));
+        }
+        if !(10..=100_000).contains(&self.max_training_epochs) {
+            return Err(AppError::Config(format!("Training set size must be 10-100000, got {}", self.max_training_epochs)));
+        }
+        if let Some(label) = self.target_markers.iter().find(|label| self.non_target_markers.contains(label)) {
+            return Err(AppError::Config(format!("Marker '{}' is both target and non-target", label)));
+        }
+        Ok(())
+    }
+}
+
+/// 分类器状态（train_classifier / classify_online / get_classifier_status 的返回值）
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ClassifierStatus {
+    pub classifier: String,
+    pub training_epochs: usize,
+    pub target_epochs: usize,
+    pub trained: bool,
+    pub training_accuracy: Option<f64>,     // 训练集上的分类准确率
+    pub online: bool,
+}
+
+/// classifier-prediction 事件负载
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClassifierPrediction {
+    pub label: String,                      // 分段的标记
+    pub marker_timestamp: f64,
+    pub probability: f64,                   // 属于目标类的概率
+    pub is_target: bool,                    // probability >= 0.5
+    pub expected_target: Option<bool>,      // 标记属于已配置的类别时给出真实类别
+}
+
+/// 空间滤波器 - 每个输出为导联后各通道的线性组合（如CSP成分），替换送往显示/FFT/频带功率的通道
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpatialFilter {
+    pub input_labels: Vec<String>,      // 须与导联后的通道标签一致
+    pub output_labels: Vec<String>,
+    pub weights: Vec<Vec<f64>>,         // [输出][输入]
+}
+
+impl SpatialFilter {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.input_labels.is_empty() || self.output_labels.is_empty() {
+            return Err(AppError::Config("Spatial filter needs at least one input and one output".to_string()));
+        }
+        if self.weights.len() != self.output_labels.len()
+            || self.weights.iter().any(|row| row.len() != self.input_labels.len())
+        {
+            return Err(AppError::Config(format!(
+                "Spatial filter weights must be {}x{}", self.output_labels.len(), self.input_labels.len()
+            )));
+        }
+        if self.weights.iter().flatten().any(|weight| !weight.is_finite()) {
+            return Err(AppError::Config("Spatial filter weights must be finite".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// CSP训练请求 - 两类标记的分段，取标记后 [start, end) 区间
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CspTrainingRequest {
+    pub class_a: Vec<String>,
+    pub class_b: Vec<String>,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub components_per_class: usize,    // 两端各取的成分数
+}
+
+impl Default for CspTrainingRequest {
+    fn default() -> Self {
+        Self {
+            class_a: Vec::new(),
+            class_b: Vec::new(),
+            start_seconds: 0.5,
+            end_seconds: 2.5,
+            components_per_class: 2,
+        }
+    }
+}
+
+impl CspTrainingRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.class_a.is_empty() || self.class_b.is_empty() {
+            return Err(AppError::Config("CSP needs markers for both classes".to_string()));
+        }
+        if let Some(label) = self.class_a.iter().find(|label| self.class_b.contains(label)) {
+            return Err(AppError::Config(format!("Marker '{}' is in both CSP classes", label)));
+        }
+        if !(0.0..=10.0).contains(&self.start_seconds) || self.end_seconds <= self.start_seconds || self.end_seconds > 10.0 {
+            return Err(AppError::Config(format!(
+                "CSP window must satisfy 0 <= start < end <= 10 seconds, got {}-{}", self.start_seconds, self.end_seconds
+            )));
+        }
+        if !(1..=16).contains(&self.components_per_class) {
+            return Err(AppError::Config(format!("CSP components per class must be 1-16, got {}", self.components_per_class)));
+        }
+        Ok(())
+    }
+}
+
+/// train_csp 结果；filter 可直接传给 set_spatial_filter
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CspResult {
+    pub filter: SpatialFilter,
+    pub eigenvalues: Vec<f64>,          // 与输出顺序一致，接近1为A类方差占优，接近0为B类
+    pub epochs_a: usize,
+    pub epochs_b: usize,
+}
+
+/// 生理信号配置 - 指向原始流中的ECG/呼吸通道（不受通道选择与导联影响）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct VitalsConfig {
+    pub enabled: bool,
+    pub ecg_channel: Option<u32>,
+    pub respiration_channel: Option<u32>,
+    pub update_interval_ms: u64,
+}
+
+impl Default for VitalsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ecg_channel: None,
+            respiration_channel: None,
+            update_interval_ms: 1000,
+        }
+    }
+}
+
+impl VitalsConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.enabled && self.ecg_channel.is_none() && self.respiration_channel.is_none() {
+            return Err(AppError::Config("Vitals need an ECG or respiration channel".to_string()));
+        }
+        if !(200..=10_000).contains(&self.update_interval_ms) {
+            return Err(AppError::Config(format!("Vitals interval must be 200-10000ms, got {}", self.update_interval_ms)));
+        }
+        Ok(())
+    }
+}
+
+/// vitals-update 事件负载；数据不足时对应字段为 None
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VitalsUpdate {
+    pub timestamp: f64,
+    pub heart_rate_bpm: Option<f64>,        // 最近10秒RR间期中位数换算
+    pub rr_intervals_ms: Vec<f64>,          // 最近10秒的RR间期
+    pub hrv_rmssd_ms: Option<f64>,
+    pub last_r_peak: Option<f64>,           // 最近R波时间戳（含检测延迟约0.1秒）
+    pub respiration_rate_bpm: Option<f64>,  // 最近30秒呼吸周期中位数换算（次/分）
+}
+
+/// 阻抗监测配置 - 分级阈值与阻抗检查协议参数
+///
+/// 检查协议适用于自带导联脱落检测电流的放大器（如ADS1299）：开启驱动电流后，
+/// 电极阻抗 = 驱动频率处的电压幅值 / 驱动电流幅值 − 串联电阻。
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct ImpedanceConfig {
+    pub good_kohm: f64,                 // 不超过该值为良好
+    pub poor_kohm: f64,                 // 超过该值为差，介于两者之间为一般
+    pub drive_frequency: f64,           // 检测电流频率（Hz）
+    pub drive_current_na: f64,          // 检测电流幅值（nA）
+    pub series_resistance_kohm: f64,    // 放大器输入端的串联电阻，从结果中扣除
+    pub check_seconds: f64,             // 检查协议的测量时长
+}
+
+impl Default for ImpedanceConfig {
+    fn default() -> Self {
+        Self {
+            good_kohm: 10.0,
+            poor_kohm: 50.0,
+            drive_frequency: 31.25,
+            drive_current_na: 6.0,
+            series_resistance_kohm: 2.2,
+            check_seconds: 2.0,
+        }
+    }
+}
+
+impl ImpedanceConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(self.good_kohm > 0.0 && self.good_kohm < self.poor_kohm && self.poor_kohm.is_finite()) {
+            return Err(AppError::Config("Impedance thresholds must satisfy 0 < good < poor".to_string()));
+        }
+        if !(self.drive_frequency > 0.0 && self.drive_frequency.is_finite()) {
+            return Err(AppError::Config(format!("Invalid impedance drive frequency: {}", self.drive_frequency)));
+        }
+        if !(self.drive_current_na > 0.0 && self.drive_current_na.is_finite()) {
+            return Err(AppError::Config(format!("Invalid impedance drive current: {}nA", self.drive_current_na)));
+        }
+        if !(self.series_resistance_kohm >= 0.0 && self.series_resistance_kohm.is_finite()) {
+            return Err(AppError::Config("Series resistance must be non-negative".to_string()));
+        }
+        if !(0.5..=30.0).contains(&self.check_seconds) {
+            return Err(AppError::Config("Impedance check must last 0.5-30 seconds".to_string()));
+        }
+        Ok(())
+    }
+    
+    pub fn status(&self, kohm: f64) -> ImpedanceStatus {
+        if kohm <= self.good_kohm {
+            ImpedanceStatus::Good
+        } else if kohm <= self.poor_kohm {
+            ImpedanceStatus::Fair
+        } else {
+            ImpedanceStatus::Poor
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImpedanceStatus {
+    Good,
+    Fair,
+    Poor,
+}
+
+/// 阻抗值的来源
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpedanceSource {
+    Stream,             // 放大器发布的阻抗LSL流
+    ChannelMetadata,    // EEG流描述中各通道的 <impedance>
+    Check,              // 检查协议（检测电流幅值换算）
+}
+
+/// 单个电极的阻抗
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ElectrodeImpedance {
+    pub label: String,
+    pub kohm: f64,
+    pub status: ImpedanceStatus,
+    pub source: ImpedanceSource,
+    pub timestamp: f64,                 // 测量时刻（数据源时钟）
+}
+
+/// impedance-update 事件负载：本次测得的电极
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImpedanceUpdate {
+    pub source: ImpedanceSource,
+    pub timestamp: f64,
+    pub electrodes: Vec<ElectrodeImpedance>,
+}
+
+/// 高通/低通/带通滤波配置 - 两个截止频率都设置即为带通
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FilterConfig {
+    pub hp: Option<f64>,            // 高通截止频率（Hz）
+    pub lp: Option<f64>,            // 低通截止频率（Hz）
+    pub order: usize,               // Butterworth 阶数（偶数）
+    pub apply_to_recording: bool,   // false 时录制路径保留原始数据，仅滤波显示路径
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            hp: None,
+            lp: None,
+            order: 4,
+            apply_to_recording: false,
+        }
+    }
+}
+
+impl FilterConfig {
+    pub const MAX_ORDER: usize = 8;
+    
+    pub fn validate(&self, sample_rate: f64) -> Result<(), AppError> {
+        if self.order == 0 || !self.order.is_multiple_of(2) || self.order > Self::MAX_ORDER {
+            return Err(AppError::Config(format!(
+                "Filter order must be an even number between 2 and {}, got {}",
+                Self::MAX_ORDER, self.order
+            )));
+        }
+        
+        for (name, cutoff) in [("High-pass", self.hp), ("Low-pass", self.lp)] {
+            if let Some(cutoff) = cutoff {
+                if !cutoff.is_finite() || cutoff <= 0.0 {
+                    return Err(AppError::Config(format!("{} cutoff must be positive, got {}", name, cutoff)));
+                }
+                if sample_rate > 0.0 && cutoff >= sample_rate / 2.0 {
+                    return Err(AppError::Config(format!(
+                        "{} cutoff {}Hz must be below the Nyquist frequency ({}Hz)",
+                        name, cutoff, sample_rate / 2.0
+                    )));
+                }
+            }
+        }
+        
+        if let (Some(hp), Some(lp)) = (self.hp, self.lp) {
+            if hp >= lp {
+                return Err(AppError::Config(format!(
+                    "High-pass cutoff {}Hz must be below low-pass cutoff {}Hz", hp, lp
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// 通道引用 - 可用索引或标签（如 "Cz"）指定
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ChannelRef {
+    Index(usize),
+    Label(String),
+}
+
+/// 导联/重参考配置，仅作用于显示与FFT路径
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MontageConfig {
+    #[default]
+    Raw,                                                    // 保持放大器原始参考
+    CommonAverage,                                          // 共平均参考
+    LinkedMastoids { left: Option<ChannelRef>, right: Option<ChannelRef> }, // 默认 A1/A2（M1/M2、TP9/TP10）
+    Bipolar { pairs: Vec<(ChannelRef, ChannelRef)> },       // 双极导联
+    CustomReference { channels: Vec<ChannelRef> },          // 以所选通道均值为参考
+    Preset { name: String },                                // 10-20系统预设
+}
+
+/// 当前生效的导联信息
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct LslStreamInfo {
-    pub name: String,
-    pub stream_type: String,
-    pub channels_count: u32,
-    pub sample_rate: f64,
-    pub source_id: String,
-    pub hostname: String,
+pub struct MontageInfo {
+    pub config: MontageConfig,
+    pub channel_labels: Vec<String>,   // 导联后的通道标签
+}
+
+/// 通道选择 - 被排除的通道（如坏电极）不参与显示、导联、FFT和频带功率
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChannelSelection {
+    pub active: Option<Vec<u32>>,   // 参与处理的原始通道索引，None为全部
+    pub record_excluded: bool,      // 录制中仍写入被排除通道的原始数据，否则写入零值
+}
+
+impl Default for ChannelSelection {
+    fn default() -> Self {
+        Self {
+            active: None,
+            record_excluded: true,
+        }
+    }
 }
 
+impl ChannelSelection {
+    pub fn validate(&self, channels_count: u32) -> Result<(), AppError> {
+        let Some(active) = &self.active else { return Ok(()) };
+        
+        if active.is_empty() {
+            return Err(AppError::Config("At least one channel must remain active".to_string()));
+        }
+        if let Some(&index) = active.iter().find(|&&index| index >= channels_count) {
+            return Err(AppError::Config(format!(
+                "Channel index {} out of range (stream has {} channels)", index, channels_count
+            )));
+        }
+        Ok(())
+    }
+    
+    /// 每个原始通道是否参与处理
+    pub fn mask(&self, channels_count: u32) -> Vec<bool> {
+        match &self.active {
+            None => vec![true; channels_count as usize],
+            Some(active) => (0..channels_count).map(|index| active.contains(&index)).collect(),
+        }
+    }
+}
+
+/// set_active_channels 的结果
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct StreamInfo {
+pub struct ChannelSelectionInfo {
+    pub selection: ChannelSelection,
+    pub active_labels: Vec<String>,     // 参与处理的原始通道标签
+}
+
+/// 可用导联预设描述
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MontagePresetInfo {
     pub name: String,
-    pub stream_type: String,
-    pub channels_count: u32,
-    pub sample_rate: f64,
-    pub is_connected: bool,
-    pub source_id: String,
+    pub description: String,
+    pub available: bool,       // 当前流的通道标签能否构建该预设
+}
+
+/// 频带定义
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FrequencyBand {
+    pub name: String,
+    pub low_hz: f64,     // 下边界（含）
+    pub high_hz: f64,    // 上边界（不含）
+}
+
+impl FrequencyBand {
+    pub fn new(name: &str, low_hz: f64, high_hz: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            low_hz,
+            high_hz,
+        }
+    }
+}
+
+/// 频带定义集合 - 默认为经典的 delta/theta/alpha/beta/gamma
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct FrequencyBands(pub Vec<FrequencyBand>);
+
+impl Default for FrequencyBands {
+    fn default() -> Self {
+        Self(vec![
+            FrequencyBand::new("delta", 0.5, 4.0),
+            FrequencyBand::new("theta", 4.0, 8.0),
+            FrequencyBand::new("alpha", 8.0, 13.0),
+            FrequencyBand::new("beta", 13.0, 30.0),
+            FrequencyBand::new("gamma", 30.0, 45.0),
+        ])
+    }
+}
+
+impl FrequencyBands {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.0.is_empty() {
+            return Err(AppError::Config("At least one frequency band is required".to_string()));
+        }
+        
+        for (i, band) in self.0.iter().enumerate() {
+            if band.name.trim().is_empty() {
+                return Err(AppError::Config(format!("Frequency band #{} has no name", i)));
+            }
+            if !band.low_hz.is_finite() || !band.high_hz.is_finite()
+                || band.low_hz < 0.0 || band.high_hz <= band.low_hz
+            {
+                return Err(AppError::Config(format!(
+                    "Invalid edges for band '{}': {}-{}Hz", band.name, band.low_hz, band.high_hz
+                )));
+            }
+            if self.0[..i].iter().any(|other| other.name == band.name) {
+                return Err(AppError::Config(format!("Duplicate frequency band '{}'", band.name)));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().map(|band| band.name.clone()).collect()
+    }
+    
+    /// 所有频带覆盖的总频率范围，用于计算相对功率
+    pub fn span(&self) -> (f64, f64) {
+        let low = self.0.iter().map(|band| band.low_hz).fold(f64::INFINITY, f64::min);
+        let high = self.0.iter().map(|band| band.high_hz).fold(0.0, f64::max);
+        (low, high)
+    }
 }
 
+/// 单通道频带功率
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct EegSample {
+pub struct ChannelBandPower {
+    pub channel_index: u32,
+    pub absolute: Vec<f64>,    // µV²，顺序与 bands 一致
+    pub relative: Vec<f64>,    // 占所有频带覆盖范围总功率的比例
+}
+
+/// band-power-update 事件负载
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BandPowerUpdate {
+    pub batch_id: u64,
+    pub bands: Vec<String>,
+    pub channels: Vec<ChannelBandPower>,
+}
+
+/// 定量EEG频谱指标配置 - 每秒基于完整分辨率幅值谱计算一次
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct SpectralMetricsConfig {
+    pub enabled: bool,
+    pub alpha_min_hz: f64,          // 个体alpha峰值搜索范围
+    pub alpha_max_hz: f64,
+    pub min_hz: f64,                // 总功率与边缘频率的计算范围
+    pub max_hz: f64,
+    pub edge_fraction: f64,         // 边缘频率的累计功率比例，如0.95（SEF95）
+}
+
+impl Default for SpectralMetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            alpha_min_hz: 7.0,
+            alpha_max_hz: 14.0,
+            min_hz: 1.0,
+            max_hz: 40.0,
+            edge_fraction: 0.95,
+        }
+    }
+}
+
+impl SpectralMetricsConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let valid_range = |low: f64, high: f64| low.is_finite() && high.is_finite() && low >= 0.0 && high > low;
+        if !valid_range(self.alpha_min_hz, self.alpha_max_hz) {
+            return Err(AppError::Config(format!("Invalid alpha range {}-{}Hz", self.alpha_min_hz, self.alpha_max_hz)));
+        }
+        if !valid_range(self.min_hz, self.max_hz) {
+            return Err(AppError::Config(format!("Invalid metrics range {}-{}Hz", self.min_hz, self.max_hz)));
+        }
+        if !(self.edge_fraction > 0.0 && self.edge_fraction < 1.0) {
+            return Err(AppError::Config(format!("Edge fraction must be between 0 and 1, got {}", self.edge_fraction)));
+        }
+        Ok(())
+    }
+}
+
+/// 单通道频谱指标
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChannelSpectralMetrics {
+    pub channel_index: u32,
+    pub peak_alpha_hz: Option<f64>,     // alpha范围内无局部峰值时为None
+    pub spectral_edge_hz: Option<f64>,  // 范围内无功率时为None
+    pub total_power: f64,               // 幅值平方和，单位与频带功率一致
+}
+
+/// spectral-metrics 事件负载
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpectralMetricsUpdate {
+    pub batch_id: u64,
     pub timestamp: f64,
-    pub channels: Vec<f64>,
-    pub sample_id: u64,
+    pub edge_fraction: f64,
+    pub channels: Vec<ChannelSpectralMetrics>,
+}
+
+/// 通道间连接性配置 - 在FFT阶段累计互谱，按间隔输出各频带的NxN矩阵
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ConnectivityConfig {
+    pub enabled: bool,
+    pub bands: Vec<String>,             // 参与计算的频带名称（频带定义中的名称），空为全部
+    pub include_plv: bool,              // 同时计算锁相值（PLV）
+    pub update_interval_ms: u64,        // 累计时长，每个间隔输出一次并重新累计
+}
+
+impl Default for ConnectivityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bands: Vec::new(),
+            include_plv: false,
+            update_interval_ms: 1000,
+        }
+    }
+}
+
+impl ConnectivityConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(200..=10_000).contains(&self.update_interval_ms) {
+            return Err(AppError::Config(format!("Connectivity interval must be 200-10000ms, got {}", self.update_interval_ms)));
+        }
+        Ok(())
+    }
 }
 
+/// 单个频带的连接性矩阵（对称，对角线为1）
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct EegBatch {
-    pub samples: Vec<EegSample>,
+pub struct ConnectivityBand {
+    pub name: String,
+    pub coherence: Vec<Vec<f32>>,       // 幅值平方相干（MSC）
+    pub plv: Option<Vec<Vec<f32>>>,
+}
+
+/// connectivity-update 事件负载
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConnectivityUpdate {
     pub batch_id: u64,
+    pub timestamp: f64,
     pub channels_count: u32,
-    pub sample_rate: f64,
+    pub frames: u32,                    // 本次累计的FFT帧数，帧数越多估计越稳定
+    pub bands: Vec<ConnectivityBand>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FreqData {
-    pub channel_index: u32,
-    pub spectrum: Vec<f64>,
-    pub frequency_bins: Vec<f64>,
-    pub batch_id: Option<u64>,  // ✅ 添加批次ID关联
+/// 分析阶段产生的事件，统一交给前端线程发送
+#[derive(Clone, Debug)]
+pub enum AnalysisEvent {
+    BandPower(BandPowerUpdate),
+    SpectralMetrics(SpectralMetricsUpdate),
+    Connectivity(ConnectivityUpdate),
+    ScriptMetrics(ScriptMetricsUpdate),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FramePayload {
     pub time_domain: EegBatch,
     pub frequency_domain: Vec<FreqData>,
+    #[serde(default)]
+    pub aux: Option<AuxBatch>,
 }
 
 
@@ -54,15 +3466,271 @@ pub struct FramePayload {
 pub struct ConnectionStatus {
     pub is_lsl_connected: bool,
     pub is_processor_running: bool,
-    pub current_stream: Option<StreamInfo>,
+    pub current_stream: Option<StreamInfo>,    // 主会话的流
+    pub sessions: Vec<SessionInfo>,
+}
+
+/// 后台流发现的变化（stream-appeared / stream-disappeared 事件的内容为其中的流信息）
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiscoveryEvent {
+    Appeared(LslStreamInfo),
+    Disappeared(LslStreamInfo),
+}
+
+/// LSL数据源状态变化
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceEvent {
+    Connected,
+    Lost,
+    Recovered,
+    RateDeviation,      // 实际采样率偏离名义值超过容差
+    RateNormal,         // 实际采样率回到容差内
+}
+
+/// 实际采样率监测配置
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RateMonitorConfig {
+    pub window_seconds: f64,        // 估计窗口长度
+    pub tolerance_percent: f64,     // 相对名义采样率的允许偏差
+}
+
+impl Default for RateMonitorConfig {
+    fn default() -> Self {
+        Self {
+            window_seconds: 10.0,
+            tolerance_percent: 1.0,
+        }
+    }
+}
+
+impl RateMonitorConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(1.0..=600.0).contains(&self.window_seconds) {
+            return Err(AppError::Config("Rate window must be between 1 and 600 seconds".to_string()));
+        }
+        if !(self.tolerance_percent > 0.0 && self.tolerance_percent <= 50.0) {
+            return Err(AppError::Config("Rate tolerance must be between 0 and 50%".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 采样率偏差警告（sample-rate-warning）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SampleRateWarning {
+    pub nominal_sample_rate: f64,
+    pub effective_sample_rate: f64,
+    pub deviation_percent: f64,
+    pub tolerance_percent: f64,
+}
+
+/// 已连接流会话的概要信息
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionInfo {
+    pub stream_id: String,
+    pub stream_info: StreamInfo,
+    pub is_primary: bool,
+    pub event_suffix: Option<String>,   // 非主会话的事件名后缀，如 ":amp2"
+    pub is_recording: bool,
+    pub is_capturing: bool,             // 正在采集原始样本流（.cxcap）
+    pub is_playback: bool,              // 录制文件回放会话
+    pub stream_lost: bool,              // LSL流暂无数据（inlet正在等待恢复）
+    pub is_processing: bool,            // 处理管道在运行
+    pub streaming_paused: bool,         // 前端帧发送已暂停
+}
+
+/// 回放状态
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    Finished,
+}
+
+/// 录制文件回放进度
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlaybackStatus {
+    pub file: String,
+    pub state: PlaybackState,
+    pub position_seconds: f64,
+    pub duration_seconds: f64,
+    pub speed: f64,
+}
+
+/// 采集模式 - Strict模式下任何数据完整性问题都不会被静默忽略
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AcquisitionMode {
+    #[default]
+    Standard,
+    Strict,     // 研究级采集：写入错误终止、队列积压终止、禁用模拟连接、异常全部注释
+}
+
+impl AcquisitionMode {
+    pub fn is_strict(&self) -> bool {
+        matches!(self, AcquisitionMode::Strict)
+    }
+}
+
+/// 采集异常类型
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    RecordingWriteError,
+    QueueOverflow,
+    ChannelCountMismatch,
+    NonMonotonicTimestamp,
+}
+
+impl AnomalyKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnomalyKind::RecordingWriteError => "recording write error",
+            AnomalyKind::QueueOverflow => "queue overflow",
+            AnomalyKind::ChannelCountMismatch => "channel count mismatch",
+            AnomalyKind::NonMonotonicTimestamp => "non-monotonic timestamp",
+        }
+    }
+}
+
+/// 采集异常事件（acquisition-anomaly / acquisition-fatal）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AcquisitionAnomaly {
+    pub kind: AnomalyKind,
+    pub message: String,
+    pub sample_id: Option<u64>,
+    pub lsl_timestamp: Option<f64>,
+    pub fatal: bool,
+}
+
+/// 日志条目（get_recent_logs / log-event）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SystemHealth {
     pub lsl_manager_status: String,
     pub processor_status: String,
+    pub active_sessions: usize,
     pub memory_usage_mb: u64,
     pub uptime_seconds: u64,
+    pub pipelines: Vec<PipelineHealth>,
+}
+
+/// 单个会话处理管道的运行状况
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PipelineHealth {
+    pub stream_id: String,
+    pub uptime_seconds: u64,
+    pub samples_distributed: u64,
+    pub samples_recorded: u64,
+    pub recording_errors: u64,
+    pub dropped_samples: u64,
+    pub dropped_batches: u64,
+    pub data_gaps: u64,
+    pub effective_sample_rate: Option<f64>,
+    pub queue_depths: QueueDepths,
+    pub overflow_drops: OverflowDrops,
+    pub backpressure: ChannelBackpressure,
+    pub frontend: FrontendHealth,
+}
+
+/// 管道阶段：LSL拉取 → 分发器 → 时域收集器 → FFT → 前端发送，以及录制写入
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    LslPull,        // 延迟：最早样本的LSL时间戳到交付管道；项：样本
+    Distributor,    // 延迟：单个样本块的分发处理；项：样本
+    Collector,      // 延迟：批次内首个样本到达至批次发出；项：显示样本
+    Fft,            // 延迟：单次频谱计算与分析；项：频谱
+    Emit,           // 延迟：单帧序列化与发送；项：帧
+    Recording,      // 延迟：单个样本块写入；项：样本
+}
+
+/// 管道阶段故障类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineFault {
+    Panicked,
+    Exited,         // 管道运行中任务意外结束
+    Stalled,        // 心跳超时（任务可能卡死）
+}
+
+/// 管道阶段故障事件（pipeline-error）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PipelineError {
+    pub stage: PipelineStage,
+    pub fault: PipelineFault,
+    pub message: String,
+    pub restarted: bool,            // 已自动重启该阶段
+    pub restarts: u32,              // 该阶段累计重启次数
+}
+
+/// 单个阶段的延迟分位数（最近窗口）与吞吐
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StageLatency {
+    pub stage: PipelineStage,
+    pub total_items: u64,
+    pub items_per_second: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    pub window: usize,              // 参与统计的最近样本数
+}
+
+/// 性能剖析快照（get_pipeline_metrics / 每2秒的 pipeline-metrics 事件）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PipelineMetricsReport {
+    pub uptime_seconds: u64,
+    pub stages: Vec<StageLatency>,
+    pub queue_depths: QueueDepths,
+}
+
+/// 前端发送状况：实际帧率低于设定值且 under_load 为 true 时，积压批次正被合并发送
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct FrontendHealth {
+    pub effective_frame_rate: f64,
+    pub emit_latency_ms: f64,
+    pub coalesced_batches: u64,
+    pub under_load: bool,
+}
+
+/// 有界通道按丢弃策略（DropOldest/DropNewest）丢弃的项数
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct OverflowDrops {
+    pub recording: u64,
+    pub time_domain: u64,
+    pub fft: u64,
+    pub frontend: u64,
+}
+
+/// Block策略下通道满时的阻塞发送次数与累计阻塞时长（背压，数据未丢失）
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct ChannelBackpressure {
+    pub recording_blocked_sends: u64,
+    pub recording_blocked_seconds: f64,
+    pub time_domain_blocked_sends: u64,
+    pub time_domain_blocked_seconds: f64,
+    pub fft_blocked_sends: u64,
+    pub fft_blocked_seconds: f64,
+    pub frontend_blocked_sends: u64,
+    pub frontend_blocked_seconds: f64,
+}
+
+/// 管道各通道的积压（分发器前后按块计数，前端按批次计数）
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct QueueDepths {
+    pub source: usize,
+    pub recording: usize,
+    pub time_domain: usize,
+    pub frontend: usize,
 }
 
 // ✅ 简化的通道优先数据结构
@@ -76,6 +3744,24 @@ pub struct OptimizedEegBatch {
     
     // ✅ 纯数据，去除冗余元信息
     pub channel_data: Vec<ChannelSamples>,
+    
+    // 每个样本的LSL时间戳，以及与上一样本之间是否有间隙
+    pub sample_timestamps: Vec<f64>,
+    pub gap_flags: Vec<u8>,
+}
+
+impl OptimizedEegBatch {
+    // 相邻样本间隔超过名义间隔的该倍数时视为间隙
+    const GAP_TOLERANCE: f64 = 1.5;
+    
+    /// 只保留订阅的通道，channel_index 仍为原显示通道索引
+    pub fn retain_subscribed(&mut self, subscription: &FrameSubscription) {
+        if let Some(channels) = &subscription.channels {
+            let available = self.channels_count;
+            self.channel_data.retain(|channel| channels.contains(&channel.channel_index));
+            self.channels_count = channels.iter().filter(|&&index| index < available).count() as u32;
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -85,11 +3771,30 @@ pub struct ChannelSamples {
 }
 
 // ✅ 极简二进制帧构建器
+//
+// 帧封装（通过 ipc::Channel 以原始字节发送，全部小端序）：
+// [Envelope: 16 bytes] magic "OCAF"(4) + version u16 + section_count u16 + payload_len u32 + reserved u32
+// [Section]*           kind u32 + length u32 + payload(length bytes)
+//   kind=1 时域：与 build_channel_major_frame 的布局相同
+//   kind=2 频域：batch_id(8) + channels_count(4) + bins(4) + frequency_bins[f32 * bins]
+//                + 每通道 channel_index(4) + spectrum[f32 * bins]
+//   kind=3 时间戳：samples(4) + nominal_dt(8) + timestamps[f64 * samples] + gap_flags[u8 * samples]
+//                gap_flag=1 表示该样本与前一样本之间有时间间隙或sample_id跳变
+//   kind=4 辅助流：channels(4) + samples(4) + timestamps[f64 * samples] + values[f32 * samples * channels]
+//                样本优先；通道标签见 get_aux_stream_info
 pub struct BinaryFrameBuilder {
     buffer: Vec<u8>,
 }
 
 impl BinaryFrameBuilder {
+    pub const MAGIC: &'static [u8; 4] = b"OCAF";
+    pub const VERSION: u16 = 1;
+    pub const SECTION_TIME_DOMAIN: u32 = 1;
+    pub const SECTION_FREQUENCY: u32 = 2;
+    pub const SECTION_TIMESTAMPS: u32 = 3;
+    pub const SECTION_AUX: u32 = 4;
+    const ENVELOPE_HEADER_LEN: usize = 16;
+    
     pub fn new() -> Self {
         Self { 
             buffer: Vec::with_capacity(65536),      // 64KB预分配
@@ -103,7 +3808,57 @@ impl BinaryFrameBuilder {
     /// Channel Block: channel_index(4) + [samples: 4*N bytes]
     pub fn build_channel_major_frame(&mut self, batch: &OptimizedEegBatch) -> Vec<u8> {
         self.buffer.clear();
+        self.write_time_domain(batch);
+        self.buffer.clone()
+    }
+    
+    /// 构建封装帧：时域段 + 时间戳段 + 频域段 + 辅助流段（空批次/无频谱/无辅助样本时省略对应段）
+    pub fn build_envelope(&mut self, batch: &OptimizedEegBatch, freq_data: &[FreqData], aux: Option<&AuxBatch>) -> Vec<u8> {
+        self.buffer.clear();
+        self.buffer.resize(Self::ENVELOPE_HEADER_LEN, 0);
+        
+        let mut section_count = 1u16;
+        self.write_section(Self::SECTION_TIME_DOMAIN, |builder| builder.write_time_domain(batch));
+        
+        if !batch.sample_timestamps.is_empty() {
+            section_count += 1;
+            self.write_section(Self::SECTION_TIMESTAMPS, |builder| builder.write_timestamps(batch));
+        }
+        
+        if !freq_data.is_empty() {
+            section_count += 1;
+            self.write_section(Self::SECTION_FREQUENCY, |builder| {
+                builder.write_frequency(batch.batch_id, freq_data)
+            });
+        }
+        
+        if let Some(aux) = aux.filter(|aux| !aux.is_empty()) {
+            section_count += 1;
+            self.write_section(Self::SECTION_AUX, |builder| builder.write_aux(aux));
+        }
+        
+        let payload_len = (self.buffer.len() - Self::ENVELOPE_HEADER_LEN) as u32;
+        self.buffer[0..4].copy_from_slice(Self::MAGIC);
+        self.buffer[4..6].copy_from_slice(&Self::VERSION.to_le_bytes());
+        self.buffer[6..8].copy_from_slice(&section_count.to_le_bytes());
+        self.buffer[8..12].copy_from_slice(&payload_len.to_le_bytes());
+        
+        self.buffer.clone()
+    }
+    
+    /// 写入段头并在写完payload后回填长度
+    fn write_section(&mut self, kind: u32, write_payload: impl FnOnce(&mut Self)) {
+        self.buffer.extend(&kind.to_le_bytes());
+        let length_offset = self.buffer.len();
+        self.buffer.extend(&0u32.to_le_bytes());
+        
+        write_payload(self);
         
+        let length = (self.buffer.len() - length_offset - 4) as u32;
+        self.buffer[length_offset..length_offset + 4].copy_from_slice(&length.to_le_bytes());
+    }
+    
+    fn write_time_domain(&mut self, batch: &OptimizedEegBatch) {
         // ✅ 写入帧头部 (32 bytes)
         self.buffer.extend(&batch.batch_id.to_le_bytes());           // 8 bytes
         self.buffer.extend(&batch.timestamp.to_le_bytes());          // 8 bytes  
@@ -119,8 +3874,54 @@ impl BinaryFrameBuilder {
             // ✅ SIMD优化的样本数据写入
             self.write_samples_simd(&channel.samples);
         }
+    }
+    
+    fn write_timestamps(&mut self, batch: &OptimizedEegBatch) {
+        let nominal_dt = if batch.sample_rate > 0.0 { 1.0 / batch.sample_rate } else { 0.0 };
         
-        self.buffer.clone()
+        self.buffer.extend(&(batch.sample_timestamps.len() as u32).to_le_bytes());
+        self.buffer.extend(&nominal_dt.to_le_bytes());
+        for timestamp in &batch.sample_timestamps {
+            self.buffer.extend(&timestamp.to_le_bytes());
+        }
+        self.buffer.extend(&batch.gap_flags);
+    }
+    
+    fn write_aux(&mut self, aux: &AuxBatch) {
+        let channels = aux.channel_labels.len();
+        
+        self.buffer.extend(&(channels as u32).to_le_bytes());
+        self.buffer.extend(&(aux.timestamps.len() as u32).to_le_bytes());
+        for timestamp in &aux.timestamps {
+            self.buffer.extend(&timestamp.to_le_bytes());
+        }
+        for sample in &aux.samples {
+            for channel in 0..channels {
+                let value = sample.get(channel).copied().unwrap_or(0.0);
+                self.buffer.extend(&value.to_le_bytes());
+            }
+        }
+    }
+    
+    fn write_frequency(&mut self, batch_id: u64, freq_data: &[FreqData]) {
+        let bins = freq_data[0].frequency_bins.len();
+        
+        self.buffer.extend(&batch_id.to_le_bytes());
+        self.buffer.extend(&(freq_data.len() as u32).to_le_bytes());
+        self.buffer.extend(&(bins as u32).to_le_bytes());
+        
+        for &frequency in &freq_data[0].frequency_bins {
+            self.buffer.extend(&(frequency as f32).to_le_bytes());
+        }
+        
+        for channel in freq_data {
+            self.buffer.extend(&channel.channel_index.to_le_bytes());
+            // 各通道频点一致，长度不符时补零/截断以保持定长布局
+            for bin in 0..bins {
+                let value = channel.spectrum.get(bin).copied().unwrap_or(0.0) as f32;
+                self.buffer.extend(&value.to_le_bytes());
+            }
+        }
     }
     
     /// ✅ 使用SIMD加速的样本写入
@@ -167,6 +3968,7 @@ impl BinaryFrameBuilder {
 // ✅ 高性能数据转换器
 pub struct DataConverter {
     channel_buffers: Vec<Vec<f32>>,
+    last_sample: Option<(f64, u64)>,    // 上一批次末样本的(时间戳, sample_id)
 }
 
 impl DataConverter {
@@ -175,6 +3977,7 @@ impl DataConverter {
             channel_buffers: (0..channels_count)
                 .map(|_| Vec::with_capacity(128))    // 预分配每通道缓冲区
                 .collect(),
+            last_sample: None,
         }
     }
     
@@ -204,6 +4007,8 @@ impl DataConverter {
                 samples_per_channel: 0,
                 sample_rate: eeg_batch.sample_rate,
                 channel_data: Vec::new(),
+                sample_timestamps: Vec::new(),
+                gap_flags: Vec::new(),
             };
         }
         
@@ -223,7 +4028,7 @@ impl DataConverter {
         for sample in &eeg_batch.samples {
             for (ch, &value) in sample.channels.iter().enumerate() {
                 if ch < self.channel_buffers.len() {
-                    self.channel_buffers[ch].push(value as f32);
+                    self.channel_buffers[ch].push(value);
                 }
             }
         }
@@ -239,6 +4044,8 @@ impl DataConverter {
             });
         }
         
+        let (sample_timestamps, gap_flags) = self.sample_timing(eeg_batch);
+        
         OptimizedEegBatch {
             batch_id,
             timestamp: std::time::SystemTime::now()
@@ -248,7 +4055,37 @@ impl DataConverter {
             samples_per_channel,
             sample_rate: eeg_batch.sample_rate,
             channel_data,
+            sample_timestamps,
+            gap_flags,
+        }
+    }
+    
+    /// 逐样本时间戳与间隙标记，跨批次比较（批次首样本与上一批次末样本）
+    fn sample_timing(&mut self, eeg_batch: &EegBatch) -> (Vec<f64>, Vec<u8>) {
+        let gap_threshold = if eeg_batch.sample_rate > 0.0 {
+            OptimizedEegBatch::GAP_TOLERANCE / eeg_batch.sample_rate
+        } else {
+            f64::INFINITY
+        };
+        
+        let mut timestamps = Vec::with_capacity(eeg_batch.samples.len());
+        let mut gap_flags = Vec::with_capacity(eeg_batch.samples.len());
+        
+        for sample in &eeg_batch.samples {
+            let is_gap = match self.last_sample {
+                Some((last_timestamp, last_id)) => {
+                    sample.timestamp - last_timestamp > gap_threshold
+                        || sample.sample_id != last_id.wrapping_add(1)
+                }
+                None => false,
+            };
+            
+            timestamps.push(sample.timestamp);
+            gap_flags.push(is_gap as u8);
+            self.last_sample = Some((sample.timestamp, sample.sample_id));
         }
+        
+        (timestamps, gap_flags)
     }
 }
 
@@ -0,0 +1,143 @@
+use crate::error::AppError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// 纯文本（CSV/TSV）写入器 - 每个样本一行：timestamp + 各通道数值
+/// 注释写入旁路文件 `<文件名>_annotations.<扩展名>`
+pub struct DelimitedWriter {
+    file: BufWriter<File>,
+    annotations: Option<BufWriter<File>>,
+    annotations_path: String,
+    delimiter: char,
+    row: String,
+}
+
+impl DelimitedWriter {
+    pub fn create(filename: &str, channel_labels: &[String], delimiter: char) -> Result<Self, AppError> {
+        let file = File::create(filename)
+            .map_err(|e| AppError::Recording(format!("Failed to create text file: {}", e)))?;
+        
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            annotations: None,
+            annotations_path: annotations_path(filename),
+            delimiter,
+            row: String::new(),
+        };
+        
+        let mut header = String::from("timestamp");
+        for label in channel_labels {
+            header.push(delimiter);
+            header.push_str(&writer.escape(label));
+        }
+        writeln!(writer.file, "{}", header)
+            .map_err(|e| AppError::Recording(format!("Failed to write text header: {}", e)))?;
+        
+        Ok(writer)
+    }
+    
+    pub fn write_row(&mut self, timestamp: f64, channels: &[f64]) -> Result<(), AppError> {
+        use std::fmt::Write as _;
+        
+        self.row.clear();
+        let _ = write!(self.row, "{:.6}", timestamp);
+        for value in channels {
+            self.row.push(self.delimiter);
+            let _ = write!(self.row, "{}", value);
+        }
+        self.row.push('\n');
+        
+        self.file.write_all(self.row.as_bytes())
+            .map_err(|e| AppError::Recording(format!("Failed to write text row: {}", e)))
+    }
+    
+    /// 注释首次出现时才创建旁路文件
+    pub fn add_annotation(&mut self, onset: f64, duration: Option<f64>, description: &str) -> Result<(), AppError> {
+        if self.annotations.is_none() {
+            let mut file = File::create(&self.annotations_path)
+                .map(BufWriter::new)
+                .map_err(|e| AppError::Recording(format!("Failed to create annotation file: {}", e)))?;
+            writeln!(file, "onset{d}duration{d}description", d = self.delimiter)
+                .map_err(|e| AppError::Recording(format!("Failed to write annotation header: {}", e)))?;
+            self.annotations = Some(file);
+        }
+        
+        let line = format!(
+            "{:.6}{d}{}{d}{}",
+            onset,
+            duration.map(|d| format!("{:.6}", d)).unwrap_or_default(),
+            self.escape(description),
+            d = self.delimiter,
+        );
+        
+        if let Some(file) = self.annotations.as_mut() {
+            writeln!(file, "{}", line)
+                .map_err(|e| AppError::Recording(format!("Failed to write annotation: {}", e)))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 写出缓冲的行和注释
+    pub fn flush(&mut self) -> Result<(), AppError> {
+        self.file.flush()
+            .map_err(|e| AppError::Recording(format!("Failed to flush text file: {}", e)))?;
+        
+        if let Some(file) = self.annotations.as_mut() {
+            file.flush()
+                .map_err(|e| AppError::Recording(format!("Failed to flush annotation file: {}", e)))?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn finalize(mut self) -> Result<(), AppError> {
+        self.flush()
+    }
+    
+    /// 含分隔符、引号或换行的字段加双引号
+    fn escape(&self, field: &str) -> String {
+        if field.contains(self.delimiter) || field.contains('"') || field.contains('\n') {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+}
+
+pub(crate) fn annotations_path(filename: &str) -> String {
+    let path = Path::new(filename);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_annotations.{}", stem, ext),
+        None => format!("{}_annotations", stem),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_csv_rows_and_annotation_sidecar() {
+        let path = std::env::temp_dir().join(format!("delimited_test_{}.csv", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let labels = vec!["Fp1".to_string(), "C3,ref".to_string()];
+        
+        let mut writer = DelimitedWriter::create(&filename, &labels, ',').unwrap();
+        writer.write_row(1.5, &[10.0, -2.5]).unwrap();
+        writer.add_annotation(0.25, None, "stimulus").unwrap();
+        let sidecar = writer.annotations_path.clone();
+        writer.finalize().unwrap();
+        
+        let text = std::fs::read_to_string(&path).unwrap();
+        let annotations = std::fs::read_to_string(&sidecar).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&sidecar);
+        
+        assert_eq!(text, "timestamp,Fp1,\"C3,ref\"\n1.500000,10,-2.5\n");
+        assert_eq!(annotations, "onset,duration,description\n0.250000,,stimulus\n");
+    }
+}
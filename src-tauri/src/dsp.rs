@@ -0,0 +1,315 @@
+use crate::data_types::{FilterConfig, NotchFilterConfig};
+use std::f64::consts::PI;
+
+/// 二阶IIR滤波器（Direct Form II Transposed）
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn from_coefficients(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+    
+    /// 陷波滤波器（RBJ Audio EQ Cookbook）
+    pub fn notch(sample_rate: f64, center_hz: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * center_hz / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+        
+        Self::from_coefficients(
+            1.0, -2.0 * cos_w0, 1.0,
+            1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha,
+        )
+    }
+    
+    /// 二阶低通（RBJ Audio EQ Cookbook）
+    pub fn lowpass(sample_rate: f64, cutoff_hz: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+        
+        Self::from_coefficients(
+            (1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0,
+            1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha,
+        )
+    }
+    
+    /// 二阶高通（RBJ Audio EQ Cookbook）
+    pub fn highpass(sample_rate: f64, cutoff_hz: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+        
+        Self::from_coefficients(
+            (1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0,
+            1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha,
+        )
+    }
+    
+    #[inline]
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// 多通道工频陷波滤波器组 - 每个通道独立维护滤波器状态
+pub struct NotchFilterBank {
+    channels: Vec<Vec<Biquad>>,
+    frequencies: Vec<f64>,
+}
+
+impl NotchFilterBank {
+    /// 最多滤除的谐波数（含基频）
+    const MAX_HARMONICS: usize = 5;
+    
+    pub fn new(config: &NotchFilterConfig, sample_rate: f64, channels_count: usize) -> Self {
+        let frequencies = Self::notch_frequencies(config, sample_rate);
+        
+        let prototype: Vec<Biquad> = frequencies
+            .iter()
+            .map(|&freq| Biquad::notch(sample_rate, freq, config.q_factor))
+            .collect();
+        
+        Self {
+            channels: vec![prototype; channels_count],
+            frequencies,
+        }
+    }
+    
+    /// 需要滤除的频率（基频及低于奈奎斯特频率的谐波）
+    fn notch_frequencies(config: &NotchFilterConfig, sample_rate: f64) -> Vec<f64> {
+        if !config.enabled || sample_rate <= 0.0 {
+            return Vec::new();
+        }
+        
+        let limit = sample_rate / 2.0 * 0.95;
+        let harmonics = if config.harmonics { Self::MAX_HARMONICS } else { 1 };
+        
+        (1..=harmonics)
+            .map(|k| config.frequency_hz * k as f64)
+            .take_while(|&freq| freq < limit)
+            .collect()
+    }
+    
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+    
+    pub fn is_active(&self) -> bool {
+        !self.frequencies.is_empty()
+    }
+    
+    /// 原地滤波单个多通道样本
+    pub fn process_sample(&mut self, channels: &mut [f64]) {
+        process_cascades(&mut self.channels, channels);
+    }
+}
+
+/// Butterworth 各二阶节的Q值（order 为偶数）
+fn butterworth_section_qs(order: usize) -> Vec<f64> {
+    let sections = order / 2;
+    (0..sections)
+        .map(|k| {
+            let theta = PI * (2 * k + 1) as f64 / (2 * order) as f64;
+            1.0 / (2.0 * theta.cos())
+        })
+        .collect()
+}
+
+/// 多通道高通/低通/带通滤波链 - Butterworth 二阶节级联，每个通道独立状态
+pub struct FilterChain {
+    channels: Vec<Vec<Biquad>>,
+    sections: usize,
+}
+
+impl FilterChain {
+    pub fn new(config: &FilterConfig, sample_rate: f64, channels_count: usize) -> Self {
+        let mut prototype = Vec::new();
+        
+        if sample_rate > 0.0 {
+            let qs = butterworth_section_qs(config.order);
+            
+            if let Some(hp) = config.hp {
+                prototype.extend(qs.iter().map(|&q| Biquad::highpass(sample_rate, hp, q)));
+            }
+            if let Some(lp) = config.lp {
+                prototype.extend(qs.iter().map(|&q| Biquad::lowpass(sample_rate, lp, q)));
+            }
+        }
+        
+        Self {
+            sections: prototype.len(),
+            channels: vec![prototype; channels_count],
+        }
+    }
+    
+    pub fn is_active(&self) -> bool {
+        self.sections > 0
+    }
+    
+    /// 原地滤波单个多通道样本
+    pub fn process_sample(&mut self, channels: &mut [f64]) {
+        process_cascades(&mut self.channels, channels);
+    }
+}
+
+/// 多通道抽取器 - 先以新奈奎斯特频率的80%低通抗混叠，再每 factor 个样本保留一个
+pub struct Decimator {
+    channels: Vec<Vec<Biquad>>,
+    factor: usize,
+    phase: usize,
+}
+
+impl Decimator {
+    /// 抗混叠滤波器阶数
+    const ANTI_ALIAS_ORDER: usize = 8;
+    
+    pub fn new(factor: usize, sample_rate: f64, channels_count: usize) -> Self {
+        let factor = factor.max(1);
+        let prototype: Vec<Biquad> = if factor > 1 && sample_rate > 0.0 {
+            let cutoff = sample_rate / factor as f64 / 2.0 * 0.8;
+            butterworth_section_qs(Self::ANTI_ALIAS_ORDER)
+                .into_iter()
+                .map(|q| Biquad::lowpass(sample_rate, cutoff, q))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        
+        Self {
+            channels: vec![prototype; channels_count],
+            factor,
+            phase: 0,
+        }
+    }
+    
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+    
+    pub fn channels_count(&self) -> usize {
+        self.channels.len()
+    }
+    
+    /// 原地滤波单个多通道样本，返回该样本是否保留
+    pub fn process_sample(&mut self, channels: &mut [f64]) -> bool {
+        process_cascades(&mut self.channels, channels);
+        
+        let keep = self.phase == 0;
+        self.phase = (self.phase + 1) % self.factor;
+        keep
+    }
+}
+
+/// 对每个通道依次通过其二阶节级联
+fn process_cascades(cascades: &mut [Vec<Biquad>], channels: &mut [f64]) {
+    for (value, filters) in channels.iter_mut().zip(cascades.iter_mut()) {
+        for filter in filters.iter_mut() {
+            *value = filter.process(*value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn steady_state_amplitude(filter: &mut Biquad, freq: f64, sample_rate: f64) -> f64 {
+        let total = (sample_rate * 4.0) as usize;
+        let mut peak: f64 = 0.0;
+        for n in 0..total {
+            let x = (2.0 * PI * freq * n as f64 / sample_rate).sin();
+            let y = filter.process(x);
+            if n > total / 2 {
+                peak = peak.max(y.abs());
+            }
+        }
+        peak
+    }
+    
+    #[test]
+    fn test_notch_attenuates_line_frequency_only() {
+        let sample_rate = 500.0;
+        
+        let mut notch = Biquad::notch(sample_rate, 50.0, 30.0);
+        assert!(steady_state_amplitude(&mut notch, 50.0, sample_rate) < 0.05);
+        
+        let mut notch = Biquad::notch(sample_rate, 50.0, 30.0);
+        assert!(steady_state_amplitude(&mut notch, 10.0, sample_rate) > 0.95);
+    }
+    
+    #[test]
+    fn test_butterworth_bandpass_chain() {
+        let sample_rate = 250.0;
+        let config = FilterConfig {
+            hp: Some(1.0),
+            lp: Some(30.0),
+            order: 4,
+            apply_to_recording: false,
+        };
+        
+        let measure = |freq: f64| {
+            let mut chain = FilterChain::new(&config, sample_rate, 1);
+            let total = (sample_rate * 10.0) as usize;
+            let mut peak: f64 = 0.0;
+            for n in 0..total {
+                let mut value = [(2.0 * PI * freq * n as f64 / sample_rate).sin()];
+                chain.process_sample(&mut value);
+                if n > total / 2 {
+                    peak = peak.max(value[0].abs());
+                }
+            }
+            peak
+        };
+        
+        assert!(measure(10.0) > 0.9);
+        assert!(measure(80.0) < 0.05);
+        assert!(measure(0.1) < 0.05);
+    }
+    
+    #[test]
+    fn test_decimator_rejects_aliases() {
+        let sample_rate = 1000.0;
+        
+        let measure = |freq: f64| {
+            let mut decimator = Decimator::new(4, sample_rate, 1);
+            let total = (sample_rate * 4.0) as usize;
+            let mut kept = 0;
+            let mut peak: f64 = 0.0;
+            for n in 0..total {
+                let mut value = [(2.0 * PI * freq * n as f64 / sample_rate).sin()];
+                if decimator.process_sample(&mut value) {
+                    kept += 1;
+                    if n > total / 2 {
+                        peak = peak.max(value[0].abs());
+                    }
+                }
+            }
+            assert_eq!(kept, total / 4);
+            peak
+        };
+        
+        // 250Hz输出：通带内保留，高于新奈奎斯特频率的成分被抑制而非混叠
+        assert!(measure(20.0) > 0.9);
+        assert!(measure(200.0) < 0.01);
+    }
+}
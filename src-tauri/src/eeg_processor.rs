@@ -1,228 +1,2461 @@
 use crate::data_types::*;
-use crate::error::AppError;
-use crate::recorder::EdfRecorder;
+use crate::error::{AppError, ErrorSource};
+use crate::recorder::{EdfRecorder, FinishedRecording, RecordingStats};
+use crate::multi_recorder::MultiRecorder;
+use crate::capture::CaptureWriter;
+use crate::catalog::RecordingCatalog;
+use crate::upload::AutoUpload;
 use crate::fft_processor::{FftProcessor, utils as fft_utils}; // ✅ 导入FFT模块
+use crate::dsp::{Decimator, FilterChain, NotchFilterBank};
+use crate::montage::{self, Montage};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::Mutex;
-use tauri::{AppHandle, Emitter};
+use crate::session::StreamEmitter;
+use crate::metrics::{FramePacer, PipelineMetrics, StageStats};
+use crate::watchdog::Supervisor;
+use crate::stages::{StageBatch, StagePipeline, StageRegistry};
+use crate::scripting::{self, ScriptHooks};
+use crate::plugins;
+use crate::integrity::GapDetector;
+use crate::signal_quality::QualityMonitor;
+use crate::artifacts::ArtifactDetector;
+use crate::history::SampleHistory;
+use crate::spectrogram::SpectrogramBuffer;
+use crate::snapshot::Snapshot;
+use crate::triggers::{self, TriggerEngine, TriggerFired};
+use crate::asr::AsrStage;
+use crate::ica::OnlineIca;
+use crate::epochs::EpochExtractor;
+use crate::erp::ErpAverager;
+use crate::time_features::TimeFeatureExtractor;
+use crate::neurofeedback::FeedbackEngine;
+use crate::ssvep::SsvepDetector;
+use crate::classifier::ClassifierSession;
+use crate::csp::{self, CspEpochStore};
+use crate::vitals::VitalsMonitor;
+use crate::impedance::{self, ImpedanceTracker};
+use crate::osc::OscSender;
+use crate::pipeline_channel::{self, PipelineSender};
 use crossbeam_channel;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+#[cfg(feature = "desktop")]
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tracing::{debug, error, info, warn};
 
-// ✅ 只保留时域处理相关的常量
-const FRAME_INTERVAL_MS: u64 = 33;
+// 无界面构建没有前端IPC通道，二进制帧订阅始终为空
+#[cfg(not(feature = "desktop"))]
+pub enum Channel {}
+
+// Strict模式下管道队列允许积压的最长时长（秒）
+const STRICT_MAX_QUEUE_SECONDS: f64 = 5.0;
+
+// 前端线程缓冲的辅助流样本上限（EEG停滞时丢弃最旧样本）
+const AUX_BUFFER_CAPACITY: usize = 4096;
+
+// 阻塞接收的最长等待，超时后更新心跳（供监督器判断阶段是否卡死）
+pub(crate) const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+// pipeline-metrics 事件的推送间隔
+const PIPELINE_METRICS_INTERVAL: Duration = Duration::from_secs(2);
+
+// 录制期间推送 recording-progress 的间隔
+const RECORDING_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+// 录制期间磁盘空间检查间隔，以及低空间警告的最短间隔
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const LOW_DISK_WARNING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 录制结束后的去向：记为会话最近一次录制，登记到录制索引并自动上传（如已启用）
+#[derive(Clone, Default)]
+struct RecordingLog {
+    last: Arc<Mutex<Option<FinishedRecording>>>,
+    catalog: Option<Arc<RecordingCatalog>>,
+    upload: Option<AutoUpload>,
+}
+
+impl RecordingLog {
+    /// 结束录制的所有输出，返回主输出的统计信息；附加输出同样登记和上传
+    async fn finish(&self, recorder: MultiRecorder) -> Result<RecordingStats, AppError> {
+        let mut results = recorder.finish().into_iter();
+        let primary = results.next()
+            .ok_or_else(|| AppError::Recording("Recording has no outputs".to_string()))?;
+        for finished in results.flatten() {
+            self.register(&finished).await;
+        }
+        
+        let finished = primary?;
+        self.register(&finished).await;
+        let stats = finished.stats.clone();
+        *self.last.lock().await = Some(finished);
+        Ok(stats)
+    }
+    
+    async fn register(&self, finished: &FinishedRecording) {
+        if let Some(catalog) = &self.catalog {
+            // 索引失败不影响录制本身
+            if let Err(e) = catalog.insert(finished) {
+                error!("❌ Failed to index recording {}: {}", finished.stats.filename, e);
+            }
+        }
+        if let Some(upload) = &self.upload {
+            upload.recording_finished(finished).await;
+        }
+    }
+}
+
+// 未录制时暂存的采集异常上限，超出后丢弃最早的
+const MAX_PENDING_ANOMALIES: usize = 1000;
+
+/// 采集异常记录：正在录制时直接写入注释，否则暂存，下次开始录制时写入文件
+#[derive(Clone, Default)]
+struct AnomalyLog {
+    pending: Arc<Mutex<std::collections::VecDeque<AcquisitionAnomaly>>>,
+}
+
+impl AnomalyLog {
+    async fn record(&self, recorder: Option<&mut MultiRecorder>, anomaly: &AcquisitionAnomaly) {
+        match recorder {
+            Some(recorder) => Self::annotate(recorder, anomaly),
+            None => {
+                let mut pending = self.pending.lock().await;
+                if pending.len() >= MAX_PENDING_ANOMALIES {
+                    pending.pop_front();
+                }
+                pending.push_back(anomaly.clone());
+            }
+        }
+    }
+    
+    /// 新录制开始时写入之前暂存的异常（早于首个样本的按文件开头对齐）
+    async fn flush(&self, recorder: &mut MultiRecorder) {
+        let pending: Vec<AcquisitionAnomaly> = self.pending.lock().await.drain(..).collect();
+        if !pending.is_empty() {
+            info!("📝 Annotating {} anomalies recorded before the recording started", pending.len());
+        }
+        for anomaly in &pending {
+            Self::annotate(recorder, anomaly);
+        }
+    }
+    
+    fn annotate(recorder: &mut MultiRecorder, anomaly: &AcquisitionAnomaly) {
+        let description = format!("ANOMALY {}: {}", anomaly.kind.label(), anomaly.message);
+        let result = match anomaly.lsl_timestamp {
+            Some(timestamp) => recorder.write_annotation_at(timestamp, None, &description),
+            None => recorder.write_annotation(recorder.elapsed_seconds(), None, &description),
+        };
+        if let Err(e) = result {
+            error!("❌ Failed to annotate anomaly: {}", e);
+        }
+    }
+}
 
 pub struct EegProcessor {
     stream_info: StreamInfo,
-    app_handle: AppHandle,
-    data_rx: Option<crossbeam_channel::Receiver<EegSample>>,
-    recorder: Arc<Mutex<Option<EdfRecorder>>>,
+    app_handle: StreamEmitter,
+    data_rx: Option<crossbeam_channel::Receiver<EegChunk>>,
+    marker_rx: Option<crossbeam_channel::Receiver<LslMarker>>,
+    aux_rx: Option<crossbeam_channel::Receiver<AuxSample>>,
+    impedance_rx: Option<crossbeam_channel::Receiver<AuxSample>>,
+    clock_rx: Option<crossbeam_channel::Receiver<ClockOffsetSample>>,
+    recorder: Arc<Mutex<Option<MultiRecorder>>>,
+    recording_log: RecordingLog,                // 本会话最近完成的录制与录制索引
+    anomalies: AnomalyLog,                      // 未录制时发生的采集异常，开始录制时写入
+    capture: Arc<Mutex<Option<CaptureWriter>>>, // 原始样本采集（回放回归测试用）
     is_running: Arc<tokio::sync::RwLock<bool>>,
     thread_handles: Vec<tokio::task::JoinHandle<()>>,
+    fft_shutdown: Option<crossbeam_channel::Sender<()>>, // 释放即通知FFT线程退出
     fft_processor: Option<FftProcessor>, // ✅ 添加FFT处理器
+    fft_config: Arc<tokio::sync::RwLock<FftConfig>>,
+    spectrum_range: Arc<tokio::sync::RwLock<SpectrumRange>>,
+    spectrum_display: Arc<tokio::sync::RwLock<SpectrumDisplayOptions>>,
+    spectral_metrics: Arc<tokio::sync::RwLock<SpectralMetricsConfig>>,
+    connectivity: Arc<tokio::sync::RwLock<ConnectivityConfig>>,
+    frequency_bands: Arc<tokio::sync::RwLock<FrequencyBands>>,
+    notch_config: Arc<tokio::sync::RwLock<NotchFilterConfig>>,
+    filter_config: Arc<tokio::sync::RwLock<FilterConfig>>,
+    montage_config: Arc<tokio::sync::RwLock<MontageConfig>>,
+    channel_selection: Arc<tokio::sync::RwLock<ChannelSelection>>,
+    signal_quality: Arc<tokio::sync::RwLock<SignalQualityConfig>>,
+    artifact_config: Arc<tokio::sync::RwLock<ArtifactConfig>>,
+    asr_config: Arc<tokio::sync::RwLock<AsrConfig>>,
+    asr_calibration_requests: Arc<AtomicU64>,   // 每次校准请求递增，分发器据此开始采集校准数据
+    ica_config: Arc<tokio::sync::RwLock<IcaConfig>>,
+    ica_rejected: Arc<tokio::sync::RwLock<Vec<u32>>>,
+    display_rate: Arc<tokio::sync::RwLock<DisplayRateConfig>>,
+    frame_rate: Arc<tokio::sync::RwLock<FrameRateConfig>>, // 前端帧率（批次与发送间隔）
+    processing_stages: Arc<tokio::sync::RwLock<ProcessingStagesConfig>>, // 显示路径上可组合的处理阶段
+    scripting: Arc<tokio::sync::RwLock<ScriptingConfig>>,   // 自定义指标脚本
+    history: Arc<Mutex<SampleHistory>>,         // 最近N秒原始样本（陷波前）
+    spectrogram: Arc<Mutex<SpectrogramBuffer>>, // 显示路径频谱图历史（FFT线程写入）
+    recording_triggers: Arc<tokio::sync::RwLock<RecordingTriggerConfig>>,
+    epoch_config: Arc<tokio::sync::RwLock<EpochConfig>>,
+    erp_config: Arc<tokio::sync::RwLock<ErpConfig>>,
+    time_features: Arc<tokio::sync::RwLock<TimeFeaturesConfig>>,
+    neurofeedback: Arc<tokio::sync::RwLock<NeurofeedbackConfig>>,
+    ssvep: Arc<tokio::sync::RwLock<SsvepConfig>>,
+    classifier: Arc<Mutex<ClassifierSession>>,   // 单试次分类：训练集与模型
+    spatial_filter: Arc<tokio::sync::RwLock<Option<SpatialFilter>>>,
+    csp_epochs: Arc<Mutex<CspEpochStore>>,       // CSP训练用的最近分段
+    vitals: Arc<tokio::sync::RwLock<VitalsConfig>>,
+    impedance: Arc<Mutex<ImpedanceTracker>>,    // 各电极最近一次阻抗
+    osc: Arc<tokio::sync::RwLock<OscConfig>>,
+    session_metadata: Arc<tokio::sync::RwLock<SessionMetadata>>, // 写入录制文件头
+    acquisition_mode: AcquisitionMode,
+    channel_config: PipelineChannelConfig,
+    frame_channel: Arc<tokio::sync::RwLock<Option<Channel>>>, // 二进制帧订阅（原始字节IPC）
+    frame_subscription: Arc<tokio::sync::RwLock<FrameSubscription>>, // 前端视图需要的通道/频谱/帧率
+    streaming_paused: Arc<tokio::sync::RwLock<bool>>, // 暂停前端帧发送，采集与录制不受影响
+    metrics: Arc<PipelineMetrics>,
 }
 
 impl EegProcessor {
-    pub fn new(stream_info: StreamInfo, app_handle: AppHandle) -> Result<Self, AppError> {
+    pub fn new(stream_info: StreamInfo, app_handle: StreamEmitter) -> Result<Self, AppError> {
         let processor = Self {
             stream_info: stream_info.clone(),
             app_handle,
             data_rx: None,
+            marker_rx: None,
+            aux_rx: None,
+            impedance_rx: None,
+            clock_rx: None,
             recorder: Arc::new(Mutex::new(None)),
+            recording_log: RecordingLog::default(),
+            anomalies: AnomalyLog::default(),
+            capture: Arc::new(Mutex::new(None)),
             is_running: Arc::new(tokio::sync::RwLock::new(false)),
             thread_handles: Vec::new(),
+            fft_shutdown: None,
             fft_processor: None, // 延迟初始化
+            fft_config: Arc::new(tokio::sync::RwLock::new(FftConfig::default())),
+            spectrum_range: Arc::new(tokio::sync::RwLock::new(SpectrumRange::default())),
+            spectrum_display: Arc::new(tokio::sync::RwLock::new(SpectrumDisplayOptions::default())),
+            spectral_metrics: Arc::new(tokio::sync::RwLock::new(SpectralMetricsConfig::default())),
+            connectivity: Arc::new(tokio::sync::RwLock::new(ConnectivityConfig::default())),
+            frequency_bands: Arc::new(tokio::sync::RwLock::new(FrequencyBands::default())),
+            notch_config: Arc::new(tokio::sync::RwLock::new(NotchFilterConfig::default())),
+            filter_config: Arc::new(tokio::sync::RwLock::new(FilterConfig::default())),
+            montage_config: Arc::new(tokio::sync::RwLock::new(MontageConfig::default())),
+            channel_selection: Arc::new(tokio::sync::RwLock::new(ChannelSelection::default())),
+            signal_quality: Arc::new(tokio::sync::RwLock::new(SignalQualityConfig::default())),
+            artifact_config: Arc::new(tokio::sync::RwLock::new(ArtifactConfig::default())),
+            asr_config: Arc::new(tokio::sync::RwLock::new(AsrConfig::default())),
+            asr_calibration_requests: Arc::new(AtomicU64::new(0)),
+            ica_config: Arc::new(tokio::sync::RwLock::new(IcaConfig::default())),
+            ica_rejected: Arc::new(tokio::sync::RwLock::new(Vec::new())),
+            display_rate: Arc::new(tokio::sync::RwLock::new(DisplayRateConfig::default())),
+            frame_rate: Arc::new(tokio::sync::RwLock::new(FrameRateConfig::default())),
+            processing_stages: Arc::new(tokio::sync::RwLock::new(ProcessingStagesConfig::default())),
+            scripting: Arc::new(tokio::sync::RwLock::new(ScriptingConfig::default())),
+            history: Arc::new(Mutex::new(SampleHistory::new(HistoryConfig::default(), stream_info.sample_rate))),
+            spectrogram: Arc::new(Mutex::new(SpectrogramBuffer::new(SpectrogramConfig::default()))),
+            recording_triggers: Arc::new(tokio::sync::RwLock::new(RecordingTriggerConfig::default())),
+            epoch_config: Arc::new(tokio::sync::RwLock::new(EpochConfig::default())),
+            erp_config: Arc::new(tokio::sync::RwLock::new(ErpConfig::default())),
+            time_features: Arc::new(tokio::sync::RwLock::new(TimeFeaturesConfig::default())),
+            neurofeedback: Arc::new(tokio::sync::RwLock::new(NeurofeedbackConfig::default())),
+            ssvep: Arc::new(tokio::sync::RwLock::new(SsvepConfig::default())),
+            classifier: Arc::new(Mutex::new(ClassifierSession::default())),
+            spatial_filter: Arc::new(tokio::sync::RwLock::new(None)),
+            csp_epochs: Arc::new(Mutex::new(CspEpochStore::default())),
+            vitals: Arc::new(tokio::sync::RwLock::new(VitalsConfig::default())),
+            impedance: Arc::new(Mutex::new(ImpedanceTracker::new(ImpedanceConfig::default()))),
+            osc: Arc::new(tokio::sync::RwLock::new(OscConfig::default())),
+            session_metadata: Arc::new(tokio::sync::RwLock::new(SessionMetadata::default())),
+            acquisition_mode: AcquisitionMode::default(),
+            channel_config: PipelineChannelConfig::default(),
+            frame_channel: Arc::new(tokio::sync::RwLock::new(None)),
+            frame_subscription: Arc::new(tokio::sync::RwLock::new(FrameSubscription::default())),
+            streaming_paused: Arc::new(tokio::sync::RwLock::new(false)),
+            metrics: Arc::new(PipelineMetrics::new()),
         };
         
         Ok(processor)
     }
     
-    /// 设置数据源（由LslManager提供）
-    pub fn set_data_source(&mut self, data_rx: crossbeam_channel::Receiver<EegSample>) {
-        self.data_rx = Some(data_rx);
+    pub fn stream_info(&self) -> &StreamInfo {
+        &self.stream_info
     }
     
-    /// 启动EEG处理
-    pub async fn start(&mut self) -> Result<(), AppError> {
-        let mut is_running = self.is_running.write().await;
-        if *is_running {
-            return Err(AppError::Config("Processor already running".to_string()));
+    pub fn metrics(&self) -> &PipelineMetrics {
+        &self.metrics
+    }
+    
+    /// 非主会话的事件作用域
+    pub fn event_scope(&self) -> Option<String> {
+        self.app_handle.scope()
+    }
+    
+    /// 成为主会话后事件改用原事件名
+    pub fn promote_events(&self) {
+        self.app_handle.promote();
+    }
+    
+    /// 处理管道是否在运行（Strict模式终止采集后为false）
+    pub async fn is_running(&self) -> bool {
+        *self.is_running.read().await
+    }
+    
+    pub async fn is_recording(&self) -> bool {
+        self.recorder.lock().await.is_some()
+    }
+    
+    pub async fn is_capturing(&self) -> bool {
+        self.capture.lock().await.is_some()
+    }
+    
+    /// 当前录制进度；未在录制时各项为空
+    pub async fn get_recording_status(&self) -> RecordingStatus {
+        let mut status = match self.recorder.lock().await.as_ref() {
+            Some(recorder) => recorder.status(),
+            None => return RecordingStatus::default(),
+        };
+        status.buffer_fill = Self::recording_queue_fill(&self.metrics, self.channel_config.recording.capacity);
+        status
+    }
+    
+    /// 录制队列占用比例（队列深度按块计）
+    fn recording_queue_fill(metrics: &PipelineMetrics, capacity: usize) -> f64 {
+        let depth = metrics.recording_queue.load(Ordering::Relaxed);
+        (depth as f64 / capacity.max(1) as f64).min(1.0)
+    }
+    
+    pub async fn get_fft_config(&self) -> FftConfig {
+        self.fft_config.read().await.clone()
+    }
+    
+    pub async fn get_spectrum_range(&self) -> SpectrumRange {
+        self.spectrum_range.read().await.clone()
+    }
+    
+    pub async fn get_spectrum_display_options(&self) -> SpectrumDisplayOptions {
+        *self.spectrum_display.read().await
+    }
+    
+    pub async fn get_spectral_metrics_config(&self) -> SpectralMetricsConfig {
+        *self.spectral_metrics.read().await
+    }
+    
+    pub async fn get_connectivity_config(&self) -> ConnectivityConfig {
+        self.connectivity.read().await.clone()
+    }
+    
+    pub async fn get_frequency_bands(&self) -> FrequencyBands {
+        self.frequency_bands.read().await.clone()
+    }
+    
+    pub async fn get_notch_filter(&self) -> NotchFilterConfig {
+        *self.notch_config.read().await
+    }
+    
+    pub async fn get_filters(&self) -> FilterConfig {
+        *self.filter_config.read().await
+    }
+    
+    pub async fn get_montage(&self) -> MontageConfig {
+        self.montage_config.read().await.clone()
+    }
+    
+    /// 更新FFT配置，运行中的FFT线程会在下一个批次重建计划
+    pub async fn set_fft_config(&self, config: FftConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.fft_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    /// 更新频谱输出范围，返回按当前流奈奎斯特频率截断后的实际范围
+    pub async fn set_spectrum_range(&self, range: SpectrumRange) -> Result<SpectrumRange, AppError> {
+        self.check_spectrum_range(&range)?;
+        
+        let effective = range.clamped_to(self.stream_info.sample_rate);
+        
+        let mut range_guard = self.spectrum_range.write().await;
+        *range_guard = range;
+        
+        Ok(effective)
+    }
+    
+    fn check_spectrum_range(&self, range: &SpectrumRange) -> Result<(), AppError> {
+        range.validate()?;
+        
+        let sample_rate = self.stream_info.sample_rate;
+        if sample_rate > 0.0 && range.min_hz >= sample_rate / 2.0 {
+            return Err(AppError::Config(format!(
+                "Spectrum min {}Hz is above the Nyquist frequency ({}Hz) of stream '{}'",
+                range.min_hz, sample_rate / 2.0, self.stream_info.name
+            )));
+        }
+        Ok(())
+    }
+    
+    /// 按当前流校验一项运行时配置更新，不写入
+    pub fn check_config_update(&self, update: &ProcessorConfigUpdate) -> Result<(), AppError> {
+        match update {
+            ProcessorConfigUpdate::Filters(config) => config.validate(self.stream_info.sample_rate),
+            ProcessorConfigUpdate::NotchFilter(config) => config.validate(),
+            ProcessorConfigUpdate::DisplayRate(config) => config.validate(),
+            ProcessorConfigUpdate::SpectrumRange(range) => self.check_spectrum_range(range),
+            ProcessorConfigUpdate::SpectrumDisplay(options) => options.validate(),
+            ProcessorConfigUpdate::Fft(config) => config.validate(),
+            ProcessorConfigUpdate::FrameRate(config) => config.validate(),
+        }
+    }
+    
+    /// 热更新一项处理配置 - 写入共享配置，分发器/收集器/FFT/前端线程在下一个数据块或下一帧生效
+    pub async fn apply_config_update(&self, update: &ProcessorConfigUpdate) -> Result<(), AppError> {
+        match update {
+            ProcessorConfigUpdate::Filters(config) => self.set_filters(*config).await,
+            ProcessorConfigUpdate::NotchFilter(config) => self.set_notch_filter(*config).await,
+            ProcessorConfigUpdate::DisplayRate(config) => self.set_display_rate(*config).await,
+            ProcessorConfigUpdate::SpectrumRange(range) => self.set_spectrum_range(range.clone()).await.map(|_| ()),
+            ProcessorConfigUpdate::SpectrumDisplay(options) => self.set_spectrum_display_options(*options).await,
+            ProcessorConfigUpdate::Fft(config) => self.set_fft_config(config.clone()).await,
+            ProcessorConfigUpdate::FrameRate(config) => self.set_frame_rate(*config).await,
         }
+    }
+    
+    /// 更新频谱显示后处理，FFT线程在下一帧生效（平滑状态重置）
+    pub async fn set_spectrum_display_options(&self, options: SpectrumDisplayOptions) -> Result<(), AppError> {
+        options.validate()?;
         
-        let data_rx = self.data_rx.as_ref()
-            .ok_or(AppError::NotConnected)?
-            .clone();
+        let mut options_guard = self.spectrum_display.write().await;
+        *options_guard = options;
         
-        *is_running = true;
-        drop(is_running); // 早释放锁
+        Ok(())
+    }
+    
+    /// 更新频谱指标（alpha峰值、谱边缘频率）的计算范围
+    pub async fn set_spectral_metrics_config(&self, config: SpectralMetricsConfig) -> Result<(), AppError> {
+        config.validate()?;
         
-        // 启动全crossbeam处理管道
-        self.start_crossbeam_pipeline(data_rx).await?;
+        let mut config_guard = self.spectral_metrics.write().await;
+        *config_guard = config;
         
         Ok(())
     }
     
-    /// ✅ 消费式停止 - 消费 self，返回统计信息
-    pub async fn stop(mut self) -> Result<EegProcessorStats, AppError> {
-        println!("🛑 Stopping EEG Processor");
+    /// 更新连接性计算配置，FFT线程在下一帧重建累计器
+    pub async fn set_connectivity_config(&self, config: ConnectivityConfig) -> Result<(), AppError> {
+        config.validate()?;
         
-        let mut is_running = self.is_running.write().await;
-        *is_running = false;
-        drop(is_running);
+        let mut config_guard = self.connectivity.write().await;
+        *config_guard = config;
         
-        // 等待所有线程结束
-        let threads_spawned = self.thread_handles.len() as u32;
-        while let Some(handle) = self.thread_handles.pop() {
-            if let Err(e) = handle.await {
-                println!("Thread join error: {:?}", e);
-            }
-        }
+        Ok(())
+    }
+    
+    /// 更新频带功率计算使用的频带定义
+    pub async fn set_frequency_bands(&self, bands: FrequencyBands) -> Result<(), AppError> {
+        bands.validate()?;
         
-        // 停止录制并获取统计信息
-        let recording_stats = {
-            let mut recorder_guard = self.recorder.lock().await;
-            if let Some(recorder) = recorder_guard.take() {
-                Some(recorder.close()?)
-            } else {
-                None
-            }
-        };
+        let mut bands_guard = self.frequency_bands.write().await;
+        *bands_guard = bands;
         
-        // 生成处理器统计信息
-        let stats = EegProcessorStats {
-            stream_info: self.stream_info.clone(),
-            recording_stats: recording_stats.clone(),
-            threads_spawned,
-        };
+        Ok(())
+    }
+    
+    /// 更新工频陷波滤波器配置，分发器在下一个样本时重建滤波器
+    pub async fn set_notch_filter(&self, config: NotchFilterConfig) -> Result<(), AppError> {
+        config.validate()?;
         
-        // ✅ 实际使用统计字段
-        println!("📊 EEG Processor stopped:");
-        println!("   - Stream: {} ({}Hz, {} channels)", 
-                 stats.stream_info.name, 
-                 stats.stream_info.sample_rate, 
-                 stats.stream_info.channels_count);
-        println!("   - Threads spawned: {}", stats.threads_spawned);
+        let mut config_guard = self.notch_config.write().await;
+        *config_guard = config;
         
-        if let Some(ref rec_stats) = stats.recording_stats {
-            println!("   - Recording stats:");
-            println!("     • Samples recorded: {}", rec_stats.samples_written);
-            println!("     • Duration: {:.2}s", rec_stats.duration_seconds);
-            println!("     • File size: {} bytes", rec_stats.file_size_bytes);
-        } else {
-            println!("   - No recording session");
+        Ok(())
+    }
+    
+    /// 更新高通/低通滤波链配置，分发器在下一个样本时重建滤波器
+    pub async fn set_filters(&self, config: FilterConfig) -> Result<(), AppError> {
+        config.validate(self.stream_info.sample_rate)?;
+        
+        let mut config_guard = self.filter_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    /// 更新导联配置，按当前流的通道标签解析，返回导联后的通道标签
+    pub async fn set_montage(&self, config: MontageConfig) -> Result<MontageInfo, AppError> {
+        let montage = Montage::build(&config, &self.stream_info.channel_labels)?;
+        let channel_labels = montage.output_labels(&self.stream_info.channel_labels);
+        
+        let mut config_guard = self.montage_config.write().await;
+        *config_guard = config.clone();
+        
+        Ok(MontageInfo { config, channel_labels })
+    }
+    
+    pub async fn get_signal_quality(&self) -> SignalQualityConfig {
+        *self.signal_quality.read().await
+    }
+    
+    /// 更新坏导检测阈值，分发器在下一个样本块时重建监测窗口
+    pub async fn set_signal_quality(&self, config: SignalQualityConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.signal_quality.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_artifact_config(&self) -> ArtifactConfig {
+        self.artifact_config.read().await.clone()
+    }
+    
+    /// 更新伪迹检测配置，分发器在下一个样本块时重建检测器
+    pub async fn set_artifact_config(&self, config: ArtifactConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.artifact_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_asr_config(&self) -> AsrConfig {
+        *self.asr_config.read().await
+    }
+    
+    /// 更新ASR配置；仅改变阈值时保留已有校准
+    pub async fn set_asr_config(&self, config: AsrConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.asr_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    /// 请求ASR校准：分发器从下一个样本块开始采集 calibration_seconds 的数据
+    pub async fn request_asr_calibration(&self) -> Result<(), AppError> {
+        if !self.asr_config.read().await.enabled {
+            return Err(AppError::Config("ASR is disabled".to_string()));
+        }
+        if !self.is_running().await {
+            return Err(AppError::Config("Processor is not running".to_string()));
         }
         
-        Ok(stats)
+        self.asr_calibration_requests.fetch_add(1, Ordering::Relaxed);
+        Ok(())
     }
     
-    pub async fn start_recording(&self, filename: &str) -> Result<(), AppError> {
-        let mut recorder_guard = self.recorder.lock().await;
+    pub async fn get_ica_config(&self) -> IcaConfig {
+        *self.ica_config.read().await
+    }
+    
+    /// 更新在线ICA配置，分发器重建分解（已学习的成分随之重置）
+    pub async fn set_ica_config(&self, config: IcaConfig) -> Result<(), AppError> {
+        config.validate()?;
         
-        // 如果已在录制，先停止
-        if recorder_guard.is_some() {
-            drop(recorder_guard);
-            self.stop_recording().await?;
-            recorder_guard = self.recorder.lock().await;
+        let mut config_guard = self.ica_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_ica_rejected(&self) -> Vec<u32> {
+        self.ica_rejected.read().await.clone()
+    }
+    
+    /// 设置重混合显示信号时置零的ICA成分
+    pub async fn set_ica_rejected(&self, components: Vec<u32>) -> Result<(), AppError> {
+        let channels = Self::active_stream_info(&self.stream_info, &*self.channel_selection.read().await).channels_count;
+        if let Some(&component) = components.iter().find(|&&component| component >= channels) {
+            return Err(AppError::Config(format!("ICA component {} out of range (0-{})", component, channels.saturating_sub(1))));
         }
         
-        // 创建新的录制器
-        let new_recorder = EdfRecorder::new(
-            filename.to_string(),
-            self.stream_info.clone(),
-        )?;
+        let mut rejected_guard = self.ica_rejected.write().await;
+        *rejected_guard = components;
         
-        *recorder_guard = Some(new_recorder);
+        Ok(())
+    }
+    
+    pub async fn get_display_rate(&self) -> DisplayRateInfo {
+        let config = *self.display_rate.read().await;
+        DisplayRateInfo {
+            decimation_factor: config.decimation_factor,
+            sample_rate: self.stream_info.sample_rate,
+            display_rate: config.display_rate(self.stream_info.sample_rate),
+        }
+    }
+    
+    /// 更新显示路径抽取倍数，时域收集器在下一帧生效
+    pub async fn set_display_rate(&self, config: DisplayRateConfig) -> Result<(), AppError> {
+        config.validate()?;
         
-        println!("Recording started: {}", filename);
+        let mut config_guard = self.display_rate.write().await;
+        *config_guard = config;
         
         Ok(())
     }
     
-    pub async fn stop_recording(&self) -> Result<(), AppError> {
-        let mut recorder_guard = self.recorder.lock().await;
+    pub async fn get_frame_rate(&self) -> FrameRateConfig {
+        *self.frame_rate.read().await
+    }
+    
+    /// 更新前端帧率，时域收集器与前端线程在下一帧重建定时器
+    pub async fn set_frame_rate(&self, config: FrameRateConfig) -> Result<(), AppError> {
+        config.validate()?;
+        *self.frame_rate.write().await = config;
+        Ok(())
+    }
+    
+    pub async fn get_processing_stages(&self) -> ProcessingStagesConfig {
+        self.processing_stages.read().await.clone()
+    }
+    
+    /// 更新处理阶段链，分发器在下一个数据块重建（阶段状态随之重置）
+    pub async fn set_processing_stages(&self, config: ProcessingStagesConfig) -> Result<(), AppError> {
+        StageRegistry::global()
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .check(&config, self.stream_info.sample_rate)?;
         
-        if let Some(recorder) = recorder_guard.take() {
-            // 关闭录制器并获取统计信息
-            let stats = recorder.close()?;
-            println!("Recording stopped: {:?}", stats);
+        *self.processing_stages.write().await = config;
+        Ok(())
+    }
+    
+    pub async fn get_scripting(&self) -> ScriptingConfig {
+        self.scripting.read().await.clone()
+    }
+    
+    /// 更新自定义指标脚本，分发器与FFT线程在下一个数据块重新编译（脚本状态随之重置）
+    pub async fn set_scripting(&self, config: ScriptingConfig) -> Result<(), AppError> {
+        ScriptHooks::check(&config)?;
+        *self.scripting.write().await = config;
+        Ok(())
+    }
+    
+    pub async fn get_history_config(&self) -> HistoryConfig {
+        self.history.lock().await.config()
+    }
+    
+    /// 调整历史缓冲长度，缩短时立即丢弃多余的旧样本
+    pub async fn set_history_config(&self, config: HistoryConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.history.lock().await.resize(config);
+        Ok(())
+    }
+    
+    /// 单通道最近 seconds 秒的原始数据
+    pub async fn get_history(&self, channel: u32, seconds: f64) -> Result<HistorySegment, AppError> {
+        if channel >= self.stream_info.channels_count {
+            return Err(AppError::Config(format!("Channel {} out of range (0-{})", 
+                                                channel, self.stream_info.channels_count.saturating_sub(1))));
+        }
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return Err(AppError::Config(format!("Invalid history duration: {}", seconds)));
         }
         
+        let history = self.history.lock().await;
+        let (timestamps, values) = history.last_seconds(seconds)
+            .map(|sample| (sample.timestamp, sample.channels.get(channel as usize).copied().unwrap_or(0.0)))
+            .unzip();
+        
+        Ok(HistorySegment {
+            channel,
+            label: self.stream_info.channel_labels.get(channel as usize).cloned().unwrap_or_default(),
+            sample_rate: self.stream_info.sample_rate,
+            timestamps,
+            values,
+        })
+    }
+    
+    pub async fn get_spectrogram_config(&self) -> SpectrogramConfig {
+        self.spectrogram.lock().await.config()
+    }
+    
+    /// 调整频谱图深度/列速率，缩短时立即丢弃多余的旧列
+    pub async fn set_spectrogram_config(&self, config: SpectrogramConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.spectrogram.lock().await.resize(config);
         Ok(())
     }
     
-    /// ✅ 数据分发器 - 确保每个样本都复制给所有消费者
-    async fn spawn_data_distributor(
-        &self,
-        data_rx: crossbeam_channel::Receiver<EegSample>,
-        recording_tx: crossbeam_channel::Sender<EegSample>,
-        time_domain_tx: crossbeam_channel::Sender<EegSample>,
-        is_running: Arc<tokio::sync::RwLock<bool>>,
-    ) -> tokio::task::JoinHandle<()> {
-        tokio::spawn(async move {
-            println!("🟣 Data distributor started - ensuring no data loss");
-            
-            let mut samples_distributed = 0u64;
-            let mut recording_failures = 0u64;
-            let mut time_domain_failures = 0u64;
-            let mut last_stats_time = std::time::Instant::now();
-            
-            loop {
-                // 非阻塞检查停止状态
-                {
-                    let running = is_running.try_read();
-                    if let Ok(running) = running {
-                        if !*running {
-                            println!("🟣 Data distributor stopping");
-                            break;
+    /// 冻结最近 seconds 秒的原始数据（历史缓冲不足时为全部）与当前频谱
+    pub async fn capture_snapshot(&self, seconds: f64) -> Result<Snapshot, AppError> {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return Err(AppError::Config(format!("Invalid snapshot duration: {}", seconds)));
+        }
+        
+        let samples: Vec<EegSample> = self.history.lock().await.last_seconds(seconds).cloned().collect();
+        if samples.is_empty() {
+            return Err(AppError::Config("No data buffered yet for a snapshot".to_string()));
+        }
+        
+        Ok(Snapshot {
+            stream_info: self.stream_info.clone(),
+            requested_seconds: seconds,
+            samples,
+            spectrum: self.spectrogram.lock().await.latest(),
+            notch_filter: *self.notch_config.read().await,
+            filters: *self.filter_config.read().await,
+        })
+    }
+    
+    /// 单通道（显示路径，导联之后）最近 seconds 秒的频谱图
+    pub async fn get_spectrogram(&self, channel: u32, seconds: f64) -> Result<SpectrogramSegment, AppError> {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return Err(AppError::Config(format!("Invalid spectrogram duration: {}", seconds)));
+        }
+        
+        self.spectrogram.lock().await.segment(channel, seconds)
+    }
+    
+    /// 将最近 seconds 秒的历史数据写入独立文件，不影响正在进行的录制
+    pub async fn export_snapshot(&self, filename: &str, seconds: f64, options: RecordingOptions) -> Result<crate::recorder::RecordingStats, AppError> {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return Err(AppError::Config(format!("Invalid snapshot duration: {}", seconds)));
+        }
+        
+        let samples: Vec<EegSample> = {
+            let history = self.history.lock().await;
+            if history.buffered_seconds() < seconds {
+                warn!("⚠️ Snapshot requested {}s but only {:.1}s buffered", seconds, history.buffered_seconds());
+            }
+            history.last_seconds(seconds).cloned().collect()
+        };
+        if samples.is_empty() {
+            return Err(AppError::Recording("History buffer is empty".to_string()));
+        }
+        
+        let metadata = self.session_metadata.read().await.clone();
+        let mut recorder = EdfRecorder::new(filename.to_string(), self.stream_info.clone(), options, &metadata)?;
+        for sample in &samples {
+            recorder.write_sample(sample)?;
+        }
+        let stats = recorder.close()?;
+        
+        info!("📸 Snapshot exported: {} ({} samples)", filename, samples.len());
+        
+        Ok(stats)
+    }
+    
+    pub async fn get_recording_triggers(&self) -> RecordingTriggerConfig {
+        self.recording_triggers.read().await.clone()
+    }
+    
+    /// 更新自动录制触发规则，分发器在下一个样本块时重建规则引擎
+    pub async fn set_recording_triggers(&self, config: RecordingTriggerConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.recording_triggers.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_epoch_config(&self) -> EpochConfig {
+        self.epoch_config.read().await.clone()
+    }
+    
+    /// 更新分段配置，分发器重建分段器（未完成的分段与计数清零）
+    pub async fn set_epoch_config(&self, config: EpochConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.epoch_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_erp_config(&self) -> ErpConfig {
+        *self.erp_config.read().await
+    }
+    
+    /// 更新ERP平均配置，已累计的平均随之清零
+    pub async fn set_erp_config(&self, config: ErpConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.erp_config.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_time_features_config(&self) -> TimeFeaturesConfig {
+        *self.time_features.read().await
+    }
+    
+    /// 更新时域特征配置，分发器重建滑动窗口
+    pub async fn set_time_features_config(&self, config: TimeFeaturesConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.time_features.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_neurofeedback_config(&self) -> NeurofeedbackConfig {
+        self.neurofeedback.read().await.clone()
+    }
+    
+    /// 更新神经反馈规则，分发器重置各规则的保持/不应期状态
+    pub async fn set_neurofeedback_config(&self, config: NeurofeedbackConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.neurofeedback.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_ssvep_config(&self) -> SsvepConfig {
+        self.ssvep.read().await.clone()
+    }
+    
+    /// 更新SSVEP检测配置，分发器重建检测窗口与参考信号
+    pub async fn set_ssvep_config(&self, config: SsvepConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.ssvep.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_classifier_config(&self) -> ClassifierConfig {
+        self.classifier.lock().await.config().clone()
+    }
+    
+    /// 更新分类配置；特征定义变化时清空训练集并需重新训练
+    pub async fn set_classifier_config(&self, config: ClassifierConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.classifier.lock().await.set_config(config);
+        Ok(())
+    }
+    
+    /// 用已收集的带标签分段训练分类器
+    pub async fn train_classifier(&self) -> Result<ClassifierStatus, AppError> {
+        self.classifier.lock().await.train()
+    }
+    
+    /// 开启/关闭在线分类：开启后每个完成的分段推送 classifier-prediction
+    pub async fn set_online_classification(&self, enabled: bool) -> Result<ClassifierStatus, AppError> {
+        self.classifier.lock().await.set_online(enabled)
+    }
+    
+    pub async fn get_classifier_status(&self) -> ClassifierStatus {
+        self.classifier.lock().await.status()
+    }
+    
+    pub async fn get_spatial_filter(&self) -> Option<SpatialFilter> {
+        self.spatial_filter.read().await.clone()
+    }
+    
+    /// 设置/清除空间滤波器，分发器在导联标签一致时启用
+    pub async fn set_spatial_filter(&self, filter: Option<SpatialFilter>) -> Result<(), AppError> {
+        if let Some(filter) = filter.as_ref() {
+            filter.validate()?;
+        }
+        
+        let mut filter_guard = self.spatial_filter.write().await;
+        *filter_guard = filter;
+        
+        Ok(())
+    }
+    
+    /// 用已收集的两类分段训练CSP（不自动应用）
+    pub async fn train_csp(&self, request: &CspTrainingRequest) -> Result<CspResult, AppError> {
+        request.validate()?;
+        self.csp_epochs.lock().await.train(request)
+    }
+    
+    pub async fn get_vitals_config(&self) -> VitalsConfig {
+        *self.vitals.read().await
+    }
+    
+    /// 更新生理信号配置，通道索引为原始流中的索引
+    pub async fn set_vitals_config(&self, config: VitalsConfig) -> Result<(), AppError> {
+        config.validate()?;
+        for channel in [config.ecg_channel, config.respiration_channel].into_iter().flatten() {
+            if channel >= self.stream_info.channels_count {
+                return Err(AppError::Config(format!("Vitals channel {} out of range (0-{})", 
+                                                    channel, self.stream_info.channels_count.saturating_sub(1))));
+            }
+        }
+        
+        let mut config_guard = self.vitals.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_impedance_config(&self) -> ImpedanceConfig {
+        self.impedance.lock().await.config()
+    }
+    
+    /// 更新阻抗阈值与检查协议参数，已有的阻抗按新阈值重新分级
+    pub async fn set_impedance_config(&self, config: ImpedanceConfig) -> Result<(), AppError> {
+        config.validate()?;
+        let snapshot = {
+            let mut tracker = self.impedance.lock().await;
+            tracker.set_config(config);
+            tracker.snapshot()
+        };
+        self.session_metadata.write().await.impedances = snapshot;
+        Ok(())
+    }
+    
+    /// 各电极最近一次阻抗，按电极首次出现的顺序
+    pub async fn get_impedances(&self) -> Vec<ElectrodeImpedance> {
+        self.impedance.lock().await.snapshot()
+    }
+    
+    /// 记录一组阻抗值（阻抗流、通道元数据或检查协议），写入会话元数据并发送 impedance-update
+    pub async fn update_impedances(&self, source: ImpedanceSource, readings: &[(String, f64)], timestamp: f64) -> ImpedanceUpdate {
+        Self::publish_impedances(&self.app_handle, &self.impedance, &self.session_metadata, source, readings, timestamp).await
+    }
+    
+    /// 阻抗检查协议：放大器开启检测电流后调用，测量 check_seconds 秒的数据并换算各电极阻抗
+    pub async fn run_impedance_check(&self) -> Result<ImpedanceUpdate, AppError> {
+        if !*self.is_running.read().await {
+            return Err(AppError::NotConnected);
+        }
+        
+        let config = self.get_impedance_config().await;
+        info!("🔋 Running impedance check for {:.1}s at {}Hz", config.check_seconds, config.drive_frequency);
+        tokio::time::sleep(Duration::from_secs_f64(config.check_seconds)).await;
+        
+        let samples: Vec<EegSample> = self.history.lock().await.last_seconds(config.check_seconds).cloned().collect();
+        let readings = impedance::check_impedances(&samples, &self.stream_info.channel_labels, self.stream_info.sample_rate, &config)?;
+        let timestamp = samples.last().map(|sample| sample.timestamp).unwrap_or_default();
+        
+        Ok(self.update_impedances(ImpedanceSource::Check, &readings, timestamp).await)
+    }
+    
+    async fn publish_impedances(
+        app_handle: &StreamEmitter,
+        tracker: &Mutex<ImpedanceTracker>,
+        session_metadata: &tokio::sync::RwLock<SessionMetadata>,
+        source: ImpedanceSource,
+        readings: &[(String, f64)],
+        timestamp: f64
+    ) -> ImpedanceUpdate {
+        let (update, snapshot) = {
+            let mut tracker = tracker.lock().await;
+            let update = tracker.update(source, readings, timestamp);
+            (update, tracker.snapshot())
+        };
+        session_metadata.write().await.impedances = snapshot;
+        
+        if let Err(e) = app_handle.emit("impedance-update", &update) {
+            error!("Failed to emit impedance-update: {}", e);
+        }
+        update
+    }
+    
+    pub async fn get_osc_config(&self) -> OscConfig {
+        self.osc.read().await.clone()
+    }
+    
+    /// 更新OSC输出配置，分发器重建发送端
+    pub async fn set_osc_config(&self, config: OscConfig) -> Result<(), AppError> {
+        config.validate()?;
+        
+        let mut config_guard = self.osc.write().await;
+        *config_guard = config;
+        
+        Ok(())
+    }
+    
+    pub async fn get_session_metadata(&self) -> SessionMetadata {
+        self.session_metadata.read().await.clone()
+    }
+    
+    /// 更新会话元数据，从下一个录制文件起生效；未提供阻抗时保留已测得的阻抗
+    pub async fn set_session_metadata(&self, mut metadata: SessionMetadata) -> Result<(), AppError> {
+        metadata.validate()?;
+        
+        let mut metadata_guard = self.session_metadata.write().await;
+        if metadata.impedances.is_empty() {
+            metadata.impedances = std::mem::take(&mut metadata_guard.impedances);
+        }
+        *metadata_guard = metadata;
+        
+        Ok(())
+    }
+    
+    pub async fn get_channel_selection(&self) -> ChannelSelection {
+        self.channel_selection.read().await.clone()
+    }
+    
+    /// 更新通道选择，分发器在下一个样本块时重建导联
+    pub async fn set_channel_selection(&self, selection: ChannelSelection) -> Result<ChannelSelectionInfo, AppError> {
+        selection.validate(self.stream_info.channels_count)?;
+        
+        let active_labels = Self::active_stream_info(&self.stream_info, &selection).channel_labels;
+        
+        let mut selection_guard = self.channel_selection.write().await;
+        *selection_guard = selection.clone();
+        
+        Ok(ChannelSelectionInfo { selection, active_labels })
+    }
+    
+    /// 按配置创建OSC发送端，目标无法解析或绑定失败时只记录错误
+    fn build_osc_sender(config: &OscConfig) -> Option<OscSender> {
+        if !config.enabled {
+            return None;
+        }
+        match OscSender::new(config) {
+            Ok(sender) => Some(sender),
+            Err(e) => {
+                error!("❌ Failed to start OSC output: {}", e);
+                None
+            }
+        }
+    }
+    
+    /// 仅含参与处理通道的流信息（导联按此解析）
+    fn active_stream_info(stream_info: &StreamInfo, selection: &ChannelSelection) -> StreamInfo {
+        let mask = selection.mask(stream_info.channels_count);
+        let channel_labels: Vec<String> = stream_info.channel_labels.iter()
+            .zip(&mask)
+            .filter(|(_, &active)| active)
+            .map(|(label, _)| label.clone())
+            .collect();
+        
+        StreamInfo {
+            channels_count: channel_labels.len() as u32,
+            channel_labels,
+            ..stream_info.clone()
+        }
+    }
+    
+    /// 当前流可用的导联预设
+    pub fn available_montages(&self) -> Vec<MontagePresetInfo> {
+        montage::available_presets(&self.stream_info.channel_labels)
+    }
+    
+    /// 设置采集模式，需在start之前调用
+    pub fn set_acquisition_mode(&mut self, mode: AcquisitionMode) {
+        self.acquisition_mode = mode;
+    }
+    
+    /// 设置管道通道容量与溢出策略，在start或restart_pipeline之前调用
+    pub fn set_channel_config(&mut self, config: PipelineChannelConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.channel_config = config;
+        Ok(())
+    }
+    
+    /// 订阅二进制帧（传入None取消），订阅期间不再发送JSON帧事件
+    pub async fn set_frame_channel(&self, channel: Option<Channel>) {
+        *self.frame_channel.write().await = channel;
+    }
+    
+    /// 设置前端帧订阅（运行中生效）
+    pub async fn set_frame_subscription(&self, subscription: FrameSubscription) -> Result<(), AppError> {
+        subscription.validate()?;
+        *self.frame_subscription.write().await = subscription;
+        Ok(())
+    }
+    
+    pub async fn get_frame_subscription(&self) -> FrameSubscription {
+        self.frame_subscription.read().await.clone()
+    }
+    
+    /// 暂停/恢复前端帧发送（暂停期间到达的显示批次直接丢弃）
+    pub async fn set_streaming_paused(&self, paused: bool) {
+        *self.streaming_paused.write().await = paused;
+    }
+    
+    pub async fn is_streaming_paused(&self) -> bool {
+        *self.streaming_paused.read().await
+    }
+    
+    /// 设置LSL拉取阶段的延迟统计（由LslManager提供）
+    pub fn set_source_stats(&self, stats: Arc<StageStats>) {
+        let _ = self.metrics.lsl_pull.set(stats);
+    }
+    
+    /// 设置数据源（由LslManager提供）
+    pub fn set_data_source(&mut self, data_rx: crossbeam_channel::Receiver<EegChunk>) {
+        self.data_rx = Some(data_rx);
+    }
+    
+    /// 设置标记流来源（由LslManager提供）
+    pub fn set_marker_source(&mut self, marker_rx: crossbeam_channel::Receiver<LslMarker>) {
+        self.marker_rx = Some(marker_rx);
+    }
+    
+    /// 设置辅助流（IMU等）来源（由LslManager提供）
+    pub fn set_aux_source(&mut self, aux_rx: crossbeam_channel::Receiver<AuxSample>) {
+        self.aux_rx = Some(aux_rx);
+    }
+    
+    /// 设置阻抗流来源（由LslManager提供）
+    pub fn set_impedance_source(&mut self, impedance_rx: crossbeam_channel::Receiver<AuxSample>) {
+        self.impedance_rx = Some(impedance_rx);
+    }
+    
+    /// 设置LSL时钟偏移测量来源（由LslManager提供），录制时写入旁路文件
+    pub fn set_clock_source(&mut self, clock_rx: crossbeam_channel::Receiver<ClockOffsetSample>) {
+        self.clock_rx = Some(clock_rx);
+    }
+    
+    /// 启动EEG处理
+    pub async fn start(&mut self) -> Result<(), AppError> {
+        let mut is_running = self.is_running.write().await;
+        if *is_running {
+            return Err(AppError::Config("Processor already running".to_string()));
+        }
+        
+        let data_rx = self.data_rx.as_ref()
+            .ok_or(AppError::NotConnected)?
+            .clone();
+        
+        *is_running = true;
+        drop(is_running); // 早释放锁
+        
+        // 启动全crossbeam处理管道
+        self.start_crossbeam_pipeline(data_rx).await?;
+        
+        Ok(())
+    }
+    
+    /// 重建处理线程而不断开数据源
+    ///
+    /// 录制器与数据源通道保留：停止期间到达的样本块在数据源通道中排队，由新的分发器继续处理；
+    /// 录制线程退出前取空录制队列，录制文件不缺样本。滤波器状态与等待对齐的标记随线程重建。
+    pub async fn restart_pipeline(&mut self) -> Result<(), AppError> {
+        let data_rx = self.data_rx.clone().ok_or(AppError::NotConnected)?;
+        if !self.is_running().await {
+            return Err(AppError::Config("Processor is not running".to_string()));
+        }
+        
+        info!("🔄 Restarting processing pipeline for {}", self.stream_info.name);
+        *self.is_running.write().await = false;
+        self.fft_shutdown = None;
+        while let Some(handle) = self.thread_handles.pop() {
+            if let Err(e) = handle.await {
+                error!("Thread join error: {:?}", e);
+            }
+        }
+        
+        *self.is_running.write().await = true;
+        self.start_crossbeam_pipeline(data_rx).await?;
+        info!("✅ Processing pipeline restarted ({} queued chunks)", self.data_rx.as_ref().map_or(0, |rx| rx.len()));
+        
+        Ok(())
+    }
+    
+    /// ✅ 消费式停止 - 消费 self，返回统计信息
+    pub async fn stop(mut self) -> Result<EegProcessorStats, AppError> {
+        info!("🛑 Stopping EEG Processor");
+        
+        let mut is_running = self.is_running.write().await;
+        *is_running = false;
+        drop(is_running);
+        self.fft_shutdown = None;
+        
+        // 等待所有线程结束
+        let threads_spawned = self.thread_handles.len() as u32;
+        while let Some(handle) = self.thread_handles.pop() {
+            if let Err(e) = handle.await {
+                error!("Thread join error: {:?}", e);
+            }
+        }
+        
+        // 停止录制并获取统计信息
+        let recording_stats = {
+            let mut recorder_guard = self.recorder.lock().await;
+            if let Some(recorder) = recorder_guard.take() {
+                Some(self.recording_log.finish(recorder).await?)
+            } else {
+                None
+            }
+        };
+        
+        let capture_stats = self.stop_capture().await?;
+        
+        // 生成处理器统计信息
+        let stats = EegProcessorStats {
+            stream_info: self.stream_info.clone(),
+            recording_stats: recording_stats.clone(),
+            capture_stats,
+            threads_spawned,
+        };
+        
+        // ✅ 实际使用统计字段
+        info!("📊 EEG Processor stopped:");
+        info!("   - Stream: {} ({}Hz, {} channels)", 
+                 stats.stream_info.name, 
+                 stats.stream_info.sample_rate, 
+                 stats.stream_info.channels_count);
+        info!("   - Threads spawned: {}", stats.threads_spawned);
+        
+        if let Some(ref rec_stats) = stats.recording_stats {
+            info!("   - Recording stats:");
+            info!("     • Samples recorded: {}", rec_stats.samples_written);
+            info!("     • Duration: {:.2}s", rec_stats.duration_seconds);
+            info!("     • File size: {} bytes", rec_stats.file_size_bytes);
+        } else {
+            info!("   - No recording session");
+        }
+        if let Some(ref capture_stats) = stats.capture_stats {
+            info!("   - Capture: {} samples to {}", capture_stats.samples_written, capture_stats.filename);
+        }
+        
+        Ok(stats)
+    }
+    
+    /// 开始录制；`outputs` 为同时写入的附加文件（如原始存档 + 滤波复核）
+    pub async fn start_recording(
+        &self,
+        filename: &str,
+        options: RecordingOptions,
+        outputs: Vec<RecordingOutput>,
+    ) -> Result<(), AppError> {
+        let mut recorder_guard = self.recorder.lock().await;
+        
+        // 如果已在录制，先停止
+        if recorder_guard.is_some() {
+            drop(recorder_guard);
+            self.stop_recording().await?;
+            recorder_guard = self.recorder.lock().await;
+        }
+        
+        // 创建新的录制器
+        let metadata = self.session_metadata.read().await.clone();
+        let new_recorder = Self::create_recorder(filename, &self.stream_info, options, outputs, &metadata, &self.history, &self.anomalies).await?;
+        
+        *recorder_guard = Some(new_recorder);
+        
+        info!("Recording started: {}", filename);
+        
+        Ok(())
+    }
+    
+    /// 本会话最近完成的录制（录制进行中时为上一次）
+    pub async fn last_recording(&self) -> Option<FinishedRecording> {
+        self.recording_log.last.lock().await.clone()
+    }
+    
+    /// 录制结束时登记到录制索引；需在 start() 之前设置
+    pub fn set_catalog(&mut self, catalog: Arc<RecordingCatalog>) {
+        self.recording_log.catalog = Some(catalog);
+    }
+    
+    /// 录制结束时按共享的上传配置自动上传；需在 start() 之前设置
+    pub fn set_auto_upload(&mut self, config: Arc<Mutex<UploadConfig>>) {
+        self.recording_log.upload = Some(AutoUpload::new(config, self.app_handle.clone()));
+    }
+    
+    /// 创建录制器并按需前置历史数据，写入未录制期间的采集异常；调用方需持有录制器锁，
+    /// 录制线程随后写入的重复样本按 sample_id 去重
+    async fn create_recorder(
+        filename: &str,
+        stream_info: &StreamInfo,
+        options: RecordingOptions,
+        outputs: Vec<RecordingOutput>,
+        metadata: &SessionMetadata,
+        history: &Mutex<SampleHistory>,
+        anomalies: &AnomalyLog,
+    ) -> Result<MultiRecorder, AppError> {
+        let include_last_seconds = options.include_last_seconds.filter(|&seconds| seconds > 0.0);
+        
+        let mut recorder = MultiRecorder::new(filename.to_string(), options, outputs, stream_info, metadata)?;
+        
+        if let Some(seconds) = include_last_seconds {
+            let samples: Vec<EegSample> = history.lock().await.last_seconds(seconds).cloned().collect();
+            recorder.prepend_history(&samples)?;
+            info!("⏪ Prepended {:.1}s of buffered data", samples.len() as f64 / stream_info.sample_rate);
+        }
+        anomalies.flush(&mut recorder).await;
+        
+        Ok(recorder)
+    }
+    
+    pub async fn stop_recording(&self) -> Result<(), AppError> {
+        let mut recorder_guard = self.recorder.lock().await;
+        
+        if let Some(recorder) = recorder_guard.take() {
+            // 关闭录制器并获取统计信息
+            let stats = self.recording_log.finish(recorder).await?;
+            info!("Recording stopped: {:?}", stats);
+        }
+        
+        Ok(())
+    }
+    
+    /// 开始采集处理器收到的原始样本块与标记，可用 headless::replay 确定性回放
+    pub async fn start_capture(&self, filename: &str) -> Result<(), AppError> {
+        let mut capture = self.capture.lock().await;
+        if capture.is_some() {
+            return Err(AppError::Capture("A capture is already in progress".to_string()));
+        }
+        *capture = Some(CaptureWriter::create(filename, &self.stream_info)?);
+        Ok(())
+    }
+    
+    /// 结束采集，未在采集时返回 None
+    pub async fn stop_capture(&self) -> Result<Option<CaptureStats>, AppError> {
+        self.capture.lock().await.take().map(CaptureWriter::finish).transpose()
+    }
+    
+    pub async fn pause_recording(&self) -> Result<(), AppError> {
+        let mut recorder_guard = self.recorder.lock().await;
+        recorder_guard.as_mut()
+            .ok_or_else(|| AppError::Recording("No active recording".to_string()))?
+            .pause()
+    }
+    
+    /// 恢复录制，返回本次暂停时长（秒）
+    pub async fn resume_recording(&self) -> Result<f64, AppError> {
+        let mut recorder_guard = self.recorder.lock().await;
+        recorder_guard.as_mut()
+            .ok_or_else(|| AppError::Recording("No active recording".to_string()))?
+            .resume()
+    }
+    
+    /// 在当前录制时间点写入事件注释，返回注释起点（秒）
+    pub async fn add_annotation(&self, description: &str, duration_seconds: Option<f64>) -> Result<f64, AppError> {
+        let description = description.trim();
+        if description.is_empty() {
+            return Err(AppError::Config("Annotation description must not be empty".to_string()));
+        }
+        if let Some(duration) = duration_seconds {
+            if !duration.is_finite() || duration < 0.0 {
+                return Err(AppError::Config(format!("Invalid annotation duration: {}", duration)));
+            }
+        }
+        
+        let mut recorder_guard = self.recorder.lock().await;
+        let recorder = recorder_guard.as_mut()
+            .ok_or_else(|| AppError::Recording("No active recording".to_string()))?;
+        
+        let onset = recorder.elapsed_seconds();
+        recorder.write_marker(onset, duration_seconds, description)?;
+        
+        Ok(onset)
+    }
+    
+    /// 上报采集异常：写入EDF+注释（未录制时暂存到下次录制）并发送事件
+    async fn report_anomaly(
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+        anomalies: &AnomalyLog,
+        anomaly: AcquisitionAnomaly,
+    ) {
+        warn!("⚠️ Acquisition anomaly ({}): {}", anomaly.kind.label(), anomaly.message);
+        
+        {
+            let mut recorder_guard = recorder.lock().await;
+            anomalies.record(recorder_guard.as_mut(), &anomaly).await;
+        }
+        
+        let event = if anomaly.fatal { "acquisition-fatal" } else { "acquisition-anomaly" };
+        if let Err(e) = app_handle.emit(event, &anomaly) {
+            error!("Failed to emit {}: {}", event, e);
+        }
+    }
+    
+    /// 上报数据间隙：写入EDF+注释（如正在录制，时长为间隙长度）并发送 data-gap 事件
+    async fn report_gap(
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+        metrics: &PipelineMetrics,
+        mut gap: DataGap,
+    ) {
+        PipelineMetrics::add(&metrics.data_gaps, 1);
+        warn!("⚠️ Data gap ({:?}) before sample #{}: {:.3}s, ~{} samples missing", 
+                 gap.kind, gap.sample_id, gap.duration_seconds, gap.missing_samples);
+        
+        {
+            let mut recorder_guard = recorder.lock().await;
+            if let Some(recorder) = recorder_guard.as_mut() {
+                let onset = recorder.onset_for_timestamp(gap.start_timestamp);
+                let description = format!("Data gap ({} samples missing)", gap.missing_samples);
+                match recorder.write_annotation(onset, Some(gap.duration_seconds.max(0.0)), &description) {
+                    Ok(_) => gap.recording_onset = Some(onset),
+                    Err(e) => error!("❌ Failed to annotate data gap: {}", e),
+                }
+            }
+        }
+        
+        if let Err(e) = app_handle.emit("data-gap", &gap) {
+            error!("Failed to emit data-gap: {}", e);
+        }
+    }
+    
+    /// 上报伪迹段：按配置写入EDF+注释并发送 artifact-detected 事件
+    async fn report_artifact(
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+        annotate: bool,
+        mut artifact: ArtifactEvent,
+    ) {
+        if annotate {
+            let mut recorder_guard = recorder.lock().await;
+            if let Some(recorder) = recorder_guard.as_mut() {
+                let onset = recorder.onset_for_timestamp(artifact.start_timestamp);
+                let duration = (artifact.end_timestamp - artifact.start_timestamp).max(0.0);
+                let description = format!("Artifact {} ({})", artifact.kind.label(), artifact.channel_label);
+                match recorder.write_annotation(onset, Some(duration), &description) {
+                    Ok(_) => artifact.recording_onset = Some(onset),
+                    Err(e) => error!("❌ Failed to annotate artifact: {}", e),
+                }
+            }
+        }
+        
+        if let Err(e) = app_handle.emit("artifact-detected", &artifact) {
+            error!("Failed to emit artifact-detected: {}", e);
+        }
+    }
+    
+    /// 上报完成的分段：epoch-extracted 携带分段数据（可关闭）与各标记的累计计数
+    fn report_epoch(app_handle: &StreamEmitter, counts: &[EpochCount], emit_epochs: bool, epoch: Epoch) {
+        if let Some(reason) = epoch.reject_reason.as_ref() {
+            debug!("🟣 Epoch '{}' rejected: {}", epoch.label, reason);
+        }
+        
+        let event = EpochEvent {
+            label: epoch.label.clone(),
+            rejected: epoch.rejected,
+            epoch: emit_epochs.then_some(epoch),
+            counts: counts.to_vec(),
+        };
+        if let Err(e) = app_handle.emit("epoch-extracted", &event) {
+            error!("Failed to emit epoch-extracted: {}", e);
+        }
+    }
+    
+    /// Strict模式致命错误：注释并完成当前录制，然后停止整个处理管道
+    async fn abort_acquisition(
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+        recording_log: &RecordingLog,
+        anomalies: &AnomalyLog,
+        is_running: &Arc<tokio::sync::RwLock<bool>>,
+        anomaly: AcquisitionAnomaly,
+    ) {
+        Self::report_anomaly(app_handle, recorder, anomalies, anomaly).await;
+        
+        {
+            let mut recorder_guard = recorder.lock().await;
+            if let Some(recorder) = recorder_guard.take() {
+                match recording_log.finish(recorder).await {
+                    Ok(stats) => info!("🛑 Strict mode: recording finalized: {:?}", stats),
+                    Err(e) => {
+                        error!("❌ Strict mode: failed to finalize recording: {}", e);
+                        app_handle.emit_error(ErrorSource::Recording, &e);
+                    }
+                }
+            }
+        }
+        
+        *is_running.write().await = false;
+        info!("🛑 Strict mode: acquisition aborted");
+    }
+    
+    /// Strict模式下的逐样本完整性检查
+    fn check_sample_integrity(
+        sample: &EegSample,
+        last_timestamp: Option<f64>,
+        expected_channels: u32,
+    ) -> Option<AcquisitionAnomaly> {
+        if sample.channels.len() != expected_channels as usize {
+            return Some(AcquisitionAnomaly {
+                kind: AnomalyKind::ChannelCountMismatch,
+                message: format!("Sample has {} channels, expected {}", 
+                                 sample.channels.len(), expected_channels),
+                sample_id: Some(sample.sample_id),
+                lsl_timestamp: Some(sample.timestamp),
+                fatal: false,
+            });
+        }
+        
+        if let Some(last) = last_timestamp {
+            if sample.timestamp < last {
+                return Some(AcquisitionAnomaly {
+                    kind: AnomalyKind::NonMonotonicTimestamp,
+                    message: format!("Timestamp {:.6} precedes previous {:.6}", sample.timestamp, last),
+                    sample_id: Some(sample.sample_id),
+                    lsl_timestamp: Some(sample.timestamp),
+                    fatal: false,
+                });
+            }
+        }
+        
+        None
+    }
+    
+    /// 标记融合：把时间戳不晚于当前样本的标记对齐到该样本，发送事件并写入EDF+注释
+    async fn fuse_markers(
+        pending_markers: &mut std::collections::VecDeque<LslMarker>,
+        sample: &EegSample,
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+    ) -> Vec<MarkerEvent> {
+        let mut fused = Vec::new();
+        
+        while pending_markers.front().is_some_and(|marker| marker.timestamp <= sample.timestamp) {
+            let Some(marker) = pending_markers.pop_front() else { break };
+            
+            let recording_onset = {
+                let mut recorder_guard = recorder.lock().await;
+                match recorder_guard.as_mut() {
+                    // 录制线程尚未写入首个样本时由录制器暂存，写入后按LSL时间对齐
+                    Some(recorder) => {
+                        match recorder.write_marker_at(marker.timestamp, None, &marker.label) {
+                            Ok(onset) => onset,
+                            Err(e) => {
+                                error!("❌ Failed to annotate marker '{}': {}", marker.label, e);
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                }
+            };
+            
+            let event = MarkerEvent {
+                label: marker.label,
+                timestamp: marker.timestamp,
+                stream_name: marker.stream_name,
+                sample_id: sample.sample_id,
+                recording_onset,
+            };
+            
+            if let Err(e) = app_handle.emit("marker-received", &event) {
+                warn!("⚠️ Failed to emit marker event: {}", e);
+            }
+            
+            fused.push(event);
+        }
+        
+        fused
+    }
+    
+    /// 执行触发规则：开始/停止录制，写入原因注释并发送 recording-triggered 事件
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_trigger(
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+        recording_log: &RecordingLog,
+        anomalies: &AnomalyLog,
+        history: &Mutex<SampleHistory>,
+        session_metadata: &tokio::sync::RwLock<SessionMetadata>,
+        stream_info: &StreamInfo,
+        config: &RecordingTriggerConfig,
+        fired: TriggerFired,
+        timestamp: f64,
+    ) {
+        let mut recorder_guard = recorder.lock().await;
+        
+        let filename = match fired.action {
+            TriggerAction::Start => {
+                if recorder_guard.is_some() {
+                    return;
+                }
+                
+                let filename = triggers::triggered_filename(&config.filename, chrono::Local::now());
+                let metadata = session_metadata.read().await.clone();
+                match Self::create_recorder(&filename, stream_info, config.options.clone(), Vec::new(), &metadata, history, anomalies).await {
+                    Ok(mut new_recorder) => {
+                        let onset = new_recorder.elapsed_seconds();
+                        if let Err(e) = new_recorder.write_annotation(onset, None, &format!("Triggered start: {}", fired.reason)) {
+                            error!("❌ Failed to annotate trigger: {}", e);
+                        }
+                        *recorder_guard = Some(new_recorder);
+                        filename
+                    }
+                    Err(e) => {
+                        error!("❌ Triggered recording failed to start: {}", e);
+                        app_handle.emit_error(ErrorSource::Trigger, &e);
+                        return;
+                    }
+                }
+            }
+            TriggerAction::Stop => {
+                let Some(mut active) = recorder_guard.take() else {
+                    return;
+                };
+                
+                let onset = active.onset_for_timestamp(timestamp);
+                if let Err(e) = active.write_annotation(onset, None, &format!("Triggered stop: {}", fired.reason)) {
+                    error!("❌ Failed to annotate trigger: {}", e);
+                }
+                let filename = active.current_filename().to_string();
+                match recording_log.finish(active).await {
+                    Ok(stats) => info!("Recording stopped by trigger: {:?}", stats),
+                    Err(e) => {
+                        error!("❌ Failed to close triggered recording: {}", e);
+                        app_handle.emit_error(ErrorSource::Trigger, &e);
+                    }
+                }
+                filename
+            }
+        };
+        
+        info!("🎯 Recording {:?} by rule #{}: {}", fired.action, fired.rule_index, fired.reason);
+        
+        let event = RecordingTriggerEvent {
+            action: fired.action,
+            rule_index: fired.rule_index,
+            reason: fired.reason,
+            timestamp,
+            filename: Some(filename),
+        };
+        if let Err(e) = app_handle.emit("recording-triggered", &event) {
+            error!("Failed to emit recording-triggered: {}", e);
+        }
+    }
+    
+    /// 录制路径：被排除的通道写入零值，文件通道布局不变
+    fn zero_excluded(channels: &mut [f64], mask: &[bool]) {
+        for (value, &active) in channels.iter_mut().zip(mask) {
+            if !active {
+                *value = 0.0;
+            }
+        }
+    }
+    
+    /// 显示路径：只保留参与处理的通道
+    fn retain_active(channels: &mut Vec<f64>, mask: &[bool]) {
+        let mut index = 0;
+        channels.retain(|_| {
+            let keep = mask.get(index).copied().unwrap_or(true);
+            index += 1;
+            keep
+        });
+    }
+    
+    /// 构建导联，配置与当前流不匹配时回退为原始参考
+    fn build_montage(config: &MontageConfig, stream_info: &StreamInfo) -> Montage {
+        Montage::build(config, &stream_info.channel_labels).unwrap_or_else(|e| {
+            warn!("⚠️ Montage not applicable to stream '{}': {}, using raw reference", stream_info.name, e);
+            Montage::Raw
+        })
+    }
+    
+    /// 按当前显示路径通道构建处理阶段链，无法构建时跳过整条链
+    fn build_stage_pipeline(config: &ProcessingStagesConfig, stream_info: &StreamInfo, labels: &[String]) -> Option<StagePipeline> {
+        if config.stages.is_empty() {
+            return None;
+        }
+        
+        let info = StreamInfo {
+            channels_count: labels.len() as u32,
+            channel_labels: labels.to_vec(),
+            ..stream_info.clone()
+        };
+        let registry = StageRegistry::global().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match StagePipeline::build(config, &info, &registry) {
+            Ok(pipeline) => {
+                info!("🟣 Processing stages: {:?}", pipeline.names());
+                Some(pipeline)
+            }
+            Err(e) => {
+                warn!("⚠️ Processing stages not applicable to {:?}: {}; disabled", labels, e);
+                None
+            }
+        }
+    }
+    
+    /// ✅ 数据分发器 - 确保每个样本都复制给所有消费者
+    #[allow(clippy::too_many_arguments)]
+    async fn spawn_data_distributor(
+        &self,
+        data_rx: crossbeam_channel::Receiver<EegChunk>,
+        marker_rx: crossbeam_channel::Receiver<LslMarker>,
+        aux_rx: crossbeam_channel::Receiver<AuxSample>,
+        aux_frame_tx: crossbeam_channel::Sender<AuxSample>,
+        impedance_rx: crossbeam_channel::Receiver<AuxSample>,
+        clock_rx: crossbeam_channel::Receiver<ClockOffsetSample>,
+        recording_tx: PipelineSender<RecordingChunk>,
+        time_domain_tx: PipelineSender<DisplayChunk>,
+        app_handle: StreamEmitter,
+        recorder: Arc<Mutex<Option<MultiRecorder>>>,
+        stream_info: StreamInfo,
+        notch_config: Arc<tokio::sync::RwLock<NotchFilterConfig>>,
+        filter_config: Arc<tokio::sync::RwLock<FilterConfig>>,
+        montage_config: Arc<tokio::sync::RwLock<MontageConfig>>,
+        channel_selection: Arc<tokio::sync::RwLock<ChannelSelection>>,
+        signal_quality: Arc<tokio::sync::RwLock<SignalQualityConfig>>,
+        artifact_config: Arc<tokio::sync::RwLock<ArtifactConfig>>,
+        asr_config: Arc<tokio::sync::RwLock<AsrConfig>>,
+        asr_calibration_requests: Arc<AtomicU64>,
+        ica_config: Arc<tokio::sync::RwLock<IcaConfig>>,
+        ica_rejected: Arc<tokio::sync::RwLock<Vec<u32>>>,
+        history: Arc<Mutex<SampleHistory>>,
+        recording_triggers: Arc<tokio::sync::RwLock<RecordingTriggerConfig>>,
+        epoch_config: Arc<tokio::sync::RwLock<EpochConfig>>,
+        erp_config: Arc<tokio::sync::RwLock<ErpConfig>>,
+        time_features: Arc<tokio::sync::RwLock<TimeFeaturesConfig>>,
+        neurofeedback: Arc<tokio::sync::RwLock<NeurofeedbackConfig>>,
+        ssvep: Arc<tokio::sync::RwLock<SsvepConfig>>,
+        classifier: Arc<Mutex<ClassifierSession>>,
+        spatial_filter: Arc<tokio::sync::RwLock<Option<SpatialFilter>>>,
+        csp_epochs: Arc<Mutex<CspEpochStore>>,
+        vitals: Arc<tokio::sync::RwLock<VitalsConfig>>,
+        impedance: Arc<Mutex<ImpedanceTracker>>,
+        osc: Arc<tokio::sync::RwLock<OscConfig>>,
+        session_metadata: Arc<tokio::sync::RwLock<SessionMetadata>>,
+        processing_stages: Arc<tokio::sync::RwLock<ProcessingStagesConfig>>,
+        scripting: Arc<tokio::sync::RwLock<ScriptingConfig>>,
+        band_power_rx: crossbeam_channel::Receiver<BandPowerUpdate>,
+        script_metrics_rx: crossbeam_channel::Receiver<ScriptMetricsUpdate>,
+        acquisition_mode: AcquisitionMode,
+        metrics: Arc<PipelineMetrics>,
+        is_running: Arc<tokio::sync::RwLock<bool>>,
+    ) -> tokio::task::JoinHandle<()> {
+        let recording_log = self.recording_log.clone();
+        let anomalies = self.anomalies.clone();
+        let capture = self.capture.clone();
+        tokio::spawn(async move {
+            info!("🟣 Data distributor started - ensuring no data loss");
+            
+            let mut samples_distributed = 0u64;
+            let mut recording_failures = 0u64;
+            let mut time_domain_failures = 0u64;
+            let mut last_stats_time = std::time::Instant::now();
+            
+            // Strict模式：完整性检查与队列深度上限
+            let strict = acquisition_mode.is_strict();
+            let max_queue_depth = (stream_info.sample_rate.max(1.0) * STRICT_MAX_QUEUE_SECONDS) as usize;
+            let mut last_timestamp: Option<f64> = None;
+            
+            // 录制输出需要的原始/滤波后通道值；录制器锁被占用时沿用上次的结果
+            let mut record_raw = false;
+            let mut record_filtered = false;
+            
+            // 样本丢失/时间间隙检测（所有采集模式）
+            let mut gap_detector = GapDetector::new(stream_info.sample_rate);
+            
+            // 工频陷波滤波器 - 在分发前处理，所有下游消费者收到同样的滤波数据
+            let channels_count = stream_info.channels_count as usize;
+            let mut current_notch = *notch_config.read().await;
+            let mut notch_bank = NotchFilterBank::new(&current_notch, stream_info.sample_rate, channels_count);
+            
+            // 高通/低通滤波链 - 默认只作用于显示路径
+            let mut current_filters = *filter_config.read().await;
+            let mut filter_chain = FilterChain::new(&current_filters, stream_info.sample_rate, channels_count);
+            
+            // 导联 - 只作用于显示与FFT路径，录制始终保留放大器原始参考
+            // 坏导检测 - 基于陷波前的原始数据，每秒评分一次
+            let build_quality_monitor = |config: SignalQualityConfig, line_frequency: f64| {
+                config.enabled.then(|| QualityMonitor::new(
+                    config, stream_info.sample_rate, channels_count, line_frequency
+                ))
+            };
+            let mut current_quality = *signal_quality.read().await;
+            let mut quality_monitor = build_quality_monitor(current_quality, current_notch.frequency_hz);
+            
+            // 伪迹检测 - 基于陷波后、显示滤波前的数据
+            let build_artifact_detector = |config: &ArtifactConfig| {
+                config.enabled.then(|| {
+                    let detector = ArtifactDetector::new(config.clone(), &stream_info);
+                    info!("🟣 Artifact detection on {:?}", detector.channel_labels());
+                    detector
+                })
+            };
+            let mut current_artifacts = artifact_config.read().await.clone();
+            let mut artifact_detector = build_artifact_detector(&current_artifacts);
+            
+            // 自动录制触发规则
+            let mut current_triggers = recording_triggers.read().await.clone();
+            let mut trigger_engine = TriggerEngine::new(&current_triggers);
+            
+            // 心率/呼吸 - 基于原始流中的辅助通道（陷波之后）
+            let mut current_vitals = *vitals.read().await;
+            let mut vitals_monitor = current_vitals.enabled.then(|| VitalsMonitor::new(current_vitals, stream_info.sample_rate));
+            
+            // 神经反馈阈值规则
+            let mut current_feedback = neurofeedback.read().await.clone();
+            let mut feedback_engine = FeedbackEngine::new(&current_feedback);
+            
+            // OSC输出 - 频带功率与反馈事件
+            let mut current_osc = osc.read().await.clone();
+            let mut osc_sender = Self::build_osc_sender(&current_osc);
+            
+            // 通道选择 - 被排除的通道在导联之前从显示路径移除
+            let mut current_selection = channel_selection.read().await.clone();
+            let mut channel_mask = current_selection.mask(stream_info.channels_count);
+            let mut active_info = Self::active_stream_info(&stream_info, &current_selection);
+            
+            let mut current_montage_config = montage_config.read().await.clone();
+            let mut current_montage = Self::build_montage(&current_montage_config, &active_info);
+            
+            // ASR清洗 - 只作用于显示路径，位于通道选择之后、导联之前；校准需显式请求
+            let mut current_asr = *asr_config.read().await;
+            let mut asr_stage = current_asr.enabled.then(|| AsrStage::new(
+                current_asr, stream_info.sample_rate, active_info.channels_count as usize
+            ));
+            let mut asr_requests_seen = asr_calibration_requests.load(Ordering::Relaxed);
+            
+            // 在线ICA - 紧接ASR之后，成分按参与处理的通道估计
+            let build_ica = |config: IcaConfig, info: &StreamInfo, rejected: &[u32]| {
+                config.enabled.then(|| {
+                    let mut ica = OnlineIca::new(config, stream_info.sample_rate, info.channel_labels.clone());
+                    ica.set_rejected(rejected);
+                    ica
+                })
+            };
+            let mut current_ica = *ica_config.read().await;
+            let mut current_rejected = ica_rejected.read().await.clone();
+            let mut ica = build_ica(current_ica, &active_info, &current_rejected);
+            
+            // 标记锁定分段 - 基于导联后的显示路径数据
+            let build_epochs = |config: &EpochConfig, labels: Vec<String>| {
+                config.enabled.then(|| {
+                    let extractor = EpochExtractor::new(config.clone(), stream_info.sample_rate, labels);
+                    info!("🟣 Epoching on {:?}", extractor.channel_labels());
+                    extractor
+                })
+            };
+            let mut current_epochs = epoch_config.read().await.clone();
+            let mut epoch_extractor = build_epochs(
+                &current_epochs, current_montage.output_labels(&active_info.channel_labels)
+            );
+            
+            // ERP平均 - 累计分段器接受的分段
+            let mut current_erp = *erp_config.read().await;
+            let mut erp_averager = current_erp.enabled.then(|| ErpAverager::new(current_erp));
+            
+            // 时域特征（Hjorth参数等）- 同样基于导联后的显示路径
+            let build_time_features = |config: TimeFeaturesConfig, labels: Vec<String>| {
+                config.enabled.then(|| TimeFeatureExtractor::new(config, stream_info.sample_rate, labels))
+            };
+            let mut current_time_features = *time_features.read().await;
+            let mut feature_extractor = build_time_features(
+                current_time_features, current_montage.output_labels(&active_info.channel_labels)
+            );
+            
+            // SSVEP检测（CCA）- 基于导联后的显示路径
+            let build_ssvep = |config: &SsvepConfig, labels: Vec<String>| {
+                config.enabled.then(|| SsvepDetector::new(config.clone(), stream_info.sample_rate, labels))
+            };
+            let mut current_ssvep = ssvep.read().await.clone();
+            let mut ssvep_detector = build_ssvep(
+                &current_ssvep, current_montage.output_labels(&active_info.channel_labels)
+            );
+            
+            // 空间滤波（CSP等）- 位于所有分析之后，只替换送往显示/FFT的通道
+            let build_spatial_filter = |filter: &Option<SpatialFilter>, labels: Vec<String>| {
+                let filter = filter.as_ref()?;
+                if filter.input_labels != labels {
+                    warn!("⚠️ Spatial filter expects channels {:?}, got {:?}; disabled", filter.input_labels, labels);
+                    return None;
+                }
+                Some(filter.clone())
+            };
+            let mut current_spatial_filter = spatial_filter.read().await.clone();
+            let mut spatial_stage = build_spatial_filter(
+                &current_spatial_filter, current_montage.output_labels(&active_info.channel_labels)
+            );
+            
+            // 可组合处理阶段 - 位于空间滤波之后，按此时的显示路径通道构建
+            let display_labels = |spatial_stage: &Option<SpatialFilter>, montage: &Montage, info: &StreamInfo| {
+                spatial_stage.as_ref()
+                    .map(|filter| filter.output_labels.clone())
+                    .unwrap_or_else(|| montage.output_labels(&info.channel_labels))
+            };
+            let mut current_stages = processing_stages.read().await.clone();
+            let mut stage_labels = display_labels(&spatial_stage, &current_montage, &active_info);
+            let mut stage_pipeline = Self::build_stage_pipeline(&current_stages, &stream_info, &stage_labels);
+            
+            // 数据块脚本（on_batch）- 位于处理阶段链之后
+            let mut current_scripting = scripting.read().await.clone();
+            let mut batch_scripts = scripting::build_hooks(&current_scripting, ScriptInput::Batch);
+            
+            // 等待对齐的标记（标记可能先于对应的EEG样本到达）
+            let mut pending_markers = std::collections::VecDeque::new();
+            
+            loop {
+                // 非阻塞检查停止状态
+                {
+                    let running = is_running.try_read();
+                    if let Ok(running) = running {
+                        if !*running {
+                            info!("🟣 Data distributor stopping");
+                            break;
+                        }
+                    }
+                }
+                
+                // ✅ 阻塞接收确保不丢失任何样本块（超时仅用于心跳）
+                match data_rx.recv_timeout(HEARTBEAT_INTERVAL) {
+                    Ok(chunk) => {
+                        let Some(first_sample) = chunk.first() else { continue };
+                        let distribute_started = Instant::now();
+                        
+                        // Strict模式：队列无限增长即终止采集（队列按块计数，折算为样本数）
+                        if strict {
+                            let queue_depth = recording_tx.queue_len().max(time_domain_tx.queue_len()) * chunk.len();
+                            if queue_depth > max_queue_depth {
+                                Self::abort_acquisition(&app_handle, &recorder, &recording_log, &anomalies, &is_running, AcquisitionAnomaly {
+                                    kind: AnomalyKind::QueueOverflow,
+                                    message: format!("Pipeline queue depth {} exceeded limit {}", 
+                                                     queue_depth, max_queue_depth),
+                                    sample_id: Some(first_sample.sample_id),
+                                    lsl_timestamp: Some(first_sample.timestamp),
+                                    fatal: true,
+                                }).await;
+                                break;
+                            }
+                        }
+                        
+                        // 配置变更时重建滤波器（滤波器状态随之重置），每块检查一次
+                        if let Ok(latest) = notch_config.try_read() {
+                            if *latest != current_notch {
+                                current_notch = *latest;
+                                notch_bank = NotchFilterBank::new(&current_notch, stream_info.sample_rate, channels_count);
+                                info!("🟣 Notch filter updated: {:?}Hz", notch_bank.frequencies());
+                            }
+                        }
+                        
+                        if let Ok(latest) = filter_config.try_read() {
+                            if *latest != current_filters {
+                                current_filters = *latest;
+                                filter_chain = FilterChain::new(&current_filters, stream_info.sample_rate, channels_count);
+                                info!("🟣 Filter chain updated: hp={:?}, lp={:?}, order={}, recording={}", 
+                                         current_filters.hp, current_filters.lp, 
+                                         current_filters.order, current_filters.apply_to_recording);
+                            }
+                        }
+                        
+                        if let Ok(latest) = signal_quality.try_read() {
+                            if *latest != current_quality {
+                                current_quality = *latest;
+                                quality_monitor = build_quality_monitor(current_quality, current_notch.frequency_hz);
+                                info!("🟣 Signal quality config updated: {:?}", current_quality);
+                            }
+                        }
+                        if let Some(monitor) = quality_monitor.as_mut() {
+                            monitor.set_line_frequency(current_notch.frequency_hz);
+                        }
+                        
+                        if let Ok(latest) = artifact_config.try_read() {
+                            if *latest != current_artifacts {
+                                current_artifacts = latest.clone();
+                                artifact_detector = build_artifact_detector(&current_artifacts);
+                            }
+                        }
+                        
+                        let mut montage_changed = false;
+                        if let Ok(latest) = channel_selection.try_read() {
+                            if *latest != current_selection {
+                                current_selection = latest.clone();
+                                channel_mask = current_selection.mask(stream_info.channels_count);
+                                active_info = Self::active_stream_info(&stream_info, &current_selection);
+                                montage_changed = true;
+                                info!("🟣 Active channels updated: {:?}", active_info.channel_labels);
+                            }
+                        }
+                        
+                        if let Ok(latest) = montage_config.try_read() {
+                            if *latest != current_montage_config {
+                                current_montage_config = latest.clone();
+                                montage_changed = true;
+                            }
+                        }
+                        
+                        if let Ok(latest) = asr_config.try_read() {
+                            if *latest != current_asr {
+                                current_asr = *latest;
+                                asr_stage = match (current_asr.enabled, asr_stage.take()) {
+                                    (false, _) => None,
+                                    (true, Some(stage)) => Some(stage.reconfigure(current_asr, stream_info.sample_rate)),
+                                    (true, None) => Some(AsrStage::new(
+                                        current_asr, stream_info.sample_rate, active_info.channels_count as usize
+                                    )),
+                                };
+                                info!("🟣 ASR updated: {:?}", current_asr);
+                            }
+                        }
+                        
+                        // 通道数变化后原校准失效
+                        if let Some(stage) = asr_stage.as_ref() {
+                            if stage.channels() != active_info.channels_count as usize {
+                                if stage.is_calibrated() {
+                                    warn!("⚠️ Active channels changed, ASR calibration discarded");
+                                }
+                                asr_stage = Some(AsrStage::new(
+                                    current_asr, stream_info.sample_rate, active_info.channels_count as usize
+                                ));
+                            }
+                        }
+                        
+                        if let Ok(latest) = ica_config.try_read() {
+                            let channels_changed = ica.as_ref()
+                                .is_some_and(|ica| ica.channels() != active_info.channels_count as usize);
+                            if *latest != current_ica || channels_changed {
+                                current_ica = *latest;
+                                ica = build_ica(current_ica, &active_info, &current_rejected);
+                                info!("🟣 ICA updated: {:?}", current_ica);
+                            }
+                        }
+                        
+                        if let Ok(latest) = ica_rejected.try_read() {
+                            if *latest != current_rejected {
+                                current_rejected = latest.clone();
+                                if let Some(ica) = ica.as_mut() {
+                                    ica.set_rejected(&current_rejected);
+                                }
+                            }
+                        }
+                        
+                        let asr_requests = asr_calibration_requests.load(Ordering::Relaxed);
+                        if asr_requests != asr_requests_seen {
+                            asr_requests_seen = asr_requests;
+                            if let Some(stage) = asr_stage.as_mut() {
+                                stage.start_calibration();
+                                info!("🟣 ASR calibration started ({}s)", current_asr.calibration_seconds);
+                            }
+                        }
+                        
+                        if montage_changed {
+                            current_montage = Self::build_montage(&current_montage_config, &active_info);
+                            info!("🟣 Montage updated: {:?}", 
+                                     current_montage.output_labels(&active_info.channel_labels));
+                        }
+                        
+                        if let Ok(latest) = epoch_config.try_read() {
+                            if *latest != current_epochs || montage_changed {
+                                current_epochs = latest.clone();
+                                epoch_extractor = build_epochs(
+                                    &current_epochs, current_montage.output_labels(&active_info.channel_labels)
+                                );
+                            }
+                        }
+                        
+                        if let Ok(latest) = erp_config.try_read() {
+                            if *latest != current_erp {
+                                current_erp = *latest;
+                                erp_averager = current_erp.enabled.then(|| ErpAverager::new(current_erp));
+                                info!("🟣 ERP averaging updated: {:?}", current_erp);
+                            }
+                        }
+                        
+                        if let Ok(latest) = time_features.try_read() {
+                            if *latest != current_time_features || montage_changed {
+                                current_time_features = *latest;
+                                feature_extractor = build_time_features(
+                                    current_time_features, current_montage.output_labels(&active_info.channel_labels)
+                                );
+                                if let Some(extractor) = feature_extractor.as_ref() {
+                                    info!("🟣 Time features on {:?}", extractor.channel_labels());
+                                }
+                            }
+                        }
+                        
+                        if let Ok(latest) = ssvep.try_read() {
+                            if *latest != current_ssvep || montage_changed {
+                                current_ssvep = latest.clone();
+                                ssvep_detector = build_ssvep(
+                                    &current_ssvep, current_montage.output_labels(&active_info.channel_labels)
+                                );
+                                if let Some(detector) = ssvep_detector.as_ref() {
+                                    info!("🟣 SSVEP detection on {:?} for {:?} Hz", detector.channel_labels(), current_ssvep.target_frequencies);
+                                }
+                            }
+                        }
+                        
+                        let mut display_changed = montage_changed;
+                        if let Ok(latest) = spatial_filter.try_read() {
+                            if *latest != current_spatial_filter || montage_changed {
+                                current_spatial_filter = latest.clone();
+                                spatial_stage = build_spatial_filter(
+                                    &current_spatial_filter, current_montage.output_labels(&active_info.channel_labels)
+                                );
+                                if let Some(filter) = spatial_stage.as_ref() {
+                                    info!("🟣 Spatial filter: {:?}", filter.output_labels);
+                                }
+                                display_changed = true;
+                            }
+                        }
+                        
+                        if let Ok(latest) = processing_stages.try_read() {
+                            if *latest != current_stages || display_changed {
+                                current_stages = latest.clone();
+                                stage_labels = display_labels(&spatial_stage, &current_montage, &active_info);
+                                stage_pipeline = Self::build_stage_pipeline(&current_stages, &stream_info, &stage_labels);
+                            }
+                        }
+                        
+                        if let Ok(latest) = scripting.try_read() {
+                            if *latest != current_scripting {
+                                current_scripting = latest.clone();
+                                batch_scripts = scripting::build_hooks(&current_scripting, ScriptInput::Batch);
+                            }
+                        }
+                        
+                        let queued_markers = pending_markers.len();
+                        pending_markers.extend(marker_rx.try_iter());
+                        
+                        // 原始采集：先写本次收到的标记再写样本块，回放时按相同顺序送入
+                        {
+                            let mut capture_guard = capture.lock().await;
+                            if let Some(writer) = capture_guard.as_mut() {
+                                let written = pending_markers.iter().skip(queued_markers)
+                                    .try_for_each(|marker| writer.write_marker(marker))
+                                    .and_then(|_| writer.write_chunk(&chunk));
+                                if let Err(e) = written {
+                                    error!("❌ Capture failed, stopping capture: {}", e);
+                                    app_handle.emit_error(ErrorSource::Capture, &e);
+                                    if let Some(writer) = capture_guard.take() {
+                                        let _ = writer.finish();
+                                    }
+                                }
+                            }
+                        }
+                        
+                        // 辅助流：按需写入录制旁路文件，再交给前端线程与EEG批次对齐
+                        let aux_samples: Vec<AuxSample> = aux_rx.try_iter().collect();
+                        if !aux_samples.is_empty() {
+                            if let Some(recorder) = recorder.lock().await.as_mut() {
+                                if let Err(e) = recorder.write_aux(&aux_samples) {
+                                    error!("❌ Failed to record aux samples: {}", e);
+                                    app_handle.emit_error(ErrorSource::Aux, &e);
+                                }
+                            }
+                            for sample in aux_samples {
+                                let _ = aux_frame_tx.try_send(sample);
+                            }
+                        }
+                        
+                        // 阻抗流：只取最新一组，更新会话元数据并通知前端
+                        if let Some(sample) = impedance_rx.try_iter().last() {
+                            let readings = impedance::readings_from_sample(&sample);
+                            Self::publish_impedances(&app_handle, &impedance, &session_metadata, ImpedanceSource::Stream,
+                                                     &readings, sample.timestamp).await;
+                        }
+                        
+                        let clock_offsets: Vec<ClockOffsetSample> = clock_rx.try_iter().collect();
+                        if !clock_offsets.is_empty() {
+                            if let Some(recorder) = recorder.lock().await.as_mut() {
+                                if let Err(e) = recorder.write_clock_offsets(&clock_offsets) {
+                                    error!("❌ Failed to record clock offsets: {}", e);
+                                    app_handle.emit_error(ErrorSource::ClockSync, &e);
+                                }
+                            }
+                        }
+                        
+                        PipelineMetrics::add(&metrics.samples_distributed, chunk.len() as u64);
+                        PipelineMetrics::set(&metrics.source_queue, data_rx.len());
+                        
+                        let filter_recording = filter_chain.is_active() && current_filters.apply_to_recording;
+                        history.lock().await.extend(&chunk);
+                        
+                        if let Ok(latest) = recording_triggers.try_read() {
+                            if *latest != current_triggers {
+                                current_triggers = latest.clone();
+                                trigger_engine = TriggerEngine::new(&current_triggers);
+                                info!("🟣 Recording triggers updated: {} rules", current_triggers.rules.len());
+                            }
+                        }
+                        
+                        if let Ok(latest) = vitals.try_read() {
+                            if *latest != current_vitals {
+                                current_vitals = *latest;
+                                vitals_monitor = current_vitals.enabled.then(|| VitalsMonitor::new(current_vitals, stream_info.sample_rate));
+                                info!("🟣 Vitals updated: {:?}", current_vitals);
+                            }
+                        }
+                        
+                        if let Ok(latest) = neurofeedback.try_read() {
+                            if *latest != current_feedback {
+                                current_feedback = latest.clone();
+                                feedback_engine = FeedbackEngine::new(&current_feedback);
+                                info!("🟣 Neurofeedback rules updated: {} rules", current_feedback.rules.len());
+                            }
+                        }
+                        
+                        if let Ok(latest) = osc.try_read() {
+                            if *latest != current_osc {
+                                current_osc = latest.clone();
+                                osc_sender = Self::build_osc_sender(&current_osc);
+                            }
+                        }
+                        
+                        // 频带功率来自FFT线程，按块评估
+                        let chunk_timestamp = first_sample.timestamp;
+                        for update in band_power_rx.try_iter() {
+                            for fired in trigger_engine.on_band_power(&update) {
+                                Self::execute_trigger(&app_handle, &recorder, &recording_log, &anomalies, &history, &session_metadata, &stream_info,
+                                                      &current_triggers, fired, chunk_timestamp).await;
+                            }
+                            for event in feedback_engine.on_band_power(&update, chunk_timestamp) {
+                                if let Some(sender) = &osc_sender {
+                                    sender.on_feedback(&event);
+                                }
+                                if let Err(e) = app_handle.emit("feedback-trigger", &event) {
+                                    error!("Failed to emit feedback-trigger: {}", e);
+                                }
+                            }
+                            if let Some(sender) = &osc_sender {
+                                sender.on_band_power(&update);
+                            }
+                        }
+                        
+                        if let Ok(recorder_guard) = recorder.try_lock() {
+                            let active = recorder_guard.as_ref();
+                            record_raw = active.is_some_and(|r| r.needs(RecordingProcessing::Raw));
+                            record_filtered = active.is_some_and(|r| r.needs(RecordingProcessing::Filtered));
+                        }
+                        
+                        let mut recording_chunk = Vec::with_capacity(chunk.len());
+                        let mut display_samples = Vec::with_capacity(chunk.len());
+                        
+                        for mut sample in chunk {
+                            samples_distributed += 1;
+                            
+                            if let Some(gap) = gap_detector.check(&sample) {
+                                Self::report_gap(&app_handle, &recorder, &metrics, gap).await;
+                            }
+                            
+                            if strict {
+                                if let Some(anomaly) = Self::check_sample_integrity(
+                                    &sample, last_timestamp, stream_info.channels_count
+                                ) {
+                                    Self::report_anomaly(&app_handle, &recorder, &anomalies, anomaly).await;
+                                }
+                                last_timestamp = Some(sample.timestamp);
+                            }
+                            
+                            if let Some(update) = quality_monitor.as_mut().and_then(|monitor| monitor.push(&sample)) {
+                                if let Err(e) = app_handle.emit("channel-quality-update", &update) {
+                                    error!("Failed to emit channel-quality-update: {}", e);
+                                }
+                            }
+                            
+                            let markers = if pending_markers.is_empty() {
+                                Vec::new()
+                            } else {
+                                Self::fuse_markers(&mut pending_markers, &sample, &app_handle, &recorder).await
+                            };
+                            for marker in &markers {
+                                for fired in trigger_engine.on_marker(marker) {
+                                    Self::execute_trigger(&app_handle, &recorder, &recording_log, &anomalies, &history, &session_metadata, &stream_info,
+                                                          &current_triggers, fired, marker.timestamp).await;
+                                }
+                            }
+                            
+                            let mut raw = record_raw.then(|| sample.channels.clone());
+                            
+                            if notch_bank.is_active() {
+                                notch_bank.process_sample(&mut sample.channels);
+                            }
+                            
+                            if !trigger_engine.is_empty() {
+                                for fired in trigger_engine.on_sample(&sample) {
+                                    Self::execute_trigger(&app_handle, &recorder, &recording_log, &anomalies, &history, &session_metadata, &stream_info,
+                                                          &current_triggers, fired, sample.timestamp).await;
+                                }
+                            }
+                            
+                            if let Some(detector) = artifact_detector.as_mut() {
+                                for artifact in detector.push(&sample) {
+                                    Self::report_artifact(&app_handle, &recorder, current_artifacts.annotate, artifact).await;
+                                }
+                            }
+                            
+                            if let Some(update) = vitals_monitor.as_mut().and_then(|monitor| monitor.push(&sample)) {
+                                if let Err(e) = app_handle.emit("vitals-update", &update) {
+                                    error!("Failed to emit vitals-update: {}", e);
+                                }
+                            }
+                            
+                            if filter_recording {
+                                filter_chain.process_sample(&mut sample.channels);
+                            }
+                            
+                            // ✅ 克隆样本并分发到所有消费者
+                            let mut sample_for_recording = sample.clone();
+                            let mut sample_for_time_domain = sample;
+                            
+                            // 录制保留原始数据时，仅对显示路径滤波
+                            if filter_chain.is_active() && !filter_recording {
+                                filter_chain.process_sample(&mut sample_for_time_domain.channels);
+                            }
+                            let mut filtered = record_filtered.then(|| sample_for_time_domain.channels.clone());
+                            
+                            if current_selection.active.is_some() {
+                                if !current_selection.record_excluded {
+                                    Self::zero_excluded(&mut sample_for_recording.channels, &channel_mask);
+                                    for channels in raw.iter_mut().chain(filtered.iter_mut()) {
+                                        Self::zero_excluded(channels, &channel_mask);
+                                    }
+                                }
+                                Self::retain_active(&mut sample_for_time_domain.channels, &channel_mask);
+                            }
+                            
+                            if let Some(stage) = asr_stage.as_mut() {
+                                match stage.process_sample(&mut sample_for_time_domain.channels) {
+                                    Some(Ok(calibration)) => {
+                                        info!("✅ ASR calibrated on {} samples", calibration.samples);
+                                        if let Err(e) = app_handle.emit("asr-calibrated", &calibration) {
+                                            error!("Failed to emit asr-calibrated: {}", e);
+                                        }
+                                    }
+                                    Some(Err(e)) => {
+                                        warn!("⚠️ ASR calibration failed: {}", e);
+                                        if let Err(e) = app_handle.emit("asr-calibration-failed", e.to_string()) {
+                                            error!("Failed to emit asr-calibration-failed: {}", e);
+                                        }
+                                    }
+                                    None => {}
+                                }
+                            }
+                            
+                            if let Some(update) = ica.as_mut().and_then(|ica| ica.push(&mut sample_for_time_domain.channels)) {
+                                if let Err(e) = app_handle.emit("ica-update", &update) {
+                                    error!("Failed to emit ica-update: {}", e);
+                                }
+                            }
+                            
+                            if !current_montage.is_raw() {
+                                current_montage.apply(&mut sample_for_time_domain.channels);
+                            }
+                            
+                            if let Some(extractor) = epoch_extractor.as_mut() {
+                                for marker in &markers {
+                                    extractor.on_marker(marker);
+                                }
+                                for epoch in extractor.push(sample_for_time_domain.sample_id, &sample_for_time_domain.channels) {
+                                    if let Some(averager) = erp_averager.as_mut() {
+                                        averager.add(&epoch);
+                                    }
+                                    if let Some(prediction) = classifier.lock().await.on_epoch(&epoch) {
+                                        if let Err(e) = app_handle.emit("classifier-prediction", &prediction) {
+                                            error!("Failed to emit classifier-prediction: {}", e);
+                                        }
+                                    }
+                                    csp_epochs.lock().await.push(&epoch);
+                                    Self::report_epoch(&app_handle, extractor.counts(), current_epochs.emit_epochs, epoch);
+                                }
+                            }
+                            
+                            if let Some(update) = feature_extractor.as_mut()
+                                .and_then(|extractor| extractor.push(sample_for_time_domain.timestamp, &sample_for_time_domain.channels))
+                            {
+                                if let Err(e) = app_handle.emit("time-features", &update) {
+                                    error!("Failed to emit time-features: {}", e);
+                                }
+                            }
+                            
+                            if let Some(detection) = ssvep_detector.as_mut()
+                                .and_then(|detector| detector.push(sample_for_time_domain.timestamp, &sample_for_time_domain.channels))
+                            {
+                                if let Err(e) = app_handle.emit("ssvep-detection", &detection) {
+                                    error!("Failed to emit ssvep-detection: {}", e);
+                                }
+                            }
+                            
+                            if let Some(filter) = spatial_stage.as_ref() {
+                                csp::apply_spatial_filter(filter, &mut sample_for_time_domain.channels);
+                            }
+                            
+                            recording_chunk.push(RecordingSample { sample: sample_for_recording, raw, filtered });
+                            display_samples.push(sample_for_time_domain);
                         }
-                    }
-                }
-                
-                // ✅ 阻塞接收确保不丢失任何样本
-                match data_rx.recv() {
-                    Ok(sample) => {
-                        samples_distributed += 1;
                         
-                        // ✅ 克隆样本并分发到所有消费者
-                        let sample_for_recording = sample.clone();
-                        let sample_for_time_domain = sample;
+                        // 处理阶段链与数据块脚本按块作用于显示路径
+                        let mut batch = StageBatch { channel_labels: stage_labels.clone(), samples: display_samples };
+                        if let Some(pipeline) = stage_pipeline.as_mut() {
+                            for update in pipeline.process(&mut batch) {
+                                if let Err(e) = app_handle.emit("stage-metrics", &update) {
+                                    error!("Failed to emit stage-metrics: {}", e);
+                                }
+                                // 插件阶段另发到其命名空间事件
+                                if let Some(event) = plugins::event_name(&update.stage) {
+                                    if let Err(e) = app_handle.emit(&event, &update) {
+                                        error!("Failed to emit {}: {}", event, e);
+                                    }
+                                }
+                            }
+                        }
+                        
+                        let mut script_updates = Vec::new();
+                        if let Some(hooks) = batch_scripts.as_mut() {
+                            for update in hooks.on_batch(&batch, stream_info.sample_rate) {
+                                if let Err(e) = app_handle.emit("script-metrics", &update) {
+                                    error!("Failed to emit script-metrics: {}", e);
+                                }
+                                script_updates.push(update);
+                            }
+                        }
+                        // 频谱脚本的指标由前端线程发送后回送至此
+                        script_updates.extend(script_metrics_rx.try_iter());
+                        script_updates.retain(|update| current_scripting.is_recorded(&update.script));
+                        if !script_updates.is_empty() {
+                            if let Some(recorder) = recorder.lock().await.as_mut() {
+                                for update in &script_updates {
+                                    if let Err(e) = recorder.write_script_metrics(update) {
+                                        error!("❌ Failed to record script metrics: {}", e);
+                                        app_handle.emit_error(ErrorSource::Scripting, &e);
+                                    }
+                                }
+                            }
+                        }
+                        
+                        // 可视化/FFT路径在此转换为f32
+                        let time_domain_chunk: Vec<DisplaySample> = batch.samples.into_iter()
+                            .map(DisplaySample::from)
+                            .collect();
+                        
+                        if let Some(averager) = erp_averager.as_mut() {
+                            for update in averager.poll(chunk_timestamp) {
+                                if let Err(e) = app_handle.emit("erp-update", &update) {
+                                    error!("Failed to emit erp-update: {}", e);
+                                }
+                            }
+                        }
                         
                         // 分发到录制线程（高优先级）
-                        if let Err(_) = recording_tx.send(sample_for_recording) {
+                        let chunk_len = recording_chunk.len() as u64;
+                        if let Err(_) = recording_tx.send(recording_chunk) {
+                            PipelineMetrics::add(&metrics.dropped_samples, chunk_len);
                             recording_failures += 1;
                             if recording_failures <= 5 {
-                                println!("⚠️ Recording channel dropped (failure #{})", recording_failures);
+                                warn!("⚠️ Recording channel dropped (failure #{})", recording_failures);
                             }
                         }
                         
                         // 分发到时域收集器
-                        if let Err(_) = time_domain_tx.send(sample_for_time_domain) {
+                        if let Err(_) = time_domain_tx.send(time_domain_chunk) {
+                            PipelineMetrics::add(&metrics.dropped_samples, chunk_len);
                             time_domain_failures += 1;
                             if time_domain_failures <= 5 {
-                                println!("⚠️ Time domain channel dropped (failure #{})", time_domain_failures);
+                                warn!("⚠️ Time domain channel dropped (failure #{})", time_domain_failures);
                             }
                         }
                         
+                        PipelineMetrics::set(&metrics.recording_queue, recording_tx.queue_len());
+                        PipelineMetrics::set(&metrics.time_domain_queue, time_domain_tx.queue_len());
+                        metrics.distributor.record(distribute_started.elapsed(), chunk_len);
+                        
                         // ✅ 每秒统计分发状态
                         if last_stats_time.elapsed() >= Duration::from_secs(1) {
-                            println!("🟣 Distributor: {}Hz distributed, failures: rec={}, time={}", 
+                            debug!("🟣 Distributor: {}Hz distributed, failures: rec={}, time={}", 
                                      samples_distributed, recording_failures, time_domain_failures);
                             last_stats_time = std::time::Instant::now();
                         }
                         
                         // 如果两个通道都断开，退出分发器
                         if recording_failures > 0 && time_domain_failures > 0 {
-                            println!("🟣 All consumers disconnected, distributor stopping");
+                            info!("🟣 All consumers disconnected, distributor stopping");
                             break;
                         }
                     }
-                    Err(_) => {
-                        println!("🟣 Data distributor: source disconnected");
+                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                        metrics.distributor.beat();
+                    }
+                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                        info!("🟣 Data distributor: source disconnected");
                         break;
                     }
                 }
             }
             
-            println!("🟣 Data distributor stopped - total distributed: {}, failures: rec={}, time={}", 
+            info!("🟣 Data distributor stopped - total distributed: {}, failures: rec={}, time={}", 
                      samples_distributed, recording_failures, time_domain_failures);
         })
     }
@@ -230,7 +2463,7 @@ impl EegProcessor {
     /// 全crossbeam处理管道
     async fn start_crossbeam_pipeline(
         &mut self,
-        data_rx: crossbeam_channel::Receiver<EegSample>,
+        data_rx: crossbeam_channel::Receiver<EegChunk>,
     ) -> Result<(), AppError> {
         let stream_info = self.stream_info.clone();
         let app_handle = self.app_handle.clone();
@@ -238,36 +2471,119 @@ impl EegProcessor {
         let is_running = self.is_running.clone();
         
         // ✅ 初始化FFT处理器
+        let (fft_shutdown_tx, fft_shutdown_rx) = crossbeam_channel::bounded(0);
+        self.fft_shutdown = Some(fft_shutdown_tx);
         self.fft_processor = Some(FftProcessor::new(
             stream_info.clone(),
-            is_running.clone(),
+            fft_shutdown_rx,
+            self.fft_config.clone(),
+            self.spectrum_range.clone(),
+            self.frequency_bands.clone(),
+            self.spectrogram.clone(),
+            self.spectrum_display.clone(),
+            self.spectral_metrics.clone(),
+            self.connectivity.clone(),
+            self.scripting.clone(),
         ));
         
-        // ✅ 创建分发通道 - 避免数据竞争
-        let (recording_tx, recording_rx) = crossbeam_channel::unbounded::<EegSample>();
-        let (time_domain_data_tx, time_domain_data_rx) = crossbeam_channel::unbounded::<EegSample>();
+        // 未连接标记流时使用永不就绪的通道
+        let marker_rx = self.marker_rx.clone().unwrap_or_else(crossbeam_channel::never);
+        let aux_rx = self.aux_rx.clone().unwrap_or_else(crossbeam_channel::never);
+        let impedance_rx = self.impedance_rx.clone().unwrap_or_else(crossbeam_channel::never);
+        let clock_rx = self.clock_rx.clone().unwrap_or_else(crossbeam_channel::never);
+        
+        // ✅ 创建有界分发通道 - 录制路径阻塞不丢数据，可视化路径丢弃最旧项
+        let channels = self.channel_config;
+        let metrics = &self.metrics;
+        let (recording_tx, recording_rx) = pipeline_channel::bounded::<RecordingChunk>(
+            channels.recording, metrics.recording_channel.clone());
+        let (time_domain_data_tx, time_domain_data_rx) = pipeline_channel::bounded::<DisplayChunk>(
+            channels.time_domain, metrics.time_domain_channel.clone());
         
-        // 下游通道保持不变
-        let (freq_tx, freq_rx) = crossbeam_channel::unbounded();
-        let (time_domain_tx, time_domain_rx) = crossbeam_channel::unbounded();
-        let (fft_trigger_tx, fft_trigger_rx) = crossbeam_channel::unbounded();
+        // 下游通道
+        let (freq_tx, freq_rx) = pipeline_channel::bounded(channels.frontend, metrics.frontend_channel.clone());
+        let (time_domain_tx, time_domain_rx) = pipeline_channel::bounded(channels.frontend, metrics.frontend_channel.clone());
+        let (fft_trigger_tx, fft_trigger_rx) = pipeline_channel::bounded(channels.fft, metrics.fft_channel.clone());
+        let (analysis_tx, analysis_rx) = pipeline_channel::bounded(channels.frontend, metrics.frontend_channel.clone());
+        // 频带功率回送分发器，用于录制触发规则（满时丢弃）
+        let (band_power_tx, band_power_rx) = crossbeam_channel::bounded(16);
+        // 频谱脚本指标回送分发器，用于写入录制（满时丢弃）
+        let (script_metrics_tx, script_metrics_rx) = crossbeam_channel::bounded(16);
+        // 辅助流样本转交前端线程（满时丢弃）
+        let (aux_frame_tx, aux_frame_rx) = crossbeam_channel::bounded(AUX_BUFFER_CAPACITY);
         
         // ✅ 数据分发器 - 第一优先级线程
         let distributor_handle = self.spawn_data_distributor(
             data_rx,                    // 从LSL接收
+            marker_rx,                  // LSL标记流
+            aux_rx,                     // 辅助流（IMU等）
+            aux_frame_tx,
+            impedance_rx,               // 阻抗流
+            clock_rx,                   // LSL时钟偏移测量
             recording_tx,               // 分发给录制线程
             time_domain_data_tx,        // 分发给时域收集器
+            app_handle.clone(),
+            recorder.clone(),
+            stream_info.clone(),
+            self.notch_config.clone(),
+            self.filter_config.clone(),
+            self.montage_config.clone(),
+            self.channel_selection.clone(),
+            self.signal_quality.clone(),
+            self.artifact_config.clone(),
+            self.asr_config.clone(),
+            self.asr_calibration_requests.clone(),
+            self.ica_config.clone(),
+            self.ica_rejected.clone(),
+            self.history.clone(),
+            self.recording_triggers.clone(),
+            self.epoch_config.clone(),
+            self.erp_config.clone(),
+            self.time_features.clone(),
+            self.neurofeedback.clone(),
+            self.ssvep.clone(),
+            self.classifier.clone(),
+            self.spatial_filter.clone(),
+            self.csp_epochs.clone(),
+            self.vitals.clone(),
+            self.impedance.clone(),
+            self.osc.clone(),
+            self.session_metadata.clone(),
+            self.processing_stages.clone(),
+            self.scripting.clone(),
+            band_power_rx,
+            script_metrics_rx,
+            self.acquisition_mode,
+            self.metrics.clone(),
             is_running.clone()
         ).await;
         self.thread_handles.push(distributor_handle);
         
+        // 监督器接管录制与FFT任务，panic或意外退出后用相同的通道端点重启
+        let mut supervisor = Supervisor::new();
+        
         // ✅ 录制线程 - 使用专用通道，不再竞争
-        let recording_handle = self.spawn_recording_thread(
-            recording_rx,               // 专用录制通道
-            recorder,
-            is_running.clone()
-        ).await;
-        self.thread_handles.push(recording_handle);
+        let spawn_recording = {
+            let app_handle = app_handle.clone();
+            let acquisition_mode = self.acquisition_mode;
+            let recording_log = self.recording_log.clone();
+            let anomalies = self.anomalies.clone();
+            let recording_capacity = self.channel_config.recording.capacity;
+            let metrics = self.metrics.clone();
+            let is_running = is_running.clone();
+            move || Self::spawn_recording_thread(
+                recording_rx.clone(),   // 专用录制通道
+                recorder.clone(),
+                app_handle.clone(),
+                acquisition_mode,
+                recording_log.clone(),
+                anomalies.clone(),
+                recording_capacity,
+                metrics.clone(),
+                is_running.clone(),
+            ).into()
+        };
+        supervisor.supervise(PipelineStage::Recording, spawn_recording(), Some(Box::new(spawn_recording)));
         
         // ✅ 时域收集器 - 使用专用通道，不再竞争
         let time_domain_handle = self.spawn_time_domain_collector(
@@ -275,207 +2591,427 @@ impl EegProcessor {
             time_domain_tx,
             fft_trigger_tx,
             stream_info.clone(),
+            self.display_rate.clone(),
+            self.frame_rate.clone(),
+            self.metrics.clone(),
             is_running.clone()
         ).await;
         self.thread_handles.push(time_domain_handle);
         
-        // FFT线程和前端线程保持不变
+        // FFT线程 - 频域分析统一由 FftProcessor 完成
         if let Some(fft_processor) = &self.fft_processor {
-            let fft_handle = fft_processor.spawn_fft_thread(
-                fft_trigger_rx,
-                freq_tx,
-            ).await;
-            self.thread_handles.push(fft_handle);
+            let spawn_fft = {
+                let fft_processor = fft_processor.clone();
+                let analysis_tx = analysis_tx.clone();
+                let stage_stats = self.metrics.fft.clone();
+                move || fft_processor.spawn_fft_thread(
+                    fft_trigger_rx.clone(),
+                    freq_tx.clone(),
+                    analysis_tx.clone(),
+                    stage_stats.clone(),
+                ).into()
+            };
+            supervisor.supervise(PipelineStage::Fft, spawn_fft(), Some(Box::new(spawn_fft)));
         }
         
         let frontend_handle = self.spawn_frontend_thread(
             freq_rx,
             time_domain_rx,
+            analysis_rx,
+            aux_frame_rx,
+            band_power_tx,
+            script_metrics_tx,
             app_handle,
             stream_info.channels_count,
             stream_info.sample_rate,
+            self.spectrum_range.clone(),
+            self.spectrum_display.clone(),
+            self.frame_channel.clone(),
+            self.frame_subscription.clone(),
+            self.frame_rate.clone(),
+            self.streaming_paused.clone(),
+            self.metrics.clone(),
             is_running.clone()
         ).await;
         self.thread_handles.push(frontend_handle);
         
+        let supervisor_handle = supervisor.spawn(self.metrics.clone(), self.app_handle.clone(), is_running);
+        self.thread_handles.push(supervisor_handle);
+        
         Ok(())
     }
     
-    /// 录制线程 - 最高优先级，专用通道，确保数据完整性
-    async fn spawn_recording_thread(
-        &self,
-        recording_rx: crossbeam_channel::Receiver<EegSample>,  // ✅ 专用通道
-        recorder: Arc<Mutex<Option<EdfRecorder>>>,
+    /// 录制线程 - 最高优先级，专用通道，确保数据完整性（监督器重启时再次调用）
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_recording_thread(
+        recording_rx: crossbeam_channel::Receiver<RecordingChunk>,   // ✅ 专用通道
+        recorder: Arc<Mutex<Option<MultiRecorder>>>,
+        app_handle: StreamEmitter,
+        acquisition_mode: AcquisitionMode,
+        recording_log: RecordingLog,
+        anomalies: AnomalyLog,
+        recording_capacity: usize,
+        metrics: Arc<PipelineMetrics>,
         is_running: Arc<tokio::sync::RwLock<bool>>,
     ) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
-            println!("🔴 Recording thread started (DEDICATED CHANNEL)");
+            info!("🔴 Recording thread started (DEDICATED CHANNEL)");
             
+            let strict = acquisition_mode.is_strict();
             let mut samples_recorded = 0u64;
             let mut recording_errors = 0u64;
             let mut last_report = std::time::Instant::now();
+            let mut last_disk_check = std::time::Instant::now();
+            let mut last_disk_warning: Option<std::time::Instant> = None;
             
             loop {
-                // ✅ 阻塞接收，确保不丢失任何样本
-                match recording_rx.recv() {
-                    Ok(sample) => {
+                // ✅ 阻塞接收，确保不丢失任何样本块（超时仅用于心跳）
+                match recording_rx.recv_timeout(HEARTBEAT_INTERVAL) {
+                    Ok(chunk) => {
+                        metrics.recording.beat();
+                        
                         // 非阻塞检查停止状态
                         {
                             let running = is_running.try_read();
                             if let Ok(running) = running {
                                 if !*running {
-                                    // 即使停止，也要处理完当前样本
-                                    println!("🔴 Recording stopping after processing current sample");
+                                    // 停止后继续写入，直到分发器退出、录制队列取空
+                                    debug!("🔴 Recording draining queue before stopping");
                                 }
                             }
                         }
                         
-                        // 录制样本
-                        let mut recorder_guard = recorder.lock().await;
-                        if let Some(recorder) = recorder_guard.as_mut() {
-                            match recorder.write_sample(&sample) {
-                                Ok(_) => {
-                                    samples_recorded += 1;
-                                    
-                                    // 每秒报告录制状态
-                                    if last_report.elapsed() >= Duration::from_secs(1) {
-                                        println!("🔴 Recording: {}Hz (errors: {})", 
-                                                 samples_recorded, recording_errors);
-                                        last_report = std::time::Instant::now();
+                        // 录制样本块 - 每块只加锁一次
+                        let mut fatal_error = None;
+                        let mut progress = None;
+                        {
+                            let mut recorder_guard = recorder.lock().await;
+                            if let Some(recorder) = recorder_guard.as_mut() {
+                                let write_started = Instant::now();
+                                for sample in &chunk {
+                                    match recorder.write_sample(sample) {
+                                        Ok(_) => {
+                                            samples_recorded += 1;
+                                            PipelineMetrics::add(&metrics.samples_recorded, 1);
+                                        }
+                                        Err(e) => {
+                                            recording_errors += 1;
+                                            PipelineMetrics::add(&metrics.recording_errors, 1);
+                                            if recording_errors <= 10 {
+                                                error!("❌ Recording error #{}: {}", recording_errors, e);
+                                                app_handle.emit_error(ErrorSource::Recording, &e);
+                                            }
+                                            if strict {
+                                                fatal_error = Some((e.to_string(), sample.sample.sample_id, sample.sample.timestamp));
+                                                break;
+                                            }
+                                        }
                                     }
                                 }
-                                Err(e) => {
-                                    recording_errors += 1;
-                                    if recording_errors <= 10 {
-                                        println!("❌ Recording error #{}: {}", recording_errors, e);
-                                    }
+                                metrics.recording.record(write_started.elapsed(), chunk.len() as u64);
+                                
+                                // 每秒报告录制状态
+                                if last_report.elapsed() >= RECORDING_PROGRESS_INTERVAL {
+                                    debug!("🔴 Recording: {}Hz (errors: {})", 
+                                             samples_recorded, recording_errors);
+                                    let mut status = recorder.status();
+                                    status.buffer_fill = Self::recording_queue_fill(&metrics, recording_capacity);
+                                    progress = Some(status);
+                                    last_report = std::time::Instant::now();
                                 }
                             }
                         }
                         
-                        // 检查停止状态（在处理完样本后）
-                        {
-                            let running = is_running.try_read();
-                            if let Ok(running) = running {
-                                if !*running {
-                                    break;
-                                }
+                        if let Some(status) = progress {
+                            if let Err(e) = app_handle.emit("recording-progress", &status) {
+                                error!("❌ Failed to emit recording-progress: {}", e);
                             }
                         }
+                        
+                        // 定期检查磁盘空间
+                        if last_disk_check.elapsed() >= DISK_CHECK_INTERVAL {
+                            last_disk_check = std::time::Instant::now();
+                            Self::check_disk_space(&app_handle, &recorder, &recording_log, &mut last_disk_warning).await;
+                        }
+                        
+                        // Strict模式：写入错误立即终止会话
+                        if let Some((message, sample_id, timestamp)) = fatal_error {
+                            Self::abort_acquisition(&app_handle, &recorder, &recording_log, &anomalies, &is_running, AcquisitionAnomaly {
+                                kind: AnomalyKind::RecordingWriteError,
+                                message,
+                                sample_id: Some(sample_id),
+                                lsl_timestamp: Some(timestamp),
+                                fatal: true,
+                            }).await;
+                            break;
+                        }
+                    }
+                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                        metrics.recording.beat();
                     }
-                    Err(_) => {
-                        println!("🔴 Recording: data distributor disconnected");
+                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                        info!("🔴 Recording: data distributor disconnected");
                         break;
                     }
                 }
             }
             
-            println!("🔴 Recording thread stopped - recorded: {}, errors: {}", 
+            info!("🔴 Recording thread stopped - recorded: {}, errors: {}", 
                      samples_recorded, recording_errors);
         })
     }
     
     
+    /// 磁盘空间监控：低于警告阈值时提示（每分钟最多一次），低于停止阈值时正常结束录制
+    async fn check_disk_space(
+        app_handle: &StreamEmitter,
+        recorder: &Arc<Mutex<Option<MultiRecorder>>>,
+        recording_log: &RecordingLog,
+        last_warning: &mut Option<std::time::Instant>,
+    ) {
+        let mut recorder_guard = recorder.lock().await;
+        let Some(active) = recorder_guard.as_ref() else {
+            return;
+        };
+        
+        let available = match active.available_megabytes() {
+            Ok(available) => available,
+            Err(e) => {
+                warn!("⚠️  Disk space check failed: {}", e);
+                return;
+            }
+        };
+        
+        let disk = active.disk_config();
+        if available >= disk.warning_megabytes {
+            return;
+        }
+        
+        let stop = available < disk.stop_megabytes;
+        if !stop && last_warning.is_some_and(|at| at.elapsed() < LOW_DISK_WARNING_INTERVAL) {
+            return;
+        }
+        *last_warning = Some(std::time::Instant::now());
+        
+        let warning = LowDiskWarning {
+            filename: active.current_filename().to_string(),
+            available_megabytes: available,
+            warning_megabytes: disk.warning_megabytes,
+            stop_megabytes: disk.stop_megabytes,
+            recording_stopped: stop,
+        };
+        
+        if stop {
+            // 在磁盘写满前结束录制，确保文件头完整
+            if let Some(recorder) = recorder_guard.take() {
+                match recording_log.finish(recorder).await {
+                    Ok(stats) => info!("💾 Low disk space ({:.0}MB): recording finalized: {:?}", available, stats),
+                    Err(e) => {
+                        error!("❌ Low disk space: failed to finalize recording: {}", e);
+                        app_handle.emit_error(ErrorSource::DiskMonitor, &e);
+                    }
+                }
+            }
+        } else {
+            warn!("⚠️  Low disk space: {:.0}MB available for {}", available, warning.filename);
+        }
+        drop(recorder_guard);
+        
+        if let Err(e) = app_handle.emit("low-disk-warning", &warning) {
+            error!("❌ Failed to emit low-disk-warning: {}", e);
+        }
+    }
+    
+    /// 批次通道数 - 双极导联会改变显示通道数
+    fn batch_channels_count(batch: &[DisplaySample], stream_info: &StreamInfo) -> u32 {
+        batch
+            .first()
+            .map(|sample| sample.channels.len() as u32)
+            .unwrap_or(stream_info.channels_count)
+    }
+    
+    /// 显示路径抽取：通道数变化（导联/通道选择）时重建抗混叠滤波器
+    ///
+    /// 输出的 sample_id 为源 sample_id 除以抽取因子，使连续的抽取样本编号连续，
+    /// 前端的间隙检测只在真正丢样时标记。
+    fn decimate_sample(
+        decimator: &mut Option<Decimator>,
+        config: DisplayRateConfig,
+        sample_rate: f64,
+        sample: &DisplaySample,
+    ) -> Option<DisplaySample> {
+        let factor = config.decimation_factor as usize;
+        if factor <= 1 {
+            return Some(sample.clone());
+        }
+        
+        let channels_count = sample.channels.len();
+        let decimator = match decimator {
+            Some(existing) if existing.factor() == factor && existing.channels_count() == channels_count => existing,
+            slot => slot.insert(Decimator::new(factor, sample_rate, channels_count)),
+        };
+        
+        let mut values: Vec<f64> = sample.channels.iter().map(|&v| v as f64).collect();
+        decimator.process_sample(&mut values).then(|| DisplaySample {
+            timestamp: sample.timestamp,
+            channels: values.into_iter().map(|v| v as f32).collect(),
+            sample_id: sample.sample_id / factor as u64,
+        })
+    }
+    
     /// 重构：时域收集器 + FFT触发器
+    #[allow(clippy::too_many_arguments)]
     async fn spawn_time_domain_collector(
         &self,
-        data_rx: crossbeam_channel::Receiver<EegSample>,
-        time_domain_tx: crossbeam_channel::Sender<EegBatch>,
-        fft_trigger_tx: crossbeam_channel::Sender<(u64, Vec<EegSample>)>, // ✅ 传递(batch_id, samples)
+        data_rx: crossbeam_channel::Receiver<DisplayChunk>,
+        time_domain_tx: PipelineSender<EegBatch>,
+        fft_trigger_tx: PipelineSender<(u64, Vec<DisplaySample>)>, // ✅ 传递(batch_id, samples)
         stream_info: StreamInfo,
+        display_rate: Arc<tokio::sync::RwLock<DisplayRateConfig>>,
+        frame_rate: Arc<tokio::sync::RwLock<FrameRateConfig>>,
+        metrics: Arc<PipelineMetrics>,
         is_running: Arc<tokio::sync::RwLock<bool>>,
     ) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
-            println!("🟢 Time domain collector started (with FFT sync)");
+            info!("🟢 Time domain collector started (with FFT sync)");
             
-            let send_interval = Duration::from_millis(FRAME_INTERVAL_MS); // 33ms
+            let mut current_frame_rate = *frame_rate.read().await;
             let mut current_batch = Vec::new();
+            // FFT使用全速率数据，前端批次按抽取倍数降采样
+            let mut display_batch = Vec::new();
+            let mut current_display = *display_rate.read().await;
+            let mut decimator: Option<Decimator> = None;
             let mut batch_id = 0u64;
-            let mut batch_timer = tokio::time::interval(send_interval);
+            let mut batch_timer = tokio::time::interval(current_frame_rate.interval());
+            // 当前批次首个样本的到达时刻（收集器阶段延迟）
+            let mut batch_started: Option<Instant> = None;
             
             batch_timer.tick().await;
             
             loop {
                 tokio::select! {
                     _ = batch_timer.tick() => {
+                        metrics.collector.beat();
                         {
                             let running = is_running.read().await;
                             if !*running {
                                 if !current_batch.is_empty() {
                                     let final_batch = EegBatch {
-                                        samples: current_batch.clone(),
+                                        channels_count: Self::batch_channels_count(&current_batch, &stream_info),
+                                        samples: std::mem::take(&mut display_batch),
                                         batch_id,
-                                        channels_count: stream_info.channels_count,
-                                        sample_rate: stream_info.sample_rate,
+                                        sample_rate: current_display.display_rate(stream_info.sample_rate),
                                     };
                                     let _ = time_domain_tx.send(final_batch);
                                     
                                     // ✅ 最后一次FFT触发
                                     let _ = fft_trigger_tx.send((batch_id, current_batch));
                                 }
-                                println!("🟢 Time domain collector stopping");
+                                info!("🟢 Time domain collector stopping");
                                 break;
                             }
                         }
                         
                         // ✅ 发送时域批次
+                        if let Some(started) = batch_started.take() {
+                            metrics.collector.record(started.elapsed(), display_batch.len() as u64);
+                        }
                         let batch = EegBatch {
-                            samples: current_batch.clone(),
+                            channels_count: Self::batch_channels_count(&current_batch, &stream_info),
+                            samples: std::mem::take(&mut display_batch),
                             batch_id,
-                            channels_count: stream_info.channels_count,
-                            sample_rate: stream_info.sample_rate,
+                            sample_rate: current_display.display_rate(stream_info.sample_rate),
                         };
                         
                         if time_domain_tx.send(batch).is_err() {
-                            println!("🟢 Time domain: receiver dropped");
+                            info!("🟢 Time domain: receiver dropped");
                             break;
                         }
                         
-                        // ✅ 同步触发FFT计算（传递批次ID）
+                        if batch_id % 30 == 0 && batch_id > 0 {
+                            debug!("🟢 Batch #{}: {} samples → FFT trigger", 
+                                     batch_id, current_batch.len());
+                        }
+                        
+                        // ✅ 同步触发FFT计算（传递批次ID）- 批次移交FFT线程，不复制样本
                         if !current_batch.is_empty() {
-                            if let Err(_) = fft_trigger_tx.send((batch_id, current_batch.clone())) {
-                                println!("🟢 Time domain: FFT trigger dropped");
+                            let capacity = current_batch.len();
+                            let fft_batch = std::mem::replace(&mut current_batch, Vec::with_capacity(capacity));
+                            if let Err(_) = fft_trigger_tx.send((batch_id, fft_batch)) {
+                                info!("🟢 Time domain: FFT trigger dropped");
                             }
                         }
                         
-                        if batch_id % 30 == 0 && batch_id > 0 {
-                            println!("🟢 Batch #{}: {} samples → FFT trigger", 
-                                     batch_id, current_batch.len());
+                        batch_id += 1;
+                        
+                        if let Ok(latest) = display_rate.try_read() {
+                            if *latest != current_display {
+                                current_display = *latest;
+                                decimator = None;
+                                info!("🟢 Display rate: {:.1}Hz (decimation x{})", 
+                                         current_display.display_rate(stream_info.sample_rate),
+                                         current_display.decimation_factor);
+                            }
                         }
                         
-                        current_batch.clear();
-                        batch_id += 1;
+                        if let Ok(latest) = frame_rate.try_read() {
+                            if *latest != current_frame_rate {
+                                current_frame_rate = *latest;
+                                batch_timer = Self::frame_timer(current_frame_rate);
+                                info!("🟢 Batch interval: {:?} ({} FPS)", 
+                                         current_frame_rate.interval(), current_frame_rate.effective_fps());
+                            }
+                        }
                     }
                     
                     _ = tokio::time::sleep(Duration::from_micros(100)) => {
-                        while let Ok(sample) = data_rx.try_recv() {
-                            current_batch.push(sample);
+                        while let Ok(chunk) = data_rx.try_recv() {
+                            batch_started.get_or_insert_with(Instant::now);
+                            for sample in &chunk {
+                                if let Some(sample) = Self::decimate_sample(
+                                    &mut decimator, current_display, stream_info.sample_rate, sample
+                                ) {
+                                    display_batch.push(sample);
+                                }
+                            }
+                            current_batch.extend(chunk);
                         }
                     }
                 }
             }
             
-            println!("🟢 Time domain collector stopped");
+            info!("🟢 Time domain collector stopped");
         })
     }
     
-
+    
     /// 前端发送线程 - 使用FFT工具函数
+    #[allow(clippy::too_many_arguments)]
     async fn spawn_frontend_thread(
         &self,
         freq_rx: crossbeam_channel::Receiver<(u64, Vec<FreqData>)>,
         time_domain_rx: crossbeam_channel::Receiver<EegBatch>,
-        app_handle: AppHandle,
+        analysis_rx: crossbeam_channel::Receiver<AnalysisEvent>,
+        aux_rx: crossbeam_channel::Receiver<AuxSample>,
+        band_power_tx: crossbeam_channel::Sender<BandPowerUpdate>,
+        script_metrics_tx: crossbeam_channel::Sender<ScriptMetricsUpdate>,
+        app_handle: StreamEmitter,
         channels_count: u32,
         sample_rate: f64,
+        spectrum_range: Arc<tokio::sync::RwLock<SpectrumRange>>,
+        spectrum_display: Arc<tokio::sync::RwLock<SpectrumDisplayOptions>>,
+        frame_channel: Arc<tokio::sync::RwLock<Option<Channel>>>,
+        frame_subscription: Arc<tokio::sync::RwLock<FrameSubscription>>,
+        frame_rate: Arc<tokio::sync::RwLock<FrameRateConfig>>,
+        streaming_paused: Arc<tokio::sync::RwLock<bool>>,
+        metrics: Arc<PipelineMetrics>,
         is_running: Arc<tokio::sync::RwLock<bool>>,
     ) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
-            println!("🔥 Frontend thread started (with binary optimization)");
+            info!("🔥 Frontend thread started (with binary optimization)");
             
-            let mut frame_timer = tokio::time::interval(
-                Duration::from_millis(FRAME_INTERVAL_MS)
-            );
+            let mut current_frame_rate = *frame_rate.read().await;
+            let mut frame_timer = tokio::time::interval(current_frame_rate.interval());
             
             // ✅ 添加优化组件
             let mut data_converter = DataConverter::new(channels_count as usize);
@@ -489,90 +3025,183 @@ impl EegProcessor {
             let mut next_expected_batch_id = 0u64;
             let mut binary_frames_sent = 0u64;
             
-            // ✅ 使用FFT模块的工具函数
-            let create_empty_freq_data = move || fft_utils::create_empty_freq_data(channels_count);
+            // 跳步FFT并非每个批次都有新频谱，沿用最近一次结果
+            let mut last_freq_data: Option<Vec<FreqData>> = None;
+            
+            // 导联后的显示通道数，随时域批次更新
+            let mut display_channels = channels_count;
+            
+            // 等待对齐的辅助流样本
+            let mut aux_buffer: std::collections::VecDeque<AuxSample> = std::collections::VecDeque::new();
+            
+            // 订阅限速时尚未发送的批次（合并）及其最新频谱
+            let mut pending_time: Option<EegBatch> = None;
+            let mut pending_freq: Option<Vec<FreqData>> = None;
+            let mut last_sent: Option<Instant> = None;
+            
+            // 发送耗时过长时拉长发送间隔，积压批次合并为一帧
+            let mut pacer = FramePacer::new();
+            let mut last_metrics_report = Instant::now();
             
             loop {
                 tokio::select! {
                     // 定时发送frame-update事件
                     _ = frame_timer.tick() => {
+                        metrics.emit.beat();
                         // 检查停止状态
                         {
                             let running = is_running.read().await;
                             if !*running {
-                                println!("🔥 Frontend thread stopping");
+                                info!("🔥 Frontend thread stopping");
                                 break;
                             }
                         }
                         
+                        // ✅ 使用FFT模块的工具函数（空频谱与当前输出范围一致）
+                        let empty_range = spectrum_range.read().await.clamped_to(sample_rate);
+                        let display_options = *spectrum_display.read().await;
+                        let subscription = frame_subscription.read().await.clone();
+                        
+                        let latest_frame_rate = *frame_rate.read().await;
+                        if latest_frame_rate != current_frame_rate {
+                            current_frame_rate = latest_frame_rate;
+                            frame_timer = Self::frame_timer(current_frame_rate);
+                            info!("🔥 Frame rate: {} FPS{}", current_frame_rate.effective_fps(),
+                                     if current_frame_rate.low_power { " (low power)" } else { "" });
+                        }
+                        
                         // 收集数据到缓冲区（保持现有逻辑）
                         while let Ok((batch_id, freq_data)) = freq_rx.try_recv() {
                             freq_buffer.insert(batch_id, freq_data);
                         }
                         
                         while let Ok(time_domain) = time_domain_rx.try_recv() {
+                            if !time_domain.samples.is_empty() {
+                                display_channels = time_domain.channels_count;
+                            }
                             time_buffer.insert(time_domain.batch_id, time_domain);
                         }
                         
-                        // ✅ 处理匹配的数据对
-                        let mut sent_data = false;
+                        aux_buffer.extend(aux_rx.try_iter());
+                        if aux_buffer.len() > AUX_BUFFER_CAPACITY {
+                            aux_buffer.drain(..aux_buffer.len() - AUX_BUFFER_CAPACITY);
+                        }
                         
-                        if let (Some(time_domain), freq_data) = (
-                            time_buffer.remove(&next_expected_batch_id),
-                            freq_buffer.remove(&next_expected_batch_id)
-                        ) {
-                            let freq_data = freq_data.unwrap_or_else(|| create_empty_freq_data());
-                            
-                            // ✅ 发送二进制优化版本
-                            Self::send_optimized_frame(
-                                &mut data_converter,
-                                &mut binary_builder,
-                                &time_domain,
-                                &freq_data,
-                                &app_handle,
-                            ).await;
-                            
-                            frame_count += 1;
-                            binary_frames_sent += 1;
-                            sent_data = true;
-                            
-                            if frame_count <= 5 {
-                                println!("🔥 Binary Frame #{} sent - matched batch #{}", 
-                                         frame_count, next_expected_batch_id);
+                        // 发送分析阶段事件
+                        while let Ok(event) = analysis_rx.try_recv() {
+                            Self::emit_analysis_event(&app_handle, &event);
+                            match event {
+                                AnalysisEvent::BandPower(update) => {
+                                    let _ = band_power_tx.try_send(update);
+                                }
+                                AnalysisEvent::ScriptMetrics(update) => {
+                                    let _ = script_metrics_tx.try_send(update);
+                                }
+                                _ => {}
+                            }
+                        }
+                        
+                        if last_metrics_report.elapsed() >= PIPELINE_METRICS_INTERVAL {
+                            last_metrics_report = Instant::now();
+                            if let Err(e) = app_handle.emit("pipeline-metrics", metrics.stage_report()) {
+                                error!("Failed to emit pipeline-metrics: {}", e);
+                            }
+                        }
+                        
+                        // 暂停期间丢弃显示数据，不发送帧；恢复后从最新批次继续
+                        if *streaming_paused.read().await {
+                            if let Some(&latest) = time_buffer.keys().max() {
+                                next_expected_batch_id = next_expected_batch_id.max(latest + 1);
+                            }
+                            time_buffer.clear();
+                            freq_buffer.clear();
+                            aux_buffer.clear();
+                            pending_time = None;
+                            pending_freq = None;
+                            PipelineMetrics::set(&metrics.frontend_queue, time_domain_rx.len());
+                            continue;
+                        }
+                        
+                        let create_empty_freq_data = || fft_utils::create_empty_freq_data(
+                            display_channels, &empty_range, &display_options
+                        );
+                        
+                        // 期望批次已被有界通道丢弃时，跳到最早的已缓冲批次
+                        if !time_buffer.contains_key(&next_expected_batch_id) {
+                            if let Some(&oldest) = time_buffer.keys().min() {
+                                if oldest > next_expected_batch_id {
+                                    PipelineMetrics::add(&metrics.dropped_batches, oldest - next_expected_batch_id);
+                                    next_expected_batch_id = oldest;
+                                }
                             }
+                        }
+                        
+                        // ✅ 处理匹配的数据对：所有已到达的连续批次并入待发送批次，按订阅帧率与负载发出
+                        while let Some(time_domain) = time_buffer.remove(&next_expected_batch_id) {
+                            let freq_data = match freq_buffer.remove(&next_expected_batch_id) {
+                                Some(freq_data) => {
+                                    last_freq_data = Some(freq_data.clone());
+                                    freq_data
+                                }
+                                None => last_freq_data.clone()
+                                    .unwrap_or_else(|| create_empty_freq_data()),
+                            };
                             
+                            if pending_time.is_some() {
+                                PipelineMetrics::add(&metrics.coalesced_batches, 1);
+                            }
+                            Self::merge_pending(&mut pending_time, time_domain);
+                            pending_freq = Some(freq_data);
                             next_expected_batch_id += 1;
+                        }
+                        
+                        let min_interval = subscription.max_rate
+                            .map_or(0.0, |rate| 1.0 / rate)
+                            .max(pacer.min_interval());
+                        let due = min_interval == 0.0 || last_sent.is_none_or(|sent| {
+                            // 容忍半个定时周期的抖动，避免帧率上限恰为定时频率时隔帧跳过
+                            sent.elapsed().as_secs_f64() + current_frame_rate.interval().as_secs_f64() / 2.0 >= min_interval
+                        });
+                        let emit_started = Instant::now();
+                        
+                        if !due {
+                            // 未到发送时间，样本保留在 pending_time 中
+                        } else if let Some(time_domain) = pending_time.take() {
+                            let freq_data = pending_freq.take()
+                                .unwrap_or_else(create_empty_freq_data);
                             
-                        } else if let Some(time_domain) = time_buffer.remove(&next_expected_batch_id) {
-                            let freq_data = create_empty_freq_data();
-                            
-                            // ✅ 发送二进制优化版本（仅时域）
+                            // ✅ 发送二进制优化版本
+                            let aux = Self::take_aligned_aux(&mut aux_buffer, &time_domain);
                             Self::send_optimized_frame(
                                 &mut data_converter,
                                 &mut binary_builder,
                                 &time_domain,
                                 &freq_data,
+                                aux.as_ref(),
+                                &subscription,
                                 &app_handle,
+                                &frame_channel,
                             ).await;
                             
                             frame_count += 1;
                             binary_frames_sent += 1;
-                            sent_data = true;
+                            last_sent = Some(Instant::now());
+                            metrics.emit.record(emit_started.elapsed(), 1);
                             
-                            if frame_count <= 10 {
-                                println!("🔥 Binary Frame #{} sent - batch #{} (time only)", 
-                                         frame_count, next_expected_batch_id);
+                            if pacer.record_emit(emit_started.elapsed(), current_frame_rate.interval(), &metrics) {
+                                Self::log_frontend_load(&pacer);
                             }
                             
-                            next_expected_batch_id += 1;
-                        }
-                        
-                        // ✅ 空帧处理
-                        if !sent_data {
+                            if frame_count <= 5 {
+                                debug!("🔥 Binary Frame #{} sent - up to batch #{}", 
+                                         frame_count, time_domain.batch_id);
+                            }
+                        } else {
+                            // ✅ 空帧处理
                             let empty_time = EegBatch {
                                 samples: vec![],
                                 batch_id: frame_count,
-                                channels_count,
+                                channels_count: display_channels,
                                 sample_rate,
                             };
                             
@@ -583,20 +3212,31 @@ impl EegProcessor {
                                 &mut binary_builder,
                                 &empty_time,
                                 &empty_freq,
+                                None,
+                                &subscription,
                                 &app_handle,
+                                &frame_channel,
                             ).await;
                             
                             frame_count += 1;
+                            last_sent = Some(Instant::now());
+                            
+                            if pacer.record_emit(emit_started.elapsed(), current_frame_rate.interval(), &metrics) {
+                                Self::log_frontend_load(&pacer);
+                            }
                         }
                         
                         // 清理缓冲区（保持现有逻辑）
                         let cleanup_threshold = next_expected_batch_id.saturating_sub(10);
                         freq_buffer.retain(|&batch_id, _| batch_id >= cleanup_threshold);
+                        let buffered = time_buffer.len();
                         time_buffer.retain(|&batch_id, _| batch_id >= cleanup_threshold);
+                        PipelineMetrics::add(&metrics.dropped_batches, (buffered - time_buffer.len()) as u64);
+                        PipelineMetrics::set(&metrics.frontend_queue, time_buffer.len() + time_domain_rx.len());
                         
                         // ✅ 增强统计信息
                         if frame_count % 300 == 0 && frame_count > 0 {
-                            println!("🔥 Status: {} frames sent, {} binary, buffer: freq={}, time={}", 
+                            debug!("🔥 Status: {} frames sent, {} binary, buffer: freq={}, time={}", 
                                      frame_count, binary_frames_sent, 
                                      freq_buffer.len(), time_buffer.len());
                         }
@@ -604,37 +3244,124 @@ impl EegProcessor {
                 }
             }
             
-            println!("🔥 Frontend thread stopped - frames: {}, binary: {}", 
+            info!("🔥 Frontend thread stopped - frames: {}, binary: {}", 
                      frame_count, binary_frames_sent);
         })
     }
     
-    /// ✅ 发送优化帧的辅助函数
+    /// 发送分析阶段事件
+    fn emit_analysis_event(app_handle: &StreamEmitter, event: &AnalysisEvent) {
+        let result = match event {
+            AnalysisEvent::BandPower(update) => app_handle.emit("band-power-update", update),
+            AnalysisEvent::SpectralMetrics(update) => app_handle.emit("spectral-metrics", update),
+            AnalysisEvent::Connectivity(update) => app_handle.emit("connectivity-update", update),
+            AnalysisEvent::ScriptMetrics(update) => app_handle.emit("script-metrics", update),
+        };
+        
+        if let Err(e) = result {
+            error!("Failed to emit analysis event: {}", e);
+        }
+    }
+    
+    /// 帧率变化后重建定时器，首次触发在一个新间隔之后（避免立即多发一帧）
+    fn frame_timer(config: FrameRateConfig) -> tokio::time::Interval {
+        let period = config.interval();
+        tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+    }
+    
+    fn log_frontend_load(pacer: &FramePacer) {
+        if pacer.under_load() {
+            warn!("🔥 Frontend falling behind (emit {:.1}ms), coalescing batches into larger frames", 
+                     pacer.emit_latency_ms());
+        } else {
+            info!("🔥 Frontend caught up (emit {:.1}ms), normal frame rate restored", pacer.emit_latency_ms());
+        }
+    }
+    
+    /// 将批次并入待发送批次（限速期间累积的样本在下一帧一并发出）
+    fn merge_pending(pending: &mut Option<EegBatch>, batch: EegBatch) {
+        match pending {
+            Some(merged) if merged.channels_count == batch.channels_count => {
+                merged.samples.extend(batch.samples);
+                merged.batch_id = batch.batch_id;
+            }
+            // 导联切换导致通道数变化时，丢弃旧通道布局的样本
+            _ => *pending = Some(batch),
+        }
+    }
+    
+    /// 取出时间戳不晚于批次最后一个EEG样本的辅助流样本
+    fn take_aligned_aux(aux_buffer: &mut std::collections::VecDeque<AuxSample>, time_domain: &EegBatch) -> Option<AuxBatch> {
+        let until = time_domain.samples.last()?.timestamp;
+        let count = aux_buffer.iter().take_while(|sample| sample.timestamp <= until).count();
+        if count == 0 {
+            return None;
+        }
+        let aligned: Vec<AuxSample> = aux_buffer.drain(..count).collect();
+        Some(AuxBatch::from_samples(&aligned))
+    }
+    
+    /// ✅ 发送优化帧的辅助函数 - 有订阅时走原始字节通道，否则退回事件
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(not(feature = "desktop"), allow(unused_variables))]
     async fn send_optimized_frame(
         data_converter: &mut DataConverter,
         binary_builder: &mut BinaryFrameBuilder,
         time_domain: &EegBatch,
         freq_data: &[FreqData],
-        app_handle: &AppHandle,
+        aux: Option<&AuxBatch>,
+        subscription: &FrameSubscription,
+        app_handle: &StreamEmitter,
+        frame_channel: &tokio::sync::RwLock<Option<Channel>>,
     ) {
-        // ✅ 转换为优化格式
-        let optimized_batch = data_converter.convert_eeg_batch_to_optimized(
+        // ✅ 转换为优化格式，并只保留订阅的通道
+        let mut optimized_batch = data_converter.convert_eeg_batch_to_optimized(
             time_domain,
             time_domain.batch_id
         );
+        optimized_batch.retain_subscribed(subscription);
+        
+        let subscribed_freq: Vec<FreqData>;
+        let freq_data = if !subscription.include_spectrum {
+            &[][..]
+        } else if subscription.channels.is_some() {
+            subscribed_freq = freq_data.iter()
+                .filter(|freq| subscription.wants_channel(freq.channel_index))
+                .cloned()
+                .collect();
+            &subscribed_freq[..]
+        } else {
+            freq_data
+        };
+        
+        // ✅ 原始字节IPC：时域与频域封装在同一帧内，无JSON序列化
+        #[cfg(feature = "desktop")]
+        if let Some(channel) = frame_channel.read().await.as_ref() {
+            let envelope = binary_builder.build_envelope(&optimized_batch, freq_data, aux);
+            match channel.send(InvokeResponseBody::Raw(envelope)) {
+                Ok(()) => return,
+                Err(e) => error!("Failed to send binary frame over channel: {}", e),
+            }
+        }
         
         // ✅ 生成二进制帧
         let binary_frame = binary_builder.build_channel_major_frame(&optimized_batch);
         
         // ✅ 发送二进制数据到前端
         if let Err(e) = app_handle.emit("binary-frame-update", &binary_frame) {
-            println!("Failed to emit binary frame: {}", e);
+            error!("Failed to emit binary frame: {}", e);
         }
         
         // ✅ 可选：同时发送频域数据（如果需要保持兼容性）
         if !freq_data.is_empty() {
             if let Err(e) = app_handle.emit("frequency-update", &freq_data) {
-                println!("Failed to emit frequency data: {}", e);
+                error!("Failed to emit frequency data: {}", e);
+            }
+        }
+        
+        if let Some(aux) = aux {
+            if let Err(e) = app_handle.emit("aux-update", aux) {
+                error!("Failed to emit aux data: {}", e);
             }
         }
     }
@@ -645,5 +3372,166 @@ impl EegProcessor {
 pub struct EegProcessorStats {
     pub stream_info: StreamInfo,
     pub recording_stats: Option<crate::recorder::RecordingStats>,
+    pub capture_stats: Option<CaptureStats>,
     pub threads_spawned: u32,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn test_stream_info() -> StreamInfo {
+        StreamInfo {
+            name: "Test EEG".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 2,
+            sample_rate: 100.0,
+            is_connected: true,
+            source_id: "test_device".to_string(),
+            channel_labels: StreamInfo::default_channel_labels(2),
+            effective_sample_rate: None,
+        }
+    }
+    
+    fn anomaly(kind: AnomalyKind, lsl_timestamp: f64, fatal: bool) -> AcquisitionAnomaly {
+        AcquisitionAnomaly { kind, message: "test".to_string(), sample_id: Some(0), lsl_timestamp: Some(lsl_timestamp), fatal }
+    }
+    
+    async fn csv_recorder(name: &str, anomalies: &AnomalyLog) -> MultiRecorder {
+        let stream_info = test_stream_info();
+        let path = std::env::temp_dir().join(format!("{}_{}.csv", name, std::process::id()));
+        let options = RecordingOptions { format: RecordingFormat::Csv, ..RecordingOptions::default() };
+        let history = Mutex::new(SampleHistory::new(HistoryConfig::default(), stream_info.sample_rate));
+        EegProcessor::create_recorder(path.to_str().unwrap(), &stream_info, options, Vec::new(),
+                                      &SessionMetadata::default(), &history, anomalies).await.unwrap()
+    }
+    
+    fn remove_files(finished: &FinishedRecording) {
+        for segment in &finished.stats.segments {
+            let _ = std::fs::remove_file(segment);
+            let _ = std::fs::remove_file(crate::delimited::annotations_path(segment));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_spectrum_range_above_nyquist_is_clamped() {
+        // 100Hz 流的奈奎斯特频率为 50Hz
+        let processor = EegProcessor::new(test_stream_info(), StreamEmitter::headless()).unwrap();
+        let requested = SpectrumRange { min_hz: 10.0, max_hz: 120.0, bin_count: 41 };
+        
+        let effective = processor.set_spectrum_range(requested.clone()).await.unwrap();
+        assert_eq!((effective.min_hz, effective.max_hz, effective.bin_count), (10.0, 50.0, 41));
+        assert_eq!(effective.frequencies().last().copied(), Some(50.0));
+        
+        // 保存的是用户请求的范围，换到更高采样率的流时不丢失
+        assert_eq!(processor.get_spectrum_range().await.max_hz, 120.0);
+        
+        let above = SpectrumRange { min_hz: 60.0, max_hz: 80.0, bin_count: 41 };
+        assert!(processor.set_spectrum_range(above).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_spectrum_range_min_above_max_is_rejected() {
+        let processor = EegProcessor::new(test_stream_info(), StreamEmitter::headless()).unwrap();
+        let before = processor.get_spectrum_range().await;
+        
+        for (min_hz, max_hz) in [(30.0, 10.0), (20.0, 20.0)] {
+            let inverted = SpectrumRange { min_hz, max_hz, bin_count: 41 };
+            assert!(matches!(processor.set_spectrum_range(inverted.clone()).await, Err(AppError::Config(_))));
+            assert!(processor.check_config_update(&ProcessorConfigUpdate::SpectrumRange(inverted)).is_err());
+        }
+        assert_eq!(processor.get_spectrum_range().await.max_hz, before.max_hz);
+    }
+    
+    #[tokio::test]
+    async fn test_anomalies_before_recording_are_annotated_when_it_starts() {
+        let app_handle = StreamEmitter::headless();
+        let recorder = Arc::new(Mutex::new(None));
+        let anomalies = AnomalyLog::default();
+        
+        // 未录制时发生的异常暂存
+        EegProcessor::report_anomaly(&app_handle, &recorder, &anomalies, anomaly(AnomalyKind::NonMonotonicTimestamp, 10.0, false)).await;
+        
+        let mut active = csv_recorder("anomaly_flush", &anomalies).await;
+        assert!(anomalies.pending.lock().await.is_empty());
+        for sample_id in 0..100u64 {
+            let sample = EegSample { timestamp: 20.0 + sample_id as f64 / 100.0, channels: vec![0.0, 0.0], sample_id };
+            active.write_sample(&RecordingSample { sample, raw: None, filtered: None }).unwrap();
+        }
+        *recorder.lock().await = Some(active);
+        
+        // 录制中的异常按LSL时间戳对齐
+        EegProcessor::report_anomaly(&app_handle, &recorder, &anomalies, anomaly(AnomalyKind::ChannelCountMismatch, 20.5, false)).await;
+        
+        let finished = recorder.lock().await.take().unwrap().finish().into_iter().next().unwrap().unwrap();
+        remove_files(&finished);
+        let annotations: Vec<(&str, f64)> = finished.annotations.iter()
+            .map(|annotation| (annotation.description.as_str(), annotation.onset_seconds))
+            .collect();
+        assert_eq!(annotations, vec![
+            ("ANOMALY non-monotonic timestamp: test", 0.0),
+            ("ANOMALY channel count mismatch: test", 0.5),
+        ]);
+    }
+    
+    #[tokio::test]
+    async fn test_strict_abort_finalizes_recording_and_stops_pipeline() {
+        let app_handle = StreamEmitter::headless();
+        let anomalies = AnomalyLog::default();
+        let recording_log = RecordingLog::default();
+        let is_running = Arc::new(tokio::sync::RwLock::new(true));
+        
+        let mut active = csv_recorder("strict_abort", &anomalies).await;
+        for sample_id in 0..50u64 {
+            let sample = EegSample { timestamp: sample_id as f64 / 100.0, channels: vec![1.0, -1.0], sample_id };
+            active.write_sample(&RecordingSample { sample, raw: None, filtered: None }).unwrap();
+        }
+        let recorder = Arc::new(Mutex::new(Some(active)));
+        
+        EegProcessor::abort_acquisition(&app_handle, &recorder, &recording_log, &anomalies, &is_running,
+                                        anomaly(AnomalyKind::QueueOverflow, 0.25, true)).await;
+        
+        assert!(!*is_running.read().await);
+        assert!(recorder.lock().await.is_none());
+        
+        let finished = recording_log.last.lock().await.clone().expect("recording finalized");
+        remove_files(&finished);
+        assert_eq!(finished.stats.samples_written, 50);
+        assert_eq!(finished.annotations.last().map(|annotation| annotation.description.as_str()),
+                   Some("ANOMALY queue overflow: test"));
+    }
+    
+    #[test]
+    fn test_decimated_display_samples_have_no_gap_flags() {
+        let sample_rate = 500.0;
+        let config = DisplayRateConfig { decimation_factor: 4 };
+        let mut decimator = None;
+        let mut converter = DataConverter::new(2);
+        
+        let decimated: Vec<DisplaySample> = (0..400u64).filter_map(|n| {
+            let sample = DisplaySample { timestamp: n as f64 / sample_rate, channels: vec![1.0, -1.0], sample_id: n };
+            EegProcessor::decimate_sample(&mut decimator, config, sample_rate, &sample)
+        }).collect();
+        assert_eq!(decimated.len(), 100);
+        
+        // 分两个批次，跨批次比较也不应标记间隙
+        for (batch_id, samples) in decimated.chunks(50).enumerate() {
+            let batch = EegBatch {
+                samples: samples.to_vec(),
+                batch_id: batch_id as u64,
+                channels_count: 2,
+                sample_rate: config.display_rate(sample_rate),
+            };
+            let optimized = converter.convert_eeg_batch_to_optimized(&batch, batch_id as u64);
+            assert!(optimized.gap_flags.iter().all(|&flag| flag == 0), "gap flags {:?}", optimized.gap_flags);
+        }
+        
+        // 真正丢样（跳过一个抽取周期）仍标记为间隙
+        let mut dropped = decimated[50..60].to_vec();
+        dropped.remove(5);
+        let batch = EegBatch { samples: dropped, batch_id: 2, channels_count: 2, sample_rate: config.display_rate(sample_rate) };
+        let mut converter = DataConverter::new(2);
+        let optimized = converter.convert_eeg_batch_to_optimized(&batch, 2);
+        assert_eq!(optimized.gap_flags.iter().filter(|&&flag| flag == 1).count(), 1);
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use crate::data_types::*;
+
+/// 标记锁定分段 - 保留标记前 pre 秒的样本，标记到达后继续收集 post 秒
+///
+/// 标记在其对齐样本处理之前加入，所以最近样本缓冲即为标记前区间。
+/// 通道布局变化（导联/通道选择）时丢弃未完成的分段。
+pub struct EpochExtractor {
+    config: EpochConfig,
+    sample_rate: f64,
+    pre_samples: usize,
+    post_samples: usize,
+    channel_labels: Vec<String>,
+    recent: VecDeque<Vec<f64>>,
+    pending: Vec<PendingEpoch>,
+    counts: Vec<EpochCount>,
+}
+
+struct PendingEpoch {
+    label: String,
+    marker_timestamp: f64,
+    sample_id: Option<u64>,
+    samples: Vec<Vec<f64>>,
+    pre_available: usize,
+}
+
+impl EpochExtractor {
+    pub fn new(config: EpochConfig, sample_rate: f64, channel_labels: Vec<String>) -> Self {
+        let pre_samples = (config.pre_seconds * sample_rate).round() as usize;
+        let post_samples = ((config.post_seconds * sample_rate).round() as usize).max(1);
+        
+        Self {
+            config,
+            sample_rate,
+            pre_samples,
+            post_samples,
+            channel_labels,
+            recent: VecDeque::with_capacity(pre_samples + 1),
+            pending: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+    
+    pub fn channel_labels(&self) -> &[String] {
+        &self.channel_labels
+    }
+    
+    pub fn counts(&self) -> &[EpochCount] {
+        &self.counts
+    }
+    
+    /// 标记到达：以当前缓冲的最近样本作为标记前区间
+    pub fn on_marker(&mut self, marker: &MarkerEvent) {
+        if !self.config.markers.is_empty() && !self.config.markers.contains(&marker.label) {
+            return;
+        }
+        
+        self.pending.push(PendingEpoch {
+            label: marker.label.clone(),
+            marker_timestamp: marker.timestamp,
+            sample_id: None,
+            samples: self.recent.iter().cloned().collect(),
+            pre_available: self.recent.len(),
+        });
+    }
+    
+    /// 加入一个显示路径样本，返回本样本完成的分段
+    pub fn push(&mut self, sample_id: u64, channels: &[f64]) -> Vec<Epoch> {
+        if channels.len() != self.channel_labels.len() {
+            self.pending.clear();
+            self.recent.clear();
+            return Vec::new();
+        }
+        
+        let total = self.pre_samples + self.post_samples;
+        let mut completed = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            let pending = &mut self.pending[i];
+            pending.sample_id.get_or_insert(sample_id);
+            pending.samples.push(channels.to_vec());
+            
+            if pending.samples.len() - pending.pre_available >= self.post_samples {
+                let pending = self.pending.remove(i);
+                completed.push(self.finalize(pending, total));
+            } else {
+                i += 1;
+            }
+        }
+        
+        if self.pre_samples > 0 {
+            if self.recent.len() == self.pre_samples {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(channels.to_vec());
+        }
+        
+        completed
+    }
+    
+    fn finalize(&mut self, pending: PendingEpoch, total: usize) -> Epoch {
+        let channels = self.channel_labels.len();
+        let mut data: Vec<Vec<f64>> = (0..channels)
+            .map(|ch| pending.samples.iter().map(|sample| sample[ch]).collect())
+            .collect();
+        
+        let mut reject_reason = None;
+        if pending.samples.len() < total {
+            reject_reason = Some("Insufficient pre-marker data".to_string());
+        }
+        
+        if self.config.baseline_correction && pending.pre_available > 0 {
+            for channel in &mut data {
+                let baseline = channel[..pending.pre_available].iter().sum::<f64>() / pending.pre_available as f64;
+                channel.iter_mut().for_each(|value| *value -= baseline);
+            }
+        }
+        
+        if let (None, Some(threshold)) = (&reject_reason, self.config.reject_peak_to_peak) {
+            for (ch, channel) in data.iter().enumerate() {
+                let max = channel.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let min = channel.iter().copied().fold(f64::INFINITY, f64::min);
+                if max - min > threshold {
+                    reject_reason = Some(format!("{} peak-to-peak {:.1}µV > {:.1}µV",
+                                                 self.channel_labels[ch], max - min, threshold));
+                    break;
+                }
+            }
+        }
+        
+        let rejected = reject_reason.is_some();
+        let count = match self.counts.iter_mut().find(|count| count.label == pending.label) {
+            Some(count) => count,
+            None => {
+                self.counts.push(EpochCount { label: pending.label.clone(), ..EpochCount::default() });
+                self.counts.last_mut().unwrap()
+            }
+        };
+        if rejected {
+            count.rejected += 1;
+        } else {
+            count.accepted += 1;
+        }
+        
+        Epoch {
+            label: pending.label,
+            marker_timestamp: pending.marker_timestamp,
+            sample_id: pending.sample_id.unwrap_or_default(),
+            sample_rate: self.sample_rate,
+            pre_seconds: pending.pre_available as f64 / self.sample_rate,
+            channel_labels: self.channel_labels.clone(),
+            data: data.into_iter().map(|channel| channel.into_iter().map(|v| v as f32).collect()).collect(),
+            rejected,
+            reject_reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_epoch_window_baseline_and_rejection() {
+        let config = EpochConfig {
+            enabled: true,
+            pre_seconds: 0.1,
+            post_seconds: 0.2,
+            reject_peak_to_peak: Some(100.0),
+            ..EpochConfig::default()
+        };
+        let mut extractor = EpochExtractor::new(config, 100.0, vec!["Cz".to_string()]);
+        let marker = |label: &str, timestamp: f64| MarkerEvent {
+            label: label.to_string(),
+            timestamp,
+            stream_name: "Markers".to_string(),
+            sample_id: 0,
+            recording_onset: None,
+        };
+        
+        let mut epochs = Vec::new();
+        for n in 0..100u64 {
+            if n == 20 {
+                extractor.on_marker(&marker("target", 0.2));
+            }
+            if n == 60 {
+                extractor.on_marker(&marker("target", 0.6));
+            }
+            // 直流偏置10µV，第二个标记之后出现大幅伪迹
+            let value = if (65..70).contains(&n) { 500.0 } else { 10.0 };
+            epochs.extend(extractor.push(n, &[value]));
+        }
+        
+        assert_eq!(epochs.len(), 2);
+        let first = &epochs[0];
+        assert_eq!(first.sample_id, 20);
+        assert_eq!(first.data[0].len(), 30);
+        assert!(!first.rejected);
+        assert!(first.data[0].iter().all(|v| v.abs() < 1e-6));
+        
+        assert!(epochs[1].rejected);
+        assert_eq!(extractor.counts(), &[EpochCount { label: "target".to_string(), accepted: 1, rejected: 1 }]);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::data_types::*;
+
+/// 单个标记的累计平均（逐点 Welford 均值/方差）
+struct RunningErp {
+    label: String,
+    sample_rate: f64,
+    pre_seconds: f64,
+    channel_labels: Vec<String>,
+    trials: u32,
+    mean: Vec<Vec<f64>>,
+    m2: Vec<Vec<f64>>,
+    dirty: bool,
+}
+
+impl RunningErp {
+    fn new(epoch: &Epoch) -> Self {
+        let shape: Vec<Vec<f64>> = epoch.data.iter().map(|channel| vec![0.0; channel.len()]).collect();
+        Self {
+            label: epoch.label.clone(),
+            sample_rate: epoch.sample_rate,
+            pre_seconds: epoch.pre_seconds,
+            channel_labels: epoch.channel_labels.clone(),
+            trials: 0,
+            mean: shape.clone(),
+            m2: shape,
+            dirty: false,
+        }
+    }
+    
+    fn matches(&self, epoch: &Epoch) -> bool {
+        self.channel_labels == epoch.channel_labels
+            && self.mean.iter().zip(&epoch.data).all(|(mean, data)| mean.len() == data.len())
+    }
+    
+    fn add(&mut self, epoch: &Epoch) {
+        self.trials += 1;
+        let n = self.trials as f64;
+        for ((mean, m2), data) in self.mean.iter_mut().zip(self.m2.iter_mut()).zip(&epoch.data) {
+            for ((mean, m2), &value) in mean.iter_mut().zip(m2.iter_mut()).zip(data) {
+                let delta = value as f64 - *mean;
+                *mean += delta / n;
+                *m2 += delta * (value as f64 - *mean);
+            }
+        }
+        self.dirty = true;
+    }
+    
+    fn snapshot(&self, z: f64) -> ErpUpdate {
+        let n = self.trials as f64;
+        let half_width: Vec<Vec<f64>> = self.m2.iter()
+            .map(|m2| m2.iter().map(|&m2| {
+                if self.trials < 2 { 0.0 } else { z * (m2 / (n - 1.0)).sqrt() / n.sqrt() }
+            }).collect())
+            .collect();
+        let bound = |sign: f64| self.mean.iter().zip(&half_width)
+            .map(|(mean, width)| mean.iter().zip(width).map(|(m, w)| (m + sign * w) as f32).collect())
+            .collect();
+        
+        ErpUpdate {
+            label: self.label.clone(),
+            trials: self.trials,
+            sample_rate: self.sample_rate,
+            pre_seconds: self.pre_seconds,
+            channel_labels: self.channel_labels.clone(),
+            average: self.mean.iter().map(|mean| mean.iter().map(|&m| m as f32).collect()).collect(),
+            ci_lower: bound(-1.0),
+            ci_upper: bound(1.0),
+        }
+    }
+}
+
+/// 在线ERP平均 - 接收分段器接受的分段，按标记分别累计，按数据时间间隔推送有新试次的标记
+///
+/// 置信区间按正态近似（均值 ± z·SEM），试次较少时偏窄。
+/// 分段形状（通道或长度）变化时该标记的平均重新开始。
+pub struct ErpAverager {
+    config: ErpConfig,
+    z: f64,
+    averages: Vec<RunningErp>,
+    last_update: Option<f64>,
+}
+
+impl ErpAverager {
+    pub fn new(config: ErpConfig) -> Self {
+        Self {
+            config,
+            z: normal_quantile(0.5 + config.confidence_level / 2.0),
+            averages: Vec::new(),
+            last_update: None,
+        }
+    }
+    
+    pub fn add(&mut self, epoch: &Epoch) {
+        if epoch.rejected {
+            return;
+        }
+        
+        match self.averages.iter_mut().find(|erp| erp.label == epoch.label) {
+            Some(erp) if erp.matches(epoch) => erp.add(epoch),
+            Some(erp) => {
+                *erp = RunningErp::new(epoch);
+                erp.add(epoch);
+            }
+            None => {
+                let mut erp = RunningErp::new(epoch);
+                erp.add(epoch);
+                self.averages.push(erp);
+            }
+        }
+    }
+    
+    /// 到推送间隔时返回自上次推送以来有新试次的标记
+    pub fn poll(&mut self, timestamp: f64) -> Vec<ErpUpdate> {
+        let interval = self.config.update_interval_ms as f64 / 1000.0;
+        if self.last_update.is_some_and(|last| timestamp - last < interval) {
+            return Vec::new();
+        }
+        if !self.averages.iter().any(|erp| erp.dirty) {
+            return Vec::new();
+        }
+        self.last_update = Some(timestamp);
+        
+        let z = self.z;
+        self.averages.iter_mut()
+            .filter(|erp| erp.dirty)
+            .map(|erp| {
+                erp.dirty = false;
+                erp.snapshot(z)
+            })
+            .collect()
+    }
+}
+
+/// 标准正态分布分位数（对 erf 近似二分求解）
+fn normal_quantile(p: f64) -> f64 {
+    let cdf = |x: f64| 0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2));
+    let (mut low, mut high) = (-10.0, 10.0);
+    for _ in 0..60 {
+        let mid = (low + high) / 2.0;
+        if cdf(mid) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Abramowitz-Stegun 7.1.26，绝对误差 < 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = x.signum();
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn epoch(label: &str, values: &[f32], rejected: bool) -> Epoch {
+        Epoch {
+            label: label.to_string(),
+            marker_timestamp: 0.0,
+            sample_id: 0,
+            sample_rate: 100.0,
+            pre_seconds: 0.0,
+            channel_labels: vec!["Cz".to_string()],
+            data: vec![values.to_vec()],
+            rejected,
+            reject_reason: None,
+        }
+    }
+    
+    #[test]
+    fn test_running_average_and_cadence() {
+        assert!((normal_quantile(0.975) - 1.96).abs() < 1e-3);
+        
+        let mut averager = ErpAverager::new(ErpConfig { enabled: true, ..ErpConfig::default() });
+        averager.add(&epoch("target", &[1.0, 2.0], false));
+        averager.add(&epoch("target", &[3.0, 2.0], false));
+        averager.add(&epoch("target", &[100.0, 100.0], true));
+        averager.add(&epoch("standard", &[0.0, 0.0], false));
+        
+        let updates = averager.poll(10.0);
+        assert_eq!(updates.len(), 2);
+        let target = &updates[0];
+        assert_eq!(target.trials, 2);
+        assert_eq!(target.average, vec![vec![2.0, 2.0]]);
+        // 第一个点 SD=√2，SEM=1
+        assert!((target.ci_upper[0][0] - (2.0 + 1.96)).abs() < 1e-2);
+        assert_eq!(target.ci_lower[0][1], 2.0);
+        
+        // 间隔内不推送，之后只推送有新试次的标记
+        averager.add(&epoch("standard", &[2.0, 2.0], false));
+        assert!(averager.poll(10.5).is_empty());
+        let updates = averager.poll(11.0);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].label, "standard");
+        assert_eq!(updates[0].average, vec![vec![1.0, 1.0]]);
+    }
+}
@@ -1,12 +1,24 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 use thiserror::Error;
 
+/// 命令与 backend-error 事件返回的错误
+///
+/// 序列化为 `{ code, message, context }`：code 为稳定错误码（见 ErrorCode），
+/// context 为按错误类型附带的结构化字段，如 unknown_stream 的 `stream_id`。
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("LSL error: {0}")]
     Lsl(String),
     
+    #[error("LSL stream not found: {0}")]
+    StreamNotFound(String),
+    
     #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
+    
+    #[error("Disk full: {0}")]
+    DiskFull(String),
     
     #[error("Channel communication error: {0}")]
     Channel(String),
@@ -19,6 +31,148 @@ pub enum AppError {
     
     #[error("Invalid configuration: {0}")]
     Config(String),
+    
+    #[error("Unknown stream: {0}")]
+    UnknownStream(String),
+    
+    #[error("Multiple streams connected ({0}), specify a stream id")]
+    AmbiguousStream(String),
+    
+    #[error("Playback error: {0}")]
+    Playback(String),
+    
+    #[error("Recording catalog error: {0}")]
+    Catalog(String),
+    
+    #[error("Upload error: {0}")]
+    Upload(String),
+    
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+    
+    #[error("Capture error: {0}")]
+    Capture(String),
+}
+
+/// 稳定错误码 - 前端据此区分错误类型，已有取值不再更改
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Lsl,
+    StreamNotFound,
+    Io,
+    FileNotFound,
+    PermissionDenied,
+    DiskFull,
+    Channel,
+    Recording,
+    NotConnected,
+    InvalidConfig,
+    UnknownStream,
+    AmbiguousStream,
+    Playback,
+    Catalog,
+    Upload,
+    Plugin,
+    Capture,
+}
+
+/// AppError 的可序列化形式
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub context: Map<String, Value>,
+}
+
+/// backend-error 事件中失败发生的后台环节
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorSource {
+    Recording,
+    Capture,
+    Trigger,
+    Aux,
+    ClockSync,
+    Scripting,
+    DiskMonitor,
+}
+
+/// 后台线程异步失败时推送给前端的 backend-error 事件
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackendError {
+    pub source: ErrorSource,
+    #[serde(flatten)]
+    pub error: ErrorInfo,
+}
+
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Lsl(_) => ErrorCode::Lsl,
+            AppError::StreamNotFound(_) => ErrorCode::StreamNotFound,
+            AppError::Io(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+                std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+                _ => ErrorCode::Io,
+            },
+            AppError::DiskFull(_) => ErrorCode::DiskFull,
+            AppError::Channel(_) => ErrorCode::Channel,
+            AppError::Recording(_) => ErrorCode::Recording,
+            AppError::NotConnected => ErrorCode::NotConnected,
+            AppError::Config(_) => ErrorCode::InvalidConfig,
+            AppError::UnknownStream(_) => ErrorCode::UnknownStream,
+            AppError::AmbiguousStream(_) => ErrorCode::AmbiguousStream,
+            AppError::Playback(_) => ErrorCode::Playback,
+            AppError::Catalog(_) => ErrorCode::Catalog,
+            AppError::Upload(_) => ErrorCode::Upload,
+            AppError::Plugin(_) => ErrorCode::Plugin,
+            AppError::Capture(_) => ErrorCode::Capture,
+        }
+    }
+    
+    /// 结构化上下文字段
+    pub fn context(&self) -> Map<String, Value> {
+        let context = match self {
+            AppError::StreamNotFound(name) => json!({ "stream": name }),
+            AppError::Io(e) => json!({ "kind": format!("{:?}", e.kind()) }),
+            AppError::UnknownStream(stream_id) => json!({ "stream_id": stream_id }),
+            AppError::AmbiguousStream(stream_ids) => json!({ "stream_ids": stream_ids.split(", ").collect::<Vec<_>>() }),
+            _ => return Map::new(),
+        };
+        match context {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        }
+    }
+    
+    pub fn info(&self) -> ErrorInfo {
+        ErrorInfo { code: self.code(), message: self.to_string(), context: self.context() }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.info().serialize(serializer)
+    }
+}
+
+// 磁盘已满单独归类，其余IO错误保留原始错误
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => AppError::DiskFull(err.to_string()),
+            _ => AppError::Io(err),
+        }
+    }
+}
+
+// 录制索引数据库错误
+impl From<rusqlite::Error> for AppError {
+    fn from(err: rusqlite::Error) -> Self {
+        AppError::Catalog(err.to_string())
+    }
 }
 
 // 添加对std::sync::mpsc的支持
@@ -40,4 +194,45 @@ impl From<tokio::sync::oneshot::error::RecvError> for AppError {
     fn from(err: tokio::sync::oneshot::error::RecvError) -> Self {
         AppError::Channel(format!("OneShot receive error: {}", err))
     }
-}
\ No newline at end of file
+}
+
+// 阻塞线程池中的任务失败（panic或被取消）
+impl From<tokio::task::JoinError> for AppError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        AppError::Channel(format!("Background task failed: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_errors_serialize_with_code_and_context() {
+        let value = serde_json::to_value(AppError::UnknownStream("amp2".to_string())).unwrap();
+        assert_eq!(value, json!({
+            "code": "unknown_stream",
+            "message": "Unknown stream: amp2",
+            "context": { "stream_id": "amp2" },
+        }));
+        
+        let value = serde_json::to_value(AppError::NotConnected).unwrap();
+        assert_eq!(value, json!({ "code": "not_connected", "message": "Stream not connected" }));
+        
+        let disk_full: AppError = std::io::Error::from(std::io::ErrorKind::StorageFull).into();
+        assert_eq!(disk_full.code(), ErrorCode::DiskFull);
+        let missing: AppError = std::io::Error::from(std::io::ErrorKind::NotFound).into();
+        assert_eq!(missing.info().code, ErrorCode::FileNotFound);
+        assert_eq!(missing.context()["kind"], "NotFound");
+        
+        let ambiguous = AppError::AmbiguousStream("amp1, amp2".to_string());
+        assert_eq!(ambiguous.context()["stream_ids"], json!(["amp1", "amp2"]));
+        
+        let event = BackendError { source: ErrorSource::ClockSync, error: AppError::Recording("closed".to_string()).info() };
+        assert_eq!(serde_json::to_value(&event).unwrap(), json!({
+            "source": "clock_sync",
+            "code": "recording",
+            "message": "Recording error: closed",
+        }));
+    }
+}
@@ -1,220 +1,715 @@
 use crate::data_types::*;
-use rustfft::{FftPlanner, num_complex::Complex};
-use std::collections::VecDeque;
+use crate::spectrogram::SpectrogramBuffer;
+use crate::band_power;
+use crate::spectral_metrics;
+use crate::connectivity::ConnectivityAccumulator;
+use crate::metrics::StageStats;
+use crate::scripting;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::collections::{HashMap, VecDeque};
 use crossbeam_channel;
-use std::sync::Arc;
-use std::time::Duration;
+use crate::pipeline_channel::PipelineSender;
+use crate::eeg_processor::HEARTBEAT_INTERVAL;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use tracing::{debug, info};
 
-// FFT相关常量
-const FFT_WINDOW_SIZE: usize = 256;
-const OUTPUT_FREQ_BINS: usize = 50;
+// spectral-metrics 推送间隔（秒，按数据时间）
+const SPECTRAL_METRICS_INTERVAL_SECONDS: f64 = 1.0;
+
+/// FFT计划与窗函数系数缓存 - 按FFT长度索引，进程级共享
+///
+/// 重连或切换配置时直接复用已有的计划和窗系数表，避免重复规划造成的卡顿。
+pub struct FftPlanCache {
+    planner: FftPlanner<f64>,
+    plans: HashMap<usize, Arc<dyn Fft<f64>>>,
+    windows: HashMap<usize, Arc<Vec<f64>>>,
+}
+
+impl FftPlanCache {
+    fn new() -> Self {
+        Self {
+            planner: FftPlanner::new(),
+            plans: HashMap::new(),
+            windows: HashMap::new(),
+        }
+    }
+    
+    /// 获取全局缓存实例
+    pub fn global() -> &'static std::sync::Mutex<FftPlanCache> {
+        static CACHE: OnceLock<std::sync::Mutex<FftPlanCache>> = OnceLock::new();
+        CACHE.get_or_init(|| std::sync::Mutex::new(FftPlanCache::new()))
+    }
+    
+    /// 获取（或创建）指定长度的前向FFT计划
+    pub fn forward_plan(&mut self, size: usize) -> Arc<dyn Fft<f64>> {
+        if let Some(plan) = self.plans.get(&size) {
+            return plan.clone();
+        }
+        
+        let plan = self.planner.plan_fft_forward(size);
+        self.plans.insert(size, plan.clone());
+        plan
+    }
+    
+    /// 获取（或创建）指定长度的Hanning窗系数表
+    pub fn hanning_window(&mut self, size: usize) -> Arc<Vec<f64>> {
+        self.windows
+            .entry(size)
+            .or_insert_with(|| Arc::new(hanning_coefficients(size)))
+            .clone()
+    }
+    
+    /// 预热常用长度，供启动时调用
+    pub fn prewarm(&mut self, sizes: &[usize]) {
+        for &size in sizes {
+            self.forward_plan(size);
+            self.hanning_window(size);
+        }
+    }
+}
+
+/// 从全局缓存获取FFT计划和窗系数
+fn cached_plan_and_window(config: &FftConfig) -> (Arc<dyn Fft<f64>>, Arc<Vec<f64>>) {
+    let mut cache = FftPlanCache::global()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    (cache.forward_plan(config.fft_size()), cache.hanning_window(config.window_size))
+}
 
 /// FFT处理器 - 专门负责频域分析
+#[derive(Clone)]
 pub struct FftProcessor {
     stream_info: StreamInfo,
-    is_running: Arc<tokio::sync::RwLock<bool>>,
+    shutdown: crossbeam_channel::Receiver<()>,     // 发送端随管道停止释放
+    config: Arc<tokio::sync::RwLock<FftConfig>>,
+    spectrum_range: Arc<tokio::sync::RwLock<SpectrumRange>>,
+    frequency_bands: Arc<tokio::sync::RwLock<FrequencyBands>>,
+    spectrogram: Arc<tokio::sync::Mutex<SpectrogramBuffer>>,
+    display_options: Arc<tokio::sync::RwLock<SpectrumDisplayOptions>>,
+    metrics_config: Arc<tokio::sync::RwLock<SpectralMetricsConfig>>,
+    connectivity_config: Arc<tokio::sync::RwLock<ConnectivityConfig>>,
+    scripting: Arc<tokio::sync::RwLock<ScriptingConfig>>,
 }
 
 impl FftProcessor {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         stream_info: StreamInfo,
-        is_running: Arc<tokio::sync::RwLock<bool>>,
+        shutdown: crossbeam_channel::Receiver<()>,
+        config: Arc<tokio::sync::RwLock<FftConfig>>,
+        spectrum_range: Arc<tokio::sync::RwLock<SpectrumRange>>,
+        frequency_bands: Arc<tokio::sync::RwLock<FrequencyBands>>,
+        spectrogram: Arc<tokio::sync::Mutex<SpectrogramBuffer>>,
+        display_options: Arc<tokio::sync::RwLock<SpectrumDisplayOptions>>,
+        metrics_config: Arc<tokio::sync::RwLock<SpectralMetricsConfig>>,
+        connectivity_config: Arc<tokio::sync::RwLock<ConnectivityConfig>>,
+        scripting: Arc<tokio::sync::RwLock<ScriptingConfig>>,
     ) -> Self {
         Self {
             stream_info,
-            is_running,
+            shutdown,
+            config,
+            spectrum_range,
+            frequency_bands,
+            spectrogram,
+            display_options,
+            metrics_config,
+            connectivity_config,
+            scripting,
         }
     }
     
-    /// 启动FFT处理线程
-    pub async fn spawn_fft_thread(
+    /// 启动FFT处理线程（专用OS线程，监督器重启时再次调用）
+    pub fn spawn_fft_thread(
         &self,
-        fft_trigger_rx: crossbeam_channel::Receiver<(u64, Vec<EegSample>)>,
-        freq_tx: crossbeam_channel::Sender<(u64, Vec<FreqData>)>,
-    ) -> tokio::task::JoinHandle<()> {
+        fft_trigger_rx: crossbeam_channel::Receiver<(u64, Vec<DisplaySample>)>,
+        freq_tx: PipelineSender<(u64, Vec<FreqData>)>,
+        analysis_tx: PipelineSender<AnalysisEvent>,
+        stage_stats: Arc<StageStats>,
+    ) -> thread::JoinHandle<()> {
         let stream_info = self.stream_info.clone();
-        let is_running = self.is_running.clone();
+        let shutdown = self.shutdown.clone();
+        let shared_config = self.config.clone();
+        let shared_range = self.spectrum_range.clone();
+        let shared_bands = self.frequency_bands.clone();
+        let spectrogram = self.spectrogram.clone();
+        let shared_display = self.display_options.clone();
+        let shared_metrics = self.metrics_config.clone();
+        let shared_connectivity = self.connectivity_config.clone();
+        let shared_scripting = self.scripting.clone();
         
-        tokio::spawn(async move {
-            println!("🟡 FFT thread started (batch-triggered, 1-50Hz)");
+        thread::spawn(move || {
+            info!("🟡 FFT thread started (batch-triggered)");
+            
+            let mut config = shared_config.blocking_read().clone();
+            
+            // 输出频点按流的奈奎斯特频率截断
+            let mut requested_range = shared_range.blocking_read().clone();
+            let mut target_freqs = requested_range.clamped_to(stream_info.sample_rate).frequencies();
+            
+            // ✅ 从缓存获取计划和窗系数，重连时无需重新规划
+            let (mut fft, mut window_coefficients) = cached_plan_and_window(&config);
+            let mut buffers = FftBuffers::new(fft.as_ref());
             
-            let mut fft_planner = FftPlanner::new();
-            let fft = fft_planner.plan_fft_forward(FFT_WINDOW_SIZE);
+            // 帧间平滑状态（显示后处理前的幅值谱）
+            let mut display_options = *shared_display.blocking_read();
+            let mut smoothed: Vec<Vec<f64>> = Vec::new();
+            
+            // 频谱指标按数据时间每秒计算一次
+            let mut last_metrics_timestamp: Option<f64> = None;
+            
+            // 连接性累计器，配置、频带定义或通道数变化时重建
+            let mut connectivity_config = ConnectivityConfig::default();
+            let mut connectivity_bands = FrequencyBands::default();
+            let mut connectivity: Option<ConnectivityAccumulator> = None;
+            
+            // 频谱脚本（on_spectrum），配置变化时重新编译
+            let mut scripting_config = shared_scripting.blocking_read().clone();
+            let mut spectrum_scripts = scripting::build_hooks(&scripting_config, ScriptInput::Spectrum);
             
             // 为每个通道维护滑动窗口
             let mut channel_windows: Vec<VecDeque<f64>> = (0..stream_info.channels_count)
-                .map(|_| VecDeque::with_capacity(FFT_WINDOW_SIZE + 100))
+                .map(|_| VecDeque::with_capacity(config.window_size + 100))
                 .collect();
             
             let mut batches_processed = 0u64;
             let mut ffts_computed = 0u64;
+            let mut samples_since_fft = 0usize;
             
-            let freq_resolution = stream_info.sample_rate / FFT_WINDOW_SIZE as f64;
-            println!("🟡 FFT config: size={}, resolution={:.2}Hz/bin, target=1-50Hz", 
-                     FFT_WINDOW_SIZE, freq_resolution);
+            Self::log_config(&config, &requested_range, stream_info.sample_rate);
             
             loop {
-                tokio::select! {
-                    batch_result = tokio::task::spawn_blocking({
-                        let fft_trigger_rx = fft_trigger_rx.clone();
-                        move || fft_trigger_rx.recv()
-                    }) => {
+                crossbeam_channel::select! {
+                    // 管道停止时发送端释放，立即唤醒
+                    recv(shutdown) -> _ => {
+                        info!("🟡 FFT thread stopping");
+                        break;
+                    }
+                    
+                    recv(fft_trigger_rx) -> batch_result => {
                         match batch_result {
-                            Ok(Ok((batch_id, sample_batch))) => {
+                            Ok((batch_id, sample_batch)) => {
                                 batches_processed += 1;
                                 
+                                // ✅ 配置变更时重建FFT计划和滑动窗口
+                                {
+                                    let latest = shared_config.blocking_read();
+                                    if *latest != config {
+                                        config = latest.clone();
+                                        (fft, window_coefficients) = cached_plan_and_window(&config);
+                                        buffers = FftBuffers::new(fft.as_ref());
+                                        
+                                        // 保留最新的样本，丢弃超出新窗口长度的部分
+                                        for window in &mut channel_windows {
+                                            while window.len() > config.window_size {
+                                                window.pop_front();
+                                            }
+                                        }
+                                        samples_since_fft = 0;
+                                        
+                                        Self::log_config(&config, &requested_range, stream_info.sample_rate);
+                                    }
+                                }
+                                
+                                // ✅ 频谱范围变更时重新生成输出频点
+                                {
+                                    let latest = shared_range.blocking_read();
+                                    if *latest != requested_range {
+                                        requested_range = latest.clone();
+                                        target_freqs = requested_range
+                                            .clamped_to(stream_info.sample_rate)
+                                            .frequencies();
+                                        
+                                        Self::log_config(&config, &requested_range, stream_info.sample_rate);
+                                    }
+                                }
+                                
+                                {
+                                    let latest = shared_scripting.blocking_read();
+                                    if *latest != scripting_config {
+                                        scripting_config = latest.clone();
+                                        spectrum_scripts = scripting::build_hooks(&scripting_config, ScriptInput::Spectrum);
+                                    }
+                                }
+                                
+                                {
+                                    let latest = shared_display.blocking_read();
+                                    if *latest != display_options {
+                                        display_options = *latest;
+                                        smoothed.clear();
+                                        info!("🟡 Spectrum display: {:?}", display_options);
+                                    }
+                                }
+                                
+                                // 导联改变通道数时重建滑动窗口
+                                if let Some(first) = sample_batch.first() {
+                                    if first.channels.len() != channel_windows.len() {
+                                        channel_windows = (0..first.channels.len())
+                                            .map(|_| VecDeque::with_capacity(config.window_size + 100))
+                                            .collect();
+                                        samples_since_fft = 0;
+                                    }
+                                }
+                                
+                                samples_since_fft += sample_batch.len();
+                                let batch_timestamp = sample_batch.last().map_or(0.0, |sample| sample.timestamp);
+                                
                                 // 更新滑动窗口
                                 for sample in sample_batch {
                                     for (ch_idx, &value) in sample.channels.iter().enumerate() {
                                         if ch_idx < channel_windows.len() {
                                             let window = &mut channel_windows[ch_idx];
-                                            window.push_back(value);
+                                            window.push_back(value as f64);
                                             
-                                            if window.len() > FFT_WINDOW_SIZE {
+                                            if window.len() > config.window_size {
                                                 window.pop_front();
                                             }
                                         }
                                     }
                                 }
                                 
-                                // 计算FFT并关联批次ID
-                                if channel_windows[0].len() >= FFT_WINDOW_SIZE {
-                                    let mut freq_data = compute_fixed_range_fft(
+                                // 计算FFT并关联批次ID（窗口已满且满足跳步长度）
+                                if channel_windows[0].len() >= config.window_size
+                                    && samples_since_fft >= config.hop_size
+                                {
+                                    samples_since_fft = 0;
+                                    let fft_started = std::time::Instant::now();
+                                    
+                                    buffers.compute(
                                         &channel_windows,
                                         fft.as_ref(),
-                                        stream_info.sample_rate,
+                                        &window_coefficients,
+                                        &config,
                                     );
+                                    let complex_spectra = &buffers.spectra;
+                                    let magnitude_spectra = &buffers.magnitudes;
+                                    let freq_resolution = stream_info.sample_rate / config.fft_size() as f64;
                                     
-                                    // 为每个频域数据关联批次ID
-                                    for freq_item in &mut freq_data {
-                                        freq_item.batch_id = Some(batch_id);
-                                    }
+                                    // 输出范围内的频谱，同时写入频谱图历史
+                                    let spectra: Vec<Vec<f64>> = magnitude_spectra
+                                        .iter()
+                                        .map(|magnitudes| sample_spectrum(magnitudes, &target_freqs, freq_resolution))
+                                        .collect();
+                                    spectrogram.blocking_lock().push(batch_timestamp, &target_freqs, &spectra);
+                                    
+                                    // 显示后处理：平滑、归一化、刻度，并关联批次ID
+                                    let mut spectra = spectra;
+                                    smooth_spectra(&mut smoothed, &mut spectra, display_options.smoothing);
+                                    let freq_data: Vec<FreqData> = spectra
+                                        .into_iter()
+                                        .map(|mut spectrum| {
+                                            display_options.apply(&mut spectrum);
+                                            spectrum
+                                        })
+                                        .enumerate()
+                                        .map(|(ch_idx, spectrum)| FreqData {
+                                            channel_index: ch_idx as u32,
+                                            spectrum,
+                                            frequency_bins: target_freqs.clone(),
+                                            batch_id: Some(batch_id),
+                                        })
+                                        .collect();
                                     
                                     if freq_tx.send((batch_id, freq_data)).is_err() {
-                                        println!("🟡 FFT: frequency receiver dropped");
+                                        info!("🟡 FFT: frequency receiver dropped");
                                         break;
                                     }
                                     
+                                    // ✅ 频带功率（基于完整分辨率的幅值谱）
+                                    let bands = shared_bands.blocking_read().clone();
+                                    let band_power = BandPowerUpdate {
+                                        batch_id,
+                                        bands: bands.names(),
+                                        channels: magnitude_spectra
+                                            .iter()
+                                            .enumerate()
+                                            .map(|(ch_idx, magnitudes)| band_power::compute_band_powers(
+                                                ch_idx as u32, magnitudes, freq_resolution, &bands
+                                            ))
+                                            .collect(),
+                                    };
+                                    let _ = analysis_tx.send(AnalysisEvent::BandPower(band_power));
+                                    
+                                    // ✅ 定量EEG指标（alpha峰值、谱边缘频率、总功率）
+                                    let metrics_config = *shared_metrics.blocking_read();
+                                    let metrics_due = last_metrics_timestamp
+                                        .is_none_or(|last| batch_timestamp - last >= SPECTRAL_METRICS_INTERVAL_SECONDS);
+                                    if metrics_config.enabled && metrics_due {
+                                        last_metrics_timestamp = Some(batch_timestamp);
+                                        let update = SpectralMetricsUpdate {
+                                            batch_id,
+                                            timestamp: batch_timestamp,
+                                            edge_fraction: metrics_config.edge_fraction,
+                                            channels: magnitude_spectra
+                                                .iter()
+                                                .enumerate()
+                                                .map(|(ch_idx, magnitudes)| spectral_metrics::compute_spectral_metrics(
+                                                    ch_idx as u32, magnitudes, freq_resolution, &metrics_config
+                                                ))
+                                                .collect(),
+                                        };
+                                        let _ = analysis_tx.send(AnalysisEvent::SpectralMetrics(update));
+                                    }
+                                    
+                                    // ✅ 通道间连接性（互谱累计）
+                                    {
+                                        let latest = shared_connectivity.blocking_read();
+                                        let channels_changed = connectivity.as_ref()
+                                            .is_some_and(|accumulator| accumulator.channels() != complex_spectra.len());
+                                        if *latest != connectivity_config || bands != connectivity_bands || channels_changed {
+                                            connectivity_config = latest.clone();
+                                            connectivity_bands = bands.clone();
+                                            connectivity = connectivity_config.enabled.then(|| ConnectivityAccumulator::new(
+                                                connectivity_config.clone(), &connectivity_bands, complex_spectra.len()
+                                            ));
+                                        }
+                                    }
+                                    if let Some(accumulator) = connectivity.as_mut() {
+                                        accumulator.push(batch_timestamp, complex_spectra, freq_resolution);
+                                        if let Some(update) = accumulator.poll(batch_timestamp, batch_id) {
+                                            let _ = analysis_tx.send(AnalysisEvent::Connectivity(update));
+                                        }
+                                    }
+                                    
+                                    // ✅ 自定义频谱脚本
+                                    if let Some(hooks) = spectrum_scripts.as_mut() {
+                                        for update in hooks.on_spectrum(batch_timestamp, freq_resolution, magnitude_spectra) {
+                                            let _ = analysis_tx.send(AnalysisEvent::ScriptMetrics(update));
+                                        }
+                                    }
+                                    
                                     ffts_computed += 1;
+                                    stage_stats.record(fft_started.elapsed(), 1);
                                     
                                     if ffts_computed <= 5 {
-                                        println!("🟡 FFT #{} for batch #{} → {} channels, 1-50Hz", 
-                                                 ffts_computed, batch_id, stream_info.channels_count);
+                                        debug!("🟡 FFT #{} for batch #{} → {} channels, {} bins", 
+                                                 ffts_computed, batch_id, stream_info.channels_count,
+                                                 target_freqs.len());
                                     } else if ffts_computed % 60 == 0 {
-                                        println!("🟡 FFT progress: {} computations completed", ffts_computed);
+                                        debug!("🟡 FFT progress: {} computations completed", ffts_computed);
                                     }
                                 }
                             }
-                            Ok(Err(_)) => {
-                                println!("🟡 FFT: trigger channel disconnected");
+                            Err(_) => {
+                                info!("🟡 FFT: trigger channel disconnected");
                                 break;
                             }
-                            Err(e) => {
-                                println!("🟡 FFT: batch processing error: {:?}", e);
-                            }
                         }
                     }
                     
-                    // 定期检查停止状态
-                    _ = tokio::time::sleep(Duration::from_millis(100)) => {
-                        let running = is_running.read().await;
-                        if !*running {
-                            println!("🟡 FFT thread stopping");
-                            break;
-                        }
-                    }
+                    // 空闲时更新心跳
+                    default(HEARTBEAT_INTERVAL) => stage_stats.beat(),
                 }
             }
             
-            println!("🟡 FFT thread stopped - batches: {}, FFTs: {}", batches_processed, ffts_computed);
+            info!("🟡 FFT thread stopped - batches: {}, FFTs: {}", batches_processed, ffts_computed);
         })
     }
+    
+    fn log_config(config: &FftConfig, range: &SpectrumRange, sample_rate: f64) {
+        let freq_resolution = sample_rate / config.fft_size() as f64;
+        let effective_range = range.clamped_to(sample_rate);
+        info!("🟡 FFT config: window={}, hop={}, fft size={}, overlap={:.0}%, resolution={:.2}Hz/bin, target={}-{}Hz ({} bins)", 
+                 config.window_size, config.hop_size, config.fft_size(),
+                 config.overlap() * 100.0, freq_resolution,
+                 effective_range.min_hz, effective_range.max_hz, effective_range.bin_count);
+        
+        if effective_range.max_hz < range.max_hz {
+            info!("🟡 Spectrum range clamped to Nyquist ({}Hz)", effective_range.max_hz);
+        }
+    }
 }
 
-/// 计算固定1-50Hz范围的FFT
-fn compute_fixed_range_fft(
-    channel_windows: &[VecDeque<f64>],
-    fft: &dyn rustfft::Fft<f64>,
-    sample_rate: f64,
-) -> Vec<FreqData> {
-    let mut results = Vec::new();
-    let freq_resolution = sample_rate / FFT_WINDOW_SIZE as f64;
-    
-    for (ch_idx, window) in channel_windows.iter().enumerate() {
-        if window.len() < FFT_WINDOW_SIZE {
-            continue;
+/// FFT工作缓冲 - 输入与scratch按FFT长度预分配，输出谱按通道复用，FFT计划变化时重建
+struct FftBuffers {
+    input: Vec<Complex<f64>>,
+    scratch: Vec<Complex<f64>>,
+    spectra: Vec<Vec<Complex<f64>>>,    // 单边复数谱
+    magnitudes: Vec<Vec<f64>>,          // 对应的幅值谱
+}
+
+impl FftBuffers {
+    fn new(fft: &dyn Fft<f64>) -> Self {
+        Self {
+            input: vec![Complex::new(0.0, 0.0); fft.len()],
+            scratch: vec![Complex::new(0.0, 0.0); fft.get_inplace_scratch_len()],
+            spectra: Vec::new(),
+            magnitudes: Vec::new(),
         }
+    }
+    
+    /// 计算每个通道的单边复数谱（长度 fft_size/2 + 1，按窗长归一化）及其幅值谱，写入复用的输出缓冲
+    fn compute(
+        &mut self,
+        channel_windows: &[VecDeque<f64>],
+        fft: &dyn Fft<f64>,
+        window_coefficients: &[f64],
+        config: &FftConfig,
+    ) {
+        let fft_size = config.fft_size();
+        let ready = channel_windows.iter().filter(|window| window.len() >= config.window_size).count();
+        self.spectra.resize_with(ready, Vec::new);
+        self.magnitudes.resize_with(ready, Vec::new);
         
-        // 准备FFT输入数据
-        let mut fft_input: Vec<Complex<f64>> = window
-            .iter()
-            .take(FFT_WINDOW_SIZE)
-            .map(|&x| Complex::new(x, 0.0))
-            .collect();
-        
-        // 应用Hanning窗函数
-        apply_hanning_window(&mut fft_input);
-        
-        // 执行FFT
-        fft.process(&mut fft_input);
-        
-        // 构建1-50Hz的输出
-        let mut spectrum = Vec::with_capacity(OUTPUT_FREQ_BINS);
-        let mut frequency_bins = Vec::with_capacity(OUTPUT_FREQ_BINS);
-        
-        for target_freq in 1..=50 {
-            let target_freq_f64 = target_freq as f64;
-            let fft_bin_index = (target_freq_f64 / freq_resolution).round() as usize;
+        let ready_windows = channel_windows.iter().filter(|window| window.len() >= config.window_size);
+        for ((window, spectrum), magnitudes) in ready_windows.zip(&mut self.spectra).zip(&mut self.magnitudes) {
+            // 准备FFT输入数据，应用Hanning窗函数（预计算系数），其余补零到FFT长度
+            for (slot, &x) in self.input.iter_mut().zip(window.iter().take(config.window_size)) {
+                *slot = Complex::new(x, 0.0);
+            }
+            apply_window(&mut self.input[..config.window_size], window_coefficients);
+            self.input[config.window_size..].fill(Complex::new(0.0, 0.0));
             
-            let magnitude = if fft_bin_index < fft_input.len() / 2 {
-                fft_input[fft_bin_index].norm() / FFT_WINDOW_SIZE as f64
-            } else {
-                0.0
-            };
+            // 执行FFT
+            fft.process_with_scratch(&mut self.input, &mut self.scratch);
             
-            spectrum.push(magnitude);
-            frequency_bins.push(target_freq_f64);
+            spectrum.clear();
+            spectrum.extend(self.input[..=fft_size / 2].iter().map(|value| value / config.window_size as f64));
+            magnitudes.clear();
+            magnitudes.extend(spectrum.iter().map(|value| value.norm()));
         }
+    }
+}
+
+/// 在目标频点上线性插值相邻FFT bin的幅值
+fn sample_spectrum(magnitudes: &[f64], target_freqs: &[f64], freq_resolution: f64) -> Vec<f64> {
+    let last_bin = magnitudes.len().saturating_sub(1);
+    
+    target_freqs
+        .iter()
+        .map(|&freq| {
+            let position = freq / freq_resolution;
+            let lower = position.floor() as usize;
         
-        results.push(FreqData {
-            channel_index: ch_idx as u32,
-            spectrum,
-            frequency_bins,
-            batch_id: None,
-        });
+            if lower > last_bin {
+                return 0.0;
+            }
+        
+            let upper = (lower + 1).min(last_bin);
+            let fraction = position - lower as f64;
+            magnitudes[lower] * (1.0 - fraction) + magnitudes[upper] * fraction
+        })
+        .collect()
+}
+
+/// 帧间指数平滑：smoothed = α·smoothed + (1-α)·spectrum，形状变化时从当前帧重新开始
+fn smooth_spectra(smoothed: &mut Vec<Vec<f64>>, spectra: &mut [Vec<f64>], alpha: f64) {
+    if alpha <= 0.0 {
+        return;
     }
     
-    results
+    let same_shape = smoothed.len() == spectra.len()
+        && smoothed.iter().zip(spectra.iter()).all(|(previous, current)| previous.len() == current.len());
+    if same_shape {
+        for (previous, current) in smoothed.iter().zip(spectra.iter_mut()) {
+            for (p, c) in previous.iter().zip(current.iter_mut()) {
+                *c = alpha * p + (1.0 - alpha) * *c;
+            }
+        }
+    }
+    *smoothed = spectra.to_vec();
 }
 
-/// 应用Hanning窗函数
-fn apply_hanning_window(data: &mut [Complex<f64>]) {
-    let n = data.len();
-    for (i, sample) in data.iter_mut().enumerate() {
-        let window_val = 0.5 * (1.0 - (2.0 * std::f64::consts::PI * i as f64 / (n - 1) as f64).cos());
-        sample.re *= window_val;
-        sample.im *= window_val;
+/// 离线逐帧计算幅值谱 - 与FFT线程使用同一计划缓存、窗函数与归一化，供回放回归比对
+///
+/// 每新增 hop_size 个样本（为0时取窗长）对最近 window_size 个样本计算一帧。
+pub(crate) fn offline_spectra<'a>(
+    samples: impl IntoIterator<Item = &'a EegSample>,
+    channels_count: usize,
+    sample_rate: f64,
+    config: &FftConfig,
+) -> Vec<SpectrumFrame> {
+    let (fft, window_coefficients) = cached_plan_and_window(config);
+    let mut buffers = FftBuffers::new(fft.as_ref());
+    let hop_size = if config.hop_size == 0 { config.window_size } else { config.hop_size };
+    let resolution = sample_rate / config.fft_size() as f64;
+    
+    let mut channel_windows = vec![VecDeque::with_capacity(config.window_size + 1); channels_count];
+    let mut samples_since_fft = 0;
+    let mut frames = Vec::new();
+    for sample in samples {
+        for (window, &value) in channel_windows.iter_mut().zip(&sample.channels) {
+            window.push_back(value);
+            if window.len() > config.window_size {
+                window.pop_front();
+            }
+        }
+        samples_since_fft += 1;
+        
+        if channel_windows.first().is_some_and(|window| window.len() >= config.window_size) && samples_since_fft >= hop_size {
+            samples_since_fft = 0;
+            buffers.compute(&channel_windows, fft.as_ref(), &window_coefficients, config);
+            frames.push(SpectrumFrame {
+                timestamp: sample.timestamp,
+                resolution,
+                magnitudes: buffers.magnitudes.clone(),
+            });
+        }
     }
+    
+    frames
 }
 
-/// FFT相关的公共常量和函数
-pub mod constants {
-    pub const OUTPUT_FREQ_BINS: usize = 50;
-    pub const TARGET_FREQ_MIN: u32 = 1;
-    pub const TARGET_FREQ_MAX: u32 = 50;
+/// 计算Hanning窗系数
+fn hanning_coefficients(n: usize) -> Vec<f64> {
+    if n < 2 {
+        return vec![1.0; n];
+    }
+    
+    (0..n)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f64::consts::PI * i as f64 / (n - 1) as f64).cos()))
+        .collect()
+}
+
+/// 应用预计算的窗函数系数
+fn apply_window(data: &mut [Complex<f64>], coefficients: &[f64]) {
+    for (sample, &window_val) in data.iter_mut().zip(coefficients) {
+        sample.re *= window_val;
+        sample.im *= window_val;
+    }
 }
 
 /// FFT配置和优化相关的实用函数
 pub mod utils {
-    use super::constants::*;
+    use crate::data_types::{FreqData, SpectrumDisplayOptions, SpectrumRange};
     
-    /// 创建空的频域数据
-    pub fn create_empty_freq_data(channels_count: u32) -> Vec<crate::data_types::FreqData> {
-        (0..channels_count).map(|i| crate::data_types::FreqData {
+    /// 创建空的频域数据（range 应已按奈奎斯特频率截断），空值与显示刻度一致
+    pub fn create_empty_freq_data(channels_count: u32, range: &SpectrumRange, options: &SpectrumDisplayOptions) -> Vec<FreqData> {
+        let frequency_bins = range.frequencies();
+        let mut empty = vec![0.0; frequency_bins.len()];
+        options.apply(&mut empty);
+        
+        (0..channels_count).map(|i| FreqData {
             channel_index: i,
-            spectrum: vec![0.0; OUTPUT_FREQ_BINS],
-            frequency_bins: (TARGET_FREQ_MIN..=TARGET_FREQ_MAX).map(|f| f as f64).collect(),
+            spectrum: empty.clone(),
+            frequency_bins: frequency_bins.clone(),
             batch_id: None,
         }).collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_plan_cache_reuses_plans_by_size() {
+        let mut cache = FftPlanCache::new();
+        
+        // 同一长度返回同一个计划与窗系数表
+        let plan = cache.forward_plan(256);
+        assert!(Arc::ptr_eq(&plan, &cache.forward_plan(256)));
+        assert!(Arc::ptr_eq(&cache.hanning_window(256), &cache.hanning_window(256)));
+        
+        // 长度变化时生成新计划，旧计划仍保留供切回时复用
+        let padded = cache.forward_plan(512);
+        assert_eq!((plan.len(), padded.len()), (256, 512));
+        assert!(Arc::ptr_eq(&plan, &cache.forward_plan(256)));
+        assert_eq!(cache.hanning_window(128).len(), 128);
+        
+        cache.prewarm(&[1024]);
+        assert_eq!(cache.plans.len(), 3);
+        assert!(cache.windows.contains_key(&1024));
+    }
+    
+    #[test]
+    fn test_display_smoothing_and_scaling() {
+        let mut smoothed = Vec::new();
+        let mut first = vec![vec![2.0, 0.0]];
+        smooth_spectra(&mut smoothed, &mut first, 0.5);
+        assert_eq!(first, vec![vec![2.0, 0.0]]);
+        
+        let mut second = vec![vec![0.0, 2.0]];
+        smooth_spectra(&mut smoothed, &mut second, 0.5);
+        assert_eq!(second, vec![vec![1.0, 1.0]]);
+        
+        // 相对功率：各频点之和为1，dB为 10·log10(0.5)
+        let options = SpectrumDisplayOptions { scale: SpectrumScale::Db, relative: true, smoothing: 0.5 };
+        let mut spectrum = second[0].clone();
+        options.apply(&mut spectrum);
+        assert!(spectrum.iter().all(|v| (v - 10.0 * 0.5f64.log10()).abs() < 1e-9));
+        
+        let empty = utils::create_empty_freq_data(1, &SpectrumRange::default(), &options);
+        assert!(empty[0].spectrum.iter().all(|&v| v == SpectrumDisplayOptions::DB_FLOOR));
+    }
+    
+    #[test]
+    fn test_sample_spectrum_interpolates_bins() {
+        let magnitudes = vec![0.0, 1.0, 3.0];
+        // 分辨率 2Hz：3Hz 位于 bin 1 与 bin 2 之间，超出最后一个bin的频点为0
+        let sampled = sample_spectrum(&magnitudes, &[0.0, 3.0, 4.0, 10.0], 2.0);
+        assert_eq!(sampled, vec![0.0, 2.0, 3.0, 0.0]);
+    }
+    
+    #[test]
+    fn test_hop_size_schedules_frames() {
+        let samples: Vec<EegSample> = (0..256u64)
+            .map(|i| EegSample { timestamp: i as f64 / 128.0, channels: vec![0.0], sample_id: i })
+            .collect();
+        
+        // 第一帧在窗填满时计算，之后每新增 hop_size 个样本一帧
+        let overlapped = FftConfig { window_size: 64, hop_size: 16, zero_padding_factor: 1 };
+        let frames = offline_spectra(&samples, 1, 128.0, &overlapped);
+        assert_eq!(frames.len(), 1 + (256 - 64) / 16);
+        assert_eq!(frames[0].timestamp, samples[63].timestamp);
+        assert_eq!(frames[1].timestamp, samples[79].timestamp);
+        
+        // hop_size 为0时按窗长推进，不重叠
+        let disjoint = FftConfig { hop_size: 0, ..overlapped };
+        let frames = offline_spectra(&samples, 1, 128.0, &disjoint);
+        assert_eq!(frames.len(), 256 / 64);
+        assert_eq!(frames[1].timestamp, samples[127].timestamp);
+    }
+    
+    #[test]
+    fn test_zero_padding_narrows_bin_spacing() {
+        let sample_rate = 128.0;
+        // 8Hz 正弦：64点窗分辨率 2Hz，补零4倍后 0.5Hz
+        let samples: Vec<EegSample> = (0..64u64)
+            .map(|i| EegSample {
+                timestamp: i as f64 / sample_rate,
+                channels: vec![(2.0 * std::f64::consts::PI * 8.0 * i as f64 / sample_rate).sin()],
+                sample_id: i,
+            })
+            .collect();
+        
+        for factor in [1, 4] {
+            let config = FftConfig { window_size: 64, hop_size: 0, zero_padding_factor: factor };
+            let frame = offline_spectra(&samples, 1, sample_rate, &config).remove(0);
+            assert_eq!(frame.resolution, sample_rate / (64 * factor) as f64);
+            assert_eq!(frame.magnitudes[0].len(), 64 * factor / 2 + 1);
+            
+            // 峰值仍落在8Hz，补零不改变频率位置
+            let peak = frame.magnitudes[0].iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+            assert_eq!(peak as f64 * frame.resolution, 8.0);
+        }
+    }
+    
+    #[test]
+    fn test_reused_buffers_match_fresh_computation() {
+        let config = FftConfig { window_size: 64, hop_size: 0, zero_padding_factor: 2 };
+        let (fft, coefficients) = cached_plan_and_window(&config);
+        
+        // 正弦位于第8个bin（补零后第16个）
+        let sine: VecDeque<f64> = (0..64)
+            .map(|i| (2.0 * std::f64::consts::PI * 8.0 * i as f64 / 64.0).sin())
+            .collect();
+        let windows = vec![sine.clone(), sine.iter().map(|x| x * 2.0).collect()];
+        
+        let mut buffers = FftBuffers::new(fft.as_ref());
+        buffers.compute(&windows, fft.as_ref(), &coefficients, &config);
+        let first = buffers.magnitudes.clone();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].len(), config.fft_size() / 2 + 1);
+        
+        let peak = first[0].iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+        assert_eq!(peak, 16);
+        assert!((first[1][peak] - 2.0 * first[0][peak]).abs() < 1e-9);
+        
+        // 复用缓冲（输入残留、通道数减少）结果不变
+        buffers.compute(&windows[..1], fft.as_ref(), &coefficients, &config);
+        assert_eq!(buffers.magnitudes.len(), 1);
+        assert_eq!(buffers.magnitudes[0], first[0]);
+    }
+}
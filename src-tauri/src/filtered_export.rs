@@ -0,0 +1,340 @@
+use crate::bdf::{BdfSignal, BdfWriter};
+use crate::data_types::*;
+use crate::dsp::{FilterChain, NotchFilterBank};
+use crate::error::AppError;
+use crate::gdf::{GdfPatient, GdfSignal, GdfWriter, GDF_DIMENSION_MICROVOLT};
+use crate::reader::RecordingReader;
+use crate::recorder::{RecordLayout, MAX_RECORD_BYTES};
+use crate::resample::Resampler;
+use chrono::Utc;
+use std::path::Path;
+use tracing::info;
+
+// 两端延拓的长度：最低截止频率的周期数，边缘瞬态在延拓段内衰减
+const PAD_CYCLES: f64 = 3.0;
+
+/// 离线导出的写入器 - 输出 BDF+ (24位) 或 GDF (float32)，保留滤波后的分辨率
+enum ExportWriter {
+    Bdf(BdfWriter),
+    Gdf(GdfWriter),
+}
+
+impl ExportWriter {
+    fn write_samples(&mut self, record: &[Vec<f64>]) -> Result<(), AppError> {
+        match self {
+            ExportWriter::Bdf(writer) => writer.write_samples(record),
+            ExportWriter::Gdf(writer) => writer.write_samples(record),
+        }
+    }
+    
+    fn add_annotation(&mut self, annotation: &RecordingFileAnnotation) -> Result<(), AppError> {
+        match self {
+            ExportWriter::Bdf(writer) => writer.add_annotation(annotation.onset, annotation.duration, &annotation.description),
+            ExportWriter::Gdf(writer) => writer.add_annotation(annotation.onset, annotation.duration, &annotation.description),
+        }
+    }
+    
+    fn finalize(self) -> Result<(), AppError> {
+        match self {
+            ExportWriter::Bdf(writer) => writer.finalize(),
+            ExportWriter::Gdf(writer) => writer.finalize(),
+        }
+    }
+}
+
+/// 处理后的一个通道
+struct ExportColumn {
+    sample_rate: f64,
+    samples: Vec<f64>,
+}
+
+/// 对已完成的 EDF/BDF 录制做零相位滤波（前向-后向）和可选重采样，写入新的 BDF+/GDF 文件
+///
+/// 输出格式由目标文件扩展名决定。整个文件按通道读入内存处理；注释按原时间复制，
+/// 零相位滤波和重采样都不移动波形的时间位置。
+pub fn export_filtered(source: &Path, destination: &Path, spec: &OfflineFilterSpec) -> Result<FilteredExportResult, AppError> {
+    if destination.exists() {
+        return Err(AppError::Config(format!("Destination already exists: {}", destination.display())));
+    }
+    let format = match RecordingFormat::from_extension(&destination.to_string_lossy()) {
+        Some(format @ (RecordingFormat::Bdf | RecordingFormat::Gdf)) => format,
+        _ => {
+            return Err(AppError::Config(format!(
+                "Filtered export writes .bdf or .gdf files, got {}", destination.display()
+            )));
+        }
+    };
+    
+    let mut reader = RecordingReader::open(source)?;
+    let info = reader.info().clone();
+    if info.channels.is_empty() || info.records == 0 {
+        return Err(AppError::Recording(format!("{} contains no data to export", source.display())));
+    }
+    for channel in &info.channels {
+        spec.validate(channel.sample_rate)?;
+    }
+    
+    let mut columns = Vec::with_capacity(info.channels.len());
+    for (index, channel) in info.channels.iter().enumerate() {
+        let filtered = zero_phase(&reader.read_channel(index)?, spec, channel.sample_rate);
+        columns.push(match spec.resample_hz {
+            Some(rate) if rate != channel.sample_rate => ExportColumn {
+                sample_rate: rate,
+                samples: resample(&filtered, channel.sample_rate, rate)?,
+            },
+            _ => ExportColumn { sample_rate: channel.sample_rate, samples: filtered },
+        });
+    }
+    
+    // 重采样后所有通道同速率，按新速率选择记录长度；否则沿用源文件的记录
+    let record_duration = match spec.resample_hz {
+        Some(rate) => {
+            let bytes_per_sample = if format == RecordingFormat::Bdf { 3 } else { 4 };
+            RecordLayout::for_rate(rate, None, MAX_RECORD_BYTES / (columns.len() * bytes_per_sample))?.duration
+        }
+        None => info.record_duration,
+    };
+    let samples_per_record: Vec<usize> = columns.iter()
+        .map(|column| (column.sample_rate * record_duration).round().max(1.0) as usize)
+        .collect();
+    let records = columns.iter().zip(&samples_per_record)
+        .map(|(column, &samples)| column.samples.len().div_ceil(samples))
+        .max()
+        .unwrap_or(0);
+    
+    let mut writer = create_writer(destination, format, &info, &columns, &samples_per_record, record_duration, spec)?;
+    let mut annotations = info.annotations.iter().peekable();
+    for record in 0..records {
+        // 注释随其所在的数据记录写入
+        let record_end = (record + 1) as f64 * record_duration;
+        while let Some(annotation) = annotations.next_if(|annotation| annotation.onset < record_end) {
+            writer.add_annotation(annotation)?;
+        }
+        
+        let data: Vec<Vec<f64>> = columns.iter().zip(&samples_per_record)
+            .map(|(column, &samples)| record_slice(&column.samples, record * samples, samples))
+            .collect();
+        writer.write_samples(&data)?;
+    }
+    for annotation in annotations {
+        writer.add_annotation(annotation)?;
+    }
+    writer.finalize()?;
+    
+    info!("🎛️  Exported zero-phase filtered copy of {} to {} ({} records)", source.display(), destination.display(), records);
+    Ok(FilteredExportResult {
+        source: source.display().to_string(),
+        destination: destination.display().to_string(),
+        channels: columns.len(),
+        sample_rate: columns[0].sample_rate,
+        duration_seconds: records as f64 * record_duration,
+        annotations: info.annotations.len(),
+    })
+}
+
+fn create_writer(
+    destination: &Path,
+    format: RecordingFormat,
+    info: &RecordingFileInfo,
+    columns: &[ExportColumn],
+    samples_per_record: &[usize],
+    record_duration: f64,
+    spec: &OfflineFilterSpec,
+) -> Result<ExportWriter, AppError> {
+    let filename = destination.to_string_lossy();
+    let start_time = info.start_time.map_or_else(Utc::now, |start| start.and_utc());
+    let channels = info.channels.iter().zip(columns).zip(samples_per_record);
+    
+    let writer = match format {
+        RecordingFormat::Gdf => {
+            let signals = channels
+                .map(|((channel, _), &samples)| GdfSignal {
+                    label: channel.label.clone(),
+                    transducer: String::new(),
+                    physical_dimension: channel.physical_dimension.clone(),
+                    dimension_code: if channel.physical_dimension == "uV" { GDF_DIMENSION_MICROVOLT } else { 0 },
+                    prefilter: prefilter(&channel.prefilter, spec),
+                    highpass: spec.filters.hp.map(|hp| hp as f32),
+                    lowpass: spec.filters.lp.map(|lp| lp as f32),
+                    samples_per_record: samples,
+                })
+                .collect();
+            let patient = GdfPatient { id: info.patient.clone(), ..GdfPatient::default() };
+            ExportWriter::Gdf(GdfWriter::create(
+                &filename, signals, record_duration, columns[0].sample_rate, start_time, &patient, &info.recording
+            )?)
+        }
+        _ => {
+            let signals = channels
+                .map(|((channel, column), &samples)| {
+                    let range = physical_range(&column.samples);
+                    BdfSignal {
+                        label: channel.label.clone(),
+                        transducer: String::new(),
+                        physical_dimension: channel.physical_dimension.clone(),
+                        physical_min: range.min,
+                        physical_max: range.max,
+                        prefilter: prefilter(&channel.prefilter, spec),
+                        samples_per_record: samples,
+                    }
+                })
+                .collect();
+            ExportWriter::Bdf(BdfWriter::create(
+                &filename, signals, record_duration, start_time, &info.patient, &info.recording
+            )?)
+        }
+    };
+    Ok(writer)
+}
+
+/// 前向-后向滤波：两端奇对称延拓以减小边缘瞬态，结果相位为零，幅频响应为单遍的平方
+fn zero_phase(samples: &[f64], spec: &OfflineFilterSpec, sample_rate: f64) -> Vec<f64> {
+    let lowest = [spec.filters.hp, spec.filters.lp, spec.notch.enabled.then_some(spec.notch.frequency_hz)]
+        .into_iter()
+        .flatten()
+        .fold(f64::INFINITY, f64::min);
+    if !lowest.is_finite() || samples.len() < 2 {
+        return samples.to_vec();
+    }
+    
+    let n = samples.len();
+    let pad = ((PAD_CYCLES * sample_rate / lowest).ceil() as usize).min(n - 1);
+    let (first, last) = (samples[0], samples[n - 1]);
+    let mut padded = Vec::with_capacity(n + 2 * pad);
+    padded.extend((1..=pad).rev().map(|i| 2.0 * first - samples[i]));
+    padded.extend_from_slice(samples);
+    padded.extend((1..=pad).map(|i| 2.0 * last - samples[n - 1 - i]));
+    
+    filter_pass(&mut padded, spec, sample_rate);
+    padded.reverse();
+    filter_pass(&mut padded, spec, sample_rate);
+    padded.reverse();
+    
+    padded[pad..pad + n].to_vec()
+}
+
+/// 单遍因果滤波（陷波后高通/低通，与实时路径顺序相同）
+fn filter_pass(samples: &mut [f64], spec: &OfflineFilterSpec, sample_rate: f64) {
+    let mut notch = NotchFilterBank::new(&spec.notch, sample_rate, 1);
+    let mut chain = FilterChain::new(&spec.filters, sample_rate, 1);
+    for value in samples.iter_mut() {
+        let mut sample = [*value];
+        notch.process_sample(&mut sample);
+        chain.process_sample(&mut sample);
+        *value = sample[0];
+    }
+}
+
+/// 整段重采样；重采样器输出有延迟，末尾以最后一个值延续直到输出足够的样本
+fn resample(samples: &[f64], input_rate: f64, output_rate: f64) -> Result<Vec<f64>, AppError> {
+    let mut resampler = Resampler::new(input_rate, output_rate, 1)?;
+    let expected = (samples.len() as f64 * output_rate / input_rate).floor() as usize;
+    let last = samples.last().copied().unwrap_or(0.0);
+    
+    let mut output = Vec::with_capacity(expected);
+    for value in samples.iter().copied().chain(std::iter::repeat(last)) {
+        if output.len() >= expected {
+            break;
+        }
+        output.extend(resampler.process_sample(&[value]).into_iter().map(|sample| sample[0]));
+    }
+    output.truncate(expected);
+    Ok(output)
+}
+
+/// 一个数据记录的样本，最后一个记录不足时以最后一个值补齐
+fn record_slice(samples: &[f64], start: usize, count: usize) -> Vec<f64> {
+    let end = (start + count).min(samples.len());
+    let mut slice = samples.get(start..end).map(<[f64]>::to_vec).unwrap_or_default();
+    let fill = slice.last().or(samples.last()).copied().unwrap_or(0.0);
+    slice.resize(count, fill);
+    slice
+}
+
+/// 按滤波后数据的实际范围设置 BDF 物理量程，平坦信号取 ±1
+fn physical_range(samples: &[f64]) -> ChannelRange {
+    let (min, max) = samples.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| (min.min(value), max.max(value)));
+    if min.is_finite() && max > min {
+        ChannelRange { min, max }
+    } else {
+        let center = if min.is_finite() { min } else { 0.0 };
+        ChannelRange { min: center - 1.0, max: center + 1.0 }
+    }
+}
+
+/// 预滤波字段：源文件的预滤波加上离线处理说明
+fn prefilter(source: &str, spec: &OfflineFilterSpec) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if !source.is_empty() {
+        parts.push(source.to_string());
+    }
+    parts.push("ZeroPhase".to_string());
+    if let Some(hp) = spec.filters.hp {
+        parts.push(format!("HP:{}Hz", hp));
+    }
+    if let Some(lp) = spec.filters.lp {
+        parts.push(format!("LP:{}Hz", lp));
+    }
+    if spec.notch.enabled {
+        parts.push(format!("N:{}Hz", spec.notch.frequency_hz));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    
+    #[test]
+    fn test_zero_phase_export_and_resample() {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("filtered_export_source_{}.bdf", std::process::id()));
+        let destination = dir.join(format!("filtered_export_dest_{}.bdf", std::process::id()));
+        let _ = std::fs::remove_file(&destination);
+        
+        // 10Hz 正弦叠加 100μV 直流偏移
+        let rate = 250.0;
+        let signal = BdfSignal {
+            label: "Cz".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -500.0,
+            physical_max: 500.0,
+            prefilter: String::new(),
+            samples_per_record: 250,
+        };
+        let mut writer = BdfWriter::create(source.to_str().unwrap(), vec![signal], 1.0, Utc::now(), "X X X X", "Startdate X X X X").unwrap();
+        writer.add_annotation(2.0, None, "stimulus").unwrap();
+        let sine = |i: usize| 50.0 * (2.0 * PI * 10.0 * i as f64 / rate).sin();
+        for record in 0..8 {
+            let samples: Vec<f64> = (0..250).map(|i| 100.0 + sine(record * 250 + i)).collect();
+            writer.write_samples(&[samples]).unwrap();
+        }
+        writer.finalize().unwrap();
+        
+        let spec = OfflineFilterSpec {
+            filters: FilterConfig { hp: Some(1.0), lp: Some(40.0), ..FilterConfig::default() },
+            resample_hz: Some(125.0),
+            ..OfflineFilterSpec::default()
+        };
+        let result = export_filtered(&source, &destination, &spec).unwrap();
+        assert_eq!(result.sample_rate, 125.0);
+        assert_eq!(result.duration_seconds, 8.0);
+        assert!(export_filtered(&source, &destination, &spec).is_err());
+        
+        let mut reader = RecordingReader::open(&destination).unwrap();
+        let output = reader.read_channel(0).unwrap();
+        let annotations = reader.info().annotations.clone();
+        let _ = std::fs::remove_file(&source);
+        let _ = std::fs::remove_file(&destination);
+        
+        // 中段：直流被去除，正弦无相位偏移
+        assert_eq!(output.len(), 1000);
+        for (i, value) in output.iter().enumerate().take(750).skip(250) {
+            let expected = sine(i * 2);
+            assert!((value - expected).abs() < 2.5, "sample {}: {} vs {}", i, value, expected);
+        }
+        assert_eq!(annotations, vec![RecordingFileAnnotation { onset: 2.0, duration: None, description: "stimulus".to_string() }]);
+    }
+}
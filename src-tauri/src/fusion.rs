@@ -0,0 +1,437 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::resample::Resampler;
+use crate::stream_source::{EegConnection, MarkerInlet, SampleInlet, SourceClock, StreamResolver, StreamSource};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// 相邻样本间隔超过该数目的输出周期视为数据中断，不跨中断插值（输出中留下间隙）
+const MAX_INTERPOLATION_GAP: f64 = 4.0;
+
+// 某个流停止发送时其他流最多缓冲的时长（秒），超出后丢弃最早的样本
+const MAX_BUFFERED_SECONDS: f64 = 10.0;
+
+/// 融合数据源 - 包装另一个数据源，把多个EEG流合并为一个虚拟流
+///
+/// 按虚拟流名称连接时分别连接各成员流；标记流、辅助流与其他EEG流直接交给内部数据源。
+pub struct FusionSource {
+    inner: Box<dyn StreamSource>,
+    config: FusionConfig,
+}
+
+impl FusionSource {
+    pub fn new(inner: Box<dyn StreamSource>, config: FusionConfig) -> Self {
+        Self { inner, config }
+    }
+}
+
+/// 拼接各流的通道标签；与前面的流重名时加上 "流名称:" 前缀
+fn fused_labels(streams: &[StreamInfo]) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for stream in streams {
+        for label in &stream.channel_labels {
+            if labels.contains(label) {
+                labels.push(format!("{}:{}", stream.name, label));
+            } else {
+                labels.push(label.clone());
+            }
+        }
+    }
+    labels
+}
+
+impl StreamSource for FusionSource {
+    fn clock(&self) -> SourceClock {
+        self.inner.clock()
+    }
+    
+    fn resolve_streams(&mut self) -> Result<Vec<LslStreamInfo>, AppError> {
+        self.inner.resolve_streams()
+    }
+    
+    fn connect(&mut self, selector: &StreamSelector, strict: bool, inlet_config: &LslInletConfig) -> Result<EegConnection, AppError> {
+        if selector.name != self.config.name {
+            return self.inner.connect(selector, strict, inlet_config);
+        }
+        
+        let mut connections = Vec::new();
+        for member in &self.config.streams {
+            let EegConnection { info, inlet, impedances } = self.inner.connect(member, strict, inlet_config)?;
+            let inlet = inlet
+                .ok_or_else(|| AppError::Lsl(format!("Stream '{}' has no data inlet, cannot fuse", info.name)))?;
+            if info.sample_rate <= 0.0 {
+                return Err(AppError::Lsl(format!("Stream '{}' has an irregular sample rate, cannot fuse", info.name)));
+            }
+            connections.push((info, inlet, impedances));
+        }
+        
+        let output_rate = self.config.sample_rate.unwrap_or(connections[0].0.sample_rate);
+        let infos: Vec<StreamInfo> = connections.iter().map(|(info, _, _)| info.clone()).collect();
+        
+        // 通道阻抗按通道顺序拼接，未提供的流补 None
+        let impedances = if connections.iter().all(|(_, _, impedances)| impedances.is_empty()) {
+            Vec::new()
+        } else {
+            connections.iter()
+                .flat_map(|(info, _, impedances)| (0..info.channels_count as usize).map(|ch| impedances.get(ch).copied().flatten()))
+                .collect()
+        };
+        
+        let members = connections.into_iter()
+            .map(|(info, inlet, _)| FusionMember::new(inlet, &info, output_rate))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        
+        let info = StreamInfo {
+            name: self.config.name.clone(),
+            stream_type: infos[0].stream_type.clone(),
+            channels_count: infos.iter().map(|info| info.channels_count).sum(),
+            sample_rate: output_rate,
+            is_connected: true,
+            source_id: format!("fusion_{}", infos.iter().map(|info| info.source_id.as_str()).collect::<Vec<_>>().join("+")),
+            channel_labels: fused_labels(&infos),
+            effective_sample_rate: None,
+        };
+        info!("🔗 Fused {} streams into '{}': {} channels @ {}Hz",
+              infos.len(), info.name, info.channels_count, output_rate);
+        
+        Ok(EegConnection { info, inlet: Some(Box::new(FusedInlet::new(members, output_rate))), impedances })
+    }
+    
+    fn connect_marker(&mut self, name: &str) -> Result<(LslStreamInfo, Box<dyn MarkerInlet>), AppError> {
+        self.inner.connect_marker(name)
+    }
+    
+    fn connect_aux(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
+        self.inner.connect_aux(name)
+    }
+    
+    fn connect_impedance(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
+        self.inner.connect_impedance(name)
+    }
+    
+    fn continuous_resolver(&mut self) -> Result<Box<dyn StreamResolver>, AppError> {
+        self.inner.continuous_resolver()
+    }
+}
+
+/// 融合中的一个流：采样率与输出不同时先重采样，缓冲带时间戳的样本等待对齐
+struct FusionMember {
+    inlet: Box<dyn SampleInlet>,
+    name: String,
+    channels_count: usize,
+    input_rate: f64,
+    resampler: Option<Resampler>,
+    input_timestamps: VecDeque<f64>,    // 最近的输入时间戳，用于换算重采样输出的时刻
+    timestamp_history: usize,
+    inputs_seen: u64,
+    buffer: VecDeque<(f64, Vec<f64>)>,  // 输出速率的样本（时间戳，通道值）
+}
+
+impl FusionMember {
+    fn new(inlet: Box<dyn SampleInlet>, info: &StreamInfo, output_rate: f64) -> Result<Self, AppError> {
+        let channels_count = info.channels_count as usize;
+        let resampler = if (info.sample_rate - output_rate).abs() > 1e-9 {
+            Some(Resampler::new(info.sample_rate, output_rate, channels_count)?)
+        } else {
+            None
+        };
+        // 输出 k 在输入越过其位置半个滤波器长度后才算出，需保留这段输入的时间戳
+        let timestamp_history = resampler.as_ref()
+            .map_or(1, |resampler| (resampler.latency_seconds() * info.sample_rate).ceil() as usize + 2);
+        
+        Ok(Self {
+            inlet,
+            name: info.name.clone(),
+            channels_count,
+            input_rate: info.sample_rate,
+            resampler,
+            input_timestamps: VecDeque::with_capacity(timestamp_history),
+            timestamp_history,
+            inputs_seen: 0,
+            buffer: VecDeque::new(),
+        })
+    }
+    
+    /// 加入一个输入样本；重采样输出的时刻按输入时间戳换算，跟随发送端时钟而非名义速率
+    fn push(&mut self, values: Vec<f64>, timestamp: f64) {
+        let Some(resampler) = self.resampler.as_mut() else {
+            self.buffer.push_back((timestamp, values));
+            return;
+        };
+        let outputs = resampler.process_sample(&values);
+        let first = resampler.output_count() - outputs.len() as u64;
+        let ratio = self.input_rate / resampler.output_rate();
+        
+        if self.input_timestamps.len() == self.timestamp_history {
+            self.input_timestamps.pop_front();
+        }
+        self.input_timestamps.push_back(timestamp);
+        self.inputs_seen += 1;
+        
+        for (k, output) in (first..).zip(outputs) {
+            let time = self.input_time(k as f64 * ratio);
+            self.buffer.push_back((time, output));
+        }
+    }
+    
+    /// 输入序号（可为小数）对应的时刻：取最近的已知输入时间戳，按名义速率补足小数部分
+    fn input_time(&self, position: f64) -> f64 {
+        let front = self.inputs_seen - self.input_timestamps.len() as u64;
+        let index = (position.max(0.0).floor() as u64).clamp(front, self.inputs_seen - 1);
+        self.input_timestamps[(index - front) as usize] + (position - index as f64) / self.input_rate
+    }
+    
+    /// 已收到的最新时刻，尚无数据时为负无穷
+    fn latest(&self) -> f64 {
+        self.buffer.back().map(|(timestamp, _)| *timestamp)
+            .or(self.input_timestamps.back().copied())
+            .unwrap_or(f64::NEG_INFINITY)
+    }
+}
+
+/// 融合inlet - 各成员流按时间戳线性插值到统一的输出时间网格
+///
+/// 网格从所有流都有数据的时刻开始，以输出采样率等间隔推进；
+/// 只有每个流都已覆盖的网格时刻才输出，因此整体延迟取决于最慢的流。
+struct FusedInlet {
+    members: Vec<FusionMember>,
+    output_rate: f64,
+    grid_start: Option<f64>,
+    grid_index: u64,
+    ready: VecDeque<(Vec<f64>, f64)>,
+    overflow_warned: bool,
+}
+
+impl FusedInlet {
+    fn new(members: Vec<FusionMember>, output_rate: f64) -> Self {
+        Self { members, output_rate, grid_start: None, grid_index: 0, ready: VecDeque::new(), overflow_warned: false }
+    }
+    
+    /// 非阻塞取出各流已到达的样本
+    fn fill(&mut self) -> Result<(), AppError> {
+        for member in &mut self.members {
+            let (values, timestamps) = member.inlet.pull_chunk()?;
+            for (values, timestamp) in values.into_iter().zip(timestamps) {
+                member.push(values, timestamp);
+            }
+        }
+        Ok(())
+    }
+    
+    /// 依次输出所有流都已覆盖的网格时刻
+    fn align(&mut self) {
+        let max_buffered = (MAX_BUFFERED_SECONDS * self.output_rate) as usize;
+        for member in &mut self.members {
+            if member.buffer.len() > max_buffered {
+                member.buffer.drain(..member.buffer.len() - max_buffered);
+                if !self.overflow_warned {
+                    warn!("⚠️ Fusion: other streams stalled, dropping buffered samples of '{}'", member.name);
+                    self.overflow_warned = true;
+                }
+            }
+        }
+        if self.members.iter().any(|member| member.buffer.is_empty()) {
+            return;
+        }
+        
+        let earliest = self.members.iter().map(|member| member.buffer[0].0).fold(f64::NEG_INFINITY, f64::max);
+        let start = *self.grid_start.get_or_insert(earliest);
+        loop {
+            let t = start + self.grid_index as f64 / self.output_rate;
+            
+            // 某个流最早的样本晚于网格时刻（缓冲被丢弃后）：网格跳到该时刻之后
+            let earliest = self.members.iter().map(|member| member.buffer[0].0).fold(f64::NEG_INFINITY, f64::max);
+            if earliest > t {
+                let index = ((earliest - start) * self.output_rate).ceil() as u64;
+                self.grid_index = index.max(self.grid_index + 1);
+                continue;
+            }
+            if self.members.iter().any(|member| member.buffer.back().is_none_or(|(timestamp, _)| *timestamp < t)) {
+                break;
+            }
+            
+            let mut sample = Vec::new();
+            let mut complete = true;
+            for member in &mut self.members {
+                // 保留紧邻 t 之前（含）的一个样本
+                while member.buffer.len() > 1 && member.buffer[1].0 <= t {
+                    member.buffer.pop_front();
+                }
+                let (t0, v0) = &member.buffer[0];
+                match member.buffer.get(1) {
+                    Some((t1, v1)) if *t0 < t => {
+                        if t1 - t0 > MAX_INTERPOLATION_GAP / self.output_rate {
+                            complete = false;
+                        }
+                        let weight = (t - t0) / (t1 - t0);
+                        sample.extend(v0.iter().zip(v1).map(|(a, b)| a + (b - a) * weight));
+                    }
+                    _ => sample.extend_from_slice(v0),
+                }
+            }
+            
+            if complete {
+                self.ready.push_back((sample, t));
+            }
+            self.grid_index += 1;
+        }
+    }
+}
+
+impl SampleInlet for FusedInlet {
+    fn pull_chunk(&mut self) -> Result<(Vec<Vec<f64>>, Vec<f64>), AppError> {
+        self.fill()?;
+        self.align();
+        Ok(self.ready.drain(..).unzip())
+    }
+    
+    fn pull_sample(&mut self, buffer: &mut Vec<f64>, timeout: f64) -> Result<f64, AppError> {
+        let deadline = Instant::now() + Duration::from_secs_f64(timeout);
+        while self.ready.is_empty() {
+            self.fill()?;
+            self.align();
+            if !self.ready.is_empty() {
+                break;
+            }
+            
+            let remaining = deadline.saturating_duration_since(Instant::now()).as_secs_f64();
+            if remaining <= 0.0 {
+                return Ok(0.0);
+            }
+            // 阻塞等待最落后的流
+            let Some(lagging) = self.members.iter_mut().min_by(|a, b| a.latest().total_cmp(&b.latest())) else {
+                return Ok(0.0);
+            };
+            let mut values = vec![0.0; lagging.channels_count];
+            let timestamp = lagging.inlet.pull_sample(&mut values, remaining)?;
+            if timestamp > 0.0 {
+                lagging.push(values, timestamp);
+            }
+        }
+        
+        let Some((values, timestamp)) = self.ready.pop_front() else { return Ok(0.0) };
+        for (value, fused) in buffer.iter_mut().zip(values) {
+            *value = fused;
+        }
+        Ok(timestamp)
+    }
+    
+    // 以第一个流的发送端时钟为参考
+    fn time_correction(&mut self, timeout: f64) -> Result<f64, AppError> {
+        self.members[0].inlet.time_correction(timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::stream_source::{MockSource, MockStream};
+    use std::f64::consts::PI;
+    
+    /// 按预设的块依次返回样本
+    struct ScriptedInlet {
+        chunks: VecDeque<(Vec<Vec<f64>>, Vec<f64>)>,
+    }
+    
+    impl SampleInlet for ScriptedInlet {
+        fn pull_chunk(&mut self) -> Result<(Vec<Vec<f64>>, Vec<f64>), AppError> {
+            Ok(self.chunks.pop_front().unwrap_or_default())
+        }
+        
+        fn pull_sample(&mut self, _buffer: &mut Vec<f64>, _timeout: f64) -> Result<f64, AppError> {
+            Ok(0.0)
+        }
+        
+        fn time_correction(&mut self, _timeout: f64) -> Result<f64, AppError> {
+            Ok(0.0)
+        }
+    }
+    
+    fn member(name: &str, sample_rate: f64, timestamps: Vec<f64>, signal: fn(f64) -> f64, output_rate: f64) -> FusionMember {
+        let chunks = timestamps.chunks(25)
+            .map(|chunk| (chunk.iter().map(|&t| vec![signal(t)]).collect(), chunk.to_vec()))
+            .collect();
+        let info = StreamInfo {
+            name: name.to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 1,
+            sample_rate,
+            is_connected: true,
+            source_id: name.to_string(),
+            channel_labels: vec!["Cz".to_string()],
+            effective_sample_rate: None,
+        };
+        FusionMember::new(Box::new(ScriptedInlet { chunks }), &info, output_rate).unwrap()
+    }
+    
+    #[test]
+    fn test_fused_inlet_aligns_and_resamples_by_timestamp() {
+        // A: 250Hz斜坡，1.0~1.2秒之间断流；B: 500Hz、10Hz正弦，起点晚1.3ms
+        let ramp = |t: f64| (t - 100.0) * 250.0;
+        let sine = |t: f64| (2.0 * PI * 10.0 * t).sin();
+        let a_times = (0..750).filter(|n| !(250..300).contains(n)).map(|n| 100.0 + n as f64 / 250.0).collect();
+        let b_times = (0..1500).map(|n| 100.0013 + n as f64 / 500.0).collect();
+        let mut inlet = FusedInlet::new(vec![
+            member("A", 250.0, a_times, ramp, 250.0),
+            member("B", 500.0, b_times, sine, 250.0),
+        ], 250.0);
+        
+        let mut values = Vec::new();
+        let mut timestamps = Vec::new();
+        for _ in 0..60 {
+            let (chunk_values, chunk_timestamps) = inlet.pull_chunk().unwrap();
+            values.extend(chunk_values);
+            timestamps.extend(chunk_timestamps);
+        }
+        assert!(timestamps.len() > 600, "{} samples", timestamps.len());
+        assert!((timestamps[0] - 100.0013).abs() < 1e-9);
+        
+        for (sample, &t) in values.iter().zip(&timestamps) {
+            assert_eq!(sample.len(), 2);
+            assert!((sample[0] - ramp(t)).abs() < 1e-6, "A at {}: {}", t, sample[0]);
+            // 跳过重采样器的启动瞬态
+            if t > 100.5 {
+                assert!((sample[1] - sine(t)).abs() < 0.03, "B at {}: {} vs {}", t, sample[1], sine(t));
+            }
+        }
+        
+        // 网格等间隔；A断流期间不插值，输出中留下间隙
+        let gaps: Vec<f64> = timestamps.windows(2).map(|pair| pair[1] - pair[0]).filter(|dt| (dt - 0.004).abs() > 1e-9).collect();
+        assert_eq!(gaps.len(), 1);
+        assert!(gaps[0] > 0.19 && gaps[0] < 0.21, "gap {}", gaps[0]);
+        
+        let mut buffer = vec![0.0; 2];
+        assert_eq!(inlet.pull_sample(&mut buffer, 0.01).unwrap(), 0.0);
+    }
+    
+    #[test]
+    fn test_fusion_source_builds_virtual_stream() {
+        let inner = MockSource::new()
+            .with_stream(MockStream::sine("AmpA", 4, 250.0))
+            .with_stream(MockStream::sine("AmpB", 4, 500.0));
+        let selector = |name: &str| StreamSelector { name: name.to_string(), source_id: None, hostname: None };
+        let config = FusionConfig { name: "Lab".to_string(), streams: vec![selector("AmpA"), selector("AmpB")], sample_rate: None };
+        config.validate().unwrap();
+        let mut source = FusionSource::new(Box::new(inner), config.clone());
+        
+        let connection = source.connect(&selector("Lab"), false, &LslInletConfig::default()).unwrap();
+        assert_eq!(connection.info.channels_count, 8);
+        assert_eq!(connection.info.sample_rate, 250.0);
+        assert_eq!(connection.info.source_id, "fusion_mock_AmpA+mock_AmpB");
+        assert_eq!(connection.info.channel_labels[..5], ["Ch1", "Ch2", "Ch3", "Ch4", "AmpB:Ch1"]);
+        assert!(connection.inlet.is_some());
+        
+        // 其他流照常连接
+        assert_eq!(source.connect(&selector("AmpB"), false, &LslInletConfig::default()).unwrap().info.channels_count, 4);
+        
+        let missing = FusionConfig { streams: vec![selector("AmpA"), selector("AmpC")], ..config.clone() };
+        let mut source = FusionSource::new(Box::new(MockSource::new().with_stream(MockStream::sine("AmpA", 4, 250.0))), missing);
+        let error = source.connect(&selector("Lab"), false, &LslInletConfig::default()).err().unwrap();
+        assert_eq!(error.code(), ErrorCode::StreamNotFound);
+        
+        assert!(FusionConfig { streams: vec![selector("AmpA")], ..config.clone() }.validate().is_err());
+        assert!(FusionConfig { streams: vec![selector("AmpA"), selector("AmpA")], ..config }.validate().is_err());
+    }
+}
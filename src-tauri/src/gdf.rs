@@ -0,0 +1,392 @@
+use crate::error::AppError;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+// GDF 数据类型编码：float32
+const GDFTYP_FLOAT32: u32 = 16;
+
+// 物理单位编码：μV（V = 4256，前缀 μ = 19）
+pub const GDF_DIMENSION_MICROVOLT: u16 = 4275;
+
+// 电极阻抗未知
+const IMPEDANCE_UNKNOWN: u8 = 0xFF;
+
+// 事件表模式3：含通道和持续时间
+const EVENT_TABLE_MODE: u8 = 3;
+
+// 事件类型 0x0001-0x00FF 为用户自定义，描述写入文件头3的事件描述表（标签1）
+const MAX_USER_EVENT_CODE: u16 = 0x00FF;
+const STANDARD_EVENT_CODE_MIN: u16 = 0x0100;
+const EVENT_DESCRIPTION_TAG: u8 = 1;
+
+// 为文件头3预留的块数（每块256字节），描述表在结束录制时才写入
+const HEADER3_BLOCKS: usize = 8;
+const HEADER3_BYTES: usize = HEADER3_BLOCKS * 256;
+
+// 文件头中"数据记录数"字段的偏移（int64）
+const NUM_RECORDS_OFFSET: u64 = 236;
+
+// MATLAB datenum 中 1970-01-01 对应的天数，GDF 时间以 1/2^32 天为单位
+const DATENUM_UNIX_EPOCH: f64 = 719_529.0;
+
+/// GDF信号参数
+#[derive(Clone, Debug)]
+pub struct GdfSignal {
+    pub label: String,
+    pub transducer: String,
+    pub physical_dimension: String,
+    pub dimension_code: u16,
+    pub prefilter: String,
+    pub highpass: Option<f32>,
+    pub lowpass: Option<f32>,
+    pub samples_per_record: usize,
+}
+
+/// GDF文件头中的受试者信息
+#[derive(Clone, Debug, Default)]
+pub struct GdfPatient {
+    pub id: String,
+    pub sex: Option<String>,
+    pub birthdate: Option<NaiveDate>,
+}
+
+/// GDF 2.20 写入器 - float32 数据记录，事件表在结束录制时写在数据之后
+///
+/// 数值标签（如 "769"）且不小于 0x0100 时直接作为事件类型，其余标签分配
+/// 用户自定义类型并写入事件描述表。异常退出时数据记录可用 checkpoint 保留，事件表会丢失。
+pub struct GdfWriter {
+    file: BufWriter<File>,
+    signals: Vec<GdfSignal>,
+    sample_rate: f64,
+    records_written: u64,
+    events: Vec<GdfEvent>,
+    descriptions: Vec<String>,
+    codes: HashMap<String, u16>,
+}
+
+struct GdfEvent {
+    position: u32,      // 从1开始的样本位置
+    code: u16,
+    duration: u32,      // 样本数
+}
+
+impl GdfWriter {
+    pub fn create(
+        filename: &str,
+        signals: Vec<GdfSignal>,
+        record_duration: f64,
+        sample_rate: f64,
+        start_time: DateTime<Utc>,
+        patient: &GdfPatient,
+        recording_id: &str,
+    ) -> Result<Self, AppError> {
+        if signals.is_empty() {
+            return Err(AppError::Recording("GDF file needs at least one signal".to_string()));
+        }
+        if signals.len() + 1 + HEADER3_BLOCKS > u16::MAX as usize {
+            return Err(AppError::Recording(format!("GDF header cannot describe {} signals", signals.len())));
+        }
+        
+        let file = File::create(filename)
+            .map_err(|e| AppError::Recording(format!("Failed to create GDF file: {}", e)))?;
+        
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            signals,
+            sample_rate,
+            records_written: 0,
+            events: Vec::new(),
+            descriptions: Vec::new(),
+            codes: HashMap::new(),
+        };
+        writer.write_header(record_duration, start_time, patient, recording_id)?;
+        
+        Ok(writer)
+    }
+    
+    fn write_header(&mut self, record_duration: f64, start_time: DateTime<Utc>, patient: &GdfPatient, recording_id: &str) -> Result<(), AppError> {
+        let signal_count = self.signals.len();
+        let header_blocks = 1 + signal_count + HEADER3_BLOCKS;
+        let (numerator, denominator) = duration_fraction(record_duration);
+        
+        let mut header = Vec::with_capacity(header_blocks * 256);
+        push_text(&mut header, "GDF 2.20", 8);
+        push_text(&mut header, &patient.id, 66);
+        header.extend_from_slice(&[0; 10]);
+        header.extend_from_slice(&[0; 3]);                  // 吸烟等状况、体重、身高：未知
+        header.push(match patient.sex.as_deref() {
+            Some("M") => 1,
+            Some("F") => 2,
+            _ => 0,
+        });
+        push_text(&mut header, recording_id, 64);
+        header.extend_from_slice(&[0; 16]);                 // 记录地点
+        header.extend_from_slice(&gdf_time(start_time.timestamp_millis() as f64 / 86_400_000.0).to_le_bytes());
+        let birthday = patient.birthdate
+            .map(|date| gdf_time(date.signed_duration_since(DateTime::UNIX_EPOCH.date_naive()).num_days() as f64))
+            .unwrap_or(0);
+        header.extend_from_slice(&birthday.to_le_bytes());
+        header.extend_from_slice(&(header_blocks as u16).to_le_bytes());
+        header.extend_from_slice(&[0; 6]);                  // 受试者分类
+        header.extend_from_slice(&[0; 8]);                  // 设备提供方ID
+        header.extend_from_slice(&[0; 6]);
+        header.extend_from_slice(&[0; 6]);                  // 头围尺寸
+        header.extend_from_slice(&[0; 24]);                 // 参考/接地电极位置
+        header.extend_from_slice(&(-1i64).to_le_bytes());   // 完成时回填
+        header.extend_from_slice(&numerator.to_le_bytes());
+        header.extend_from_slice(&denominator.to_le_bytes());
+        header.extend_from_slice(&(signal_count as u16).to_le_bytes());
+        header.extend_from_slice(&[0; 2]);
+        debug_assert_eq!(header.len(), 256);
+        
+        // float32 数据不需要换算，物理范围与数字范围相同
+        let limit = f32::MAX as f64;
+        let signals = &self.signals;
+        for signal in signals { push_text(&mut header, &signal.label, 16); }
+        for signal in signals { push_text(&mut header, &signal.transducer, 80); }
+        for signal in signals { push_text(&mut header, &signal.physical_dimension, 6); }
+        for signal in signals { header.extend_from_slice(&signal.dimension_code.to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&(-limit).to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&limit.to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&(-limit).to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&limit.to_le_bytes()); }
+        for signal in signals { push_text(&mut header, &signal.prefilter, 68); }
+        for signal in signals { header.extend_from_slice(&signal.lowpass.unwrap_or(f32::NAN).to_le_bytes()); }
+        for signal in signals { header.extend_from_slice(&signal.highpass.unwrap_or(f32::NAN).to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&f32::NAN.to_le_bytes()); }
+        for signal in signals { header.extend_from_slice(&(signal.samples_per_record as u32).to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&GDFTYP_FLOAT32.to_le_bytes()); }
+        for _ in signals { header.extend_from_slice(&[0; 12]); }
+        header.resize(header.len() + signals.len(), IMPEDANCE_UNKNOWN);
+        for _ in signals { header.extend_from_slice(&[0; 19]); }
+        
+        // 文件头3先以零填充（零标签即结束），结束录制时写入事件描述表
+        header.resize(header_blocks * 256, 0);
+        
+        self.file.write_all(&header)
+            .map_err(|e| AppError::Recording(format!("Failed to write GDF header: {}", e)))
+    }
+    
+    /// 记录一个事件，结束录制时写入事件表
+    pub fn add_annotation(&mut self, onset: f64, duration: Option<f64>, description: &str) -> Result<(), AppError> {
+        let code = self.event_code(description)?;
+        let position = (onset.max(0.0) * self.sample_rate).round() + 1.0;
+        let duration = duration.map_or(0.0, |duration| (duration.max(0.0) * self.sample_rate).round());
+        
+        self.events.push(GdfEvent {
+            position: position.min(u32::MAX as f64) as u32,
+            code,
+            duration: duration.min(u32::MAX as f64) as u32,
+        });
+        Ok(())
+    }
+    
+    /// 标签对应的事件类型；新的文本标签按出现顺序分配用户自定义类型
+    fn event_code(&mut self, description: &str) -> Result<u16, AppError> {
+        let description = description.trim().replace('\0', " ");
+        if let Ok(code) = description.parse::<u16>() {
+            if code >= STANDARD_EVENT_CODE_MIN {
+                return Ok(code);
+            }
+        }
+        if let Some(code) = self.codes.get(&description) {
+            return Ok(*code);
+        }
+        
+        if self.descriptions.len() >= MAX_USER_EVENT_CODE as usize
+            || self.description_table_len() + description.len() + 1 > HEADER3_BYTES {
+            return Err(AppError::Recording(format!(
+                "GDF event description table is full, cannot add '{}'", description
+            )));
+        }
+        
+        self.descriptions.push(description.clone());
+        let code = self.descriptions.len() as u16;
+        self.codes.insert(description, code);
+        Ok(code)
+    }
+    
+    /// 标签1：标签(1字节) + 长度(3字节) + 以\0结尾的描述，末尾零标签结束文件头3
+    fn description_table_len(&self) -> usize {
+        4 + self.descriptions.iter().map(|d| d.len() + 1).sum::<usize>() + 1
+    }
+    
+    /// 写入一个数据记录，record[i] 为第i个信号的物理值
+    pub fn write_samples(&mut self, record: &[Vec<f64>]) -> Result<(), AppError> {
+        if record.len() != self.signals.len() {
+            return Err(AppError::Recording(format!(
+                "Record has {} signals, GDF header declares {}", record.len(), self.signals.len()
+            )));
+        }
+        
+        let mut bytes = Vec::with_capacity(self.signals.iter().map(|s| s.samples_per_record * 4).sum());
+        for (signal, samples) in self.signals.iter().zip(record) {
+            for i in 0..signal.samples_per_record {
+                let value = samples.get(i).copied().unwrap_or(0.0) as f32;
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        
+        self.file.write_all(&bytes)
+            .map_err(|e| AppError::Recording(format!("Failed to write GDF data record: {}", e)))?;
+        self.records_written += 1;
+        
+        Ok(())
+    }
+    
+    /// 检查点：回填当前数据记录数并同步到磁盘
+    pub fn checkpoint(&mut self) -> Result<(), AppError> {
+        self.write_record_count()
+            .and_then(|_| self.file.seek(SeekFrom::End(0)))
+            .and_then(|_| self.file.get_ref().sync_data())
+            .map_err(|e| AppError::Recording(format!("Failed to checkpoint GDF file: {}", e)))
+    }
+    
+    /// 在数据之后写入事件表，回填事件描述表和数据记录数并关闭文件
+    pub fn finalize(mut self) -> Result<(), AppError> {
+        self.write_events()
+            .and_then(|_| self.write_descriptions())
+            .and_then(|_| self.write_record_count())
+            .map_err(|e| AppError::Recording(format!("Failed to finalize GDF file: {}", e)))
+    }
+    
+    fn write_events(&mut self) -> std::io::Result<()> {
+        let count = self.events.len().min(0x00FF_FFFF);
+        let mut table = Vec::with_capacity(8 + count * 12);
+        table.push(EVENT_TABLE_MODE);
+        table.extend_from_slice(&(count as u32).to_le_bytes()[..3]);
+        table.extend_from_slice(&(self.sample_rate as f32).to_le_bytes());
+        
+        let events = &self.events[..count];
+        for event in events { table.extend_from_slice(&event.position.to_le_bytes()); }
+        for event in events { table.extend_from_slice(&event.code.to_le_bytes()); }
+        for _ in events { table.extend_from_slice(&0u16.to_le_bytes()); }     // 0 = 所有通道
+        for event in events { table.extend_from_slice(&event.duration.to_le_bytes()); }
+        
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&table)
+    }
+    
+    fn write_descriptions(&mut self) -> std::io::Result<()> {
+        if self.descriptions.is_empty() {
+            return Ok(());
+        }
+        
+        let mut value = Vec::new();
+        for description in &self.descriptions {
+            value.extend_from_slice(description.as_bytes());
+            value.push(0);
+        }
+        let mut table = Vec::with_capacity(self.description_table_len());
+        table.push(EVENT_DESCRIPTION_TAG);
+        table.extend_from_slice(&(value.len() as u32).to_le_bytes()[..3]);
+        table.extend_from_slice(&value);
+        table.push(0);
+        
+        self.file.seek(SeekFrom::Start(256 * (1 + self.signals.len() as u64)))?;
+        self.file.write_all(&table)
+    }
+    
+    /// 写出缓冲数据并将已写入的记录数填入文件头
+    fn write_record_count(&mut self) -> std::io::Result<()> {
+        self.file.seek(SeekFrom::Start(NUM_RECORDS_OFFSET))?;
+        self.file.write_all(&(self.records_written as i64).to_le_bytes())?;
+        self.file.flush()
+    }
+}
+
+/// 以零补齐的文本字段，超长截断
+fn push_text(buffer: &mut Vec<u8>, value: &str, width: usize) {
+    let mut bytes: Vec<u8> = value.bytes().filter(|b| b.is_ascii()).take(width).collect();
+    bytes.resize(width, 0);
+    buffer.extend_from_slice(&bytes);
+}
+
+/// 自1970-01-01起的天数转换为GDF时间（MATLAB datenum × 2^32）
+fn gdf_time(unix_days: f64) -> u64 {
+    ((DATENUM_UNIX_EPOCH + unix_days) * 4_294_967_296.0).round() as u64
+}
+
+/// 数据记录时长写成分数（分子, 分母），精确到微秒
+fn duration_fraction(seconds: f64) -> (u32, u32) {
+    let mut numerator = (seconds * 1_000_000.0).round().max(1.0) as u64;
+    let mut denominator = 1_000_000u64;
+    let divisor = gcd(numerator, denominator);
+    numerator /= divisor;
+    denominator /= divisor;
+    (numerator.min(u32::MAX as u64) as u32, denominator as u32)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+    
+    #[test]
+    fn test_gdf_header_samples_and_events() {
+        let path = std::env::temp_dir().join(format!("gdf_writer_test_{}.gdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        
+        let signal = |label: &str| GdfSignal {
+            label: label.to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            dimension_code: GDF_DIMENSION_MICROVOLT,
+            prefilter: String::new(),
+            highpass: None,
+            lowpass: None,
+            samples_per_record: 2,
+        };
+        let patient = GdfPatient { id: "P01".to_string(), sex: Some("F".to_string()), birthdate: None };
+        let start = DateTime::from_timestamp(0, 0).unwrap();
+        
+        let mut writer = GdfWriter::create(&filename, vec![signal("C3"), signal("C4")], 0.5, 4.0, start, &patient, "Startdate X X X X").unwrap();
+        writer.add_annotation(0.25, None, "769").unwrap();
+        writer.add_annotation(0.5, Some(0.5), "rest").unwrap();
+        writer.add_annotation(0.75, None, "rest").unwrap();
+        writer.write_samples(&[vec![1.5, -2.0], vec![3.0, 4.0]]).unwrap();
+        writer.finalize().unwrap();
+        
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        assert_eq!(&bytes[..8], b"GDF 2.20");
+        assert!(bytes[8..74].starts_with(b"P01\0"));
+        assert_eq!(bytes[87], 2);
+        assert_eq!(u64::from_le_bytes(bytes[168..176].try_into().unwrap()), 719_529u64 << 32);
+        let header_blocks = u16::from_le_bytes([bytes[184], bytes[185]]) as usize;
+        assert_eq!(header_blocks, 3 + HEADER3_BLOCKS);
+        assert_eq!(i64::from_le_bytes(bytes[236..244].try_into().unwrap()), 1);
+        assert_eq!((u32_at(&bytes, 244), u32_at(&bytes, 248)), (1, 2));
+        assert_eq!(u16::from_le_bytes([bytes[252], bytes[253]]), 2);
+        assert_eq!(&bytes[256..258], b"C3");
+        
+        // 文件头3：事件描述表
+        assert_eq!(&bytes[768..777], b"\x01\x05\0\0rest\0");
+        
+        let data = header_blocks * 256;
+        let values: Vec<f32> = bytes[data..data + 16].chunks(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, vec![1.5, -2.0, 3.0, 4.0]);
+        
+        let events = &bytes[data + 16..];
+        assert_eq!(events[0], EVENT_TABLE_MODE);
+        assert_eq!(u32::from_le_bytes([events[1], events[2], events[3], 0]), 3);
+        assert_eq!(f32::from_le_bytes(events[4..8].try_into().unwrap()), 4.0);
+        assert_eq!((u32_at(events, 8), u32_at(events, 12), u32_at(events, 16)), (2, 3, 4));
+        let codes: Vec<u16> = events[20..26].chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        assert_eq!(codes, vec![769, 1, 1]);
+        assert_eq!(u32_at(events, 32 + 4), 2);
+        assert_eq!(events.len(), 8 + 3 * 12);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::data_types::StreamInfo;
+use crate::error::AppError;
+use hdf5::types::VarLenUnicode;
+use hdf5::{Dataset, File, H5Type};
+use ndarray::{ArrayView1, ArrayView2};
+use tracing::info;
+
+// 每个数据块的行数（样本数）上限，实际取 1 秒数据
+const MAX_CHUNK_ROWS: usize = 4096;
+// gzip压缩等级
+const DEFLATE_LEVEL: u8 = 4;
+
+/// 标记表的一行
+#[derive(H5Type, Clone, Debug)]
+#[repr(C)]
+struct MarkerRow {
+    onset: f64,
+    duration: f64, // 无持续时间时为 0
+    description: VarLenUnicode,
+}
+
+/// HDF5 写入器 - /eeg (样本 × 通道, f32) 与 /timestamps 为分块压缩数据集，
+/// 通道标签与采样率写入属性，标记在关闭时写入 /markers 表
+pub struct Hdf5Writer {
+    file: File,
+    eeg: Dataset,
+    timestamps: Dataset,
+    channels_count: usize,
+    chunk_rows: usize,
+    rows_written: usize,
+    pending_samples: Vec<f32>,
+    pending_timestamps: Vec<f64>,
+    markers: Vec<MarkerRow>,
+}
+
+impl Hdf5Writer {
+    pub fn create(filename: &str, stream_info: &StreamInfo, channel_labels: &[String]) -> Result<Self, AppError> {
+        let channels_count = stream_info.channels_count as usize;
+        let chunk_rows = (stream_info.sample_rate.round() as usize).clamp(1, MAX_CHUNK_ROWS);
+        
+        let file = File::create(filename).map_err(h5_error)?;
+        
+        let eeg = file.new_dataset::<f32>()
+            .chunk((chunk_rows, channels_count))
+            .deflate(DEFLATE_LEVEL)
+            .shape((0.., channels_count))
+            .create("eeg")
+            .map_err(h5_error)?;
+        
+        let timestamps = file.new_dataset::<f64>()
+            .chunk(chunk_rows)
+            .deflate(DEFLATE_LEVEL)
+            .shape(0..)
+            .create("timestamps")
+            .map_err(h5_error)?;
+        
+        // 元数据
+        let labels: Vec<VarLenUnicode> = channel_labels
+            .iter()
+            .map(|label| label.parse().unwrap_or_default())
+            .collect();
+        eeg.new_attr::<VarLenUnicode>().shape(labels.len()).create("channel_labels")
+            .and_then(|attr| attr.write(&labels))
+            .map_err(h5_error)?;
+        eeg.new_attr::<f64>().create("sample_rate")
+            .and_then(|attr| attr.write_scalar(&stream_info.sample_rate))
+            .map_err(h5_error)?;
+        
+        let unit: VarLenUnicode = "uV".parse().unwrap_or_default();
+        eeg.new_attr::<VarLenUnicode>().create("unit")
+            .and_then(|attr| attr.write_scalar(&unit))
+            .map_err(h5_error)?;
+        
+        let stream_name: VarLenUnicode = stream_info.name.parse().unwrap_or_default();
+        file.new_attr::<VarLenUnicode>().create("stream_name")
+            .and_then(|attr| attr.write_scalar(&stream_name))
+            .map_err(h5_error)?;
+        
+        Ok(Self {
+            file,
+            eeg,
+            timestamps,
+            channels_count,
+            chunk_rows,
+            rows_written: 0,
+            pending_samples: Vec::with_capacity(chunk_rows * channels_count),
+            pending_timestamps: Vec::with_capacity(chunk_rows),
+            markers: Vec::new(),
+        })
+    }
+    
+    pub fn write_row(&mut self, timestamp: f64, channels: &[f64]) -> Result<(), AppError> {
+        // 通道数不一致时补零/截断，保持矩阵形状
+        for ch_idx in 0..self.channels_count {
+            self.pending_samples.push(channels.get(ch_idx).copied().unwrap_or(0.0) as f32);
+        }
+        self.pending_timestamps.push(timestamp);
+        
+        if self.pending_timestamps.len() >= self.chunk_rows {
+            self.flush_rows()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 将缓冲的样本追加到数据集末尾
+    fn flush_rows(&mut self) -> Result<(), AppError> {
+        let rows = self.pending_timestamps.len();
+        if rows == 0 {
+            return Ok(());
+        }
+        
+        let start = self.rows_written;
+        let end = start + rows;
+        
+        let samples = ArrayView2::from_shape((rows, self.channels_count), &self.pending_samples)
+            .map_err(|e| AppError::Recording(format!("Invalid HDF5 block shape: {}", e)))?;
+        
+        self.eeg.resize((end, self.channels_count)).map_err(h5_error)?;
+        self.eeg.write_slice(samples, (start..end, ..)).map_err(h5_error)?;
+        
+        self.timestamps.resize(end).map_err(h5_error)?;
+        self.timestamps.write_slice(ArrayView1::from(&self.pending_timestamps[..]), start..end)
+            .map_err(h5_error)?;
+        
+        self.rows_written = end;
+        self.pending_samples.clear();
+        self.pending_timestamps.clear();
+        
+        Ok(())
+    }
+    
+    pub fn add_annotation(&mut self, onset: f64, duration: Option<f64>, description: &str) -> Result<(), AppError> {
+        let description = description.parse::<VarLenUnicode>()
+            .map_err(|e| AppError::Recording(format!("Invalid annotation text: {}", e)))?;
+        
+        self.markers.push(MarkerRow {
+            onset,
+            duration: duration.unwrap_or(0.0),
+            description,
+        });
+        
+        Ok(())
+    }
+    
+    /// 检查点：写出缓冲的样本（标记在关闭时写入）
+    pub fn checkpoint(&mut self) -> Result<(), AppError> {
+        self.flush_rows()?;
+        self.file.flush().map_err(h5_error)
+    }
+    
+    pub fn finalize(mut self) -> Result<(), AppError> {
+        self.flush_rows()?;
+        
+        let markers = self.file.new_dataset::<MarkerRow>()
+            .shape(self.markers.len())
+            .create("markers")
+            .map_err(h5_error)?;
+        if !self.markers.is_empty() {
+            markers.write(&self.markers[..]).map_err(h5_error)?;
+        }
+        
+        self.file.flush().map_err(h5_error)?;
+        
+        info!("✅ HDF5 file closed: {} rows, {} markers", self.rows_written, self.markers.len());
+        
+        Ok(())
+    }
+}
+
+fn h5_error(e: hdf5::Error) -> AppError {
+    AppError::Recording(format!("HDF5 error: {}", e))
+}
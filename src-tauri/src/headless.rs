@@ -0,0 +1,247 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::capture::Capture;
+use crate::data_types::*;
+use crate::eeg_processor::EegProcessor;
+use crate::error::AppError;
+use crate::fft_processor;
+use crate::logging::Logging;
+use crate::lsl_manager::{LslManager, LslSource};
+use crate::recorder::RecordingStats;
+use crate::session::StreamEmitter;
+use crate::stream_source::StreamSource;
+
+// 回放时等待分发器取空队列的轮询间隔
+const REPLAY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 无界面录制请求 - 供 cortexctl 等不启动Tauri的前端使用
+#[derive(Clone, Debug, Default)]
+pub struct RecordRequest {
+    pub stream: StreamSelector,
+    pub marker_stream: Option<String>,
+    pub filename: String,
+    pub options: RecordingOptions,
+    pub duration: Option<Duration>,     // None 时录制到 Ctrl-C
+    pub capture: Option<String>,        // 同时把原始样本流采集到该文件（.cxcap）
+}
+
+/// 回放请求 - 把采集文件确定性地送入 EegProcessor
+#[derive(Clone, Debug, Default)]
+pub struct ReplayRequest {
+    pub capture: String,
+    pub filename: Option<String>,       // 回放时录制到该文件，None 不录制
+    pub options: RecordingOptions,
+    pub notch: NotchFilterConfig,
+    pub filters: FilterConfig,
+    pub fft: Option<FftConfig>,         // 同时逐帧计算幅值谱
+}
+
+/// 回放结果 - 只含与运行时刻无关的字段，可保存为金标准文件供CI比对
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplayReport {
+    pub samples_replayed: u64,
+    pub markers_replayed: u64,
+    pub samples_written: u64,
+    pub markers_written: u64,
+    pub checksums: Vec<Option<String>>, // 录制文件的 SHA-256（CSV/TSV 文件头不含录制时刻）
+    pub spectra: Vec<SpectrumFrame>,
+}
+
+impl ReplayReport {
+    /// 与金标准比对：计数与校验和须完全一致，谱值允许相对误差 tolerance（不同CPU上FFT末位可能不同）
+    pub fn compare(&self, golden: &ReplayReport, tolerance: f64) -> Result<(), String> {
+        let counts = |report: &ReplayReport| {
+            (report.samples_replayed, report.markers_replayed, report.samples_written, report.markers_written)
+        };
+        if counts(self) != counts(golden) {
+            return Err(format!("Counts {:?} differ from golden {:?}", counts(self), counts(golden)));
+        }
+        if self.checksums != golden.checksums {
+            return Err(format!("Recording checksums {:?} differ from golden {:?}", self.checksums, golden.checksums));
+        }
+        if self.spectra.len() != golden.spectra.len() {
+            return Err(format!("{} spectrum frames, golden has {}", self.spectra.len(), golden.spectra.len()));
+        }
+        
+        for (index, (frame, expected)) in self.spectra.iter().zip(&golden.spectra).enumerate() {
+            let same_shape = frame.timestamp == expected.timestamp
+                && frame.resolution == expected.resolution
+                && frame.magnitudes.len() == expected.magnitudes.len()
+                && frame.magnitudes.iter().zip(&expected.magnitudes).all(|(a, b)| a.len() == b.len());
+            if !same_shape {
+                return Err(format!("Spectrum frame {} differs in timestamp, resolution or shape", index));
+            }
+            for (channel, (values, expected)) in frame.magnitudes.iter().zip(&expected.magnitudes).enumerate() {
+                for (bin, (value, expected)) in values.iter().zip(expected).enumerate() {
+                    if (value - expected).abs() > tolerance * expected.abs().max(1.0) {
+                        return Err(format!(
+                            "Spectrum frame {} channel {} bin {}: {} differs from golden {}", index, channel, bin, value, expected
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 安装控制台日志订阅器
+pub fn init_logging() {
+    Logging::init();
+}
+
+/// 发现LSL流
+pub async fn list_streams(filter: StreamFilter) -> Result<Vec<LslStreamInfo>, AppError> {
+    let mut manager = LslManager::new();
+    manager.start().await?;
+    let result = manager.discover_streams(filter).await;
+    manager.stop().await?;
+    result
+}
+
+/// 连接流并录制，直到达到时长或收到 Ctrl-C
+///
+/// 与GUI共用 LslManager / EegProcessor，处理管道的事件不发送到前端。
+pub async fn record(request: RecordRequest) -> Result<Option<RecordingStats>, AppError> {
+    record_with_source(Box::new(LslSource), request).await
+}
+
+/// 从指定数据源（如 stream_source::MockSource）录制，其余流程与 record 相同
+pub async fn record_with_source(source: Box<dyn StreamSource>, request: RecordRequest) -> Result<Option<RecordingStats>, AppError> {
+    request.options.validate()?;
+    
+    let mut manager = LslManager::with_source(source);
+    manager.start().await?;
+    
+    let stream_info = match manager.connect_to_stream(&request.stream).await {
+        Ok(info) => info,
+        Err(e) => {
+            manager.stop().await?;
+            return Err(e);
+        }
+    };
+    info!("✅ Connected to stream: {} ({} channels @ {}Hz)",
+             stream_info.name, stream_info.channels_count, stream_info.sample_rate);
+    
+    if let Some(name) = request.marker_stream.as_deref() {
+        let marker_info = manager.connect_marker_stream(name).await?;
+        info!("🏷️  Recording markers from '{}'", marker_info.name);
+    }
+    
+    let channels_lost = || AppError::Channel("LSL manager receivers already taken".to_string());
+    let data_rx = manager.get_data_receiver().ok_or_else(channels_lost)?;
+    let marker_rx = manager.get_marker_receiver().ok_or_else(channels_lost)?;
+    let aux_rx = manager.get_aux_receiver().ok_or_else(channels_lost)?;
+    let clock_rx = manager.get_clock_receiver().ok_or_else(channels_lost)?;
+    let source_events = manager.get_source_event_receiver().ok_or_else(channels_lost)?;
+    
+    let mut processor = EegProcessor::new(stream_info, StreamEmitter::headless())?;
+    processor.set_data_source(data_rx);
+    processor.set_source_stats(manager.pull_stats());
+    processor.set_marker_source(marker_rx);
+    processor.set_aux_source(aux_rx);
+    processor.set_clock_source(clock_rx);
+    processor.start().await?;
+    
+    if let Some(capture) = request.capture.as_deref() {
+        processor.start_capture(capture).await?;
+    }
+    processor.start_recording(&request.filename, request.options.clone(), Vec::new()).await?;
+    info!("🔴 Recording to {} ({})", request.filename,
+          request.duration.map_or("until Ctrl-C".to_string(), |duration| format!("{:.0}s", duration.as_secs_f64())));
+    
+    let deadline = async {
+        match request.duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+    let source_watch = async {
+        let mut ticker = tokio::time::interval(Duration::from_millis(200));
+        loop {
+            ticker.tick().await;
+            for event in source_events.try_iter() {
+                match event {
+                    SourceEvent::Lost => warn!("⚠️ Stream lost, waiting for it to recover"),
+                    SourceEvent::Recovered => info!("✅ Stream recovered"),
+                    _ => {}
+                }
+            }
+        }
+    };
+    
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("⚠️ Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    
+    tokio::select! {
+        _ = deadline => info!("⏱️  Recording duration reached"),
+        _ = interrupt => info!("⏹️  Interrupted, finishing recording"),
+        _ = source_watch => {}
+    }
+    
+    let stats = processor.stop().await?;
+    manager.stop().await?;
+    
+    Ok(stats.recording_stats)
+}
+
+/// 回放采集文件：样本块按采集顺序全部送入处理器（不按实时节奏），录制输出与谱只由文件内容和配置决定
+///
+/// 标记先于样本送入，分发器在第一个样本块时取走全部标记，对齐结果与线程调度无关。
+pub async fn replay(request: ReplayRequest) -> Result<ReplayReport, AppError> {
+    request.options.validate()?;
+    let capture = Capture::read(&request.capture)?;
+    let stream_info = capture.stream_info.clone();
+    info!("⏯️  Replaying {} ({} samples, {} markers)", request.capture, capture.samples_count(), capture.markers.len());
+    
+    let spectra = match &request.fft {
+        Some(config) => {
+            config.validate()?;
+            fft_processor::offline_spectra(capture.samples(), stream_info.channels_count as usize, stream_info.sample_rate, config)
+        }
+        None => Vec::new(),
+    };
+    
+    let (data_tx, data_rx) = crossbeam_channel::unbounded();
+    let (marker_tx, marker_rx) = crossbeam_channel::unbounded();
+    let mut processor = EegProcessor::new(stream_info, StreamEmitter::headless())?;
+    processor.set_notch_filter(request.notch).await?;
+    processor.set_filters(request.filters).await?;
+    processor.set_data_source(data_rx.clone());
+    processor.set_marker_source(marker_rx);
+    processor.start().await?;
+    if let Some(filename) = request.filename.as_deref() {
+        processor.start_recording(filename, request.options.clone(), Vec::new()).await?;
+    }
+    
+    let samples_replayed = capture.samples_count();
+    let markers_replayed = capture.markers.len() as u64;
+    for marker in capture.markers {
+        marker_tx.send(marker)?;
+    }
+    for chunk in capture.chunks {
+        data_tx.send(chunk)?;
+    }
+    drop(data_tx);
+    drop(marker_tx);
+    
+    // 分发器取完队列后再停止：停止后不再取新块，已分发的块由录制线程写完
+    while !data_rx.is_empty() {
+        tokio::time::sleep(REPLAY_POLL_INTERVAL).await;
+    }
+    let recording = processor.stop().await?.recording_stats;
+    
+    Ok(ReplayReport {
+        samples_replayed,
+        markers_replayed,
+        samples_written: recording.as_ref().map_or(0, |stats| stats.samples_written),
+        markers_written: recording.as_ref().map_or(0, |stats| stats.markers_written),
+        checksums: recording.map(|stats| stats.checksums).unwrap_or_default(),
+        spectra,
+    })
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use crate::data_types::*;
+
+/// 原始样本环形缓冲 - 按样本数限长，超出时丢弃最旧的样本
+pub struct SampleHistory {
+    config: HistoryConfig,
+    samples: VecDeque<EegSample>,
+    capacity: usize,
+    sample_rate: f64,
+}
+
+impl SampleHistory {
+    pub fn new(config: HistoryConfig, sample_rate: f64) -> Self {
+        let capacity = Self::capacity_for(config, sample_rate);
+        Self {
+            config,
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            sample_rate,
+        }
+    }
+    
+    fn capacity_for(config: HistoryConfig, sample_rate: f64) -> usize {
+        ((config.seconds * sample_rate).ceil() as usize).max(1)
+    }
+    
+    pub fn config(&self) -> HistoryConfig {
+        self.config
+    }
+    
+    /// 调整缓冲长度，缩短时丢弃最旧的样本
+    pub fn resize(&mut self, config: HistoryConfig) {
+        self.config = config;
+        self.capacity = Self::capacity_for(config, self.sample_rate);
+        self.trim();
+    }
+    
+    pub fn extend<'a>(&mut self, samples: impl IntoIterator<Item = &'a EegSample>) {
+        self.samples.extend(samples.into_iter().cloned());
+        self.trim();
+    }
+    
+    fn trim(&mut self) {
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+    
+    /// 缓冲中已有的时长（秒）
+    pub fn buffered_seconds(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate
+    }
+    
+    /// 最近 seconds 秒的样本（不足时返回全部）
+    pub fn last_seconds(&self, seconds: f64) -> impl Iterator<Item = &EegSample> {
+        let count = ((seconds.max(0.0) * self.sample_rate).round() as usize).min(self.samples.len());
+        self.samples.range(self.samples.len() - count..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_history_keeps_most_recent_samples() {
+        let mut history = SampleHistory::new(HistoryConfig { seconds: 2.0 }, 10.0);
+        let samples: Vec<EegSample> = (0..50)
+            .map(|i| EegSample { timestamp: i as f64 / 10.0, channels: vec![i as f64], sample_id: i })
+            .collect();
+        
+        history.extend(&samples);
+        assert_eq!(history.buffered_seconds(), 2.0);
+        
+        let last: Vec<u64> = history.last_seconds(0.5).map(|s| s.sample_id).collect();
+        assert_eq!(last, vec![45, 46, 47, 48, 49]);
+        assert_eq!(history.last_seconds(10.0).count(), 20);
+        
+        history.resize(HistoryConfig { seconds: 1.0 });
+        assert_eq!(history.last_seconds(10.0).next().map(|s| s.sample_id), Some(40));
+    }
+}
@@ -0,0 +1,246 @@
+use nalgebra::{DMatrix, DVector};
+use crate::data_types::*;
+
+// 峰度估计的平滑系数（按块）
+const KURTOSIS_SMOOTHING: f64 = 0.1;
+
+/// 在线ICA - 递归白化 + 扩展Infomax自然梯度（ORICA思路的块更新版本）
+///
+/// 白化矩阵来自指数遗忘的协方差估计，解混矩阵在白化空间中按块更新并保持正交，
+/// 每个成分根据峰度符号在超高斯/亚高斯非线性之间切换。
+/// 成分编号在更新间保持连续，用户拒绝的成分在显示信号中被减去。
+pub struct OnlineIca {
+    config: IcaConfig,
+    sample_rate: f64,
+    channel_labels: Vec<String>,
+    channels: usize,
+    alpha: f64,
+    mean: DVector<f64>,
+    covariance: DMatrix<f64>,
+    samples_seen: usize,
+    weights: DMatrix<f64>,          // 白化空间中的正交解混矩阵
+    fourth_moments: Vec<f64>,
+    block: Vec<DVector<f64>>,
+    unmixing: Option<DMatrix<f64>>,
+    mixing: DMatrix<f64>,
+    rejected: Vec<bool>,
+    time_courses: Vec<Vec<f32>>,
+    update_every: usize,
+    since_update: usize,
+}
+
+impl OnlineIca {
+    pub fn new(config: IcaConfig, sample_rate: f64, channel_labels: Vec<String>) -> Self {
+        let channels = channel_labels.len();
+        let sample_rate = sample_rate.max(1.0);
+        
+        Self {
+            config,
+            sample_rate,
+            channel_labels,
+            channels,
+            alpha: 1.0 / (config.memory_seconds * sample_rate),
+            mean: DVector::zeros(channels),
+            covariance: DMatrix::zeros(channels, channels),
+            samples_seen: 0,
+            weights: DMatrix::identity(channels, channels),
+            fourth_moments: vec![3.0; channels],
+            block: Vec::with_capacity(config.block_size),
+            unmixing: None,
+            mixing: DMatrix::identity(channels, channels),
+            rejected: vec![false; channels],
+            time_courses: vec![Vec::new(); channels],
+            update_every: ((config.update_interval_ms as f64 / 1000.0 * sample_rate) as usize).max(1),
+            since_update: 0,
+        }
+    }
+    
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+    
+    /// 设置显示时置零的成分，超出范围的编号忽略
+    pub fn set_rejected(&mut self, components: &[u32]) {
+        self.rejected = vec![false; self.channels];
+        for &component in components {
+            if let Some(flag) = self.rejected.get_mut(component as usize) {
+                *flag = true;
+            }
+        }
+    }
+    
+    /// 加入一个样本并原地去除被拒绝的成分，到推送间隔时返回成分更新
+    pub fn push(&mut self, channels: &mut [f64]) -> Option<IcaUpdate> {
+        if channels.len() != self.channels || self.channels == 0 {
+            return None;
+        }
+        
+        // 预热阶段用算术平均，之后切换为指数遗忘
+        self.samples_seen += 1;
+        let rate = (1.0 / self.samples_seen as f64).max(self.alpha);
+        let x = DVector::from_column_slice(channels);
+        self.mean += (&x - &self.mean) * rate;
+        let centered = &x - &self.mean;
+        self.covariance = &self.covariance * (1.0 - rate) + &centered * centered.transpose() * rate;
+        
+        // 至少一秒数据后才开始学习
+        if self.samples_seen as f64 >= self.sample_rate {
+            self.block.push(centered.clone());
+            if self.block.len() >= self.config.block_size {
+                self.learn_block();
+            }
+        }
+        
+        let unmixing = self.unmixing.as_ref()?;
+        let components = unmixing * &centered;
+        
+        for (course, value) in self.time_courses.iter_mut().zip(components.iter()) {
+            course.push(*value as f32);
+        }
+        
+        if self.rejected.iter().any(|&rejected| rejected) {
+            let removed = DVector::from_iterator(self.channels, components.iter().zip(&self.rejected)
+                .map(|(value, &rejected)| if rejected { *value } else { 0.0 }));
+            let artifact = &self.mixing * removed;
+            for (value, artifact) in channels.iter_mut().zip(artifact.iter()) {
+                *value -= artifact;
+            }
+        }
+        
+        self.since_update += 1;
+        if self.since_update < self.update_every {
+            return None;
+        }
+        self.since_update = 0;
+        
+        Some(IcaUpdate {
+            channel_labels: self.channel_labels.clone(),
+            scalp_weights: self.mixing.column_iter()
+                .map(|column| column.iter().map(|&w| w as f32).collect())
+                .collect(),
+            time_courses: self.time_courses.iter_mut().map(std::mem::take).collect(),
+            sample_rate: self.sample_rate,
+            rejected: self.rejected.iter().enumerate()
+                .filter(|(_, &rejected)| rejected)
+                .map(|(component, _)| component as u32)
+                .collect(),
+        })
+    }
+    
+    fn learn_block(&mut self) {
+        let whitening = inverse_sqrt(&self.covariance);
+        let whitened = DMatrix::from_columns(&self.block);
+        let whitened = &whitening * whitened;
+        let count = self.block.len() as f64;
+        self.block.clear();
+        
+        let outputs = &self.weights * &whitened;
+        
+        // 峰度符号：正为超高斯（tanh），负为亚高斯
+        for (component, moment) in self.fourth_moments.iter_mut().enumerate() {
+            let block_moment = outputs.row(component).iter().map(|y| y.powi(4)).sum::<f64>() / count;
+            *moment += (block_moment - *moment) * KURTOSIS_SMOOTHING;
+        }
+        let signs = DMatrix::from_diagonal(&DVector::from_iterator(self.channels,
+            self.fourth_moments.iter().map(|&m| if m >= 3.0 { 1.0 } else { -1.0 })));
+        
+        // 扩展Infomax：ΔW = μ (I - K·tanh(Y)Yᵀ - YYᵀ) W
+        let nonlinear = outputs.map(f64::tanh);
+        let gradient = DMatrix::identity(self.channels, self.channels)
+            - (signs * nonlinear * outputs.transpose() + &outputs * outputs.transpose()) / count;
+        let updated = &self.weights + gradient * &self.weights * self.config.learning_rate;
+        
+        // 对称去相关保持正交，防止发散
+        self.weights = inverse_sqrt(&(&updated * updated.transpose())) * updated;
+        
+        let unmixing = &self.weights * whitening;
+        if let Ok(mixing) = unmixing.clone().pseudo_inverse(1e-10) {
+            self.mixing = mixing;
+            self.unmixing = Some(unmixing);
+        }
+    }
+}
+
+/// 对称正定矩阵的逆平方根
+fn inverse_sqrt(matrix: &DMatrix<f64>) -> DMatrix<f64> {
+    let eigen = matrix.clone().symmetric_eigen();
+    let scale = eigen.eigenvalues.map(|v| if v > 1e-12 { 1.0 / v.sqrt() } else { 0.0 });
+    &eigen.eigenvectors * DMatrix::from_diagonal(&scale) * eigen.eigenvectors.transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn correlation(a: &[f64], b: &[f64]) -> f64 {
+        let mean_a = a.iter().sum::<f64>() / a.len() as f64;
+        let mean_b = b.iter().sum::<f64>() / b.len() as f64;
+        let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+        for (x, y) in a.iter().zip(b) {
+            cov += (x - mean_a) * (y - mean_b);
+            var_a += (x - mean_a).powi(2);
+            var_b += (y - mean_b).powi(2);
+        }
+        cov / (var_a * var_b).sqrt()
+    }
+    
+    #[test]
+    fn test_separation_and_rejection() {
+        let sample_rate = 250.0;
+        let config = IcaConfig { enabled: true, ..IcaConfig::default() };
+        let mut ica = OnlineIca::new(config, sample_rate, vec!["C3".to_string(), "C4".to_string()]);
+        
+        // 亚高斯（正弦）与超高斯（拉普拉斯噪声）源按固定矩阵混合
+        let mut seed = 7u64;
+        let mut laplace = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let u = ((seed >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+            if u < 0.5 { (2.0 * u).ln() } else { -(2.0 * (1.0 - u)).ln() }
+        };
+        let mut sources = Vec::new();
+        for n in 0..60_000 {
+            let sine = 1.4 * (2.0 * std::f64::consts::PI * 3.0 * n as f64 / sample_rate).sin();
+            sources.push([sine, laplace()]);
+        }
+        let mix = |s: &[f64; 2]| vec![10.0 * (s[0] + 0.6 * s[1]), 10.0 * (0.5 * s[0] + s[1])];
+        
+        let mut courses = vec![Vec::new(); 2];
+        let mut courses_end = 0;
+        for (n, source) in sources[..50_000].iter().enumerate() {
+            let mut sample = mix(source);
+            if let Some(update) = ica.push(&mut sample) {
+                courses_end = n + 1;
+                assert_eq!(update.scalp_weights.len(), 2);
+                for (course, update_course) in courses.iter_mut().zip(update.time_courses) {
+                    course.extend(update_course.into_iter().map(f64::from));
+                }
+            }
+        }
+        
+        // 最后10秒每个源都应与某个成分高度相关
+        let tail = 2500;
+        let window = &sources[courses_end - tail..courses_end];
+        let sine: Vec<f64> = window.iter().map(|s| s[0]).collect();
+        let noise: Vec<f64> = window.iter().map(|s| s[1]).collect();
+        let offset = courses[0].len() - tail;
+        let best = |target: &[f64]| (0..2)
+            .map(|c| (c, correlation(target, &courses[c][offset..]).abs()))
+            .fold((0, 0.0), |best, current| if current.1 > best.1 { current } else { best });
+        let (sine_component, sine_corr) = best(&sine);
+        let (noise_component, noise_corr) = best(&noise);
+        assert_ne!(sine_component, noise_component);
+        assert!(sine_corr > 0.95, "sine correlation {}", sine_corr);
+        assert!(noise_corr > 0.95, "noise correlation {}", noise_corr);
+        
+        // 拒绝噪声成分后显示信号只剩正弦
+        ica.set_rejected(&[noise_component as u32]);
+        let mut cleaned = Vec::new();
+        for source in &sources[50_000..] {
+            let mut sample = mix(source);
+            ica.push(&mut sample);
+            cleaned.push(sample[0]);
+        }
+        let sine: Vec<f64> = sources[50_000..].iter().map(|s| s[0]).collect();
+        assert!(correlation(&cleaned, &sine) > 0.95);
+    }
+}
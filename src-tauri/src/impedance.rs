@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use crate::data_types::*;
+use crate::error::AppError;
+
+/// 电极阻抗跟踪 - 保存每个电极最近一次的阻抗，供 impedance-update 事件与会话元数据使用
+///
+/// 三种来源（阻抗流、通道元数据、检查协议）写入同一张表，后到的值覆盖先前的值。
+pub struct ImpedanceTracker {
+    config: ImpedanceConfig,
+    latest: HashMap<String, ElectrodeImpedance>,
+    order: Vec<String>,                 // 电极首次出现的顺序，快照按此排列
+}
+
+impl ImpedanceTracker {
+    pub fn new(config: ImpedanceConfig) -> Self {
+        Self { config, latest: HashMap::new(), order: Vec::new() }
+    }
+    
+    pub fn config(&self) -> ImpedanceConfig {
+        self.config
+    }
+    
+    /// 更新阈值后按新阈值重新分级已有的值
+    pub fn set_config(&mut self, config: ImpedanceConfig) {
+        self.config = config;
+        for electrode in self.latest.values_mut() {
+            electrode.status = config.status(electrode.kohm);
+        }
+    }
+    
+    pub fn update(&mut self, source: ImpedanceSource, readings: &[(String, f64)], timestamp: f64) -> ImpedanceUpdate {
+        let mut electrodes = Vec::with_capacity(readings.len());
+        
+        for (label, kohm) in readings {
+            if !kohm.is_finite() || *kohm < 0.0 {
+                continue;
+            }
+            let electrode = ElectrodeImpedance {
+                label: label.clone(),
+                kohm: *kohm,
+                status: self.config.status(*kohm),
+                source,
+                timestamp,
+            };
+            if self.latest.insert(label.clone(), electrode.clone()).is_none() {
+                self.order.push(label.clone());
+            }
+            electrodes.push(electrode);
+        }
+        
+        ImpedanceUpdate { source, timestamp, electrodes }
+    }
+    
+    pub fn snapshot(&self) -> Vec<ElectrodeImpedance> {
+        self.order.iter().filter_map(|label| self.latest.get(label).cloned()).collect()
+    }
+}
+
+/// 阻抗流样本 -> (电极, kΩ)
+pub fn readings_from_sample(sample: &AuxSample) -> Vec<(String, f64)> {
+    sample.channel_labels.iter().cloned().zip(sample.values.iter().copied()).collect()
+}
+
+/// 通道元数据中的阻抗 -> (电极, kΩ)，跳过未提供阻抗的通道
+pub fn readings_from_metadata(labels: &[String], impedances: &[Option<f64>]) -> Vec<(String, f64)> {
+    labels.iter()
+        .zip(impedances)
+        .filter_map(|(label, kohm)| kohm.map(|kohm| (label.clone(), kohm)))
+        .collect()
+}
+
+/// 检查协议 - 由检测电流期间采集的数据计算各电极阻抗
+///
+/// 取整数个驱动周期的数据、去均值后在驱动频率处做单频DFT得到电压幅值（µV），
+/// 阻抗 = 幅值 / 检测电流 − 串联电阻。
+pub fn check_impedances(
+    samples: &[EegSample],
+    labels: &[String],
+    sample_rate: f64,
+    config: &ImpedanceConfig
+) -> Result<Vec<(String, f64)>, AppError> {
+    if config.drive_frequency >= sample_rate / 2.0 {
+        return Err(AppError::Config(format!(
+            "Impedance drive frequency {}Hz must be below Nyquist ({}Hz)", config.drive_frequency, sample_rate / 2.0
+        )));
+    }
+    
+    let cycles = (samples.len() as f64 * config.drive_frequency / sample_rate).floor();
+    let len = ((cycles * sample_rate / config.drive_frequency).round() as usize).min(samples.len());
+    if cycles < 1.0 || len < 4 {
+        return Err(AppError::Config("Not enough data for impedance check".to_string()));
+    }
+    let window = &samples[samples.len() - len..];
+    
+    let omega = TAU * config.drive_frequency / sample_rate;
+    let current_amps = config.drive_current_na * 1e-9;
+    let mut readings = Vec::with_capacity(labels.len());
+    
+    for (channel, label) in labels.iter().enumerate() {
+        let values: Vec<f64> = window.iter().map(|s| s.channels.get(channel).copied().unwrap_or(0.0)).collect();
+        let mean = values.iter().sum::<f64>() / len as f64;
+        
+        let (mut re, mut im) = (0.0, 0.0);
+        for (n, value) in values.iter().enumerate() {
+            let phase = omega * n as f64;
+            re += (value - mean) * phase.cos();
+            im -= (value - mean) * phase.sin();
+        }
+        let amplitude_uv = 2.0 * (re * re + im * im).sqrt() / len as f64;
+        
+        let kohm = (amplitude_uv * 1e-6 / current_amps / 1000.0 - config.series_resistance_kohm).max(0.0);
+        readings.push((label.clone(), kohm));
+    }
+    
+    Ok(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_check_protocol_and_tracker() {
+        let config = ImpedanceConfig::default();
+        let sample_rate = 250.0;
+        let labels = vec!["Fp1".to_string(), "Fp2".to_string()];
+        
+        // 6nA 检测电流流过 (5 + 2.2)kΩ 与 (80 + 2.2)kΩ：幅值 43.2µV 与 493.2µV，叠加直流偏移
+        let samples: Vec<EegSample> = (0..500).map(|n| {
+            let drive = (TAU * config.drive_frequency * n as f64 / sample_rate).sin();
+            EegSample {
+                timestamp: n as f64 / sample_rate,
+                channels: vec![43.2 * drive + 120.0, 493.2 * drive - 30.0],
+                sample_id: n as u64,
+            }
+        }).collect();
+        
+        let readings = check_impedances(&samples, &labels, sample_rate, &config).unwrap();
+        assert!((readings[0].1 - 5.0).abs() < 0.01, "Fp1 {}", readings[0].1);
+        assert!((readings[1].1 - 80.0).abs() < 0.01, "Fp2 {}", readings[1].1);
+        
+        let mut tracker = ImpedanceTracker::new(config);
+        let update = tracker.update(ImpedanceSource::Check, &readings, 2.0);
+        assert_eq!(update.electrodes[0].status, ImpedanceStatus::Good);
+        assert_eq!(update.electrodes[1].status, ImpedanceStatus::Poor);
+        
+        // 后到的元数据覆盖同名电极，快照保持首次出现的顺序
+        let metadata = readings_from_metadata(&labels, &[None, Some(20.0)]);
+        tracker.update(ImpedanceSource::ChannelMetadata, &metadata, 3.0);
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].source, ImpedanceSource::Check);
+        assert_eq!((snapshot[1].kohm, snapshot[1].status), (20.0, ImpedanceStatus::Fair));
+        
+        tracker.set_config(ImpedanceConfig { good_kohm: 30.0, ..config });
+        assert_eq!(tracker.snapshot()[1].status, ImpedanceStatus::Good);
+    }
+}
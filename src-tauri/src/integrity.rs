@@ -0,0 +1,84 @@
+use crate::data_types::*;
+
+// 时间戳间隔超过名义间隔的该倍数才视为间隙（LSL分块时间戳有抖动）
+const TIMING_GAP_FACTOR: f64 = 2.0;
+
+/// 数据完整性监视 - 检查相邻样本的 sample_id 连续性与时间戳间隔
+pub struct GapDetector {
+    nominal_dt: Option<f64>,    // 不规则采样率的流不做时间检查
+    last: Option<(f64, u64)>,   // 上一样本的(时间戳, sample_id)
+}
+
+impl GapDetector {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            nominal_dt: (sample_rate > 0.0).then(|| 1.0 / sample_rate),
+            last: None,
+        }
+    }
+    
+    /// 检查下一个样本，发现间隙时返回（recording_onset 由调用方填写）
+    pub fn check(&mut self, sample: &EegSample) -> Option<DataGap> {
+        let previous = self.last.replace((sample.timestamp, sample.sample_id));
+        let (last_timestamp, last_id) = previous?;
+        
+        let duration_seconds = sample.timestamp - last_timestamp;
+        let gap = |kind, missing_samples| DataGap {
+            kind,
+            start_timestamp: last_timestamp,
+            end_timestamp: sample.timestamp,
+            duration_seconds,
+            missing_samples,
+            sample_id: sample.sample_id,
+            recording_onset: None,
+        };
+        
+        // sample_id 回退（重连、回放跳转）时重新开始计数，不报告间隙
+        if sample.sample_id <= last_id {
+            return None;
+        }
+        
+        let skipped_ids = sample.sample_id - last_id - 1;
+        if skipped_ids > 0 {
+            return Some(gap(GapKind::SampleId, skipped_ids));
+        }
+        
+        let nominal_dt = self.nominal_dt?;
+        if duration_seconds > nominal_dt * TIMING_GAP_FACTOR {
+            let missing_samples = (duration_seconds / nominal_dt).round() as u64 - 1;
+            return Some(gap(GapKind::Timing, missing_samples));
+        }
+        
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: f64, sample_id: u64) -> EegSample {
+        EegSample { timestamp, channels: vec![0.0], sample_id }
+    }
+
+    #[test]
+    fn test_gap_detection() {
+        let mut detector = GapDetector::new(100.0);
+        
+        assert!(detector.check(&sample(0.00, 0)).is_none());
+        assert!(detector.check(&sample(0.01, 1)).is_none());
+        
+        let missing = detector.check(&sample(0.05, 5)).unwrap();
+        assert_eq!(missing.kind, GapKind::SampleId);
+        assert_eq!(missing.missing_samples, 3);
+        
+        let timing = detector.check(&sample(0.55, 6)).unwrap();
+        assert_eq!(timing.kind, GapKind::Timing);
+        assert_eq!(timing.missing_samples, 49);
+        assert!((timing.duration_seconds - 0.5).abs() < 1e-9);
+        
+        // sample_id 回退视为新的起点
+        assert!(detector.check(&sample(10.0, 0)).is_none());
+        assert!(detector.check(&sample(10.01, 1)).is_none());
+    }
+}
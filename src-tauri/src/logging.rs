@@ -0,0 +1,153 @@
+use crate::data_types::LogEntry;
+use crate::error::AppError;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "desktop")]
+use std::sync::OnceLock;
+#[cfg(feature = "desktop")]
+use tauri::{AppHandle, Emitter};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Layer, Registry};
+
+const LOG_BUFFER_CAPACITY: usize = 2000;
+const DEFAULT_RECENT_LOGS: usize = 200;
+
+// 前端句柄在setup阶段才可用，日志层通过它推送 log-event
+#[cfg(feature = "desktop")]
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+type LogBuffer = Arc<Mutex<VecDeque<(Level, LogEntry)>>>;
+
+/// 日志子系统 - tracing订阅器 + 最近日志环形缓冲区
+pub struct Logging {
+    buffer: LogBuffer,
+    level_handle: reload::Handle<LevelFilter, Registry>,
+}
+
+impl Logging {
+    /// 安装全局订阅器（控制台输出 + 环形缓冲区），默认级别INFO
+    pub fn init() -> Self {
+        let buffer: LogBuffer = Arc::new(Mutex::new(VecDeque::with_capacity(LOG_BUFFER_CAPACITY)));
+        let (filter, level_handle) = reload::Layer::new(LevelFilter::INFO);
+        
+        let result = tracing_subscriber::registry()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer())
+            .with(RingBufferLayer { buffer: buffer.clone() })
+            .try_init();
+        
+        if let Err(e) = result {
+            eprintln!("⚠️ Failed to install log subscriber: {}", e);
+        }
+        
+        Self { buffer, level_handle }
+    }
+    
+    /// 最近n条日志（不低于指定级别），按时间顺序返回
+    pub fn recent(&self, min_level: Option<Level>, n: Option<usize>) -> Vec<LogEntry> {
+        let n = n.unwrap_or(DEFAULT_RECENT_LOGS);
+        let buffer = match self.buffer.lock() {
+            Ok(buffer) => buffer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        
+        // tracing中越严重的级别越"小"（ERROR < WARN < INFO）
+        let mut entries: Vec<LogEntry> = buffer.iter()
+            .rev()
+            .filter(|(level, _)| min_level.is_none_or(|min| *level <= min))
+            .take(n)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        entries.reverse();
+        entries
+    }
+    
+    /// 运行时调整日志级别（同时作用于控制台与缓冲区）
+    pub fn set_level(&self, level: Level) -> Result<(), AppError> {
+        self.level_handle.reload(LevelFilter::from_level(level))
+            .map_err(|e| AppError::Config(format!("Failed to set log level: {}", e)))
+    }
+}
+
+/// setup阶段调用，之后的日志同时以 log-event 推送到前端
+#[cfg(feature = "desktop")]
+pub fn attach_app_handle(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+pub fn parse_level(level: &str) -> Result<Level, AppError> {
+    level.parse::<Level>()
+        .map_err(|_| AppError::Config(format!("Unknown log level: {} (expected trace/debug/info/warn/error)", level)))
+}
+
+struct RingBufferLayer {
+    buffer: LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for RingBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        
+        let entry = LogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.finish(),
+        };
+        
+        if let Ok(mut buffer) = self.buffer.lock() {
+            if buffer.len() >= LOG_BUFFER_CAPACITY {
+                buffer.pop_front();
+            }
+            buffer.push_back((*metadata.level(), entry.clone()));
+        }
+        
+        // 注意：此处不能再调用tracing宏，否则会递归
+        #[cfg(feature = "desktop")]
+        if let Some(app) = APP_HANDLE.get() {
+            let _ = app.emit("log-event", &entry);
+        }
+    }
+}
+
+/// 收集事件的message字段，其余字段以 key=value 追加
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        if self.fields.is_empty() {
+            self.message
+        } else {
+            format!("{} {}", self.message, self.fields.join(" "))
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+    
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
@@ -3,38 +3,143 @@ use crate::error::AppError;
 use crossbeam_channel;
 use std::thread::{self, JoinHandle};
 use std::sync::mpsc;
+use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 use lsl;
 use lsl::Pullable;
+use crate::metrics::StageStats;
+use crate::stream_source::{
+    self, EegConnection, MarkerInlet, SampleInlet, SourceClock, StreamResolver, StreamSource,
+};
+use tracing::{debug, error, info, warn};
+
+// 已连接流超过该时长没有数据即视为丢失
+const STREAM_LOST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// 后台流发现：结果轮询间隔，以及流消失后被遗忘的时长
+const DISCOVERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DISCOVERY_FORGET_AFTER_SECONDS: f64 = 5.0;
+
+// 时钟偏移：测量间隔、单次测量超时，以及保留的测量数（约1小时）
+const CLOCK_OFFSET_INTERVAL: Duration = Duration::from_secs(5);
+const CLOCK_OFFSET_TIMEOUT_SECONDS: f64 = 1.0;
+const CLOCK_OFFSET_HISTORY: usize = 720;
 
 pub struct LslManager {
     // 工作线程句柄
     worker_handle: Option<JoinHandle<()>>,
     
+    // 数据源（启动时移交给工作线程）及其时钟
+    source: Option<Box<dyn StreamSource>>,
+    clock: SourceClock,
+    
     // 控制通道
     control_tx: mpsc::Sender<ControlCommand>,
     
     // 数据输出通道
-    data_tx: Option<crossbeam_channel::Sender<EegSample>>,
-    data_rx: Option<crossbeam_channel::Receiver<EegSample>>,
+    data_tx: Option<crossbeam_channel::Sender<EegChunk>>,
+    data_rx: Option<crossbeam_channel::Receiver<EegChunk>>,
+    
+    // 标记流输出通道
+    marker_tx: Option<crossbeam_channel::Sender<LslMarker>>,
+    marker_rx: Option<crossbeam_channel::Receiver<LslMarker>>,
+    
+    // 辅助流（加速度计/IMU）输出通道
+    aux_tx: Option<crossbeam_channel::Sender<AuxSample>>,
+    aux_rx: Option<crossbeam_channel::Receiver<AuxSample>>,
+    
+    // 阻抗流输出通道
+    impedance_tx: Option<crossbeam_channel::Sender<AuxSample>>,
+    impedance_rx: Option<crossbeam_channel::Receiver<AuxSample>>,
+    
+    // 时钟偏移测量：新测量推送给处理器（写入录制），同时保留最近的测量序列
+    clock_tx: Option<crossbeam_channel::Sender<ClockOffsetSample>>,
+    clock_rx: Option<crossbeam_channel::Receiver<ClockOffsetSample>>,
+    clock_offsets: Arc<std::sync::Mutex<VecDeque<ClockOffsetSample>>>,
+    
+    // 后台流发现事件
+    discovery_tx: Option<crossbeam_channel::Sender<DiscoveryEvent>>,
+    discovery_rx: Option<crossbeam_channel::Receiver<DiscoveryEvent>>,
+    
+    // 数据源状态事件（连接/丢失/恢复）
+    source_event_tx: Option<crossbeam_channel::Sender<SourceEvent>>,
+    source_event_rx: Option<crossbeam_channel::Receiver<SourceEvent>>,
+    stream_lost: Arc<AtomicBool>,
+    
+    // 实际采样率估计（f64位模式，0表示尚未估计）
+    effective_rate: Arc<AtomicU64>,
+    rate_monitor: RateMonitorConfig,
+    
+    // 交付时的数据年龄（LSL拉取阶段延迟）
+    pull_stats: Arc<StageStats>,
     
     // 当前流信息
     current_stream: Option<StreamInfo>,
     
+    // 当前流描述中的通道阻抗（kΩ）
+    channel_impedances: Vec<Option<f64>>,
+    
+    // 当前标记流信息
+    current_marker_stream: Option<LslStreamInfo>,
+    
+    // 当前辅助流信息
+    current_aux_stream: Option<StreamInfo>,
+    
+    // 当前阻抗流信息
+    current_impedance_stream: Option<StreamInfo>,
+    
     // 运行状态
     is_running: bool,
+    
+    // 采集模式（Strict模式禁用模拟连接）
+    acquisition_mode: AcquisitionMode,
+    
+    // 拉取分块配置
+    chunk_config: ChunkConfig,
+    
+    // EEG inlet参数
+    inlet_config: LslInletConfig,
 }
 
+// 辅助流inlet及其通道标签（随每个样本发送）
+type AuxInlet = (Box<dyn SampleInlet>, Arc<[String]>);
+
+// 连接成功的EEG流及其流描述中的通道阻抗（kΩ）
+type ConnectedStream = (StreamInfo, Vec<Option<f64>>);
+
 // 重新设计控制命令
 #[derive(Debug)]
 enum ControlCommand {
     DiscoverStreams { 
+        filter: StreamFilter,
         response_tx: mpsc::Sender<Result<Vec<LslStreamInfo>, AppError>> 
     },
     ConnectToStream { 
-        name: String, 
-        response_tx: mpsc::Sender<Result<StreamInfo, AppError>> 
+        selector: StreamSelector, 
+        strict: bool,
+        inlet_config: LslInletConfig,
+        response_tx: mpsc::Sender<Result<ConnectedStream, AppError>> 
+    },
+    ConnectMarkerStream {
+        name: String,
+        response_tx: mpsc::Sender<Result<LslStreamInfo, AppError>>
     },
+    DisconnectMarkerStream,
+    ConnectAuxStream {
+        name: String,
+        response_tx: mpsc::Sender<Result<StreamInfo, AppError>>
+    },
+    DisconnectAuxStream,
+    ConnectImpedanceStream {
+        name: String,
+        response_tx: mpsc::Sender<Result<StreamInfo, AppError>>
+    },
+    DisconnectImpedanceStream,
+    StartDiscovery(StreamFilter),
+    SetChunkConfig(ChunkConfig),
+    SetRateMonitor(RateMonitorConfig),
     GetStats { 
         response_tx: mpsc::Sender<WorkerStats> 
     },
@@ -51,50 +156,189 @@ struct WorkerStats {
 
 impl LslManager {
     pub fn new() -> Self {
+        Self::with_source(Box::new(LslSource))
+    }
+    
+    /// 使用指定数据源（如 MockSource）创建管理器
+    pub fn with_source(source: Box<dyn StreamSource>) -> Self {
         let (control_tx, _) = mpsc::channel(); // 临时创建，工作线程启动时会重建
         let (data_tx, data_rx) = crossbeam_channel::unbounded();
+        let (marker_tx, marker_rx) = crossbeam_channel::unbounded();
+        let (aux_tx, aux_rx) = crossbeam_channel::unbounded();
+        let (impedance_tx, impedance_rx) = crossbeam_channel::unbounded();
+        let (discovery_tx, discovery_rx) = crossbeam_channel::unbounded();
+        let (clock_tx, clock_rx) = crossbeam_channel::unbounded();
+        let (source_event_tx, source_event_rx) = crossbeam_channel::unbounded();
         
         Self {
             worker_handle: None,
+            clock: source.clock(),
+            source: Some(source),
             control_tx,
             data_tx: Some(data_tx),
             data_rx: Some(data_rx),
+            marker_tx: Some(marker_tx),
+            marker_rx: Some(marker_rx),
+            aux_tx: Some(aux_tx),
+            aux_rx: Some(aux_rx),
+            impedance_tx: Some(impedance_tx),
+            impedance_rx: Some(impedance_rx),
+            clock_tx: Some(clock_tx),
+            clock_rx: Some(clock_rx),
+            clock_offsets: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+            discovery_tx: Some(discovery_tx),
+            discovery_rx: Some(discovery_rx),
+            source_event_tx: Some(source_event_tx),
+            source_event_rx: Some(source_event_rx),
+            stream_lost: Arc::new(AtomicBool::new(false)),
+            effective_rate: Arc::new(AtomicU64::new(0)),
+            pull_stats: Arc::new(StageStats::new()),
+            rate_monitor: RateMonitorConfig::default(),
             current_stream: None,
+            channel_impedances: Vec::new(),
+            current_marker_stream: None,
+            current_aux_stream: None,
+            current_impedance_stream: None,
             is_running: false,
+            acquisition_mode: AcquisitionMode::default(),
+            chunk_config: ChunkConfig::default(),
+            inlet_config: LslInletConfig::default(),
+        }
+    }
+    
+    /// 设置采集模式，需在连接前调用
+    pub fn set_acquisition_mode(&mut self, mode: AcquisitionMode) {
+        self.acquisition_mode = mode;
+    }
+    
+    /// 设置EEG inlet参数，需在连接前调用
+    pub fn set_inlet_config(&mut self, config: LslInletConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.inlet_config = config;
+        Ok(())
+    }
+    
+    /// 设置拉取分块配置，运行中的工作线程立即生效
+    pub fn set_chunk_config(&mut self, config: ChunkConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.chunk_config = config;
+        
+        if self.is_running {
+            self.control_tx.send(ControlCommand::SetChunkConfig(config))
+                .map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn get_chunk_config(&self) -> ChunkConfig {
+        self.chunk_config
+    }
+    
+    /// 设置实际采样率监测配置，运行中的工作线程立即生效（估计窗口重新开始）
+    pub fn set_rate_monitor(&mut self, config: RateMonitorConfig) -> Result<(), AppError> {
+        config.validate()?;
+        self.rate_monitor = config;
+        
+        if self.is_running {
+            self.control_tx.send(ControlCommand::SetRateMonitor(config))
+                .map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 在工作线程中持续发现流，变化通过 get_discovery_receiver 报告；再次调用时替换过滤条件，
+    /// 随管理器停止而结束
+    pub fn start_discovery(&mut self, filter: StreamFilter) -> Result<(), AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        self.control_tx.send(ControlCommand::StartDiscovery(filter))
+            .map_err(|_| AppError::Channel("Control channel closed".to_string()))
+    }
+    
+    pub fn get_rate_monitor(&self) -> RateMonitorConfig {
+        self.rate_monitor
+    }
+    
+    /// LSL拉取阶段的延迟统计，交给处理器汇总
+    pub fn pull_stats(&self) -> Arc<StageStats> {
+        self.pull_stats.clone()
+    }
+    
+    /// 按LSL时间戳估计的实际采样率，窗口未满时为None
+    pub fn effective_sample_rate(&self) -> Option<f64> {
+        match self.effective_rate.load(Ordering::Relaxed) {
+            0 => None,
+            bits => Some(f64::from_bits(bits)),
         }
     }
     
+    /// 当前实际采样率相对名义值的偏差（用于 sample-rate-warning）
+    pub fn rate_warning(&self) -> Option<SampleRateWarning> {
+        let nominal = self.current_stream.as_ref()?.sample_rate;
+        let effective = self.effective_sample_rate()?;
+        
+        Some(SampleRateWarning {
+            nominal_sample_rate: nominal,
+            effective_sample_rate: effective,
+            deviation_percent: RateEstimator::deviation_percent(nominal, effective),
+            tolerance_percent: self.rate_monitor.tolerance_percent,
+        })
+    }
+    
     pub async fn start(&mut self) -> Result<(), AppError> {
         if self.is_running {
             return Err(AppError::Config("Manager already running".to_string()));
         }
+        let source = self.source.take()
+            .ok_or_else(|| AppError::Config("Stream source already consumed".to_string()))?;
         
         // ✅ 修复：创建新的通道对，避免克隆Receiver
         let (control_tx, control_rx) = mpsc::channel();
         self.control_tx = control_tx;
         
         let data_tx = self.data_tx.as_ref().unwrap().clone();
+        let marker_tx = self.marker_tx.as_ref().unwrap().clone();
+        let aux_tx = self.aux_tx.as_ref().unwrap().clone();
+        let impedance_tx = self.impedance_tx.as_ref().unwrap().clone();
+        let discovery_tx = self.discovery_tx.as_ref().unwrap().clone();
+        let clock_tx = self.clock_tx.as_ref().unwrap().clone();
+        let clock_offsets = self.clock_offsets.clone();
+        // 状态事件发送端交给工作线程，线程退出时接收端即可感知
+        let source_event_tx = self.source_event_tx.take().unwrap_or_else(|| crossbeam_channel::unbounded().0);
+        let stream_lost = self.stream_lost.clone();
+        let effective_rate = self.effective_rate.clone();
+        let pull_stats = self.pull_stats.clone();
+        let chunk_config = self.chunk_config;
+        let rate_monitor = self.rate_monitor;
         
         // 启动工作线程
         let handle = thread::spawn(move || {
-            Self::worker_thread(control_rx, data_tx);
+            Self::worker_thread(
+                source, control_rx, data_tx, marker_tx, aux_tx, impedance_tx, discovery_tx, clock_tx, clock_offsets, source_event_tx, stream_lost,
+                effective_rate, pull_stats, chunk_config, rate_monitor,
+            );
         });
         
         self.worker_handle = Some(handle);
         self.is_running = true;
         
-        println!("✅ LSL Manager started");
+        info!("✅ LSL Manager started");
         Ok(())
     }
     
-    pub async fn discover_streams(&mut self) -> Result<Vec<LslStreamInfo>, AppError> {
+    /// 发现局域网内的LSL流，按过滤条件筛选（默认条件返回所有流）
+    pub async fn discover_streams(&mut self, filter: StreamFilter) -> Result<Vec<LslStreamInfo>, AppError> {
         if !self.is_running {
             return Err(AppError::NotConnected);
         }
         
         let (response_tx, response_rx) = mpsc::channel();
         
-        self.control_tx.send(ControlCommand::DiscoverStreams { response_tx })
+        self.control_tx.send(ControlCommand::DiscoverStreams { filter, response_tx })
             .map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
         
         // 等待响应
@@ -104,7 +348,8 @@ impl LslManager {
         response
     }
     
-    pub async fn connect_to_stream(&mut self, name: &str) -> Result<StreamInfo, AppError> {
+    /// 连接EEG流；同名流存在多个时需在 selector 中指定 source_id
+    pub async fn connect_to_stream(&mut self, selector: &StreamSelector) -> Result<StreamInfo, AppError> {
         if !self.is_running {
             return Err(AppError::NotConnected);
         }
@@ -112,7 +357,9 @@ impl LslManager {
         let (response_tx, response_rx) = mpsc::channel();
         
         self.control_tx.send(ControlCommand::ConnectToStream { 
-            name: name.to_string(), 
+            selector: selector.clone(), 
+            strict: self.acquisition_mode.is_strict(),
+            inlet_config: self.inlet_config,
             response_tx 
         }).map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
         
@@ -121,25 +368,195 @@ impl LslManager {
             .map_err(|_| AppError::Channel("Connect timeout".to_string()))?;
         
         match response {
-            Ok(stream_info) => {
+            Ok((stream_info, impedances)) => {
                 self.current_stream = Some(stream_info.clone());
+                self.channel_impedances = impedances;
                 Ok(stream_info)
             }
             Err(e) => Err(e)
         }
     }
     
+    /// 连接字符串格式的LSL标记流（与EEG流并行接收）
+    pub async fn connect_marker_stream(&mut self, name: &str) -> Result<LslStreamInfo, AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.control_tx.send(ControlCommand::ConnectMarkerStream {
+            name: name.to_string(),
+            response_tx
+        }).map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        
+        let response = response_rx.recv_timeout(Duration::from_secs(30))
+            .map_err(|_| AppError::Channel("Connect marker stream timeout".to_string()))?;
+        
+        let marker_info = response?;
+        self.current_marker_stream = Some(marker_info.clone());
+        Ok(marker_info)
+    }
+    
+    /// 断开标记流，EEG流不受影响
+    pub async fn disconnect_marker_stream(&mut self) -> Result<(), AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        self.control_tx.send(ControlCommand::DisconnectMarkerStream)
+            .map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        
+        self.current_marker_stream = None;
+        Ok(())
+    }
+    
+    /// 连接辅助流（加速度计/陀螺仪等IMU数据，与EEG流并行接收）
+    pub async fn connect_aux_stream(&mut self, name: &str) -> Result<StreamInfo, AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.control_tx.send(ControlCommand::ConnectAuxStream {
+            name: name.to_string(),
+            response_tx
+        }).map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        
+        let response = response_rx.recv_timeout(Duration::from_secs(30))
+            .map_err(|_| AppError::Channel("Connect aux stream timeout".to_string()))?;
+        
+        let aux_info = response?;
+        self.current_aux_stream = Some(aux_info.clone());
+        Ok(aux_info)
+    }
+    
+    /// 断开辅助流，EEG流不受影响
+    pub async fn disconnect_aux_stream(&mut self) -> Result<(), AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        self.control_tx.send(ControlCommand::DisconnectAuxStream)
+            .map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        
+        self.current_aux_stream = None;
+        Ok(())
+    }
+    
+    /// 连接阻抗流（放大器发布的各电极阻抗，kΩ），与EEG流并行接收
+    pub async fn connect_impedance_stream(&mut self, name: &str) -> Result<StreamInfo, AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.control_tx.send(ControlCommand::ConnectImpedanceStream {
+            name: name.to_string(),
+            response_tx
+        }).map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        
+        let response = response_rx.recv_timeout(Duration::from_secs(30))
+            .map_err(|_| AppError::Channel("Connect impedance stream timeout".to_string()))?;
+        
+        let impedance_info = response?;
+        self.current_impedance_stream = Some(impedance_info.clone());
+        Ok(impedance_info)
+    }
+    
+    /// 断开阻抗流，EEG流不受影响
+    pub async fn disconnect_impedance_stream(&mut self) -> Result<(), AppError> {
+        if !self.is_running {
+            return Err(AppError::NotConnected);
+        }
+        
+        self.control_tx.send(ControlCommand::DisconnectImpedanceStream)
+            .map_err(|_| AppError::Channel("Control channel closed".to_string()))?;
+        
+        self.current_impedance_stream = None;
+        Ok(())
+    }
+    
     pub async fn get_current_stream_info(&self) -> Option<StreamInfo> {
-        self.current_stream.clone()
+        self.current_stream.clone().map(|mut stream_info| {
+            stream_info.effective_sample_rate = self.effective_sample_rate();
+            stream_info
+        })
     }
     
-    pub fn get_data_receiver(&mut self) -> Option<crossbeam_channel::Receiver<EegSample>> {
+    pub fn get_marker_stream_info(&self) -> Option<LslStreamInfo> {
+        self.current_marker_stream.clone()
+    }
+    
+    pub fn get_aux_stream_info(&self) -> Option<StreamInfo> {
+        self.current_aux_stream.clone()
+    }
+    
+    pub fn get_impedance_stream_info(&self) -> Option<StreamInfo> {
+        self.current_impedance_stream.clone()
+    }
+    
+    /// 当前流描述中的通道阻抗（kΩ），按通道顺序；流未提供时为空
+    pub fn channel_impedances(&self) -> &[Option<f64>] {
+        &self.channel_impedances
+    }
+    
+    pub fn get_data_receiver(&mut self) -> Option<crossbeam_channel::Receiver<EegChunk>> {
         self.data_rx.take() // 转移所有权
     }
     
+    /// 数据源状态事件；工作线程结束后通道断开
+    pub fn get_source_event_receiver(&mut self) -> Option<crossbeam_channel::Receiver<SourceEvent>> {
+        self.source_event_rx.take()
+    }
+    
+    /// 已连接的流是否超过 STREAM_LOST_TIMEOUT 没有数据
+    pub fn is_stream_lost(&self) -> bool {
+        self.stream_lost.load(Ordering::Relaxed)
+    }
+    
+    pub fn get_marker_receiver(&mut self) -> Option<crossbeam_channel::Receiver<LslMarker>> {
+        self.marker_rx.take() // 转移所有权
+    }
+    
+    pub fn get_aux_receiver(&mut self) -> Option<crossbeam_channel::Receiver<AuxSample>> {
+        self.aux_rx.take() // 转移所有权
+    }
+    
+    pub fn get_impedance_receiver(&mut self) -> Option<crossbeam_channel::Receiver<AuxSample>> {
+        self.impedance_rx.take()
+    }
+    
+    /// 时钟偏移测量；录制时写入旁路文件
+    pub fn get_clock_receiver(&mut self) -> Option<crossbeam_channel::Receiver<ClockOffsetSample>> {
+        self.clock_rx.take()
+    }
+    
+    /// 当前EEG流的时钟偏移序列与 LSL→Unix 时间换算
+    pub fn get_clock_offset(&self) -> Option<ClockOffsetInfo> {
+        let stream = self.current_stream.as_ref()?;
+        let samples: Vec<ClockOffsetSample> = self.clock_offsets.lock()
+            .map(|offsets| offsets.iter().copied().collect())
+            .unwrap_or_default();
+        
+        Some(ClockOffsetInfo {
+            stream_name: stream.name.clone(),
+            offset: samples.last().map(|sample| sample.offset),
+            lsl_to_unix: unix_now() - (self.clock)(),
+            samples,
+        })
+    }
+    
+    /// 后台流发现事件；工作线程结束后通道断开
+    pub fn get_discovery_receiver(&mut self) -> Option<crossbeam_channel::Receiver<DiscoveryEvent>> {
+        self.discovery_rx.take()
+    }
+    
     /// ✅ 消费式停止 - 消费 self，返回统计信息
     pub async fn stop(mut self) -> Result<LslManagerStats, AppError> {
-        println!("🛑 Stopping LSL Manager");
+        info!("🛑 Stopping LSL Manager");
         
         // 先获取工作线程统计信息
         let worker_stats = if self.is_running {
@@ -155,14 +572,14 @@ impl LslManager {
         
         // 发送停止命令
         if let Err(_) = self.control_tx.send(ControlCommand::Stop) {
-            println!("⚠️  Control channel already closed");
+            warn!("⚠️  Control channel already closed");
         }
         
         // 等待工作线程结束
         if let Some(handle) = self.worker_handle.take() {
             match handle.join() {
-                Ok(_) => println!("✅ LSL worker thread stopped"),
-                Err(_) => println!("⚠️  LSL worker thread panicked"),
+                Ok(_) => info!("✅ LSL worker thread stopped"),
+                Err(_) => warn!("⚠️  LSL worker thread panicked"),
             }
         }
         
@@ -186,12 +603,12 @@ impl LslManager {
         };
         
         // ✅ 实际使用统计字段
-        println!("📊 LSL Manager stopped:");
-        println!("   - Streams discovered: {}", stats.streams_discovered);
-        println!("   - Samples received: {}", stats.samples_received);
-        println!("   - Connection duration: {:.2}s", stats.connection_duration_seconds);
+        info!("📊 LSL Manager stopped:");
+        info!("   - Streams discovered: {}", stats.streams_discovered);
+        info!("   - Samples received: {}", stats.samples_received);
+        info!("   - Connection duration: {:.2}s", stats.connection_duration_seconds);
         if let Some(ref stream) = stats.final_stream {
-            println!("   - Final stream: {} ({}Hz, {} channels)", 
+            info!("   - Final stream: {} ({}Hz, {} channels)", 
                 stream.name, stream.sample_rate, stream.channels_count);
         }
         
@@ -199,31 +616,121 @@ impl LslManager {
     }
     
     // 工作线程 - 同步代码
+    #[allow(clippy::too_many_arguments)]
     fn worker_thread(
+        mut source: Box<dyn StreamSource>,
         control_rx: mpsc::Receiver<ControlCommand>,
-        data_tx: crossbeam_channel::Sender<EegSample>,
+        data_tx: crossbeam_channel::Sender<EegChunk>,
+        marker_tx: crossbeam_channel::Sender<LslMarker>,
+        aux_tx: crossbeam_channel::Sender<AuxSample>,
+        impedance_tx: crossbeam_channel::Sender<AuxSample>,
+        discovery_tx: crossbeam_channel::Sender<DiscoveryEvent>,
+        clock_tx: crossbeam_channel::Sender<ClockOffsetSample>,
+        clock_offsets: Arc<std::sync::Mutex<VecDeque<ClockOffsetSample>>>,
+        source_event_tx: crossbeam_channel::Sender<SourceEvent>,
+        stream_lost: Arc<AtomicBool>,
+        effective_rate: Arc<AtomicU64>,
+        pull_stats: Arc<StageStats>,
+        chunk_config: ChunkConfig,
+        rate_monitor: RateMonitorConfig,
     ) {
-        println!("🔄 LSL worker thread started");
+        info!("🔄 LSL worker thread started");
         
-        let mut current_inlet: Option<lsl::StreamInlet> = None;
+        let clock = source.clock();
+        let mut current_inlet: Option<Box<dyn SampleInlet>> = None;
+        let mut chunk_config = chunk_config;
+        let mut pending_chunk: EegChunk = Vec::with_capacity(chunk_config.chunk_size);
+        let mut pull_buffer = PullBuffer::new(0);
+        let mut chunk_started: Option<std::time::Instant> = None;
+        let mut last_reported_thousand = 0u64;
+        let mut marker_inlet: Option<(Box<dyn MarkerInlet>, String)> = None;
+        let mut aux_inlet: Option<AuxInlet> = None;
+        let mut impedance_inlet: Option<AuxInlet> = None;
+        let mut discovery: Option<StreamDiscovery> = None;
+        let mut last_clock_check: Option<std::time::Instant> = None;
         let mut sample_count = 0u64;
+        let mut last_sample_count = 0u64;
         let mut discovery_count = 0u32;
         let start_time = std::time::Instant::now();
+        let mut last_data_at = std::time::Instant::now();
+        let mut rate_estimator = RateEstimator::new(rate_monitor, 0.0);
         
         loop {
             // 检查控制命令
             match control_rx.try_recv() {
-                Ok(ControlCommand::DiscoverStreams { response_tx }) => {
-                    let result = Self::discover_streams_impl();
+                Ok(ControlCommand::DiscoverStreams { filter, response_tx }) => {
+                    let result = Self::discover_streams_impl(source.as_mut(), &filter);
                     if result.is_ok() {
                         discovery_count += 1;
                     }
                     let _ = response_tx.send(result);
                 }
-                Ok(ControlCommand::ConnectToStream { name, response_tx }) => {
-                    let result = Self::connect_to_stream_impl(&name, &mut current_inlet);
+                Ok(ControlCommand::ConnectToStream { selector, strict, inlet_config, response_tx }) => {
+                    let result = Self::connect_to_stream_impl(source.as_mut(), &selector, strict, &inlet_config, &mut current_inlet);
+                    if let Ok((stream_info, _)) = &result {
+                        // 缓冲区按连接流的实际通道数分配
+                        pull_buffer = PullBuffer::new(stream_info.channels_count as usize);
+                        last_data_at = std::time::Instant::now();
+                        stream_lost.store(false, Ordering::Relaxed);
+                        rate_estimator = RateEstimator::new(rate_estimator.config, stream_info.sample_rate);
+                        effective_rate.store(0, Ordering::Relaxed);
+                        last_clock_check = None;
+                        if let Ok(mut offsets) = clock_offsets.lock() {
+                            offsets.clear();
+                        }
+                        let _ = source_event_tx.send(SourceEvent::Connected);
+                    }
+                    pending_chunk.clear();
+                    chunk_started = None;
+                    let _ = response_tx.send(result);
+                }
+                Ok(ControlCommand::ConnectMarkerStream { name, response_tx }) => {
+                    let result = Self::connect_marker_stream_impl(source.as_mut(), &name, &mut marker_inlet);
+                    let _ = response_tx.send(result);
+                }
+                Ok(ControlCommand::SetChunkConfig(config)) => {
+                    info!("📦 Chunk config updated: {} samples / {}ms", config.chunk_size, config.timeout_ms);
+                    chunk_config = config;
+                }
+                Ok(ControlCommand::SetRateMonitor(config)) => {
+                    info!("⏱️  Rate monitor updated: {}s window, ±{}%", config.window_seconds, config.tolerance_percent);
+                    rate_estimator = RateEstimator::new(config, rate_estimator.nominal_rate);
+                    effective_rate.store(0, Ordering::Relaxed);
+                }
+                Ok(ControlCommand::DisconnectMarkerStream) => {
+                    if let Some((_, name)) = marker_inlet.take() {
+                        info!("🔌 Marker stream disconnected: {}", name);
+                    }
+                }
+                Ok(ControlCommand::ConnectAuxStream { name, response_tx }) => {
+                    let result = Self::connect_aux_stream_impl(source.as_mut(), &name, &mut aux_inlet);
+                    let _ = response_tx.send(result);
+                }
+                Ok(ControlCommand::DisconnectAuxStream) => {
+                    if aux_inlet.take().is_some() {
+                        info!("🔌 Aux stream disconnected");
+                    }
+                }
+                Ok(ControlCommand::ConnectImpedanceStream { name, response_tx }) => {
+                    let result = Self::connect_impedance_stream_impl(source.as_mut(), &name, &mut impedance_inlet);
                     let _ = response_tx.send(result);
                 }
+                Ok(ControlCommand::DisconnectImpedanceStream) => {
+                    if impedance_inlet.take().is_some() {
+                        info!("🔌 Impedance stream disconnected");
+                    }
+                }
+                Ok(ControlCommand::StartDiscovery(filter)) => {
+                    // 替换过滤条件时保留已知列表，不符合新条件的流随下一次轮询报告消失
+                    let known = discovery.take().map(|d| d.known).unwrap_or_default();
+                    match source.continuous_resolver() {
+                        Ok(resolver) => {
+                            info!("🛰️  Background discovery started ({:?})", filter);
+                            discovery = Some(StreamDiscovery { resolver, filter, known, last_poll: None });
+                        }
+                        Err(e) => error!("❌ Failed to create continuous resolver: {}", e),
+                    }
+                }
                 Ok(ControlCommand::GetStats { response_tx }) => {
                     let stats = WorkerStats {
                         samples_processed: sample_count,
@@ -233,135 +740,439 @@ impl LslManager {
                     let _ = response_tx.send(stats);
                 }
                 Ok(ControlCommand::Stop) => {
-                    println!("🛑 Worker received stop command");
+                    info!("🛑 Worker received stop command");
                     break;
                 }
                 Err(mpsc::TryRecvError::Empty) => {
                     // 没有命令，继续数据处理
                 }
                 Err(mpsc::TryRecvError::Disconnected) => {
-                    println!("🔌 Control channel disconnected");
+                    info!("🔌 Control channel disconnected");
                     break;
                 }
             }
             
-            // 处理数据
-            if let Some(inlet) = &current_inlet {
-                // ✅ 根据LSL示例修正数据接收
-                let mut sample_data = vec![0.0f64; 32]; // 预分配缓冲区，支持最多32通道
-                
-                match inlet.pull_sample_buf(&mut sample_data, 0.0) {
-                    Ok(timestamp) if timestamp > 0.0 => {
-                        // 获取实际的通道数
-                        let info = inlet.info(0.0);
-                        let channel_count = if let Ok(info) = info {
-                            info.channel_count() as usize
-                        } else {
-                            sample_data.len()
-                        };
-                        
-                        // 只取实际使用的通道
-                        sample_data.truncate(channel_count);
-                        
-                        // ✅ 修复：添加缺失的 sample_id 字段
-                        let sample = EegSample {
-                            timestamp,
-                            channels: sample_data,
-                            sample_id: sample_count,  // ✅ 使用样本计数作为ID
-                        };
-                        
-                        if data_tx.send(sample).is_err() {
-                            println!("📡 Data receiver dropped, stopping");
+            // 标记流：取出所有已到达的标记（不规则采样率，非阻塞）
+            if let Some((inlet, stream_name)) = &mut marker_inlet {
+                loop {
+                    match inlet.pull_marker() {
+                        Ok(Some((values, timestamp))) => {
+                            let marker = LslMarker {
+                                label: values.join("|"),
+                                timestamp,
+                                stream_name: stream_name.clone(),
+                            };
+                            if marker_tx.send(marker).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            error!("❌ Marker inlet error: {}", e);
                             break;
                         }
-                        
-                        sample_count += 1;
-                        
-                        // 每1000个样本打印一次状态
-                        if sample_count % 1000 == 0 {
-                            println!("📊 Processed {} samples", sample_count);
+                    }
+                }
+            }
+            
+            if let Some(discovery) = discovery.as_mut() {
+                for event in discovery.poll() {
+                    if discovery_tx.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+            
+            // 时钟偏移：定期测量（首次测量可能阻塞至超时，inlet缓冲保证不丢数据）
+            if let Some(inlet) = &mut current_inlet {
+                if last_clock_check.is_none_or(|at| at.elapsed() >= CLOCK_OFFSET_INTERVAL) {
+                    last_clock_check = Some(std::time::Instant::now());
+                    match inlet.time_correction(CLOCK_OFFSET_TIMEOUT_SECONDS) {
+                        Ok(offset) => {
+                            let sample = ClockOffsetSample { local_time: clock(), offset, unix_time: unix_now() };
+                            if let Ok(mut offsets) = clock_offsets.lock() {
+                                if offsets.len() == CLOCK_OFFSET_HISTORY {
+                                    offsets.pop_front();
+                                }
+                                offsets.push_back(sample);
+                            }
+                            let _ = clock_tx.send(sample);
+                        }
+                        Err(e) => debug!("⏱️  Clock offset measurement failed: {}", e),
+                    }
+                }
+            }
+            
+            // 辅助流：非阻塞取出已到达的样本，时间戳与EEG同为时钟同步后的LSL时间
+            if let Some((inlet, channel_labels)) = &mut aux_inlet {
+                match inlet.pull_chunk() {
+                    Ok((values, timestamps)) => {
+                        for (values, timestamp) in values.into_iter().zip(timestamps) {
+                            let sample = AuxSample { timestamp, values, channel_labels: channel_labels.clone() };
+                            if aux_tx.send(sample).is_err() {
+                                break;
+                            }
                         }
                     }
+                    Err(e) => error!("❌ Aux inlet error: {}", e),
+                }
+            }
+            
+            // 阻抗流：通常1Hz左右，非阻塞取出
+            if let Some((inlet, channel_labels)) = &mut impedance_inlet {
+                match inlet.pull_chunk() {
+                    Ok((values, timestamps)) => {
+                        for (values, timestamp) in values.into_iter().zip(timestamps) {
+                            let sample = AuxSample { timestamp, values, channel_labels: channel_labels.clone() };
+                            if impedance_tx.send(sample).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(e) => error!("❌ Impedance inlet error: {}", e),
+                }
+            }
+            
+            // 处理数据 - 按块拉取，凑满块或超时后整块发送
+            if let Some(inlet) = &mut current_inlet {
+                match inlet.pull_chunk() {
+                    Ok((values, timestamps)) if !timestamps.is_empty() => {
+                        pull_buffer.append_chunk(values, timestamps, &mut sample_count, &mut pending_chunk);
+                    }
                     Ok(_) => {
-                        // 没有数据，短暂休眠
-                        thread::sleep(Duration::from_millis(1));
+                        // inlet中暂无数据：阻塞等待下一个样本，避免忙等
+                        let pulled = inlet.pull_sample(&mut pull_buffer.sample, chunk_config.timeout().as_secs_f64());
+                        match pulled {
+                            Ok(timestamp) if timestamp > 0.0 => {
+                                pull_buffer.append_buffered(timestamp, &mut sample_count, &mut pending_chunk);
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                error!("❌ Inlet error: {}", e);
+                                thread::sleep(Duration::from_millis(100)); // 错误后稍长休眠
+                            }
+                        }
                     }
                     Err(e) => {
-                        println!("❌ LSL inlet error: {:?}", e);
+                        error!("❌ Inlet error: {}", e);
                         thread::sleep(Duration::from_millis(100)); // 错误后稍长休眠
                     }
                 }
+                
+                if !pending_chunk.is_empty() && chunk_started.is_none() {
+                    chunk_started = Some(std::time::Instant::now());
+                }
+                
+                // 实际采样率估计：每次拉取后以最新样本更新窗口
+                if let Some(last) = pending_chunk.last() {
+                    if let Some(rate) = rate_estimator.update(last.timestamp, sample_count) {
+                        effective_rate.store(rate.to_bits(), Ordering::Relaxed);
+                    }
+                    if let Some(deviating) = rate_estimator.check_deviation() {
+                        let event = if deviating { SourceEvent::RateDeviation } else { SourceEvent::RateNormal };
+                        let _ = source_event_tx.send(event);
+                    }
+                }
+                
+                // 数据中断检测：LSL inlet会自动重连，这里只报告状态变化
+                if sample_count > last_sample_count {
+                    last_sample_count = sample_count;
+                    last_data_at = std::time::Instant::now();
+                    if stream_lost.swap(false, Ordering::Relaxed) {
+                        info!("✅ LSL stream recovered");
+                        let _ = source_event_tx.send(SourceEvent::Recovered);
+                    }
+                } else if last_data_at.elapsed() >= STREAM_LOST_TIMEOUT && !stream_lost.swap(true, Ordering::Relaxed) {
+                    warn!("⚠️  LSL stream lost: no data for {:?}", STREAM_LOST_TIMEOUT);
+                    let _ = source_event_tx.send(SourceEvent::Lost);
+                }
+                
+                // 满块立即发送；不满的块等待超时后发送，保证显示延迟有上限
+                let mut receiver_dropped = false;
+                while pending_chunk.len() >= chunk_config.chunk_size {
+                    let chunk: EegChunk = pending_chunk.drain(..chunk_config.chunk_size).collect();
+                    receiver_dropped |= !Self::deliver_chunk(&data_tx, chunk, &pull_stats, &clock);
+                }
+                let timed_out = chunk_started.is_some_and(|started| started.elapsed() >= chunk_config.timeout());
+                if !pending_chunk.is_empty() && timed_out {
+                    receiver_dropped |= !Self::deliver_chunk(&data_tx, std::mem::take(&mut pending_chunk), &pull_stats, &clock);
+                }
+                if pending_chunk.is_empty() {
+                    chunk_started = None;
+                }
+                
+                if receiver_dropped {
+                    info!("📡 Data receiver dropped, stopping");
+                    break;
+                }
+                
+                // 每1000个样本打印一次状态
+                if sample_count / 1000 > last_reported_thousand {
+                    last_reported_thousand = sample_count / 1000;
+                    debug!("📊 Processed {} samples", sample_count);
+                }
             } else {
                 // 没有连接，休眠更长时间
                 thread::sleep(Duration::from_millis(10));
             }
         }
         
-        println!("🔄 LSL worker thread stopped, processed {} samples", sample_count);
+        info!("🔄 LSL worker thread stopped, processed {} samples", sample_count);
     }
     
-    fn discover_streams_impl() -> Result<Vec<LslStreamInfo>, AppError> {
-        println!("🔍 Discovering LSL streams...");
-        // 最宽松，发现所有流
-        let streams = match lsl::resolve_streams(2.0) {
-            Ok(s) => s,
-            Err(e) => {
-                println!("⚠️  resolve_streams error: {:?}", e);
-                vec![]
-            }
-        };
+    /// 发送样本块并记录数据年龄（最早样本的LSL时间戳到交付时刻），接收端已断开时返回 false
+    fn deliver_chunk(
+        data_tx: &crossbeam_channel::Sender<EegChunk>, chunk: EegChunk, pull_stats: &StageStats, clock: &SourceClock
+    ) -> bool {
+        if let Some(first) = chunk.first() {
+            pull_stats.record_seconds(clock() - first.timestamp, chunk.len() as u64);
+        }
+        data_tx.send(chunk).is_ok()
+    }
+    
+    fn discover_streams_impl(source: &mut dyn StreamSource, filter: &StreamFilter) -> Result<Vec<LslStreamInfo>, AppError> {
+        info!("🔍 Discovering LSL streams ({:?})...", filter);
+        let streams = source.resolve_streams()?;
         for stream in &streams {
-            println!("发现流: name={}, type={}, source_id={}", stream.stream_name(), stream.stream_type(), stream.source_id());
+            info!("发现流: name={}, type={}, source_id={}", stream.name, stream.stream_type, stream.source_id);
         }
-        let lsl_streams = streams.iter().map(|stream| LslStreamInfo {
+        Ok(streams.into_iter().filter(|stream| filter.matches(stream)).collect())
+    }
+    
+    fn connect_to_stream_impl(
+        source: &mut dyn StreamSource,
+        selector: &StreamSelector, 
+        strict: bool,
+        inlet_config: &LslInletConfig,
+        current_inlet: &mut Option<Box<dyn SampleInlet>>
+    ) -> Result<ConnectedStream, AppError> {
+        info!("🔌 Connecting to stream: {}", selector.predicate());
+        
+        let EegConnection { info, inlet, impedances } = source.connect(selector, strict, inlet_config)?;
+        *current_inlet = inlet;
+        Ok((info, impedances))
+    }
+    
+    fn connect_marker_stream_impl(
+        source: &mut dyn StreamSource,
+        name: &str,
+        marker_inlet: &mut Option<(Box<dyn MarkerInlet>, String)>
+    ) -> Result<LslStreamInfo, AppError> {
+        info!("🏷️  Connecting to marker stream: {}", name);
+        
+        let (marker_info, inlet) = source.connect_marker(name)?;
+        *marker_inlet = Some((inlet, marker_info.name.clone()));
+        
+        info!("✅ Connected to marker stream: {}", name);
+        Ok(marker_info)
+    }
+    
+    fn connect_aux_stream_impl(
+        source: &mut dyn StreamSource,
+        name: &str,
+        aux_inlet: &mut Option<AuxInlet>
+    ) -> Result<StreamInfo, AppError> {
+        info!("🧭 Connecting to aux stream: {}", name);
+        
+        let (aux_info, inlet) = source.connect_aux(name)?;
+        *aux_inlet = Some((inlet, aux_info.channel_labels.clone().into()));
+        
+        info!("✅ Connected to aux stream: {} ({} channels @ {}Hz)", name, aux_info.channels_count, aux_info.sample_rate);
+        Ok(aux_info)
+    }
+    
+    fn connect_impedance_stream_impl(
+        source: &mut dyn StreamSource,
+        name: &str,
+        impedance_inlet: &mut Option<AuxInlet>
+    ) -> Result<StreamInfo, AppError> {
+        info!("🔋 Connecting to impedance stream: {}", name);
+        
+        let (impedance_info, inlet) = source.connect_impedance(name)?;
+        *impedance_inlet = Some((inlet, impedance_info.channel_labels.clone().into()));
+        
+        info!("✅ Connected to impedance stream: {} ({} electrodes)", name, impedance_info.channels_count);
+        Ok(impedance_info)
+    }
+}
+
+/// LSL数据源 - 通过 liblsl 发现局域网内的流并创建inlet
+pub struct LslSource;
+
+impl LslSource {
+    fn stream_summary(stream: &lsl::StreamInfo) -> LslStreamInfo {
+        LslStreamInfo {
             name: stream.stream_name(),
             stream_type: stream.stream_type(),
             channels_count: stream.channel_count() as u32,
             sample_rate: stream.nominal_srate(),
             source_id: stream.source_id(),
             hostname: stream.hostname(),
-        }).collect();
-        Ok(lsl_streams)
+        }
     }
     
-    fn connect_to_stream_impl(
-        name: &str, 
-        current_inlet: &mut Option<lsl::StreamInlet>
-    ) -> Result<StreamInfo, AppError> {
-        println!("🔌 Connecting to stream: {}", name);
+    /// 从流描述 <channels><channel><label> 读取通道标签，缺失项用 ChN 补齐
+    fn read_channel_labels(info: &mut lsl::StreamInfo, channels_count: u32) -> Vec<String> {
+        let mut labels = StreamInfo::default_channel_labels(channels_count);
+        let mut channel = info.desc().child("channels").child("channel");
+        let mut index = 0;
         
-        // ✅ 使用真实的LSL连接
+        while channel.is_valid() && index < labels.len() {
+            let label = channel.child_value_named("label");
+            if !label.trim().is_empty() {
+                labels[index] = label.trim().to_string();
+            }
+            channel = channel.next_sibling();
+            index += 1;
+        }
+        
+        labels
+    }
+    
+    /// 从流描述 <channels><channel><impedance> 读取通道阻抗（kΩ）；没有任何通道提供时返回空
+    fn read_channel_impedances(info: &mut lsl::StreamInfo, channels_count: u32) -> Vec<Option<f64>> {
+        let mut impedances = vec![None; channels_count as usize];
+        let mut channel = info.desc().child("channels").child("channel");
+        let mut index = 0;
+        
+        while channel.is_valid() && index < impedances.len() {
+            impedances[index] = channel.child_value_named("impedance").trim().parse::<f64>().ok()
+                .filter(|kohm| kohm.is_finite() && *kohm >= 0.0);
+            channel = channel.next_sibling();
+            index += 1;
+        }
+        
+        if impedances.iter().all(Option::is_none) {
+            impedances.clear();
+        }
+        impedances
+    }
+    
+    /// 连接数值型的辅助流或阻抗流；check 校验流类型
+    fn connect_numeric(
+        name: &str,
+        kind: &str,
+        check: fn(&str, &str) -> Result<(), AppError>
+    ) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
         let predicate = format!("name='{}'", name);
+        let streams = lsl::resolve_bypred(&predicate, 1, 5.0)
+            .map_err(|e| AppError::Lsl(format!("LSL resolve error: {:?}", e)))?;
+        let stream = streams.first()
+            .ok_or_else(|| AppError::StreamNotFound(name.to_string()))?;
+        
+        let stream_type = stream.stream_type();
+        check(name, &stream_type)?;
+        if matches!(stream.channel_format(), lsl::ChannelFormat::String) {
+            return Err(AppError::Lsl(format!("{} stream '{}' is not numeric", kind, name)));
+        }
+        
+        let inlet = lsl::StreamInlet::new(stream, 360, 0, true)
+            .map_err(|e| AppError::Lsl(format!("Failed to create {} inlet: {:?}", kind, e)))?;
+        
+        let mut post_processing = vec![lsl::ProcessingOption::ClockSync];
+        if stream.nominal_srate() != lsl::IRREGULAR_RATE {
+            post_processing.push(lsl::ProcessingOption::Dejitter);
+        }
+        if let Err(e) = inlet.set_postprocessing(&post_processing) {
+            warn!("⚠️  Failed to set {} post-processing: {:?}", kind, e);
+        }
+        
+        let channels_count = stream.channel_count() as u32;
+        let channel_labels = match inlet.info(2.0) {
+            Ok(mut full_info) => Self::read_channel_labels(&mut full_info, channels_count),
+            Err(e) => {
+                warn!("⚠️  Failed to read full {} stream info: {:?}", kind, e);
+                StreamInfo::default_channel_labels(channels_count)
+            }
+        };
+        
+        let info = StreamInfo {
+            name: stream.stream_name(),
+            stream_type,
+            channels_count,
+            sample_rate: stream.nominal_srate(),
+            is_connected: true,
+            source_id: stream.source_id(),
+            channel_labels,
+            effective_sample_rate: None,
+        };
+        
+        Ok((info, Box::new(LslInlet(inlet))))
+    }
+}
+
+impl StreamSource for LslSource {
+    fn clock(&self) -> SourceClock {
+        Arc::new(lsl::local_clock)
+    }
+    
+    fn resolve_streams(&mut self) -> Result<Vec<LslStreamInfo>, AppError> {
+        // 最宽松，发现所有流
+        let streams = match lsl::resolve_streams(2.0) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("⚠️  resolve_streams error: {:?}", e);
+                vec![]
+            }
+        };
+        Ok(streams.iter().map(Self::stream_summary).collect())
+    }
+    
+    fn connect(&mut self, selector: &StreamSelector, strict: bool, inlet_config: &LslInletConfig) -> Result<EegConnection, AppError> {
+        let name = selector.name.as_str();
+        
+        // ✅ 使用真实的LSL连接
+        let predicate = selector.predicate();
         
         match lsl::resolve_bypred(&predicate, 1, 10.0) {
+            // 同名设备：不猜测，要求按 source_id 连接
+            Ok(streams) if streams.len() > 1 && !selector.has_source_id() => {
+                let source_ids: Vec<String> = streams.iter().map(|stream| stream.source_id()).collect();
+                Err(AppError::Lsl(format!(
+                    "{} streams are named '{}' (source ids {:?}); connect by source_id", streams.len(), name, source_ids
+                )))
+            }
             Ok(streams) if !streams.is_empty() => {
                 let stream = &streams[0];
                 
                 // 创建inlet
-                match lsl::StreamInlet::new(stream, 300, 0, true) {
+                match lsl::StreamInlet::new(
+                    stream, inlet_config.buffer_seconds, inlet_config.max_chunk_length, inlet_config.recover
+                ) {
                     Ok(inlet) => {
+                        let channels_count = stream.channel_count() as u32;
+                        
+                        // 解析时的StreamInfo不含desc，需从inlet获取完整信息
+                        let (channel_labels, impedances) = match inlet.info(2.0) {
+                            Ok(mut full_info) => (
+                                Self::read_channel_labels(&mut full_info, channels_count),
+                                Self::read_channel_impedances(&mut full_info, channels_count),
+                            ),
+                            Err(e) => {
+                                warn!("⚠️  Failed to read full stream info: {:?}", e);
+                                (StreamInfo::default_channel_labels(channels_count), Vec::new())
+                            }
+                        };
+                        
                         // ✅ 修复：添加缺失的字段
                         let stream_info = StreamInfo {
                             name: stream.stream_name(),                    // ✅ 修复
                             stream_type: stream.stream_type(),             // ✅ 新增：流类型
-                            channels_count: stream.channel_count() as u32, // ✅ 修复
+                            channels_count,                                // ✅ 修复
                             sample_rate: stream.nominal_srate(),           // ✅ 修复
                             is_connected: true,                            // ✅ 新增：连接状态
                             source_id: stream.source_id(),                 // ✅ 修复
+                            channel_labels,
+                            effective_sample_rate: None,
                         };
                         
                         // 设置后处理选项
-                        if let Err(e) = inlet.set_postprocessing(&[
-                            lsl::ProcessingOption::ClockSync,
-                            lsl::ProcessingOption::Dejitter,
-                        ]) {
-                            println!("⚠️  Failed to set post-processing: {:?}", e);
+                        if let Err(e) = inlet.set_postprocessing(&inlet_config.processing_options()) {
+                            warn!("⚠️  Failed to set post-processing: {:?}", e);
                         }
                         
-                        *current_inlet = Some(inlet);
-                        
-                        println!("✅ Connected to LSL stream: {}", name);
-                        Ok(stream_info)
+                        info!("✅ Connected to LSL stream: {}", name);
+                        Ok(EegConnection { info: stream_info, inlet: Some(Box::new(LslInlet(inlet))), impedances })
                     }
                     Err(e) => {
                         Err(AppError::Lsl(format!("Failed to create inlet: {:?}", e)))
@@ -369,10 +1180,16 @@ impl LslManager {
                 }
             }
             Ok(_) => {
-                Err(AppError::Lsl(format!("Stream '{}' not found", name)))
+                Err(AppError::StreamNotFound(name.to_string()))
+            }
+            Err(e) if strict => {
+                // Strict模式下不允许回退到模拟连接
+                Err(AppError::Lsl(format!(
+                    "LSL resolve error: {:?} (mock fallback disabled in strict mode)", e
+                )))
             }
             Err(e) => {
-                println!("⚠️  LSL resolve error: {:?}, falling back to mock connection", e);
+                warn!("⚠️  LSL resolve error: {:?}, falling back to mock connection", e);
                 
                 // ✅ 修复：测试用的模拟连接，添加缺失字段
                 let stream_info = StreamInfo {
@@ -382,13 +1199,296 @@ impl LslManager {
                     sample_rate: 250.0,
                     is_connected: true,                                   // ✅ 新增：模拟连接成功
                     source_id: "mock_device_001".to_string(),
+                    channel_labels: StreamInfo::default_channel_labels(8),
+                    effective_sample_rate: None,
                 };
                 
                 // TODO: 在实际部署中移除这个mock
-                println!("🔧 Mock connection established for testing");
-                Ok(stream_info)
+                info!("🔧 Mock connection established for testing");
+                Ok(EegConnection { info: stream_info, inlet: None, impedances: Vec::new() })
+            }
+        }
+    }
+    
+    fn connect_marker(&mut self, name: &str) -> Result<(LslStreamInfo, Box<dyn MarkerInlet>), AppError> {
+        let predicate = format!("name='{}'", name);
+        let streams = lsl::resolve_bypred(&predicate, 1, 5.0)
+            .map_err(|e| AppError::Lsl(format!("LSL resolve error: {:?}", e)))?;
+        let stream = streams.first()
+            .ok_or_else(|| AppError::StreamNotFound(name.to_string()))?;
+        
+        if !matches!(stream.channel_format(), lsl::ChannelFormat::String) {
+            return Err(AppError::Lsl(format!(
+                "Stream '{}' is not a string-format marker stream", name
+            )));
+        }
+        if stream.nominal_srate() != lsl::IRREGULAR_RATE {
+            warn!("⚠️  Marker stream '{}' has regular rate {}Hz", name, stream.nominal_srate());
+        }
+        
+        let inlet = lsl::StreamInlet::new(stream, 360, 0, true)
+            .map_err(|e| AppError::Lsl(format!("Failed to create marker inlet: {:?}", e)))?;
+        
+        // 只做时钟同步，不规则流不适合去抖动
+        if let Err(e) = inlet.set_postprocessing(&[lsl::ProcessingOption::ClockSync]) {
+            warn!("⚠️  Failed to set marker post-processing: {:?}", e);
+        }
+        
+        Ok((Self::stream_summary(stream), Box::new(LslInlet(inlet))))
+    }
+    
+    fn connect_aux(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
+        Self::connect_numeric(name, "aux", stream_source::check_aux_type)
+    }
+    
+    fn connect_impedance(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
+        Self::connect_numeric(name, "impedance", stream_source::check_impedance_type)
+    }
+    
+    fn continuous_resolver(&mut self) -> Result<Box<dyn StreamResolver>, AppError> {
+        let resolver = lsl::ContinuousResolver::new(DISCOVERY_FORGET_AFTER_SECONDS)
+            .map_err(|e| AppError::Lsl(format!("{:?}", e)))?;
+        Ok(Box::new(LslResolver(resolver)))
+    }
+}
+
+/// LSL inlet，数值流与标记流共用
+struct LslInlet(lsl::StreamInlet);
+
+fn lsl_error(e: lsl::Error) -> AppError {
+    AppError::Lsl(format!("{:?}", e))
+}
+
+impl SampleInlet for LslInlet {
+    fn pull_chunk(&mut self) -> Result<(Vec<Vec<f64>>, Vec<f64>), AppError> {
+        self.0.pull_chunk().map_err(lsl_error)
+    }
+    
+    fn pull_sample(&mut self, buffer: &mut Vec<f64>, timeout: f64) -> Result<f64, AppError> {
+        self.0.pull_sample_buf(buffer, timeout).map_err(lsl_error)
+    }
+    
+    fn time_correction(&mut self, timeout: f64) -> Result<f64, AppError> {
+        self.0.time_correction(timeout).map_err(lsl_error)
+    }
+}
+
+impl MarkerInlet for LslInlet {
+    fn pull_marker(&mut self) -> Result<Option<(Vec<String>, f64)>, AppError> {
+        let pulled: Result<(Vec<String>, f64), _> = self.0.pull_sample(0.0);
+        match pulled.map_err(lsl_error)? {
+            (values, timestamp) if timestamp > 0.0 && !values.is_empty() => Ok(Some((values, timestamp))),
+            _ => Ok(None),
+        }
+    }
+}
+
+struct LslResolver(lsl::ContinuousResolver);
+
+impl StreamResolver for LslResolver {
+    fn results(&mut self) -> Result<Vec<LslStreamInfo>, AppError> {
+        let streams = self.0.results().map_err(lsl_error)?;
+        Ok(streams.iter().map(LslSource::stream_summary).collect())
+    }
+}
+
+/// 本机墙钟（Unix秒）
+fn unix_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// 发现结果中的流标识：名称、类型、source_id、主机名
+type StreamKey = (String, String, String, String);
+
+fn stream_key(stream: &LslStreamInfo) -> StreamKey {
+    (stream.name.clone(), stream.stream_type.clone(), stream.source_id.clone(), stream.hostname.clone())
+}
+
+/// 后台流发现 - 按间隔读取持续解析器的结果，与已知列表比较得到出现/消失事件
+struct StreamDiscovery {
+    resolver: Box<dyn StreamResolver>,
+    filter: StreamFilter,
+    known: HashMap<StreamKey, LslStreamInfo>,
+    last_poll: Option<std::time::Instant>,
+}
+
+impl StreamDiscovery {
+    fn poll(&mut self) -> Vec<DiscoveryEvent> {
+        if self.last_poll.is_some_and(|at| at.elapsed() < DISCOVERY_POLL_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_poll = Some(std::time::Instant::now());
+        
+        let streams = match self.resolver.results() {
+            Ok(streams) => streams,
+            Err(e) => {
+                warn!("⚠️  Continuous resolver error: {}", e);
+                return Vec::new();
+            }
+        };
+        let current = streams.into_iter()
+            .filter(|stream| self.filter.matches(stream))
+            .collect();
+        
+        Self::diff(&mut self.known, current)
+    }
+    
+    /// 更新已知列表并返回变化
+    fn diff(known: &mut HashMap<StreamKey, LslStreamInfo>, current: Vec<LslStreamInfo>) -> Vec<DiscoveryEvent> {
+        let current: HashMap<StreamKey, LslStreamInfo> = current.into_iter().map(|stream| (stream_key(&stream), stream)).collect();
+        
+        let mut events: Vec<DiscoveryEvent> = known.iter()
+            .filter(|(id, _)| !current.contains_key(*id))
+            .map(|(_, stream)| DiscoveryEvent::Disappeared(stream.clone()))
+            .collect();
+        events.extend(current.iter()
+            .filter(|(id, _)| !known.contains_key(*id))
+            .map(|(_, stream)| DiscoveryEvent::Appeared(stream.clone())));
+        
+        for event in &events {
+            match event {
+                DiscoveryEvent::Appeared(stream) => info!("🛰️  Stream appeared: {} ({})", stream.name, stream.stream_type),
+                DiscoveryEvent::Disappeared(stream) => info!("🛰️  Stream disappeared: {} ({})", stream.name, stream.stream_type),
             }
         }
+        
+        *known = current;
+        events
+    }
+}
+
+/// 实际采样率估计器 - 在滑动窗口内以 (样本数差 / LSL时间戳差) 估计
+struct RateEstimator {
+    config: RateMonitorConfig,
+    nominal_rate: f64,
+    points: VecDeque<(f64, u64)>,   // (最新样本时间戳, 累计样本数)
+    estimate: Option<f64>,
+    deviating: bool,
+}
+
+impl RateEstimator {
+    // 相邻检查点间隔超过该时长（流中断或时钟跳变）时重新开始估计
+    const RESET_GAP_SECONDS: f64 = 1.0;
+    
+    fn new(config: RateMonitorConfig, nominal_rate: f64) -> Self {
+        Self {
+            config,
+            nominal_rate,
+            points: VecDeque::new(),
+            estimate: None,
+            deviating: false,
+        }
+    }
+    
+    fn deviation_percent(nominal: f64, effective: f64) -> f64 {
+        (effective - nominal) / nominal * 100.0
+    }
+    
+    /// 加入检查点，窗口基本填满后返回最新估计
+    fn update(&mut self, timestamp: f64, sample_count: u64) -> Option<f64> {
+        if let Some(&(last_timestamp, last_count)) = self.points.back() {
+            if timestamp <= last_timestamp || sample_count <= last_count {
+                return None;
+            }
+            if timestamp - last_timestamp > Self::RESET_GAP_SECONDS {
+                self.points.clear();
+                self.estimate = None;
+            }
+        }
+        
+        self.points.push_back((timestamp, sample_count));
+        while self.points.front().is_some_and(|&(first, _)| timestamp - first > self.config.window_seconds) {
+            self.points.pop_front();
+        }
+        
+        let &(first_timestamp, first_count) = self.points.front()?;
+        let span = timestamp - first_timestamp;
+        
+        // 窗口填满90%之前估计不稳定，不报告
+        if span < self.config.window_seconds * 0.9 {
+            return None;
+        }
+        
+        self.estimate = Some((sample_count - first_count) as f64 / span);
+        self.estimate
+    }
+    
+    /// 偏差状态变化时返回新的状态（true为超出容差）
+    fn check_deviation(&mut self) -> Option<bool> {
+        let effective = self.estimate?;
+        if self.nominal_rate <= 0.0 {
+            return None;
+        }
+        
+        let deviation = Self::deviation_percent(self.nominal_rate, effective);
+        let deviating = deviation.abs() > self.config.tolerance_percent;
+        if deviating == self.deviating {
+            return None;
+        }
+        
+        self.deviating = deviating;
+        if deviating {
+            warn!("⚠️  Effective sample rate {:.3}Hz deviates {:+.2}% from nominal {}Hz", 
+                     effective, deviation, self.nominal_rate);
+        } else {
+            info!("✅ Effective sample rate {:.3}Hz back within tolerance", effective);
+        }
+        Some(deviating)
+    }
+}
+
+/// 按已连接流的通道数分配的拉取缓冲区
+/// 样本宽度与流的通道数不符时告警，绝不静默截断
+struct PullBuffer {
+    sample: Vec<f64>,
+    width_mismatches: u64,
+}
+
+impl PullBuffer {
+    fn new(channels_count: usize) -> Self {
+        Self {
+            sample: vec![0.0; channels_count],
+            width_mismatches: 0,
+        }
+    }
+    
+    /// 追加 pull_chunk 拉取到的样本块
+    fn append_chunk(
+        &mut self,
+        values: Vec<Vec<f64>>,
+        timestamps: Vec<f64>,
+        sample_count: &mut u64,
+        out: &mut EegChunk,
+    ) {
+        for (channels, timestamp) in values.into_iter().zip(timestamps) {
+            self.push(channels, timestamp, sample_count, out);
+        }
+    }
+    
+    /// 追加 pull_sample_buf 写入缓冲区的单个样本
+    fn append_buffered(&mut self, timestamp: f64, sample_count: &mut u64, out: &mut EegChunk) {
+        let channels = self.sample.clone();
+        self.push(channels, timestamp, sample_count, out);
+    }
+    
+    fn push(&mut self, channels: Vec<f64>, timestamp: f64, sample_count: &mut u64, out: &mut EegChunk) {
+        if channels.len() != self.sample.len() {
+            self.width_mismatches += 1;
+            if self.width_mismatches <= 5 {
+                warn!("⚠️  Sample #{} has {} channels, stream declares {}", 
+                         sample_count, channels.len(), self.sample.len());
+            }
+        }
+        
+        out.push(EegSample {
+            timestamp,
+            channels,
+            sample_id: *sample_count,  // ✅ 使用样本计数作为ID
+        });
+        *sample_count += 1;
     }
 }
 
@@ -399,4 +1499,166 @@ pub struct LslManagerStats {
     pub samples_received: u64,
     pub connection_duration_seconds: f64,
     pub final_stream: Option<StreamInfo>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream_source::{MockSource, MockStream};
+    
+    #[tokio::test]
+    async fn test_worker_delivers_mock_stream() {
+        let stream = MockStream::sine("MockEEG", 4, 1000.0).with_total_samples(100);
+        let source = MockSource::new()
+            .with_stream(stream.clone())
+            .with_markers("MockMarkers", vec![(0.0, "start".to_string())]);
+        let mut manager = LslManager::with_source(Box::new(source));
+        manager.set_chunk_config(ChunkConfig { chunk_size: 16, timeout_ms: 20 }).unwrap();
+        manager.start().await.unwrap();
+        
+        assert_eq!(manager.discover_streams(StreamFilter::default()).await.unwrap().len(), 2);
+        let selector = StreamSelector { name: "MockEEG".to_string(), source_id: None, hostname: None };
+        let info = manager.connect_to_stream(&selector).await.unwrap();
+        assert_eq!(info.channels_count, 4);
+        manager.connect_marker_stream("MockMarkers").await.unwrap();
+        
+        let data_rx = manager.get_data_receiver().unwrap();
+        let marker_rx = manager.get_marker_receiver().unwrap();
+        let mut samples = Vec::new();
+        while samples.len() < 100 {
+            samples.extend(data_rx.recv_timeout(Duration::from_secs(2)).unwrap());
+        }
+        
+        assert_eq!(samples.len(), 100);
+        for (index, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.sample_id, index as u64);
+            assert_eq!(sample.channels, stream.sample(index as u64));
+        }
+        assert!(samples.windows(2).all(|pair| (pair[1].timestamp - pair[0].timestamp - 0.001).abs() < 1e-9));
+        assert_eq!(marker_rx.recv_timeout(Duration::from_secs(1)).unwrap().label, "start");
+        
+        let stats = manager.stop().await.unwrap();
+        assert_eq!(stats.samples_received, 100);
+    }
+    
+    #[test]
+    fn test_rate_estimator_detects_drift() {
+        let config = RateMonitorConfig { window_seconds: 2.0, tolerance_percent: 1.0 };
+        let mut estimator = RateEstimator::new(config, 250.0);
+        
+        // 实际 255Hz（+2%），每块10个样本
+        let mut count = 0u64;
+        let mut last = None;
+        for i in 1..=100 {
+            count += 10;
+            let timestamp = i as f64 * 10.0 / 255.0;
+            last = estimator.update(timestamp, count).or(last);
+        }
+        
+        let rate = last.unwrap();
+        assert!((rate - 255.0).abs() < 1e-6);
+        assert_eq!(estimator.check_deviation(), Some(true));
+        assert_eq!(estimator.check_deviation(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_64_channel_stream_is_not_truncated() {
+        const CHANNELS: u32 = 64;
+        
+        // 64通道模拟流经工作线程的拉取与转换（块拉取与单样本拉取）到达 EegSample
+        let stream = MockStream::sine("Mock64", CHANNELS, 500.0).with_total_samples(300);
+        let mut manager = LslManager::with_source(Box::new(MockSource::new().with_stream(stream.clone())));
+        manager.set_chunk_config(ChunkConfig { chunk_size: 32, timeout_ms: 20 }).unwrap();
+        manager.start().await.unwrap();
+        
+        let selector = StreamSelector { name: "Mock64".to_string(), source_id: None, hostname: None };
+        let info = manager.connect_to_stream(&selector).await.unwrap();
+        assert_eq!(info.channels_count, CHANNELS);
+        assert_eq!(info.channel_labels.len(), CHANNELS as usize);
+        
+        let data_rx = manager.get_data_receiver().unwrap();
+        let mut samples = Vec::new();
+        while samples.len() < 300 {
+            samples.extend(data_rx.recv_timeout(Duration::from_secs(2)).unwrap());
+        }
+        
+        assert_eq!(samples.len(), 300);
+        for (index, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.sample_id, index as u64);
+            assert_eq!(sample.channels.len(), CHANNELS as usize);
+            assert_eq!(sample.channels, stream.sample(index as u64));
+        }
+        
+        let stats = manager.stop().await.unwrap();
+        assert_eq!(stats.samples_received, 300);
+    }
+    
+    #[test]
+    fn test_stream_filter() {
+        let stream = |name: &str, stream_type: &str| LslStreamInfo {
+            name: name.to_string(),
+            stream_type: stream_type.to_string(),
+            channels_count: 8,
+            sample_rate: 250.0,
+            source_id: String::new(),
+            hostname: "lab-pc".to_string(),
+        };
+        
+        assert!(StreamFilter::default().matches(&stream("Markers", "Markers")));
+        
+        let by_type = StreamFilter { stream_type: Some("emg".to_string()), ..StreamFilter::default() };
+        assert!(by_type.matches(&stream("Delsys", "EMG")));
+        assert!(!by_type.matches(&stream("OpenBCI", "EEG")));
+        
+        let by_name = StreamFilter { name_pattern: Some("openbci*-?".to_string()), ..StreamFilter::default() };
+        assert!(by_name.matches(&stream("OpenBCI_Cyton-1", "EEG")));
+        assert!(!by_name.matches(&stream("OpenBCI_Cyton-12", "EEG")));
+        
+        let by_host = StreamFilter { hostname: Some("other".to_string()), name_pattern: Some(" ".to_string()), ..StreamFilter::default() };
+        assert!(!by_host.matches(&stream("OpenBCI", "EEG")));
+    }
+    
+    #[test]
+    fn test_stream_selector_predicate() {
+        let by_name = StreamSelector { name: "EEG".to_string(), source_id: Some(" ".to_string()), hostname: None };
+        assert_eq!(by_name.predicate(), "name='EEG'");
+        assert!(!by_name.has_source_id());
+        assert_eq!(by_name.default_session_id(), "EEG");
+        
+        let by_source = StreamSelector::from(LslStreamInfo {
+            name: "Bob's \"amp\"".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 32,
+            sample_rate: 500.0,
+            source_id: "amp-2".to_string(),
+            hostname: "lab-pc".to_string(),
+        });
+        assert_eq!(
+            by_source.predicate(),
+            "name=concat('Bob', \"'\", 's \"amp\"') and source_id='amp-2' and hostname='lab-pc'"
+        );
+        assert_eq!(by_source.default_session_id(), "Bob's \"amp\"_amp-2");
+    }
+    
+    #[test]
+    fn test_discovery_diff_reports_changes() {
+        let stream = |name: &str| LslStreamInfo {
+            name: name.to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 8,
+            sample_rate: 250.0,
+            source_id: format!("{}-id", name),
+            hostname: "lab-pc".to_string(),
+        };
+        let mut known = HashMap::new();
+        
+        let events = StreamDiscovery::diff(&mut known, vec![stream("A"), stream("B")]);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|event| matches!(event, DiscoveryEvent::Appeared(_))));
+        
+        assert!(StreamDiscovery::diff(&mut known, vec![stream("B"), stream("A")]).is_empty());
+        
+        let events = StreamDiscovery::diff(&mut known, vec![stream("B"), stream("C")]);
+        assert_eq!(events, vec![DiscoveryEvent::Disappeared(stream("A")), DiscoveryEvent::Appeared(stream("C"))]);
+    }
+}
@@ -0,0 +1,369 @@
+use crate::data_types::*;
+use crate::pipeline_channel::ChannelCounters;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+// 每个阶段保留的最近延迟样本数（分位数与吞吐按此窗口计算）
+const STAGE_WINDOW: usize = 512;
+
+/// 管道运行指标 - 各线程以原子操作更新，get_system_health 读取快照
+/// 队列深度由生产者在发送后写入，不持有通道端点（避免影响断开检测）
+pub struct PipelineMetrics {
+    started_at: Instant,
+    
+    pub samples_distributed: AtomicU64,
+    pub samples_recorded: AtomicU64,
+    pub recording_errors: AtomicU64,
+    pub dropped_samples: AtomicU64,     // 下游通道断开导致未送达的样本
+    pub dropped_batches: AtomicU64,     // 前端未能按序发送而被清理的批次
+    pub data_gaps: AtomicU64,           // 数据源的样本丢失/时间间隙次数
+    
+    pub source_queue: AtomicUsize,      // 数据源 → 分发器（块）
+    pub recording_queue: AtomicUsize,   // 分发器 → 录制线程（块）
+    pub time_domain_queue: AtomicUsize, // 分发器 → 时域收集器（块）
+    pub frontend_queue: AtomicUsize,    // 收集器/FFT → 前端线程（批次）
+    
+    // 前端发送状况，由 FramePacer 更新
+    pub emit_latency_us: AtomicU64,     // 单帧发送耗时（平滑）
+    pub effective_frame_rate: AtomicU64, // 实际帧率（f64 位模式）
+    pub coalesced_batches: AtomicU64,   // 并入同一帧的积压批次数
+    pub frontend_under_load: AtomicBool,
+    
+    // 各阶段延迟与吞吐，由对应线程记录；LSL拉取阶段由数据源提供
+    pub lsl_pull: OnceLock<Arc<StageStats>>,
+    pub distributor: StageStats,
+    pub collector: StageStats,
+    pub fft: Arc<StageStats>,
+    pub emit: StageStats,
+    pub recording: StageStats,
+    
+    // 有界通道的丢弃与阻塞计数，由 PipelineSender 直接更新
+    pub recording_channel: ChannelCounters,
+    pub time_domain_channel: ChannelCounters,
+    pub fft_channel: ChannelCounters,
+    pub frontend_channel: ChannelCounters,
+}
+
+impl PipelineMetrics {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            samples_distributed: AtomicU64::new(0),
+            samples_recorded: AtomicU64::new(0),
+            recording_errors: AtomicU64::new(0),
+            dropped_samples: AtomicU64::new(0),
+            dropped_batches: AtomicU64::new(0),
+            data_gaps: AtomicU64::new(0),
+            source_queue: AtomicUsize::new(0),
+            recording_queue: AtomicUsize::new(0),
+            time_domain_queue: AtomicUsize::new(0),
+            frontend_queue: AtomicUsize::new(0),
+            emit_latency_us: AtomicU64::new(0),
+            effective_frame_rate: AtomicU64::new(0f64.to_bits()),
+            coalesced_batches: AtomicU64::new(0),
+            frontend_under_load: AtomicBool::new(false),
+            lsl_pull: OnceLock::new(),
+            distributor: StageStats::new(),
+            collector: StageStats::new(),
+            fft: Arc::new(StageStats::new()),
+            emit: StageStats::new(),
+            recording: StageStats::new(),
+            recording_channel: ChannelCounters::default(),
+            time_domain_channel: ChannelCounters::default(),
+            fft_channel: ChannelCounters::default(),
+            frontend_channel: ChannelCounters::default(),
+        }
+    }
+    
+    pub fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+    
+    pub fn set(gauge: &AtomicUsize, value: usize) {
+        gauge.store(value, Ordering::Relaxed);
+    }
+    
+    pub fn snapshot(&self, stream_id: &str) -> PipelineHealth {
+        PipelineHealth {
+            stream_id: stream_id.to_string(),
+            uptime_seconds: self.started_at.elapsed().as_secs(),
+            samples_distributed: self.samples_distributed.load(Ordering::Relaxed),
+            samples_recorded: self.samples_recorded.load(Ordering::Relaxed),
+            recording_errors: self.recording_errors.load(Ordering::Relaxed),
+            dropped_samples: self.dropped_samples.load(Ordering::Relaxed),
+            dropped_batches: self.dropped_batches.load(Ordering::Relaxed),
+            data_gaps: self.data_gaps.load(Ordering::Relaxed),
+            effective_sample_rate: None,    // 由数据源填写
+            queue_depths: QueueDepths {
+                source: self.source_queue.load(Ordering::Relaxed),
+                recording: self.recording_queue.load(Ordering::Relaxed),
+                time_domain: self.time_domain_queue.load(Ordering::Relaxed),
+                frontend: self.frontend_queue.load(Ordering::Relaxed),
+            },
+            overflow_drops: OverflowDrops {
+                recording: self.recording_channel.overflows(),
+                time_domain: self.time_domain_channel.overflows(),
+                fft: self.fft_channel.overflows(),
+                frontend: self.frontend_channel.overflows(),
+            },
+            backpressure: ChannelBackpressure {
+                recording_blocked_sends: self.recording_channel.blocked_sends(),
+                recording_blocked_seconds: self.recording_channel.blocked_seconds(),
+                time_domain_blocked_sends: self.time_domain_channel.blocked_sends(),
+                time_domain_blocked_seconds: self.time_domain_channel.blocked_seconds(),
+                fft_blocked_sends: self.fft_channel.blocked_sends(),
+                fft_blocked_seconds: self.fft_channel.blocked_seconds(),
+                frontend_blocked_sends: self.frontend_channel.blocked_sends(),
+                frontend_blocked_seconds: self.frontend_channel.blocked_seconds(),
+            },
+            frontend: FrontendHealth {
+                effective_frame_rate: f64::from_bits(self.effective_frame_rate.load(Ordering::Relaxed)),
+                emit_latency_ms: self.emit_latency_us.load(Ordering::Relaxed) as f64 / 1000.0,
+                coalesced_batches: self.coalesced_batches.load(Ordering::Relaxed),
+                under_load: self.frontend_under_load.load(Ordering::Relaxed),
+            },
+        }
+    }
+}
+
+impl PipelineMetrics {
+    /// 各阶段延迟分位数、吞吐与队列深度（get_pipeline_metrics / pipeline-metrics）
+    pub fn stage_report(&self) -> PipelineMetricsReport {
+        let mut stages = Vec::with_capacity(6);
+        if let Some(lsl_pull) = self.lsl_pull.get() {
+            stages.push(lsl_pull.report(PipelineStage::LslPull));
+        }
+        stages.push(self.distributor.report(PipelineStage::Distributor));
+        stages.push(self.collector.report(PipelineStage::Collector));
+        stages.push(self.fft.report(PipelineStage::Fft));
+        stages.push(self.emit.report(PipelineStage::Emit));
+        stages.push(self.recording.report(PipelineStage::Recording));
+        
+        let health = self.snapshot("");
+        PipelineMetricsReport {
+            uptime_seconds: health.uptime_seconds,
+            stages,
+            queue_depths: health.queue_depths,
+        }
+    }
+    
+    /// 由监督器检查心跳的阶段（LSL拉取由数据源自行检测中断）
+    pub fn heartbeat_stages(&self) -> [(PipelineStage, &StageStats); 5] {
+        [
+            (PipelineStage::Distributor, &self.distributor),
+            (PipelineStage::Collector, &self.collector),
+            (PipelineStage::Fft, &self.fft),
+            (PipelineStage::Emit, &self.emit),
+            (PipelineStage::Recording, &self.recording),
+        ]
+    }
+}
+
+/// 单个管道阶段的最近延迟样本、处理项计数与心跳
+pub struct StageStats {
+    recent: std::sync::Mutex<VecDeque<(Instant, f64, u64)>>, // (记录时刻, 延迟秒, 项数)
+    total_items: AtomicU64,
+    created_at: Instant,
+    last_beat_ms: AtomicU64,            // 相对 created_at
+}
+
+impl StageStats {
+    pub fn new() -> Self {
+        Self {
+            recent: std::sync::Mutex::new(VecDeque::with_capacity(STAGE_WINDOW)),
+            total_items: AtomicU64::new(0),
+            created_at: Instant::now(),
+            last_beat_ms: AtomicU64::new(0),
+        }
+    }
+    
+    /// 阶段任务仍在运行（空闲等待时也应定期调用）
+    pub fn beat(&self) {
+        self.last_beat_ms.store(self.created_at.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+    
+    pub fn heartbeat_age(&self) -> Duration {
+        self.created_at.elapsed()
+            .saturating_sub(Duration::from_millis(self.last_beat_ms.load(Ordering::Relaxed)))
+    }
+    
+    pub fn record(&self, latency: Duration, items: u64) {
+        self.record_seconds(latency.as_secs_f64(), items);
+    }
+    
+    pub fn record_seconds(&self, latency: f64, items: u64) {
+        self.beat();
+        self.total_items.fetch_add(items, Ordering::Relaxed);
+        let mut recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if recent.len() == STAGE_WINDOW {
+            recent.pop_front();
+        }
+        recent.push_back((Instant::now(), latency.max(0.0), items));
+    }
+    
+    pub fn report(&self, stage: PipelineStage) -> StageLatency {
+        let recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut latencies: Vec<f64> = recent.iter().map(|&(_, latency, _)| latency).collect();
+        latencies.sort_by(f64::total_cmp);
+        let percentile_ms = |fraction: f64| match latencies.len() {
+            0 => 0.0,
+            len => latencies[((len - 1) as f64 * fraction).round() as usize] * 1000.0,
+        };
+        
+        // 吞吐：窗口内第一条之后的项数 / 时间跨度
+        let items_per_second = match (recent.front(), recent.back()) {
+            (Some(first), Some(last)) if last.0 > first.0 => {
+                let items: u64 = recent.iter().skip(1).map(|&(_, _, items)| items).sum();
+                items as f64 / (last.0 - first.0).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+        
+        StageLatency {
+            stage,
+            total_items: self.total_items.load(Ordering::Relaxed),
+            items_per_second,
+            p50_ms: percentile_ms(0.5),
+            p95_ms: percentile_ms(0.95),
+            max_ms: percentile_ms(1.0),
+            window: latencies.len(),
+        }
+    }
+}
+
+/// 前端发送节流 - 平滑单帧发送耗时并统计实际帧率
+///
+/// 发送耗时超过帧间隔一半时视为前端跟不上：最小发送间隔拉长为发送耗时的两倍，
+/// 期间到达的批次合并为更大的帧；耗时回落到帧间隔四分之一以下时恢复。
+pub struct FramePacer {
+    emit_latency: Option<f64>,      // 秒
+    window_start: Instant,
+    window_frames: u32,
+    under_load: bool,
+}
+
+impl FramePacer {
+    const LATENCY_SMOOTHING: f64 = 0.2;
+    const RATE_WINDOW: Duration = Duration::from_secs(1);
+    
+    pub fn new() -> Self {
+        Self { emit_latency: None, window_start: Instant::now(), window_frames: 0, under_load: false }
+    }
+    
+    /// 记录一帧的发送耗时，负载状态变化时返回 true
+    pub fn record_emit(&mut self, latency: Duration, frame_interval: Duration, metrics: &PipelineMetrics) -> bool {
+        let latency = latency.as_secs_f64();
+        let smoothed = match self.emit_latency {
+            Some(previous) => previous + Self::LATENCY_SMOOTHING * (latency - previous),
+            None => latency,
+        };
+        self.emit_latency = Some(smoothed);
+        metrics.emit_latency_us.store((smoothed * 1e6) as u64, Ordering::Relaxed);
+        
+        self.window_frames += 1;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Self::RATE_WINDOW {
+            let rate = self.window_frames as f64 / elapsed.as_secs_f64();
+            metrics.effective_frame_rate.store(rate.to_bits(), Ordering::Relaxed);
+            self.window_start = Instant::now();
+            self.window_frames = 0;
+        }
+        
+        let interval = frame_interval.as_secs_f64();
+        let under_load = if self.under_load { smoothed > interval / 4.0 } else { smoothed > interval / 2.0 };
+        let changed = under_load != self.under_load;
+        self.under_load = under_load;
+        metrics.frontend_under_load.store(under_load, Ordering::Relaxed);
+        changed
+    }
+    
+    /// 负载下的最小发送间隔（秒），未过载时为 0
+    pub fn min_interval(&self) -> f64 {
+        match (self.under_load, self.emit_latency) {
+            (true, Some(latency)) => latency * 2.0,
+            _ => 0.0,
+        }
+    }
+    
+    pub fn under_load(&self) -> bool {
+        self.under_load
+    }
+    
+    pub fn emit_latency_ms(&self) -> f64 {
+        self.emit_latency.unwrap_or(0.0) * 1000.0
+    }
+}
+
+/// 当前进程的常驻内存（MB）
+pub fn process_memory_mb() -> u64 {
+    let Ok(pid) = sysinfo::get_current_pid() else {
+        return 0;
+    };
+    
+    let mut system = sysinfo::System::new();
+    system.refresh_process(pid);
+    system.process(pid)
+        .map(|process| process.memory() / (1024 * 1024))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_stage_percentiles() {
+        let stats = StageStats::new();
+        for ms in 1..=100 {
+            stats.record(Duration::from_millis(ms), 10);
+        }
+        
+        let report = stats.report(PipelineStage::Fft);
+        assert_eq!(report.total_items, 1000);
+        assert_eq!(report.window, 100);
+        assert!((report.p50_ms - 51.0).abs() < 0.01);
+        assert!((report.p95_ms - 95.0).abs() < 0.01);
+        assert!((report.max_ms - 100.0).abs() < 0.01);
+        
+        // 窗口满后丢弃最旧的样本
+        for _ in 0..STAGE_WINDOW {
+            stats.record(Duration::from_millis(2), 1);
+        }
+        let report = stats.report(PipelineStage::Fft);
+        assert_eq!(report.window, STAGE_WINDOW);
+        assert!((report.max_ms - 2.0).abs() < 0.01);
+    }
+    
+    #[test]
+    fn test_pacer_enters_and_leaves_load() {
+        let metrics = PipelineMetrics::new();
+        let mut pacer = FramePacer::new();
+        let interval = Duration::from_millis(33);
+        
+        assert!(!pacer.record_emit(Duration::from_millis(2), interval, &metrics));
+        assert_eq!(pacer.min_interval(), 0.0);
+        
+        // 发送持续变慢，平滑后超过半个帧间隔
+        let mut changed = false;
+        for _ in 0..20 {
+            changed |= pacer.record_emit(Duration::from_millis(40), interval, &metrics);
+        }
+        assert!(changed && pacer.under_load());
+        assert!(pacer.min_interval() > interval.as_secs_f64());
+        assert!(metrics.snapshot("test").frontend.under_load);
+        
+        // 介于四分之一与一半之间保持过载（滞回）
+        for _ in 0..30 {
+            pacer.record_emit(Duration::from_millis(12), interval, &metrics);
+        }
+        assert!(pacer.under_load());
+        
+        for _ in 0..30 {
+            pacer.record_emit(Duration::from_millis(1), interval, &metrics);
+        }
+        assert!(!pacer.under_load());
+        assert!(metrics.snapshot("test").frontend.emit_latency_ms < 8.25);
+    }
+}
@@ -0,0 +1,284 @@
+use crate::data_types::*;
+use crate::error::AppError;
+
+/// 10-20系统新旧命名的等价关系
+const LABEL_ALIASES: &[(&str, &str)] = &[
+    ("T3", "T7"),
+    ("T4", "T8"),
+    ("T5", "P7"),
+    ("T6", "P8"),
+    ("A1", "M1"),
+    ("A2", "M2"),
+];
+
+/// 纵向双极导联（"double banana"）
+const DOUBLE_BANANA: &[(&str, &str)] = &[
+    ("Fp1", "F7"), ("F7", "T3"), ("T3", "T5"), ("T5", "O1"),
+    ("Fp2", "F8"), ("F8", "T4"), ("T4", "T6"), ("T6", "O2"),
+    ("Fp1", "F3"), ("F3", "C3"), ("C3", "P3"), ("P3", "O1"),
+    ("Fp2", "F4"), ("F4", "C4"), ("C4", "P4"), ("P4", "O2"),
+    ("Fz", "Cz"), ("Cz", "Pz"),
+];
+
+/// 横向双极导联
+const TRANSVERSE: &[(&str, &str)] = &[
+    ("F7", "Fp1"), ("Fp1", "Fp2"), ("Fp2", "F8"),
+    ("F7", "F3"), ("F3", "Fz"), ("Fz", "F4"), ("F4", "F8"),
+    ("T3", "C3"), ("C3", "Cz"), ("Cz", "C4"), ("C4", "T4"),
+    ("T5", "P3"), ("P3", "Pz"), ("Pz", "P4"), ("P4", "T6"),
+    ("O1", "O2"),
+];
+
+/// 10-20系统预设：(名称, 描述)
+const PRESETS: &[(&str, &str)] = &[
+    ("common_average", "Common average reference over all channels"),
+    ("linked_mastoids", "Average of A1/A2 (M1/M2, TP9/TP10) as reference"),
+    ("cz_reference", "Cz as reference electrode"),
+    ("double_banana", "10-20 longitudinal bipolar montage"),
+    ("transverse", "10-20 transverse bipolar montage"),
+];
+
+/// 已解析的导联 - 每个样本按此变换
+#[derive(Clone, Debug)]
+pub enum Montage {
+    Raw,
+    /// 每个通道减去参考通道集合的均值（共平均、连接乳突、自定义参考）
+    Referenced { reference: Vec<usize>, labels: Vec<String> },
+    /// 输出为通道对的差值
+    Bipolar { pairs: Vec<(usize, usize)>, labels: Vec<String> },
+}
+
+impl Montage {
+    /// 根据配置和流的通道标签构建导联
+    pub fn build(config: &MontageConfig, channel_labels: &[String]) -> Result<Montage, AppError> {
+        let all_channels: Vec<usize> = (0..channel_labels.len()).collect();
+        
+        match config {
+            MontageConfig::Raw => Ok(Montage::Raw),
+            MontageConfig::CommonAverage => Ok(Self::referenced(all_channels, channel_labels, "AVG")),
+            MontageConfig::LinkedMastoids { left, right } => {
+                let left = match left {
+                    Some(channel) => resolve(channel, channel_labels)?,
+                    None => find_any(&["A1", "TP9"], channel_labels)
+                        .ok_or_else(|| AppError::Config("Left mastoid channel (A1/M1/TP9) not found".to_string()))?,
+                };
+                let right = match right {
+                    Some(channel) => resolve(channel, channel_labels)?,
+                    None => find_any(&["A2", "TP10"], channel_labels)
+                        .ok_or_else(|| AppError::Config("Right mastoid channel (A2/M2/TP10) not found".to_string()))?,
+                };
+                Ok(Self::referenced(vec![left, right], channel_labels, "LM"))
+            }
+            MontageConfig::CustomReference { channels } => {
+                if channels.is_empty() {
+                    return Err(AppError::Config("Custom reference needs at least one channel".to_string()));
+                }
+                let reference = channels
+                    .iter()
+                    .map(|channel| resolve(channel, channel_labels))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let suffix = reference
+                    .iter()
+                    .map(|&i| channel_labels[i].as_str())
+                    .collect::<Vec<_>>()
+                    .join("+");
+                Ok(Self::referenced(reference, channel_labels, &suffix))
+            }
+            MontageConfig::Bipolar { pairs } => {
+                if pairs.is_empty() {
+                    return Err(AppError::Config("Bipolar montage needs at least one channel pair".to_string()));
+                }
+                let pairs = pairs
+                    .iter()
+                    .map(|(a, b)| Ok((resolve(a, channel_labels)?, resolve(b, channel_labels)?)))
+                    .collect::<Result<Vec<_>, AppError>>()?;
+                Ok(Self::bipolar(pairs, channel_labels))
+            }
+            MontageConfig::Preset { name } => Self::build_preset(name, channel_labels),
+        }
+    }
+    
+    fn build_preset(name: &str, channel_labels: &[String]) -> Result<Montage, AppError> {
+        match name {
+            "common_average" => Self::build(&MontageConfig::CommonAverage, channel_labels),
+            "linked_mastoids" => Self::build(
+                &MontageConfig::LinkedMastoids { left: None, right: None },
+                channel_labels,
+            ),
+            "cz_reference" => Self::build(
+                &MontageConfig::CustomReference { channels: vec![ChannelRef::Label("Cz".to_string())] },
+                channel_labels,
+            ),
+            "double_banana" | "transverse" => {
+                let table = if name == "double_banana" { DOUBLE_BANANA } else { TRANSVERSE };
+                
+                // 只保留当前流中两个电极都存在的导联对
+                let pairs: Vec<(usize, usize)> = table
+                    .iter()
+                    .filter_map(|(a, b)| Some((find(a, channel_labels)?, find(b, channel_labels)?)))
+                    .collect();
+                
+                if pairs.is_empty() {
+                    return Err(AppError::Config(format!(
+                        "Stream has no 10-20 electrode labels for preset '{}'", name
+                    )));
+                }
+                Ok(Self::bipolar(pairs, channel_labels))
+            }
+            _ => Err(AppError::Config(format!("Unknown montage preset '{}'", name))),
+        }
+    }
+    
+    fn referenced(reference: Vec<usize>, channel_labels: &[String], reference_name: &str) -> Montage {
+        let labels = channel_labels
+            .iter()
+            .map(|label| format!("{}-{}", label, reference_name))
+            .collect();
+        Montage::Referenced { reference, labels }
+    }
+    
+    fn bipolar(pairs: Vec<(usize, usize)>, channel_labels: &[String]) -> Montage {
+        let labels = pairs
+            .iter()
+            .map(|&(a, b)| format!("{}-{}", channel_labels[a], channel_labels[b]))
+            .collect();
+        Montage::Bipolar { pairs, labels }
+    }
+    
+    /// 导联后的通道标签
+    pub fn output_labels(&self, channel_labels: &[String]) -> Vec<String> {
+        match self {
+            Montage::Raw => channel_labels.to_vec(),
+            Montage::Referenced { labels, .. } | Montage::Bipolar { labels, .. } => labels.clone(),
+        }
+    }
+    
+    pub fn is_raw(&self) -> bool {
+        matches!(self, Montage::Raw)
+    }
+    
+    /// 对单个样本应用导联（双极导联会改变通道数）
+    pub fn apply(&self, channels: &mut Vec<f64>) {
+        match self {
+            Montage::Raw => {}
+            Montage::Referenced { reference, .. } => {
+                let valid: Vec<f64> = reference
+                    .iter()
+                    .filter_map(|&i| channels.get(i).copied())
+                    .collect();
+                if valid.is_empty() {
+                    return;
+                }
+                
+                let reference_value = valid.iter().sum::<f64>() / valid.len() as f64;
+                for value in channels.iter_mut() {
+                    *value -= reference_value;
+                }
+            }
+            Montage::Bipolar { pairs, .. } => {
+                let derived: Vec<f64> = pairs
+                    .iter()
+                    .map(|&(a, b)| {
+                        channels.get(a).copied().unwrap_or(0.0) - channels.get(b).copied().unwrap_or(0.0)
+                    })
+                    .collect();
+                *channels = derived;
+            }
+        }
+    }
+}
+
+/// 可用预设列表，标注当前通道标签能否构建
+pub fn available_presets(channel_labels: &[String]) -> Vec<MontagePresetInfo> {
+    PRESETS
+        .iter()
+        .map(|(name, description)| MontagePresetInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+            available: Montage::build_preset(name, channel_labels).is_ok(),
+        })
+        .collect()
+}
+
+/// 标签规范化：去掉 "EEG " 前缀和 "-REF" 后缀，转为大写
+fn normalize_label(label: &str) -> String {
+    let upper = label.trim().to_uppercase();
+    let upper = upper.strip_prefix("EEG ").unwrap_or(&upper);
+    let upper = upper.strip_suffix("-REF").unwrap_or(upper);
+    upper.trim().to_string()
+}
+
+/// 按标签查找通道，支持10-20新旧命名
+fn find(label: &str, channel_labels: &[String]) -> Option<usize> {
+    let target = normalize_label(label);
+    let alias = LABEL_ALIASES
+        .iter()
+        .find_map(|&(old, new)| {
+            if normalize_label(old) == target {
+                Some(normalize_label(new))
+            } else if normalize_label(new) == target {
+                Some(normalize_label(old))
+            } else {
+                None
+            }
+        });
+    
+    channel_labels.iter().position(|candidate| {
+        let candidate = normalize_label(candidate);
+        candidate == target || alias.as_deref() == Some(candidate.as_str())
+    })
+}
+
+fn find_any(labels: &[&str], channel_labels: &[String]) -> Option<usize> {
+    labels.iter().find_map(|label| find(label, channel_labels))
+}
+
+fn resolve(channel: &ChannelRef, channel_labels: &[String]) -> Result<usize, AppError> {
+    match channel {
+        ChannelRef::Index(index) if *index < channel_labels.len() => Ok(*index),
+        ChannelRef::Index(index) => Err(AppError::Config(format!(
+            "Channel index {} out of range ({} channels)", index, channel_labels.len()
+        ))),
+        ChannelRef::Label(label) => find(label, channel_labels)
+            .ok_or_else(|| AppError::Config(format!("Channel '{}' not found", label))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn labels(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_common_average_sums_to_zero() {
+        let channel_labels = labels(&["Fz", "Cz", "Pz", "Oz"]);
+        let montage = Montage::build(&MontageConfig::CommonAverage, &channel_labels).unwrap();
+        
+        let mut sample = vec![1.0, 2.0, 3.0, 10.0];
+        montage.apply(&mut sample);
+        
+        assert!(sample.iter().sum::<f64>().abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_double_banana_uses_available_pairs_and_aliases() {
+        // 新命名 T7/P7 应匹配预设中的 T3/T5
+        let channel_labels = labels(&["Fp1", "F7", "T7", "P7", "O1"]);
+        let montage = Montage::build(
+            &MontageConfig::Preset { name: "double_banana".to_string() },
+            &channel_labels,
+        ).unwrap();
+        
+        assert_eq!(
+            montage.output_labels(&channel_labels),
+            labels(&["Fp1-F7", "F7-T7", "T7-P7", "P7-O1"])
+        );
+        
+        let mut sample = vec![5.0, 3.0, 2.0, 1.0, 0.0];
+        montage.apply(&mut sample);
+        assert_eq!(sample, vec![2.0, 1.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::Serialize;
+use tracing::{debug, error, info, warn};
+use crate::data_types::*;
+
+/// 连接断开后的重连间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// 客户端请求队列长度，积压时丢弃新消息而不阻塞采集
+const REQUEST_CAPACITY: usize = 256;
+
+/// 全局MQTT发布端 - 各会话的事件发送器共享，未启用时为 None
+pub type SharedMqtt = Arc<RwLock<Option<MqttPublisher>>>;
+
+/// MQTT发布端 - 将事件负载序列化为JSON发布到配置的主题（QoS 0）
+///
+/// 网络事件循环与周期性任务在后台运行，发布端被替换或移除时终止。
+pub struct MqttPublisher {
+    client: AsyncClient,
+    topics: MqttTopics,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl MqttPublisher {
+    /// 创建客户端并启动网络事件循环（断线自动重连）
+    pub fn connect(config: &MqttConfig) -> Self {
+        let mut options = MqttOptions::new(config.client_id.trim(), config.host.trim(), config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = config.username.as_ref() {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        
+        let (client, mut event_loop) = AsyncClient::new(options, REQUEST_CAPACITY);
+        let broker = format!("{}:{}", config.host, config.port);
+        
+        let event_task = tokio::spawn(async move {
+            let mut connected = false;
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        connected = true;
+                        info!("📡 MQTT connected to {}", broker);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        // 只在状态变化时记录，避免broker不可用时刷屏
+                        if connected {
+                            warn!("⚠️ MQTT connection to {} lost: {}", broker, e);
+                        } else {
+                            debug!("MQTT connect to {} failed: {}", broker, e);
+                        }
+                        connected = false;
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+        
+        Self {
+            client,
+            topics: config.topics.clone(),
+            tasks: vec![event_task],
+        }
+    }
+    
+    pub fn topics(&self) -> &MqttTopics {
+        &self.topics
+    }
+    
+    /// 附加随发布端一同终止的后台任务（如系统健康定时发布）
+    pub fn attach_task(&mut self, task: tokio::task::JoinHandle<()>) {
+        self.tasks.push(task);
+    }
+    
+    /// 发布一个事件；未配置主题的事件忽略
+    pub fn publish_event<S: Serialize>(&self, event: &str, stream_id: Option<&str>, payload: &S) {
+        let Some(template) = self.topics.for_event(event) else { return };
+        let topic = resolve_topic(template, stream_id);
+        
+        let payload = match serde_json::to_vec(payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to serialize {} for MQTT: {}", event, e);
+                return;
+            }
+        };
+        if let Err(e) = self.client.try_publish(topic, QoS::AtMostOnce, false, payload) {
+            debug!("MQTT publish of {} dropped: {}", event, e);
+        }
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        let _ = self.client.try_disconnect();
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// 若已启用MQTT则发布事件
+pub fn forward<S: Serialize>(mqtt: &SharedMqtt, event: &str, stream_id: Option<&str>, payload: &S) {
+    if let Ok(guard) = mqtt.read() {
+        if let Some(publisher) = guard.as_ref() {
+            publisher.publish_event(event, stream_id, payload);
+        }
+    }
+}
+
+/// 替换主题模板中的 "{stream}"
+fn resolve_topic(template: &str, stream_id: Option<&str>) -> String {
+    template.replace("{stream}", stream_id.unwrap_or("all"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_topics_and_validation() {
+        let topics = MqttTopics::default();
+        let band_power = topics.for_event("band-power-update").unwrap();
+        assert_eq!(resolve_topic(band_power, Some("eeg_1")), "cortexarray/eeg_1/band_power");
+        assert_eq!(resolve_topic(topics.for_event("system-health").unwrap(), None), "cortexarray/health");
+        assert!(topics.for_event("vitals-update").is_none());
+        
+        let mut config = MqttConfig::default();
+        config.validate().unwrap();
+        config.topics.artifacts = Some("lab/+/artifacts".to_string());
+        assert!(config.validate().is_err());
+        config.topics.artifacts = None;
+        config.health_interval_secs = 0;
+        assert!(config.validate().is_err());
+    }
+}
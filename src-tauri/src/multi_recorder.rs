@@ -0,0 +1,283 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::recorder::{EdfRecorder, FinishedRecording};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// 同一录制会话的一组输出，如未滤波的存档文件加滤波后的复核文件
+///
+/// 第一个输出为主输出：状态、录制时长和注释起点以它为准。
+/// 注释、暂停/恢复和辅助流写入所有输出。
+pub struct MultiRecorder {
+    outputs: Vec<RecordingTarget>,
+}
+
+struct RecordingTarget {
+    processing: RecordingProcessing,
+    recorder: EdfRecorder,
+    started: bool,                      // 已写入首个实时样本
+}
+
+impl MultiRecorder {
+    pub fn new(
+        filename: String,
+        options: RecordingOptions,
+        additional: Vec<RecordingOutput>,
+        stream_info: &StreamInfo,
+        metadata: &SessionMetadata,
+    ) -> Result<Self, AppError> {
+        if additional.len() > RecordingOutput::MAX_OUTPUTS {
+            return Err(AppError::Config(format!(
+                "At most {} additional recording outputs are supported, got {}", RecordingOutput::MAX_OUTPUTS, additional.len()
+            )));
+        }
+        
+        let outputs: Vec<RecordingOutput> = std::iter::once(RecordingOutput { filename, options })
+            .chain(additional)
+            .collect();
+        let mut filenames = HashSet::new();
+        for output in &outputs {
+            if output.filename.trim().is_empty() || !filenames.insert(output.filename.as_str()) {
+                return Err(AppError::Config(format!("Recording outputs need distinct filenames, got '{}'", output.filename)));
+            }
+            output.options.validate()?;
+        }
+        
+        let mut recorders = Self { outputs: Vec::with_capacity(outputs.len()) };
+        for output in outputs {
+            let processing = output.options.processing;
+            match EdfRecorder::new(output.filename, stream_info.clone(), output.options, metadata) {
+                Ok(recorder) => recorders.outputs.push(RecordingTarget { processing, recorder, started: false }),
+                Err(e) => {
+                    // 已创建的输出不保留空文件
+                    recorders.discard();
+                    return Err(e);
+                }
+            }
+        }
+        
+        if recorders.outputs.len() > 1 {
+            let summary: Vec<String> = recorders.outputs.iter()
+                .map(|target| format!("{} ({:?})", target.recorder.current_filename(), target.processing))
+                .collect();
+            info!("🔀 Recording to {} outputs: {}", summary.len(), summary.join(", "));
+        }
+        Ok(recorders)
+    }
+    
+    fn primary(&self) -> &EdfRecorder {
+        &self.outputs[0].recorder
+    }
+    
+    fn discard(self) {
+        for target in self.outputs {
+            if let Ok(stats) = target.recorder.close() {
+                for segment in stats.segments {
+                    let _ = std::fs::remove_file(segment);
+                }
+            }
+        }
+    }
+    
+    /// 是否有输出需要该处理方式的样本
+    pub fn needs(&self, processing: RecordingProcessing) -> bool {
+        self.outputs.iter().any(|target| target.processing == processing)
+    }
+    
+    /// 按各输出的处理方式写入样本；某个输出出错时仍写入其余输出，返回第一个错误
+    ///
+    /// 分发线程按录制开始前的需求准备样本，开始后最先到达的样本可能缺少所需的通道值，
+    /// 这些样本在该输出中跳过
+    pub fn write_sample(&mut self, sample: &RecordingSample) -> Result<(), AppError> {
+        let mut result = Ok(());
+        for target in &mut self.outputs {
+            let written = match (target.processing, sample.channels(target.processing)) {
+                (RecordingProcessing::Pipeline, _) => target.recorder.write_sample(&sample.sample),
+                (_, Some(channels)) => target.recorder.write_sample(&EegSample {
+                    timestamp: sample.sample.timestamp,
+                    channels: channels.to_vec(),
+                    sample_id: sample.sample.sample_id,
+                }),
+                (processing, None) if target.started => Err(AppError::Recording(format!(
+                    "Sample {} has no {:?} channel values for {}", sample.sample.sample_id, processing, target.recorder.current_filename()
+                ))),
+                (_, None) => continue,
+            };
+            target.started = true;
+            if result.is_ok() {
+                result = written;
+            }
+        }
+        result
+    }
+    
+    pub fn prepend_history(&mut self, samples: &[EegSample]) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.prepend_history(samples))
+    }
+    
+    pub fn write_annotation(&mut self, onset_seconds: f64, duration_seconds: Option<f64>, description: &str) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.write_annotation(onset_seconds, duration_seconds, description))
+    }
+    
+    pub fn write_marker(&mut self, onset_seconds: f64, duration_seconds: Option<f64>, label: &str) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.write_marker(onset_seconds, duration_seconds, label))
+    }
+    
+    /// 按LSL时间戳写入事件标记，返回主输出中的录制时间（首个样本写入前暂存时为 None）
+    pub fn write_marker_at(&mut self, lsl_timestamp: f64, duration_seconds: Option<f64>, label: &str) -> Result<Option<f64>, AppError> {
+        let mut result = Ok(None);
+        for (index, target) in self.outputs.iter_mut().enumerate() {
+            let written = target.recorder.write_marker_at(lsl_timestamp, duration_seconds, label);
+            if index == 0 || written.is_err() && result.is_ok() {
+                result = written;
+            }
+        }
+        result
+    }
+    
+    pub fn write_annotation_at(&mut self, lsl_timestamp: f64, duration_seconds: Option<f64>, description: &str) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.write_annotation_at(lsl_timestamp, duration_seconds, description).map(|_| ()))
+    }
+    
+    pub fn write_aux(&mut self, samples: &[AuxSample]) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.write_aux(samples))
+    }
+    
+    pub fn write_clock_offsets(&mut self, samples: &[ClockOffsetSample]) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.write_clock_offsets(samples))
+    }
+    
+    pub fn write_script_metrics(&mut self, update: &ScriptMetricsUpdate) -> Result<(), AppError> {
+        self.for_each(|recorder| recorder.write_script_metrics(update))
+    }
+    
+    pub fn pause(&mut self) -> Result<(), AppError> {
+        self.for_each(EdfRecorder::pause)
+    }
+    
+    /// 恢复所有输出，返回主输出的暂停时长（秒）
+    pub fn resume(&mut self) -> Result<f64, AppError> {
+        let mut paused = Ok(0.0);
+        for (index, target) in self.outputs.iter_mut().enumerate() {
+            let result = target.recorder.resume();
+            if index == 0 || (paused.is_ok() && result.is_err()) {
+                paused = result;
+            }
+        }
+        paused
+    }
+    
+    fn for_each(&mut self, mut write: impl FnMut(&mut EdfRecorder) -> Result<(), AppError>) -> Result<(), AppError> {
+        let mut result = Ok(());
+        for target in &mut self.outputs {
+            let written = write(&mut target.recorder);
+            if result.is_ok() {
+                result = written;
+            }
+        }
+        result
+    }
+    
+    pub fn status(&self) -> RecordingStatus {
+        self.primary().status()
+    }
+    
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.primary().elapsed_seconds()
+    }
+    
+    pub fn onset_for_timestamp(&self, lsl_timestamp: f64) -> f64 {
+        self.primary().onset_for_timestamp(lsl_timestamp)
+    }
+    
+    pub fn current_filename(&self) -> &str {
+        self.primary().current_filename()
+    }
+    
+    /// 各输出所在磁盘中最少的可用空间（MB）
+    pub fn available_megabytes(&self) -> Result<f64, AppError> {
+        let mut available = f64::INFINITY;
+        for target in &self.outputs {
+            available = available.min(target.recorder.available_megabytes()?);
+        }
+        Ok(available)
+    }
+    
+    pub fn disk_config(&self) -> DiskSpaceConfig {
+        self.primary().disk_config()
+    }
+    
+    /// 结束所有输出，主输出在前；附加输出失败不影响其余输出
+    pub fn finish(self) -> Vec<Result<FinishedRecording, AppError>> {
+        self.outputs.into_iter()
+            .map(|target| {
+                let filename = target.recorder.current_filename().to_string();
+                target.recorder.finish().inspect_err(|e| warn!("⚠️  Failed to finalize {}: {}", filename, e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_raw_and_filtered_outputs() {
+        let dir = std::env::temp_dir();
+        let raw_path = dir.join(format!("multi_raw_{}.csv", std::process::id()));
+        let filtered_path = dir.join(format!("multi_filtered_{}.tsv", std::process::id()));
+        let stream_info = StreamInfo {
+            name: "Test EEG".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 2,
+            sample_rate: 100.0,
+            is_connected: true,
+            source_id: "test_device".to_string(),
+            channel_labels: StreamInfo::default_channel_labels(2),
+            effective_sample_rate: None,
+        };
+        let raw = RecordingOptions { format: RecordingFormat::Csv, processing: RecordingProcessing::Raw, ..RecordingOptions::default() };
+        let filtered = RecordingOutput {
+            filename: filtered_path.to_str().unwrap().to_string(),
+            options: RecordingOptions { format: RecordingFormat::Tsv, processing: RecordingProcessing::Filtered, ..RecordingOptions::default() },
+        };
+        
+        let duplicate = RecordingOutput { filename: raw_path.to_str().unwrap().to_string(), options: RecordingOptions::default() };
+        assert!(MultiRecorder::new(raw_path.to_str().unwrap().to_string(), raw.clone(), vec![duplicate], &stream_info, &SessionMetadata::default()).is_err());
+        
+        let mut recorder = MultiRecorder::new(
+            raw_path.to_str().unwrap().to_string(), raw, vec![filtered], &stream_info, &SessionMetadata::default()
+        ).unwrap();
+        assert!(recorder.needs(RecordingProcessing::Raw) && recorder.needs(RecordingProcessing::Filtered));
+        assert!(!recorder.needs(RecordingProcessing::Pipeline));
+        
+        // 开始前准备的样本缺少通道值时跳过，开始写入后缺少则报错
+        let sample = |sample_id: u64, raw: Option<f64>, filtered: Option<f64>| RecordingSample {
+            sample: EegSample { timestamp: sample_id as f64 / 100.0, channels: vec![0.0, 0.0], sample_id },
+            raw: raw.map(|value| vec![value, value]),
+            filtered: filtered.map(|value| vec![value, value]),
+        };
+        recorder.write_sample(&sample(0, None, None)).unwrap();
+        for sample_id in 1..=3 {
+            recorder.write_sample(&sample(sample_id, Some(1.5), Some(-7.25))).unwrap();
+        }
+        assert!(recorder.write_sample(&sample(4, Some(1.5), None)).is_err());
+        recorder.write_marker(0.01, None, "stimulus").unwrap();
+        
+        let finished: Vec<FinishedRecording> = recorder.finish().into_iter().map(Result::unwrap).collect();
+        let raw_text = std::fs::read_to_string(&raw_path).unwrap();
+        let filtered_text = std::fs::read_to_string(&filtered_path).unwrap();
+        for path in [&raw_path, &filtered_path] {
+            let _ = std::fs::remove_file(path);
+            let _ = std::fs::remove_file(crate::delimited::annotations_path(path.to_str().unwrap()));
+        }
+        
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0].stats.samples_written, 4);
+        assert_eq!(finished[1].stats.samples_written, 3);
+        assert!(raw_text.contains("1.5") && !raw_text.contains("-7.25"));
+        assert!(filtered_text.contains("-7.25") && !filtered_text.contains("1.5"));
+        assert!(finished.iter().all(|recording| recording.stats.markers_written == 1));
+    }
+}
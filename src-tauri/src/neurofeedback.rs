@@ -0,0 +1,118 @@
+use crate::band_power;
+use crate::data_types::*;
+
+#[derive(Default)]
+struct RuleState {
+    since: Option<f64>,         // 条件开始成立的时刻
+    latched: bool,              // 已触发、等待释放
+    last_fired: Option<f64>,
+}
+
+/// 神经反馈阈值引擎 - 按频带功率更新评估规则
+///
+/// 条件持续 hold_ms 后触发（不应期内不触发），触发后保持，
+/// 直到数值越过 阈值∓滞回量 才释放并重新开始计时。时间均为数据时间（秒）。
+pub struct FeedbackEngine {
+    rules: Vec<FeedbackRule>,
+    states: Vec<RuleState>,
+}
+
+impl FeedbackEngine {
+    pub fn new(config: &NeurofeedbackConfig) -> Self {
+        Self {
+            rules: config.rules.clone(),
+            states: config.rules.iter().map(|_| RuleState::default()).collect(),
+        }
+    }
+    
+    pub fn on_band_power(&mut self, update: &BandPowerUpdate, timestamp: f64) -> Vec<FeedbackEvent> {
+        let mut events = Vec::new();
+        
+        for (rule_index, (rule, state)) in self.rules.iter().zip(self.states.iter_mut()).enumerate() {
+            let Some(value) = band_power::band_value(update, rule.channel, &rule.band, rule.relative) else { continue };
+            
+            let (active, released) = if rule.above {
+                (value > rule.threshold, value < rule.threshold - rule.hysteresis)
+            } else {
+                (value < rule.threshold, value > rule.threshold + rule.hysteresis)
+            };
+            let event = |active: bool| FeedbackEvent {
+                rule_index,
+                name: rule.name.clone(),
+                active,
+                value,
+                threshold: rule.threshold,
+                timestamp,
+            };
+            
+            if state.latched {
+                if released {
+                    state.latched = false;
+                    state.since = None;
+                    events.push(event(false));
+                }
+                continue;
+            }
+            
+            if !active {
+                state.since = None;
+                continue;
+            }
+            
+            let since = *state.since.get_or_insert(timestamp);
+            let held = timestamp - since >= rule.hold_ms as f64 / 1000.0;
+            let refractory_over = state.last_fired
+                .is_none_or(|fired| timestamp - fired >= rule.refractory_ms as f64 / 1000.0);
+            if held && refractory_over {
+                state.latched = true;
+                state.last_fired = Some(timestamp);
+                events.push(event(true));
+            }
+        }
+        
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn update(alpha: f64) -> BandPowerUpdate {
+        BandPowerUpdate {
+            batch_id: 0,
+            bands: vec!["alpha".to_string()],
+            channels: vec![ChannelBandPower { channel_index: 2, absolute: vec![alpha], relative: vec![0.5] }],
+        }
+    }
+    
+    #[test]
+    fn test_hold_hysteresis_and_refractory() {
+        let config = NeurofeedbackConfig {
+            rules: vec![FeedbackRule {
+                name: "alpha up".to_string(),
+                channel: 2,
+                threshold: 10.0,
+                hold_ms: 500,
+                hysteresis: 2.0,
+                refractory_ms: 2000,
+                ..FeedbackRule::default()
+            }],
+        };
+        let mut engine = FeedbackEngine::new(&config);
+        
+        // 每0.1秒一次更新：0.0-0.3秒超过阈值后回落（不足500ms），0.5秒起持续超过
+        let values = [12.0, 12.0, 12.0, 12.0, 9.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0,
+                      9.0, 7.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0,
+                      12.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0, 12.0];
+        let events: Vec<(bool, usize)> = values.iter()
+            .enumerate()
+            .flat_map(|(i, &value)| engine.on_band_power(&update(value), i as f64 * 0.1)
+                .into_iter()
+                .map(move |event| (event.active, i)))
+            .collect();
+        
+        // 1.0秒触发；9µV在滞回范围内不释放，7µV释放；再次满足持续条件时仍在不应期，3.0秒后才触发
+        assert_eq!(events, vec![(true, 10), (false, 12), (true, 30)]);
+    }
+}
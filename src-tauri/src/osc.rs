@@ -0,0 +1,166 @@
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use tracing::{debug, info};
+use crate::band_power;
+use crate::data_types::*;
+use crate::error::AppError;
+
+/// OSC 1.0 消息参数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+}
+
+/// 编码一条OSC消息：地址与类型标签均以NUL结尾并补齐到4字节，参数为大端序
+pub fn encode_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    fn push_padded(buffer: &mut Vec<u8>, text: &str) {
+        buffer.extend(text.as_bytes());
+        buffer.push(0);
+        while !buffer.len().is_multiple_of(4) {
+            buffer.push(0);
+        }
+    }
+    
+    let mut buffer = Vec::with_capacity(address.len() + 8 + args.len() * 4);
+    push_padded(&mut buffer, address);
+    
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+        }))
+        .collect();
+    push_padded(&mut buffer, &tags);
+    
+    for arg in args {
+        match arg {
+            OscArg::Int(value) => buffer.extend(&value.to_be_bytes()),
+            OscArg::Float(value) => buffer.extend(&value.to_be_bytes()),
+        }
+    }
+    buffer
+}
+
+/// OSC输出 - 将频带功率与神经反馈触发按映射以UDP发送（Max/MSP、TouchDesigner等）
+///
+/// 频带功率发送 `,f`；反馈规则发送 `,if`（1 触发 / 0 释放，触发时的数值）。
+pub struct OscSender {
+    socket: UdpSocket,
+    target: SocketAddr,
+    mappings: Vec<OscMapping>,
+}
+
+impl OscSender {
+    pub fn new(config: &OscConfig) -> Result<Self, AppError> {
+        let target = (config.host.trim(), config.port).to_socket_addrs()?
+            .next()
+            .ok_or_else(|| AppError::Config(format!("Cannot resolve OSC host '{}'", config.host)))?;
+        let bind_address = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind_address)?;
+        socket.set_nonblocking(true)?;
+        
+        info!("🎛️  OSC output to {} ({} mappings)", target, config.mappings.len());
+        
+        Ok(Self {
+            socket,
+            target,
+            mappings: config.mappings.clone(),
+        })
+    }
+    
+    pub fn on_band_power(&self, update: &BandPowerUpdate) {
+        for mapping in &self.mappings {
+            let OscSource::BandPower { band, channel, relative } = &mapping.source else { continue };
+            
+            let value = match channel {
+                Some(channel) => band_power::band_value(update, *channel, band, *relative),
+                None => {
+                    let values: Vec<f64> = update.channels.iter()
+                        .filter_map(|power| band_power::band_value(update, power.channel_index, band, *relative))
+                        .collect();
+                    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+                }
+            };
+            if let Some(value) = value {
+                self.send(&mapping.address, &[OscArg::Float(value as f32)]);
+            }
+        }
+    }
+    
+    pub fn on_feedback(&self, event: &FeedbackEvent) {
+        for mapping in &self.mappings {
+            let OscSource::FeedbackTrigger { rule } = &mapping.source else { continue };
+            if rule.as_ref().is_some_and(|name| name != &event.name) {
+                continue;
+            }
+            self.send(&mapping.address, &[OscArg::Int(event.active as i32), OscArg::Float(event.value as f32)]);
+        }
+    }
+    
+    fn send(&self, address: &str, args: &[OscArg]) {
+        // UDP发送失败（接收端未启动等）不影响采集
+        if let Err(e) = self.socket.send_to(&encode_message(address, args), self.target) {
+            debug!("OSC send to {} failed: {}", self.target, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_band_power_and_feedback_messages() {
+        assert_eq!(
+            encode_message("/eeg", &[OscArg::Float(1.0)]),
+            [b"/eeg\0\0\0\0".as_slice(), b",f\0\0", &1.0f32.to_be_bytes()].concat()
+        );
+        
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let config = OscConfig {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: receiver.local_addr().unwrap().port(),
+            mappings: vec![
+                OscMapping {
+                    address: "/alpha".to_string(),
+                    source: OscSource::BandPower { band: "Alpha".to_string(), channel: None, relative: true },
+                },
+                OscMapping {
+                    address: "/trigger".to_string(),
+                    source: OscSource::FeedbackTrigger { rule: Some("relax".to_string()) },
+                },
+            ],
+        };
+        config.validate().unwrap();
+        let sender = OscSender::new(&config).unwrap();
+        
+        let update = BandPowerUpdate {
+            batch_id: 0,
+            bands: vec!["alpha".to_string()],
+            channels: (0..2).map(|channel| ChannelBandPower {
+                channel_index: channel,
+                absolute: vec![10.0],
+                relative: vec![0.2 + 0.2 * channel as f64],
+            }).collect(),
+        };
+        sender.on_band_power(&update);
+        let event = |name: &str| FeedbackEvent {
+            rule_index: 0,
+            name: name.to_string(),
+            active: true,
+            value: 0.5,
+            threshold: 0.4,
+            timestamp: 1.0,
+        };
+        sender.on_feedback(&event("focus"));
+        sender.on_feedback(&event("relax"));
+        
+        let mut buffer = [0u8; 64];
+        let length = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..length], encode_message("/alpha", &[OscArg::Float(0.3)]));
+        let length = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..length], encode_message("/trigger", &[OscArg::Int(1), OscArg::Float(0.5)]));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::data_types::{ChannelConfig, OverflowPolicy};
+use crossbeam_channel::{Receiver, SendError, Sender, TrySendError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 通道计数器：丢弃策略丢弃的项数，以及Block策略下通道满时的阻塞发送（背压，不丢数据）
+#[derive(Clone, Default)]
+pub struct ChannelCounters {
+    pub overflows: Arc<AtomicU64>,
+    pub blocked_sends: Arc<AtomicU64>,
+    pub blocked_micros: Arc<AtomicU64>,
+}
+
+impl ChannelCounters {
+    pub fn overflows(&self) -> u64 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+    
+    pub fn blocked_sends(&self) -> u64 {
+        self.blocked_sends.load(Ordering::Relaxed)
+    }
+    
+    pub fn blocked_seconds(&self) -> f64 {
+        self.blocked_micros.load(Ordering::Relaxed) as f64 / 1e6
+    }
+}
+
+/// 有界管道通道 - 按配置的溢出策略发送
+pub fn bounded<T>(config: ChannelConfig, counters: ChannelCounters) -> (PipelineSender<T>, Receiver<T>) {
+    let (tx, rx) = crossbeam_channel::bounded(config.capacity);
+    
+    // DropOldest需要从发送端弹出最旧项，因此持有一个接收端
+    // 代价是接收端全部退出后发送不会失败，生产者依赖is_running停止
+    let drain = match config.overflow {
+        OverflowPolicy::DropOldest => Some(rx.clone()),
+        _ => None,
+    };
+    
+    let sender = PipelineSender {
+        tx,
+        drain,
+        policy: config.overflow,
+        counters,
+    };
+    
+    (sender, rx)
+}
+
+pub struct PipelineSender<T> {
+    tx: Sender<T>,
+    drain: Option<Receiver<T>>,
+    policy: OverflowPolicy,
+    counters: ChannelCounters,
+}
+
+impl<T> Clone for PipelineSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            drain: self.drain.clone(),
+            policy: self.policy,
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl<T> PipelineSender<T> {
+    /// 发送一项；通道已满时按策略阻塞或丢弃，仅在接收端全部断开时返回错误
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        let item = match self.tx.try_send(item) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Disconnected(item)) => return Err(SendError(item)),
+            Err(TrySendError::Full(item)) => item,
+        };
+        
+        match self.policy {
+            OverflowPolicy::Block => {
+                // 阻塞是背压而非丢弃，单独统计次数与时长
+                self.counters.blocked_sends.fetch_add(1, Ordering::Relaxed);
+                let blocked_at = Instant::now();
+                let result = self.tx.send(item);
+                self.counters.blocked_micros.fetch_add(blocked_at.elapsed().as_micros() as u64, Ordering::Relaxed);
+                result
+            }
+            OverflowPolicy::DropNewest => {
+                self.counters.overflows.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            OverflowPolicy::DropOldest => {
+                let mut item = item;
+                loop {
+                    // 消费者可能同时在接收，只统计实际弹出的项
+                    if let Some(drain) = &self.drain {
+                        if drain.try_recv().is_ok() {
+                            self.counters.overflows.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    match self.tx.try_send(item) {
+                        Ok(()) => return Ok(()),
+                        Err(TrySendError::Disconnected(rejected)) => return Err(SendError(rejected)),
+                        Err(TrySendError::Full(rejected)) => item = rejected,
+                    }
+                }
+            }
+        }
+    }
+    
+    /// 当前积压项数
+    pub fn queue_len(&self) -> usize {
+        self.tx.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::PipelineChannelConfig;
+    
+    #[test]
+    fn test_recording_channel_must_block() {
+        assert!(PipelineChannelConfig::default().validate().is_ok());
+        for overflow in [OverflowPolicy::DropOldest, OverflowPolicy::DropNewest] {
+            let config = PipelineChannelConfig {
+                recording: ChannelConfig::new(4096, overflow),
+                ..PipelineChannelConfig::default()
+            };
+            assert!(config.validate().is_err());
+        }
+    }
+    
+    #[test]
+    fn test_drop_oldest_keeps_latest() {
+        let counters = ChannelCounters::default();
+        let (tx, rx) = bounded(ChannelConfig::new(2, OverflowPolicy::DropOldest), counters.clone());
+        
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(counters.overflows(), 3);
+    }
+    
+    #[test]
+    fn test_drop_newest_and_disconnect() {
+        let counters = ChannelCounters::default();
+        let (tx, rx) = bounded(ChannelConfig::new(1, OverflowPolicy::DropNewest), counters.clone());
+        
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(counters.overflows(), 1);
+        
+        drop(rx);
+        assert!(tx.send(3).is_err());
+    }
+    
+    #[test]
+    fn test_block_counts_backpressure_not_drops() {
+        let counters = ChannelCounters::default();
+        let (tx, rx) = bounded(ChannelConfig::new(1, OverflowPolicy::Block), counters.clone());
+        
+        tx.send(1).unwrap();
+        let consumer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            rx.iter().take(2).collect::<Vec<_>>()
+        });
+        tx.send(2).unwrap();
+        
+        assert_eq!(consumer.join().unwrap(), vec![1, 2]);
+        assert_eq!(counters.overflows(), 0);
+        assert_eq!(counters.blocked_sends(), 1);
+        assert!(counters.blocked_seconds() > 0.0);
+    }
+}
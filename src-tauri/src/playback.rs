@@ -0,0 +1,596 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::reader::RecordingReader;
+use crate::xdf::{self, XdfStream, XdfValues};
+use edfplus::EdfReader;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// EDF头中的时间单位为100纳秒
+const EDF_TIME_UNITS: f64 = 10_000_000.0;
+// 工作线程节拍
+const TICK: Duration = Duration::from_millis(10);
+// 回放速度范围
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 16.0;
+
+/// 录制文件回放 - 把EDF/BDF/XDF文件按实时（或调整后的）速度送入处理管道，
+/// 数据和标记通道与LslManager相同，EegProcessor无需区分来源
+pub struct PlaybackPlayer {
+    worker_handle: Option<JoinHandle<()>>,
+    control_tx: mpsc::Sender<PlaybackCommand>,
+    data_rx: Option<crossbeam_channel::Receiver<EegChunk>>,
+    marker_rx: Option<crossbeam_channel::Receiver<LslMarker>>,
+    status: Arc<Mutex<PlaybackStatus>>,
+    stream_info: StreamInfo,
+}
+
+#[derive(Debug)]
+enum PlaybackCommand {
+    Play,
+    Pause,
+    Seek(f64),
+    SetSpeed(f64),
+    Stop,
+}
+
+impl PlaybackPlayer {
+    /// 打开文件并启动工作线程（初始为暂停状态）
+    pub fn open(path: &str, chunk_config: ChunkConfig) -> Result<Self, AppError> {
+        let file = PlaybackFile::open(path)?;
+        let stream_info = file.stream_info.clone();
+        
+        let status = Arc::new(Mutex::new(PlaybackStatus {
+            file: path.to_string(),
+            state: PlaybackState::Paused,
+            position_seconds: 0.0,
+            duration_seconds: file.duration_seconds(),
+            speed: 1.0,
+        }));
+        
+        let (control_tx, control_rx) = mpsc::channel();
+        let (data_tx, data_rx) = crossbeam_channel::unbounded();
+        let (marker_tx, marker_rx) = crossbeam_channel::unbounded();
+        
+        let worker_status = status.clone();
+        let worker_handle = thread::Builder::new()
+            .name("playback-worker".to_string())
+            .spawn(move || {
+                Self::worker_thread(file, control_rx, data_tx, marker_tx, worker_status, chunk_config);
+            })?;
+        
+        info!("✅ Playback opened: {} ({} channels @ {}Hz, {:.1}s)",
+                 path, stream_info.channels_count, stream_info.sample_rate,
+                 status.lock().unwrap().duration_seconds);
+        
+        Ok(Self {
+            worker_handle: Some(worker_handle),
+            control_tx,
+            data_rx: Some(data_rx),
+            marker_rx: Some(marker_rx),
+            status,
+            stream_info,
+        })
+    }
+    
+    pub fn stream_info(&self) -> &StreamInfo {
+        &self.stream_info
+    }
+    
+    pub fn get_data_receiver(&mut self) -> Option<crossbeam_channel::Receiver<EegChunk>> {
+        self.data_rx.take()
+    }
+    
+    pub fn get_marker_receiver(&mut self) -> Option<crossbeam_channel::Receiver<LslMarker>> {
+        self.marker_rx.take()
+    }
+    
+    pub fn play(&self) -> Result<(), AppError> {
+        self.send(PlaybackCommand::Play)
+    }
+    
+    pub fn pause(&self) -> Result<(), AppError> {
+        self.send(PlaybackCommand::Pause)
+    }
+    
+    pub fn seek(&self, position_seconds: f64) -> Result<(), AppError> {
+        if !position_seconds.is_finite() || position_seconds < 0.0 {
+            return Err(AppError::Config(format!("Invalid seek position: {}", position_seconds)));
+        }
+        self.send(PlaybackCommand::Seek(position_seconds))
+    }
+    
+    pub fn set_speed(&self, speed: f64) -> Result<(), AppError> {
+        if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+            return Err(AppError::Config(format!(
+                "Playback speed must be between {} and {}, got {}", MIN_SPEED, MAX_SPEED, speed
+            )));
+        }
+        self.send(PlaybackCommand::SetSpeed(speed))
+    }
+    
+    pub fn status(&self) -> PlaybackStatus {
+        self.status.lock().unwrap().clone()
+    }
+    
+    fn send(&self, command: PlaybackCommand) -> Result<(), AppError> {
+        self.control_tx.send(command)
+            .map_err(|_| AppError::Playback("Playback worker has stopped".to_string()))
+    }
+    
+    /// 消费式停止
+    pub fn stop(mut self) {
+        let _ = self.control_tx.send(PlaybackCommand::Stop);
+        
+        if let Some(handle) = self.worker_handle.take() {
+            match handle.join() {
+                Ok(_) => info!("✅ Playback worker stopped"),
+                Err(_) => warn!("⚠️  Playback worker panicked"),
+            }
+        }
+    }
+    
+    // 工作线程 - 按挂钟节奏发送样本块
+    fn worker_thread(
+        mut file: PlaybackFile,
+        control_rx: mpsc::Receiver<PlaybackCommand>,
+        data_tx: crossbeam_channel::Sender<EegChunk>,
+        marker_tx: crossbeam_channel::Sender<LslMarker>,
+        status: Arc<Mutex<PlaybackStatus>>,
+        chunk_config: ChunkConfig,
+    ) {
+        info!("🎞️  Playback worker started");
+        
+        let mut playing = false;
+        let mut speed = 1.0;
+        // 节奏锚点：(挂钟时间, 当时的样本位置)
+        let mut anchor = (Instant::now(), file.position);
+        
+        loop {
+            // 暂停或结束时阻塞等待命令，播放时非阻塞
+            let command = if playing {
+                control_rx.try_recv().ok()
+            } else {
+                match control_rx.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => break,
+                }
+            };
+            
+            if let Some(command) = command {
+                match command {
+                    PlaybackCommand::Play => {
+                        if file.position >= file.total_samples {
+                            file.seek(0);
+                        }
+                        playing = true;
+                    }
+                    PlaybackCommand::Pause => playing = false,
+                    PlaybackCommand::Seek(seconds) => {
+                        let target = (seconds * file.sample_rate()) as u64;
+                        file.seek(target.min(file.total_samples));
+                    }
+                    PlaybackCommand::SetSpeed(new_speed) => speed = new_speed,
+                    PlaybackCommand::Stop => break,
+                }
+                
+                anchor = (Instant::now(), file.position);
+                Self::update_status(&status, &file, playing, speed);
+                continue;
+            }
+            
+            // 按挂钟计算此刻应已发送到的位置
+            let due = anchor.1 + (anchor.0.elapsed().as_secs_f64() * file.sample_rate() * speed) as u64;
+            let due = due.min(file.total_samples);
+            
+            while file.position < due {
+                let count = ((due - file.position) as usize).min(chunk_config.chunk_size);
+                let chunk = match file.read_chunk(count) {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        error!("❌ Playback read error: {}", e);
+                        playing = false;
+                        break;
+                    }
+                };
+                if chunk.is_empty() {
+                    break;
+                }
+                
+                for marker in file.markers_until(file.position) {
+                    let _ = marker_tx.send(marker);
+                }
+                
+                if data_tx.send(chunk).is_err() {
+                    warn!("⚠️  Playback data channel closed");
+                    return;
+                }
+            }
+            
+            if file.position >= file.total_samples {
+                info!("🏁 Playback reached end of file");
+                playing = false;
+            }
+            
+            Self::update_status(&status, &file, playing, speed);
+            
+            if playing {
+                thread::sleep(TICK);
+            }
+        }
+        
+        info!("🛑 Playback worker exiting");
+    }
+    
+    fn update_status(status: &Mutex<PlaybackStatus>, file: &PlaybackFile, playing: bool, speed: f64) {
+        let mut status = status.lock().unwrap();
+        status.position_seconds = file.position as f64 / file.sample_rate();
+        status.speed = speed;
+        status.state = if playing {
+            PlaybackState::Playing
+        } else if file.position >= file.total_samples {
+            PlaybackState::Finished
+        } else {
+            PlaybackState::Paused
+        };
+    }
+}
+
+/// 回放数据来源
+enum PlaybackData {
+    Edf { reader: EdfReader, signals: Vec<usize> },              // 参与回放的信号索引（采样率与首个信号一致）
+    Bdf { reader: Box<RecordingReader>, channels: Vec<usize> },  // 同上，按通道序号（不含注释信号）
+    Memory(Vec<Vec<f64>>),                                       // XDF：所选流整个读入内存，每个样本一行
+}
+
+/// 已打开的录制文件及读取位置
+struct PlaybackFile {
+    data: PlaybackData,
+    stream_info: StreamInfo,
+    total_samples: u64,
+    position: u64,
+    markers: Vec<(f64, String)>,    // (起始秒, 描述)，按时间排序
+    next_marker: usize,
+}
+
+impl PlaybackFile {
+    fn open(path: &str) -> Result<Self, AppError> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        
+        match extension.as_deref() {
+            Some("edf") => Self::open_edf(path),
+            Some("bdf") => Self::open_bdf(path),
+            Some("xdf") => Self::from_xdf(path, xdf::read(Path::new(path))?),
+            _ => Err(AppError::Playback(format!(
+                "Unsupported playback file (supported: EDF/EDF+, BDF/BDF+, XDF): {}", path
+            ))),
+        }
+    }
+    
+    fn open_edf(path: &str) -> Result<Self, AppError> {
+        let reader = EdfReader::open(path)
+            .map_err(|e| AppError::Playback(format!("Failed to open {}: {}", path, e)))?;
+        
+        let header = reader.header().clone();
+        let first = header.signals.first()
+            .ok_or_else(|| AppError::Playback(format!("{} contains no signals", path)))?;
+        
+        let record_duration = header.datarecord_duration as f64 / EDF_TIME_UNITS;
+        if record_duration <= 0.0 {
+            return Err(AppError::Playback(format!("{} has an invalid data record duration", path)));
+        }
+        
+        // 只回放与首个信号采样率相同的信号
+        let samples_per_record = first.samples_per_record;
+        let signals: Vec<usize> = header.signals
+            .iter()
+            .enumerate()
+            .filter(|(_, signal)| signal.samples_per_record == samples_per_record)
+            .map(|(idx, _)| idx)
+            .collect();
+        if signals.len() < header.signals.len() {
+            warn!("⚠️  Skipping {} signals with a different sample rate",
+                     header.signals.len() - signals.len());
+        }
+        
+        let stream_info = StreamInfo {
+            name: file_stem(path),
+            stream_type: "EEG".to_string(),
+            channels_count: signals.len() as u32,
+            sample_rate: samples_per_record as f64 / record_duration,
+            is_connected: true,
+            source_id: format!("playback:{}", path),
+            channel_labels: signals.iter().map(|&idx| header.signals[idx].label.trim().to_string()).collect(),
+            effective_sample_rate: None,
+        };
+        
+        let markers: Vec<(f64, String)> = reader.annotations()
+            .iter()
+            .map(|a| (a.onset as f64 / EDF_TIME_UNITS, a.description.clone()))
+            .collect();
+        
+        let total_samples = header.datarecords_in_file.max(0) as u64 * samples_per_record.max(0) as u64;
+        Ok(Self::new(PlaybackData::Edf { reader, signals }, stream_info, total_samples, markers))
+    }
+    
+    fn open_bdf(path: &str) -> Result<Self, AppError> {
+        let reader = RecordingReader::open(Path::new(path))?;
+        let info = reader.info().clone();
+        let first = info.channels.first()
+            .ok_or_else(|| AppError::Playback(format!("{} contains no signals", path)))?;
+        
+        // 只回放与首个信号采样率相同的信号
+        let channels: Vec<usize> = (0..info.channels.len())
+            .filter(|&channel| info.channels[channel].sample_rate == first.sample_rate)
+            .collect();
+        if channels.len() < info.channels.len() {
+            warn!("⚠️  Skipping {} signals with a different sample rate", info.channels.len() - channels.len());
+        }
+        
+        let stream_info = StreamInfo {
+            name: file_stem(path),
+            stream_type: "EEG".to_string(),
+            channels_count: channels.len() as u32,
+            sample_rate: first.sample_rate,
+            is_connected: true,
+            source_id: format!("playback:{}", path),
+            channel_labels: channels.iter().map(|&channel| info.channels[channel].label.clone()).collect(),
+            effective_sample_rate: None,
+        };
+        
+        let markers = info.annotations.iter()
+            .map(|annotation| (annotation.onset, annotation.description.clone()))
+            .collect();
+        
+        let total_samples = (info.duration_seconds * first.sample_rate).round() as u64;
+        Ok(Self::new(PlaybackData::Bdf { reader: Box::new(reader), channels }, stream_info, total_samples, markers))
+    }
+    
+    /// 回放XDF中的EEG流（没有EEG类型时取第一个规则采样的数值流），字符串流作为标记；
+    /// 时间以EEG流第一个样本的LSL时间戳为零点
+    fn from_xdf(path: &str, streams: Vec<XdfStream>) -> Result<Self, AppError> {
+        let regular = |stream: &&XdfStream| matches!(stream.values, XdfValues::Numeric(_)) && stream.nominal_srate > 0.0;
+        let eeg = streams.iter().filter(regular).find(|stream| stream.stream_type.eq_ignore_ascii_case("EEG"))
+            .or_else(|| streams.iter().find(regular))
+            .ok_or_else(|| AppError::Playback(format!("{} contains no regularly sampled numeric stream", path)))?;
+        let XdfValues::Numeric(rows) = &eeg.values else { unreachable!() };
+        let origin = eeg.timestamps.first().copied().unwrap_or(0.0);
+        
+        let channels_count = eeg.channel_count as u32;
+        let stream_info = StreamInfo {
+            name: if eeg.name.is_empty() { file_stem(path) } else { eeg.name.clone() },
+            stream_type: if eeg.stream_type.is_empty() { "EEG".to_string() } else { eeg.stream_type.clone() },
+            channels_count,
+            sample_rate: eeg.nominal_srate,
+            is_connected: true,
+            source_id: format!("playback:{}", path),
+            channel_labels: if eeg.channel_labels.len() == eeg.channel_count {
+                eeg.channel_labels.clone()
+            } else {
+                StreamInfo::default_channel_labels(channels_count)
+            },
+            effective_sample_rate: None,
+        };
+        
+        let markers = streams.iter()
+            .filter_map(|stream| match &stream.values {
+                XdfValues::Strings(rows) => Some(stream.timestamps.iter().zip(rows)),
+                XdfValues::Numeric(_) => None,
+            })
+            .flatten()
+            .map(|(timestamp, row)| (timestamp - origin, row.join(",")))
+            .collect();
+        
+        let rows = rows.clone();
+        let total_samples = rows.len() as u64;
+        Ok(Self::new(PlaybackData::Memory(rows), stream_info, total_samples, markers))
+    }
+    
+    fn new(data: PlaybackData, stream_info: StreamInfo, total_samples: u64, mut markers: Vec<(f64, String)>) -> Self {
+        markers.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { data, stream_info, total_samples, position: 0, markers, next_marker: 0 }
+    }
+    
+    fn sample_rate(&self) -> f64 {
+        self.stream_info.sample_rate
+    }
+    
+    fn duration_seconds(&self) -> f64 {
+        self.total_samples as f64 / self.sample_rate()
+    }
+    
+    /// 读取count个样本，时间戳为文件内秒数
+    fn read_chunk(&mut self, count: usize) -> Result<EegChunk, AppError> {
+        let rows = self.read_rows(count)?;
+        if rows.is_empty() {
+            // 文件实际长度短于头信息
+            self.total_samples = self.position;
+            return Ok(Vec::new());
+        }
+        
+        let chunk = rows.into_iter()
+            .enumerate()
+            .map(|(i, channels)| {
+                let sample_id = self.position + i as u64;
+                EegSample {
+                    timestamp: sample_id as f64 / self.sample_rate(),
+                    channels,
+                    sample_id,
+                }
+            })
+            .collect::<Vec<_>>();
+        
+        self.position += chunk.len() as u64;
+        Ok(chunk)
+    }
+    
+    /// 从当前位置读取至多count个样本，每个样本一行
+    fn read_rows(&mut self, count: usize) -> Result<Vec<Vec<f64>>, AppError> {
+        let columns = match &mut self.data {
+            PlaybackData::Memory(rows) => {
+                let start = (self.position as usize).min(rows.len());
+                let end = (start + count).min(rows.len());
+                return Ok(rows[start..end].to_vec());
+            }
+            PlaybackData::Edf { reader, signals } => {
+                let mut columns = Vec::with_capacity(signals.len());
+                for &signal in signals.iter() {
+                    let samples = reader.read_physical_samples(signal, count)
+                        .map_err(|e| AppError::Playback(format!("Failed to read signal {}: {}", signal, e)))?;
+                    columns.push(samples);
+                }
+                columns
+            }
+            PlaybackData::Bdf { reader, channels } => reader.read_samples(channels, self.position, count)?,
+        };
+        
+        let available = columns.iter().map(|c| c.len()).min().unwrap_or(0).min(count);
+        Ok((0..available).map(|i| columns.iter().map(|c| c[i]).collect()).collect())
+    }
+    
+    fn seek(&mut self, position: u64) {
+        // 按样本序号读取的来源无需定位
+        if let PlaybackData::Edf { reader, signals } = &mut self.data {
+            for &signal in signals.iter() {
+                if let Err(e) = reader.seek(signal, position as i64) {
+                    warn!("⚠️  Seek failed on signal {}: {}", signal, e);
+                }
+            }
+        }
+        
+        self.position = position;
+        let seconds = position as f64 / self.sample_rate();
+        self.next_marker = self.markers.partition_point(|(onset, _)| *onset < seconds);
+    }
+    
+    /// 取出起始时间早于当前位置的注释，作为标记送入管道
+    fn markers_until(&mut self, position: u64) -> Vec<LslMarker> {
+        let seconds = position as f64 / self.sample_rate();
+        let mut markers = Vec::new();
+        
+        while let Some((onset, description)) = self.markers.get(self.next_marker) {
+            if *onset >= seconds {
+                break;
+            }
+            markers.push(LslMarker {
+                label: description.clone(),
+                timestamp: *onset,
+                stream_name: self.stream_info.name.clone(),
+            });
+            self.next_marker += 1;
+        }
+        
+        markers
+    }
+}
+
+/// 文件名（不含扩展名）作为回放流名称
+fn file_stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("playback")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::{BdfSignal, BdfWriter};
+    use chrono::Utc;
+    
+    #[test]
+    fn test_playback_bdf_file() {
+        let path = std::env::temp_dir().join(format!("playback_test_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        
+        let signals = ["Cz", "Pz"].iter().map(|label| BdfSignal {
+            label: label.to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -1000.0,
+            physical_max: 1000.0,
+            prefilter: String::new(),
+            samples_per_record: 4,
+        }).collect();
+        let mut writer = BdfWriter::create(&filename, signals, 1.0, Utc::now(), "X", "X").unwrap();
+        writer.add_annotation(1.5, None, "stimulus").unwrap();
+        for record in 0..2 {
+            let base = record as f64 * 4.0;
+            writer.write_samples(&[
+                (0..4).map(|i| base + i as f64).collect(),
+                (0..4).map(|i| -(base + i as f64)).collect(),
+            ]).unwrap();
+        }
+        writer.finalize().unwrap();
+        
+        let mut file = PlaybackFile::open(&filename).unwrap();
+        assert_eq!(file.stream_info.channel_labels, vec!["Cz", "Pz"]);
+        assert_eq!(file.sample_rate(), 4.0);
+        assert_eq!(file.total_samples, 8);
+        
+        file.seek(3);
+        let chunk = file.read_chunk(3).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        assert_eq!(chunk.iter().map(|s| s.sample_id).collect::<Vec<_>>(), vec![3, 4, 5]);
+        for sample in &chunk {
+            let expected = sample.sample_id as f64;
+            assert!((sample.channels[0] - expected).abs() < 0.01);
+            assert!((sample.channels[1] + expected).abs() < 0.01);
+        }
+        assert_eq!(file.markers, vec![(1.5, "stimulus".to_string())]);
+        assert_eq!(file.next_marker, 0);
+    }
+    
+    #[test]
+    fn test_playback_xdf_streams() {
+        let eeg = XdfStream {
+            id: 2,
+            name: "Amp".to_string(),
+            stream_type: "EEG".to_string(),
+            channel_count: 2,
+            nominal_srate: 100.0,
+            channel_labels: vec!["C3".to_string(), "C4".to_string()],
+            timestamps: vec![10.0, 10.01, 10.02],
+            values: XdfValues::Numeric(vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]]),
+        };
+        let markers = XdfStream {
+            id: 1,
+            name: "Markers".to_string(),
+            stream_type: "Markers".to_string(),
+            channel_count: 1,
+            nominal_srate: 0.0,
+            channel_labels: Vec::new(),
+            timestamps: vec![10.015],
+            values: XdfValues::Strings(vec![vec!["go".to_string()]]),
+        };
+        
+        let mut file = PlaybackFile::from_xdf("session.xdf", vec![markers, eeg]).unwrap();
+        assert_eq!(file.stream_info.name, "Amp");
+        assert_eq!(file.stream_info.channel_labels, vec!["C3", "C4"]);
+        assert_eq!(file.total_samples, 3);
+        assert_eq!(file.markers.len(), 1);
+        assert!((file.markers[0].0 - 0.015).abs() < 1e-9);
+        
+        file.seek(1);
+        let chunk = file.read_chunk(10).unwrap();
+        assert_eq!(chunk.iter().map(|s| s.channels.clone()).collect::<Vec<_>>(), vec![vec![3.0, 4.0], vec![5.0, 6.0]]);
+        assert_eq!(file.next_marker, 0);
+        assert!(file.read_chunk(10).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_unsupported_extension_lists_formats() {
+        let Err(AppError::Playback(message)) = PlaybackFile::open("recording.gdf") else {
+            panic!("expected a playback error");
+        };
+        assert!(message.contains("BDF") && message.contains("XDF"));
+    }
+}
@@ -0,0 +1,285 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::stages::{self, StageConstructor, StageRegistry};
+use std::sync::{Mutex, OnceLock};
+use tracing::info;
+#[cfg(feature = "dynamic-plugins")]
+use std::path::Path;
+#[cfg(feature = "dynamic-plugins")]
+use tracing::error;
+
+/// 插件接口版本，动态库插件须与宿主一致
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// 宿主版本 - 动态库插件经 export_plugin! 记录其构建时所链接的版本
+pub const HOST_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 应用数据目录下存放动态库插件的子目录
+pub const PLUGINS_DIR: &str = "plugins";
+
+/// 第三方插件 - 启动时注册处理阶段，配置中以 StageSpec::Custom `<插件>/<阶段>` 引用
+///
+/// 插件阶段的指标除 stage-metrics 外还发送到命名空间事件 `plugin:<插件>/<阶段>`。
+pub trait Plugin: Send + Sync {
+    /// 插件名称（字母、数字、下划线与连字符），作为阶段与事件的命名空间
+    fn name(&self) -> &str;
+    
+    fn version(&self) -> &str {
+        "0.0.0"
+    }
+    
+    fn register(&self, registrar: &mut PluginRegistrar) -> Result<(), AppError>;
+}
+
+/// 插件注册器 - 收集插件的阶段，全部成功后才写入全局阶段注册表
+pub struct PluginRegistrar {
+    stages: Vec<(String, StageConstructor)>,
+}
+
+impl PluginRegistrar {
+    pub fn register_stage(&mut self, name: &str, constructor: StageConstructor) -> Result<(), AppError> {
+        if self.stages.iter().any(|(existing, _)| existing == name) {
+            return Err(AppError::Plugin(format!("Stage '{}' registered twice", name)));
+        }
+        self.stages.push((name.to_string(), constructor));
+        Ok(())
+    }
+}
+
+/// 动态库插件导出的声明，由 export_plugin! 生成
+///
+/// 插件与宿主之间传递的是 Rust trait 对象，动态库必须以相同的编译器与宿主版本构建。
+pub struct PluginDeclaration {
+    pub api_version: u32,
+    pub host_version: &'static str,
+    pub create: fn() -> Box<dyn Plugin>,
+}
+
+/// 动态库中插件声明的导出符号
+#[cfg(feature = "dynamic-plugins")]
+const DECLARATION_SYMBOL: &[u8] = b"CORTEXARRAY_PLUGIN\0";
+
+/// 在插件动态库（cdylib）中导出插件声明
+///
+/// ```ignore
+/// cortexarray_lib::export_plugin!(SeizureDetectorPlugin::default());
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($constructor:expr) => {
+        #[no_mangle]
+        pub static CORTEXARRAY_PLUGIN: $crate::plugins::PluginDeclaration = $crate::plugins::PluginDeclaration {
+            api_version: $crate::plugins::PLUGIN_API_VERSION,
+            host_version: $crate::plugins::HOST_VERSION,
+            create: {
+                fn create() -> Box<dyn $crate::plugins::Plugin> {
+                    Box::new($constructor)
+                }
+                create
+            },
+        };
+    };
+}
+
+#[derive(Default)]
+struct PluginHost {
+    plugins: Vec<PluginInfo>,
+    // 动态库在进程生命周期内不卸载，阶段构造函数指向其中的代码
+    #[cfg(feature = "dynamic-plugins")]
+    libraries: Vec<libloading::Library>,
+}
+
+fn host() -> &'static Mutex<PluginHost> {
+    static HOST: OnceLock<Mutex<PluginHost>> = OnceLock::new();
+    HOST.get_or_init(|| Mutex::new(PluginHost::default()))
+}
+
+/// 安装插件并注册其阶段
+pub fn install(plugin: Box<dyn Plugin>) -> Result<PluginInfo, AppError> {
+    install_from(plugin.as_ref(), None)
+}
+
+fn install_from(plugin: &dyn Plugin, library: Option<String>) -> Result<PluginInfo, AppError> {
+    let name = plugin.name().to_string();
+    if !stages::is_valid_stage_name(&name) {
+        return Err(AppError::Plugin(format!("Invalid plugin name '{}'", name)));
+    }
+    let mut host = host().lock().map_err(|e| AppError::Plugin(e.to_string()))?;
+    if host.plugins.iter().any(|installed| installed.name == name) {
+        return Err(AppError::Plugin(format!("Plugin '{}' is already installed", name)));
+    }
+    
+    let mut registrar = PluginRegistrar { stages: Vec::new() };
+    plugin.register(&mut registrar)
+        .map_err(|e| AppError::Plugin(format!("Plugin '{}' failed to register: {}", name, e)))?;
+    
+    // 先在副本上注册，任一阶段失败时不留下部分注册
+    let mut registry = StageRegistry::global().write().map_err(|e| AppError::Plugin(e.to_string()))?;
+    let mut staged = StageRegistry::new();
+    for (stage, constructor) in &registrar.stages {
+        let qualified = format!("{}/{}", name, stage);
+        if registry.contains(&qualified) {
+            return Err(AppError::Plugin(format!("Processing stage '{}' is already registered", qualified)));
+        }
+        staged.register_plugin_stage(&name, stage, constructor.clone())?;
+    }
+    for (stage, constructor) in registrar.stages {
+        registry.register_plugin_stage(&name, &stage, constructor)?;
+    }
+    
+    let info = PluginInfo {
+        name: name.clone(),
+        version: plugin.version().to_string(),
+        stages: staged.names(),
+        library,
+    };
+    info!("🧩 Plugin '{}' {} installed: {:?}", info.name, info.version, info.stages);
+    host.plugins.push(info.clone());
+    Ok(info)
+}
+
+/// 已安装的插件
+pub fn installed() -> Vec<PluginInfo> {
+    host().lock().map(|host| host.plugins.clone()).unwrap_or_default()
+}
+
+/// 插件阶段指标的命名空间事件名，非插件阶段返回 None
+pub fn event_name(stage: &str) -> Option<String> {
+    stage.contains('/').then(|| format!("plugin:{}", stage))
+}
+
+/// 加载动态库插件
+#[cfg(feature = "dynamic-plugins")]
+pub fn load_library(path: &Path) -> Result<PluginInfo, AppError> {
+    let load_error = |e: libloading::Error| AppError::Plugin(format!("{}: {}", path.display(), e));
+    
+    // SAFETY: 加载动态库会执行其初始化代码；插件目录由用户管理，视为可信
+    let library = unsafe { libloading::Library::new(path) }.map_err(load_error)?;
+    // SAFETY: 符号由 export_plugin! 导出，类型为 PluginDeclaration
+    let declaration = unsafe {
+        let symbol = library.get::<*const PluginDeclaration>(DECLARATION_SYMBOL).map_err(load_error)?;
+        &**symbol
+    };
+    
+    if declaration.api_version != PLUGIN_API_VERSION || declaration.host_version != HOST_VERSION {
+        return Err(AppError::Plugin(format!(
+            "{}: built for plugin API {} / host {}, expected {} / {}",
+            path.display(), declaration.api_version, declaration.host_version, PLUGIN_API_VERSION, HOST_VERSION
+        )));
+    }
+    
+    let plugin = (declaration.create)();
+    let info = install_from(plugin.as_ref(), Some(path.display().to_string()))?;
+    if let Ok(mut host) = host().lock() {
+        host.libraries.push(library);
+    }
+    Ok(info)
+}
+
+/// 加载目录中的全部动态库插件，单个插件失败不影响其余插件
+#[cfg(feature = "dynamic-plugins")]
+pub fn load_directory(dir: &Path) -> Vec<PluginInfo> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION))
+        .collect();
+    paths.sort();
+    
+    paths.iter()
+        .filter_map(|path| match load_library(path) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                error!("❌ Failed to load plugin: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stages::{ProcessingStage, StageBatch, StagePipeline};
+    use std::sync::Arc;
+    
+    struct ThresholdStage;
+    
+    impl ProcessingStage for ThresholdStage {
+        fn name(&self) -> &str {
+            "threshold"
+        }
+        
+        fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric> {
+            let peak = batch.samples.iter()
+                .flat_map(|sample| sample.channels.iter().map(|value| value.abs()))
+                .fold(0.0, f64::max);
+            vec![StageMetric { name: "peak".to_string(), channel_index: None, value: peak }]
+        }
+    }
+    
+    struct TestPlugin {
+        name: &'static str,
+        stages: Vec<&'static str>,
+    }
+    
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+        
+        fn register(&self, registrar: &mut PluginRegistrar) -> Result<(), AppError> {
+            for stage in &self.stages {
+                registrar.register_stage(stage, Arc::new(|_: &StreamInfo, _: &serde_json::Value| {
+                    Ok(Box::new(ThresholdStage) as Box<dyn ProcessingStage>)
+                }))?;
+            }
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn test_plugin_stages_are_namespaced_and_registered_atomically() {
+        let info = install(Box::new(TestPlugin { name: "test_plugin", stages: vec!["threshold"] })).unwrap();
+        assert_eq!(info.stages, vec!["test_plugin/threshold"]);
+        assert!(installed().contains(&info));
+        assert!(StageRegistry::global().read().unwrap().contains("test_plugin/threshold"));
+        assert_eq!(event_name("test_plugin/threshold").as_deref(), Some("plugin:test_plugin/threshold"));
+        assert_eq!(event_name("notch"), None);
+        
+        // 指标以注册名称报告
+        let config = ProcessingStagesConfig {
+            stages: vec![StageSpec::Custom { name: "test_plugin/threshold".to_string(), params: serde_json::Value::Null }],
+        };
+        let stream_info = StreamInfo {
+            name: "Test".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 1,
+            sample_rate: 250.0,
+            is_connected: true,
+            source_id: "test".to_string(),
+            channel_labels: vec!["Cz".to_string()],
+            effective_sample_rate: None,
+        };
+        let mut pipeline = StagePipeline::build(&config, &stream_info, &StageRegistry::global().read().unwrap()).unwrap();
+        let mut batch = StageBatch {
+            channel_labels: stream_info.channel_labels.clone(),
+            samples: vec![EegSample { timestamp: 1.0, channels: vec![-3.0], sample_id: 0 }],
+        };
+        let updates = pipeline.process(&mut batch);
+        assert_eq!(updates[0].stage, "test_plugin/threshold");
+        assert_eq!(updates[0].metrics[0].value, 3.0);
+        
+        // 同名插件不能重复安装
+        assert!(install(Box::new(TestPlugin { name: "test_plugin", stages: vec!["other"] })).is_err());
+        
+        // 任一阶段名称无效时整个插件都不注册
+        assert!(install(Box::new(TestPlugin { name: "broken_plugin", stages: vec!["ok", "bad name"] })).is_err());
+        let registry = StageRegistry::global().read().unwrap();
+        assert!(!registry.contains("broken_plugin/ok"));
+        assert!(!installed().iter().any(|plugin| plugin.name == "broken_plugin"));
+    }
+}
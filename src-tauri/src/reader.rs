@@ -0,0 +1,396 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::info;
+
+// 每个信号的头部字段宽度，按字段依次排列（标签、传感器、单位、物理/数字范围、预滤波、样本数、保留）
+const SIGNAL_FIELD_WIDTHS: [usize; 10] = [16, 80, 8, 8, 8, 8, 8, 80, 8, 32];
+
+// 单次读取的样本总数上限（所有选中通道合计），避免前端请求过长的片段
+const MAX_SEGMENT_SAMPLES: f64 = 20_000_000.0;
+
+/// 文件头中的一个信号
+struct FileSignal {
+    channel: RecordingFileChannel,
+    digital_min: f64,
+    digital_max: f64,
+    samples_per_record: usize,
+    offset: usize,                  // 在数据记录中的字节偏移
+    annotations: bool,              // "EDF Annotations" / "BDF Annotations"
+}
+
+impl FileSignal {
+    fn to_physical(&self, digital: i32) -> f64 {
+        let channel = &self.channel;
+        let scale = (channel.physical_max - channel.physical_min) / (self.digital_max - self.digital_min);
+        (digital as f64 - self.digital_min) * scale + channel.physical_min
+    }
+}
+
+/// 已打开的 EDF(+)/BDF(+) 录制文件，用于复核已录制的数据
+///
+/// 数据记录按连续排列处理（EDF+C/BDF+C），注释在打开时一次读出。
+/// 未完成的文件（记录数为 -1）按文件长度计算完整的数据记录数。
+pub struct RecordingReader {
+    file: BufReader<File>,
+    info: RecordingFileInfo,
+    signals: Vec<FileSignal>,
+    header_bytes: u64,
+    record_bytes: usize,
+    bytes_per_sample: usize,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let mut file = BufReader::new(File::open(path)?);
+        
+        let mut fixed = [0u8; 256];
+        file.read_exact(&mut fixed)
+            .map_err(|_| invalid(path, "file is shorter than the fixed header"))?;
+        let (format, bytes_per_sample) = match fixed[0] {
+            0xFF if &fixed[1..8] == b"BIOSEMI" => (RecordingFormat::Bdf, 3),
+            b'0' => (RecordingFormat::Edf, 2),
+            _ => return Err(invalid(path, "not an EDF or BDF file")),
+        };
+        
+        let header_bytes: u64 = parse_field(&fixed[184..192]).ok_or_else(|| invalid(path, "bad header size"))?;
+        let records_in_header: i64 = parse_field(&fixed[236..244]).unwrap_or(-1);
+        let record_duration: f64 = parse_field(&fixed[244..252]).ok_or_else(|| invalid(path, "bad record duration"))?;
+        let signal_count: usize = parse_field(&fixed[252..256]).ok_or_else(|| invalid(path, "bad signal count"))?;
+        if signal_count == 0 || header_bytes != 256 * (signal_count as u64 + 1) {
+            return Err(invalid(path, "header size does not match signal count"));
+        }
+        if record_duration <= 0.0 {
+            return Err(invalid(path, "data record duration must be positive"));
+        }
+        
+        let mut signal_header = vec![0u8; signal_count * 256];
+        file.read_exact(&mut signal_header)
+            .map_err(|_| invalid(path, "file is shorter than the signal headers"))?;
+        let signals = parse_signals(&signal_header, signal_count, bytes_per_sample, record_duration)
+            .ok_or_else(|| invalid(path, "bad signal header"))?;
+        
+        let record_bytes = signals.iter().map(|signal| signal.samples_per_record * bytes_per_sample).sum::<usize>();
+        if record_bytes == 0 {
+            return Err(invalid(path, "data records are empty"));
+        }
+        let records_in_file = file.get_ref().metadata()?.len().saturating_sub(header_bytes) / record_bytes as u64;
+        let records = match u64::try_from(records_in_header) {
+            Ok(records) => records.min(records_in_file),
+            Err(_) => records_in_file,
+        };
+        
+        let recording = text_field(&fixed[88..168]);
+        let mut reader = Self {
+            file,
+            info: RecordingFileInfo {
+                path: path.display().to_string(),
+                format,
+                patient: text_field(&fixed[8..88]),
+                start_time: start_time(&fixed[168..176], &fixed[176..184], &recording),
+                recording,
+                duration_seconds: records as f64 * record_duration,
+                record_duration,
+                records,
+                channels: signals.iter().filter(|signal| !signal.annotations).map(|signal| signal.channel.clone()).collect(),
+                annotations: Vec::new(),
+            },
+            signals,
+            header_bytes,
+            record_bytes,
+            bytes_per_sample,
+        };
+        reader.info.annotations = reader.read_annotations()?;
+        
+        info!("📖 Opened {} for review: {} channels, {:.1}s, {} annotations",
+              path.display(), reader.info.channels.len(), reader.info.duration_seconds, reader.info.annotations.len());
+        Ok(reader)
+    }
+    
+    pub fn info(&self) -> &RecordingFileInfo {
+        &self.info
+    }
+    
+    /// 读取所有数据记录中注释信号的TAL，跳过每个记录开头的时间标记
+    fn read_annotations(&mut self) -> Result<Vec<RecordingFileAnnotation>, AppError> {
+        let mut annotations = Vec::new();
+        let annotation_signals: Vec<(usize, usize)> = self.signals.iter()
+            .filter(|signal| signal.annotations)
+            .map(|signal| (signal.offset, signal.samples_per_record * self.bytes_per_sample))
+            .collect();
+        if annotation_signals.is_empty() {
+            return Ok(annotations);
+        }
+        
+        let mut record = vec![0u8; self.record_bytes];
+        self.file.seek(SeekFrom::Start(self.header_bytes))?;
+        for _ in 0..self.info.records {
+            self.file.read_exact(&mut record)?;
+            for &(offset, length) in &annotation_signals {
+                annotations.extend(parse_tals(&record[offset..offset + length]));
+            }
+        }
+        annotations.sort_by(|a, b| a.onset.total_cmp(&b.onset));
+        Ok(annotations)
+    }
+    
+    /// 读取 [start, start + duration) 内选中通道的物理值；channels 为 None 时读取全部通道
+    pub fn read_segment(&mut self, start: f64, duration: f64, channels: Option<&[String]>) -> Result<RecordingSegment, AppError> {
+        if !start.is_finite() || !duration.is_finite() || duration <= 0.0 {
+            return Err(AppError::Config(format!("Invalid segment {}s + {}s", start, duration)));
+        }
+        
+        let selected: Vec<usize> = match channels {
+            Some(labels) => labels.iter()
+                .map(|label| self.signals.iter()
+                    .position(|signal| !signal.annotations && signal.channel.label == label.trim())
+                    .ok_or_else(|| AppError::Config(format!("{} has no channel '{}'", self.info.path, label))))
+                .collect::<Result<_, _>>()?,
+            None => (0..self.signals.len()).filter(|&index| !self.signals[index].annotations).collect(),
+        };
+        
+        let start = start.clamp(0.0, self.info.duration_seconds);
+        let end = (start + duration).min(self.info.duration_seconds);
+        let requested: f64 = selected.iter().map(|&index| self.signals[index].channel.sample_rate * (end - start)).sum();
+        if requested > MAX_SEGMENT_SAMPLES {
+            return Err(AppError::Config(format!(
+                "Segment of {:.1}s would read {:.0} samples, at most {} per request", end - start, requested, MAX_SEGMENT_SAMPLES
+            )));
+        }
+        
+        let columns = self.read_signals(&selected, start, end)?;
+        Ok(RecordingSegment {
+            start,
+            duration: end - start,
+            channels: selected.iter().zip(columns)
+                .map(|(&index, samples)| SegmentChannel {
+                    label: self.signals[index].channel.label.clone(),
+                    sample_rate: self.signals[index].channel.sample_rate,
+                    samples,
+                })
+                .collect(),
+            annotations: self.info.annotations.iter()
+                .filter(|annotation| annotation.onset < end && annotation.onset + annotation.duration.unwrap_or(0.0) >= start)
+                .cloned()
+                .collect(),
+        })
+    }
+    
+    /// 读取第 channel 个通道（不含注释信号）的全部物理值，供离线处理使用
+    pub fn read_channel(&mut self, channel: usize) -> Result<Vec<f64>, AppError> {
+        let index = self.signal_index(channel)?;
+        let duration = self.info.duration_seconds;
+        Ok(self.read_signals(&[index], 0.0, duration)?.remove(0))
+    }
+    
+    /// 按样本序号读取各通道（不含注释信号，采样率需相同）的 [first, first + count) 物理值，供回放使用
+    pub fn read_samples(&mut self, channels: &[usize], first: u64, count: usize) -> Result<Vec<Vec<f64>>, AppError> {
+        let indices = channels.iter().map(|&channel| self.signal_index(channel)).collect::<Result<Vec<_>, _>>()?;
+        let Some(&index) = indices.first() else { return Ok(Vec::new()) };
+        
+        let rate = self.signals[index].channel.sample_rate;
+        let start = first as f64 / rate;
+        let end = ((first + count as u64) as f64 / rate).min(self.info.duration_seconds);
+        if start >= end {
+            return Ok(vec![Vec::new(); indices.len()]);
+        }
+        self.read_signals(&indices, start, end)
+    }
+    
+    /// 第 channel 个通道（不含注释信号）的信号索引
+    fn signal_index(&self, channel: usize) -> Result<usize, AppError> {
+        (0..self.signals.len())
+            .filter(|&index| !self.signals[index].annotations)
+            .nth(channel)
+            .ok_or_else(|| AppError::Config(format!("{} has no channel {}", self.info.path, channel)))
+    }
+    
+    /// 读取 [start, end) 内各信号的物理值，signals 为信号索引
+    fn read_signals(&mut self, signals: &[usize], start: f64, end: f64) -> Result<Vec<Vec<f64>>, AppError> {
+        let record_duration = self.info.record_duration;
+        let first_record = (start / record_duration).floor() as u64;
+        let last_record = ((end / record_duration).ceil() as u64).min(self.info.records);
+        let mut columns: Vec<Vec<f64>> = vec![Vec::new(); signals.len()];
+        
+        let mut record = vec![0u8; self.record_bytes];
+        self.file.seek(SeekFrom::Start(self.header_bytes + first_record * self.record_bytes as u64))?;
+        for record_index in first_record..last_record {
+            self.file.read_exact(&mut record)?;
+            let record_onset = record_index as f64 * record_duration;
+            
+            for (column, &index) in columns.iter_mut().zip(signals) {
+                let signal = &self.signals[index];
+                let rate = signal.channel.sample_rate;
+                // 该记录内落在片段中的样本范围
+                let from = ((start - record_onset) * rate).round().max(0.0) as usize;
+                let to = (((end - record_onset) * rate).round().max(0.0) as usize).min(signal.samples_per_record);
+                for sample in from..to {
+                    let offset = signal.offset + sample * self.bytes_per_sample;
+                    column.push(signal.to_physical(read_digital(&record[offset..offset + self.bytes_per_sample])));
+                }
+            }
+        }
+        Ok(columns)
+    }
+}
+
+/// 按字段排列的信号头部解析为各信号参数
+fn parse_signals(header: &[u8], signal_count: usize, bytes_per_sample: usize, record_duration: f64) -> Option<Vec<FileSignal>> {
+    let mut field_offsets = [0usize; SIGNAL_FIELD_WIDTHS.len()];
+    for field in 1..SIGNAL_FIELD_WIDTHS.len() {
+        field_offsets[field] = field_offsets[field - 1] + SIGNAL_FIELD_WIDTHS[field - 1] * signal_count;
+    }
+    let field = |field: usize, signal: usize| {
+        let start = field_offsets[field] + SIGNAL_FIELD_WIDTHS[field] * signal;
+        &header[start..start + SIGNAL_FIELD_WIDTHS[field]]
+    };
+    
+    let mut signals = Vec::with_capacity(signal_count);
+    let mut offset = 0;
+    for signal in 0..signal_count {
+        let label = text_field(field(0, signal));
+        let samples_per_record: usize = parse_field(field(8, signal))?;
+        let digital_min: f64 = parse_field(field(5, signal))?;
+        let digital_max: f64 = parse_field(field(6, signal))?;
+        if digital_max <= digital_min {
+            return None;
+        }
+        
+        signals.push(FileSignal {
+            annotations: label == "EDF Annotations" || label == "BDF Annotations",
+            channel: RecordingFileChannel {
+                label,
+                sample_rate: samples_per_record as f64 / record_duration,
+                physical_dimension: text_field(field(2, signal)),
+                physical_min: parse_field(field(3, signal))?,
+                physical_max: parse_field(field(4, signal))?,
+                prefilter: text_field(field(7, signal)),
+            },
+            digital_min,
+            digital_max,
+            samples_per_record,
+            offset,
+        });
+        offset += samples_per_record * bytes_per_sample;
+    }
+    Some(signals)
+}
+
+/// 注释信号中的TAL："+起点[\x15时长]\x14描述\x14[描述\x14...]\0"，描述为空的是时间标记
+fn parse_tals(bytes: &[u8]) -> Vec<RecordingFileAnnotation> {
+    let mut annotations = Vec::new();
+    for tal in bytes.split(|&byte| byte == 0).filter(|tal| !tal.is_empty()) {
+        let text = String::from_utf8_lossy(tal);
+        let mut parts = text.split('\u{14}');
+        let Some(timing) = parts.next() else { continue };
+        let mut timing = timing.split('\u{15}');
+        let Some(onset) = timing.next().and_then(|onset| onset.parse::<f64>().ok()) else { continue };
+        let duration = timing.next().and_then(|duration| duration.parse::<f64>().ok());
+        
+        for description in parts.filter(|description| !description.is_empty()) {
+            annotations.push(RecordingFileAnnotation { onset, duration, description: description.to_string() });
+        }
+    }
+    annotations
+}
+
+/// 小端补码样本（EDF 2字节，BDF 3字节）
+fn read_digital(bytes: &[u8]) -> i32 {
+    match bytes {
+        [low, high] => i16::from_le_bytes([*low, *high]) as i32,
+        [low, middle, high] => i32::from_le_bytes([0, *low, *middle, *high]) >> 8,
+        _ => 0,
+    }
+}
+
+/// 起始时间：EDF+ 记录字段中有四位年份时优先使用，否则按 EDF 规则解析两位年份（85-99 为19xx）
+fn start_time(date: &[u8], time: &[u8], recording: &str) -> Option<NaiveDateTime> {
+    let time = NaiveTime::parse_from_str(&text_field(time), "%H.%M.%S").ok()?;
+    let edf_plus_date = recording.strip_prefix("Startdate ")
+        .and_then(|rest| rest.split(' ').next())
+        .and_then(|date| NaiveDate::parse_from_str(date, "%d-%b-%Y").ok());
+    let date = match edf_plus_date {
+        Some(date) => date,
+        None => {
+            let date = text_field(date);
+            let mut parts = date.split('.').map(|part| part.parse::<u32>().ok());
+            let (day, month, year) = (parts.next()??, parts.next()??, parts.next()??);
+            let year = if year >= 85 { 1900 + year } else { 2000 + year };
+            NaiveDate::from_ymd_opt(year as i32, month, day)?
+        }
+    };
+    Some(date.and_time(time))
+}
+
+fn text_field(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+fn parse_field<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.trim().parse().ok()
+}
+
+fn invalid(path: &Path, reason: &str) -> AppError {
+    AppError::Recording(format!("{} is not a valid EDF/BDF file: {}", path.display(), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::{BdfSignal, BdfWriter};
+    use chrono::{TimeZone, Utc};
+    
+    #[test]
+    fn test_read_bdf_segment_and_annotations() {
+        let path = std::env::temp_dir().join(format!("reader_test_{}.bdf", std::process::id()));
+        let signal = |label: &str| BdfSignal {
+            label: label.to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -8_388_608.0,
+            physical_max: 8_388_607.0,
+            prefilter: String::new(),
+            samples_per_record: 4,
+        };
+        let start = Utc.with_ymd_and_hms(2024, 3, 7, 14, 5, 9).unwrap();
+        let mut writer = BdfWriter::create(
+            path.to_str().unwrap(), vec![signal("Fz"), signal("Cz")], 1.0, start, "X X X X", "Startdate 07-MAR-2024 X X X"
+        ).unwrap();
+        writer.add_annotation(1.25, Some(0.5), "blink").unwrap();
+        for record in 0..3 {
+            let base = record as f64 * 4.0;
+            let fz: Vec<f64> = (0..4).map(|i| base + i as f64).collect();
+            let cz: Vec<f64> = fz.iter().map(|value| -value).collect();
+            writer.write_samples(&[fz, cz]).unwrap();
+        }
+        writer.finalize().unwrap();
+        
+        let mut reader = RecordingReader::open(&path).unwrap();
+        let info = reader.info().clone();
+        assert_eq!(info.format, RecordingFormat::Bdf);
+        assert_eq!(info.records, 3);
+        assert_eq!(info.duration_seconds, 3.0);
+        assert_eq!(info.start_time, Some(start.naive_utc()));
+        assert_eq!(info.channels.iter().map(|c| c.label.as_str()).collect::<Vec<_>>(), vec!["Fz", "Cz"]);
+        assert_eq!(info.channels[0].sample_rate, 4.0);
+        assert_eq!(info.annotations, vec![RecordingFileAnnotation { onset: 1.25, duration: Some(0.5), description: "blink".to_string() }]);
+        
+        // 跨越记录边界的片段
+        let segment = reader.read_segment(0.5, 1.0, Some(&["Cz".to_string()])).unwrap();
+        assert_eq!(segment.channels.len(), 1);
+        assert_eq!(segment.channels[0].samples, vec![-2.0, -3.0, -4.0, -5.0]);
+        assert_eq!(segment.annotations.len(), 1);
+        
+        // 超出文件末尾时截短，未知通道报错
+        let tail = reader.read_segment(2.5, 10.0, None).unwrap();
+        assert_eq!(tail.duration, 0.5);
+        assert_eq!(tail.channels[0].samples, vec![10.0, 11.0]);
+        assert!(tail.annotations.is_empty());
+        assert!(reader.read_segment(0.0, 1.0, Some(&["Pz".to_string()])).is_err());
+        
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,51 +1,489 @@
 use crate::data_types::*;
 use crate::error::AppError;
+use crate::bdf::{push_field, BdfSignal, BdfWriter};
+use crate::delimited::DelimitedWriter;
+use crate::gdf::{GdfPatient, GdfSignal, GdfWriter, GDF_DIMENSION_MICROVOLT};
+use crate::resample::Resampler;
+#[cfg(feature = "hdf5")]
+use crate::hdf5_writer::Hdf5Writer;
 use edfplus::{EdfWriter, SignalParam};
 use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use chrono::{DateTime, Utc};
+use tracing::{debug, info, warn};
+
+// 默认每个数据记录1秒
+const RECORD_DURATION_SEC: f64 = 1.0;
+
+// EDF 规范建议每个数据记录不超过 61440 字节
+pub(crate) const MAX_RECORD_BYTES: usize = 61440;
+
+// 分数采样率自动选择记录长度时最多尝试的整秒数
+const MAX_AUTO_RECORD_SECONDS: u32 = 10;
+
+// EDF 文件头中记录字段的偏移（80字节）
+const EDF_RECORDING_OFFSET: u64 = 88;
+
+// 检查点间隔 - 异常退出时最多丢失约这么长的数据
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(2);
+
+// BDF默认物理范围（μV），24位下分辨率约31.25nV
+const BDF_PHYSICAL_MIN: f64 = -262144.0;
+const BDF_PHYSICAL_MAX: f64 = 262143.0;
+
+// EDF默认物理范围（±μV），16位下分辨率约0.1μV
+const EDF_PHYSICAL_LIMIT: f64 = 3200.0;
+
+// 自动量程的下限（±μV），避免估计期间电极未接触等平坦信号得到过小的量程
+const AUTO_RANGE_FLOOR: f64 = 50.0;
+
+/// 录制文件写入器 - EDF+ (16位)、BDF+ (24位)、GDF (float32)，或逐样本写入的纯文本/HDF5
+enum RecordingWriter {
+    Edf(EdfWriter, EdfRecordingField),
+    Bdf(BdfWriter),
+    Gdf(GdfWriter),
+    Text(DelimitedWriter),
+    #[cfg(feature = "hdf5")]
+    Hdf5(Hdf5Writer),
+    /// 自动量程估计完成前：文件头尚未写入，注释暂存，数据留在通道缓冲中
+    Pending(Vec<(f64, Option<f64>, String)>),
+}
+
+impl RecordingWriter {
+    fn write_samples(&mut self, record: &[Vec<f64>]) -> Result<(), AppError> {
+        match self {
+            RecordingWriter::Edf(writer, _) => writer.write_samples(record)
+                .map_err(|e| AppError::Recording(format!("Failed to write data record: {}", e))),
+            RecordingWriter::Bdf(writer) => writer.write_samples(record),
+            RecordingWriter::Gdf(writer) => writer.write_samples(record),
+            // 纯文本/HDF5逐样本写入，不按数据记录
+            RecordingWriter::Text(_) => Ok(()),
+            #[cfg(feature = "hdf5")]
+            RecordingWriter::Hdf5(_) => Ok(()),
+            RecordingWriter::Pending(_) => Err(AppError::Recording("Physical range calibration is not complete".to_string())),
+        }
+    }
+    
+    fn add_annotation(&mut self, onset: f64, duration: Option<f64>, description: &str) -> Result<(), AppError> {
+        match self {
+            RecordingWriter::Edf(writer, _) => writer.add_annotation(onset, duration, description)
+                .map_err(|e| AppError::Recording(format!("Failed to write annotation: {}", e))),
+            RecordingWriter::Bdf(writer) => writer.add_annotation(onset, duration, description),
+            RecordingWriter::Gdf(writer) => writer.add_annotation(onset, duration, description),
+            RecordingWriter::Text(writer) => writer.add_annotation(onset, duration, description),
+            #[cfg(feature = "hdf5")]
+            RecordingWriter::Hdf5(writer) => writer.add_annotation(onset, duration, description),
+            RecordingWriter::Pending(annotations) => {
+                annotations.push((onset, duration, description.to_string()));
+                Ok(())
+            }
+        }
+    }
+    
+    fn finalize(self) -> Result<(), AppError> {
+        match self {
+            RecordingWriter::Edf(writer, recording_field) => {
+                writer.finalize()
+                    .map_err(|e| AppError::Recording(format!("Failed to finalize EDF file: {}", e)))?;
+                recording_field.write()
+            }
+            RecordingWriter::Bdf(writer) => writer.finalize(),
+            RecordingWriter::Gdf(writer) => writer.finalize(),
+            RecordingWriter::Text(writer) => writer.finalize(),
+            #[cfg(feature = "hdf5")]
+            RecordingWriter::Hdf5(writer) => writer.finalize(),
+            RecordingWriter::Pending(_) => Ok(()),
+        }
+    }
+    
+    /// 检查点：写出缓冲数据；BDF+/GDF 同时回填文件头中的记录数。
+    /// EDF写入器（edfplus）不提供回填接口，异常退出后的文件用 repair::repair_recording 按长度修复
+    fn checkpoint(&mut self) -> Result<(), AppError> {
+        match self {
+            RecordingWriter::Edf(..) | RecordingWriter::Pending(_) => Ok(()),
+            RecordingWriter::Bdf(writer) => writer.checkpoint(),
+            RecordingWriter::Gdf(writer) => writer.checkpoint(),
+            RecordingWriter::Text(writer) => writer.flush(),
+            #[cfg(feature = "hdf5")]
+            RecordingWriter::Hdf5(writer) => writer.checkpoint(),
+        }
+    }
+    
+    /// 逐样本写入的格式；其余格式按数据记录缓冲
+    fn write_row(&mut self, timestamp: f64, channels: &[f64]) -> Option<Result<(), AppError>> {
+        match self {
+            RecordingWriter::Text(writer) => Some(writer.write_row(timestamp, channels)),
+            #[cfg(feature = "hdf5")]
+            RecordingWriter::Hdf5(writer) => Some(writer.write_row(timestamp, channels)),
+            _ => None,
+        }
+    }
+}
+
+/// 首个样本写入前按LSL时间戳添加的注释（标记或异常等）
+struct PendingAnnotation {
+    lsl_timestamp: f64,
+    duration_seconds: Option<f64>,
+    description: String,
+    is_marker: bool,
+}
 
 pub struct EdfRecorder {
-    writer: EdfWriter,
+    writer: RecordingWriter,
+    format: RecordingFormat,
     filename: String,
     stream_info: StreamInfo,
     samples_written: u64,
+    annotations_written: u64,
+    markers_written: u64,          // 其中来自标记流和手动添加的事件标记
+    first_timestamp: Option<f64>,  // 首个样本的LSL时间戳，用于标记对齐
+    last_timestamp: Option<f64>,
+    pending_annotations: Vec<PendingAnnotation>,    // 首个样本写入前按LSL时间戳添加的注释
+    
+    // 按目标速率录制 - stream_info 描述写入文件的速率，input_sample_rate 为采集速率
+    resampler: Option<Resampler>,
+    input_sample_rate: f64,
+    
+    // 只录制部分通道时各信号在流样本中的下标，stream_info 描述缩减后的通道
+    channel_indices: Option<Vec<usize>>,
+    
+    // 前置历史数据的最后一个 sample_id，之后到达的重复样本（已在管道队列中）被跳过
+    history_until: Option<u64>,
+    
+    // 暂停状态 - 暂停期间丢弃样本，文件保持打开
+    paused_at: Option<Instant>,
+    paused_duration: Duration,
+    resume_pending: bool,          // 恢复后的首个样本用于计算LSL时间间隙
+    timestamp_offset: f64,         // 暂停造成的LSL时间偏移（秒），标记对齐时扣除
     
     // 数据缓冲区 - 每个通道一个队列
     channel_buffers: Vec<VecDeque<f64>>,
     
+    // EDF/BDF 物理量程 - 自动量程估计完成前 ranges 为空
+    ranges: Vec<ChannelRange>,
+    calibration: Option<RangeCalibration>,
+    clipped_samples: Vec<u64>,     // 各通道超出量程被截断的样本数
+    
     // EDF+配置参数
-    samples_per_record: usize,    // 每个数据记录的样本数
+    layout: RecordLayout,         // 数据记录长度和每个记录的样本数
     
     // 录制元数据
     start_time: DateTime<Utc>,
+    
+    // 文件分段
+    rotation: Option<RotationConfig>,
+    segments: Vec<String>,
+    segment_start_sample: u64,     // 当前分段首个样本在整个录制中的序号
+    segment_started_at: Instant,
+    last_checkpoint: Instant,
+    
+    // 磁盘空间阈值
+    disk: DiskSpaceConfig,
+    
+    // 辅助流旁路文件 - 首个辅助样本到达时按其通道标签创建
+    record_aux: bool,
+    aux_writer: Option<(DelimitedWriter, Arc<[String]>)>,
+    aux_samples_written: u64,
+    
+    // 时钟偏移旁路文件 - 用于将LSL时间换算到墙钟/UTC
+    clock_writer: Option<DelimitedWriter>,
+    
+    // 脚本指标旁路文件 - (脚本名, 写入器, 列名)，首次写入时按该次指标建立列
+    script_writers: Vec<(String, DelimitedWriter, Vec<String>)>,
+    
+    // 会话元数据 - 每个分段的文件头都写入
+    metadata: SessionMetadata,
+    tags: Vec<String>,             // 录制索引中的标签
+    annotation_log: Vec<AnnotationRecord>,  // 已写入的全部注释，随完成的录制保留
 }
 
 impl EdfRecorder {
     pub fn new(
         filename: String, 
         stream_info: StreamInfo,
+        options: RecordingOptions,
+        metadata: &SessionMetadata,
     ) -> Result<Self, AppError> {
+        options.validate()?;
+        metadata.validate()?;
+        let format = options.format;
+        let (channel_indices, stream_info) = Self::select_channels(stream_info, options.channels.as_deref())?;
         
-        // 计算EDF+参数
-        let record_duration_sec = 1.0; // 1秒每个数据记录
-        let samples_per_record = (stream_info.sample_rate * record_duration_sec) as usize;
+        let input_sample_rate = stream_info.sample_rate;
+        let resampler = match options.resample_hz {
+            Some(rate) if rate != input_sample_rate => {
+                let resampler = Resampler::new(input_sample_rate, rate, stream_info.channels_count as usize)?;
+                info!("🔄 Recording resampled {} -> {} Hz (latency {:.3}s)", 
+                         input_sample_rate, resampler.output_rate(), resampler.latency_seconds());
+                Some(resampler)
+            }
+            _ => None,
+        };
+        let stream_info = StreamInfo {
+            sample_rate: resampler.as_ref().map_or(input_sample_rate, Resampler::output_rate),
+            ..stream_info
+        };
         
-        let mut writer = EdfWriter::create(&filename)
-            .map_err(|e| AppError::Recording(format!("Failed to create EDF file: {}", e)))?;
+        // 计算EDF+参数；纯文本/HDF5 的"记录"只用于控制分段检查的频率
+        let layout = match format {
+            RecordingFormat::Edf | RecordingFormat::Bdf | RecordingFormat::Gdf => {
+                let bytes_per_sample = match format {
+                    RecordingFormat::Edf => 2,
+                    RecordingFormat::Bdf => 3,
+                    _ => 4,
+                };
+                let max_samples = MAX_RECORD_BYTES / (stream_info.channels_count.max(1) as usize * bytes_per_sample);
+                RecordLayout::for_rate(stream_info.sample_rate, options.record_duration, max_samples)?
+            }
+            _ => RecordLayout {
+                duration: RECORD_DURATION_SEC,
+                samples_per_record: (stream_info.sample_rate.round() as usize).max(1),
+            },
+        };
+        let samples_per_record = layout.samples_per_record;
+        debug!("{:?} data records: {}s, {} samples per channel", format, layout.duration, samples_per_record);
         
         // 设置文件头信息
         let start_time = Utc::now();
         
+        // 分段录制时文件名追加序号：session.edf -> session_001.edf
+        let rotation = options.rotation.filter(|r| r.is_enabled());
+        let segment_filename = match rotation {
+            Some(_) => Self::segment_filename(&filename, 1),
+            None => filename.clone(),
+        };
+        
+        // EDF/BDF 的物理量程；自动量程在估计完成后才写入文件头，GDF/纯文本/HDF5 不限制量程
+        let channels = stream_info.channels_count as usize;
+        let default_range = match format {
+            RecordingFormat::Bdf => ChannelRange { min: BDF_PHYSICAL_MIN, max: BDF_PHYSICAL_MAX },
+            _ => ChannelRange::symmetric(EDF_PHYSICAL_LIMIT),
+        };
+        let (ranges, calibration, writer) = match options.physical_range.mode {
+            _ if !matches!(format, RecordingFormat::Edf | RecordingFormat::Bdf) => {
+                let writer = Self::create_writer(&segment_filename, &stream_info, format, layout, start_time, metadata, &[])?;
+                (Vec::new(), None, writer)
+            }
+            PhysicalRangeMode::Fixed => {
+                let ranges = options.physical_range.fixed_ranges(channels, default_range)?;
+                let writer = Self::create_writer(&segment_filename, &stream_info, format, layout, start_time, metadata, &ranges)?;
+                (ranges, None, writer)
+            }
+            PhysicalRangeMode::Auto => {
+                // 先创建空文件，路径错误在开始录制时即可发现
+                std::fs::File::create(&segment_filename)
+                    .map_err(|e| AppError::Recording(format!("Failed to create {}: {}", segment_filename, e)))?;
+                let required = (options.physical_range.calibration_seconds * stream_info.sample_rate).ceil() as usize;
+                let calibration = RangeCalibration::new(channels, required.max(1), options.physical_range.headroom);
+                (Vec::new(), Some(calibration), RecordingWriter::Pending(Vec::new()))
+            }
+        };
+        
+        // 初始化通道缓冲区
+        let channel_buffers = (0..stream_info.channels_count)
+            .map(|_| VecDeque::with_capacity(samples_per_record * 2))
+            .collect();
+        
+        let mut recorder = Self {
+            writer,
+            format,
+            filename: filename.clone(),
+            stream_info,
+            samples_written: 0,
+            annotations_written: 0,
+            markers_written: 0,
+            first_timestamp: None,
+            pending_annotations: Vec::new(),
+            last_timestamp: None,
+            resampler,
+            input_sample_rate,
+            channel_indices,
+            history_until: None,
+            paused_at: None,
+            paused_duration: Duration::ZERO,
+            resume_pending: false,
+            timestamp_offset: 0.0,
+            channel_buffers,
+            ranges,
+            calibration,
+            clipped_samples: vec![0; channels],
+            layout,
+            start_time,
+            rotation,
+            segments: vec![segment_filename],
+            segment_start_sample: 0,
+            segment_started_at: Instant::now(),
+            last_checkpoint: Instant::now(),
+            disk: options.disk,
+            record_aux: options.record_aux,
+            aux_writer: None,
+            aux_samples_written: 0,
+            clock_writer: None,
+            script_writers: Vec::new(),
+            metadata: metadata.clone(),
+            tags: options.tags,
+            annotation_log: Vec::new(),
+        };
+        recorder.write_metadata_annotations()?;
+        
+        Ok(recorder)
+    }
+    
+    /// 文件头容纳不下的会话信息（备注）以注释形式写在录制开头
+    fn write_metadata_annotations(&mut self) -> Result<(), AppError> {
+        let metadata = self.metadata.clone();
+        if let Some(notes) = metadata.notes.as_deref().map(str::trim).filter(|notes| !notes.is_empty()) {
+            self.write_annotation(0.0, None, &format!("Notes: {}", notes))?;
+        }
+        if !metadata.impedances.is_empty() {
+            let impedances: Vec<String> = metadata.impedances.iter()
+                .map(|electrode| format!("{}={:.1}", electrode.label, electrode.kohm))
+                .collect();
+            self.write_annotation(0.0, None, &format!("Impedance (kOhm): {}", impedances.join(", ")))?;
+        }
+        Ok(())
+    }
+    
+    /// 只录制选中的通道：返回其在流中的下标，以及信号列表随之缩减的流信息
+    fn select_channels(stream_info: StreamInfo, selected: Option<&[String]>) -> Result<(Option<Vec<usize>>, StreamInfo), AppError> {
+        let Some(selected) = selected else {
+            return Ok((None, stream_info));
+        };
+        
+        let labels = Self::channel_labels(&stream_info);
+        let indices = selected.iter()
+            .map(|label| labels.iter().position(|candidate| candidate == label)
+                .ok_or_else(|| AppError::Config(format!("Stream '{}' has no channel '{}'", stream_info.name, label))))
+            .collect::<Result<Vec<_>, _>>()?;
+        info!("🎚️  Recording {} of {} channels: {}", indices.len(), labels.len(), selected.join(", "));
+        
+        let stream_info = StreamInfo {
+            channels_count: indices.len() as u32,
+            channel_labels: selected.to_vec(),
+            ..stream_info
+        };
+        Ok((Some(indices), stream_info))
+    }
+    
+    /// 流描述中的通道标签，数量不符时使用 Ch1..ChN
+    pub fn channel_labels(stream_info: &StreamInfo) -> Vec<String> {
+        if stream_info.channel_labels.len() == stream_info.channels_count as usize {
+            stream_info.channel_labels.clone()
+        } else {
+            StreamInfo::default_channel_labels(stream_info.channels_count)
+        }
+    }
+    
+    fn create_writer(
+        filename: &str,
+        stream_info: &StreamInfo,
+        format: RecordingFormat,
+        layout: RecordLayout,
+        start_time: DateTime<Utc>,
+        metadata: &SessionMetadata,
+        ranges: &[ChannelRange],
+    ) -> Result<RecordingWriter, AppError> {
+        let writer = match format {
+            RecordingFormat::Edf => RecordingWriter::Edf(
+                Self::create_edf_writer(filename, stream_info, layout, metadata, ranges)?,
+                EdfRecordingField { filename: filename.to_string(), metadata: metadata.clone(), start_time },
+            ),
+            RecordingFormat::Bdf => {
+                let signals = ranges.iter().enumerate()
+                    .map(|(ch_idx, range)| BdfSignal {
+                        label: signal_label(ch_idx as u32),
+                        transducer: "AgAgCl electrodes".to_string(),
+                        physical_dimension: "uV".to_string(),
+                        physical_min: range.min,
+                        physical_max: range.max,
+                        prefilter: "HP:0.1Hz LP:70Hz".to_string(),
+                        samples_per_record: layout.samples_per_record,
+                    })
+                    .collect();
+                
+                RecordingWriter::Bdf(BdfWriter::create(
+                    filename, signals, layout.duration, start_time,
+                    &metadata.edf_patient_field(), &metadata.edf_recording_field(start_time)
+                )?)
+            }
+            RecordingFormat::Gdf => {
+                let signals = (0..stream_info.channels_count)
+                    .map(|ch_idx| GdfSignal {
+                        label: signal_label(ch_idx),
+                        transducer: "AgAgCl electrodes".to_string(),
+                        physical_dimension: "uV".to_string(),
+                        dimension_code: GDF_DIMENSION_MICROVOLT,
+                        prefilter: "HP:0.1Hz LP:70Hz".to_string(),
+                        highpass: Some(0.1),
+                        lowpass: Some(70.0),
+                        samples_per_record: layout.samples_per_record,
+                    })
+                    .collect();
+                let patient = GdfPatient {
+                    id: metadata.edf_patient_field(),
+                    sex: metadata.sex.clone(),
+                    birthdate: metadata.birthdate,
+                };
+                
+                RecordingWriter::Gdf(GdfWriter::create(
+                    filename, signals, layout.duration, stream_info.sample_rate, start_time,
+                    &patient, &metadata.edf_recording_field(start_time)
+                )?)
+            }
+            RecordingFormat::Csv | RecordingFormat::Tsv => {
+                let delimiter = if format == RecordingFormat::Csv { ',' } else { '\t' };
+                RecordingWriter::Text(DelimitedWriter::create(
+                    filename, &Self::channel_labels(stream_info), delimiter
+                )?)
+            }
+            #[cfg(feature = "hdf5")]
+            RecordingFormat::Hdf5 => RecordingWriter::Hdf5(
+                Hdf5Writer::create(filename, stream_info, &Self::channel_labels(stream_info))?
+            ),
+            #[cfg(not(feature = "hdf5"))]
+            RecordingFormat::Hdf5 => {
+                return Err(AppError::Recording(
+                    "HDF5 recording is not available in this build (enable the `hdf5` feature)".to_string()
+                ));
+            }
+        };
+        
+        Ok(writer)
+    }
+    
+    fn create_edf_writer(
+        filename: &str,
+        stream_info: &StreamInfo,
+        layout: RecordLayout,
+        metadata: &SessionMetadata,
+        ranges: &[ChannelRange],
+    ) -> Result<EdfWriter, AppError> {
+        let mut writer = EdfWriter::create(filename)
+            .map_err(|e| AppError::Recording(format!("Failed to create EDF file: {}", e)))?;
+        
+        writer.set_patient_info(
+            &edf_subfield(metadata.patient_code.as_deref()),
+            &edf_subfield(metadata.sex.as_deref()),
+            &edf_subfield(metadata.birthdate.map(|date| edf_date(&date)).as_deref()),
+            &edf_subfield(metadata.patient_name.as_deref()),
+        ).map_err(|e| AppError::Recording(format!("Failed to set patient info: {}", e)))?;
+        
+        writer.set_datarecord_duration(layout.duration)
+            .map_err(|e| AppError::Recording(format!("Failed to set data record duration: {}", e)))?;
+        
         // 为每个EEG通道添加信号参数
-        for ch_idx in 0..stream_info.channels_count {
+        for (ch_idx, range) in ranges.iter().enumerate().take(stream_info.channels_count as usize) {
             let signal_param = SignalParam {
-                label: format!("EEG Ch{:02}", ch_idx + 1),
+                label: signal_label(ch_idx as u32),
                 samples_in_file: 0,
-                physical_max: 100.0,     // μV 物理最大值
-                physical_min: -100.0,    // μV 物理最小值
+                physical_max: range.max,    // μV 物理最大值
+                physical_min: range.min,    // μV 物理最小值
                 digital_max: 32767,      // 16位ADC最大值
                 digital_min: -32768,     // 16位ADC最小值
-                samples_per_record: samples_per_record as i32,
+                samples_per_record: layout.samples_per_record as i32,
                 physical_dimension: "uV".to_string(),
                 prefilter: "HP:0.1Hz LP:70Hz".to_string(),
                 transducer: "AgAgCl electrodes".to_string(),
@@ -55,49 +493,161 @@ impl EdfRecorder {
                 .map_err(|e| AppError::Recording(format!("Failed to add signal {}: {}", ch_idx, e)))?;
         }
         
-        // 初始化通道缓冲区
-        let channel_buffers = (0..stream_info.channels_count)
-            .map(|_| VecDeque::with_capacity(samples_per_record * 2))
-            .collect();
+        Ok(writer)
+    }
+    
+    /// 在录制开头写入历史缓冲中的样本，并在其结束处写入注释标出按下录制的时刻
+    pub fn prepend_history(&mut self, samples: &[EegSample]) -> Result<(), AppError> {
+        let Some(last) = samples.last() else {
+            return Ok(());
+        };
         
-        Ok(Self {
-            writer,
-            filename: filename.clone(),
-            stream_info,
-            samples_written: 0,
-            channel_buffers,
-            samples_per_record,
-            start_time,
-        })
+        for sample in samples {
+            self.write_sample(sample)?;
+        }
+        self.history_until = Some(last.sample_id);
+        
+        let onset = self.elapsed_seconds();
+        self.write_annotation(onset, None, &format!("Recording started ({:.1}s pre-trigger)", onset))?;
+        
+        Ok(())
     }
     
     pub fn write_sample(&mut self, sample: &EegSample) -> Result<(), AppError> {
+        if self.paused_at.is_some() {
+            return Ok(());
+        }
+        
+        if let Some(until) = self.history_until {
+            if sample.sample_id <= until {
+                return Ok(());
+            }
+            self.history_until = None;
+        }
+        
+        if self.first_timestamp.is_none() {
+            self.first_timestamp = Some(sample.timestamp);
+            self.flush_pending_annotations()?;
+        }
+        
+        // 恢复后的首个样本：暂停期间经过的LSL时间不计入文件时间
+        if self.resume_pending {
+            if let Some(last) = self.last_timestamp {
+                let gap = sample.timestamp - last - 1.0 / self.input_sample_rate;
+                self.timestamp_offset += gap.max(0.0);
+            }
+            self.resume_pending = false;
+        }
+        self.last_timestamp = Some(sample.timestamp);
+        
+        let selected: Vec<f64>;
+        let channels = match &self.channel_indices {
+            Some(indices) => {
+                selected = indices.iter().map(|&index| sample.channels.get(index).copied().unwrap_or(0.0)).collect();
+                &selected
+            }
+            None => &sample.channels,
+        };
+        
+        let Some(resampler) = self.resampler.as_mut() else {
+            return self.write_values(sample.timestamp, channels);
+        };
+        
+        // 重采样输出 k 对应首个样本之后 k / 目标速率 秒
+        let first_output = resampler.output_count();
+        let outputs = resampler.process_sample(channels);
+        let first_timestamp = self.first_timestamp.unwrap_or(sample.timestamp);
+        for (i, values) in outputs.iter().enumerate() {
+            let timestamp = first_timestamp + (first_output + i as u64) as f64 / self.stream_info.sample_rate;
+            self.write_values(timestamp, values)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 以文件速率写入一个样本
+    fn write_values(&mut self, timestamp: f64, channels: &[f64]) -> Result<(), AppError> {
+        if self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            self.writer.checkpoint()?;
+            self.last_checkpoint = Instant::now();
+        }
+        
+        // 纯文本/HDF5格式直接逐行写入，无需按数据记录缓冲
+        if let Some(result) = self.writer.write_row(timestamp, channels) {
+            result?;
+            self.samples_written += 1;
+            
+            // 逐行格式每满一个记录长度检查一次分段
+            if (self.samples_written - self.segment_start_sample).is_multiple_of(self.layout.samples_per_record as u64) {
+                self.rotate_if_needed()?;
+            }
+            return Ok(());
+        }
+        
         // 将样本数据加入各通道缓冲区
-        for (ch_idx, &value) in sample.channels.iter().enumerate() {
+        for (ch_idx, &value) in channels.iter().enumerate() {
             if ch_idx < self.channel_buffers.len() {
                 self.channel_buffers[ch_idx].push_back(value);
             }
         }
+        match self.calibration.as_mut() {
+            Some(calibration) => calibration.observe(channels),
+            None => self.count_clipped(channels),
+        }
         
         self.samples_written += 1;
         
-        // 检查是否需要写入一个完整的数据记录
-        if self.channel_buffers[0].len() >= self.samples_per_record {
+        if self.calibration.as_ref().is_some_and(RangeCalibration::is_complete) {
+            self.finish_calibration()?;
+        }
+        
+        // 检查是否需要写入完整的数据记录（量程估计刚完成时缓冲中有多个）
+        while self.calibration.is_none() && self.channel_buffers[0].len() >= self.layout.samples_per_record {
             self.write_data_record()?;
         }
         
         Ok(())
     }
     
+    fn count_clipped(&mut self, channels: &[f64]) {
+        for ((value, range), clipped) in channels.iter().zip(&self.ranges).zip(&mut self.clipped_samples) {
+            if !range.contains(*value) {
+                *clipped += 1;
+            }
+        }
+    }
+    
+    /// 结束自动量程估计：按估计的量程创建文件，写出暂存的注释
+    fn finish_calibration(&mut self) -> Result<(), AppError> {
+        let Some(calibration) = self.calibration.take() else {
+            return Ok(());
+        };
+        
+        self.ranges = calibration.ranges();
+        let writer = Self::create_writer(
+            self.current_filename(), &self.stream_info, self.format, self.layout,
+            self.start_time, &self.metadata, &self.ranges
+        )?;
+        if let RecordingWriter::Pending(annotations) = std::mem::replace(&mut self.writer, writer) {
+            for (onset, duration, description) in annotations {
+                self.writer.add_annotation(onset, duration, &description)?;
+            }
+        }
+        
+        let limits: Vec<String> = self.ranges.iter().map(|range| format!("±{}", range.max)).collect();
+        info!("📏 Physical ranges estimated from {} samples: {} µV", calibration.observed, limits.join(", "));
+        Ok(())
+    }
+    
     fn write_data_record(&mut self) -> Result<(), AppError> {
         // 为每个通道收集samples_per_record个样本
         let mut record_data: Vec<Vec<f64>> = Vec::new();
         
         for channel_buffer in &mut self.channel_buffers {
-            let mut channel_samples = Vec::with_capacity(self.samples_per_record);
+            let mut channel_samples = Vec::with_capacity(self.layout.samples_per_record);
             
             // 从缓冲区取出样本
-            for _ in 0..self.samples_per_record {
+            for _ in 0..self.layout.samples_per_record {
                 if let Some(sample) = channel_buffer.pop_front() {
                     channel_samples.push(sample);
                 } else {
@@ -109,38 +659,399 @@ impl EdfRecorder {
             record_data.push(channel_samples);
         }
         
-        // 写入EDF+数据记录
-        self.writer.write_samples(&record_data)
-            .map_err(|e| AppError::Recording(format!("Failed to write data record: {}", e)))?;
+        // 写入数据记录
+        self.writer.write_samples(&record_data)?;
+        
+        debug!("{:?} data record written: {} samples per channel", self.format, self.layout.samples_per_record);
+        
+        self.rotate_if_needed()
+    }
+    
+    /// 在数据记录边界检查分段上限，缓冲中的样本进入下一个文件，不丢样本
+    fn rotate_if_needed(&mut self) -> Result<(), AppError> {
+        let Some(rotation) = self.rotation else {
+            return Ok(());
+        };
+        
+        let minutes = self.segment_started_at.elapsed().as_secs_f64() / 60.0;
+        let megabytes = std::fs::metadata(self.current_filename())
+            .map(|m| m.len() as f64 / (1024.0 * 1024.0))
+            .unwrap_or(0.0);
+        
+        let time_reached = rotation.max_minutes.is_some_and(|max| minutes >= max);
+        let size_reached = rotation.max_megabytes.is_some_and(|max| megabytes >= max);
+        if !time_reached && !size_reached {
+            return Ok(());
+        }
+        
+        let next_filename = Self::segment_filename(&self.filename, self.segments.len() + 1);
+        let writer = Self::create_writer(
+            &next_filename, &self.stream_info, self.format, self.layout, Utc::now(), &self.metadata, &self.ranges
+        )?;
+        
+        let previous = std::mem::replace(&mut self.writer, writer);
+        previous.finalize()?;
+        
+        info!("🔁 Recording rotated: {} ({:.1} min, {:.1} MB) -> {}",
+                 self.current_filename(), minutes, megabytes, next_filename);
+        
+        self.segments.push(next_filename);
+        // 缓冲中尚未写出的样本属于新分段
+        self.segment_start_sample = self.samples_written - self.channel_buffers[0].len() as u64;
+        self.segment_started_at = Instant::now();
+        
+        Ok(())
+    }
+    
+    /// 当前录制进度；队列占用由处理器填写
+    pub fn status(&self) -> RecordingStatus {
+        RecordingStatus {
+            is_recording: true,
+            is_paused: self.paused_at.is_some(),
+            filename: Some(self.current_filename().to_string()),
+            segments: self.segments.len() as u32,
+            elapsed_seconds: self.elapsed_seconds(),
+            samples_written: self.samples_written,
+            file_size_bytes: Self::file_size_bytes(&self.segments),
+            buffer_fill: 0.0,
+        }
+    }
+    
+    /// 已写入各分段的文件大小合计
+    fn file_size_bytes(segments: &[String]) -> u64 {
+        segments.iter()
+            .filter_map(|segment| std::fs::metadata(segment).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+    
+    /// 当前文件所在卷的可用空间（MB）
+    pub fn available_megabytes(&self) -> Result<f64, AppError> {
+        let path = std::path::Path::new(self.current_filename());
+        let directory = path.parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(std::path::Path::new("."));
+        
+        let bytes = fs2::available_space(directory)?;
+        Ok(bytes as f64 / (1024.0 * 1024.0))
+    }
+    
+    pub fn disk_config(&self) -> DiskSpaceConfig {
+        self.disk
+    }
+    
+    pub fn current_filename(&self) -> &str {
+        self.segments.last().map(String::as_str).unwrap_or(&self.filename)
+    }
+    
+    fn segment_filename(filename: &str, index: usize) -> String {
+        let path = std::path::Path::new(filename);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        let name = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}_{:03}.{}", stem, index, ext),
+            None => format!("{}_{:03}", stem, index),
+        };
+        path.with_file_name(name).to_string_lossy().into_owned()
+    }
+    
+    /// 未被占用的文件名：文件或其首个分段已存在时追加序号，session.edf -> session_1.edf
+    pub fn available_filename(filename: &str) -> String {
+        let taken = |candidate: &str| {
+            std::path::Path::new(candidate).exists()
+                || std::path::Path::new(&Self::segment_filename(candidate, 1)).exists()
+        };
+        if !taken(filename) {
+            return filename.to_string();
+        }
+        
+        let path = std::path::Path::new(filename);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        let extension = path.extension().and_then(|e| e.to_str());
+        (1u32..)
+            .map(|index| {
+                let name = match extension {
+                    Some(ext) => format!("{}_{}.{}", stem, index, ext),
+                    None => format!("{}_{}", stem, index),
+                };
+                path.with_file_name(name).to_string_lossy().into_owned()
+            })
+            .find(|candidate| !taken(candidate))
+            .unwrap_or_else(|| filename.to_string())
+    }
+    
+    /// 写入辅助流样本（LSL时间戳）；暂停期间丢弃，录制中途换用的不同通道布局的流被忽略
+    pub fn write_aux(&mut self, samples: &[AuxSample]) -> Result<(), AppError> {
+        if !self.record_aux || self.paused_at.is_some() || samples.is_empty() {
+            return Ok(());
+        }
+        
+        if self.aux_writer.is_none() {
+            let labels = samples[0].channel_labels.clone();
+            let path = Self::sidecar_filename(&self.filename, "aux");
+            let writer = DelimitedWriter::create(&path, &labels, ',')?;
+            info!("🧭 Recording auxiliary stream to {} ({} channels)", path, labels.len());
+            self.aux_writer = Some((writer, labels));
+        }
+        
+        let Some((writer, labels)) = self.aux_writer.as_mut() else {
+            return Ok(());
+        };
+        for sample in samples {
+            if sample.channel_labels != *labels {
+                continue;
+            }
+            writer.write_row(sample.timestamp, &sample.values)?;
+            self.aux_samples_written += 1;
+        }
+        
+        Ok(())
+    }
+    
+    /// 写入LSL时钟偏移测量到 `<文件名>_clock.csv`（local_time, offset, unix_time），暂停期间照常写入
+    pub fn write_clock_offsets(&mut self, samples: &[ClockOffsetSample]) -> Result<(), AppError> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        
+        let writer = match self.clock_writer.as_mut() {
+            Some(writer) => writer,
+            None => {
+                let path = Self::sidecar_filename(&self.filename, "clock");
+                let labels = ["offset".to_string(), "unix_time".to_string()];
+                self.clock_writer.insert(DelimitedWriter::create(&path, &labels, ',')?)
+            }
+        };
+        for sample in samples {
+            writer.write_row(sample.local_time, &[sample.offset, sample.unix_time])?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 写入脚本指标，列为 <指标> 或 <指标>[通道]；与首行列不一致的行跳过
+    pub fn write_script_metrics(&mut self, update: &ScriptMetricsUpdate) -> Result<(), AppError> {
+        if self.paused_at.is_some() || update.metrics.is_empty() {
+            return Ok(());
+        }
+        
+        let columns: Vec<String> = update.metrics.iter()
+            .map(|metric| match metric.channel_index {
+                Some(channel) => format!("{}[{}]", metric.name, channel),
+                None => metric.name.clone(),
+            })
+            .collect();
+        let index = match self.script_writers.iter().position(|(script, _, _)| *script == update.script) {
+            Some(index) => index,
+            None => {
+                let path = Self::sidecar_filename(&self.filename, &format!("script_{}", update.script));
+                let writer = DelimitedWriter::create(&path, &columns, ',')?;
+                info!("📜 Recording script '{}' metrics to {}", update.script, path);
+                self.script_writers.push((update.script.clone(), writer, columns.clone()));
+                self.script_writers.len() - 1
+            }
+        };
+        
+        let (_, writer, expected) = &mut self.script_writers[index];
+        if *expected != columns {
+            return Ok(());
+        }
+        let values: Vec<f64> = update.metrics.iter().map(|metric| metric.value).collect();
+        writer.write_row(update.timestamp, &values)
+    }
+    
+    pub(crate) fn sidecar_filename(filename: &str, suffix: &str) -> String {
+        let path = std::path::Path::new(filename);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        path.with_file_name(format!("{}_{}.csv", stem, suffix)).to_string_lossy().into_owned()
+    }
+    
+    /// 写入EDF+注释
+    /// onset_seconds 为相对录制起点的秒数
+    pub fn write_annotation(
+        &mut self,
+        onset_seconds: f64,
+        duration_seconds: Option<f64>,
+        description: &str,
+    ) -> Result<(), AppError> {
+        // 分段录制时注释起点相对当前分段
+        let segment_offset = self.segment_start_sample as f64 / self.stream_info.sample_rate;
+        self.writer.add_annotation((onset_seconds - segment_offset).max(0.0), duration_seconds, description)?;
+        
+        self.annotations_written += 1;
+        self.annotation_log.push(AnnotationRecord {
+            onset_seconds,
+            duration_seconds,
+            description: description.to_string(),
+        });
+        
+        info!("EDF+ annotation at {:.3}s: {}", onset_seconds, description);
         
-        println!("EDF+ data record written: {} samples per channel", self.samples_per_record);
+        Ok(())
+    }
+    
+    /// 写入事件标记（标记流或手动添加），计入录制索引的标记数
+    pub fn write_marker(&mut self, onset_seconds: f64, duration_seconds: Option<f64>, label: &str) -> Result<(), AppError> {
+        self.write_annotation(onset_seconds, duration_seconds, label)?;
+        self.markers_written += 1;
+        Ok(())
+    }
+    
+    /// 按LSL时间戳写入事件标记，返回录制时间（秒）；首个样本尚未写入时暂存，
+    /// 写入首个样本后按其时间戳对齐（返回 None）
+    pub fn write_marker_at(&mut self, lsl_timestamp: f64, duration_seconds: Option<f64>, label: &str) -> Result<Option<f64>, AppError> {
+        self.annotate_at(PendingAnnotation {
+            lsl_timestamp,
+            duration_seconds,
+            description: label.to_string(),
+            is_marker: true,
+        })
+    }
+    
+    /// 按LSL时间戳写入注释，与 write_marker_at 一样在首个样本写入前暂存
+    pub fn write_annotation_at(&mut self, lsl_timestamp: f64, duration_seconds: Option<f64>, description: &str) -> Result<Option<f64>, AppError> {
+        self.annotate_at(PendingAnnotation {
+            lsl_timestamp,
+            duration_seconds,
+            description: description.to_string(),
+            is_marker: false,
+        })
+    }
+    
+    fn annotate_at(&mut self, annotation: PendingAnnotation) -> Result<Option<f64>, AppError> {
+        if self.first_timestamp.is_none() {
+            self.pending_annotations.push(annotation);
+            return Ok(None);
+        }
         
+        let onset = self.onset_for_timestamp(annotation.lsl_timestamp);
+        self.write_pending(onset, annotation)?;
+        Ok(Some(onset))
+    }
+    
+    fn write_pending(&mut self, onset_seconds: f64, annotation: PendingAnnotation) -> Result<(), AppError> {
+        if annotation.is_marker {
+            self.write_marker(onset_seconds, annotation.duration_seconds, &annotation.description)
+        } else {
+            self.write_annotation(onset_seconds, annotation.duration_seconds, &annotation.description)
+        }
+    }
+    
+    fn flush_pending_annotations(&mut self) -> Result<(), AppError> {
+        for annotation in std::mem::take(&mut self.pending_annotations) {
+            let onset = self.onset_for_timestamp(annotation.lsl_timestamp);
+            self.write_pending(onset, annotation)?;
+        }
         Ok(())
     }
     
-    // TODO: 注释写入待实现
+    /// LSL时间戳对应的录制时间（秒），尚无样本时使用当前录制时长
+    pub fn onset_for_timestamp(&self, lsl_timestamp: f64) -> f64 {
+        match self.first_timestamp {
+            Some(first) => (lsl_timestamp - first - self.timestamp_offset).max(0.0),
+            None => self.elapsed_seconds(),
+        }
+    }
+    
+    /// 暂停录制：文件保持打开，之后的样本被丢弃
+    pub fn pause(&mut self) -> Result<(), AppError> {
+        if self.paused_at.is_some() {
+            return Err(AppError::Recording("Recording is already paused".to_string()));
+        }
+        
+        self.paused_at = Some(Instant::now());
+        info!("⏸️  Recording paused at {:.3}s", self.elapsed_seconds());
+        
+        Ok(())
+    }
+    
+    /// 恢复录制，并在间隙处写入注释；返回本次暂停时长（秒）
+    pub fn resume(&mut self) -> Result<f64, AppError> {
+        let paused_at = self.paused_at.take()
+            .ok_or_else(|| AppError::Recording("Recording is not paused".to_string()))?;
+        
+        let paused = paused_at.elapsed();
+        self.paused_duration += paused;
+        self.resume_pending = true;
+        
+        let onset = self.elapsed_seconds();
+        self.write_annotation(onset, None, &format!("Recording paused ({:.1}s)", paused.as_secs_f64()))?;
+        
+        info!("▶️  Recording resumed at {:.3}s after {:.1}s pause", onset, paused.as_secs_f64());
+        
+        Ok(paused.as_secs_f64())
+    }
+    
+    /// 累计暂停时长，含正在进行的暂停
+    fn total_paused_seconds(&self) -> f64 {
+        let current = self.paused_at.map(|at| at.elapsed()).unwrap_or_default();
+        (self.paused_duration + current).as_secs_f64()
+    }
+    
+    /// 当前录制时长（秒），按已写入样本数计算
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.samples_written as f64 / self.stream_info.sample_rate
+    }
+    
+    
+    /// 完成录制，同时保留流信息、会话信息、注释和旁路文件供导出等后处理使用
+    pub fn finish(mut self) -> Result<FinishedRecording, AppError> {
+        let stream_info = self.stream_info.clone();
+        let metadata = self.metadata.clone();
+        let tags = self.tags.clone();
+        let annotations = std::mem::take(&mut self.annotation_log);
+        let sidecars = [("aux", self.aux_writer.is_some()), ("clock", self.clock_writer.is_some())]
+            .into_iter()
+            .filter(|(_, created)| *created)
+            .map(|(suffix, _)| Self::sidecar_filename(&self.filename, suffix))
+            .chain(self.script_writers.iter()
+                .map(|(script, _, _)| Self::sidecar_filename(&self.filename, &format!("script_{}", script))))
+            .collect();
+        let stats = self.close()?;
+        Ok(FinishedRecording { stats, stream_info, metadata, tags, annotations, sidecars })
+    }
     
     pub fn close(mut self) -> Result<RecordingStats, AppError> {
+        // 录制短于量程估计时长时按已有数据估计
+        self.finish_calibration()?;
+        
+        // 没有写入任何样本时暂存的注释写在文件开头
+        for annotation in std::mem::take(&mut self.pending_annotations) {
+            self.write_pending(0.0, annotation)?;
+        }
+        
         // ✅ 修复：在finalize之前先收集统计信息
-        let stats = RecordingStats {
+        let mut stats = RecordingStats {
             filename: self.filename.clone(),
+            format: self.format,
+            segments: self.segments.clone(),
             duration_seconds: self.samples_written as f64 / self.stream_info.sample_rate,
             samples_written: self.samples_written,
+            annotations_written: self.annotations_written,
+            markers_written: self.markers_written,
+            paused_seconds: self.total_paused_seconds(),
             channels_count: self.stream_info.channels_count,
             sample_rate: self.stream_info.sample_rate,
             start_time: self.start_time,
-            file_size_bytes: 0, // TODO: 获取实际文件大小
+            file_size_bytes: 0,     // finalize 之后统计
+            checksums: Vec::new(),
+            physical_ranges: self.ranges.clone(),
+            clipped_samples: self.clipped_samples.clone(),
         };
         
+        // 量程估计刚完成时缓冲中可能还有完整的数据记录
+        while self.channel_buffers.first().is_some_and(|buffer| buffer.len() >= self.layout.samples_per_record) {
+            self.write_data_record()?;
+        }
+        
         // 写入剩余的缓冲数据
         if !self.channel_buffers.is_empty() && self.channel_buffers[0].len() > 0 {
-            println!("Writing remaining {} samples before closing", self.channel_buffers[0].len());
+            info!("Writing remaining {} samples before closing", self.channel_buffers[0].len());
             
             // 如果剩余样本不足一个完整记录，用0填充
             let remaining_samples = self.channel_buffers[0].len();
-            if remaining_samples < self.samples_per_record {
+            if remaining_samples < self.layout.samples_per_record {
                 for channel_buffer in &mut self.channel_buffers {
-                    let padding_needed = self.samples_per_record - remaining_samples;
+                    let padding_needed = self.layout.samples_per_record - remaining_samples;
                     for _ in 0..padding_needed {
                         channel_buffer.push_back(0.0);
                     }
@@ -151,31 +1062,213 @@ impl EdfRecorder {
             self.write_data_record()?;
         }
         
-        // 完成EDF+文件写入 - 这会消费self.writer
-        self.writer.finalize()
-            .map_err(|e| AppError::Recording(format!("Failed to finalize EDF file: {}", e)))?;
+        // 完成文件写入 - 这会消费self.writer
+        self.writer.finalize()?;
         
-        println!("Recording completed successfully:");
-        println!("  File: {}", stats.filename);
-        println!("  Duration: {:.2} seconds", stats.duration_seconds);
-        println!("  Samples: {} per channel", stats.samples_written);
-        println!("  Channels: {}", stats.channels_count);
+        if let Some((writer, _)) = self.aux_writer.take() {
+            // 辅助流文件失败不影响主录制结果
+            match writer.finalize() {
+                Ok(()) => info!("  Auxiliary samples: {}", self.aux_samples_written),
+                Err(e) => warn!("⚠️  Failed to finalize auxiliary stream file: {}", e),
+            }
+        }
+        if let Some(writer) = self.clock_writer.take() {
+            if let Err(e) = writer.finalize() {
+                warn!("⚠️  Failed to finalize clock offset file: {}", e);
+            }
+        }
+        for (script, writer, _) in std::mem::take(&mut self.script_writers) {
+            if let Err(e) = writer.finalize() {
+                warn!("⚠️  Failed to finalize script '{}' metrics file: {}", script, e);
+            }
+        }
+        
+        stats.file_size_bytes = Self::file_size_bytes(&stats.segments);
+        stats.checksums = stats.segments.iter()
+            .map(|segment| match crate::verify::sha256_file(std::path::Path::new(segment)) {
+                Ok(checksum) => Some(checksum),
+                Err(e) => {
+                    warn!("⚠️  Failed to checksum {}: {}", segment, e);
+                    None
+                }
+            })
+            .collect();
+        
+        info!("Recording completed successfully:");
+        info!("  File: {} ({} segments)", stats.filename, stats.segments.len());
+        info!("  Duration: {:.2} seconds", stats.duration_seconds);
+        info!("  Samples: {} per channel", stats.samples_written);
+        info!("  Channels: {}", stats.channels_count);
+        info!("  Annotations: {}", stats.annotations_written);
+        info!("  Paused: {:.1} seconds", stats.paused_seconds);
+        let clipped: u64 = stats.clipped_samples.iter().sum();
+        if clipped > 0 {
+            warn!("⚠️  {} samples exceeded the physical range and were clipped", clipped);
+        }
         
         Ok(stats)
     }
 }
 
+/// EDF+/BDF+ 文件中第 ch_idx 个EEG信号的标签
+pub fn signal_label(ch_idx: u32) -> String {
+    format!("EEG Ch{:02}", ch_idx + 1)
+}
+
+/// EDF+ 记录字段（研究编号、操作者、设备）
+///
+/// edfplus 写入器只能设置受试者字段，记录字段在 finalize 之后直接写入文件头，
+/// 起始日期沿用写入器生成的值
+struct EdfRecordingField {
+    filename: String,
+    metadata: SessionMetadata,
+    start_time: DateTime<Utc>,
+}
+
+impl EdfRecordingField {
+    fn write(&self) -> Result<(), AppError> {
+        let mut file = OpenOptions::new().read(true).write(true).open(&self.filename)?;
+        let mut existing = [0u8; 80];
+        file.seek(SeekFrom::Start(EDF_RECORDING_OFFSET))?;
+        file.read_exact(&mut existing)?;
+        
+        let existing = String::from_utf8_lossy(&existing);
+        let start_date = existing.strip_prefix("Startdate ")
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(str::to_string)
+            .unwrap_or_else(|| edf_date(&self.start_time.date_naive()));
+        let mut field = Vec::with_capacity(80);
+        push_field(&mut field, &self.metadata.edf_recording_field_for_date(&start_date), 80);
+        
+        file.seek(SeekFrom::Start(EDF_RECORDING_OFFSET))?;
+        file.write_all(&field)?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// EDF/BDF 数据记录的长度和每通道样本数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RecordLayout {
+    pub duration: f64,                  // 秒
+    pub samples_per_record: usize,
+}
+
+impl RecordLayout {
+    /// 指定记录长度时，采样率 × 长度必须为整数个样本。
+    ///
+    /// 未指定时：整数采样率用1秒记录，超过 `max_samples` 时取采样率不超过上限的最大约数；
+    /// 分数采样率（如 0.5Hz、512.5Hz）取能得到整数样本的最短整秒数；其余不规则的采样率
+    /// 取最接近1秒的样本数，记录长度按其换算并保留6位小数（文件头字段只有8个字符）。
+    pub fn for_rate(sample_rate: f64, requested: Option<f64>, max_samples: usize) -> Result<Self, AppError> {
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(AppError::Recording(format!(
+                "EDF/BDF recording needs a regular sample rate, got {} Hz", sample_rate
+            )));
+        }
+        
+        if let Some(duration) = requested {
+            return whole_samples(sample_rate * duration)
+                .map(|samples_per_record| Self { duration, samples_per_record })
+                .ok_or_else(|| AppError::Config(format!(
+                    "A {}s data record at {} Hz is not a whole number of samples", duration, sample_rate
+                )));
+        }
+        
+        let max_samples = max_samples.max(1);
+        if let Some(rate) = whole_samples(sample_rate) {
+            let samples_per_record = (1..=rate.min(max_samples)).rev()
+                .find(|samples| rate % samples == 0)
+                .unwrap_or(1);
+            return Ok(Self { duration: samples_per_record as f64 / rate as f64, samples_per_record });
+        }
+        
+        let fractional = (2..=MAX_AUTO_RECORD_SECONDS).find_map(|seconds| {
+            whole_samples(sample_rate * seconds as f64)
+                .filter(|&samples| samples <= max_samples)
+                .map(|samples_per_record| Self { duration: seconds as f64, samples_per_record })
+        });
+        if let Some(layout) = fractional {
+            return Ok(layout);
+        }
+        
+        let samples_per_record = (sample_rate.round() as usize).clamp(1, max_samples);
+        let duration = (samples_per_record as f64 / sample_rate * 1e6).round() / 1e6;
+        warn!("⚠️  {} Hz has no exact data record length, using {}s records", sample_rate, duration);
+        Ok(Self { duration, samples_per_record })
+    }
+}
+
+/// 接近整数（误差小于1e-6）的正样本数
+fn whole_samples(samples: f64) -> Option<usize> {
+    let rounded = samples.round();
+    (rounded >= 1.0 && (samples - rounded).abs() < 1e-6).then_some(rounded as usize)
+}
+
+/// 自动量程估计 - 记录开头一段数据各通道的绝对峰值
+struct RangeCalibration {
+    peaks: Vec<f64>,
+    observed: usize,
+    required: usize,
+    headroom: f64,
+}
+
+impl RangeCalibration {
+    fn new(channels: usize, required: usize, headroom: f64) -> Self {
+        Self { peaks: vec![0.0; channels], observed: 0, required, headroom }
+    }
+    
+    fn observe(&mut self, channels: &[f64]) {
+        for (peak, value) in self.peaks.iter_mut().zip(channels) {
+            if value.is_finite() {
+                *peak = peak.max(value.abs());
+            }
+        }
+        self.observed += 1;
+    }
+    
+    fn is_complete(&self) -> bool {
+        self.observed >= self.required
+    }
+    
+    /// 以零为中心：峰值乘以余量后向上取整到 1µV，且不低于 AUTO_RANGE_FLOOR
+    fn ranges(&self) -> Vec<ChannelRange> {
+        self.peaks.iter()
+            .map(|peak| ChannelRange::symmetric((peak * self.headroom).ceil().max(AUTO_RANGE_FLOOR)))
+            .collect()
+    }
+}
+
+/// 已完成的录制 - 统计信息及录制时的流信息（重采样后）和会话信息
+#[derive(Debug, Clone)]
+pub struct FinishedRecording {
+    pub stats: RecordingStats,
+    pub stream_info: StreamInfo,
+    pub metadata: SessionMetadata,
+    pub tags: Vec<String>,
+    pub annotations: Vec<AnnotationRecord>,
+    pub sidecars: Vec<String>,          // 辅助流、时钟偏移等旁路CSV文件
+}
+
 // 录制统计信息 - 保留 DateTime<Utc> 类型，提供更好的类型安全性
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct RecordingStats {
     pub filename: String,
+    pub format: RecordingFormat,
+    pub segments: Vec<String>,        // 实际写入的文件，未分段时只有一个
     pub duration_seconds: f64,
     pub samples_written: u64,
+    pub annotations_written: u64,
+    pub markers_written: u64,
+    pub paused_seconds: f64,
     pub channels_count: u32,
     pub sample_rate: f64,
     #[serde(serialize_with = "serialize_datetime")]
     pub start_time: DateTime<Utc>,
     pub file_size_bytes: u64,
+    pub checksums: Vec<Option<String>>, // 各分段文件的 SHA-256，计算失败时为 None
+    pub physical_ranges: Vec<ChannelRange>, // EDF/BDF 各通道的物理量程，纯文本/HDF5 为空
+    pub clipped_samples: Vec<u64>,      // 各通道超出物理量程被截断的样本数
 }
 
 /// 自定义序列化函数，将 DateTime<Utc> 转换为 ISO 8601 字符串
@@ -191,22 +1284,319 @@ where
 mod tests {
     use super::*;
     
-    #[test]
-    fn test_edf_recorder_creation() {
-        let stream_info = StreamInfo {
+    fn test_stream_info(channels: u32, sample_rate: f64) -> StreamInfo {
+        StreamInfo {
             name: "Test EEG".to_string(),
             stream_type: "EEG".to_string(),
-            channels_count: 8,
-            sample_rate: 250.0,
+            channels_count: channels,
+            sample_rate,
             is_connected: true,
             source_id: "test_device".to_string(),
-        };
+            channel_labels: StreamInfo::default_channel_labels(channels),
+            effective_sample_rate: None,
+        }
+    }
+    
+    #[test]
+    fn test_edf_recorder_creation() {
+        let stream_info = test_stream_info(8, 250.0);
         
         let recorder = EdfRecorder::new(
             "test_recording.edf".to_string(),
             stream_info,
+            RecordingOptions::default(),
+            &SessionMetadata::default(),
         );
         
         assert!(recorder.is_ok());
     }
+    
+    #[test]
+    fn test_marker_before_first_sample_uses_lsl_time_base() {
+        let stream_info = test_stream_info(2, 100.0);
+        let path = std::env::temp_dir().join(format!("early_marker_{}.csv", std::process::id()));
+        let options = RecordingOptions { format: RecordingFormat::Csv, ..RecordingOptions::default() };
+        let mut recorder = EdfRecorder::new(path.to_str().unwrap().to_string(), stream_info, options, &SessionMetadata::default()).unwrap();
+        
+        // 分发器先于录制线程融合标记：此时还没有样本，标记暂存
+        assert_eq!(recorder.write_marker_at(1000.5, None, "stim").unwrap(), None);
+        for sample_id in 0..200u64 {
+            let sample = EegSample { timestamp: 1000.0 + sample_id as f64 / 100.0, channels: vec![1.0, -1.0], sample_id };
+            recorder.write_sample(&sample).unwrap();
+        }
+        assert_eq!(recorder.write_marker_at(1001.25, None, "late").unwrap(), Some(1.25));
+        
+        let finished = recorder.finish().unwrap();
+        for segment in &finished.stats.segments {
+            let _ = std::fs::remove_file(segment);
+            let _ = std::fs::remove_file(crate::delimited::annotations_path(segment));
+        }
+        let onsets: Vec<(&str, f64)> = finished.annotations.iter()
+            .map(|annotation| (annotation.description.as_str(), annotation.onset_seconds))
+            .collect();
+        assert_eq!(onsets, vec![("stim", 0.5), ("late", 1.25)]);
+        assert_eq!(finished.stats.markers_written, 2);
+    }
+    
+    #[test]
+    fn test_session_metadata_header_fields() {
+        let metadata = SessionMetadata {
+            patient_code: Some("S01".to_string()),
+            patient_name: Some("Jane Doe".to_string()),
+            sex: Some("F".to_string()),
+            birthdate: chrono::NaiveDate::from_ymd_opt(1990, 5, 2),
+            operator: Some("A Tech".to_string()),
+            ..SessionMetadata::default()
+        };
+        metadata.validate().unwrap();
+        assert_eq!(metadata.edf_patient_field(), "S01 F 02-MAY-1990 Jane_Doe");
+        let start = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 1, 15, 9, 0, 0).unwrap();
+        assert_eq!(metadata.edf_recording_field(start), "Startdate 15-JAN-2024 X A_Tech Open-CortexArray");
+        assert_eq!(SessionMetadata::default().edf_patient_field(), "X X X X");
+        
+        let invalid = SessionMetadata { sex: Some("male".to_string()), ..SessionMetadata::default() };
+        assert!(invalid.validate().is_err());
+        let non_ascii = SessionMetadata { patient_name: Some("Zoë".to_string()), ..SessionMetadata::default() };
+        assert!(non_ascii.validate().is_err());
+    }
+    
+    #[test]
+    fn test_segment_filenames_are_sequential() {
+        assert_eq!(EdfRecorder::segment_filename("data/session.edf", 1), "data/session_001.edf");
+        assert_eq!(EdfRecorder::segment_filename("data/session.edf", 12), "data/session_012.edf");
+        assert_eq!(EdfRecorder::segment_filename("session", 2), "session_002");
+    }
+    
+    #[test]
+    fn test_aux_and_clock_sidecars() {
+        let path = std::env::temp_dir().join(format!("aux_recording_test_{}.csv", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let stream_info = test_stream_info(2, 250.0);
+        let options = RecordingOptions { format: RecordingFormat::Csv, record_aux: true, ..RecordingOptions::default() };
+        let mut recorder = EdfRecorder::new(filename.clone(), stream_info, options, &SessionMetadata::default()).unwrap();
+        
+        let imu: Arc<[String]> = vec!["AccX".to_string(), "AccY".to_string()].into();
+        let other: Arc<[String]> = vec!["GyroX".to_string()].into();
+        recorder.write_aux(&[
+            AuxSample { timestamp: 1.0, values: vec![0.5, -1.0], channel_labels: imu.clone() },
+            AuxSample { timestamp: 1.01, values: vec![3.0], channel_labels: other },
+            AuxSample { timestamp: 1.02, values: vec![0.25, 2.0], channel_labels: imu },
+        ]).unwrap();
+        recorder.write_clock_offsets(&[ClockOffsetSample { local_time: 2.0, offset: -0.5, unix_time: 1700000000.25 }]).unwrap();
+        recorder.close().unwrap();
+        
+        let aux_path = EdfRecorder::sidecar_filename(&filename, "aux");
+        let clock_path = EdfRecorder::sidecar_filename(&filename, "clock");
+        let text = std::fs::read_to_string(&aux_path).unwrap();
+        let clock = std::fs::read_to_string(&clock_path).unwrap();
+        for file in [&filename, &aux_path, &clock_path] {
+            let _ = std::fs::remove_file(file);
+        }
+        
+        // 不同通道布局的样本被忽略
+        assert_eq!(text, "timestamp,AccX,AccY\n1.000000,0.5,-1\n1.020000,0.25,2\n");
+        assert_eq!(clock, "timestamp,offset,unix_time\n2.000000,-0.5,1700000000.25\n");
+    }
+    
+    #[test]
+    fn test_recording_status() {
+        let path = std::env::temp_dir().join(format!("status_recording_test_{}.tsv", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let stream_info = test_stream_info(2, 250.0);
+        let options = RecordingOptions { format: RecordingFormat::Tsv, ..RecordingOptions::default() };
+        let mut recorder = EdfRecorder::new(filename.clone(), stream_info, options, &SessionMetadata::default()).unwrap();
+        
+        for sample_id in 0..250 {
+            let sample = EegSample { timestamp: sample_id as f64 / 250.0, channels: vec![1.0, -1.0], sample_id };
+            recorder.write_sample(&sample).unwrap();
+        }
+        recorder.pause().unwrap();
+        
+        let status = recorder.status();
+        assert!(status.is_recording && status.is_paused);
+        assert_eq!(status.filename.as_deref(), Some(filename.as_str()));
+        assert_eq!(status.samples_written, 250);
+        assert!((status.elapsed_seconds - 1.0).abs() < 1e-9);
+        
+        let stats = recorder.close().unwrap();
+        let size = std::fs::metadata(&filename).unwrap().len();
+        let _ = std::fs::remove_file(&filename);
+        assert_eq!(stats.file_size_bytes, size);
+        assert!(size > 0);
+    }
+    
+    #[test]
+    fn test_auto_physical_range_and_clipping() {
+        let path = std::env::temp_dir().join(format!("auto_range_test_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let stream_info = test_stream_info(2, 100.0);
+        
+        // 通道数与量程数不一致时拒绝
+        let mismatched = PhysicalRangeConfig {
+            ranges: vec![ChannelRange::symmetric(100.0); 3],
+            ..PhysicalRangeConfig::default()
+        };
+        let options = RecordingOptions { format: RecordingFormat::Bdf, physical_range: mismatched, ..RecordingOptions::default() };
+        assert!(EdfRecorder::new(filename.clone(), stream_info.clone(), options, &SessionMetadata::default()).is_err());
+        
+        let physical_range = PhysicalRangeConfig {
+            mode: PhysicalRangeMode::Auto,
+            calibration_seconds: 1.0,
+            headroom: 2.0,
+            ..PhysicalRangeConfig::default()
+        };
+        let options = RecordingOptions { format: RecordingFormat::Bdf, physical_range, ..RecordingOptions::default() };
+        let mut recorder = EdfRecorder::new(filename.clone(), stream_info, options, &SessionMetadata::default()).unwrap();
+        
+        // 估计期间峰值 30µV / 10µV，之后通道0有5个样本超出 ±60µV
+        for sample_id in 0..300u64 {
+            let value = match sample_id {
+                0..=99 if sample_id % 2 == 0 => 30.0,
+                0..=99 => -30.0,
+                150..=154 => 80.0,
+                _ => 5.0,
+            };
+            let sample = EegSample { timestamp: sample_id as f64 / 100.0, channels: vec![value, value / 3.0], sample_id };
+            recorder.write_sample(&sample).unwrap();
+            if sample_id == 10 {
+                recorder.write_annotation(0.1, None, "calibrating").unwrap();
+            }
+        }
+        
+        let stats = recorder.close().unwrap();
+        let header = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(stats.physical_ranges, vec![ChannelRange::symmetric(60.0), ChannelRange::symmetric(50.0)]);
+        assert_eq!(stats.clipped_samples, vec![5, 0]);
+        
+        let signals: usize = std::str::from_utf8(&header[252..256]).unwrap().trim().parse().unwrap();
+        let physical_max = 256 + signals * (16 + 80 + 8 + 8);
+        let field = |signal: usize| {
+            let start = physical_max + signal * 8;
+            std::str::from_utf8(&header[start..start + 8]).unwrap().trim().to_string()
+        };
+        assert_eq!(field(0), "60");
+        assert_eq!(field(1), "50");
+    }
+    
+    #[test]
+    fn test_record_layout_for_sample_rates() {
+        let layout = |rate: f64, requested: Option<f64>| RecordLayout::for_rate(rate, requested, 10_000).unwrap();
+        assert_eq!(layout(250.0, None), RecordLayout { duration: 1.0, samples_per_record: 250 });
+        assert_eq!(layout(512.0, None), RecordLayout { duration: 1.0, samples_per_record: 512 });
+        assert_eq!(layout(512.0, Some(0.25)), RecordLayout { duration: 0.25, samples_per_record: 128 });
+        assert!(RecordLayout::for_rate(250.0, Some(0.1234), 10_000).is_err());
+        
+        // 分数采样率取能得到整数样本的最短整秒数
+        assert_eq!(layout(512.5, None), RecordLayout { duration: 2.0, samples_per_record: 1025 });
+        assert_eq!(layout(0.5, None), RecordLayout { duration: 2.0, samples_per_record: 1 });
+        
+        // 不规则采样率：最接近1秒的整数样本，记录长度与样本数一致
+        let irregular = layout(250.123, None);
+        assert_eq!(irregular.samples_per_record, 250);
+        assert!((irregular.duration * 250.123 - 250.0).abs() < 1e-3);
+        
+        // 高采样率时缩短记录长度，使每个记录不超过上限
+        assert_eq!(RecordLayout::for_rate(16000.0, None, 480).unwrap(), RecordLayout { duration: 0.025, samples_per_record: 400 });
+        assert!(RecordLayout::for_rate(0.0, None, 10_000).is_err());
+    }
+    
+    #[test]
+    fn test_session_metadata_read_back_from_headers() {
+        let metadata = SessionMetadata {
+            patient_code: Some("S01".to_string()),
+            patient_name: Some("Doe".to_string()),
+            sex: Some("F".to_string()),
+            birthdate: chrono::NaiveDate::from_ymd_opt(1990, 5, 2),
+            admin_code: Some("STUDY7".to_string()),
+            operator: Some("Tech".to_string()),
+            ..SessionMetadata::default()
+        };
+        let stream_info = test_stream_info(2, 100.0);
+        let record = |format: RecordingFormat, extension: &str| {
+            let path = std::env::temp_dir().join(format!("metadata_test_{}.{}", std::process::id(), extension));
+            let options = RecordingOptions { format, ..RecordingOptions::default() };
+            let mut recorder = EdfRecorder::new(path.to_str().unwrap().to_string(), stream_info.clone(), options, &metadata).unwrap();
+            for sample_id in 0..200u64 {
+                let sample = EegSample { timestamp: sample_id as f64 / 100.0, channels: vec![1.0, -1.0], sample_id };
+                recorder.write_sample(&sample).unwrap();
+            }
+            recorder.close().unwrap();
+            path
+        };
+        
+        let path = record(RecordingFormat::Bdf, "bdf");
+        let header = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let field = |offset: usize| String::from_utf8_lossy(&header[offset..offset + 80]).trim().to_string();
+        assert_eq!(field(8), "S01 F 02-MAY-1990 Doe");
+        assert!(field(88).starts_with("Startdate "));
+        assert!(field(88).ends_with(" STUDY7 Tech Open-CortexArray"));
+        
+        let path = record(RecordingFormat::Edf, "edf");
+        let header = edfplus::EdfReader::open(&path).unwrap().header().clone();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(header.patient_code, "S01");
+        assert_eq!(header.sex, "F");
+        assert_eq!(header.birthdate, "02-MAY-1990");
+        assert_eq!(header.patient_name, "Doe");
+        assert_eq!(header.admincode, "STUDY7");
+        assert_eq!(header.technician, "Tech");
+        assert_eq!(header.equipment, "Open-CortexArray");
+    }
+    
+    #[test]
+    fn test_record_channel_subset() {
+        let path = std::env::temp_dir().join(format!("channel_subset_test_{}.csv", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let stream_info = StreamInfo {
+            channel_labels: vec!["Fz".to_string(), "Cz".to_string(), "EOG".to_string()],
+            ..test_stream_info(3, 100.0)
+        };
+        
+        let unknown = RecordingOptions { format: RecordingFormat::Csv, channels: Some(vec!["Pz".to_string()]), ..RecordingOptions::default() };
+        assert!(EdfRecorder::new(filename.clone(), stream_info.clone(), unknown, &SessionMetadata::default()).is_err());
+        
+        let options = RecordingOptions {
+            format: RecordingFormat::Csv,
+            channels: Some(vec!["Cz".to_string(), "Fz".to_string()]),
+            ..RecordingOptions::default()
+        };
+        let mut recorder = EdfRecorder::new(filename.clone(), stream_info, options, &SessionMetadata::default()).unwrap();
+        recorder.write_sample(&EegSample { timestamp: 0.0, channels: vec![1.0, 2.0, 99.0], sample_id: 0 }).unwrap();
+        let finished = recorder.finish().unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        assert_eq!(finished.stream_info.channels_count, 2);
+        assert_eq!(finished.stream_info.channel_labels, vec!["Cz", "Fz"]);
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("timestamp,Cz,Fz"));
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(&row[1..], &["2", "1"]);
+    }
+    
+    #[test]
+    fn test_filename_template_and_collisions() {
+        let naming = RecordingNamingConfig::default();
+        let started = chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2024, 3, 7, 14, 5, 9).unwrap();
+        assert_eq!(
+            naming.filename(Some("S01"), "OpenBCI EEG/1", RecordingFormat::Bdf, started).unwrap(),
+            "S01_20240307_140509_OpenBCI_EEG_1.bdf"
+        );
+        assert_eq!(naming.filename(None, "..", RecordingFormat::Edf, started).unwrap(), "unknown_20240307_140509_unknown.edf");
+        
+        for template in ["{subject", "{patient}.edf", "session}.edf", " "] {
+            let invalid = RecordingNamingConfig { template: template.to_string(), ..RecordingNamingConfig::default() };
+            assert!(invalid.validate().is_err(), "{}", template);
+        }
+        
+        let path = std::env::temp_dir().join(format!("collision_test_{}.edf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        assert_eq!(EdfRecorder::available_filename(&filename), filename);
+        std::fs::write(&path, b"").unwrap();
+        let second = EdfRecorder::available_filename(&filename);
+        let _ = std::fs::remove_file(&path);
+        assert!(second.ends_with(&format!("collision_test_{}_1.edf", std::process::id())));
+    }
 }
\ No newline at end of file
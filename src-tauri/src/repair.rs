@@ -0,0 +1,168 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use tracing::info;
+use crate::data_types::RepairReport;
+use crate::error::AppError;
+
+// 固定头部字段的偏移
+const HEADER_BYTES_OFFSET: usize = 184;
+const NUM_RECORDS_OFFSET: usize = 236;
+const RECORD_DURATION_OFFSET: usize = 244;
+const SIGNAL_COUNT_OFFSET: usize = 252;
+
+// 每个信号的头部字段总长（标签16 + 传感器80 + 单位8 + 物理/数字范围4x8 + 预滤波80）
+const SIGNAL_FIELDS_BEFORE_SAMPLES: usize = 216;
+
+/// 文件头描述的EDF/BDF数据布局
+pub(crate) struct FileLayout {
+    pub header_bytes: u64,
+    pub records_in_header: i64,         // 未完成的文件为 -1
+    pub record_duration: f64,
+    pub record_bytes: u64,              // 每个数据记录的字节数
+    pub signal_count: usize,
+    pub is_bdf: bool,
+}
+
+impl FileLayout {
+    /// 按文件长度计算的完整记录数和末尾多余的字节数
+    pub fn records_in(&self, file_bytes: u64) -> (u64, u64) {
+        let data_bytes = file_bytes.saturating_sub(self.header_bytes);
+        (data_bytes / self.record_bytes, data_bytes % self.record_bytes)
+    }
+}
+
+/// 读取并校验EDF(+)/BDF(+)文件头，读取后文件位置在数据记录起始处
+pub(crate) fn read_layout(file: &mut File, path: &Path) -> Result<FileLayout, AppError> {
+    let mut fixed = [0u8; 256];
+    file.read_exact(&mut fixed)
+        .map_err(|_| invalid(path, "file is shorter than the fixed header"))?;
+    
+    // BDF 以 0xFF "BIOSEMI" 开头，每样本3字节；EDF 以 "0" 开头，每样本2字节
+    let bytes_per_sample = match fixed[0] {
+        0xFF if &fixed[1..8] == b"BIOSEMI" => 3,
+        b'0' => 2,
+        _ => return Err(invalid(path, "not an EDF or BDF file")),
+    };
+    
+    let header_bytes: u64 = parse_field(&fixed, HEADER_BYTES_OFFSET, 8).ok_or_else(|| invalid(path, "bad header size"))?;
+    let records_in_header: i64 = parse_field(&fixed, NUM_RECORDS_OFFSET, 8).unwrap_or(-1);
+    let record_duration: f64 = parse_field(&fixed, RECORD_DURATION_OFFSET, 8).ok_or_else(|| invalid(path, "bad record duration"))?;
+    let signal_count: usize = parse_field(&fixed, SIGNAL_COUNT_OFFSET, 4).ok_or_else(|| invalid(path, "bad signal count"))?;
+    if signal_count == 0 || header_bytes != 256 * (signal_count as u64 + 1) {
+        return Err(invalid(path, "header size does not match signal count"));
+    }
+    
+    let mut signal_header = vec![0u8; signal_count * 256];
+    file.read_exact(&mut signal_header)
+        .map_err(|_| invalid(path, "file is shorter than the signal headers"))?;
+    let samples_offset = signal_count * SIGNAL_FIELDS_BEFORE_SAMPLES;
+    let mut samples_per_record = 0u64;
+    for signal in 0..signal_count {
+        let samples: u64 = parse_field(&signal_header, samples_offset + signal * 8, 8)
+            .ok_or_else(|| invalid(path, "bad samples per record"))?;
+        samples_per_record += samples;
+    }
+    let record_bytes = samples_per_record * bytes_per_sample;
+    if record_bytes == 0 {
+        return Err(invalid(path, "data records are empty"));
+    }
+    
+    Ok(FileLayout {
+        header_bytes,
+        records_in_header,
+        record_duration,
+        record_bytes,
+        signal_count,
+        is_bdf: bytes_per_sample == 3,
+    })
+}
+
+/// 修复异常退出留下的EDF(+)/BDF(+)文件
+///
+/// 按文件长度计算完整的数据记录数并写入文件头，截去末尾不完整的记录。
+/// 文件头已与数据一致时不做修改。
+pub fn repair_recording(path: &Path) -> Result<RepairReport, AppError> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let layout = read_layout(&mut file, path)?;
+    let records_in_header = layout.records_in_header;
+    let (records, bytes_truncated) = layout.records_in(file.metadata()?.len());
+    
+    let repaired = records_in_header != records as i64 || bytes_truncated > 0;
+    if repaired {
+        let mut field = format!("{:<8}", records).into_bytes();
+        field.truncate(8);
+        file.seek(SeekFrom::Start(NUM_RECORDS_OFFSET as u64))?;
+        file.write_all(&field)?;
+        file.set_len(layout.header_bytes + records * layout.record_bytes)?;
+        file.sync_all()?;
+        
+        info!("🩹 Repaired {}: {} -> {} data records, {} trailing bytes removed",
+              path.display(), records_in_header, records, bytes_truncated);
+    }
+    
+    Ok(RepairReport {
+        path: path.display().to_string(),
+        records_in_header,
+        records_recovered: records,
+        duration_seconds: records as f64 * layout.record_duration,
+        bytes_truncated,
+        repaired,
+    })
+}
+
+fn parse_field<T: std::str::FromStr>(header: &[u8], offset: usize, width: usize) -> Option<T> {
+    let bytes = header.get(offset..offset + width)?;
+    std::str::from_utf8(bytes).ok()?.trim().parse().ok()
+}
+
+fn invalid(path: &Path, reason: &str) -> AppError {
+    AppError::Recording(format!("{} is not a valid EDF/BDF file: {}", path.display(), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::{BdfSignal, BdfWriter};
+    
+    #[test]
+    fn test_repair_interrupted_bdf() {
+        let path = std::env::temp_dir().join(format!("repair_test_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let signal = BdfSignal {
+            label: "Cz".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -100.0,
+            physical_max: 100.0,
+            prefilter: String::new(),
+            samples_per_record: 4,
+        };
+        
+        // 检查点之后又写了一个记录，然后异常退出（未 finalize）并留下半个记录
+        let mut writer = BdfWriter::create(&filename, vec![signal], 1.0, chrono::Utc::now(), "X X X X", "Startdate X X X X").unwrap();
+        writer.write_samples(&[vec![1.0; 4]]).unwrap();
+        writer.write_samples(&[vec![2.0; 4]]).unwrap();
+        writer.checkpoint().unwrap();
+        let header = std::fs::read(&path).unwrap();
+        assert_eq!(std::str::from_utf8(&header[236..244]).unwrap().trim(), "2");
+        writer.write_samples(&[vec![3.0; 4]]).unwrap();
+        drop(writer);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0u8; 5]).unwrap();
+        drop(file);
+        
+        let report = repair_recording(&path).unwrap();
+        assert!(report.repaired);
+        assert_eq!(report.records_in_header, 2);
+        assert_eq!(report.records_recovered, 3);
+        assert_eq!(report.bytes_truncated, 5);
+        assert_eq!(report.duration_seconds, 3.0);
+        
+        // 已一致的文件不再修改
+        let again = repair_recording(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(!again.repaired);
+        assert_eq!(again.records_in_header, 3);
+    }
+}
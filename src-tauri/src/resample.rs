@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use crate::error::AppError;
+
+// 有理比 L/M 中 L 的上限（多相滤波器组大小 = L × 每相抽头数）
+const MAX_UP_FACTOR: u64 = 1024;
+// 过渡带宽度占较低采样率的比例，阻带从较低奈奎斯特频率开始
+const TRANSITION_FRACTION: f64 = 0.1;
+// Blackman 窗的过渡带宽度系数（归一化频率 × 滤波器长度）
+const BLACKMAN_TRANSITION: f64 = 5.5;
+
+/// 多相有理重采样器 - 输入速率 × L / M，多通道各自维护历史
+///
+/// 原型滤波器为 Blackman 窗 sinc，中心对齐到整数个输入样本，
+/// 输出样本 k 精确对应输入时刻 k·M/L（无相位偏移），代价是延迟半个滤波器长度输出。
+/// 混合速率的多流会话可以把各流重采样到同一速率后对齐，录制也可以按目标速率写入。
+pub struct Resampler {
+    up: u64,
+    down: u64,
+    taps: usize,
+    phases: Vec<Vec<f64>>,          // phases[p][j] = h[p + j·L]·L
+    history: Vec<VecDeque<f64>>,
+    input_count: u64,
+    output_count: u64,
+    output_rate: f64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: f64, output_rate: f64, channels_count: usize) -> Result<Self, AppError> {
+        let (up, down) = Self::rational_ratio(input_rate, output_rate)?;
+        
+        // 过渡带足够窄时阻带才不会漏入较低的奈奎斯特频率以下
+        let lower_rate = input_rate.min(output_rate);
+        let transition = TRANSITION_FRACTION * lower_rate;
+        let mut taps = (BLACKMAN_TRANSITION * input_rate / transition).ceil() as usize;
+        taps += taps % 2;
+        
+        let cutoff_hz = lower_rate / 2.0 - transition / 2.0;
+        let upsampled_rate = input_rate * up as f64;
+        let length = taps * up as usize;
+        let center = length as f64 / 2.0;
+        let fc = cutoff_hz / upsampled_rate;
+        
+        let prototype: Vec<f64> = (0..length)
+            .map(|i| {
+                let t = i as f64 - center;
+                let sinc = if t == 0.0 { 2.0 * fc } else { (2.0 * PI * fc * t).sin() / (PI * t) };
+                let w = 2.0 * PI * i as f64 / length as f64;
+                let window = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+                sinc * window * up as f64
+            })
+            .collect();
+        
+        let phases = (0..up as usize)
+            .map(|phase| (0..taps).map(|j| prototype[phase + j * up as usize]).collect())
+            .collect();
+        
+        Ok(Self {
+            up,
+            down,
+            taps,
+            phases,
+            history: vec![VecDeque::with_capacity(taps + 1); channels_count],
+            input_count: 0,
+            output_count: 0,
+            output_rate,
+        })
+    }
+    
+    /// 速率比化为最简整数比（按0.001Hz精度）
+    fn rational_ratio(input_rate: f64, output_rate: f64) -> Result<(u64, u64), AppError> {
+        if !input_rate.is_finite() || !output_rate.is_finite() || input_rate <= 0.0 || output_rate <= 0.0 {
+            return Err(AppError::Config(format!(
+                "Invalid resampling rates: {} -> {}", input_rate, output_rate
+            )));
+        }
+        
+        let input = (input_rate * 1000.0).round() as u64;
+        let output = (output_rate * 1000.0).round() as u64;
+        let divisor = gcd(input, output);
+        let (up, down) = (output / divisor, input / divisor);
+        
+        if up > MAX_UP_FACTOR {
+            return Err(AppError::Config(format!(
+                "Resampling ratio {}/{} is too fine ({} -> {} Hz)", up, down, input_rate, output_rate
+            )));
+        }
+        
+        Ok((up, down))
+    }
+    
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+    
+    /// 输出相对输入的延迟（秒）：输出 k 需等到输入越过 k·M/L 半个滤波器长度后才可计算
+    pub fn latency_seconds(&self) -> f64 {
+        (self.taps / 2) as f64 / (self.output_rate * self.down as f64 / self.up as f64)
+    }
+    
+    /// 已输出的样本数，输出 k 对应首个输入样本之后 k / output_rate 秒
+    pub fn output_count(&self) -> u64 {
+        self.output_count
+    }
+    
+    /// 加入一个多通道输入样本，返回本次可计算出的输出样本（0个或多个）
+    pub fn process_sample(&mut self, channels: &[f64]) -> Vec<Vec<f64>> {
+        for (history, &value) in self.history.iter_mut().zip(channels) {
+            if history.len() == self.taps {
+                history.pop_front();
+            }
+            history.push_back(value);
+        }
+        let newest = self.input_count;
+        self.input_count += 1;
+        
+        let mut outputs = Vec::new();
+        loop {
+            let position = self.output_count * self.down;
+            let needed = position / self.up + (self.taps / 2) as u64;
+            if needed > newest {
+                break;
+            }
+            
+            let coefficients = &self.phases[(position % self.up) as usize];
+            let lag = (newest - needed) as usize;
+            
+            let output = self.history.iter()
+                .map(|history| {
+                    coefficients.iter().enumerate()
+                        .filter_map(|(j, &h)| {
+                            let back = lag + j;
+                            (back < history.len()).then(|| h * history[history.len() - 1 - back])
+                        })
+                        .sum()
+                })
+                .collect();
+            
+            outputs.push(output);
+            self.output_count += 1;
+        }
+        
+        outputs
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// 稳态下输出与理想正弦（按输出时刻采样）的最大误差
+    fn max_error(input_rate: f64, output_rate: f64, freq: f64) -> f64 {
+        let mut resampler = Resampler::new(input_rate, output_rate, 1).unwrap();
+        let mut outputs = Vec::new();
+        for n in 0..(input_rate * 6.0) as usize {
+            let x = (2.0 * PI * freq * n as f64 / input_rate).sin();
+            outputs.extend(resampler.process_sample(&[x]).into_iter().map(|o| o[0]));
+        }
+        
+        // 跳过首秒的启动瞬态
+        outputs.iter().enumerate()
+            .skip(output_rate as usize)
+            .map(|(k, y)| (y - (2.0 * PI * freq * k as f64 / output_rate).sin()).abs())
+            .fold(0.0, f64::max)
+    }
+    
+    #[test]
+    fn test_amplitude_and_phase_fidelity() {
+        // 混合速率（500/512Hz）对齐到256Hz，以及整数倍上采样
+        assert!(max_error(500.0, 256.0, 10.0) < 0.01);
+        assert!(max_error(500.0, 256.0, 40.0) < 0.01);
+        assert!(max_error(512.0, 256.0, 25.0) < 0.01);
+        assert!(max_error(250.0, 500.0, 30.0) < 0.01);
+    }
+    
+    #[test]
+    fn test_rejects_components_above_output_nyquist() {
+        let mut resampler = Resampler::new(512.0, 256.0, 1).unwrap();
+        let mut peak: f64 = 0.0;
+        for n in 0..512 * 6 {
+            let x = (2.0 * PI * 200.0 * n as f64 / 512.0).sin();
+            for output in resampler.process_sample(&[x]) {
+                if n > 512 * 2 {
+                    peak = peak.max(output[0].abs());
+                }
+            }
+        }
+        assert!(peak < 0.01, "alias leaked: {}", peak);
+        
+        assert!(Resampler::new(500.0, 0.0, 1).is_err());
+    }
+}
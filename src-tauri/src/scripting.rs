@@ -0,0 +1,294 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use crate::stages::StageBatch;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use tracing::{info, warn};
+
+// 单次调用的操作数上限，防止死循环阻塞管道
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// 每个脚本最多记录的运行错误条数
+const MAX_REPORTED_ERRORS: u64 = 5;
+
+fn entry_point(input: ScriptInput) -> &'static str {
+    match input {
+        ScriptInput::Batch => "on_batch",
+        ScriptInput::Spectrum => "on_spectrum",
+    }
+}
+
+/// 受限的脚本引擎：限制操作数、调用深度与容器大小，不提供文件或网络访问
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(4096);
+    engine.set_max_array_size(1 << 20);
+    engine.set_max_map_size(1024);
+    engine
+}
+
+struct CompiledScript {
+    name: String,
+    ast: AST,
+    state: Dynamic,         // 脚本中以 this 访问，跨调用保留
+    errors: u64,
+}
+
+/// 已编译的某一输入类型的脚本
+///
+/// 脚本只需定义入口函数（on_batch 或 on_spectrum），顶层语句不执行；
+/// 需要跨调用保留的状态存放在 `this` 对象映射中。
+pub struct ScriptHooks {
+    engine: Engine,
+    input: ScriptInput,
+    scripts: Vec<CompiledScript>,
+}
+
+impl ScriptHooks {
+    /// 编译配置中指定输入类型的脚本
+    pub fn compile(config: &ScriptingConfig, input: ScriptInput) -> Result<Self, AppError> {
+        let engine = sandboxed_engine();
+        let scripts = config.scripts.iter()
+            .filter(|script| script.input == input)
+            .map(|script| Ok(CompiledScript {
+                name: script.name.clone(),
+                ast: Self::compile_one(&engine, script)?,
+                state: Dynamic::from_map(Map::new()),
+                errors: 0,
+            }))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        
+        Ok(Self { engine, input, scripts })
+    }
+    
+    /// 校验全部脚本：名称、语法与入口函数
+    pub fn check(config: &ScriptingConfig) -> Result<(), AppError> {
+        config.validate()?;
+        let engine = sandboxed_engine();
+        for script in &config.scripts {
+            Self::compile_one(&engine, script)?;
+        }
+        Ok(())
+    }
+    
+    fn compile_one(engine: &Engine, script: &ScriptHook) -> Result<AST, AppError> {
+        let ast = engine.compile(&script.source)
+            .map_err(|e| AppError::Config(format!("Script '{}': {}", script.name, e)))?;
+        
+        let entry = entry_point(script.input);
+        if !ast.iter_functions().any(|function| function.name == entry && function.params.len() == 1) {
+            return Err(AppError::Config(format!(
+                "Script '{}' must define fn {}(input)", script.name, entry
+            )));
+        }
+        Ok(ast)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+    
+    /// 对显示路径数据块调用 on_batch(batch)
+    ///
+    /// batch = #{ sample_rate, labels, timestamps, channels }，channels 按通道排列
+    pub fn on_batch(&mut self, batch: &StageBatch, sample_rate: f64) -> Vec<ScriptMetricsUpdate> {
+        let Some(timestamp) = batch.samples.last().map(|sample| sample.timestamp) else {
+            return Vec::new();
+        };
+        
+        let channels_count = batch.samples.first().map_or(0, |sample| sample.channels.len());
+        let channels: Array = (0..channels_count)
+            .map(|ch| Dynamic::from_array(batch.samples.iter()
+                .map(|sample| Dynamic::from_float(sample.channels.get(ch).copied().unwrap_or(0.0)))
+                .collect()))
+            .collect();
+        
+        let mut argument = Map::new();
+        argument.insert("sample_rate".into(), Dynamic::from_float(sample_rate));
+        argument.insert("labels".into(), Dynamic::from_array(
+            batch.channel_labels.iter().map(|label| Dynamic::from(label.clone())).collect()
+        ));
+        argument.insert("timestamps".into(), float_array(batch.samples.iter().map(|sample| sample.timestamp)));
+        argument.insert("channels".into(), Dynamic::from_array(channels));
+        
+        self.run(Dynamic::from_map(argument), timestamp)
+    }
+    
+    /// 对一帧完整分辨率幅值谱调用 on_spectrum(spectrum)
+    ///
+    /// spectrum = #{ timestamp, resolution, frequencies, channels }
+    pub fn on_spectrum(&mut self, timestamp: f64, resolution: f64, magnitudes: &[Vec<f64>]) -> Vec<ScriptMetricsUpdate> {
+        let bins = magnitudes.first().map_or(0, Vec::len);
+        
+        let mut argument = Map::new();
+        argument.insert("timestamp".into(), Dynamic::from_float(timestamp));
+        argument.insert("resolution".into(), Dynamic::from_float(resolution));
+        argument.insert("frequencies".into(), float_array((0..bins).map(|bin| bin as f64 * resolution)));
+        argument.insert("channels".into(), Dynamic::from_array(
+            magnitudes.iter().map(|spectrum| float_array(spectrum.iter().copied())).collect()
+        ));
+        
+        self.run(Dynamic::from_map(argument), timestamp)
+    }
+    
+    fn run(&mut self, argument: Dynamic, timestamp: f64) -> Vec<ScriptMetricsUpdate> {
+        let entry = entry_point(self.input);
+        let mut updates = Vec::new();
+        
+        for script in &mut self.scripts {
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut script.state);
+            let result = self.engine
+                .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &script.ast, entry, (argument.clone(),))
+                .map_err(|e| e.to_string())
+                .and_then(metrics_from_result);
+            
+            match result {
+                Ok(metrics) if metrics.is_empty() => {}
+                Ok(metrics) => updates.push(ScriptMetricsUpdate {
+                    script: script.name.clone(),
+                    input: self.input,
+                    timestamp,
+                    metrics,
+                }),
+                Err(e) => {
+                    script.errors += 1;
+                    if script.errors <= MAX_REPORTED_ERRORS {
+                        warn!("⚠️ Script '{}' failed (#{}): {}", script.name, script.errors, e);
+                    }
+                }
+            }
+        }
+        
+        updates
+    }
+}
+
+/// 按输入类型重建脚本，编译失败时禁用该类型的全部脚本
+pub fn build_hooks(config: &ScriptingConfig, input: ScriptInput) -> Option<ScriptHooks> {
+    match ScriptHooks::compile(config, input) {
+        Ok(hooks) if hooks.is_empty() => None,
+        Ok(hooks) => {
+            info!("📜 {} {:?} script(s) loaded", hooks.scripts.len(), input);
+            Some(hooks)
+        }
+        Err(e) => {
+            warn!("⚠️ Scripts disabled: {}", e);
+            None
+        }
+    }
+}
+
+fn float_array(values: impl Iterator<Item = f64>) -> Dynamic {
+    Dynamic::from_array(values.map(Dynamic::from_float).collect())
+}
+
+fn as_number(value: &Dynamic) -> Option<f64> {
+    value.as_float().ok().or_else(|| value.as_int().ok().map(|value| value as f64))
+}
+
+/// 脚本返回值：名称 → 数值（与通道无关）或数值数组（按通道索引），非有限值忽略
+fn metrics_from_result(result: Dynamic) -> Result<Vec<StageMetric>, String> {
+    if result.is_unit() {
+        return Ok(Vec::new());
+    }
+    let map = result.try_cast::<Map>()
+        .ok_or_else(|| "expected a map of metric values".to_string())?;
+    
+    let mut metrics = Vec::new();
+    for (name, value) in map {
+        if let Some(value) = as_number(&value) {
+            metrics.push(StageMetric { name: name.to_string(), channel_index: None, value });
+        } else if let Some(values) = value.try_cast::<Array>() {
+            for (channel, value) in values.iter().enumerate() {
+                let value = as_number(value)
+                    .ok_or_else(|| format!("metric '{}' channel {} is not a number", name, channel))?;
+                metrics.push(StageMetric { name: name.to_string(), channel_index: Some(channel as u32), value });
+            }
+        } else {
+            return Err(format!("metric '{}' must be a number or an array of numbers", name));
+        }
+    }
+    metrics.retain(|metric| metric.value.is_finite());
+    
+    Ok(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn hook(name: &str, input: ScriptInput, source: &str) -> ScriptHook {
+        ScriptHook { name: name.to_string(), source: source.to_string(), input, record: false }
+    }
+    
+    #[test]
+    fn test_batch_and_spectrum_scripts_return_metrics() {
+        let config = ScriptingConfig {
+            scripts: vec![
+                hook("mean", ScriptInput::Batch, r#"
+                    fn on_batch(batch) {
+                        if this.calls == () { this.calls = 0; }
+                        this.calls += 1;
+                        let means = [];
+                        for channel in batch.channels {
+                            let sum = 0.0;
+                            for value in channel { sum += value; }
+                            means.push(sum / channel.len());
+                        }
+                        #{ mean: means, calls: this.calls }
+                    }
+                "#),
+                hook("peak", ScriptInput::Spectrum, r#"
+                    fn on_spectrum(spectrum) {
+                        let best = 0;
+                        let magnitudes = spectrum.channels[0];
+                        for i in 0..magnitudes.len() {
+                            if magnitudes[i] > magnitudes[best] { best = i; }
+                        }
+                        #{ peak_hz: spectrum.frequencies[best] }
+                    }
+                "#),
+            ],
+        };
+        ScriptHooks::check(&config).unwrap();
+        
+        let mut batch_hooks = ScriptHooks::compile(&config, ScriptInput::Batch).unwrap();
+        let batch = StageBatch {
+            channel_labels: vec!["C3".to_string(), "C4".to_string()],
+            samples: (0..4).map(|i| EegSample {
+                timestamp: 10.0 + i as f64,
+                channels: vec![i as f64, 2.0],
+                sample_id: i,
+            }).collect(),
+        };
+        batch_hooks.on_batch(&batch, 250.0);
+        let updates = batch_hooks.on_batch(&batch, 250.0);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].timestamp, 13.0);
+        let metrics = &updates[0].metrics;
+        assert_eq!(metrics[0], StageMetric { name: "calls".to_string(), channel_index: None, value: 2.0 });
+        assert_eq!(metrics[1].value, 1.5);
+        assert_eq!(metrics[2], StageMetric { name: "mean".to_string(), channel_index: Some(1), value: 2.0 });
+        
+        let mut spectrum_hooks = ScriptHooks::compile(&config, ScriptInput::Spectrum).unwrap();
+        let updates = spectrum_hooks.on_spectrum(1.0, 0.5, &[vec![0.0, 1.0, 4.0, 2.0]]);
+        assert_eq!(updates[0].metrics[0].value, 1.0);
+    }
+    
+    #[test]
+    fn test_invalid_scripts_are_rejected() {
+        let missing_entry = ScriptingConfig { scripts: vec![hook("a", ScriptInput::Batch, "fn on_spectrum(s) { #{} }")] };
+        assert!(ScriptHooks::check(&missing_entry).is_err());
+        
+        let bad_name = ScriptingConfig { scripts: vec![hook("a b", ScriptInput::Batch, "fn on_batch(b) { #{} }")] };
+        assert!(ScriptHooks::check(&bad_name).is_err());
+        
+        // 死循环在操作数上限处中止，不产生指标
+        let looping = ScriptingConfig { scripts: vec![hook("loop", ScriptInput::Spectrum, "fn on_spectrum(s) { loop {} }")] };
+        let mut hooks = ScriptHooks::compile(&looping, ScriptInput::Spectrum).unwrap();
+        assert!(hooks.on_spectrum(0.0, 1.0, &[vec![1.0]]).is_empty());
+        assert_eq!(hooks.scripts[0].errors, 1);
+    }
+}
@@ -0,0 +1,326 @@
+use crate::data_types::*;
+use crate::eeg_processor::EegProcessor;
+use crate::error::{AppError, BackendError, ErrorSource};
+use crate::lsl_manager::LslManager;
+use crate::mqtt::{self, SharedMqtt};
+use crate::playback::PlaybackPlayer;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "desktop")]
+use tauri::{AppHandle, Emitter};
+use tracing::{error, info};
+
+/// 会话标识 - 默认由流名称生成，可在连接时指定
+pub type StreamId = String;
+
+// 事件推送失败的原因；无界面构建只转发MQTT，不会失败
+#[cfg(feature = "desktop")]
+pub type EmitError = tauri::Error;
+#[cfg(not(feature = "desktop"))]
+pub type EmitError = std::convert::Infallible;
+
+/// 会话事件发送器
+/// 主会话沿用原事件名（兼容单流前端），其他会话的事件名追加 ":<stream_id>"；
+/// 无界面运行时没有前端句柄，事件只转发到MQTT（若启用）；
+/// 作用域在各线程的副本间共享，主会话断开后提升的会话改用原事件名
+#[derive(Clone)]
+pub struct StreamEmitter {
+    #[cfg(feature = "desktop")]
+    app_handle: Option<AppHandle>,
+    scope: Arc<RwLock<Option<StreamId>>>,
+    mqtt: Option<(SharedMqtt, StreamId)>,       // 同时转发到MQTT（按会话ID解析主题）
+}
+
+impl StreamEmitter {
+    #[cfg(feature = "desktop")]
+    pub fn new(app_handle: AppHandle, scope: Option<StreamId>) -> Self {
+        Self { app_handle: Some(app_handle), scope: Arc::new(RwLock::new(scope)), mqtt: None }
+    }
+    
+    pub fn headless() -> Self {
+        Self {
+            #[cfg(feature = "desktop")]
+            app_handle: None,
+            scope: Arc::new(RwLock::new(None)),
+            mqtt: None,
+        }
+    }
+    
+    pub fn with_mqtt(mut self, mqtt: SharedMqtt, stream_id: StreamId) -> Self {
+        self.mqtt = Some((mqtt, stream_id));
+        self
+    }
+    
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> Result<(), EmitError> {
+        if let Some((shared, stream_id)) = &self.mqtt {
+            mqtt::forward(shared, event, Some(stream_id), &payload);
+        }
+        #[cfg(feature = "desktop")]
+        if let Some(app_handle) = &self.app_handle {
+            return match self.scope() {
+                Some(stream_id) => app_handle.emit(&format!("{}:{}", event, stream_id), payload),
+                None => app_handle.emit(event, payload),
+            };
+        }
+        Ok(())
+    }
+    
+    /// 后台线程中的异步失败（命令已返回，无法通过返回值报告）推送 backend-error 事件
+    pub fn emit_error(&self, source: ErrorSource, err: &AppError) {
+        let event = BackendError { source, error: err.info() };
+        if let Err(e) = self.emit("backend-error", event) {
+            error!("Failed to emit backend-error: {}", e);
+        }
+    }
+    
+    pub fn scope(&self) -> Option<StreamId> {
+        self.scope.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    /// 成为主会话：之后的事件使用原事件名
+    pub fn promote(&self) {
+        *self.scope.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// 单个流的完整会话：独立的数据源（LSL工作线程或文件回放）、处理管道和录制
+pub struct StreamSession {
+    pub stream_id: StreamId,
+    pub lsl_manager: Option<LslManager>,
+    pub playback: Option<PlaybackPlayer>,
+    pub eeg_processor: EegProcessor,
+    pub is_primary: bool,
+}
+
+impl StreamSession {
+    /// 消费式停止 - 先停处理器再停数据源
+    pub async fn stop(self) -> Result<(), AppError> {
+        info!("🛑 Stopping session '{}'", self.stream_id);
+        
+        let processor_stats = self.eeg_processor.stop().await?;
+        info!("📊 Processor stats: {:?}", processor_stats);
+        
+        if let Some(lsl_manager) = self.lsl_manager {
+            let manager_stats = lsl_manager.stop().await?;
+            info!("📊 Manager stats: {:?}", manager_stats);
+        }
+        
+        if let Some(playback) = self.playback {
+            playback.stop();
+        }
+        
+        Ok(())
+    }
+    
+    /// LSL会话的管理器；回放会话没有LSL连接
+    pub fn lsl(&self) -> Result<&LslManager, AppError> {
+        self.lsl_manager.as_ref().ok_or_else(|| {
+            AppError::Playback(format!("Session '{}' is a file playback, not an LSL stream", self.stream_id))
+        })
+    }
+    
+    pub fn lsl_mut(&mut self) -> Result<&mut LslManager, AppError> {
+        let stream_id = &self.stream_id;
+        self.lsl_manager.as_mut().ok_or_else(|| {
+            AppError::Playback(format!("Session '{}' is a file playback, not an LSL stream", stream_id))
+        })
+    }
+    
+    pub fn playback(&self) -> Result<&PlaybackPlayer, AppError> {
+        self.playback.as_ref().ok_or_else(|| {
+            AppError::Playback(format!("Session '{}' is not a file playback", self.stream_id))
+        })
+    }
+    
+    pub async fn info(&self) -> SessionInfo {
+        SessionInfo {
+            stream_id: self.stream_id.clone(),
+            stream_info: self.eeg_processor.stream_info().clone(),
+            is_primary: self.is_primary,
+            event_suffix: self.eeg_processor.event_scope().map(|scope| format!(":{}", scope)),
+            is_recording: self.eeg_processor.is_recording().await,
+            is_capturing: self.eeg_processor.is_capturing().await,
+            is_playback: self.playback.is_some(),
+            is_processing: self.eeg_processor.is_running().await,
+            streaming_paused: self.eeg_processor.is_streaming_paused().await,
+            stream_lost: self.lsl_manager.as_ref().is_some_and(|lsl_manager| lsl_manager.is_stream_lost()),
+        }
+    }
+}
+
+/// 所有已连接流的会话表
+#[derive(Default)]
+pub struct StreamSessions {
+    sessions: HashMap<StreamId, StreamSession>,
+}
+
+impl StreamSessions {
+    /// 规范化会话标识，事件名只允许字母数字和 - _
+    pub fn normalize_id(raw: &str) -> StreamId {
+        let id: String = raw
+            .trim()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        
+        if id.is_empty() { "stream".to_string() } else { id }
+    }
+    
+    pub fn has_primary(&self) -> bool {
+        self.sessions.values().any(|session| session.is_primary)
+    }
+    
+    pub fn insert(&mut self, session: StreamSession) {
+        self.sessions.insert(session.stream_id.clone(), session);
+    }
+    
+    /// 移除会话（替换同一标识的会话时使用，新会话继承主会话身份）
+    pub fn take(&mut self, stream_id: &str) -> Option<StreamSession> {
+        self.sessions.remove(&Self::normalize_id(stream_id))
+    }
+    
+    /// 断开会话；移除的是主会话时把剩余会话中排序第一的提升为主会话
+    pub fn remove(&mut self, stream_id: &str) -> Option<StreamSession> {
+        let session = self.take(stream_id)?;
+        if session.is_primary && !self.has_primary() {
+            if let Some(next) = self.sessions.values_mut().min_by(|a, b| a.stream_id.cmp(&b.stream_id)) {
+                info!("⭐ Session '{}' promoted to primary", next.stream_id);
+                next.is_primary = true;
+                next.eeg_processor.promote_events();
+            }
+        }
+        Some(session)
+    }
+    
+    pub fn drain(&mut self) -> Vec<StreamSession> {
+        self.sessions.drain().map(|(_, session)| session).collect()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+    
+    /// 主会话在前，其余按标识排序
+    pub fn iter(&self) -> impl Iterator<Item = &StreamSession> {
+        let mut sessions: Vec<&StreamSession> = self.sessions.values().collect();
+        sessions.sort_by(|a, b| b.is_primary.cmp(&a.is_primary).then_with(|| a.stream_id.cmp(&b.stream_id)));
+        sessions.into_iter()
+    }
+    
+    /// 解析命令目标：指定标识时返回该会话，未指定时只在仅有一个会话时返回
+    pub fn resolve(&self, stream_id: Option<&str>) -> Result<&StreamSession, AppError> {
+        let id = self.resolve_id(stream_id)?;
+        Ok(&self.sessions[&id])
+    }
+    
+    pub fn resolve_mut(&mut self, stream_id: Option<&str>) -> Result<&mut StreamSession, AppError> {
+        let id = self.resolve_id(stream_id)?;
+        Ok(self.sessions.get_mut(&id).expect("resolved session exists"))
+    }
+    
+    /// 配置命令的目标：指定标识时为该会话，未指定时为所有会话
+    pub fn targets(&self, stream_id: Option<&str>) -> Result<Vec<&StreamSession>, AppError> {
+        match stream_id {
+            Some(_) => Ok(vec![self.resolve(stream_id)?]),
+            None => Ok(self.iter().collect()),
+        }
+    }
+    
+    pub fn targets_mut(&mut self, stream_id: Option<&str>) -> Result<Vec<&mut StreamSession>, AppError> {
+        match stream_id {
+            Some(_) => Ok(vec![self.resolve_mut(stream_id)?]),
+            None => Ok(self.sessions.values_mut().collect()),
+        }
+    }
+    
+    /// 指定的标识与连接时一样规范化后再查找
+    fn resolve_id(&self, stream_id: Option<&str>) -> Result<StreamId, AppError> {
+        match stream_id {
+            Some(id) => {
+                let id_normalized = Self::normalize_id(id);
+                if self.sessions.contains_key(&id_normalized) {
+                    Ok(id_normalized)
+                } else {
+                    Err(AppError::UnknownStream(id.to_string()))
+                }
+            }
+            None => match self.sessions.len() {
+                0 => Err(AppError::NotConnected),
+                1 => Ok(self.sessions.keys().next().unwrap().clone()),
+                _ => Err(AppError::AmbiguousStream(
+                    self.iter().map(|session| session.stream_id.as_str()).collect::<Vec<_>>().join(", ")
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn session(name: &str, is_primary: bool) -> StreamSession {
+        let stream_id = StreamSessions::normalize_id(name);
+        let stream_info = StreamInfo {
+            name: name.to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 2,
+            sample_rate: 250.0,
+            is_connected: true,
+            source_id: String::new(),
+            channel_labels: StreamInfo::default_channel_labels(2),
+            effective_sample_rate: None,
+        };
+        let emitter = StreamEmitter {
+            scope: Arc::new(RwLock::new((!is_primary).then(|| stream_id.clone()))),
+            ..StreamEmitter::headless()
+        };
+        
+        StreamSession {
+            stream_id,
+            lsl_manager: None,
+            playback: None,
+            eeg_processor: EegProcessor::new(stream_info, emitter).unwrap(),
+            is_primary,
+        }
+    }
+    
+    #[test]
+    fn test_lookup_uses_normalized_id() {
+        let mut sessions = StreamSessions::default();
+        sessions.insert(session("OpenBCI Cyton (8ch)", true));
+        
+        assert_eq!(sessions.resolve(Some("OpenBCI Cyton (8ch)")).unwrap().stream_id, "OpenBCI_Cyton__8ch_");
+        assert!(sessions.resolve(Some("OpenBCI_Cyton__8ch_")).is_ok());
+        assert!(matches!(sessions.resolve(Some("Other")), Err(AppError::UnknownStream(_))));
+        
+        assert!(sessions.remove("OpenBCI Cyton (8ch)").is_some());
+        assert!(sessions.is_empty());
+    }
+    
+    #[test]
+    fn test_removing_primary_promotes_next_session() {
+        let mut sessions = StreamSessions::default();
+        sessions.insert(session("main", true));
+        sessions.insert(session("zeta", false));
+        sessions.insert(session("beta", false));
+        
+        sessions.remove("main").unwrap();
+        
+        let primary = sessions.iter().next().unwrap();
+        assert_eq!(primary.stream_id, "beta");
+        assert!(primary.is_primary);
+        assert_eq!(primary.eeg_processor.event_scope(), None);
+        assert!(!sessions.resolve(Some("zeta")).unwrap().is_primary);
+        
+        // 替换同一标识的会话时不提升，新会话继承主会话身份
+        let replaced = sessions.take("beta").unwrap();
+        assert!(replaced.is_primary);
+        assert!(!sessions.has_primary());
+    }
+}
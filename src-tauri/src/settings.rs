@@ -0,0 +1,166 @@
+use std::path::Path;
+use serde_json::Value;
+use tracing::{info, warn};
+use crate::data_types::Settings;
+use crate::error::AppError;
+
+/// 应用配置目录下的设置文件名
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// 读取设置文件；文件不存在或无法解析时使用默认设置
+///
+/// 解析失败的文件改名为 settings.json.bak 保留，之后保存设置不会覆盖它，便于手动修复。
+pub fn load(path: &Path) -> Settings {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Settings::default(),
+        Err(e) => {
+            warn!("⚠️ Failed to read settings {}: {}, using defaults", path.display(), e);
+            return Settings::default();
+        }
+    };
+    
+    match serde_json::from_str::<Settings>(&text).map_err(|e| e.to_string())
+        .map(without_unsaved_credentials)
+        .and_then(|settings| settings.validate().map(|_| settings).map_err(|e| e.to_string()))
+    {
+        Ok(settings) => {
+            info!("⚙️  Loaded settings from {}", path.display());
+            settings
+        }
+        Err(e) => {
+            let backup = backup_path(path);
+            match std::fs::rename(path, &backup) {
+                Ok(()) => warn!("⚠️ Invalid settings file {}: {}, moved to {} and using defaults",
+                                path.display(), e, backup.display()),
+                Err(rename_error) => warn!("⚠️ Invalid settings file {}: {}, using defaults (backup failed: {})",
+                                           path.display(), e, rename_error),
+            }
+            Settings::default()
+        }
+    }
+}
+
+/// 无法解析的设置文件的备份位置
+fn backup_path(path: &Path) -> std::path::PathBuf {
+    path.with_extension("json.bak")
+}
+
+/// 写入设置文件 - 先写临时文件再重命名，避免中途退出留下不完整的JSON
+pub fn save(path: &Path, settings: &Settings) -> Result<(), AppError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    
+    let text = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::Config(format!("Failed to serialize settings: {}", e)))?;
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, text)?;
+    std::fs::rename(&temp_path, path)?;
+    
+    Ok(())
+}
+
+/// 将部分更新合并到当前设置（JSON Merge Patch：对象递归合并，null 删除字段即恢复默认）
+pub fn merge(current: &Settings, patch: Value) -> Result<Settings, AppError> {
+    let mut value = serde_json::to_value(current)
+        .map_err(|e| AppError::Config(format!("Failed to serialize settings: {}", e)))?;
+    restore_credentials(&mut value, current);
+    merge_patch(&mut value, patch);
+    
+    let settings: Settings = serde_json::from_value(value)
+        .map_err(|e| AppError::Config(format!("Invalid settings: {}", e)))?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// 凭据不参与序列化，合并前补回，补丁未提及时保持不变
+fn restore_credentials(value: &mut Value, settings: &Settings) {
+    if let Some(password) = &settings.mqtt.password {
+        value["mqtt"]["password"] = Value::String(password.clone());
+    }
+    if let Some(secret_key) = &settings.upload.secret_key {
+        value["upload"]["secret_key"] = Value::String(secret_key.clone());
+    }
+}
+
+/// 设置文件中没有凭据：缺少密钥的S3上传先停用，重新输入密钥后再启用
+fn without_unsaved_credentials(mut settings: Settings) -> Settings {
+    if settings.upload.enabled && settings.upload.missing_credentials() {
+        warn!("⚠️ Upload credentials are not stored in the settings file, upload disabled until the secret key is entered again");
+        settings.upload.enabled = false;
+    }
+    if settings.mqtt.enabled && settings.mqtt.username.is_some() {
+        warn!("⚠️ MQTT password is not stored in the settings file, enter it again to authenticate with the broker");
+    }
+    settings
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(&key);
+            } else {
+                merge_patch(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_merge_save_and_load() {
+        let defaults = Settings::default();
+        let updated = merge(&defaults, json!({
+            "output_directory": "/data/eeg",
+            "notch_filter": { "enabled": true },
+        })).unwrap();
+        assert_eq!(updated.output_directory.as_deref(), Some("/data/eeg"));
+        assert!(updated.notch_filter.enabled);
+        assert_eq!(updated.fft_config, defaults.fft_config);
+        
+        // null 恢复默认，无效值被拒绝
+        let reset = merge(&updated, json!({ "output_directory": null })).unwrap();
+        assert_eq!(reset.output_directory, None);
+        assert!(merge(&updated, json!({ "mqtt": { "port": 0 } })).is_err());
+        
+        let dir = std::env::temp_dir().join(format!("cortexarray_settings_{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE);
+        assert_eq!(load(&path), defaults);
+        save(&path, &updated).unwrap();
+        assert_eq!(load(&path), updated);
+        
+        // 密钥不写入设置文件也不返回前端，合并其他字段时保留
+        let secret = merge(&updated, json!({
+            "upload": { "access_key": "AK", "secret_key": "SECRET" },
+            "mqtt": { "username": "lab", "password": "SECRET" },
+        })).unwrap();
+        assert!(!serde_json::to_string(&secret).unwrap().contains("SECRET"));
+        let merged = merge(&secret, json!({ "output_directory": "/data/other" })).unwrap();
+        assert_eq!(merged.upload.secret_key.as_deref(), Some("SECRET"));
+        assert_eq!(merged.mqtt.password.as_deref(), Some("SECRET"));
+        save(&path, &merged).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("SECRET"));
+        let loaded = load(&path);
+        assert_eq!((loaded.upload.secret_key, loaded.mqtt.password), (None, None));
+        
+        // 损坏的文件移到备份位置，随后的保存不会覆盖
+        std::fs::write(&path, "{ not json").unwrap();
+        assert_eq!(load(&path), defaults);
+        save(&path, &defaults).unwrap();
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), "{ not json");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use crate::data_types::*;
+use std::f64::consts::PI;
+
+/// 通道信号质量监测 - 在1秒窗口内累计每通道统计量，窗口结束时评分
+///
+/// 平线：标准差过小；贴轨：大量样本处于窗口极值（放大器饱和后数值被钳位）；
+/// 方差异常：相对所有通道中位数过大/过小；工频：Goertzel 估计的工频功率占总方差的比例。
+/// 窗口为整秒，50/60Hz 都是整数周期，直流偏置不会泄漏到工频估计中。
+pub struct QualityMonitor {
+    config: SignalQualityConfig,
+    window_len: usize,
+    line_coeff: f64,
+    line_frequency: f64,
+    channels: Vec<ChannelAccumulator>,
+    count: usize,
+}
+
+#[derive(Clone)]
+struct ChannelAccumulator {
+    sum: f64,
+    sum_sq: f64,
+    min: f64,
+    max: f64,
+    at_min: usize,
+    at_max: usize,
+    goertzel: (f64, f64),
+}
+
+impl ChannelAccumulator {
+    fn new() -> Self {
+        Self {
+            sum: 0.0,
+            sum_sq: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            at_min: 0,
+            at_max: 0,
+            goertzel: (0.0, 0.0),
+        }
+    }
+    
+    fn push(&mut self, value: f64, coeff: f64) {
+        self.sum += value;
+        self.sum_sq += value * value;
+        
+        if value < self.min {
+            self.min = value;
+            self.at_min = 1;
+        } else if value == self.min {
+            self.at_min += 1;
+        }
+        if value > self.max {
+            self.max = value;
+            self.at_max = 1;
+        } else if value == self.max {
+            self.at_max += 1;
+        }
+        
+        let (s1, s2) = self.goertzel;
+        self.goertzel = (value + coeff * s1 - s2, s1);
+    }
+}
+
+impl QualityMonitor {
+    pub fn new(config: SignalQualityConfig, sample_rate: f64, channels_count: usize, line_frequency: f64) -> Self {
+        let window_len = sample_rate.round().max(1.0) as usize;
+        let mut monitor = Self {
+            config,
+            window_len,
+            line_coeff: 0.0,
+            line_frequency: 0.0,
+            channels: vec![ChannelAccumulator::new(); channels_count],
+            count: 0,
+        };
+        monitor.set_line_frequency(line_frequency);
+        monitor
+    }
+    
+    /// 工频（50/60Hz）变化时调用，当前窗口重新开始
+    pub fn set_line_frequency(&mut self, line_frequency: f64) {
+        if line_frequency == self.line_frequency {
+            return;
+        }
+        self.line_frequency = line_frequency;
+        self.line_coeff = 2.0 * (2.0 * PI * line_frequency / self.window_len as f64).cos();
+        self.reset();
+    }
+    
+    /// 加入一个原始样本，窗口满时返回各通道质量
+    pub fn push(&mut self, sample: &EegSample) -> Option<ChannelQualityUpdate> {
+        if sample.channels.len() != self.channels.len() {
+            return None;
+        }
+        
+        for (accumulator, &value) in self.channels.iter_mut().zip(&sample.channels) {
+            accumulator.push(value, self.line_coeff);
+        }
+        self.count += 1;
+        
+        if self.count < self.window_len {
+            return None;
+        }
+        
+        let update = ChannelQualityUpdate {
+            timestamp: sample.timestamp,
+            channels: self.evaluate(),
+        };
+        self.reset();
+        Some(update)
+    }
+    
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(|accumulator| *accumulator = ChannelAccumulator::new());
+        self.count = 0;
+    }
+    
+    fn evaluate(&self) -> Vec<ChannelQuality> {
+        let n = self.count as f64;
+        let std_devs: Vec<f64> = self.channels.iter()
+            .map(|accumulator| {
+                let mean = accumulator.sum / n;
+                (accumulator.sum_sq / n - mean * mean).max(0.0).sqrt()
+            })
+            .collect();
+        
+        let median_std = median(&std_devs);
+        let log_ratio_limit = self.config.variance_ratio.ln();
+        
+        self.channels.iter().zip(&std_devs).enumerate().map(|(index, (accumulator, &std_dev))| {
+            let mut issues = Vec::new();
+            let mut score: f64 = 1.0;
+            
+            // 工频功率：|X(f)|² · 2 / N² 即该频率正弦分量的功率
+            let (s1, s2) = accumulator.goertzel;
+            let line_power = (s1 * s1 + s2 * s2 - self.line_coeff * s1 * s2) * 2.0 / (n * n);
+            let variance = std_dev * std_dev;
+            let line_noise_ratio = if variance > 0.0 { (line_power / variance).min(1.0) } else { 0.0 };
+            
+            if std_dev < self.config.flat_std {
+                issues.push(QualityIssue::Flatline);
+                score = 0.0;
+            } else {
+                let clipped = (accumulator.at_min.max(accumulator.at_max)) as f64 / n;
+                if clipped > self.config.clip_fraction {
+                    issues.push(QualityIssue::Railing);
+                }
+                score = score.min(1.0 - (clipped / self.config.clip_fraction).min(1.0));
+                
+                if median_std > 0.0 {
+                    let log_ratio = (std_dev / median_std).ln();
+                    if log_ratio > log_ratio_limit {
+                        issues.push(QualityIssue::HighVariance);
+                    } else if log_ratio < -log_ratio_limit {
+                        issues.push(QualityIssue::LowVariance);
+                    }
+                    score = score.min(1.0 - (log_ratio.abs() / log_ratio_limit).min(1.0));
+                }
+                
+                if line_noise_ratio > self.config.line_noise_ratio {
+                    issues.push(QualityIssue::LineNoise);
+                }
+                score = score.min(1.0 - line_noise_ratio);
+            }
+            
+            ChannelQuality {
+                channel_index: index as u32,
+                score: score.clamp(0.0, 1.0),
+                bad: !issues.is_empty(),
+                issues,
+                std_dev,
+                line_noise_ratio,
+            }
+        }).collect()
+    }
+}
+
+pub(crate) fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_flat_railing_and_line_noise() {
+        let sample_rate = 250.0;
+        let mut monitor = QualityMonitor::new(SignalQualityConfig::default(), sample_rate, 4, 50.0);
+        
+        let mut update = None;
+        for i in 0..250 {
+            let t = i as f64 / sample_rate;
+            // 伪随机的宽带"EEG"
+            let eeg = 20.0 * ((i * 7919 % 101) as f64 / 101.0 - 0.5) + 5.0 * (2.0 * PI * 10.0 * t).sin();
+            let channels = vec![
+                eeg,                                        // 正常
+                3.0,                                        // 平线
+                (eeg * 50.0).clamp(-100.0, 100.0),          // 贴轨
+                eeg * 0.1 + 40.0 * (2.0 * PI * 50.0 * t).sin(), // 工频
+            ];
+            update = monitor.push(&EegSample { timestamp: t, channels, sample_id: i }).or(update);
+        }
+        
+        let channels = update.unwrap().channels;
+        assert!(!channels[0].bad, "{:?}", channels[0]);
+        assert_eq!(channels[1].issues, vec![QualityIssue::Flatline]);
+        assert!(channels[2].issues.contains(&QualityIssue::Railing));
+        assert!(channels[3].issues.contains(&QualityIssue::LineNoise));
+        assert!(channels[3].line_noise_ratio > 0.9);
+    }
+}
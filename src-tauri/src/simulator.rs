@@ -0,0 +1,153 @@
+use crate::data_types::*;
+use crate::stream_source::{MockSource, MockStream};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// 10-20系统电极，按由前到后排列
+const ELECTRODES_10_20: [&str; 19] = [
+    "Fp1", "Fp2", "F7", "F3", "Fz", "F4", "F8", "T3", "C3", "Cz",
+    "C4", "T4", "T5", "P3", "Pz", "P4", "T6", "O1", "O2",
+];
+
+// α幅值包络的调制频率（节律时强时弱）
+const ALPHA_MODULATION_HZ: f64 = 0.2;
+
+// 眨眼：峰值出现在事件开始后的时间，及高斯宽度（秒）
+const BLINK_PEAK_SECONDS: f64 = 0.15;
+const BLINK_WIDTH_SECONDS: f64 = 0.07;
+
+const MUSCLE_BURST_SECONDS: f64 = 0.5;
+
+// 随机数用途区分
+const SALT_PHASE: u64 = 1;
+const SALT_NOISE: u64 = 2;
+const SALT_BLINK: u64 = 3;
+const SALT_MUSCLE: u64 = 4;
+const SALT_MUSCLE_NOISE: u64 = 5;
+
+/// 通道标签：不超过19通道时从10-20电极中由前到后均匀选取，否则为 ChN
+fn channel_labels(channels_count: u32) -> Vec<String> {
+    let n = channels_count as usize;
+    match n {
+        1 => vec!["Cz".to_string()],
+        2..=19 => (0..n)
+            .map(|ch| ELECTRODES_10_20[(ch * (ELECTRODES_10_20.len() - 1) + (n - 1) / 2) / (n - 1)].to_string())
+            .collect(),
+        _ => StreamInfo::default_channel_labels(channels_count),
+    }
+}
+
+/// splitmix64
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// 由种子与位置确定的 [0, 1) 均匀随机数
+fn unit(seed: u64, a: u64, b: u64, salt: u64) -> f64 {
+    let hash = mix(seed ^ mix(a ^ mix(b ^ mix(salt))));
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// 标准正态随机数（Box-Muller）
+fn gaussian(seed: u64, a: u64, b: u64, salt: u64) -> f64 {
+    let u1 = unit(seed, a, b, salt).max(f64::MIN_POSITIVE);
+    let u2 = unit(seed, a, b, salt.wrapping_add(0x100));
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// 按每分钟次数排布的伪迹事件：时间轴分为等长时隙，每个时隙内一个事件，开始时刻由种子决定。
+/// 返回 t 时刻所在事件的 (事件序号, 距事件开始的秒数)
+fn event_at(seed: u64, salt: u64, per_minute: f64, t: f64, duration: f64) -> Option<(u64, f64)> {
+    if per_minute <= 0.0 {
+        return None;
+    }
+    let slot_seconds = 60.0 / per_minute;
+    let slot = (t / slot_seconds).floor().max(0.0) as u64;
+    
+    // 事件可能从上一个时隙延续过来
+    [Some(slot), slot.checked_sub(1)].into_iter().flatten().find_map(|slot| {
+        let onset = (slot as f64 + 0.2 + 0.6 * unit(seed, slot, 0, salt)) * slot_seconds;
+        let elapsed = t - onset;
+        (0.0..duration).contains(&elapsed).then_some((slot, elapsed))
+    })
+}
+
+/// 模拟信号：第 index 个样本、通道 ch 的值（微伏），只由配置与 (index, ch) 决定
+fn sample_value(config: &SimulatorConfig, index: u64, ch: usize) -> f64 {
+    let t = index as f64 / config.sample_rate;
+    let seed = config.seed;
+    let n = config.channels_count as usize;
+    // 通道由前（0）到后（1）的位置
+    let position = if n > 1 { ch as f64 / (n - 1) as f64 } else { 0.5 };
+    let phase = 2.0 * PI * unit(seed, ch as u64, 0, SALT_PHASE);
+    
+    let alpha_envelope = 0.75 + 0.25 * (2.0 * PI * ALPHA_MODULATION_HZ * t + phase).sin();
+    let alpha = config.alpha_amplitude * (0.3 + 0.7 * position) * alpha_envelope
+        * (2.0 * PI * config.alpha_frequency * t + phase).sin();
+    let beta = config.beta_amplitude * (1.0 - 0.6 * position)
+        * (2.0 * PI * config.beta_frequency * t + 1.7 * phase).sin();
+    let noise = config.noise_amplitude * gaussian(seed, index, ch as u64, SALT_NOISE);
+    let line = config.line_noise_amplitude * (2.0 * PI * config.line_frequency * t).sin();
+    
+    let blink = event_at(seed, SALT_BLINK, config.blinks_per_minute, t, 2.0 * BLINK_PEAK_SECONDS)
+        .map_or(0.0, |(_, elapsed)| {
+            let shape = ((elapsed - BLINK_PEAK_SECONDS) / BLINK_WIDTH_SECONDS).powi(2);
+            config.blink_amplitude * (-4.0 * position).exp() * (-shape).exp()
+        });
+    let muscle = event_at(seed, SALT_MUSCLE, config.muscle_bursts_per_minute, t, MUSCLE_BURST_SECONDS)
+        .map_or(0.0, |(burst, elapsed)| {
+            // 每次爆发随机选择强弱不同的通道
+            let weight = unit(seed, burst, ch as u64, SALT_MUSCLE);
+            let window = (PI * elapsed / MUSCLE_BURST_SECONDS).sin();
+            config.muscle_amplitude * weight * window * gaussian(seed, index, ch as u64, SALT_MUSCLE_NOISE)
+        });
+    
+    alpha + beta + noise + line + blink + muscle
+}
+
+/// 模拟器流
+pub fn stream(config: &SimulatorConfig) -> MockStream {
+    let signal_config = config.clone();
+    let mut stream = MockStream::new(
+        &config.name, "EEG", config.channels_count, config.sample_rate,
+        Arc::new(move |index, ch| sample_value(&signal_config, index, ch)),
+    );
+    stream.info.source_id = format!("simulator_{}", config.seed);
+    stream.channel_labels = channel_labels(config.channels_count);
+    stream
+}
+
+/// 只含模拟器流的数据源，可直接交给 LslManager::with_source
+pub fn source(config: &SimulatorConfig) -> MockSource {
+    MockSource::new().with_stream(stream(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_simulator_is_deterministic_with_artifacts() {
+        let config = SimulatorConfig { blinks_per_minute: 30.0, muscle_bursts_per_minute: 0.0, ..SimulatorConfig::default() };
+        config.validate().unwrap();
+        assert_eq!(channel_labels(8), vec!["Fp1", "F3", "F4", "C3", "C4", "P3", "P4", "O2"]);
+        
+        let stream = stream(&config);
+        let again = super::stream(&config);
+        assert_eq!(stream.sample(1234), again.sample(1234));
+        let other_seed = super::stream(&SimulatorConfig { seed: 1, ..config.clone() });
+        assert_ne!(stream.sample(1234), other_seed.sample(1234));
+        
+        // 2秒一个眨眼：前部通道出现大幅偏转，后部通道基本不受影响
+        let samples: Vec<Vec<f64>> = (0..(config.sample_rate * 10.0) as u64).map(|index| stream.sample(index)).collect();
+        let peak = |ch: usize| samples.iter().map(|sample| sample[ch].abs()).fold(0.0, f64::max);
+        assert!(peak(0) > 80.0, "frontal peak {}", peak(0));
+        assert!(peak(7) < 60.0, "posterior peak {}", peak(7));
+        
+        assert!(SimulatorConfig { alpha_frequency: 200.0, ..SimulatorConfig::default() }.validate().is_err());
+        assert!(SimulatorConfig { channels_count: 0, ..SimulatorConfig::default() }.validate().is_err());
+    }
+}
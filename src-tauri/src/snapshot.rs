@@ -0,0 +1,135 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// 冻结的最近N秒原始数据与当前频谱，供问题报告或实验记录附带确切数据
+pub struct Snapshot {
+    pub stream_info: StreamInfo,
+    pub requested_seconds: f64,
+    pub samples: Vec<EegSample>,        // 陷波前的原始样本
+    pub spectrum: Option<SnapshotSpectrum>,
+    pub notch_filter: NotchFilterConfig,
+    pub filters: FilterConfig,
+}
+
+impl Snapshot {
+    /// 在 directory 下创建 snapshot_<时间>_<流名>/，写入 raw.csv、spectra.csv（有频谱时）和 snapshot.json，
+    /// 返回该目录
+    pub fn write_bundle(&self, directory: &Path) -> Result<PathBuf, AppError> {
+        let captured_at = chrono::Utc::now();
+        let stream: String = self.stream_info.name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let bundle = directory.join(format!("snapshot_{}_{}", captured_at.format("%Y%m%d_%H%M%S%.3f"), stream));
+        std::fs::create_dir_all(&bundle)?;
+        
+        let labels = crate::recorder::EdfRecorder::channel_labels(&self.stream_info);
+        let mut files = vec!["raw.csv".to_string()];
+        self.write_raw(&bundle.join("raw.csv"), &labels)?;
+        if let Some(spectrum) = &self.spectrum {
+            files.push("spectra.csv".to_string());
+            write_spectrum(&bundle.join("spectra.csv"), spectrum, &labels)?;
+        }
+        files.push("snapshot.json".to_string());
+        
+        let raw_seconds = match (self.samples.first(), self.samples.last()) {
+            (Some(first), Some(last)) => last.timestamp - first.timestamp + 1.0 / self.stream_info.sample_rate,
+            _ => 0.0,
+        };
+        let info = SnapshotInfo {
+            captured_at,
+            stream: self.stream_info.clone(),
+            requested_seconds: self.requested_seconds,
+            raw_seconds,
+            raw_samples: self.samples.len(),
+            spectrum_timestamp: self.spectrum.as_ref().map(|spectrum| spectrum.timestamp),
+            notch_filter: self.notch_filter,
+            filters: self.filters,
+            files,
+        };
+        let json = serde_json::to_string_pretty(&info)
+            .map_err(|e| AppError::Recording(format!("Failed to serialize snapshot info: {}", e)))?;
+        std::fs::write(bundle.join("snapshot.json"), json)?;
+        
+        info!("📸 Snapshot saved to {} ({} samples)", bundle.display(), self.samples.len());
+        Ok(bundle)
+    }
+    
+    /// 每行一个样本：timestamp, 各通道
+    fn write_raw(&self, path: &Path, labels: &[String]) -> Result<(), AppError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "timestamp,{}", csv_header(labels))?;
+        for sample in &self.samples {
+            let values: Vec<String> = sample.channels.iter().map(f64::to_string).collect();
+            writeln!(file, "{},{}", sample.timestamp, values.join(","))?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// 每行一个频点：frequency_hz, 各通道幅值
+fn write_spectrum(path: &Path, spectrum: &SnapshotSpectrum, labels: &[String]) -> Result<(), AppError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "frequency_hz,{}", csv_header(labels))?;
+    for (bin, frequency) in spectrum.frequency_bins.iter().enumerate() {
+        let values: Vec<String> = spectrum.spectra.iter()
+            .map(|channel| channel.get(bin).map(f32::to_string).unwrap_or_default())
+            .collect();
+        writeln!(file, "{},{}", frequency, values.join(","))?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// 通道标签中的逗号和引号会破坏CSV列，替换为下划线
+fn csv_header(labels: &[String]) -> String {
+    labels.iter().map(|label| label.replace([',', '"'], "_")).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_snapshot_bundle_files() {
+        let directory = std::env::temp_dir().join(format!("snapshot_test_{}", std::process::id()));
+        let snapshot = Snapshot {
+            stream_info: StreamInfo {
+                name: "Test EEG".to_string(),
+                stream_type: "EEG".to_string(),
+                channels_count: 2,
+                sample_rate: 100.0,
+                is_connected: true,
+                source_id: "test_device".to_string(),
+                channel_labels: vec!["Fz".to_string(), "Cz".to_string()],
+                effective_sample_rate: None,
+            },
+            requested_seconds: 5.0,
+            samples: (0..3).map(|i| EegSample { timestamp: i as f64 / 100.0, channels: vec![i as f64, -1.5], sample_id: i }).collect(),
+            spectrum: Some(SnapshotSpectrum {
+                timestamp: 0.02,
+                frequency_bins: vec![1.0, 2.0],
+                spectra: vec![vec![0.5, 0.25], vec![1.0, 2.0]],
+            }),
+            notch_filter: NotchFilterConfig::default(),
+            filters: FilterConfig::default(),
+        };
+        
+        let bundle = snapshot.write_bundle(&directory).unwrap();
+        let raw = std::fs::read_to_string(bundle.join("raw.csv")).unwrap();
+        let spectra = std::fs::read_to_string(bundle.join("spectra.csv")).unwrap();
+        let info: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(bundle.join("snapshot.json")).unwrap()).unwrap();
+        let _ = std::fs::remove_dir_all(&directory);
+        
+        assert!(bundle.file_name().unwrap().to_str().unwrap().ends_with("_Test_EEG"));
+        assert_eq!(raw.lines().collect::<Vec<_>>(), vec!["timestamp,Fz,Cz", "0,0,-1.5", "0.01,1,-1.5", "0.02,2,-1.5"]);
+        assert_eq!(spectra.lines().nth(2), Some("2,0.25,2"));
+        assert_eq!(info["raw_samples"], 3);
+        assert!((info["raw_seconds"].as_f64().unwrap() - 0.03).abs() < 1e-9);
+        assert_eq!(info["files"].as_array().unwrap().len(), 3);
+    }
+}
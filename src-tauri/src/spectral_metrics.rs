@@ -0,0 +1,94 @@
+use crate::band_power::power_in_range;
+use crate::data_types::*;
+
+/// 从单边幅值谱计算单通道的个体alpha峰值频率、谱边缘频率与总功率
+///
+/// magnitudes[k] 对应频率 k * freq_resolution，功率取幅值平方（与频带功率一致）。
+pub fn compute_spectral_metrics(
+    channel_index: u32,
+    magnitudes: &[f64],
+    freq_resolution: f64,
+    config: &SpectralMetricsConfig,
+) -> ChannelSpectralMetrics {
+    ChannelSpectralMetrics {
+        channel_index,
+        peak_alpha_hz: peak_frequency(magnitudes, freq_resolution, config.alpha_min_hz, config.alpha_max_hz),
+        spectral_edge_hz: edge_frequency(magnitudes, freq_resolution, config),
+        total_power: power_in_range(magnitudes, freq_resolution, config.min_hz, config.max_hz),
+    }
+}
+
+/// 范围内最高的局部功率峰值，按抛物线插值细化到bin之间
+fn peak_frequency(magnitudes: &[f64], freq_resolution: f64, low_hz: f64, high_hz: f64) -> Option<f64> {
+    if freq_resolution <= 0.0 {
+        return None;
+    }
+    
+    let power = |k: usize| magnitudes[k] * magnitudes[k];
+    let first_bin = ((low_hz / freq_resolution).ceil() as usize).max(1);
+    let last_bin = ((high_hz / freq_resolution).floor() as usize).min(magnitudes.len().saturating_sub(2));
+    
+    let peak = (first_bin..=last_bin)
+        .filter(|&k| power(k) > power(k - 1) && power(k) >= power(k + 1))
+        .max_by(|&a, &b| power(a).total_cmp(&power(b)))?;
+    
+    let (left, center, right) = (power(peak - 1), power(peak), power(peak + 1));
+    let curvature = left - 2.0 * center + right;
+    let offset = if curvature < 0.0 { 0.5 * (left - right) / curvature } else { 0.0 };
+    
+    Some((peak as f64 + offset) * freq_resolution)
+}
+
+/// 累计功率达到 edge_fraction 的频率，在bin内线性插值
+fn edge_frequency(magnitudes: &[f64], freq_resolution: f64, config: &SpectralMetricsConfig) -> Option<f64> {
+    let total = power_in_range(magnitudes, freq_resolution, config.min_hz, config.max_hz);
+    if total <= 0.0 {
+        return None;
+    }
+    
+    let target = total * config.edge_fraction;
+    let first_bin = (config.min_hz / freq_resolution).ceil() as usize;
+    let mut cumulative = 0.0;
+    
+    for (k, &magnitude) in magnitudes.iter().enumerate().skip(first_bin) {
+        let power = magnitude * magnitude;
+        if cumulative + power >= target && power > 0.0 {
+            // 每个bin覆盖 [k-0.5, k+0.5) 个分辨率
+            let fraction = (target - cumulative) / power;
+            let edge = (k as f64 - 0.5 + fraction) * freq_resolution;
+            return Some(edge.clamp(config.min_hz, config.max_hz));
+        }
+        cumulative += power;
+    }
+    
+    Some(config.max_hz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_alpha_peak_and_edge_frequency() {
+        // 0.5Hz分辨率，峰值在10Hz与10.5Hz之间偏向10Hz
+        let mut magnitudes = vec![0.0; 129];
+        magnitudes[20] = 4.0;
+        magnitudes[21] = 3.0;
+        magnitudes[19] = 2.0;
+        
+        let config = SpectralMetricsConfig::default();
+        let metrics = compute_spectral_metrics(3, &magnitudes, 0.5, &config);
+        let peak = metrics.peak_alpha_hz.unwrap();
+        assert!(peak > 10.0 && peak < 10.25, "peak {}", peak);
+        assert_eq!(metrics.total_power, 16.0 + 9.0 + 4.0);
+        
+        // 平坦谱（1-40Hz）的SEF95约为 1 + 0.95·39
+        let flat = vec![1.0; 129];
+        let edge = compute_spectral_metrics(0, &flat, 0.5, &config).spectral_edge_hz.unwrap();
+        assert!((edge - (1.0 + 0.95 * 39.0)).abs() < 0.5, "edge {}", edge);
+        
+        // 无局部峰值（单调下降）时不报告alpha峰
+        let falling: Vec<f64> = (0..129).map(|k| 100.0 / (k + 1) as f64).collect();
+        assert_eq!(compute_spectral_metrics(0, &falling, 0.5, &config).peak_alpha_hz, None);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+use crate::data_types::*;
+use crate::error::AppError;
+
+/// 频谱图的一列：一次FFT的全部通道
+struct SpectrogramColumn {
+    timestamp: f64,
+    spectra: Vec<Vec<f32>>,
+}
+
+/// 频谱图环形缓冲 - 由FFT线程写入，按列数限长
+///
+/// 列按数据时间限速（FFT跳步快于列间隔时跳过多余的帧）。
+/// 输出频点或通道数变化时清空，保证每列形状一致。
+pub struct SpectrogramBuffer {
+    config: SpectrogramConfig,
+    frequency_bins: Vec<f64>,
+    columns: VecDeque<SpectrogramColumn>,
+    capacity: usize,
+}
+
+impl SpectrogramBuffer {
+    pub fn new(config: SpectrogramConfig) -> Self {
+        let capacity = Self::capacity_for(config);
+        Self {
+            config,
+            frequency_bins: Vec::new(),
+            columns: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+    
+    fn capacity_for(config: SpectrogramConfig) -> usize {
+        ((config.seconds * config.columns_per_second).ceil() as usize).max(1)
+    }
+    
+    pub fn config(&self) -> SpectrogramConfig {
+        self.config
+    }
+    
+    /// 调整深度与列速率，缩短时丢弃最旧的列
+    pub fn resize(&mut self, config: SpectrogramConfig) {
+        self.config = config;
+        self.capacity = Self::capacity_for(config);
+        let excess = self.columns.len().saturating_sub(self.capacity);
+        self.columns.drain(..excess);
+    }
+    
+    /// 加入一帧频谱（spectra[通道][频点]），未到列间隔时忽略
+    pub fn push(&mut self, timestamp: f64, frequency_bins: &[f64], spectra: &[Vec<f64>]) {
+        let layout_changed = self.frequency_bins != frequency_bins
+            || self.columns.back().is_some_and(|column| column.spectra.len() != spectra.len());
+        if layout_changed {
+            self.frequency_bins = frequency_bins.to_vec();
+            self.columns.clear();
+        }
+        
+        let interval = 1.0 / self.config.columns_per_second;
+        if self.columns.back().is_some_and(|column| timestamp - column.timestamp < interval) {
+            return;
+        }
+        
+        if self.columns.len() == self.capacity {
+            self.columns.pop_front();
+        }
+        self.columns.push_back(SpectrogramColumn {
+            timestamp,
+            spectra: spectra.iter()
+                .map(|spectrum| spectrum.iter().map(|&v| v as f32).collect())
+                .collect(),
+        });
+    }
+    
+    /// 最新一列的全部通道频谱，缓冲为空时为 None
+    pub fn latest(&self) -> Option<SnapshotSpectrum> {
+        self.columns.back().map(|column| SnapshotSpectrum {
+            timestamp: column.timestamp,
+            frequency_bins: self.frequency_bins.clone(),
+            spectra: column.spectra.clone(),
+        })
+    }
+    
+    /// 单通道最近 seconds 秒的频谱图（相对最新一列）
+    pub fn segment(&self, channel: u32, seconds: f64) -> Result<SpectrogramSegment, AppError> {
+        let channels = self.columns.back().map_or(0, |column| column.spectra.len());
+        if channel as usize >= channels && !self.columns.is_empty() {
+            return Err(AppError::Config(format!("Channel {} out of range (0-{})", channel, channels - 1)));
+        }
+        
+        let newest = self.columns.back().map_or(0.0, |column| column.timestamp);
+        let (timestamps, columns) = self.columns.iter()
+            .filter(|column| newest - column.timestamp < seconds)
+            .map(|column| (column.timestamp, column.spectra[channel as usize].clone()))
+            .unzip();
+        
+        Ok(SpectrogramSegment {
+            channel,
+            frequency_bins: self.frequency_bins.clone(),
+            timestamps,
+            columns,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_rate_limit_depth_and_layout_reset() {
+        let config = SpectrogramConfig { seconds: 10.0, columns_per_second: 2.0 };
+        let mut buffer = SpectrogramBuffer::new(config);
+        let bins = [1.0, 2.0];
+        
+        // 每0.1秒一帧FFT，只保留每0.5秒一列，最多20列
+        for i in 0..200 {
+            let t = i as f64 * 0.1;
+            buffer.push(t, &bins, &[vec![t, 0.0], vec![0.0, t]]);
+        }
+        let all = buffer.segment(1, 100.0).unwrap();
+        assert_eq!(all.columns.len(), 20);
+        assert!((all.timestamps[19] - 19.5).abs() < 1e-9);
+        assert_eq!(all.columns[19], vec![0.0, 19.5]);
+        
+        let recent = buffer.segment(0, 2.0).unwrap();
+        assert_eq!(recent.timestamps.len(), 4);
+        assert!(buffer.segment(2, 1.0).is_err());
+        
+        // 频点变化后清空重建
+        buffer.push(20.0, &[1.0, 2.0, 3.0], &[vec![0.0; 3]]);
+        assert_eq!(buffer.segment(0, 100.0).unwrap().columns.len(), 1);
+    }
+}
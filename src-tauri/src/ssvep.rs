@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use nalgebra::{DMatrix, DVector};
+use tracing::warn;
+use crate::data_types::*;
+
+/// SSVEP检测 - 典型相关分析（CCA）
+///
+/// 每个目标频率构造 sin/cos(2π·h·f·t) 参考信号（h = 1..harmonics，超过奈奎斯特频率的谐波跳过），
+/// 取EEG窗口与参考信号的最大典型相关作为该目标的得分。窗口长度固定，参考信号的正交基只在构造时计算一次。
+pub struct SsvepDetector {
+    config: SsvepConfig,
+    input_channels: usize,
+    channel_indices: Vec<usize>,
+    channel_labels: Vec<String>,
+    window_samples: usize,
+    windows: Vec<VecDeque<f64>>,
+    references: Vec<Option<DMatrix<f64>>>,  // 各目标参考信号的正交基
+    update_every: usize,
+    since_update: usize,
+}
+
+impl SsvepDetector {
+    pub fn new(config: SsvepConfig, sample_rate: f64, channel_labels: Vec<String>) -> Self {
+        let window_samples = ((config.window_seconds * sample_rate).round() as usize).max(2);
+        
+        let (channel_indices, selected_labels): (Vec<usize>, Vec<String>) = channel_labels.iter()
+            .enumerate()
+            .filter(|(_, label)| config.channels.is_empty()
+                || config.channels.iter().any(|name| name.eq_ignore_ascii_case(label)))
+            .map(|(index, label)| (index, label.clone()))
+            .unzip();
+        if channel_indices.is_empty() {
+            warn!("⚠️ SSVEP: none of the channels {:?} are present", config.channels);
+        }
+        
+        let nyquist = sample_rate / 2.0;
+        let references = config.target_frequencies.iter()
+            .map(|&frequency| {
+                let columns: Vec<DVector<f64>> = (1..=config.harmonics)
+                    .map(|harmonic| harmonic as f64 * frequency)
+                    .filter(|&harmonic_hz| harmonic_hz < nyquist)
+                    .flat_map(|harmonic_hz| {
+                        let phase = |n: usize| TAU * harmonic_hz * n as f64 / sample_rate;
+                        [
+                            DVector::from_fn(window_samples, |n, _| phase(n).sin()),
+                            DVector::from_fn(window_samples, |n, _| phase(n).cos()),
+                        ]
+                    })
+                    .collect();
+                orthonormal_basis(columns)
+            })
+            .collect();
+        
+        Self {
+            windows: vec![VecDeque::with_capacity(window_samples + 1); channel_indices.len()],
+            input_channels: channel_labels.len(),
+            channel_indices,
+            channel_labels: selected_labels,
+            window_samples,
+            references,
+            update_every: ((config.update_interval_ms as f64 / 1000.0 * sample_rate) as usize).max(1),
+            since_update: 0,
+            config,
+        }
+    }
+    
+    pub fn channel_labels(&self) -> &[String] {
+        &self.channel_labels
+    }
+    
+    /// 加入一个样本（全部显示通道），窗口已满且到推送间隔时返回判定结果
+    pub fn push(&mut self, timestamp: f64, channels: &[f64]) -> Option<SsvepDetection> {
+        if channels.len() != self.input_channels || self.windows.is_empty() {
+            return None;
+        }
+        
+        for (window, &index) in self.windows.iter_mut().zip(&self.channel_indices) {
+            if window.len() == self.window_samples {
+                window.pop_front();
+            }
+            window.push_back(channels[index]);
+        }
+        
+        self.since_update += 1;
+        if self.since_update < self.update_every || self.windows[0].len() < self.window_samples {
+            return None;
+        }
+        self.since_update = 0;
+        
+        let eeg_basis = orthonormal_basis(self.windows.iter()
+            .map(|window| DVector::from_iterator(window.len(), window.iter().copied()))
+            .collect());
+        
+        let scores: Vec<SsvepScore> = self.config.target_frequencies.iter()
+            .zip(&self.references)
+            .map(|(&frequency, reference)| {
+                let correlation = match (&eeg_basis, reference) {
+                    // 两组正交基之间的奇异值即典型相关系数
+                    (Some(x), Some(y)) => (x.transpose() * y).singular_values().max().clamp(0.0, 1.0),
+                    _ => 0.0,
+                };
+                SsvepScore { frequency, correlation }
+            })
+            .collect();
+        
+        let mut ranked: Vec<&SsvepScore> = scores.iter().collect();
+        ranked.sort_by(|a, b| b.correlation.total_cmp(&a.correlation));
+        let best = ranked[0];
+        let second = ranked.get(1).map_or(0.0, |score| score.correlation);
+        
+        Some(SsvepDetection {
+            timestamp,
+            window_seconds: self.config.window_seconds,
+            channel_labels: self.channel_labels.clone(),
+            detected_hz: (best.correlation >= self.config.min_correlation).then_some(best.frequency),
+            confidence: if best.correlation > 0.0 { (best.correlation - second) / best.correlation } else { 0.0 },
+            scores,
+        })
+    }
+}
+
+/// 去均值后各列张成空间的正交基（常数列被丢弃），没有有效列时返回 None
+fn orthonormal_basis(columns: Vec<DVector<f64>>) -> Option<DMatrix<f64>> {
+    let centered: Vec<DVector<f64>> = columns.into_iter()
+        .map(|column| column.add_scalar(-column.mean()))
+        .filter(|column| column.norm() > 1e-9)
+        .collect();
+    if centered.is_empty() {
+        return None;
+    }
+    Some(DMatrix::from_columns(&centered).qr().q())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_detects_stimulus_frequency() {
+        let sample_rate = 250.0;
+        let config = SsvepConfig {
+            enabled: true,
+            target_frequencies: vec![10.0, 12.0, 15.0],
+            channels: vec!["O1".to_string(), "oz".to_string()],
+            ..SsvepConfig::default()
+        };
+        let labels = vec!["Fz".to_string(), "O1".to_string(), "Oz".to_string()];
+        let mut detector = SsvepDetector::new(config, sample_rate, labels);
+        assert_eq!(detector.channel_labels(), ["O1", "Oz"]);
+        
+        let mut seed = 5u64;
+        let mut noise = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * 10.0
+        };
+        
+        // O1/Oz 含12Hz响应（不同相位），Fz 只有大幅噪声（不参与检测）
+        let mut detections = Vec::new();
+        for n in 0..750 {
+            let t = n as f64 / sample_rate;
+            let sample = [
+                noise() * 10.0,
+                3.0 * (TAU * 12.0 * t + 0.4).sin() + noise(),
+                2.0 * (TAU * 12.0 * t + 1.1).sin() + noise(),
+            ];
+            detections.extend(detector.push(t, &sample));
+        }
+        
+        // 窗口满（2秒）后每秒一次
+        assert_eq!(detections.len(), 2);
+        let detection = detections.last().unwrap();
+        assert_eq!(detection.detected_hz, Some(12.0));
+        assert!(detection.scores[1].correlation > 0.5, "scores {:?}", detection.scores);
+        assert!(detection.scores[0].correlation < 0.3 && detection.scores[2].correlation < 0.3);
+        assert!(detection.confidence > 0.4);
+    }
+}
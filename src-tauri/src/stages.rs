@@ -0,0 +1,360 @@
+use crate::artifacts::ArtifactDetector;
+use crate::data_types::*;
+use crate::dsp::{FilterChain, NotchFilterBank};
+use crate::error::AppError;
+use crate::montage::Montage;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// 阶段处理的数据块 - 显示路径上按时间排序的样本，通道标签随改变通道的阶段更新
+pub struct StageBatch {
+    pub channel_labels: Vec<String>,
+    pub samples: Vec<EegSample>,
+}
+
+/// 可组合的处理阶段 - 原地处理一个数据块，返回本块产生的指标（无则为空）
+pub trait ProcessingStage: Send {
+    fn name(&self) -> &str;
+    
+    fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric>;
+    
+    /// 输出通道标签，改变通道的阶段（导联等）需覆盖，后续阶段按此构建
+    fn output_labels(&self, input: &[String]) -> Vec<String> {
+        input.to_vec()
+    }
+}
+
+/// 自定义阶段构造函数 - 参数为进入该阶段时的流信息（通道为当前显示路径通道）与配置中的 params
+pub type StageConstructor = Arc<
+    dyn Fn(&StreamInfo, &serde_json::Value) -> Result<Box<dyn ProcessingStage>, AppError> + Send + Sync
+>;
+
+/// 阶段名称只允许字母、数字、下划线与连字符（插件阶段名称用于事件名）
+pub(crate) fn is_valid_stage_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// 自定义阶段注册表 - 进程级共享，启动时注册，配置中以 StageSpec::Custom 按名称引用
+///
+/// 插件注册的阶段名称带命名空间 `<插件>/<阶段>`，见 plugins 模块
+pub struct StageRegistry {
+    constructors: HashMap<String, StageConstructor>,
+}
+
+impl StageRegistry {
+    pub fn new() -> Self {
+        Self { constructors: HashMap::new() }
+    }
+    
+    /// 获取全局注册表
+    pub fn global() -> &'static RwLock<StageRegistry> {
+        static REGISTRY: OnceLock<RwLock<StageRegistry>> = OnceLock::new();
+        REGISTRY.get_or_init(|| RwLock::new(StageRegistry::new()))
+    }
+    
+    pub fn register(&mut self, name: &str, constructor: StageConstructor) -> Result<(), AppError> {
+        if !is_valid_stage_name(name) {
+            return Err(AppError::Config(format!("Invalid processing stage name '{}'", name)));
+        }
+        self.insert(name.to_string(), constructor)
+    }
+    
+    /// 以 `<插件>/<阶段>` 注册插件阶段
+    pub(crate) fn register_plugin_stage(&mut self, plugin: &str, name: &str, constructor: StageConstructor) -> Result<(), AppError> {
+        if !is_valid_stage_name(plugin) || !is_valid_stage_name(name) {
+            return Err(AppError::Plugin(format!("Invalid plugin stage name '{}/{}'", plugin, name)));
+        }
+        self.insert(format!("{}/{}", plugin, name), constructor)
+    }
+    
+    fn insert(&mut self, name: String, constructor: StageConstructor) -> Result<(), AppError> {
+        if self.constructors.contains_key(&name) {
+            return Err(AppError::Config(format!("Processing stage '{}' is already registered", name)));
+        }
+        self.constructors.insert(name, constructor);
+        Ok(())
+    }
+    
+    pub fn contains(&self, name: &str) -> bool {
+        self.constructors.contains_key(name)
+    }
+    
+    /// 已注册的自定义阶段名称（排序）
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.constructors.keys().cloned().collect();
+        names.sort();
+        names
+    }
+    
+    /// 按流校验阶段链：滤波器截止频率与采样率匹配，自定义阶段均已注册
+    pub fn check(&self, config: &ProcessingStagesConfig, sample_rate: f64) -> Result<(), AppError> {
+        config.validate()?;
+        for stage in &config.stages {
+            match stage {
+                StageSpec::Filters(filters) => filters.validate(sample_rate)?,
+                StageSpec::Custom { name, .. } if !self.contains(name) => {
+                    return Err(AppError::Config(format!("Unknown processing stage '{}'", name)));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    
+    fn build_stage(&self, spec: &StageSpec, stream_info: &StreamInfo) -> Result<Box<dyn ProcessingStage>, AppError> {
+        let channels_count = stream_info.channel_labels.len();
+        let stage: Box<dyn ProcessingStage> = match spec {
+            StageSpec::Notch(config) => Box::new(NotchStage {
+                bank: NotchFilterBank::new(config, stream_info.sample_rate, channels_count),
+            }),
+            StageSpec::Filters(config) => {
+                config.validate(stream_info.sample_rate)?;
+                Box::new(FilterStage { chain: FilterChain::new(config, stream_info.sample_rate, channels_count) })
+            }
+            StageSpec::Montage(config) => {
+                let montage = Montage::build(config, &stream_info.channel_labels)?;
+                let labels = montage.output_labels(&stream_info.channel_labels);
+                Box::new(MontageStage { montage, labels })
+            }
+            StageSpec::ArtifactDetection(config) => Box::new(ArtifactStage {
+                detector: ArtifactDetector::new(config.clone(), stream_info),
+            }),
+            StageSpec::Custom { name, params } => {
+                let constructor = self.constructors.get(name)
+                    .ok_or_else(|| AppError::Config(format!("Unknown processing stage '{}'", name)))?;
+                constructor(stream_info, params)?
+            }
+        };
+        Ok(stage)
+    }
+}
+
+impl Default for StageRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 按配置构建的阶段链 - 自定义阶段以注册名称报告指标
+pub struct StagePipeline {
+    stages: Vec<(String, Box<dyn ProcessingStage>)>,
+}
+
+impl StagePipeline {
+    /// stream_info 为进入阶段链时的显示路径流信息；阶段改变通道后，后续阶段按新通道构建
+    pub fn build(config: &ProcessingStagesConfig, stream_info: &StreamInfo, registry: &StageRegistry) -> Result<Self, AppError> {
+        let mut info = stream_info.clone();
+        let mut stages = Vec::with_capacity(config.stages.len());
+        
+        for spec in &config.stages {
+            let stage = registry.build_stage(spec, &info)?;
+            let name = match spec {
+                StageSpec::Custom { name, .. } => name.clone(),
+                _ => stage.name().to_string(),
+            };
+            let labels = stage.output_labels(&info.channel_labels);
+            info = StreamInfo {
+                channels_count: labels.len() as u32,
+                channel_labels: labels,
+                ..info
+            };
+            stages.push((name, stage));
+        }
+        
+        Ok(Self { stages })
+    }
+    
+    pub fn names(&self) -> Vec<&str> {
+        self.stages.iter().map(|(name, _)| name.as_str()).collect()
+    }
+    
+    /// 依次执行各阶段，返回产生了指标的阶段更新
+    pub fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetricsUpdate> {
+        let Some(timestamp) = batch.samples.last().map(|sample| sample.timestamp) else {
+            return Vec::new();
+        };
+        
+        let mut updates = Vec::new();
+        for (name, stage) in &mut self.stages {
+            let metrics = stage.process(batch);
+            if !metrics.is_empty() {
+                updates.push(StageMetricsUpdate { stage: name.clone(), timestamp, metrics });
+            }
+        }
+        updates
+    }
+}
+
+struct NotchStage {
+    bank: NotchFilterBank,
+}
+
+impl ProcessingStage for NotchStage {
+    fn name(&self) -> &str {
+        "notch"
+    }
+    
+    fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric> {
+        if self.bank.is_active() {
+            for sample in &mut batch.samples {
+                self.bank.process_sample(&mut sample.channels);
+            }
+        }
+        Vec::new()
+    }
+}
+
+struct FilterStage {
+    chain: FilterChain,
+}
+
+impl ProcessingStage for FilterStage {
+    fn name(&self) -> &str {
+        "filters"
+    }
+    
+    fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric> {
+        if self.chain.is_active() {
+            for sample in &mut batch.samples {
+                self.chain.process_sample(&mut sample.channels);
+            }
+        }
+        Vec::new()
+    }
+}
+
+struct MontageStage {
+    montage: Montage,
+    labels: Vec<String>,
+}
+
+impl ProcessingStage for MontageStage {
+    fn name(&self) -> &str {
+        "montage"
+    }
+    
+    fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric> {
+        if !self.montage.is_raw() {
+            for sample in &mut batch.samples {
+                self.montage.apply(&mut sample.channels);
+            }
+        }
+        batch.channel_labels = self.labels.clone();
+        Vec::new()
+    }
+    
+    fn output_labels(&self, _input: &[String]) -> Vec<String> {
+        self.labels.clone()
+    }
+}
+
+/// 伪迹检测阶段 - 每个检测到的伪迹输出一个指标（名称为伪迹类型，值为峰值偏移）
+struct ArtifactStage {
+    detector: ArtifactDetector,
+}
+
+impl ProcessingStage for ArtifactStage {
+    fn name(&self) -> &str {
+        "artifact_detection"
+    }
+    
+    fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric> {
+        batch.samples.iter()
+            .flat_map(|sample| self.detector.push(sample))
+            .map(|artifact| StageMetric {
+                name: artifact.kind.label().to_string(),
+                channel_index: Some(artifact.channel_index),
+                value: artifact.peak_amplitude,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// 每块输出通道均值，并将所有样本乘以 gain
+    struct GainStage {
+        gain: f64,
+    }
+    
+    impl ProcessingStage for GainStage {
+        fn name(&self) -> &str {
+            "gain"
+        }
+        
+        fn process(&mut self, batch: &mut StageBatch) -> Vec<StageMetric> {
+            for sample in &mut batch.samples {
+                sample.channels.iter_mut().for_each(|value| *value *= self.gain);
+            }
+            let count = batch.samples.len() as f64;
+            (0..batch.channel_labels.len())
+                .map(|ch| StageMetric {
+                    name: format!("mean_{}", batch.channel_labels[ch]),
+                    channel_index: Some(ch as u32),
+                    value: batch.samples.iter().map(|sample| sample.channels[ch]).sum::<f64>() / count,
+                })
+                .collect()
+        }
+    }
+    
+    fn stream_info() -> StreamInfo {
+        StreamInfo {
+            name: "Test".to_string(),
+            stream_type: "EEG".to_string(),
+            channels_count: 2,
+            sample_rate: 250.0,
+            is_connected: true,
+            source_id: "test".to_string(),
+            channel_labels: vec!["C3".to_string(), "C4".to_string()],
+            effective_sample_rate: None,
+        }
+    }
+    
+    #[test]
+    fn test_pipeline_composes_builtin_and_custom_stages() {
+        let mut registry = StageRegistry::new();
+        registry.register("gain", Arc::new(|_: &StreamInfo, params: &serde_json::Value| {
+            let gain = params.get("gain").and_then(|gain| gain.as_f64()).unwrap_or(1.0);
+            Ok(Box::new(GainStage { gain }) as Box<dyn ProcessingStage>)
+        })).unwrap();
+        assert!(registry.register("gain", Arc::new(|_: &StreamInfo, _: &serde_json::Value| {
+            Ok(Box::new(GainStage { gain: 1.0 }) as Box<dyn ProcessingStage>)
+        })).is_err());
+        
+        let config: ProcessingStagesConfig = serde_json::from_value(serde_json::json!({
+            "stages": [
+                { "kind": "montage", "config": { "kind": "bipolar", "pairs": [["C3", "C4"]] } },
+                { "kind": "custom", "config": { "name": "gain", "params": { "gain": 2.0 } } },
+            ]
+        })).unwrap();
+        registry.check(&config, 250.0).unwrap();
+        
+        let mut pipeline = StagePipeline::build(&config, &stream_info(), &registry).unwrap();
+        assert_eq!(pipeline.names(), vec!["montage", "gain"]);
+        
+        let mut batch = StageBatch {
+            channel_labels: stream_info().channel_labels,
+            samples: (0..4).map(|i| EegSample {
+                timestamp: i as f64,
+                channels: vec![3.0, 1.0],
+                sample_id: i,
+            }).collect(),
+        };
+        let updates = pipeline.process(&mut batch);
+        
+        // 双极导联 C3-C4 = 2，再乘以增益
+        assert!(batch.samples.iter().all(|sample| sample.channels == vec![4.0]));
+        assert_eq!(batch.channel_labels.len(), 1);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].stage, "gain");
+        assert_eq!(updates[0].timestamp, 3.0);
+        assert_eq!(updates[0].metrics[0].value, 4.0);
+        
+        let unknown = ProcessingStagesConfig {
+            stages: vec![StageSpec::Custom { name: "missing".to_string(), params: serde_json::Value::Null }],
+        };
+        assert!(registry.check(&unknown, 250.0).is_err());
+    }
+}
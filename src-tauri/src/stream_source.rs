@@ -0,0 +1,432 @@
+use crate::data_types::*;
+use crate::error::AppError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// 可作为辅助流连接的流类型（不区分大小写）
+pub(crate) const AUX_STREAM_TYPES: [&str; 5] = ["Accelerometer", "IMU", "Gyroscope", "Gyro", "Motion"];
+
+// 可作为阻抗流连接的流类型（各通道为对应电极的阻抗，单位kΩ）
+pub(crate) const IMPEDANCE_STREAM_TYPES: [&str; 2] = ["Impedance", "Impedances"];
+
+/// 数据源时钟（秒），与样本时间戳同一时基，用于计算数据年龄与时钟换算
+pub type SourceClock = Arc<dyn Fn() -> f64 + Send + Sync>;
+
+/// 数值流inlet（EEG流与辅助流）
+pub trait SampleInlet {
+    /// 非阻塞取出所有已到达的样本
+    fn pull_chunk(&mut self) -> Result<(Vec<Vec<f64>>, Vec<f64>), AppError>;
+    
+    /// 最多等待 timeout 秒，将下一个样本写入 buffer 并返回其时间戳；超时返回 0.0
+    fn pull_sample(&mut self, buffer: &mut Vec<f64>, timeout: f64) -> Result<f64, AppError>;
+    
+    /// 测量与发送端的时钟偏移（秒）
+    fn time_correction(&mut self, timeout: f64) -> Result<f64, AppError>;
+}
+
+/// 字符串标记流inlet
+pub trait MarkerInlet {
+    /// 非阻塞取出一个标记，没有时返回 None
+    fn pull_marker(&mut self) -> Result<Option<(Vec<String>, f64)>, AppError>;
+}
+
+/// 持续流发现 - 返回当前可见的全部流
+pub trait StreamResolver {
+    fn results(&mut self) -> Result<Vec<LslStreamInfo>, AppError>;
+}
+
+/// EEG流连接结果；inlet 为 None 时只有流信息、没有数据（LSL非严格模式的回退连接）
+pub struct EegConnection {
+    pub info: StreamInfo,
+    pub inlet: Option<Box<dyn SampleInlet>>,
+    pub impedances: Vec<Option<f64>>,   // 流描述中各通道的阻抗（kΩ），未提供时为空
+}
+
+/// 数据源 - LslManager 工作线程通过它发现、连接并拉取流
+///
+/// 实现在工作线程内创建并使用inlet，inlet本身不要求跨线程。
+pub trait StreamSource: Send + Sync {
+    fn clock(&self) -> SourceClock;
+    
+    /// 一次性发现流
+    fn resolve_streams(&mut self) -> Result<Vec<LslStreamInfo>, AppError>;
+    
+    /// 连接EEG流；同名流存在多个时需在 selector 中指定 source_id
+    fn connect(&mut self, selector: &StreamSelector, strict: bool, inlet_config: &LslInletConfig) -> Result<EegConnection, AppError>;
+    
+    fn connect_marker(&mut self, name: &str) -> Result<(LslStreamInfo, Box<dyn MarkerInlet>), AppError>;
+    
+    fn connect_aux(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError>;
+    
+    fn connect_impedance(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError>;
+    
+    fn continuous_resolver(&mut self) -> Result<Box<dyn StreamResolver>, AppError>;
+}
+
+/// 校验辅助流类型
+pub(crate) fn check_aux_type(name: &str, stream_type: &str) -> Result<(), AppError> {
+    check_stream_type(name, stream_type, &AUX_STREAM_TYPES)
+}
+
+/// 校验阻抗流类型
+pub(crate) fn check_impedance_type(name: &str, stream_type: &str) -> Result<(), AppError> {
+    check_stream_type(name, stream_type, &IMPEDANCE_STREAM_TYPES)
+}
+
+fn check_stream_type(name: &str, stream_type: &str, expected: &[&str]) -> Result<(), AppError> {
+    if expected.iter().any(|t| t.eq_ignore_ascii_case(stream_type.trim())) {
+        Ok(())
+    } else {
+        Err(AppError::Lsl(format!(
+            "Stream '{}' has type '{}', expected one of {:?}", name, stream_type, expected
+        )))
+    }
+}
+
+/// 模拟流的信号：(样本序号, 通道) → 数值
+pub type MockSignal = Arc<dyn Fn(u64, usize) -> f64 + Send + Sync>;
+
+/// 模拟数值流 - 样本值只由样本序号与通道决定，按名义采样率实时发出
+#[derive(Clone)]
+pub struct MockStream {
+    pub info: LslStreamInfo,
+    pub channel_labels: Vec<String>,
+    pub signal: MockSignal,
+    pub total_samples: Option<u64>,     // 发出该数量后不再有数据（模拟断流），None 持续发出
+    pub impedances: Vec<Option<f64>>,   // 流描述中的通道阻抗（kΩ）
+}
+
+impl MockStream {
+    /// 确定性测试信号：通道 ch 为 (ch + 1) · sin(2π · 10Hz · t)
+    pub fn sine(name: &str, channels_count: u32, sample_rate: f64) -> Self {
+        Self::new(name, "EEG", channels_count, sample_rate, Arc::new(move |index, ch| {
+            (ch + 1) as f64 * (2.0 * std::f64::consts::PI * 10.0 * index as f64 / sample_rate).sin()
+        }))
+    }
+    
+    pub fn new(name: &str, stream_type: &str, channels_count: u32, sample_rate: f64, signal: MockSignal) -> Self {
+        Self {
+            info: LslStreamInfo {
+                name: name.to_string(),
+                stream_type: stream_type.to_string(),
+                channels_count,
+                sample_rate,
+                source_id: format!("mock_{}", name),
+                hostname: "mock".to_string(),
+            },
+            channel_labels: StreamInfo::default_channel_labels(channels_count),
+            signal,
+            total_samples: None,
+            impedances: Vec::new(),
+        }
+    }
+    
+    pub fn with_total_samples(mut self, total_samples: u64) -> Self {
+        self.total_samples = Some(total_samples);
+        self
+    }
+    
+    /// 第 index 个样本
+    pub fn sample(&self, index: u64) -> Vec<f64> {
+        (0..self.info.channels_count as usize).map(|ch| (self.signal)(index, ch)).collect()
+    }
+    
+    fn stream_info(&self) -> StreamInfo {
+        StreamInfo {
+            name: self.info.name.clone(),
+            stream_type: self.info.stream_type.clone(),
+            channels_count: self.info.channels_count,
+            sample_rate: self.info.sample_rate,
+            is_connected: true,
+            source_id: self.info.source_id.clone(),
+            channel_labels: self.channel_labels.clone(),
+            effective_sample_rate: None,
+        }
+    }
+    
+    fn matches(&self, selector: &StreamSelector) -> bool {
+        let field = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+        self.info.name == selector.name
+            && field(&selector.source_id).is_none_or(|source_id| source_id == self.info.source_id)
+            && field(&selector.hostname).is_none_or(|hostname| hostname == self.info.hostname)
+    }
+}
+
+/// 模拟标记流 - 标记在连接后的固定时刻（秒）到达
+#[derive(Clone)]
+struct MockMarkerStream {
+    info: LslStreamInfo,
+    markers: Vec<(f64, String)>,
+}
+
+/// 确定性模拟数据源 - 无需网络即可驱动 LslManager 与完整处理管道
+///
+/// 时钟从创建数据源时开始计时；样本时间戳为连接时刻加上 序号 / 采样率。
+pub struct MockSource {
+    streams: Vec<MockStream>,
+    marker_streams: Vec<MockMarkerStream>,
+    origin: Instant,
+}
+
+impl MockSource {
+    pub fn new() -> Self {
+        Self { streams: Vec::new(), marker_streams: Vec::new(), origin: Instant::now() }
+    }
+    
+    pub fn with_stream(mut self, stream: MockStream) -> Self {
+        self.streams.push(stream);
+        self
+    }
+    
+    /// 添加标记流，markers 为 (连接后的秒数, 标签)
+    pub fn with_markers(mut self, name: &str, markers: Vec<(f64, String)>) -> Self {
+        let info = LslStreamInfo {
+            name: name.to_string(),
+            stream_type: "Markers".to_string(),
+            channels_count: 1,
+            sample_rate: 0.0,
+            source_id: format!("mock_{}", name),
+            hostname: "mock".to_string(),
+        };
+        self.marker_streams.push(MockMarkerStream { info, markers });
+        self
+    }
+    
+    fn find_stream(&self, name: &str) -> Option<&MockStream> {
+        self.streams.iter().find(|stream| stream.info.name == name)
+    }
+}
+
+impl Default for MockSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamSource for MockSource {
+    fn clock(&self) -> SourceClock {
+        let origin = self.origin;
+        Arc::new(move || origin.elapsed().as_secs_f64())
+    }
+    
+    fn resolve_streams(&mut self) -> Result<Vec<LslStreamInfo>, AppError> {
+        Ok(self.streams.iter().map(|stream| stream.info.clone())
+            .chain(self.marker_streams.iter().map(|stream| stream.info.clone()))
+            .collect())
+    }
+    
+    fn connect(&mut self, selector: &StreamSelector, strict: bool, _inlet_config: &LslInletConfig) -> Result<EegConnection, AppError> {
+        // Strict模式只接受真实设备，模拟/演示数据源一律拒绝
+        if strict {
+            return Err(AppError::Config(format!(
+                "Mock stream '{}' is disabled in strict acquisition mode", selector.name
+            )));
+        }
+        
+        let matching: Vec<&MockStream> = self.streams.iter().filter(|stream| stream.matches(selector)).collect();
+        match matching.as_slice() {
+            [] => Err(AppError::StreamNotFound(selector.name.clone())),
+            [stream] => Ok(EegConnection {
+                info: stream.stream_info(),
+                inlet: Some(Box::new(MockInlet::new((*stream).clone(), self.clock()))),
+                impedances: stream.impedances.clone(),
+            }),
+            streams if !selector.has_source_id() => {
+                let source_ids: Vec<&str> = streams.iter().map(|stream| stream.info.source_id.as_str()).collect();
+                Err(AppError::Lsl(format!(
+                    "{} streams are named '{}' (source ids {:?}); connect by source_id", streams.len(), selector.name, source_ids
+                )))
+            }
+            [stream, ..] => Ok(EegConnection {
+                info: stream.stream_info(),
+                inlet: Some(Box::new(MockInlet::new((*stream).clone(), self.clock()))),
+                impedances: stream.impedances.clone(),
+            }),
+        }
+    }
+    
+    fn connect_marker(&mut self, name: &str) -> Result<(LslStreamInfo, Box<dyn MarkerInlet>), AppError> {
+        let stream = self.marker_streams.iter().find(|stream| stream.info.name == name)
+            .ok_or_else(|| AppError::StreamNotFound(name.to_string()))?;
+        
+        let clock = self.clock();
+        let start = clock();
+        let mut markers = stream.markers.clone();
+        markers.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let inlet = MockMarkerInlet { markers: markers.into_iter().map(|(offset, label)| (start + offset, label)).collect(), clock };
+        Ok((stream.info.clone(), Box::new(inlet)))
+    }
+    
+    fn connect_aux(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
+        let stream = self.find_stream(name)
+            .ok_or_else(|| AppError::StreamNotFound(name.to_string()))?;
+        check_aux_type(name, &stream.info.stream_type)?;
+        
+        Ok((stream.stream_info(), Box::new(MockInlet::new(stream.clone(), self.clock()))))
+    }
+    
+    fn connect_impedance(&mut self, name: &str) -> Result<(StreamInfo, Box<dyn SampleInlet>), AppError> {
+        let stream = self.find_stream(name)
+            .ok_or_else(|| AppError::StreamNotFound(name.to_string()))?;
+        check_impedance_type(name, &stream.info.stream_type)?;
+        
+        Ok((stream.stream_info(), Box::new(MockInlet::new(stream.clone(), self.clock()))))
+    }
+    
+    fn continuous_resolver(&mut self) -> Result<Box<dyn StreamResolver>, AppError> {
+        Ok(Box::new(MockResolver { streams: self.resolve_streams()? }))
+    }
+}
+
+/// 模拟inlet - 按时钟计算已“到达”的样本数
+struct MockInlet {
+    stream: MockStream,
+    clock: SourceClock,
+    start: f64,
+    emitted: u64,
+}
+
+impl MockInlet {
+    fn new(stream: MockStream, clock: SourceClock) -> Self {
+        let start = clock();
+        Self { stream, clock, start, emitted: 0 }
+    }
+    
+    fn timestamp(&self, index: u64) -> f64 {
+        self.start + index as f64 / self.stream.info.sample_rate
+    }
+    
+    /// 截至当前时刻已到达的样本数
+    fn available(&self) -> u64 {
+        let elapsed = ((self.clock)() - self.start).max(0.0);
+        let due = (elapsed * self.stream.info.sample_rate).floor() as u64 + 1;
+        self.stream.total_samples.map_or(due, |total| due.min(total))
+    }
+}
+
+impl SampleInlet for MockInlet {
+    fn pull_chunk(&mut self) -> Result<(Vec<Vec<f64>>, Vec<f64>), AppError> {
+        let available = self.available();
+        let indices = self.emitted..available.max(self.emitted);
+        self.emitted = indices.end;
+        
+        Ok(indices.map(|index| (self.stream.sample(index), self.timestamp(index))).unzip())
+    }
+    
+    fn pull_sample(&mut self, buffer: &mut Vec<f64>, timeout: f64) -> Result<f64, AppError> {
+        if self.stream.total_samples.is_some_and(|total| self.emitted >= total) {
+            std::thread::sleep(Duration::from_secs_f64(timeout));
+            return Ok(0.0);
+        }
+        
+        let index = self.emitted;
+        let wait = self.timestamp(index) - (self.clock)();
+        if wait > timeout {
+            std::thread::sleep(Duration::from_secs_f64(timeout));
+            return Ok(0.0);
+        }
+        if wait > 0.0 {
+            std::thread::sleep(Duration::from_secs_f64(wait));
+        }
+        
+        for (value, sample) in buffer.iter_mut().zip(self.stream.sample(index)) {
+            *value = sample;
+        }
+        self.emitted += 1;
+        Ok(self.timestamp(index))
+    }
+    
+    fn time_correction(&mut self, _timeout: f64) -> Result<f64, AppError> {
+        Ok(0.0)
+    }
+}
+
+struct MockMarkerInlet {
+    markers: std::collections::VecDeque<(f64, String)>,
+    clock: SourceClock,
+}
+
+impl MarkerInlet for MockMarkerInlet {
+    fn pull_marker(&mut self) -> Result<Option<(Vec<String>, f64)>, AppError> {
+        let now = (self.clock)();
+        if self.markers.front().is_some_and(|(timestamp, _)| *timestamp <= now) {
+            Ok(self.markers.pop_front().map(|(timestamp, label)| (vec![label], timestamp)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+struct MockResolver {
+    streams: Vec<LslStreamInfo>,
+}
+
+impl StreamResolver for MockResolver {
+    fn results(&mut self) -> Result<Vec<LslStreamInfo>, AppError> {
+        Ok(self.streams.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_strict_mode_rejects_mock_streams() {
+        let mut source = crate::simulator::source(&SimulatorConfig::default());
+        let selector = StreamSelector { name: SimulatorConfig::default().name, source_id: None, hostname: None };
+        
+        assert!(matches!(source.connect(&selector, true, &LslInletConfig::default()), Err(AppError::Config(_))));
+        assert!(source.connect(&selector, false, &LslInletConfig::default()).is_ok());
+    }
+    
+    #[test]
+    fn test_mock_inlet_is_deterministic_and_paced() {
+        let stream = MockStream::sine("Mock", 2, 1000.0).with_total_samples(50);
+        let mut source = MockSource::new().with_stream(stream.clone());
+        let selector = StreamSelector { name: "Mock".to_string(), source_id: None, hostname: None };
+        let connection = source.connect(&selector, false, &LslInletConfig::default()).unwrap();
+        assert_eq!(connection.info.channels_count, 2);
+        let mut inlet = connection.inlet.unwrap();
+        
+        let mut buffer = vec![0.0; 2];
+        let first = inlet.pull_sample(&mut buffer, 1.0).unwrap();
+        assert!(first > 0.0);
+        assert_eq!(buffer, stream.sample(0));
+        
+        // 等待全部样本到达
+        std::thread::sleep(Duration::from_millis(80));
+        let (values, timestamps) = inlet.pull_chunk().unwrap();
+        assert_eq!(values.len(), 49);
+        assert_eq!(values[9], stream.sample(10));
+        assert!((timestamps[9] - (first + 0.010)).abs() < 1e-9);
+        
+        // 断流后不再有数据
+        assert!(inlet.pull_chunk().unwrap().0.is_empty());
+        assert_eq!(inlet.pull_sample(&mut buffer, 0.01).unwrap(), 0.0);
+    }
+    
+    #[test]
+    fn test_mock_source_resolves_by_selector() {
+        let mut second_amp = MockStream::sine("Amp", 8, 250.0);
+        second_amp.info.source_id = "amp-2".to_string();
+        let mut source = MockSource::new()
+            .with_stream(MockStream::sine("Amp", 4, 250.0))
+            .with_stream(second_amp)
+            .with_stream(MockStream::new("IMU", "Accelerometer", 3, 50.0, Arc::new(|_, _| 0.0)))
+            .with_markers("Markers", vec![(0.0, "start".to_string())]);
+        assert_eq!(source.resolve_streams().unwrap().len(), 4);
+        
+        let by_name = StreamSelector { name: "Amp".to_string(), source_id: None, hostname: None };
+        assert!(source.connect(&by_name, false, &LslInletConfig::default()).is_err());
+        let by_source = StreamSelector { source_id: Some("amp-2".to_string()), ..by_name };
+        assert_eq!(source.connect(&by_source, false, &LslInletConfig::default()).unwrap().info.channels_count, 8);
+        
+        assert!(source.connect_aux("Amp").is_err());
+        assert_eq!(source.connect_aux("IMU").unwrap().0.channels_count, 3);
+        
+        let (_, mut markers) = source.connect_marker("Markers").unwrap();
+        assert_eq!(markers.pull_marker().unwrap().unwrap().0, vec!["start".to_string()]);
+        assert!(markers.pull_marker().unwrap().is_none());
+    }
+}
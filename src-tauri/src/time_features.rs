@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use crate::data_types::*;
+
+/// 时域特征提取 - 每个通道保留最近一个窗口的样本，按间隔计算Hjorth参数、RMS、峰度和过零率
+///
+/// 计算只在推送时进行，开销为每次推送 O(窗口长度 × 通道数)。
+pub struct TimeFeatureExtractor {
+    config: TimeFeaturesConfig,
+    sample_rate: f64,
+    channel_labels: Vec<String>,
+    window_samples: usize,
+    windows: Vec<VecDeque<f64>>,
+    update_every: usize,
+    since_update: usize,
+}
+
+impl TimeFeatureExtractor {
+    pub fn new(config: TimeFeaturesConfig, sample_rate: f64, channel_labels: Vec<String>) -> Self {
+        let window_samples = ((config.window_seconds * sample_rate).round() as usize).max(3);
+        
+        Self {
+            config,
+            sample_rate,
+            windows: vec![VecDeque::with_capacity(window_samples + 1); channel_labels.len()],
+            channel_labels,
+            window_samples,
+            update_every: ((config.update_interval_ms as f64 / 1000.0 * sample_rate) as usize).max(1),
+            since_update: 0,
+        }
+    }
+    
+    pub fn channel_labels(&self) -> &[String] {
+        &self.channel_labels
+    }
+    
+    /// 加入一个样本，窗口已满且到推送间隔时返回特征
+    pub fn push(&mut self, timestamp: f64, channels: &[f64]) -> Option<TimeFeaturesUpdate> {
+        if channels.len() != self.windows.len() {
+            return None;
+        }
+        
+        for (window, &value) in self.windows.iter_mut().zip(channels) {
+            if window.len() == self.window_samples {
+                window.pop_front();
+            }
+            window.push_back(value);
+        }
+        
+        self.since_update += 1;
+        if self.since_update < self.update_every || self.windows.first()?.len() < self.window_samples {
+            return None;
+        }
+        self.since_update = 0;
+        
+        Some(TimeFeaturesUpdate {
+            timestamp,
+            window_seconds: self.config.window_seconds,
+            channel_labels: self.channel_labels.clone(),
+            channels: self.windows.iter()
+                .enumerate()
+                .map(|(channel, window)| compute_features(channel as u32, window, self.sample_rate))
+                .collect(),
+        })
+    }
+}
+
+/// 单通道窗口的时域特征
+fn compute_features(channel_index: u32, window: &VecDeque<f64>, sample_rate: f64) -> ChannelTimeFeatures {
+    let samples: Vec<f64> = window.iter().copied().collect();
+    let first: Vec<f64> = samples.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let second: Vec<f64> = first.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let activity = variance(&samples);
+    let ratio = |numerator: f64, denominator: f64| if denominator > 0.0 { (numerator / denominator).sqrt() } else { 0.0 };
+    let mobility = ratio(variance(&first), activity);
+    let complexity = if mobility > 0.0 { ratio(variance(&second), variance(&first)) / mobility } else { 0.0 };
+    
+    let fourth_moment = samples.iter().map(|x| (x - mean).powi(4)).sum::<f64>() / n;
+    let kurtosis = if activity > 0.0 { fourth_moment / (activity * activity) - 3.0 } else { 0.0 };
+    
+    let crossings = samples.windows(2)
+        .filter(|pair| (pair[0] - mean).signum() != (pair[1] - mean).signum())
+        .count();
+    
+    ChannelTimeFeatures {
+        channel_index,
+        activity,
+        mobility,
+        complexity,
+        rms: (samples.iter().map(|x| x * x).sum::<f64>() / n).sqrt(),
+        kurtosis,
+        zero_crossing_rate: crossings as f64 * sample_rate / n,
+    }
+}
+
+/// 总体方差
+fn variance(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    
+    #[test]
+    fn test_sine_features() {
+        let sample_rate = 250.0;
+        let config = TimeFeaturesConfig { enabled: true, window_seconds: 2.0, update_interval_ms: 500 };
+        let mut extractor = TimeFeatureExtractor::new(config, sample_rate, vec!["Oz".to_string()]);
+        
+        let mut updates = Vec::new();
+        for n in 0..1000 {
+            let x = 10.0 * (2.0 * PI * 10.0 * n as f64 / sample_rate).sin();
+            updates.extend(extractor.push(n as f64 / sample_rate, &[x]));
+        }
+        
+        // 窗口满（2秒）后每0.5秒一次
+        assert_eq!(updates.len(), 5);
+        let features = &updates.last().unwrap().channels[0];
+        
+        // 正弦：方差 A²/2，移动度 = 2·sin(πf/fs)（差分近似），复杂度 ≈ 1，超额峰度 -1.5，过零率 2f
+        assert!((features.activity - 50.0).abs() < 0.5);
+        assert!((features.rms - 50f64.sqrt()).abs() < 0.05);
+        let expected_mobility = 2.0 * (PI * 10.0 / sample_rate).sin();
+        assert!((features.mobility - expected_mobility).abs() < 1e-3);
+        assert!((features.complexity - 1.0).abs() < 0.01);
+        assert!((features.kurtosis + 1.5).abs() < 0.05);
+        assert!((features.zero_crossing_rate - 20.0).abs() < 1.0);
+    }
+}
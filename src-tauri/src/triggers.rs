@@ -0,0 +1,149 @@
+use chrono::{DateTime, Local};
+use crate::data_types::*;
+use crate::band_power;
+
+/// 一次规则触发
+pub struct TriggerFired {
+    pub rule_index: usize,
+    pub action: TriggerAction,
+    pub reason: String,
+}
+
+/// 录制触发规则引擎 - 标记每次匹配都触发，阈值条件只在越过阈值时触发一次
+pub struct TriggerEngine {
+    rules: Vec<TriggerRule>,
+    armed: Vec<bool>,
+}
+
+impl TriggerEngine {
+    pub fn new(config: &RecordingTriggerConfig) -> Self {
+        Self {
+            rules: config.rules.clone(),
+            armed: vec![true; config.rules.len()],
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+    
+    pub fn on_marker(&self, marker: &MarkerEvent) -> Vec<TriggerFired> {
+        self.rules.iter().enumerate()
+            .filter_map(|(rule_index, rule)| match &rule.condition {
+                TriggerCondition::Marker { label } if *label == marker.label => Some(TriggerFired {
+                    rule_index,
+                    action: rule.action,
+                    reason: format!("Marker '{}'", marker.label),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+    
+    pub fn on_sample(&mut self, sample: &EegSample) -> Vec<TriggerFired> {
+        let mut fired = Vec::new();
+        for rule_index in 0..self.rules.len() {
+            let TriggerCondition::Amplitude { channel, threshold, above } = self.rules[rule_index].condition else {
+                continue;
+            };
+            let Some(&value) = sample.channels.get(channel as usize) else { continue };
+            
+            if crossed(&mut self.armed[rule_index], value.abs(), threshold, above) {
+                fired.push(TriggerFired {
+                    rule_index,
+                    action: self.rules[rule_index].action,
+                    reason: format!("Channel {} amplitude {:.1}µV {} {:.1}µV",
+                                    channel, value.abs(), if above { ">" } else { "<" }, threshold),
+                });
+            }
+        }
+        fired
+    }
+    
+    pub fn on_band_power(&mut self, update: &BandPowerUpdate) -> Vec<TriggerFired> {
+        let mut fired = Vec::new();
+        for rule_index in 0..self.rules.len() {
+            let TriggerCondition::BandPower { channel, ref band, threshold, above, relative } = self.rules[rule_index].condition else {
+                continue;
+            };
+            let Some(value) = band_power::band_value(update, channel, band, relative) else { continue };
+            
+            if crossed(&mut self.armed[rule_index], value, threshold, above) {
+                fired.push(TriggerFired {
+                    rule_index,
+                    action: self.rules[rule_index].action,
+                    reason: format!("Channel {} {} power {:.3} {} {:.3}",
+                                    channel, band, value, if above { ">" } else { "<" }, threshold),
+                });
+            }
+        }
+        fired
+    }
+}
+
+/// 条件成立且规则处于布防状态时触发；条件不成立时重新布防
+fn crossed(armed: &mut bool, value: f64, threshold: f64, above: bool) -> bool {
+    let active = if above { value > threshold } else { value < threshold };
+    
+    if !active {
+        *armed = true;
+        return false;
+    }
+    
+    std::mem::replace(armed, false)
+}
+
+/// 触发录制的文件名：session.edf -> session_20240101_120000.edf
+pub fn triggered_filename(base: &str, started: DateTime<Local>) -> String {
+    let path = std::path::Path::new(base);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let stamp = started.format("%Y%m%d_%H%M%S");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, stamp, ext),
+        None => format!("{}_{}", stem, stamp),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_threshold_rules_fire_once_per_crossing() {
+        let config = RecordingTriggerConfig {
+            rules: vec![
+                TriggerRule {
+                    action: TriggerAction::Start,
+                    condition: TriggerCondition::Amplitude { channel: 0, threshold: 100.0, above: true },
+                },
+                TriggerRule {
+                    action: TriggerAction::Stop,
+                    condition: TriggerCondition::Marker { label: "end".to_string() },
+                },
+            ],
+            filename: "session.edf".to_string(),
+            options: RecordingOptions::default(),
+        };
+        let mut engine = TriggerEngine::new(&config);
+        
+        let fired: Vec<usize> = [10.0, 150.0, -200.0, 20.0, 120.0]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &value)| engine.on_sample(&EegSample { timestamp: i as f64, channels: vec![value], sample_id: i as u64 }))
+            .map(|fired| fired.rule_index)
+            .collect();
+        assert_eq!(fired, vec![0, 0]);
+        
+        let marker = MarkerEvent {
+            label: "end".to_string(),
+            timestamp: 5.0,
+            stream_name: "Markers".to_string(),
+            sample_id: 5,
+            recording_onset: None,
+        };
+        let fired = engine.on_marker(&marker);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].action, TriggerAction::Stop);
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use crate::data_types::VerificationReport;
+use crate::error::AppError;
+use crate::repair;
+
+/// 计算文件的 SHA-256（小写十六进制）
+pub fn sha256_file(path: &Path) -> Result<String, AppError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// 小写十六进制
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// 校验录制文件
+///
+/// EDF/BDF 文件检查文件头与数据记录是否一致（EDF 另用 EdfReader 重新读取文件头）；
+/// 给出 expected_sha256 时同时比对校验和。其他格式只计算校验和。
+pub fn verify_recording(path: &Path, expected_sha256: Option<&str>) -> Result<VerificationReport, AppError> {
+    let sha256 = sha256_file(path)?;
+    let mut report = VerificationReport {
+        path: path.display().to_string(),
+        sha256,
+        expected_sha256: expected_sha256.map(str::to_string),
+        checksum_matches: None,
+        structure_checked: false,
+        records_in_header: None,
+        records_in_file: None,
+        duration_seconds: None,
+        issues: Vec::new(),
+        valid: false,
+    };
+    
+    if let Some(expected) = expected_sha256 {
+        let matches = expected.eq_ignore_ascii_case(&report.sha256);
+        report.checksum_matches = Some(matches);
+        if !matches {
+            report.issues.push(format!("SHA-256 mismatch: expected {}, file has {}", expected, report.sha256));
+        }
+    }
+    
+    let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+    if matches!(extension.as_deref(), Some("edf") | Some("bdf")) {
+        report.structure_checked = true;
+        check_structure(path, &mut report)?;
+    }
+    
+    report.valid = report.issues.is_empty();
+    if report.valid {
+        info!("✅ Verified {} (sha256 {})", report.path, report.sha256);
+    } else {
+        warn!("⚠️ Verification of {} found {} issue(s): {}", report.path, report.issues.len(), report.issues.join("; "));
+    }
+    Ok(report)
+}
+
+fn check_structure(path: &Path, report: &mut VerificationReport) -> Result<(), AppError> {
+    let mut file = File::open(path)?;
+    let layout = match repair::read_layout(&mut file, path) {
+        Ok(layout) => layout,
+        Err(e) => {
+            report.issues.push(e.to_string());
+            return Ok(());
+        }
+    };
+    let (records, trailing_bytes) = layout.records_in(file.metadata()?.len());
+    report.records_in_header = Some(layout.records_in_header);
+    report.records_in_file = Some(records);
+    report.duration_seconds = Some(records as f64 * layout.record_duration);
+    
+    if layout.records_in_header < 0 {
+        report.issues.push("Header record count was never written (file not closed, see repair_recording)".to_string());
+    } else if layout.records_in_header as u64 != records {
+        report.issues.push(format!("Header declares {} data records but the file contains {}", layout.records_in_header, records));
+    }
+    if trailing_bytes > 0 {
+        report.issues.push(format!("{} trailing bytes after the last complete data record", trailing_bytes));
+    }
+    
+    if !layout.is_bdf {
+        match edfplus::EdfReader::open(&report.path) {
+            Ok(reader) if reader.header().signals.len() != layout.signal_count => {
+                report.issues.push(format!("EDF reader found {} signals, header declares {}",
+                                           reader.header().signals.len(), layout.signal_count));
+            }
+            Ok(_) => {}
+            Err(e) => report.issues.push(format!("EDF reader failed to open the file: {}", e)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::{BdfSignal, BdfWriter};
+    
+    #[test]
+    fn test_verify_bdf_structure_and_checksum() {
+        let path = std::env::temp_dir().join(format!("verify_test_{}.bdf", std::process::id()));
+        let filename = path.to_str().unwrap().to_string();
+        let signal = BdfSignal {
+            label: "Cz".to_string(),
+            transducer: String::new(),
+            physical_dimension: "uV".to_string(),
+            physical_min: -100.0,
+            physical_max: 100.0,
+            prefilter: String::new(),
+            samples_per_record: 4,
+        };
+        let mut writer = BdfWriter::create(&filename, vec![signal], 1.0, chrono::Utc::now(), "X X X X", "Startdate X X X X").unwrap();
+        writer.write_samples(&[vec![1.0; 4]]).unwrap();
+        writer.write_samples(&[vec![2.0; 4]]).unwrap();
+        writer.finalize().unwrap();
+        
+        let checksum = sha256_file(&path).unwrap();
+        assert_eq!(checksum.len(), 64);
+        let report = verify_recording(&path, Some(&checksum.to_uppercase())).unwrap();
+        assert!(report.valid, "{:?}", report.issues);
+        assert_eq!(report.checksum_matches, Some(true));
+        assert_eq!(report.records_in_header, Some(2));
+        assert_eq!(report.records_in_file, Some(2));
+        
+        // 追加半个记录：结构和校验和都不再一致
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(&[0u8; 5]);
+        std::fs::write(&path, bytes).unwrap();
+        let report = verify_recording(&path, Some(&checksum)).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(!report.valid);
+        assert_eq!(report.checksum_matches, Some(false));
+        assert_eq!(report.issues.len(), 2);
+    }
+}
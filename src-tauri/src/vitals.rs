@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+use std::f64::consts::FRAC_1_SQRT_2;
+use crate::data_types::*;
+use crate::dsp::Biquad;
+use crate::signal_quality::median;
+
+const HEART_WINDOW_SECONDS: f64 = 10.0;
+const RESPIRATION_WINDOW_SECONDS: f64 = 30.0;
+const LEARNING_SECONDS: f64 = 2.0;
+const REFRACTORY_SECONDS: f64 = 0.25;
+const LOST_BEAT_SECONDS: f64 = 2.0;
+
+/// R波检测 - 简化的 Pan-Tompkins：5-15Hz带通、差分平方、150ms滑动积分
+///
+/// 前2秒以积分最大值作为初始R波幅度，之后阈值为已确认峰值指数平均的30%；
+/// 超过2秒未检测到R波时阈值减半（电极脱落恢复、幅度变化）。
+struct RPeakDetector {
+    highpass: Biquad,
+    lowpass: Biquad,
+    previous: f64,
+    integration: VecDeque<f64>,
+    integration_len: usize,
+    integration_sum: f64,
+    samples_seen: usize,
+    learning_samples: usize,
+    signal_level: f64,
+    candidate: Option<(f64, f64)>,      // 超过阈值区间内的 (积分峰值, 时刻)
+    last_activity: Option<f64>,
+    peaks: VecDeque<f64>,
+}
+
+impl RPeakDetector {
+    fn new(sample_rate: f64) -> Self {
+        let integration_len = ((0.15 * sample_rate).round() as usize).max(1);
+        
+        Self {
+            highpass: Biquad::highpass(sample_rate, 5.0, FRAC_1_SQRT_2),
+            lowpass: Biquad::lowpass(sample_rate, 15.0_f64.min(sample_rate * 0.45), FRAC_1_SQRT_2),
+            previous: 0.0,
+            integration: VecDeque::with_capacity(integration_len + 1),
+            integration_len,
+            integration_sum: 0.0,
+            samples_seen: 0,
+            learning_samples: (LEARNING_SECONDS * sample_rate) as usize,
+            signal_level: 0.0,
+            candidate: None,
+            last_activity: None,
+            peaks: VecDeque::new(),
+        }
+    }
+    
+    fn push(&mut self, timestamp: f64, value: f64) {
+        let filtered = self.lowpass.process(self.highpass.process(value));
+        let derivative = filtered - self.previous;
+        self.previous = filtered;
+        
+        let energy = derivative * derivative;
+        self.integration.push_back(energy);
+        self.integration_sum += energy;
+        if self.integration.len() > self.integration_len {
+            self.integration_sum -= self.integration.pop_front().unwrap_or_default();
+        }
+        let integrated = (self.integration_sum / self.integration_len as f64).max(0.0);
+        
+        self.samples_seen += 1;
+        if self.samples_seen <= self.learning_samples {
+            self.signal_level = self.signal_level.max(integrated);
+            return;
+        }
+        let last_activity = *self.last_activity.get_or_insert(timestamp);
+        
+        if integrated > 0.3 * self.signal_level {
+            if self.candidate.is_none_or(|(peak, _)| integrated > peak) {
+                self.candidate = Some((integrated, timestamp));
+            }
+        } else if let Some((peak, time)) = self.candidate.take() {
+            if self.peaks.back().is_none_or(|&last| time - last >= REFRACTORY_SECONDS) {
+                self.peaks.push_back(time);
+                self.signal_level = 0.875 * self.signal_level + 0.125 * peak;
+                self.last_activity = Some(timestamp);
+            }
+        } else if timestamp - last_activity > LOST_BEAT_SECONDS {
+            self.signal_level *= 0.5;
+            self.last_activity = Some(timestamp);
+        }
+        
+        while self.peaks.front().is_some_and(|&first| timestamp - first > HEART_WINDOW_SECONDS) {
+            self.peaks.pop_front();
+        }
+    }
+    
+    fn rr_intervals_ms(&self) -> Vec<f64> {
+        self.peaks.iter()
+            .zip(self.peaks.iter().skip(1))
+            .map(|(a, b)| (b - a) * 1000.0)
+            .collect()
+    }
+}
+
+/// 呼吸频率 - 0.1-0.7Hz带通后按带滞回的上升过零计周期
+struct RespirationEstimator {
+    highpass: Biquad,
+    lowpass: Biquad,
+    amplitude: f64,                 // |x| 的指数平均（时间常数约5秒）
+    amplitude_alpha: f64,
+    armed: bool,                    // 已低于负滞回阈值
+    crossings: VecDeque<f64>,
+}
+
+impl RespirationEstimator {
+    fn new(sample_rate: f64) -> Self {
+        Self {
+            highpass: Biquad::highpass(sample_rate, 0.1, FRAC_1_SQRT_2),
+            lowpass: Biquad::lowpass(sample_rate, 0.7, FRAC_1_SQRT_2),
+            amplitude: 0.0,
+            amplitude_alpha: 1.0 / (5.0 * sample_rate),
+            armed: false,
+            crossings: VecDeque::new(),
+        }
+    }
+    
+    fn push(&mut self, timestamp: f64, value: f64) {
+        let x = self.lowpass.process(self.highpass.process(value));
+        self.amplitude += self.amplitude_alpha * (x.abs() - self.amplitude);
+        
+        let hysteresis = 0.3 * self.amplitude;
+        if x < -hysteresis {
+            self.armed = true;
+        } else if self.armed && x > hysteresis {
+            self.armed = false;
+            self.crossings.push_back(timestamp);
+        }
+        
+        while self.crossings.front().is_some_and(|&first| timestamp - first > RESPIRATION_WINDOW_SECONDS) {
+            self.crossings.pop_front();
+        }
+    }
+    
+    /// 至少两个完整周期才给出频率（次/分）
+    fn rate_bpm(&self) -> Option<f64> {
+        let periods: Vec<f64> = self.crossings.iter()
+            .zip(self.crossings.iter().skip(1))
+            .map(|(a, b)| b - a)
+            .collect();
+        (periods.len() >= 2).then(|| 60.0 / median(&periods))
+    }
+}
+
+/// 生理信号监测 - 从原始样本中的辅助通道提取心率、HRV与呼吸频率
+pub struct VitalsMonitor {
+    ecg_channel: Option<usize>,
+    respiration_channel: Option<usize>,
+    heart: RPeakDetector,
+    respiration: RespirationEstimator,
+    update_every: usize,
+    since_update: usize,
+}
+
+impl VitalsMonitor {
+    pub fn new(config: VitalsConfig, sample_rate: f64) -> Self {
+        Self {
+            ecg_channel: config.ecg_channel.map(|channel| channel as usize),
+            respiration_channel: config.respiration_channel.map(|channel| channel as usize),
+            heart: RPeakDetector::new(sample_rate),
+            respiration: RespirationEstimator::new(sample_rate),
+            update_every: ((config.update_interval_ms as f64 / 1000.0 * sample_rate) as usize).max(1),
+            since_update: 0,
+        }
+    }
+    
+    /// 加入一个原始样本，到推送间隔时返回最新估计
+    pub fn push(&mut self, sample: &EegSample) -> Option<VitalsUpdate> {
+        if let Some(&value) = self.ecg_channel.and_then(|channel| sample.channels.get(channel)) {
+            self.heart.push(sample.timestamp, value);
+        }
+        if let Some(&value) = self.respiration_channel.and_then(|channel| sample.channels.get(channel)) {
+            self.respiration.push(sample.timestamp, value);
+        }
+        
+        self.since_update += 1;
+        if self.since_update < self.update_every {
+            return None;
+        }
+        self.since_update = 0;
+        
+        let rr_intervals_ms = self.heart.rr_intervals_ms();
+        let successive: Vec<f64> = rr_intervals_ms.windows(2).map(|pair| (pair[1] - pair[0]).powi(2)).collect();
+        
+        Some(VitalsUpdate {
+            timestamp: sample.timestamp,
+            heart_rate_bpm: (rr_intervals_ms.len() >= 2).then(|| 60_000.0 / median(&rr_intervals_ms)),
+            hrv_rmssd_ms: (!successive.is_empty())
+                .then(|| (successive.iter().sum::<f64>() / successive.len() as f64).sqrt()),
+            last_r_peak: self.heart.peaks.back().copied(),
+            respiration_rate_bpm: self.respiration.rate_bpm(),
+            rr_intervals_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+    
+    #[test]
+    fn test_heart_and_respiration_rate() {
+        let sample_rate = 250.0;
+        let config = VitalsConfig {
+            enabled: true,
+            ecg_channel: Some(1),
+            respiration_channel: Some(2),
+            update_interval_ms: 1000,
+        };
+        let mut monitor = VitalsMonitor::new(config, sample_rate);
+        
+        // ECG：72次/分的窄高斯R波叠加直流偏移；呼吸：15次/分正弦
+        let beat = 60.0 / 72.0;
+        let mut updates = Vec::new();
+        for n in 0..(40.0 * sample_rate) as usize {
+            let t = n as f64 / sample_rate;
+            let phase = (t % beat) - 0.4;
+            let ecg = 800.0 * (-(phase / 0.012).powi(2)).exp() + 200.0 + 20.0 * (TAU * 0.3 * t).sin();
+            let breath = 50.0 * (TAU * 0.25 * t).sin();
+            updates.extend(monitor.push(&EegSample { timestamp: t, channels: vec![0.0, ecg, breath], sample_id: n as u64 }));
+        }
+        
+        assert_eq!(updates.len(), 40);
+        let last = updates.last().unwrap();
+        let heart_rate = last.heart_rate_bpm.unwrap();
+        assert!((heart_rate - 72.0).abs() < 2.0, "heart rate {}", heart_rate);
+        assert!(last.hrv_rmssd_ms.unwrap() < 10.0);
+        assert!(last.rr_intervals_ms.len() >= 10);
+        let respiration = last.respiration_rate_bpm.unwrap();
+        assert!((respiration - 15.0).abs() < 1.0, "respiration {}", respiration);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::data_types::*;
+use crate::metrics::PipelineMetrics;
+use crate::session::StreamEmitter;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+// 监督检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// 阶段心跳超过该时长视为停滞
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 每个阶段最多自动重启的次数
+const MAX_RESTARTS: u32 = 3;
+
+/// 重新启动阶段任务 - 闭包持有该阶段的通道端点与共享状态
+pub type StageFactory = Box<dyn FnMut() -> StageHandle + Send>;
+
+/// 受监督阶段的句柄 - tokio任务或专用OS线程
+pub enum StageHandle {
+    Task(JoinHandle<()>),
+    Thread(std::thread::JoinHandle<()>),
+}
+
+impl StageHandle {
+    fn is_finished(&self) -> bool {
+        match self {
+            StageHandle::Task(handle) => handle.is_finished(),
+            StageHandle::Thread(handle) => handle.is_finished(),
+        }
+    }
+    
+    /// 等待结束，Err 为故障类型与说明；OS线程在阻塞线程池中join，不占用运行时
+    async fn join(self) -> Result<(), (PipelineFault, String)> {
+        match self {
+            StageHandle::Task(handle) => handle.await.map_err(|e| {
+                if e.is_panic() {
+                    (PipelineFault::Panicked, panic_message(e.into_panic()))
+                } else {
+                    (PipelineFault::Exited, e.to_string())
+                }
+            }),
+            StageHandle::Thread(handle) => match tokio::task::spawn_blocking(move || handle.join()).await {
+                Ok(result) => result.map_err(|payload| (PipelineFault::Panicked, panic_message(payload))),
+                Err(e) => Err((PipelineFault::Exited, e.to_string())),
+            },
+        }
+    }
+}
+
+impl From<JoinHandle<()>> for StageHandle {
+    fn from(handle: JoinHandle<()>) -> Self {
+        StageHandle::Task(handle)
+    }
+}
+
+impl From<std::thread::JoinHandle<()>> for StageHandle {
+    fn from(handle: std::thread::JoinHandle<()>) -> Self {
+        StageHandle::Thread(handle)
+    }
+}
+
+struct SupervisedTask {
+    stage: PipelineStage,
+    handle: Option<StageHandle>,        // None：已放弃重启
+    restart: Option<StageFactory>,
+    restarts: u32,
+}
+
+/// 管道监督器 - 监视各阶段心跳与受监督任务的退出，推送 pipeline-error，可重启的阶段自动重启
+pub struct Supervisor {
+    tasks: Vec<SupervisedTask>,
+    stalled: Vec<PipelineStage>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self { tasks: Vec::new(), stalled: Vec::new() }
+    }
+    
+    /// 接管阶段任务；提供 restart 时，任务panic或意外退出后重启（最多 MAX_RESTARTS 次）
+    pub fn supervise(&mut self, stage: PipelineStage, handle: impl Into<StageHandle>, restart: Option<StageFactory>) {
+        self.tasks.push(SupervisedTask { stage, handle: Some(handle.into()), restart, restarts: 0 });
+    }
+    
+    /// 启动监督任务；管道停止后等待受监督的任务结束再退出
+    pub fn spawn(
+        mut self,
+        metrics: Arc<PipelineMetrics>,
+        app_handle: StreamEmitter,
+        is_running: Arc<tokio::sync::RwLock<bool>>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("🩺 Pipeline supervisor started ({} supervised tasks)", self.tasks.len());
+            
+            let mut timer = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                timer.tick().await;
+                
+                let errors = self.check(&metrics).await;
+                // 停止过程中任务正常退出，不算故障
+                if !*is_running.read().await {
+                    break;
+                }
+                
+                for pipeline_error in errors {
+                    if let Err(e) = app_handle.emit("pipeline-error", &pipeline_error) {
+                        error!("Failed to emit pipeline-error: {}", e);
+                    }
+                }
+            }
+            
+            for task in &mut self.tasks {
+                if let Some(handle) = task.handle.take() {
+                    if let Err((fault, message)) = handle.join().await {
+                        error!("Thread join error ({:?}): {:?} {}", task.stage, fault, message);
+                    }
+                }
+            }
+            info!("🩺 Pipeline supervisor stopped");
+        })
+    }
+    
+    async fn check(&mut self, metrics: &PipelineMetrics) -> Vec<PipelineError> {
+        let mut errors = Vec::new();
+        
+        for task in &mut self.tasks {
+            if !task.handle.as_ref().is_some_and(StageHandle::is_finished) {
+                continue;
+            }
+            let Some(handle) = task.handle.take() else { continue };
+            
+            let (fault, message) = match handle.join().await {
+                Ok(()) => (PipelineFault::Exited, "Task exited while the pipeline was running".to_string()),
+                Err(fault) => fault,
+            };
+            
+            let restarted = match task.restart.as_mut() {
+                Some(restart) if task.restarts < MAX_RESTARTS => {
+                    task.restarts += 1;
+                    task.handle = Some(restart());
+                    true
+                }
+                // 放弃重启时释放闭包持有的通道端点，上游可感知接收端断开
+                _ => {
+                    task.restart = None;
+                    false
+                }
+            };
+            
+            error!("❌ Pipeline stage {:?} {:?}: {}{}", task.stage, fault, message,
+                     if restarted { format!(" (restart #{})", task.restarts) } else { String::new() });
+            errors.push(PipelineError {
+                stage: task.stage,
+                fault,
+                message,
+                restarted,
+                restarts: task.restarts,
+            });
+        }
+        
+        // 已放弃的任务不再按心跳重复报告
+        let dead: Vec<PipelineStage> = self.tasks.iter()
+            .filter(|task| task.handle.is_none())
+            .map(|task| task.stage)
+            .collect();
+        for (stage, stats) in metrics.heartbeat_stages() {
+            if dead.contains(&stage) {
+                continue;
+            }
+            
+            let age = stats.heartbeat_age();
+            let reported = self.stalled.contains(&stage);
+            if age > STALL_TIMEOUT && !reported {
+                warn!("⚠️ Pipeline stage {:?} stalled: no heartbeat for {:.1}s", stage, age.as_secs_f64());
+                self.stalled.push(stage);
+                errors.push(PipelineError {
+                    stage,
+                    fault: PipelineFault::Stalled,
+                    message: format!("No heartbeat for {:.1}s", age.as_secs_f64()),
+                    restarted: false,
+                    restarts: 0,
+                });
+            } else if age <= STALL_TIMEOUT && reported {
+                info!("✅ Pipeline stage {:?} recovered", stage);
+                self.stalled.retain(|&stalled| stalled != stage);
+            }
+        }
+        
+        errors
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload.downcast_ref::<&str>().map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    
+    #[test]
+    fn test_panicked_stage_is_restarted() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let metrics = PipelineMetrics::new();
+            for (_, stats) in metrics.heartbeat_stages() {
+                stats.beat();
+            }
+            
+            let spawned = Arc::new(AtomicU32::new(0));
+            let factory_spawned = spawned.clone();
+            let mut supervisor = Supervisor::new();
+            supervisor.supervise(
+                PipelineStage::Fft,
+                std::thread::spawn(|| panic!("fft exploded")),
+                Some(Box::new(move || {
+                    factory_spawned.fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(std::future::pending()).into()
+                })),
+            );
+            supervisor.supervise(PipelineStage::Recording, tokio::spawn(async {}), None);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            
+            let errors = supervisor.check(&metrics).await;
+            assert_eq!(errors.len(), 2);
+            assert_eq!(errors[0].fault, PipelineFault::Panicked);
+            assert_eq!(errors[0].message, "fft exploded");
+            assert!(errors[0].restarted);
+            assert_eq!(errors[1].stage, PipelineStage::Recording);
+            assert!(!errors[1].restarted);
+            assert_eq!(spawned.load(Ordering::Relaxed), 1);
+            
+            // 重启后的任务仍在运行，不再报告
+            assert!(supervisor.check(&metrics).await.is_empty());
+        });
+    }
+}
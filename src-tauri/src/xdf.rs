@@ -0,0 +1,312 @@
+use crate::error::AppError;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use tracing::{info, warn};
+
+// XDF 块标签
+const TAG_FILE_HEADER: u16 = 1;
+const TAG_STREAM_HEADER: u16 = 2;
+const TAG_SAMPLES: u16 = 3;
+
+/// XDF 中一个流的全部样本
+#[derive(Clone, Debug, PartialEq)]
+pub enum XdfValues {
+    Numeric(Vec<Vec<f64>>),     // 每个样本一行
+    Strings(Vec<Vec<String>>),  // 标记流
+}
+
+/// XDF 中的一个流（头信息 + 样本 + LSL时间戳）
+#[derive(Clone, Debug, PartialEq)]
+pub struct XdfStream {
+    pub id: u32,
+    pub name: String,
+    pub stream_type: String,
+    pub channel_count: usize,
+    pub nominal_srate: f64,
+    pub channel_labels: Vec<String>,
+    pub timestamps: Vec<f64>,
+    pub values: XdfValues,
+}
+
+/// 流头中的通道数据格式
+#[derive(Clone, Copy)]
+enum ChannelFormat {
+    Float32,
+    Double64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    String,
+}
+
+impl ChannelFormat {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "float32" => Some(Self::Float32),
+            "double64" => Some(Self::Double64),
+            "int8" => Some(Self::Int8),
+            "int16" => Some(Self::Int16),
+            "int32" => Some(Self::Int32),
+            "int64" => Some(Self::Int64),
+            "string" => Some(Self::String),
+            _ => None,
+        }
+    }
+}
+
+/// 读取中的流：格式与省略时间戳时用于推算的上一个时间戳
+struct StreamState {
+    stream: XdfStream,
+    format: ChannelFormat,
+    last_timestamp: f64,
+}
+
+/// 读取整个 XDF 文件（LabRecorder 格式），流按 StreamID 排序返回
+///
+/// 省略的样本时间戳按名义采样率由上一个时间戳推算；时钟偏移与流尾块不参与回放，跳过。
+pub fn read(path: &Path) -> Result<Vec<XdfStream>, AppError> {
+    let mut file = BufReader::new(File::open(path)?);
+    
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).map_err(|_| invalid(path, "file is too short"))?;
+    if &magic != b"XDF:" {
+        return Err(invalid(path, "missing XDF: magic"));
+    }
+    
+    let mut streams: BTreeMap<u32, StreamState> = BTreeMap::new();
+    let mut saw_file_header = false;
+    // 文件末尾（或未完成的最后一个块）结束读取
+    while let Ok(length) = read_varlen(&mut file) {
+        if length < 2 {
+            return Err(invalid(path, "chunk is shorter than its tag"));
+        }
+        let mut chunk = vec![0u8; length as usize];
+        if file.read_exact(&mut chunk).is_err() {
+            warn!("⚠️  {} ends with a truncated chunk, ignoring it", path.display());
+            break;
+        }
+        let tag = u16::from_le_bytes([chunk[0], chunk[1]]);
+        let mut content = &chunk[2..];
+        
+        match tag {
+            TAG_FILE_HEADER => saw_file_header = true,
+            TAG_STREAM_HEADER => {
+                let id = read_u32(&mut content).map_err(|_| invalid(path, "stream header without id"))?;
+                let xml = String::from_utf8_lossy(content);
+                let format = xml_text(&xml, "channel_format").and_then(ChannelFormat::parse)
+                    .ok_or_else(|| invalid(path, "stream header has an unknown channel_format"))?;
+                let channel_count: usize = xml_text(&xml, "channel_count").and_then(|text| text.parse().ok())
+                    .ok_or_else(|| invalid(path, "stream header has no channel_count"))?;
+                let stream = XdfStream {
+                    id,
+                    name: xml_text(&xml, "name").unwrap_or_default().to_string(),
+                    stream_type: xml_text(&xml, "type").unwrap_or_default().to_string(),
+                    channel_count,
+                    nominal_srate: xml_text(&xml, "nominal_srate").and_then(|text| text.parse().ok()).unwrap_or(0.0),
+                    channel_labels: channel_labels(&xml),
+                    timestamps: Vec::new(),
+                    values: match format {
+                        ChannelFormat::String => XdfValues::Strings(Vec::new()),
+                        _ => XdfValues::Numeric(Vec::new()),
+                    },
+                };
+                streams.insert(id, StreamState { stream, format, last_timestamp: 0.0 });
+            }
+            TAG_SAMPLES => {
+                let id = read_u32(&mut content).map_err(|_| invalid(path, "samples chunk without id"))?;
+                let state = streams.get_mut(&id)
+                    .ok_or_else(|| invalid(path, "samples chunk before its stream header"))?;
+                read_samples(&mut content, state).map_err(|_| invalid(path, "truncated samples chunk"))?;
+            }
+            _ => {}
+        }
+    }
+    
+    if !saw_file_header {
+        return Err(invalid(path, "missing file header chunk"));
+    }
+    
+    info!("📖 Read XDF {}: {} streams", path.display(), streams.len());
+    Ok(streams.into_values().map(|state| state.stream).collect())
+}
+
+/// 解析一个样本块：样本数 + 每个样本（可选时间戳 + 各通道值）
+fn read_samples(content: &mut &[u8], state: &mut StreamState) -> std::io::Result<()> {
+    let count = read_varlen(content)?;
+    let interval = if state.stream.nominal_srate > 0.0 { 1.0 / state.stream.nominal_srate } else { 0.0 };
+    
+    for _ in 0..count {
+        let timestamp = match read_u8(content)? {
+            8 => read_f64(content)?,
+            _ => state.last_timestamp + interval,
+        };
+        state.last_timestamp = timestamp;
+        state.stream.timestamps.push(timestamp);
+        
+        let channels = state.stream.channel_count;
+        match &mut state.stream.values {
+            XdfValues::Strings(rows) => {
+                let mut row = Vec::with_capacity(channels);
+                for _ in 0..channels {
+                    let length = read_varlen(content)? as usize;
+                    let mut bytes = vec![0u8; length];
+                    content.read_exact(&mut bytes)?;
+                    row.push(String::from_utf8_lossy(&bytes).into_owned());
+                }
+                rows.push(row);
+            }
+            XdfValues::Numeric(rows) => {
+                let mut row = Vec::with_capacity(channels);
+                for _ in 0..channels {
+                    row.push(read_value(content, state.format)?);
+                }
+                rows.push(row);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_value(content: &mut &[u8], format: ChannelFormat) -> std::io::Result<f64> {
+    let value = match format {
+        ChannelFormat::Float32 => f32::from_le_bytes(read_array(content)?) as f64,
+        ChannelFormat::Double64 => f64::from_le_bytes(read_array(content)?),
+        ChannelFormat::Int8 => i8::from_le_bytes(read_array(content)?) as f64,
+        ChannelFormat::Int16 => i16::from_le_bytes(read_array(content)?) as f64,
+        ChannelFormat::Int32 => i32::from_le_bytes(read_array(content)?) as f64,
+        ChannelFormat::Int64 => i64::from_le_bytes(read_array(content)?) as f64,
+        ChannelFormat::String => unreachable!("string streams are read as text"),
+    };
+    Ok(value)
+}
+
+/// 变长整数：1字节的长度（1/4/8）+ 小端整数
+fn read_varlen(reader: &mut impl Read) -> std::io::Result<u64> {
+    match read_u8(reader)? {
+        1 => Ok(read_u8(reader)? as u64),
+        4 => Ok(u32::from_le_bytes(read_array(reader)?) as u64),
+        8 => Ok(u64::from_le_bytes(read_array(reader)?)),
+        other => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid length size {}", other))),
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> std::io::Result<u8> {
+    Ok(read_array::<1>(reader)?[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    Ok(u32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_f64(reader: &mut impl Read) -> std::io::Result<f64> {
+    Ok(f64::from_le_bytes(read_array(reader)?))
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// 第一个 <tag>…</tag> 的文本
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim())
+}
+
+/// <desc><channels> 中各 <channel><label> 的文本
+fn channel_labels(xml: &str) -> Vec<String> {
+    let Some(channels) = xml_text(xml, "channels") else { return Vec::new() };
+    channels.split("<channel>")
+        .skip(1)
+        .filter_map(|channel| xml_text(channel, "label"))
+        .map(str::to_string)
+        .collect()
+}
+
+fn invalid(path: &Path, reason: &str) -> AppError {
+    AppError::Playback(format!("{} is not a valid XDF file: {}", path.display(), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// 组装一个块：长度（4字节变长）+ 标签 + 内容
+    fn chunk(tag: u16, content: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![4];
+        bytes.extend_from_slice(&(content.len() as u32 + 2).to_le_bytes());
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        bytes.extend_from_slice(content);
+        bytes
+    }
+    
+    fn stream_header(id: u32, xml: &str) -> Vec<u8> {
+        let mut content = id.to_le_bytes().to_vec();
+        content.extend_from_slice(xml.as_bytes());
+        chunk(TAG_STREAM_HEADER, &content)
+    }
+    
+    /// 两通道 100Hz float32 EEG 流（第二个样本省略时间戳）+ 字符串标记流
+    fn write_test_file(path: &Path) {
+        let mut bytes = b"XDF:".to_vec();
+        bytes.extend(chunk(TAG_FILE_HEADER, b"<?xml version=\"1.0\"?><info><version>1.0</version></info>"));
+        bytes.extend(stream_header(1, "<?xml version=\"1.0\"?><info><name>Amp</name><type>EEG</type>\
+            <channel_count>2</channel_count><nominal_srate>100</nominal_srate><channel_format>float32</channel_format>\
+            <desc><channels><channel><label>Fz</label></channel><channel><label>Cz</label></channel></channels></desc></info>"));
+        bytes.extend(stream_header(2, "<?xml version=\"1.0\"?><info><name>Events</name><type>Markers</type>\
+            <channel_count>1</channel_count><nominal_srate>0</nominal_srate><channel_format>string</channel_format></info>"));
+        
+        let mut samples = 1u32.to_le_bytes().to_vec();
+        samples.extend([1, 3]);
+        for i in 0..3 {
+            if i == 1 {
+                samples.push(0);
+            } else {
+                samples.push(8);
+                samples.extend_from_slice(&(50.0 + i as f64 * 0.01).to_le_bytes());
+            }
+            samples.extend_from_slice(&(i as f32).to_le_bytes());
+            samples.extend_from_slice(&(-(i as f32)).to_le_bytes());
+        }
+        bytes.extend(chunk(TAG_SAMPLES, &samples));
+        
+        let mut markers = 2u32.to_le_bytes().to_vec();
+        markers.extend([1, 1, 8]);
+        markers.extend_from_slice(&50.015f64.to_le_bytes());
+        markers.extend([1, 4]);
+        markers.extend_from_slice(b"stim");
+        bytes.extend(chunk(TAG_SAMPLES, &markers));
+        
+        std::fs::write(path, bytes).unwrap();
+    }
+    
+    #[test]
+    fn test_read_streams_and_deduce_timestamps() {
+        let path = std::env::temp_dir().join(format!("xdf_test_{}.xdf", std::process::id()));
+        write_test_file(&path);
+        let streams = read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        assert_eq!(streams.len(), 2);
+        let eeg = &streams[0];
+        assert_eq!((eeg.name.as_str(), eeg.stream_type.as_str(), eeg.channel_count), ("Amp", "EEG", 2));
+        assert_eq!(eeg.channel_labels, vec!["Fz", "Cz"]);
+        assert_eq!(eeg.values, XdfValues::Numeric(vec![vec![0.0, 0.0], vec![1.0, -1.0], vec![2.0, -2.0]]));
+        // 省略的时间戳 = 上一个 + 1/采样率
+        assert!((eeg.timestamps[1] - 50.01).abs() < 1e-9);
+        
+        assert_eq!(streams[1].values, XdfValues::Strings(vec![vec!["stim".to_string()]]));
+        assert_eq!(streams[1].timestamps, vec![50.015]);
+        
+        let bad = std::env::temp_dir().join(format!("xdf_bad_{}.xdf", std::process::id()));
+        std::fs::write(&bad, b"EDF:").unwrap();
+        assert!(read(&bad).is_err());
+        let _ = std::fs::remove_file(&bad);
+    }
+}
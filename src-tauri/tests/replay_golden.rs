@@ -0,0 +1,90 @@
+//! 确定性回放回归测试：固定的采集文件回放后与 tests/golden 中的金标准比对
+//!
+//! 录制、FFT等有意改变输出时，用 `UPDATE_GOLDEN=1 cargo test --test replay_golden` 重新生成金标准。
+
+use cortexarray_lib::capture::CaptureWriter;
+use cortexarray_lib::headless::{self, ReplayReport, ReplayRequest};
+use cortexarray_lib::{EegSample, FftConfig, FilterConfig, LslMarker, RecordingFormat, RecordingOptions, RecordingProcessing, StreamInfo};
+use std::path::{Path, PathBuf};
+
+const SAMPLE_RATE: f64 = 250.0;
+const CHUNK_SIZE: u64 = 10;
+const GOLDEN: &str = "tests/golden/replay_sine.json";
+
+// 谱值允许的相对误差（不同CPU上的FFT实现末位可能不同）
+const SPECTRUM_TOLERANCE: f64 = 1e-9;
+
+/// 2通道、1秒：10Hz正弦 + 通道偏置，两个标记
+fn write_capture(path: &Path) {
+    let stream_info = StreamInfo {
+        name: "GoldenEEG".to_string(),
+        stream_type: "EEG".to_string(),
+        channels_count: 2,
+        sample_rate: SAMPLE_RATE,
+        is_connected: true,
+        source_id: "golden".to_string(),
+        channel_labels: vec!["C3".to_string(), "C4".to_string()],
+        effective_sample_rate: None,
+    };
+    let sample = |index: u64| {
+        let t = index as f64 / SAMPLE_RATE;
+        let wave = (2.0 * std::f64::consts::PI * 10.0 * t).sin();
+        EegSample { timestamp: 1000.0 + t, channels: vec![20.0 * wave + 5.0, -10.0 * wave], sample_id: index }
+    };
+    
+    let mut writer = CaptureWriter::create(path.to_str().unwrap(), &stream_info).unwrap();
+    for start in (0..SAMPLE_RATE as u64).step_by(CHUNK_SIZE as usize) {
+        if start == 100 {
+            writer.write_marker(&LslMarker { label: "stim".to_string(), timestamp: 1000.402, stream_name: "Markers".to_string() }).unwrap();
+            writer.write_marker(&LslMarker { label: "resp".to_string(), timestamp: 1000.8, stream_name: "Markers".to_string() }).unwrap();
+        }
+        let chunk: Vec<EegSample> = (start..start + CHUNK_SIZE).map(sample).collect();
+        writer.write_chunk(&chunk).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+async fn replay(capture: &Path, output: &Path, processing: RecordingProcessing) -> ReplayReport {
+    headless::replay(ReplayRequest {
+        capture: capture.to_str().unwrap().to_string(),
+        filename: Some(output.to_str().unwrap().to_string()),
+        options: RecordingOptions { format: RecordingFormat::Csv, processing, ..RecordingOptions::default() },
+        filters: FilterConfig { hp: Some(1.0), lp: Some(40.0), apply_to_recording: true, ..FilterConfig::default() },
+        fft: Some(FftConfig { window_size: 64, hop_size: 64, zero_padding_factor: 1 }),
+        ..ReplayRequest::default()
+    }).await.unwrap()
+}
+
+// 处理管道的阶段在运行时线程上阻塞等待通道数据，需要多个工作线程
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_replay_matches_golden_outputs() {
+    let dir = std::env::temp_dir().join(format!("replay_golden_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let capture = dir.join("golden.cxcap");
+    write_capture(&capture);
+    
+    // 原始数据录制与FFT输出与平台无关，直接与金标准比对
+    let report = replay(&capture, &dir.join("raw.csv"), RecordingProcessing::Raw).await;
+    assert_eq!(report.samples_replayed, 250);
+    assert_eq!(report.samples_written, 250);
+    assert_eq!(report.markers_written, 2);
+    assert_eq!(report.spectra.len(), 3);
+    
+    // 滤波录制依赖平台数学库计算的滤波系数，只要求同一平台上重复回放结果一致
+    let filtered = replay(&capture, &dir.join("filtered_1.csv"), RecordingProcessing::Pipeline).await;
+    let filtered_again = replay(&capture, &dir.join("filtered_2.csv"), RecordingProcessing::Pipeline).await;
+    assert_eq!(filtered, filtered_again);
+    assert_ne!(filtered.checksums, report.checksums);
+    let _ = std::fs::remove_dir_all(&dir);
+    
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(GOLDEN);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        std::fs::write(&golden_path, serde_json::to_string_pretty(&report).unwrap()).unwrap();
+        return;
+    }
+    let golden: ReplayReport = serde_json::from_str(&std::fs::read_to_string(&golden_path).unwrap()).unwrap();
+    if let Err(e) = report.compare(&golden, SPECTRUM_TOLERANCE) {
+        panic!("Replay differs from {}: {}", GOLDEN, e);
+    }
+}